@@ -1,18 +1,38 @@
 //! A lightweight and configurable status bar for penrose
-use crate::{core::Draw, Result};
+use crate::{core::Draw, Error, Result};
 use penrose::{
-    core::{State, WindowManager},
-    pure::geometry::Rect,
-    x::{event::XEvent, Atom, ClientConfig, Prop, WinType, XConn},
+    builtin::{
+        actions::key_handler,
+        layout::{
+            messages::SetReservedSpace,
+            transformers::{ReserveBottom, ReserveLeft, ReserveRight, ReserveTop},
+        },
+    },
+    core::{
+        bindings::{KeyEventHandler, MouseEvent, MouseEventKind},
+        layout::{Layout, LayoutStack},
+        State, WindowManager,
+    },
+    extensions::util::theme::{current_theme, Theme},
+    pure::geometry::{Point, Rect},
+    x::{event::XEvent, Atom, ClientAttr, ClientConfig, Prop, WinType, XConn, XConnExt},
     Color, Xid,
 };
-use std::fmt;
+use std::{
+    cmp::{min, Ordering},
+    collections::HashMap,
+    fmt,
+    sync::mpsc::Sender,
+    time::{Duration, Instant},
+};
 use tracing::{debug, error, info};
 
 pub mod schedule;
+pub mod watch;
 pub mod widgets;
 
-use schedule::{run_update_schedules, UpdateSchedule};
+use schedule::{run_hide_timer, run_update_schedules, UpdateSchedule};
+use watch::{run_watches, Watch};
 use widgets::Widget;
 
 /// The position of a status bar
@@ -22,10 +42,58 @@ pub enum Position {
     Top,
     /// Bottom of the screen
     Bottom,
+    /// Left hand side of the screen, rendering widgets vertically
+    ///
+    /// Text is not rotated: widgets are stacked vertically but draw their contents horizontally,
+    /// so anything wider than the bar is clipped to its width.
+    Left,
+    /// Right hand side of the screen, rendering widgets vertically
+    ///
+    /// Text is not rotated: widgets are stacked vertically but draw their contents horizontally,
+    /// so anything wider than the bar is clipped to its width.
+    Right,
+}
+
+impl Position {
+    /// Whether or not a bar in this position renders its widgets vertically.
+    pub fn is_vertical(&self) -> bool {
+        matches!(self, Self::Left | Self::Right)
+    }
+
+    /// The region of a screen occupied by a bar in this position with the given thickness.
+    pub fn bar_rect(&self, r: Rect, thickness: u32) -> Rect {
+        let Rect { x, y, w, h } = r;
+        let t = if self.is_vertical() {
+            thickness.min(w)
+        } else {
+            thickness.min(h)
+        };
+
+        match self {
+            Self::Top => Rect::new(x, y, w, t),
+            Self::Bottom => Rect::new(x, y + h - t, w, t),
+            Self::Left => Rect::new(x, y, t, h),
+            Self::Right => Rect::new(x + w - t, y, t, h),
+        }
+    }
+
+    /// Wrap a [Layout] so that it reserves space for a bar in this position with the given
+    /// thickness.
+    pub fn reserve(&self, layout: Box<dyn Layout>, thickness: u32) -> Box<dyn Layout> {
+        match self {
+            Self::Top => ReserveTop::wrap(layout, thickness),
+            Self::Bottom => ReserveBottom::wrap(layout, thickness),
+            Self::Left => ReserveLeft::wrap(layout, thickness),
+            Self::Right => ReserveRight::wrap(layout, thickness),
+        }
+    }
 }
 
 /// A group of [Widget]s and associated point size to use for rendering a [StatusBar] on a single
 /// screen.
+///
+/// `h` is the height of the bar for [Position::Top] and [Position::Bottom] and its width for
+/// [Position::Left] and [Position::Right].
 pub struct PerScreen<X: XConn> {
     point_size: u8,
     h: u32,
@@ -65,7 +133,15 @@ impl<X: XConn> Widgets<X> {
         }
     }
 
-    fn for_each_mut<F>(&mut self, n_screens: usize, mut f: F)
+    fn len(&self) -> usize {
+        match self {
+            Self::Shared(_) => 1,
+            Self::PerScreen(pss) => pss.len(),
+        }
+    }
+
+    // Only the arrangements in use are visited to avoid checking widgets that are not on screen
+    fn for_each_mut<F>(&mut self, in_use: &[usize], mut f: F)
     where
         F: FnMut(&mut Box<dyn Widget<X>>),
     {
@@ -73,18 +149,27 @@ impl<X: XConn> Widgets<X> {
             Self::Shared(ps) => ps.ws.iter_mut().for_each(f),
             Self::PerScreen(pss) => pss
                 .iter_mut()
-                .take(n_screens) // avoid checking widgets that are not in use
-                .for_each(|ps| ps.ws.iter_mut().for_each(&mut f)),
+                .enumerate()
+                .filter(|(i, _)| in_use.contains(i))
+                .for_each(|(_, ps)| ps.ws.iter_mut().for_each(&mut f)),
         }
     }
 
-    fn require_draw(&self, n_screens: usize) -> bool {
+    fn require_draw(&self, in_use: &[usize]) -> bool {
         match self {
             Self::Shared(ps) => ps.ws.iter().any(|w| w.require_draw()),
             Self::PerScreen(pss) => pss
                 .iter()
-                .take(n_screens) // avoid checking widgets that are not in use
-                .any(|ps| ps.ws.iter().any(|w| w.require_draw())),
+                .enumerate()
+                .filter(|(i, _)| in_use.contains(i))
+                .any(|(_, ps)| ps.ws.iter().any(|w| w.require_draw())),
+        }
+    }
+
+    fn max_thickness(&self) -> u32 {
+        match self {
+            Self::Shared(ps) => ps.h,
+            Self::PerScreen(pss) => pss.iter().map(|ps| ps.h).max().unwrap_or(0),
         }
     }
 
@@ -101,6 +186,26 @@ impl<X: XConn> Widgets<X> {
                 .collect(),
         }
     }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        match self {
+            Self::Shared(ps) => ps.ws.iter_mut().for_each(|w| w.set_theme(theme)),
+            Self::PerScreen(pss) => pss
+                .iter_mut()
+                .flat_map(|ps| ps.ws.iter_mut())
+                .for_each(|w| w.set_theme(theme)),
+        }
+    }
+
+    fn watches(&mut self) -> Vec<Watch> {
+        match self {
+            Self::Shared(ps) => ps.ws.iter_mut().filter_map(|w| w.watch()).collect(),
+            Self::PerScreen(pss) => pss
+                .iter_mut()
+                .flat_map(|ps| ps.ws.iter_mut().filter_map(|w| w.watch()))
+                .collect(),
+        }
+    }
 }
 
 /// A simple text based status bar that renders a user defined array of [`Widget`]s.
@@ -109,9 +214,22 @@ pub struct StatusBar<X: XConn> {
     draw: Draw,
     position: Position,
     widgets: Widgets<X>,
-    screens: Vec<(Xid, u32)>,
+    screens: Vec<(Xid, u32)>, // (bar window, length along the bar)
+    widget_offsets: Vec<Vec<(u32, u32)>>, // (offset, length) of each widget per screen as last drawn
+    drawn_focus: Vec<Option<bool>>, // screen focus each bar was last drawn with (None if never drawn)
+    arrangements: Vec<usize>,       // the widget arrangement in use for each screen
+    output_arrangements: HashMap<String, usize>, // arrangements requested for named outputs
+    started: Vec<usize>,            // widget arrangements that have had startup hooks run
+    reserve_space: bool,
+    visible: bool,
+    auto_hide: Option<Duration>,
+    revealed_at: Instant,
+    hide_timer: Option<Sender<Instant>>, // for scheduling hiding an auto-hidden bar
+    triggers: Vec<Xid>, // input only windows at the bar edge for revealing an auto-hidden bar
     active_screen: usize,
     font: String,
+    follow_theme: bool,
+    theme: Option<Theme>, // the theme last applied to the bar
 }
 
 impl<X: XConn> StatusBar<X> {
@@ -133,21 +251,42 @@ impl<X: XConn> StatusBar<X> {
             position,
             widgets: Widgets::Shared(PerScreen::new(point_size, h, widgets)),
             screens: vec![],
+            widget_offsets: vec![],
+            drawn_focus: vec![],
+            arrangements: vec![],
+            output_arrangements: HashMap::new(),
+            started: vec![],
+            reserve_space: false,
+            visible: true,
+            auto_hide: None,
+            revealed_at: Instant::now(),
+            hide_timer: None,
+            triggers: vec![],
             active_screen: 0,
             font: font.to_string(),
+            follow_theme: false,
+            theme: None,
         })
     }
 
     /// Try to create a new status bar using a different arrangement of widgets for each screen.
     ///
-    /// If more screens are attached than available widget arrangements, the last widget
-    /// arrangement will be used as a fallback.
+    /// The first widget arrangement is used for the primary screen with the remaining screens
+    /// following in order, unless an arrangement has been requested for the output driving a
+    /// screen using [StatusBar::with_output_arrangement]. If more screens are attached than
+    /// available widget arrangements, the last widget arrangement will be used as a fallback.
+    /// Bar windows are created and destroyed as screens are added and removed, with the startup
+    /// hooks for each arrangement being run the first time that its screen becomes available.
     pub fn try_new_per_screen(
         position: Position,
         bg: impl Into<Color>,
         font: &str,
         widgets: Vec<PerScreen<X>>,
     ) -> Result<Self> {
+        if widgets.is_empty() {
+            return Err(Error::NoWidgetArrangements);
+        }
+
         let bg = bg.into();
         let point_size = widgets[0].point_size;
         let mut draw = Draw::new(font, point_size, bg)?;
@@ -160,16 +299,179 @@ impl<X: XConn> StatusBar<X> {
             position,
             widgets: Widgets::PerScreen(widgets),
             screens: vec![],
+            widget_offsets: vec![],
+            drawn_focus: vec![],
+            arrangements: vec![],
+            output_arrangements: HashMap::new(),
+            started: vec![],
+            reserve_space: false,
+            visible: true,
+            auto_hide: None,
+            revealed_at: Instant::now(),
+            hide_timer: None,
+            triggers: vec![],
             active_screen: 0,
             font: font.to_string(),
+            follow_theme: false,
+            theme: None,
         })
     }
 
+    /// Use the widget arrangement with the given index for the screen driven by the named RandR
+    /// output (for example "eDP-1" or "DP-1") whenever it is connected.
+    ///
+    /// This only has an effect for bars created using [StatusBar::try_new_per_screen].
+    pub fn with_output_arrangement(mut self, output: impl Into<String>, index: usize) -> Self {
+        self.output_arrangements.insert(output.into(), index);
+
+        self
+    }
+
+    /// Reserve space for this bar in the layouts of every workspace when it is added to a
+    /// [WindowManager], based on the [Position] of the bar.
+    ///
+    /// This should not be used if you are already wrapping your layouts in transformers such as
+    /// [ReserveTop] in order to make space for the bar.
+    pub fn with_reserved_space(mut self) -> Self {
+        self.reserve_space = true;
+
+        self
+    }
+
+    /// Automatically hide this bar once it has been visible for the given duration, revealing it
+    /// again when the pointer reaches the edge of the screen the bar is placed on.
+    ///
+    /// The bar is not hidden while the pointer is over it and clicking on the bar restarts the
+    /// delay. Any space reserved for the bar is released while it is hidden.
+    pub fn with_auto_hide(mut self, delay: Duration) -> Self {
+        self.auto_hide = Some(delay);
+
+        self
+    }
+
+    /// Restyle this bar and its widgets to match the active [Theme] when the window manager
+    /// starts and whenever the theme is changed using [set_theme][0].
+    ///
+    /// The bar keeps the point size it was created with as this determines the size of the text
+    /// relative to the height of the bar.
+    ///
+    ///   [0]: penrose::extensions::util::theme::set_theme
+    pub fn with_active_theme(mut self) -> Self {
+        self.follow_theme = true;
+
+        self
+    }
+
+    /// Restyle this bar and its widgets to match the given [Theme] and redraw.
+    ///
+    /// See [Widget::set_theme] for details of how individual widgets are restyled.
+    pub fn set_theme(&mut self, theme: &Theme) -> Result<()> {
+        self.draw.set_bg(theme.bg);
+        self.font = theme.font.clone();
+        self.widgets.set_theme(theme);
+        self.theme = Some(theme.clone());
+
+        self.redraw()
+    }
+
+    // Themes are compared against the one last applied so that widgets are only restyled when
+    // the active theme has changed.
+    fn apply_active_theme(&mut self, state: &State<X>) {
+        if !self.follow_theme {
+            return;
+        }
+
+        match current_theme(state) {
+            Some(theme) if self.theme.as_ref() != Some(&theme) => {
+                info!(name = %theme.name, "applying new theme to status bar");
+                if let Err(e) = self.set_theme(&theme) {
+                    error!(%e, "error applying theme to status bar");
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Whether or not this bar is currently visible.
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Show or hide this bar on all screens, reserving or releasing the space used by the bar in
+    /// the layouts of each workspace.
+    ///
+    /// The window manager state needs to be refreshed after calling this method in order for the
+    /// changes to layout positions to be applied.
+    pub fn set_visible(&mut self, visible: bool, state: &mut State<X>) -> Result<()> {
+        self.visible = visible;
+        if visible {
+            self.schedule_hide();
+        }
+
+        for ws in state.client_set.workspaces_mut() {
+            ws.broadcast_message(SetReservedSpace(visible));
+        }
+
+        self.apply_visibility()?;
+        if visible {
+            self.redraw()?;
+        }
+
+        Ok(())
+    }
+
+    // Restart the auto-hide delay (if there is one), waking the event loop once it has passed so
+    // that the bar can be hidden even if no other events are received.
+    fn schedule_hide(&mut self) {
+        self.revealed_at = Instant::now();
+        if let (Some(delay), Some(tx)) = (self.auto_hide, &self.hide_timer) {
+            if tx.send(self.revealed_at + delay).is_err() {
+                error!("status bar auto-hide timer has exited");
+            }
+        }
+    }
+
+    fn pointer_is_over_bar(&self) -> bool {
+        match self.draw.conn.query_pointer() {
+            Ok(q) => matches!(q.child, Some(id) if self.screens.iter().any(|&(bar, _)| bar == id)),
+            Err(e) => {
+                error!(%e, "unable to query pointer position");
+                false
+            }
+        }
+    }
+
+    fn apply_visibility(&self) -> Result<()> {
+        for &(id, _) in self.screens.iter() {
+            if self.visible {
+                self.draw.conn.map(id)?;
+                self.draw
+                    .conn
+                    .set_client_config(id, &[ClientConfig::StackTop])?;
+            } else {
+                self.draw.conn.unmap(id)?;
+            }
+        }
+
+        for &id in self.triggers.iter() {
+            if self.visible {
+                self.draw.conn.unmap(id)?;
+            } else {
+                self.draw.conn.map(id)?;
+                self.draw
+                    .conn
+                    .set_client_config(id, &[ClientConfig::StackTop])?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Add this [`StatusBar`] into the given [`WindowManager`] along with the required
     /// hooks for driving it from the main WindowManager event loop.
     ///
-    /// If any [UpdateSchedule]s are requested by [Widgets] then they will be extracted and run as
-    /// part of calling this method.
+    /// If any [UpdateSchedule]s or [Watch]es are requested by [Widgets] then they will be
+    /// extracted and run as part of calling this method.
     pub fn add_to(mut self, mut wm: WindowManager<X>) -> WindowManager<X>
     where
         X: 'static,
@@ -178,6 +480,20 @@ impl<X: XConn> StatusBar<X> {
         if !schedules.is_empty() {
             run_update_schedules(schedules);
         }
+        run_watches(self.widgets.watches());
+
+        if self.auto_hide.is_some() {
+            self.hide_timer = Some(run_hide_timer());
+            self.schedule_hide();
+        }
+
+        if self.reserve_space {
+            let (position, px) = (self.position, self.widgets.max_thickness());
+            for ws in wm.state.client_set.workspaces_mut() {
+                let layouts = ws.set_available_layouts(LayoutStack::default());
+                ws.set_available_layouts(layouts.map(|l| position.reserve(l, px)));
+            }
+        }
 
         wm.state.add_extension(self);
         wm.state.config.compose_or_set_event_hook(event_hook);
@@ -188,24 +504,30 @@ impl<X: XConn> StatusBar<X> {
         wm
     }
 
-    fn init_for_screens(&mut self) -> Result<()> {
+    // Input events for the bar need to be selected using the window manager's connection rather
+    // than the one held by our Draw, as that is the connection the event hooks are run for.
+    fn init_for_screens(&mut self, x: &X) -> Result<()> {
         info!("initialising per screen status bar windows");
         let screen_details = self.draw.conn.screen_details()?;
+        self.arrangements = assign_arrangements(
+            screen_details.len(),
+            &self.draw.conn.screen_names()?,
+            self.draw.conn.primary_screen()?.unwrap_or(0),
+            &self.output_arrangements,
+            self.widgets.len(),
+        );
 
         self.screens = screen_details
             .iter()
             .enumerate()
-            .map(|(i, &Rect { x, y, w, h })| {
-                let bar_h = self.widgets.for_screen_mut(i).h;
-                let y = match self.position {
-                    Position::Top => y,
-                    Position::Bottom => h - bar_h,
-                };
+            .map(|(i, &r)| {
+                let thickness = self.widgets.for_screen_mut(self.arrangements[i]).h;
+                let r_bar = self.position.bar_rect(r, thickness);
 
                 debug!("creating new window");
                 let id = self.draw.new_window(
                     WinType::InputOutput(Atom::NetWindowTypeDock),
-                    Rect::new(x, y, w, bar_h),
+                    r_bar,
                     false,
                 )?;
 
@@ -221,52 +543,119 @@ impl<X: XConn> StatusBar<X> {
                 debug!("flushing");
                 self.draw.flush(id)?;
 
-                Ok((id, w))
+                let len = if self.position.is_vertical() {
+                    r_bar.h
+                } else {
+                    r_bar.w
+                };
+
+                Ok((id, len))
             })
             .collect::<Result<Vec<(Xid, u32)>>>()?;
 
+        self.widget_offsets = vec![Vec::new(); self.screens.len()];
+        self.drawn_focus = vec![None; self.screens.len()];
+        for &(id, _) in self.screens.iter() {
+            x.set_client_attributes(id, &[ClientAttr::UiEventMask])?;
+        }
+
+        if self.auto_hide.is_some() {
+            self.triggers = screen_details
+                .iter()
+                .map(|&r| {
+                    let id = self.draw.conn.create_window(
+                        WinType::InputOnly,
+                        self.position.bar_rect(r, 1),
+                        false,
+                    )?;
+                    x.set_client_attributes(id, &[ClientAttr::UiEventMask])?;
+
+                    Ok(id)
+                })
+                .collect::<Result<Vec<Xid>>>()?;
+        }
+
+        self.apply_visibility()?;
+
         Ok(())
     }
 
-    /// Re-render all widgets in this status bar for a single screen.
+    /// Re-render widgets in this status bar for a single screen.
+    ///
+    /// If `dirty` is provided and the layout of the bar is unchanged since it was last drawn then
+    /// only the widgets marked as dirty are redrawn, otherwise the entire bar is redrawn.
     /// Will panic if `i` is out of bounds
-    fn redraw_screen(&mut self, i: usize) -> Result<()> {
-        let (id, w_screen) = self.screens[i];
+    fn redraw_screen(&mut self, i: usize, dirty: Option<&[bool]>) -> Result<()> {
+        // Flushing the draw maps the bar window so hidden bars are redrawn once revealed instead
+        if !self.visible {
+            return Ok(());
+        }
+
+        let (id, len_bar) = self.screens[i];
         let screen_has_focus = self.active_screen == i;
-        let ps = self.widgets.for_screen_mut(i);
+        let vertical = self.position.is_vertical();
+        let ps = self.widgets.for_screen_mut(self.arrangements[i]);
 
         self.draw.set_font(&self.font, ps.point_size)?;
         let mut ctx = self.draw.context_for(id)?;
-        ctx.clear()?;
 
-        let mut extents = Vec::new();
+        // Widgets are laid out along the length of the bar: horizontally using their widths or
+        // vertically using their heights.
+        let mut lengths = Vec::new();
         let mut greedy_indices = Vec::new();
 
         for (j, w) in ps.ws.iter_mut().enumerate() {
-            extents.push(w.current_extent(&mut ctx, ps.h)?);
+            let (ew, eh) = w.current_extent(&mut ctx, ps.h)?;
+            lengths.push(if vertical { eh } else { ew });
             if w.is_greedy() {
                 greedy_indices.push(j)
             }
         }
 
-        let total = extents.iter().map(|(w, _)| w).sum::<u32>();
-        let n_greedy = greedy_indices.len();
+        let centered = ps.ws.iter().position(|w| w.is_centered());
+        distribute_space(&mut lengths, &greedy_indices, centered, len_bar);
 
-        if total < w_screen && n_greedy > 0 {
-            let per_greedy = (w_screen - total) / n_greedy as u32;
-            for i in greedy_indices.iter() {
-                let (w, h) = extents[*i];
-                extents[*i] = (w + per_greedy, h);
-            }
+        let mut offset = 0;
+        let mut offsets = Vec::with_capacity(lengths.len());
+        for len in lengths {
+            offsets.push((offset, len));
+            offset += len;
         }
 
-        let mut x = 0;
-        for (wd, (w, _)) in ps.ws.iter_mut().zip(extents) {
-            wd.draw(&mut ctx, self.active_screen, screen_has_focus, w, ps.h)?;
-            x += w;
-            ctx.set_x_offset(x as i32);
+        // Widgets that have not changed size can be redrawn in place so long as nothing else
+        // affecting the whole bar has changed.
+        let dirty = dirty.filter(|_| {
+            self.drawn_focus[i] == Some(screen_has_focus) && self.widget_offsets[i] == offsets
+        });
+
+        if dirty.is_none() {
+            ctx.clear()?;
         }
 
+        for (j, (wd, &(offset, len))) in ps.ws.iter_mut().zip(offsets.iter()).enumerate() {
+            let (w, h) = if vertical { (ps.h, len) } else { (len, ps.h) };
+            if vertical {
+                ctx.set_offset(0, offset as i32);
+            } else {
+                ctx.set_offset(offset as i32, 0);
+            }
+
+            if let Some(dirty) = dirty {
+                if !dirty.get(j).copied().unwrap_or(true) {
+                    continue;
+                }
+                ctx.fill_bg(Rect::new(0, 0, w, h))?;
+            }
+
+            // Widgets are free to draw past their own extent (e.g. text in a vertical bar that
+            // is wider than the bar itself) so drawing is clipped to the space allocated for them.
+            ctx.set_clip(Rect::new(0, 0, w, h));
+            wd.draw(&mut ctx, i, screen_has_focus, w, h)?;
+        }
+        ctx.reset_clip();
+
+        self.widget_offsets[i] = offsets;
+        self.drawn_focus[i] = Some(screen_has_focus);
         self.draw.flush(id)?;
 
         Ok(())
@@ -275,38 +664,199 @@ impl<X: XConn> StatusBar<X> {
     /// Re-render all widgets in this status bar for each screen it is displayed on
     pub fn redraw(&mut self) -> Result<()> {
         for i in 0..self.screens.len() {
-            self.redraw_screen(i)?;
+            self.redraw_screen(i, None)?;
         }
 
         Ok(())
     }
 
+    /// Pass a button press on one of our bar windows through to the widget that was drawn under
+    /// the pointer, returning true if the press landed in one of the widget's clickable regions.
+    fn handle_click(&mut self, e: &MouseEvent, state: &mut State<X>, x: &X) -> bool {
+        let screen = match self.screens.iter().position(|&(id, _)| id == e.data.window) {
+            Some(screen) => screen,
+            None => return false,
+        };
+
+        let vertical = self.position.is_vertical();
+        let Point { x: px, y: py } = e.data.wpt;
+        let p = if vertical { py } else { px };
+        let (ix, (offset, len)) = match self.widget_offsets[screen]
+            .iter()
+            .copied()
+            .enumerate()
+            .find(|&(_, (offset, len))| p >= offset && p < offset + len)
+        {
+            Some(found) => found,
+            None => return false,
+        };
+
+        let ps = self.widgets.for_screen_mut(self.arrangements[screen]);
+        let thickness = ps.h;
+        let widget = &mut ps.ws[ix];
+        let (pt, w, h) = if vertical {
+            (Point::new(px, py - offset), thickness, len)
+        } else {
+            (Point::new(px - offset, py), len, thickness)
+        };
+        let region = match widget
+            .clickable_regions(w, h)
+            .iter()
+            .position(|r| r.contains_point(pt))
+        {
+            Some(region) => region,
+            None => return false,
+        };
+
+        if let Err(e) = widget.on_region_click(e.state.button, region, pt, screen, state, x) {
+            error!(%e, "error running widget click hook");
+        }
+
+        true
+    }
+
+    // Startup hooks are only run for widgets that have not yet been started so that any
+    // additional per-screen widgets are initialised when new screens are attached.
+    fn run_startup_hooks(&mut self, state: &mut State<X>, x: &X) {
+        let mut new: Vec<usize> = self
+            .arrangements
+            .iter()
+            .filter(|ix| !self.started.contains(ix))
+            .copied()
+            .collect();
+        new.sort();
+        new.dedup();
+        if new.is_empty() {
+            return;
+        }
+
+        self.widgets.for_each_mut(&new, |w| {
+            if let Err(e) = w.on_startup(state, x) {
+                error!(%e, "error running widget startup hook");
+            };
+        });
+        self.started.extend(new);
+    }
+
+    // Only widgets that require drawing are redrawn where possible. The set of dirty widgets for
+    // every screen is determined up front as drawing a shared widget marks it as clean.
     fn redraw_if_needed(&mut self) -> Result<()> {
-        if self.widgets.require_draw(self.screens.len()) {
-            self.redraw()?;
+        let n_screens = self.screens.len();
+        if !self.widgets.require_draw(&self.arrangements) {
+            return Ok(());
+        }
+
+        let dirty: Vec<Vec<bool>> = (0..n_screens)
+            .map(|i| {
+                let ps = self.widgets.for_screen_mut(self.arrangements[i]);
+                ps.ws.iter().map(|w| w.require_draw()).collect()
+            })
+            .collect();
+
+        for (i, dirty) in dirty.iter().enumerate() {
+            self.redraw_screen(i, Some(dirty))?;
+        }
+
+        Ok(())
+    }
+
+    // The bar windows are drawn from a backing surface so exposed regions only need copying back
+    // to the window rather than redrawing.
+    fn handle_expose(&mut self, id: Xid) -> Result<()> {
+        if self.visible && self.screens.iter().any(|&(bar_id, _)| bar_id == id) {
+            self.draw.damage_all(id)?;
+            self.draw.flush(id)?;
         }
 
         Ok(())
     }
 }
 
+// Per screen widget arrangements are assigned starting from the primary screen, with the
+// remaining screens following on in order, unless an arrangement was requested for the output
+// driving the screen. Indices past the end of the available arrangements use the last one.
+fn assign_arrangements(
+    n_screens: usize,
+    names: &[String],
+    primary: usize,
+    output_arrangements: &HashMap<String, usize>,
+    n_arrangements: usize,
+) -> Vec<usize> {
+    (0..n_screens)
+        .map(|screen| {
+            let requested = names
+                .get(screen)
+                .and_then(|name| output_arrangements.get(name));
+            let ix = match requested {
+                Some(&ix) => ix,
+                None => match screen.cmp(&primary) {
+                    Ordering::Equal => 0,
+                    Ordering::Less => screen + 1,
+                    Ordering::Greater => screen,
+                },
+            };
+
+            min(ix, n_arrangements - 1)
+        })
+        .collect()
+}
+
+// Share any space left over after laying out widgets using their extents between the greedy
+// widgets in the bar. If a widget is centered then greedy widgets on either side of it are sized
+// so that it sits in the middle of the bar, otherwise the space is split evenly.
+fn distribute_space(
+    lengths: &mut [u32],
+    greedy_indices: &[usize],
+    centered: Option<usize>,
+    len_bar: u32,
+) {
+    let total = lengths.iter().sum::<u32>();
+    if total >= len_bar || greedy_indices.is_empty() {
+        return;
+    }
+    let spare = len_bar - total;
+
+    if let Some(c) = centered {
+        let before: u32 = lengths[..c].iter().sum();
+        let target = (len_bar - lengths[c]) / 2;
+        let (g_before, g_after): (Vec<usize>, Vec<usize>) = greedy_indices
+            .iter()
+            .filter(|&&j| j != c)
+            .partition(|&&j| j < c);
+
+        if !g_before.is_empty() && !g_after.is_empty() && target >= before {
+            let extra_before = min(target - before, spare);
+            share(lengths, &g_before, extra_before);
+            share(lengths, &g_after, spare - extra_before);
+            return;
+        }
+    }
+
+    share(lengths, greedy_indices, spare);
+
+    // Split space evenly, with any remainder going to the last widget so the total is exact
+    fn share(lengths: &mut [u32], indices: &[usize], space: u32) {
+        let per = space / indices.len() as u32;
+        for &j in indices.iter() {
+            lengths[j] += per;
+        }
+        lengths[indices[indices.len() - 1]] += space % indices.len() as u32;
+    }
+}
+
 /// Run any widget startup actions and then redraw
 pub fn startup_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> penrose::Result<()> {
     let s = state.extension::<StatusBar<X>>()?;
     let mut bar = s.borrow_mut();
 
-    if let Err(e) = bar.init_for_screens() {
+    if let Err(e) = bar.init_for_screens(x) {
         error!(%e, "unabled to initialise for screens");
         return Err(penrose::Error::NoScreens);
     }
 
     info!("running startup widget hooks");
-    let n_screens = bar.screens.len();
-    bar.widgets.for_each_mut(n_screens, |w| {
-        if let Err(e) = w.on_startup(state, x) {
-            error!(%e, "error running widget startup hook");
-        };
-    });
+    bar.run_startup_hooks(state, x);
+    bar.apply_active_theme(state);
 
     if let Err(e) = bar.redraw() {
         error!(%e, "error redrawing status bar");
@@ -320,9 +870,14 @@ pub fn refresh_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> penrose:
     let s = state.extension::<StatusBar<X>>()?;
     let mut bar = s.borrow_mut();
 
+    bar.apply_active_theme(state);
     bar.active_screen = state.client_set.current_screen().index();
-    let n_screens = bar.screens.len();
-    bar.widgets.for_each_mut(n_screens, |w| {
+    let StatusBar {
+        widgets,
+        arrangements,
+        ..
+    } = &mut *bar;
+    widgets.for_each_mut(arrangements, |w| {
         if let Err(e) = w.on_refresh(state, x) {
             error!(%e, "error running widget refresh hook");
         }
@@ -357,15 +912,61 @@ pub fn event_hook<X: XConn + 'static>(
             }
         }
 
-        if let Err(e) = bar.init_for_screens() {
+        let triggers: Vec<_> = bar.triggers.drain(..).collect();
+        for id in triggers {
+            if let Err(e) = bar.draw.conn.destroy_window(id) {
+                error!(%e, "error when removing previous status bar trigger window");
+            }
+        }
+
+        if let Err(e) = bar.init_for_screens(x) {
             error!(%e, "unabled to initialise for screens");
             return Err(penrose::Error::NoScreens);
         }
+
+        bar.run_startup_hooks(state, x);
+        if let Err(e) = bar.redraw() {
+            error!(%e, "error redrawing status bar");
+        }
+    }
+
+    if let XEvent::Expose(e) = event {
+        if e.count == 0 {
+            if let Err(e) = bar.handle_expose(e.id) {
+                error!(%e, "error restoring exposed status bar");
+            }
+        }
+    }
+
+    let clicked = match event {
+        XEvent::MouseEvent(e) if e.kind == MouseEventKind::Press => bar.handle_click(e, state, x),
+        _ => false,
+    };
+
+    let mut visibility_changed = false;
+    if let Some(delay) = bar.auto_hide {
+        let reveal = matches!(event, XEvent::Enter(p) if bar.triggers.contains(&p.id));
+        let mut hide = bar.visible && bar.revealed_at.elapsed() >= delay;
+        if clicked || (hide && bar.pointer_is_over_bar()) {
+            bar.schedule_hide();
+            hide = false;
+        }
+
+        if reveal || hide {
+            if let Err(e) = bar.set_visible(reveal, state) {
+                error!(%e, "error updating status bar visibility");
+            }
+            visibility_changed = true;
+        }
     }
 
     bar.active_screen = state.client_set.current_screen().index();
-    let n_screens = bar.screens.len();
-    bar.widgets.for_each_mut(n_screens, |w| {
+    let StatusBar {
+        widgets,
+        arrangements,
+        ..
+    } = &mut *bar;
+    widgets.for_each_mut(arrangements, |w| {
         if let Err(e) = w.on_event(event, state, x) {
             error!(%e, "error running widget event hook");
         };
@@ -375,6 +976,12 @@ pub fn event_hook<X: XConn + 'static>(
         error!(%e, "error redrawing status bar");
     }
 
+    // Refreshing runs our own refresh hook so we need to release the borrow on the bar first
+    drop(bar);
+    if clicked || visibility_changed {
+        x.refresh(state)?;
+    }
+
     Ok(true)
 }
 
@@ -388,8 +995,12 @@ pub fn manage_hook<X: XConn + 'static>(
     let mut bar = s.borrow_mut();
 
     bar.active_screen = state.client_set.current_screen().index();
-    let n_screens = bar.screens.len();
-    bar.widgets.for_each_mut(n_screens, |w| {
+    let StatusBar {
+        widgets,
+        arrangements,
+        ..
+    } = &mut *bar;
+    widgets.for_each_mut(arrangements, |w| {
         if let Err(e) = w.on_new_client(id, state, x) {
             error!(%e, "error running widget manage hook");
         }
@@ -401,3 +1012,65 @@ pub fn manage_hook<X: XConn + 'static>(
 
     Ok(())
 }
+
+/// Toggle the visibility of the [StatusBar], releasing or reclaiming any space reserved for it
+/// in the layouts of each workspace.
+pub fn toggle_bar<X: XConn + 'static>() -> Box<dyn KeyEventHandler<X>> {
+    key_handler(|state: &mut State<X>, x: &X| {
+        let s = state.extension::<StatusBar<X>>()?;
+        let mut bar = s.borrow_mut();
+        let visible = !bar.visible;
+
+        if let Err(e) = bar.set_visible(visible, state) {
+            error!(%e, "error updating status bar visibility");
+        }
+
+        // Refreshing runs our own refresh hook so we need to release the borrow on the bar first
+        drop(bar);
+        x.refresh(state)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    #[test_case(&[10, 20, 30], &[1], 50, &[10, 20, 30]; "overflowing the bar")]
+    #[test_case(&[10, 20, 30], &[1], 60, &[10, 20, 30]; "exactly filling the bar")]
+    #[test_case(&[10, 20, 30], &[], 100, &[10, 20, 30]; "no greedy widgets")]
+    #[test_case(&[10, 20, 30], &[1], 100, &[10, 60, 30]; "single greedy widget")]
+    #[test_case(&[10, 20, 30], &[0, 2], 100, &[30, 20, 50]; "multiple greedy widgets")]
+    #[test_case(&[10, 20, 30], &[0, 1, 2], 101, &[23, 33, 45]; "remainder to last greedy widget")]
+    #[test]
+    fn distribute_space_without_centered_widget(
+        lengths: &[u32],
+        greedy: &[usize],
+        len_bar: u32,
+        expected: &[u32],
+    ) {
+        let mut lengths = lengths.to_vec();
+        distribute_space(&mut lengths, greedy, None, len_bar);
+
+        assert_eq!(lengths, expected);
+    }
+
+    #[test_case(&[10, 20, 10], &[0, 2], 100, &[40, 20, 40]; "balanced")]
+    #[test_case(&[30, 20, 10], &[0, 2], 100, &[40, 20, 40]; "wide left side")]
+    #[test_case(&[10, 20, 10], &[0, 2], 101, &[40, 20, 41]; "remainder after centered widget")]
+    #[test_case(&[10, 20, 10], &[0], 100, &[70, 20, 10]; "greedy only before centered widget")]
+    #[test_case(&[60, 20, 10], &[0, 2], 100, &[65, 20, 15]; "centering not possible")]
+    #[test]
+    fn distribute_space_with_centered_widget(
+        lengths: &[u32],
+        greedy: &[usize],
+        len_bar: u32,
+        expected: &[u32],
+    ) {
+        let mut lengths = lengths.to_vec();
+        distribute_space(&mut lengths, greedy, Some(1), len_bar);
+
+        assert_eq!(lengths, expected);
+        assert_eq!(lengths.iter().sum::<u32>(), len_bar);
+    }
+}
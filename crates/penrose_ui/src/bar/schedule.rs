@@ -1,14 +1,17 @@
 //! Utilities for running scheduled updates to widgets
-use crate::bar::widgets::Text;
-use penrose::util::spawn_with_args;
+use crate::bar::{watch::WAKEUP_ATOM, widgets::Text};
+use penrose::x11rb::Waker;
 use std::{
     cmp::max,
     fmt,
-    sync::{Arc, Mutex},
+    sync::{
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
     thread,
     time::{Duration, Instant},
 };
-use tracing::trace;
+use tracing::{error, trace};
 
 /// The minimum allowed interval for an [UpdateSchedule].
 pub const MIN_DURATION: Duration = Duration::from_secs(1);
@@ -86,19 +89,101 @@ impl UpdateSchedule {
 /// Run the polling thread for a set of [UpdateSchedule]s and update their contents on
 /// their requested intervals.
 pub(crate) fn run_update_schedules(mut schedules: Vec<UpdateSchedule>) {
-    thread::spawn(move || loop {
-        trace!("running UpdateSchedule updates for all pending widgets");
-        while schedules[0].next < Instant::now() {
-            schedules[0].update_text();
-            schedules.sort_by(|a, b| a.next.cmp(&b.next));
+    thread::spawn(move || {
+        let waker = Waker::new(WAKEUP_ATOM)
+            .map_err(|e| error!(%e, "unable to wake status bar: updates will be delayed"))
+            .ok();
+
+        loop {
+            trace!("running UpdateSchedule updates for all pending widgets");
+            while schedules[0].next < Instant::now() {
+                schedules[0].update_text();
+                schedules.sort_by_key(|a| a.next);
+            }
+
+            // Ensure that an event drops into the main window manager event loop and triggers
+            // the `on_event` hook of the status bar.
+            if let Some(waker) = &waker {
+                waker.wake();
+            }
+
+            let interval = schedules[0].next.saturating_duration_since(Instant::now());
+            trace!(?interval, "sleeping until next update point");
+            thread::sleep(interval);
         }
+    });
+}
 
-        // FIXME: this is a hack at the moment to ensure that an event drops into the main
-        // window manager event loop and triggers the `on_event` hook of the status bar.
-        let _ = spawn_with_args("xsetroot", &["-name", ""]);
+/// Run a timer thread for hiding an auto-hidden status bar, returning a [Sender] for setting the
+/// time at which the bar should next be hidden.
+///
+/// The window manager event loop is woken once each requested deadline passes so that the
+/// status bar event hook is able to hide the bar. Sending a new deadline replaces any pending one.
+pub(crate) fn run_hide_timer() -> Sender<Instant> {
+    let (tx, rx) = channel();
 
-        let interval = schedules[0].next - Instant::now();
-        trace!(?interval, "sleeping until next update point");
-        thread::sleep(interval);
+    thread::spawn(move || {
+        let waker = Waker::new(WAKEUP_ATOM)
+            .map_err(|e| error!(%e, "unable to wake status bar: auto-hide will be delayed"))
+            .ok();
+
+        wait_for_deadlines(rx, || {
+            if let Some(waker) = &waker {
+                waker.wake();
+            }
+        });
     });
+
+    tx
+}
+
+// Block until each deadline received on `rx` has passed and then call `wake`, returning once the
+// sending side of the channel has been dropped.
+fn wait_for_deadlines(rx: Receiver<Instant>, wake: impl Fn()) {
+    let mut deadline: Option<Instant> = None;
+
+    loop {
+        let res = match deadline {
+            Some(t) => rx.recv_timeout(t.saturating_duration_since(Instant::now())),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match res {
+            Ok(t) => deadline = Some(t),
+            Err(RecvTimeoutError::Timeout) => {
+                trace!("auto-hide deadline reached: waking status bar");
+                deadline = None;
+                wake();
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn wait_for_deadlines_wakes_once_per_deadline_using_the_latest_one() {
+        let (tx, rx) = channel();
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let w = wakes.clone();
+        let handle = thread::spawn(move || {
+            wait_for_deadlines(rx, || {
+                w.fetch_add(1, Ordering::SeqCst);
+            })
+        });
+
+        let now = Instant::now();
+        tx.send(now + Duration::from_secs(60)).unwrap();
+        tx.send(now + Duration::from_millis(10)).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(wakes.load(Ordering::SeqCst), 1);
+
+        drop(tx);
+        handle.join().unwrap();
+        assert_eq!(wakes.load(Ordering::SeqCst), 1);
+    }
 }
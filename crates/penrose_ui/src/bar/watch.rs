@@ -0,0 +1,77 @@
+//! Utilities for updating widgets in response to external events
+use crate::bar::widgets::Text;
+use penrose::x11rb::Waker;
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    thread,
+};
+use tracing::{error, trace, warn};
+
+// The type of the client message sent to the root window in order to wake up the window manager
+// event loop. Penrose ignores client messages it does not recognise other than passing them to
+// the event hooks, which is where the status bar checks whether it needs to redraw.
+pub(crate) const WAKEUP_ATOM: &str = "_PENROSE_UI_WAKEUP";
+
+/// For widgets that want to have their content updated as soon as new data is available from an
+/// external source (such as a socket, fifo or channel) rather than polling on an interval.
+///
+/// The provided `wait_for_text` function is run in a loop on its own thread and is expected to
+/// block until new content is available. Returning `None` indicates that the source has been
+/// closed and that the widget should stop watching for updates.
+///
+/// See [WatchText][crate::bar::widgets::WatchText] for a simple implementation of this behaviour.
+pub struct Watch {
+    pub(crate) wait_for_text: Box<dyn FnMut() -> Option<String> + Send + 'static>,
+    pub(crate) txt: Arc<Mutex<Text>>,
+}
+
+impl fmt::Debug for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watch").field("txt", &self.txt).finish()
+    }
+}
+
+impl Watch {
+    /// Construct a new [Watch] using a blocking function for producing the [Widget] content.
+    ///
+    /// The updated content will then be stored in the provided `Arc<Mutex<Text>>` for access
+    /// within your widget logic.
+    ///
+    ///   [Widget]: crate::bar::widgets::Widget
+    pub fn new(
+        wait_for_text: Box<dyn FnMut() -> Option<String> + Send + 'static>,
+        txt: Arc<Mutex<Text>>,
+    ) -> Self {
+        Self { wait_for_text, txt }
+    }
+
+    fn run(mut self) {
+        let waker = Waker::new(WAKEUP_ATOM)
+            .map_err(|e| error!(%e, "unable to wake status bar: updates will be delayed"))
+            .ok();
+
+        while let Some(s) = (self.wait_for_text)() {
+            trace!(?s, "output from running wait_for_text");
+            let mut t = match self.txt.lock() {
+                Ok(inner) => inner,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            t.set_text(s);
+            drop(t);
+
+            if let Some(waker) = &waker {
+                waker.wake();
+            }
+        }
+
+        warn!("external source for widget closed: no longer watching for updates");
+    }
+}
+
+/// Run each [Watch] on its own thread, updating its contents whenever new data is available.
+pub(crate) fn run_watches(watches: Vec<Watch>) {
+    for w in watches {
+        thread::spawn(move || w.run());
+    }
+}
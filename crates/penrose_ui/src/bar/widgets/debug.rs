@@ -2,7 +2,10 @@
 use crate::bar::widgets::{Context, Result, Text, TextStyle, Widget};
 use penrose::{
     core::State,
-    extensions::util::debug::{summarise_state, CurrentStateConfig},
+    extensions::util::{
+        debug::{summarise_state, CurrentStateConfig},
+        theme::Theme,
+    },
     x::XConn,
 };
 
@@ -38,6 +41,10 @@ impl<X: XConn> Widget<X> for ActiveWindowId {
         Widget::<X>::require_draw(&self.inner)
     }
 
+    fn set_theme(&mut self, theme: &Theme) {
+        Widget::<X>::set_theme(&mut self.inner, theme)
+    }
+
     fn on_refresh(&mut self, state: &mut State<X>, _: &X) -> Result<()> {
         if let Some(id) = state.client_set.current_client() {
             self.inner.set_text(format!("FOCUS={}", *id))
@@ -88,9 +95,67 @@ impl<X: XConn> Widget<X> for StateSummary {
         Widget::<X>::require_draw(&self.inner)
     }
 
+    fn set_theme(&mut self, theme: &Theme) {
+        Widget::<X>::set_theme(&mut self.inner, theme)
+    }
+
     fn on_refresh(&mut self, state: &mut State<X>, _: &X) -> Result<()> {
         self.inner.set_text(summarise_state(state, &self.cfg));
 
         Ok(())
     }
 }
+
+/// A text widget that shows timings for the main event loop of the window manager.
+///
+/// Shows the number of refreshes run along with their mean run time and the user hook with the
+/// largest mean run time. Updates on refresh.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventLoopMetrics {
+    inner: Text,
+}
+
+impl EventLoopMetrics {
+    /// Create a new EventLoopMetrics widget.
+    pub fn new(style: TextStyle, is_greedy: bool, right_justified: bool) -> Self {
+        Self {
+            inner: Text::new("", style, is_greedy, right_justified),
+        }
+    }
+}
+
+impl<X: XConn> Widget<X> for EventLoopMetrics {
+    fn draw(&mut self, ctx: &mut Context<'_>, s: usize, f: bool, w: u32, h: u32) -> Result<()> {
+        Widget::<X>::draw(&mut self.inner, ctx, s, f, w, h)
+    }
+
+    fn current_extent(&mut self, ctx: &mut Context<'_>, h: u32) -> Result<(u32, u32)> {
+        Widget::<X>::current_extent(&mut self.inner, ctx, h)
+    }
+
+    fn is_greedy(&self) -> bool {
+        Widget::<X>::is_greedy(&self.inner)
+    }
+
+    fn require_draw(&self) -> bool {
+        Widget::<X>::require_draw(&self.inner)
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        Widget::<X>::set_theme(&mut self.inner, theme)
+    }
+
+    fn on_refresh(&mut self, state: &mut State<X>, _: &X) -> Result<()> {
+        let metrics = state.metrics();
+        let refreshes = metrics.refreshes();
+        let mut txt = format!("REFRESH={} ({:?})", refreshes.count, refreshes.mean());
+
+        if let Some((kind, t)) = metrics.slowest_hook() {
+            txt.push_str(&format!(" SLOWEST={kind} ({:?})", t.mean()));
+        }
+
+        self.inner.set_text(txt);
+
+        Ok(())
+    }
+}
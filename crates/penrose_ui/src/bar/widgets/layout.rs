@@ -0,0 +1,361 @@
+//! Widgets for controlling the layout of other widgets within a status bar
+use crate::{
+    bar::{
+        schedule::{run_update_schedules, UpdateSchedule},
+        watch::{run_watches, Watch},
+        widgets::Widget,
+    },
+    Context, Result,
+};
+use penrose::{
+    core::{bindings::MouseButton, State},
+    extensions::util::theme::Theme,
+    pure::geometry::{Point, Rect},
+    x::{XConn, XEvent},
+    Color, Xid,
+};
+use std::fmt;
+
+/// Empty space between widgets.
+///
+/// By default a [Spacer] is greedy, expanding to fill any available space in the bar so that
+/// the widgets following it are pushed to the end of the bar. Use [Spacer::fixed] for a gap of a
+/// specific size instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spacer {
+    len: Option<u32>,
+    bg: Option<Color>,
+    require_draw: bool,
+}
+
+impl Default for Spacer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Spacer {
+    /// Create a new greedy [Spacer].
+    pub fn new() -> Self {
+        Self {
+            len: None,
+            bg: None,
+            require_draw: true,
+        }
+    }
+
+    /// Create a new [Spacer] of a fixed length in pixels along the length of the bar.
+    pub fn fixed(len: u32) -> Self {
+        Self {
+            len: Some(len),
+            ..Self::new()
+        }
+    }
+
+    /// Fill this spacer with the given color rather than the background color of the bar.
+    pub fn with_bg(mut self, bg: impl Into<Color>) -> Self {
+        self.bg = Some(bg.into());
+
+        self
+    }
+}
+
+impl<X: XConn> Widget<X> for Spacer {
+    fn draw(&mut self, ctx: &mut Context<'_>, _: usize, _: bool, w: u32, h: u32) -> Result<()> {
+        match self.bg {
+            Some(color) => ctx.fill_rect(Rect::new(0, 0, w, h), color)?,
+            None => ctx.fill_bg(Rect::new(0, 0, w, h))?,
+        }
+        self.require_draw = false;
+
+        Ok(())
+    }
+
+    fn current_extent(&mut self, _: &mut Context<'_>, _: u32) -> Result<(u32, u32)> {
+        let len = self.len.unwrap_or(0);
+
+        Ok((len, len))
+    }
+
+    fn require_draw(&self) -> bool {
+        self.require_draw
+    }
+
+    fn is_greedy(&self) -> bool {
+        self.len.is_none()
+    }
+}
+
+/// How the widgets within a [Group] are positioned within the space available to it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Align {
+    /// Pack widgets against the start of the group
+    #[default]
+    Left,
+    /// Center widgets within the group. A centered group is placed in the center of the bar
+    /// itself if there are greedy widgets on either side of it (see [Widget::is_centered]).
+    Center,
+    /// Pack widgets against the end of the group
+    Right,
+}
+
+/// A greedy section of a status bar containing a number of widgets that are aligned together.
+///
+/// Groups allow for composing a "left | center | right" bar layout without needing to manually
+/// pad widgets:
+///
+/// ```no_run
+/// use penrose::x11rb::RustConn;
+/// use penrose_ui::bar::widgets::{
+///     ActiveWindowName, Align, CurrentLayout, Group, RootWindowName, Widget, Workspaces,
+/// };
+/// # use penrose_ui::core::TextStyle;
+/// # let style = TextStyle {
+/// #     fg: 0xebdbb2ff.into(),
+/// #     bg: Some(0x282828ff.into()),
+/// #     padding: (2, 2),
+/// # };
+///
+/// let widgets: Vec<Box<dyn Widget<RustConn>>> = vec![
+///     Box::new(Group::new(
+///         Align::Left,
+///         vec![
+///             Box::new(Workspaces::new(style, 0x458588ff, 0x504945ff)),
+///             Box::new(CurrentLayout::new(style)),
+///         ],
+///     )),
+///     Box::new(Group::new(
+///         Align::Center,
+///         vec![Box::new(ActiveWindowName::new(80, style, false, false))],
+///     )),
+///     Box::new(Group::new(
+///         Align::Right,
+///         vec![Box::new(RootWindowName::new(style, false, false))],
+///     )),
+/// ];
+/// ```
+///
+/// Any greedy widgets within a group share the space left over after laying out the rest of its
+/// widgets, in which case the [Align] of the group has no effect.
+pub struct Group<X: XConn> {
+    align: Align,
+    ws: Vec<Box<dyn Widget<X>>>,
+    thickness: u32,
+    // The layout of our widgets (offset, length) and the size of the group as last drawn
+    layout: Vec<(u32, u32)>,
+    size: (u32, u32),
+    vertical: bool,
+}
+
+impl<X: XConn> fmt::Debug for Group<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Group")
+            .field("align", &self.align)
+            .field("n_widgets", &self.ws.len())
+            .field("layout", &self.layout)
+            .finish()
+    }
+}
+
+impl<X: XConn> Group<X> {
+    /// Create a new [Group] containing the given widgets.
+    pub fn new(align: Align, ws: Vec<Box<dyn Widget<X>>>) -> Self {
+        Self {
+            align,
+            ws,
+            thickness: 0,
+            layout: Vec::new(),
+            size: (0, 0),
+            vertical: false,
+        }
+    }
+
+    // The clickable regions of each widget in the group along with the offset that needs
+    // applying to map them into the coordinates of the group.
+    fn child_regions(&self) -> impl Iterator<Item = (usize, Vec<Rect>, u32)> + '_ {
+        let (w, h) = self.size;
+
+        self.ws
+            .iter()
+            .zip(self.layout.iter())
+            .enumerate()
+            .map(move |(j, (wd, &(offset, len)))| {
+                let (cw, ch) = if self.vertical { (w, len) } else { (len, h) };
+                (j, wd.clickable_regions(cw, ch), offset)
+            })
+    }
+
+    fn shift(&self, r: Rect, offset: u32) -> Rect {
+        if self.vertical {
+            Rect::new(r.x, r.y + offset, r.w, r.h)
+        } else {
+            Rect::new(r.x + offset, r.y, r.w, r.h)
+        }
+    }
+}
+
+impl<X: XConn> Widget<X> for Group<X> {
+    fn draw(&mut self, ctx: &mut Context<'_>, s: usize, f: bool, w: u32, h: u32) -> Result<()> {
+        // Groups are always drawn with the thickness of the bar across it so if that matches
+        // our width (and not our height) then we are being drawn in a vertical bar.
+        self.vertical = w == self.thickness && h != self.thickness;
+        let len_group = if self.vertical { h } else { w };
+
+        let mut lengths = Vec::with_capacity(self.ws.len());
+        let mut greedy_indices = Vec::new();
+        for (j, wd) in self.ws.iter_mut().enumerate() {
+            let (ew, eh) = wd.current_extent(ctx, self.thickness)?;
+            lengths.push(if self.vertical { eh } else { ew });
+            if wd.is_greedy() {
+                greedy_indices.push(j);
+            }
+        }
+
+        let total = lengths.iter().sum::<u32>();
+        let spare = len_group.saturating_sub(total);
+        let mut offset = match self.align {
+            _ if !greedy_indices.is_empty() => 0,
+            Align::Left => 0,
+            Align::Center => spare / 2,
+            Align::Right => spare,
+        };
+
+        if !greedy_indices.is_empty() {
+            let per_greedy = spare / greedy_indices.len() as u32;
+            for &j in greedy_indices.iter() {
+                lengths[j] += per_greedy;
+            }
+        }
+
+        let (dx, dy) = ctx.offset();
+        self.layout.clear();
+        for (wd, len) in self.ws.iter_mut().zip(lengths) {
+            let (cw, ch) = if self.vertical {
+                ctx.set_offset(dx, dy + offset as i32);
+                (w, len)
+            } else {
+                ctx.set_offset(dx + offset as i32, dy);
+                (len, h)
+            };
+
+            wd.draw(ctx, s, f, cw, ch)?;
+            self.layout.push((offset, len));
+            offset += len;
+        }
+        ctx.set_offset(dx, dy);
+        self.size = (w, h);
+
+        Ok(())
+    }
+
+    // Extents are summed along both axes as only the one running along the length of the bar is
+    // used when laying out widgets.
+    fn current_extent(&mut self, ctx: &mut Context<'_>, h: u32) -> Result<(u32, u32)> {
+        self.thickness = h;
+        let (mut total_w, mut total_h) = (0, 0);
+        for wd in self.ws.iter_mut() {
+            let (w, h) = wd.current_extent(ctx, h)?;
+            total_w += w;
+            total_h += h;
+        }
+
+        Ok((total_w, total_h))
+    }
+
+    fn require_draw(&self) -> bool {
+        self.ws.iter().any(|w| w.require_draw())
+    }
+
+    fn is_greedy(&self) -> bool {
+        true
+    }
+
+    fn is_centered(&self) -> bool {
+        self.align == Align::Center
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        self.ws.iter_mut().for_each(|w| w.set_theme(theme))
+    }
+
+    // The status bar only requests a single schedule and watch from each widget so we start
+    // those required by the widgets in this group ourselves.
+    fn update_schedule(&mut self) -> Option<UpdateSchedule> {
+        let schedules: Vec<_> = self
+            .ws
+            .iter_mut()
+            .filter_map(|w| w.update_schedule())
+            .collect();
+        if !schedules.is_empty() {
+            run_update_schedules(schedules);
+        }
+
+        None
+    }
+
+    fn watch(&mut self) -> Option<Watch> {
+        run_watches(self.ws.iter_mut().filter_map(|w| w.watch()).collect());
+
+        None
+    }
+
+    fn on_startup(&mut self, state: &mut State<X>, x: &X) -> Result<()> {
+        self.ws.iter_mut().try_for_each(|w| w.on_startup(state, x))
+    }
+
+    fn on_event(&mut self, event: &XEvent, state: &mut State<X>, x: &X) -> Result<()> {
+        self.ws
+            .iter_mut()
+            .try_for_each(|w| w.on_event(event, state, x))
+    }
+
+    fn on_refresh(&mut self, state: &mut State<X>, x: &X) -> Result<()> {
+        self.ws.iter_mut().try_for_each(|w| w.on_refresh(state, x))
+    }
+
+    fn on_new_client(&mut self, id: Xid, state: &mut State<X>, x: &X) -> Result<()> {
+        self.ws
+            .iter_mut()
+            .try_for_each(|w| w.on_new_client(id, state, x))
+    }
+
+    fn clickable_regions(&self, _: u32, _: u32) -> Vec<Rect> {
+        self.child_regions()
+            .flat_map(|(_, regions, offset)| {
+                regions.into_iter().map(move |r| self.shift(r, offset))
+            })
+            .collect()
+    }
+
+    fn on_region_click(
+        &mut self,
+        button: MouseButton,
+        region: usize,
+        pt: Point,
+        screen: usize,
+        state: &mut State<X>,
+        x: &X,
+    ) -> Result<()> {
+        let mut base = 0;
+        let mut target = None;
+        for (j, regions, offset) in self.child_regions() {
+            if region < base + regions.len() {
+                target = Some((j, region - base, offset));
+                break;
+            }
+            base += regions.len();
+        }
+
+        let (j, region, offset) = match target {
+            Some(target) => target,
+            None => return Ok(()),
+        };
+        let pt = if self.vertical {
+            Point::new(pt.x, pt.y - offset)
+        } else {
+            Point::new(pt.x - offset, pt.y)
+        };
+
+        self.ws[j].on_region_click(button, region, pt, screen, state, x)
+    }
+}
@@ -1,24 +1,38 @@
 //! Self rendering building blocks for text based UI elements
-use crate::{bar::schedule::UpdateSchedule, Context, Result, TextStyle};
+use crate::{
+    bar::{schedule::UpdateSchedule, watch::Watch},
+    Context, Result, TextDecoration, TextStyle,
+};
 use penrose::{
-    core::State,
-    pure::geometry::Rect,
+    core::{bindings::MouseButton, State},
+    extensions::util::theme::Theme,
+    pure::geometry::{Point, Rect},
     x::{XConn, XEvent},
     Color, Xid,
 };
 use std::{
     fmt,
-    sync::{Arc, Mutex, MutexGuard},
+    fs::File,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    sync::{mpsc::Receiver, Arc, Mutex, MutexGuard},
     time::Duration,
 };
 
 pub mod debug;
 pub mod sys;
 
+mod layout;
 mod simple;
+mod systray;
 mod workspaces;
 
-pub use simple::{ActiveWindowName, CurrentLayout, RootWindowName};
+pub use layout::{Align, Group, Spacer};
+pub use simple::{
+    ActiveWindowName, CurrentLayout, FocusedTitle, KeyboardLayout, MinimizedWindows,
+    RootPropertyText, RootWindowName,
+};
+pub use systray::SysTray;
 pub use workspaces::{DefaultUi, FocusState, Workspaces, WorkspacesUi, WorkspacesWidget, WsMeta};
 
 /// A status bar widget that can be rendered using a [Context]
@@ -47,12 +61,33 @@ where
     /// space will be split evenly between all widgets.
     fn is_greedy(&self) -> bool;
 
+    /// If true, this widget will be positioned in the center of the bar rather than directly
+    /// after the widgets preceding it. This is only possible when there are greedy widgets on
+    /// either side of it to absorb the remaining space and only the first centered widget in a
+    /// StatusBar is taken into account.
+    fn is_centered(&self) -> bool {
+        false
+    }
+
+    #[allow(unused_variables)]
+    /// Restyle this widget to match the given [Theme], triggering a redraw if anything changed.
+    ///
+    /// This is called by a [StatusBar][crate::StatusBar] that is following the active theme
+    /// whenever the theme changes. By default widgets keep the style they were created with.
+    fn set_theme(&mut self, theme: &Theme) {}
+
     /// An [UpdateSchedule] to allow for external updates to this Widget's state independently of
     /// the window manager event loop.
     fn update_schedule(&mut self) -> Option<UpdateSchedule> {
         None
     }
 
+    /// A [Watch] to allow for updating this Widget's state as soon as new data is available from
+    /// an external source, independently of the window manager event loop.
+    fn watch(&mut self) -> Option<Watch> {
+        None
+    }
+
     #[allow(unused_variables)]
     /// A startup hook to be run in order to initialise this Widget
     fn on_startup(&mut self, state: &mut State<X>, x: &X) -> Result<()> {
@@ -76,6 +111,53 @@ where
     fn on_new_client(&mut self, id: Xid, state: &mut State<X>, x: &X) -> Result<()> {
         Ok(())
     }
+
+    #[allow(unused_variables)]
+    /// The regions of this Widget that respond to mouse clicks when it is rendered with the
+    /// given width and height. Regions are specified relative to the top left corner of the
+    /// widget.
+    ///
+    /// By default widgets do not declare any clickable regions and will not receive calls to
+    /// [Widget::on_click] or [Widget::on_region_click].
+    fn clickable_regions(&self, w: u32, h: u32) -> Vec<Rect> {
+        Vec::new()
+    }
+
+    #[allow(unused_variables)]
+    /// A hook to be run when a mouse button is pressed within one of the regions returned by
+    /// [Widget::clickable_regions].
+    ///
+    /// `pt` is the position of the click relative to the top left corner of the widget and
+    /// `screen` is the index of the screen whose bar was clicked. The window manager will be
+    /// refreshed after this hook has run so any changes made to `state` will be applied.
+    fn on_click(
+        &mut self,
+        button: MouseButton,
+        pt: Point,
+        screen: usize,
+        state: &mut State<X>,
+        x: &X,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    #[allow(unused_variables)]
+    /// A hook to be run when a mouse button is pressed within one of the regions returned by
+    /// [Widget::clickable_regions] for widgets that need to know which region was clicked.
+    ///
+    /// `region` is the index of the region that was clicked. By default this calls
+    /// [Widget::on_click].
+    fn on_region_click(
+        &mut self,
+        button: MouseButton,
+        region: usize,
+        pt: Point,
+        screen: usize,
+        state: &mut State<X>,
+        x: &X,
+    ) -> Result<()> {
+        self.on_click(button, pt, screen, state, x)
+    }
 }
 
 /// A simple piece of static text with an optional background color.
@@ -88,6 +170,7 @@ pub struct Text {
     fg: Color,
     bg: Option<Color>,
     padding: (u32, u32),
+    decoration: TextDecoration,
     is_greedy: bool,
     right_justified: bool,
     extent: Option<(u32, u32)>,
@@ -107,6 +190,7 @@ impl Text {
             fg: style.fg,
             bg: style.bg,
             padding: style.padding,
+            decoration: TextDecoration::default(),
             is_greedy,
             right_justified,
             extent: None,
@@ -114,6 +198,13 @@ impl Text {
         }
     }
 
+    /// Set an underline and/or overline to be drawn along with the text.
+    pub fn with_decoration(mut self, decoration: TextDecoration) -> Self {
+        self.decoration = decoration;
+
+        self
+    }
+
     /// Borrow the current contents of the widget.
     pub fn get_text(&self) -> &String {
         &self.txt
@@ -124,6 +215,15 @@ impl Text {
         &mut self.txt
     }
 
+    /// Set the foreground color used to render the text and trigger a redraw
+    pub fn set_fg(&mut self, fg: impl Into<Color>) {
+        let fg = fg.into();
+        if self.fg != fg {
+            self.fg = fg;
+            self.require_draw = true;
+        }
+    }
+
     /// Set the rendered text and trigger a redraw
     pub fn set_text(&mut self, txt: impl Into<String>) {
         let new_text = txt.into();
@@ -147,9 +247,11 @@ impl<X: XConn> Widget<X> for Text {
         if right_justify {
             ctx.translate(offset, 0);
             ctx.draw_text(&self.txt, h - eh, self.padding, self.fg)?;
+            ctx.draw_text_decoration(self.decoration, ew, h)?;
             ctx.translate(-offset, 0);
         } else {
             ctx.draw_text(&self.txt, h - eh, self.padding, self.fg)?;
+            ctx.draw_text_decoration(self.decoration, ew, h)?;
         }
 
         self.require_draw = false;
@@ -178,6 +280,16 @@ impl<X: XConn> Widget<X> for Text {
     fn is_greedy(&self) -> bool {
         self.is_greedy
     }
+
+    // Text without a background is drawn over whatever is behind it so only an existing
+    // background is replaced.
+    fn set_theme(&mut self, theme: &Theme) {
+        self.set_fg(theme.fg);
+        if self.bg.is_some() && self.bg != Some(theme.bg) {
+            self.bg = Some(theme.bg);
+            self.require_draw = true;
+        }
+    }
 }
 
 /// A simple widget that does not care about window manager state.
@@ -264,6 +376,10 @@ impl<X: XConn> Widget<X> for RefreshText {
         Widget::<X>::require_draw(&self.inner)
     }
 
+    fn set_theme(&mut self, theme: &Theme) {
+        Widget::<X>::set_theme(&mut self.inner, theme)
+    }
+
     fn on_refresh(&mut self, _: &mut State<X>, _: &X) -> Result<()> {
         let txt = (self.get_text)();
 
@@ -371,6 +487,10 @@ impl<X: XConn> Widget<X> for IntervalText {
         Widget::<X>::require_draw(&*self.inner_guard())
     }
 
+    fn set_theme(&mut self, theme: &Theme) {
+        Widget::<X>::set_theme(&mut *self.inner_guard(), theme)
+    }
+
     fn update_schedule(&mut self) -> Option<UpdateSchedule> {
         Some(UpdateSchedule::new(
             self.interval,
@@ -379,3 +499,128 @@ impl<X: XConn> Widget<X> for IntervalText {
         ))
     }
 }
+
+/// A simple widget that does not care about window manager state and updates its content as
+/// soon as new data is available from an external source.
+///
+/// The provided `wait_for_text` function is run in its own thread and should block until new
+/// content is available, returning `None` once the source has been closed. Whenever the output
+/// changes, this widget will trigger a re-render of the status bar.
+///
+/// Unlike [`IntervalText`], updates are shown immediately rather than on the next tick of a
+/// polling interval. This is useful for content that changes infrequently but should be shown
+/// as soon as it does, such as the current track being played by a music player.
+///
+/// # Example
+/// ```no_run
+/// use penrose_ui::{bar::widgets::WatchText, core::TextStyle};
+///
+/// let style = TextStyle {
+///     fg: 0xebdbb2ff.into(),
+///     bg: Some(0x282828ff.into()),
+///     padding: (2, 2),
+/// };
+///
+/// // Show each line written to the fifo (created using `mkfifo /tmp/bar-fifo`)
+/// let my_widget = WatchText::from_fifo(style, "/tmp/bar-fifo");
+/// ```
+pub struct WatchText {
+    inner: Arc<Mutex<Text>>,
+    wait_for_text: Option<Box<dyn FnMut() -> Option<String> + Send + 'static>>,
+}
+
+impl fmt::Debug for WatchText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WatchText")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl WatchText {
+    /// Construct a new [`WatchText`] using the specified styling and a blocking function for
+    /// generating the widget contents. The function will be run in a loop on its own thread
+    /// until it returns `None`.
+    pub fn new<F>(style: TextStyle, wait_for_text: F) -> Self
+    where
+        F: FnMut() -> Option<String> + Send + 'static,
+    {
+        let inner = Arc::new(Mutex::new(Text::new("", style, false, false)));
+
+        Self {
+            inner,
+            wait_for_text: Some(Box::new(wait_for_text)),
+        }
+    }
+
+    /// Display each message received on the given channel.
+    pub fn from_receiver(style: TextStyle, rx: Receiver<String>) -> Self {
+        Self::new(style, move || rx.recv().ok())
+    }
+
+    /// Display each line read from the given reader, such as a socket or the read end of a pipe.
+    pub fn from_reader<R>(style: TextStyle, reader: R) -> Self
+    where
+        R: BufRead + Send + 'static,
+    {
+        let mut lines = reader.lines();
+
+        Self::new(style, move || lines.next()?.ok())
+    }
+
+    /// Display each line written to the named pipe at the given path.
+    ///
+    /// The pipe is reopened each time all writers have closed it so multiple programs are able
+    /// to update the widget over time. The pipe itself needs to be created before the status
+    /// bar is started, e.g. by running `mkfifo /tmp/bar-fifo`.
+    pub fn from_fifo(style: TextStyle, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mut lines = None;
+
+        Self::new(style, move || loop {
+            let ls = match &mut lines {
+                Some(ls) => ls,
+                // Opening a fifo for reading blocks until there is a writer
+                None => lines.insert(BufReader::new(File::open(&path).ok()?).lines()),
+            };
+
+            match ls.next() {
+                Some(Ok(line)) => return Some(line),
+                Some(Err(_)) | None => lines = None,
+            }
+        })
+    }
+
+    fn inner_guard(&self) -> MutexGuard<'_, Text> {
+        match self.inner.lock() {
+            Ok(inner) => inner,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl<X: XConn> Widget<X> for WatchText {
+    fn draw(&mut self, ctx: &mut Context<'_>, s: usize, f: bool, w: u32, h: u32) -> Result<()> {
+        Widget::<X>::draw(&mut *self.inner_guard(), ctx, s, f, w, h)
+    }
+
+    fn current_extent(&mut self, ctx: &mut Context<'_>, h: u32) -> Result<(u32, u32)> {
+        Widget::<X>::current_extent(&mut *self.inner_guard(), ctx, h)
+    }
+
+    fn is_greedy(&self) -> bool {
+        Widget::<X>::is_greedy(&*self.inner_guard())
+    }
+
+    fn require_draw(&self) -> bool {
+        Widget::<X>::require_draw(&*self.inner_guard())
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        Widget::<X>::set_theme(&mut *self.inner_guard(), theme)
+    }
+
+    fn watch(&mut self) -> Option<Watch> {
+        Some(Watch::new(self.wait_for_text.take()?, self.inner.clone()))
+    }
+}
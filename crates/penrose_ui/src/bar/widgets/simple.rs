@@ -5,12 +5,21 @@ use crate::{
     Result,
 };
 use penrose::{
-    core::State,
-    pure::geometry::Rect,
-    x::{event::PropertyEvent, Atom, XConn, XConnExt, XEvent},
+    core::{bindings::MouseButton, State},
+    extensions::{
+        actions::{restore_client, MinimizedClients},
+        util::theme::Theme,
+    },
+    pure::geometry::{Point, Rect},
+    x::{event::PropertyEvent, Atom, Prop, XConn, XConnExt, XEvent},
+    Xid,
 };
+use std::collections::HashMap;
 
 /// A text widget that is set via updating the root window name a la dwm
+///
+/// This allows for existing status scripts that make use of `xsetroot -name` (such as dwmblocks
+/// or slstatus) to be used to provide content for the penrose status bar.
 #[derive(Clone, Debug, PartialEq)]
 pub struct RootWindowName {
     inner: Text,
@@ -42,6 +51,20 @@ impl<X: XConn> Widget<X> for RootWindowName {
         Widget::<X>::require_draw(&self.inner)
     }
 
+    fn set_theme(&mut self, theme: &Theme) {
+        Widget::<X>::set_theme(&mut self.inner, theme)
+    }
+
+    // Pick up any name that was set before the window manager started
+    fn on_startup(&mut self, _: &mut State<X>, x: &X) -> Result<()> {
+        let name = x.window_title(x.root())?;
+        if !name.is_empty() {
+            self.inner.set_text(name);
+        }
+
+        Ok(())
+    }
+
     fn on_event(&mut self, event: &XEvent, _: &mut State<X>, x: &X) -> Result<()> {
         let name_props = [Atom::NetWmName.as_ref(), Atom::WmName.as_ref()];
 
@@ -59,6 +82,80 @@ impl<X: XConn> Widget<X> for RootWindowName {
     }
 }
 
+/// A text widget that displays the value of a string property set on the root window.
+///
+/// This is a generalisation of [RootWindowName] for external programs that publish their
+/// status under their own property name, for example by running
+/// `xprop -root -f _MY_STATUS 8u -set _MY_STATUS "hello"`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RootPropertyText {
+    inner: Text,
+    prop: String,
+}
+
+impl RootPropertyText {
+    /// Create a new RootPropertyText widget displaying the named property.
+    pub fn new(
+        prop: impl Into<String>,
+        style: TextStyle,
+        is_greedy: bool,
+        right_justified: bool,
+    ) -> Self {
+        Self {
+            inner: Text::new("", style, is_greedy, right_justified),
+            prop: prop.into(),
+        }
+    }
+
+    fn update<X: XConn>(&mut self, x: &X) -> Result<()> {
+        let txt = match x.get_prop(x.root(), &self.prop)? {
+            Some(Prop::UTF8String(strs)) => strs.into_iter().next().unwrap_or_default(),
+            _ => String::new(),
+        };
+        self.inner.set_text(txt);
+
+        Ok(())
+    }
+}
+
+impl<X: XConn> Widget<X> for RootPropertyText {
+    fn draw(&mut self, ctx: &mut Context<'_>, s: usize, f: bool, w: u32, h: u32) -> Result<()> {
+        Widget::<X>::draw(&mut self.inner, ctx, s, f, w, h)
+    }
+
+    fn current_extent(&mut self, ctx: &mut Context<'_>, h: u32) -> Result<(u32, u32)> {
+        Widget::<X>::current_extent(&mut self.inner, ctx, h)
+    }
+
+    fn is_greedy(&self) -> bool {
+        Widget::<X>::is_greedy(&self.inner)
+    }
+
+    fn require_draw(&self) -> bool {
+        Widget::<X>::require_draw(&self.inner)
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        Widget::<X>::set_theme(&mut self.inner, theme)
+    }
+
+    fn on_startup(&mut self, _: &mut State<X>, x: &X) -> Result<()> {
+        self.update(x)
+    }
+
+    fn on_event(&mut self, event: &XEvent, _: &mut State<X>, x: &X) -> Result<()> {
+        match event {
+            XEvent::PropertyNotify(PropertyEvent { atom, is_root, .. })
+                if *is_root && *atom == self.prop =>
+            {
+                self.update(x)
+            }
+
+            _ => Ok(()),
+        }
+    }
+}
+
 /// A text widget that shows the name of the currently focused window
 #[derive(Clone, Debug, PartialEq)]
 pub struct ActiveWindowName {
@@ -108,6 +205,10 @@ impl<X: XConn> Widget<X> for ActiveWindowName {
         Widget::<X>::require_draw(&self.inner)
     }
 
+    fn set_theme(&mut self, theme: &Theme) {
+        Widget::<X>::set_theme(&mut self.inner, theme)
+    }
+
     fn on_refresh(&mut self, state: &mut State<X>, x: &X) -> Result<()> {
         if let Some(id) = state.client_set.current_client() {
             self.set_text(&x.window_title(*id)?)
@@ -137,10 +238,157 @@ impl<X: XConn> Widget<X> for ActiveWindowName {
     }
 }
 
+const ELLIPSIS: &str = "…";
+
+/// A text widget that shows the title of the currently focused window, truncated with an
+/// ellipsis so that it renders within a maximum width in pixels.
+///
+/// The title is taken from `_NET_WM_NAME`, falling back to `WM_NAME` for clients that do not
+/// set it, and is updated whenever the focused client changes its title.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FocusedTitle {
+    inner: Text,
+    title: String,
+    max_width: u32,
+    require_fit: bool,
+}
+
+impl FocusedTitle {
+    /// Create a new FocusedTitle widget that will render at most `max_width` pixels of text
+    /// (not including padding).
+    pub fn new(max_width: u32, style: TextStyle, is_greedy: bool, right_justified: bool) -> Self {
+        Self {
+            inner: Text::new("", style, is_greedy, right_justified),
+            title: String::new(),
+            max_width,
+            require_fit: false,
+        }
+    }
+
+    fn set_title(&mut self, title: String) {
+        if self.title != title {
+            self.title = title;
+            self.require_fit = true;
+        }
+    }
+
+    fn update_title<X: XConn>(&mut self, id: Xid, x: &X) -> Result<()> {
+        let title = match x.get_prop(id, Atom::NetWmName.as_ref())? {
+            Some(Prop::UTF8String(mut strs)) if !strs.is_empty() => strs.remove(0),
+            _ => x.window_title(id)?,
+        };
+        self.set_title(title);
+
+        Ok(())
+    }
+}
+
+/// Truncate `txt` so that it fits within `max_width` pixels when rendered, marking the point
+/// of truncation with an ellipsis.
+fn truncate_to_width(ctx: &mut Context<'_>, txt: &str, max_width: u32) -> Result<String> {
+    truncate_with(txt, max_width, |s| Ok(ctx.text_extent(s)?.0))
+}
+
+// The truncation logic is separate from the rendering context so that it can be tested using
+// a fixed width for each character.
+fn truncate_with<F>(txt: &str, max_width: u32, mut width: F) -> Result<String>
+where
+    F: FnMut(&str) -> Result<u32>,
+{
+    if width(txt)? <= max_width {
+        return Ok(txt.to_string());
+    }
+
+    // Binary search for the longest prefix that fits along with the ellipsis
+    let chars: Vec<char> = txt.chars().collect();
+    let with_ellipsis = |n: usize| {
+        let mut s: String = chars[..n].iter().collect();
+        s.push_str(ELLIPSIS);
+        s
+    };
+
+    let (mut lo, mut hi) = (0, chars.len());
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+        if width(&with_ellipsis(mid))? <= max_width {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+
+    if lo == 0 && width(ELLIPSIS)? > max_width {
+        return Ok(String::new());
+    }
+
+    Ok(with_ellipsis(lo))
+}
+
+impl<X: XConn> Widget<X> for FocusedTitle {
+    fn draw(&mut self, ctx: &mut Context<'_>, s: usize, f: bool, w: u32, h: u32) -> Result<()> {
+        if f {
+            Widget::<X>::draw(&mut self.inner, ctx, s, f, w, h)
+        } else {
+            ctx.fill_bg(Rect::new(0, 0, w, h))
+        }
+    }
+
+    fn current_extent(&mut self, ctx: &mut Context<'_>, h: u32) -> Result<(u32, u32)> {
+        if self.require_fit {
+            let txt = truncate_to_width(ctx, &self.title, self.max_width)?;
+            self.inner.set_text(txt);
+            self.require_fit = false;
+        }
+
+        Widget::<X>::current_extent(&mut self.inner, ctx, h)
+    }
+
+    fn is_greedy(&self) -> bool {
+        Widget::<X>::is_greedy(&self.inner)
+    }
+
+    fn require_draw(&self) -> bool {
+        self.require_fit || Widget::<X>::require_draw(&self.inner)
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        Widget::<X>::set_theme(&mut self.inner, theme)
+    }
+
+    fn on_refresh(&mut self, state: &mut State<X>, x: &X) -> Result<()> {
+        match state.client_set.current_client() {
+            Some(&id) => self.update_title(id, x),
+            None => {
+                self.set_title(String::new());
+                Ok(())
+            }
+        }
+    }
+
+    fn on_event(&mut self, event: &XEvent, state: &mut State<X>, x: &X) -> Result<()> {
+        let name_props = [Atom::NetWmName.as_ref(), Atom::WmName.as_ref()];
+
+        match (event, state.client_set.current_client()) {
+            (XEvent::PropertyNotify(PropertyEvent { id, atom, .. }), Some(focused))
+                if id == focused && name_props.contains(&atom.as_ref()) =>
+            {
+                self.update_title(*id, x)
+            }
+
+            _ => Ok(()),
+        }
+    }
+}
+
 /// A text widget that shows the current layout name
+///
+/// Clicking on the widget will cycle the layout of the workspace on the screen that was clicked:
+/// left click and scrolling up move to the next layout while right click and scrolling down
+/// move to the previous one.
 #[derive(Clone, Debug, PartialEq)]
 pub struct CurrentLayout {
     inner: Text,
+    symbols: HashMap<String, String>,
 }
 
 impl CurrentLayout {
@@ -148,6 +396,27 @@ impl CurrentLayout {
     pub fn new(style: TextStyle) -> Self {
         Self {
             inner: Text::new("", style, false, false),
+            symbols: HashMap::new(),
+        }
+    }
+
+    /// Display `symbol` in place of the name of the layout named `layout_name`.
+    ///
+    /// Layouts without a symbol are displayed as their name wrapped in square brackets.
+    pub fn with_symbol(
+        mut self,
+        layout_name: impl Into<String>,
+        symbol: impl Into<String>,
+    ) -> Self {
+        self.symbols.insert(layout_name.into(), symbol.into());
+
+        self
+    }
+
+    fn display_name(&self, layout_name: String) -> String {
+        match self.symbols.get(&layout_name) {
+            Some(symbol) => symbol.clone(),
+            None => format!("[{layout_name}]"),
         }
     }
 }
@@ -169,10 +438,271 @@ impl<X: XConn> Widget<X> for CurrentLayout {
         Widget::<X>::require_draw(&self.inner)
     }
 
+    fn set_theme(&mut self, theme: &Theme) {
+        Widget::<X>::set_theme(&mut self.inner, theme)
+    }
+
     fn on_refresh(&mut self, state: &mut State<X>, _: &X) -> Result<()> {
         let layout_name = state.client_set.current_workspace().layout_name();
-        self.inner.set_text(format!("[{layout_name}]"));
+        let txt = self.display_name(layout_name);
+        self.inner.set_text(txt);
+
+        Ok(())
+    }
+
+    fn clickable_regions(&self, w: u32, h: u32) -> Vec<Rect> {
+        vec![Rect::new(0, 0, w, h)]
+    }
+
+    fn on_click(
+        &mut self,
+        button: MouseButton,
+        _: Point,
+        screen: usize,
+        state: &mut State<X>,
+        _: &X,
+    ) -> Result<()> {
+        let ws = match state.client_set.screens_mut().find(|s| s.index() == screen) {
+            Some(s) => &mut s.workspace,
+            None => return Ok(()),
+        };
+
+        match button {
+            MouseButton::Left | MouseButton::ScrollUp => ws.next_layout(),
+            MouseButton::Right | MouseButton::ScrollDown => ws.previous_layout(),
+            MouseButton::Middle => (),
+        }
+
+        Ok(())
+    }
+}
+
+/// A text widget that shows the currently active keyboard layout (XKB group).
+///
+/// Layouts are displayed using the name reported by the X server (e.g. "English (US)") unless a
+/// label has been provided for them using [KeyboardLayout::with_label]. Clicking on the widget
+/// will cycle through the configured layouts: left click and scrolling up move to the next
+/// layout while right click and scrolling down move to the previous one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyboardLayout {
+    inner: Text,
+    labels: HashMap<String, String>,
+    layouts: Vec<String>,
+    current: u8,
+}
+
+impl KeyboardLayout {
+    /// Create a new KeyboardLayout widget
+    pub fn new(style: TextStyle) -> Self {
+        Self {
+            inner: Text::new("", style, false, false),
+            labels: HashMap::new(),
+            layouts: Vec::new(),
+            current: 0,
+        }
+    }
+
+    /// Display `label` in place of the name of the layout named `layout_name`.
+    pub fn with_label(mut self, layout_name: impl Into<String>, label: impl Into<String>) -> Self {
+        self.labels.insert(layout_name.into(), label.into());
+
+        self
+    }
+
+    fn update<X: XConn>(&mut self, current: u8, x: &X) -> Result<()> {
+        self.layouts = x.keyboard_layouts()?;
+        self.current = current;
+
+        let txt = match self.layouts.get(current as usize) {
+            Some(name) => self.labels.get(name).unwrap_or(name).clone(),
+            None => current.to_string(),
+        };
+        self.inner.set_text(txt);
+
+        Ok(())
+    }
+}
+
+impl<X: XConn> Widget<X> for KeyboardLayout {
+    fn draw(&mut self, ctx: &mut Context<'_>, s: usize, f: bool, w: u32, h: u32) -> Result<()> {
+        Widget::<X>::draw(&mut self.inner, ctx, s, f, w, h)
+    }
+
+    fn current_extent(&mut self, ctx: &mut Context<'_>, h: u32) -> Result<(u32, u32)> {
+        Widget::<X>::current_extent(&mut self.inner, ctx, h)
+    }
+
+    fn is_greedy(&self) -> bool {
+        Widget::<X>::is_greedy(&self.inner)
+    }
+
+    fn require_draw(&self) -> bool {
+        Widget::<X>::require_draw(&self.inner)
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        Widget::<X>::set_theme(&mut self.inner, theme)
+    }
+
+    fn on_startup(&mut self, _: &mut State<X>, x: &X) -> Result<()> {
+        self.update(x.keyboard_layout()?, x)
+    }
+
+    fn on_event(&mut self, event: &XEvent, _: &mut State<X>, x: &X) -> Result<()> {
+        match event {
+            XEvent::KeyboardLayoutChange(current) => self.update(*current, x),
+            _ => Ok(()),
+        }
+    }
+
+    fn clickable_regions(&self, w: u32, h: u32) -> Vec<Rect> {
+        vec![Rect::new(0, 0, w, h)]
+    }
+
+    fn on_click(
+        &mut self,
+        button: MouseButton,
+        _: Point,
+        _: usize,
+        _: &mut State<X>,
+        x: &X,
+    ) -> Result<()> {
+        let n = self.layouts.len();
+        if n == 0 {
+            return Ok(());
+        }
+        let current = self.current as usize;
+
+        let next = match button {
+            MouseButton::Left | MouseButton::ScrollUp => (current + 1) % n,
+            MouseButton::Right | MouseButton::ScrollDown => (current + n - 1) % n,
+            MouseButton::Middle => return Ok(()),
+        };
+
+        Ok(x.set_keyboard_layout(next as u8)?)
+    }
+}
+
+/// A text widget that lists the titles of minimized windows.
+///
+/// Requires [MinimizedClients] to have been added to the window manager: nothing is shown
+/// otherwise. Titles are listed from least to most recently minimized and left clicking on the
+/// widget will restore the most recently minimized window.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MinimizedWindows {
+    inner: Text,
+    max_chars: usize,
+}
+
+impl MinimizedWindows {
+    /// Create a new MinimizedWindows widget with a maximum character count for each title.
+    ///
+    /// max_chars can not be lower than 3.
+    pub fn new(max_chars: usize, style: TextStyle, is_greedy: bool, right_justified: bool) -> Self {
+        Self {
+            inner: Text::new("", style, is_greedy, right_justified),
+            max_chars: max_chars.max(3),
+        }
+    }
+
+    fn truncated(&self, title: String) -> String {
+        if title.chars().count() <= self.max_chars {
+            title
+        } else {
+            let s: String = title.chars().take(self.max_chars - 3).collect();
+            format!("{}...", s)
+        }
+    }
+}
+
+impl<X: XConn + 'static> Widget<X> for MinimizedWindows {
+    fn draw(&mut self, ctx: &mut Context<'_>, s: usize, f: bool, w: u32, h: u32) -> Result<()> {
+        Widget::<X>::draw(&mut self.inner, ctx, s, f, w, h)
+    }
+
+    fn current_extent(&mut self, ctx: &mut Context<'_>, h: u32) -> Result<(u32, u32)> {
+        Widget::<X>::current_extent(&mut self.inner, ctx, h)
+    }
+
+    fn is_greedy(&self) -> bool {
+        Widget::<X>::is_greedy(&self.inner)
+    }
+
+    fn require_draw(&self) -> bool {
+        Widget::<X>::require_draw(&self.inner)
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        Widget::<X>::set_theme(&mut self.inner, theme)
+    }
+
+    fn on_refresh(&mut self, state: &mut State<X>, x: &X) -> Result<()> {
+        let ids: Vec<Xid> = match state.extension::<MinimizedClients>() {
+            Ok(s) => s.borrow().clients().collect(),
+            Err(_) => vec![],
+        };
+
+        let titles: Vec<String> = ids
+            .into_iter()
+            .map(|id| self.truncated(x.window_title(id).unwrap_or_default()))
+            .collect();
+        self.inner.set_text(titles.join(" | "));
 
         Ok(())
     }
+
+    fn clickable_regions(&self, w: u32, h: u32) -> Vec<Rect> {
+        vec![Rect::new(0, 0, w, h)]
+    }
+
+    fn on_click(
+        &mut self,
+        button: MouseButton,
+        _: Point,
+        _: usize,
+        state: &mut State<X>,
+        x: &X,
+    ) -> Result<()> {
+        if button != MouseButton::Left {
+            return Ok(());
+        }
+
+        let last = match state.extension::<MinimizedClients>() {
+            Ok(s) => s.borrow().clients().last(),
+            Err(_) => return Ok(()),
+        };
+
+        match last {
+            Some(id) => Ok(restore_client(id, state, x)?),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    // Each character is rendered 2px wide apart from the ellipsis which is 3px wide
+    fn width(s: &str) -> Result<u32> {
+        Ok(s.chars().map(|c| if c == '…' { 3 } else { 2 }).sum())
+    }
+
+    #[test_case("hello", 10, "hello"; "fits exactly")]
+    #[test_case("hello", 100, "hello"; "fits with room to spare")]
+    #[test_case("hello world", 10, "hel…"; "truncated")]
+    #[test_case("héllo wörld", 10, "hél…"; "multibyte characters")]
+    #[test_case("日本語のテキスト", 8, "日本…"; "non-latin characters")]
+    #[test_case("hello", 4, "…"; "only the ellipsis fits")]
+    #[test_case("hello", 2, ""; "ellipsis wider than max width")]
+    #[test_case("hello", 0, ""; "zero width")]
+    #[test_case("", 0, ""; "empty text with zero width")]
+    #[test]
+    fn truncate_with_works(txt: &str, max_width: u32, expected: &str) {
+        let truncated = truncate_with(txt, max_width, width).unwrap();
+
+        assert_eq!(truncated, expected);
+        assert!(width(&truncated).unwrap() <= max_width);
+    }
 }
@@ -1,4 +1,48 @@
 //! System monitor widgets and utility functions
+//!
+//! The widgets in this module are styled using a [TextStyle] and poll for their content on the
+//! interval provided when they are constructed.
+//!
+//! # Example
+//! ```no_run
+//! use penrose_ui::{
+//!     bar::widgets::sys::{Battery, Clock, Cpu},
+//!     core::TextStyle,
+//! };
+//! use std::time::Duration;
+//!
+//! let style = TextStyle {
+//!     fg: 0xebdbb2ff.into(),
+//!     bg: Some(0x282828ff.into()),
+//!     padding: (2, 2),
+//! };
+//!
+//! let battery = Battery::new("BAT0", style, Duration::from_secs(30));
+//! let clock = Clock::new("%H:%M", style, Duration::from_secs(10));
+//! let cpu = Cpu::new(style, Duration::from_secs(2));
+//! ```
+use crate::{
+    bar::{
+        schedule::UpdateSchedule,
+        widgets::{IntervalText, Text, Widget},
+    },
+    core::{Context, TextStyle},
+    Result,
+};
+use helpers::BatteryStatus;
+use penrose::{
+    core::{bindings::MouseButton, State},
+    extensions::util::theme::Theme,
+    pure::geometry::{Point, Rect},
+    util::spawn,
+    x::XConn,
+    Color,
+};
+use std::{
+    fmt,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
 
 /// Helper functions for obtaining system information for use in status bar widgets
 pub mod helpers {
@@ -42,19 +86,64 @@ pub mod helpers {
             .next()
     }
 
+    /// The charge level and status of a battery as reported by sysfs.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct BatteryStatus {
+        /// The current charge as a percentage of the battery's capacity
+        pub charge: u32,
+        /// The status reported for the battery (e.g. "Charging", "Discharging" or "Full")
+        pub status: String,
+    }
+
+    impl BatteryStatus {
+        /// Whether or not the battery is currently charging.
+        pub fn is_charging(&self) -> bool {
+            self.status == "Charging"
+        }
+    }
+
+    /// Fetch the requested battery's charge as a percentage of its total along with its current
+    /// status.
+    ///
+    /// The charge is read from `capacity` if it is available, falling back to computing it from
+    /// the `charge_*` or `energy_*` files depending on what the battery driver provides. This
+    /// will return `None` if it is unable to read or parse the required system files for the
+    /// requested battery.
+    pub fn battery_status(bat: &str) -> Option<BatteryStatus> {
+        let status = read_sys_file(bat, "status")?;
+
+        let charge = match read_sys_file(bat, "capacity").and_then(|s| s.parse().ok()) {
+            Some(charge) => charge,
+            None => ["charge", "energy"].iter().find_map(|prefix| {
+                let now: u64 = read_sys_file(bat, &format!("{prefix}_now"))?.parse().ok()?;
+                let full: u64 = read_sys_file(bat, &format!("{prefix}_full"))?
+                    .parse()
+                    .ok()?;
+
+                (full > 0).then(|| (now * 100 / full) as u32)
+            })?,
+        };
+
+        Some(BatteryStatus {
+            charge: charge.min(100),
+            status,
+        })
+    }
+
     /// Fetch the requested battery's charge as a percentage of its total along with an indicator
     /// of whether it is charging or discharging.
     ///
     /// This will return `None` if it is unable to read or parse the required system files for the
     /// requested battery.
     pub fn battery_text(bat: &str) -> Option<String> {
-        let status = read_sys_file(bat, "status")?;
-        let energy_now: u32 = read_sys_file(bat, "charge_now")?.parse().ok()?;
-        let energy_full: u32 = read_sys_file(bat, "charge_full")?.parse().ok()?;
+        battery_status(bat).map(|s| format_battery_status(&s))
+    }
 
-        let charge = energy_now * 100 / energy_full;
+    pub(super) fn format_battery_status(s: &BatteryStatus) -> String {
+        let BatteryStatus { charge, status } = s;
+        let charge = *charge;
 
-        let icon = if status == "Charging" {
+        let icon = if s.is_charging() {
             ""
         } else if charge >= 90 || status == "Full" {
             ""
@@ -68,7 +157,7 @@ pub mod helpers {
             ""
         };
 
-        Some(format!("{icon} {charge}%"))
+        format!("{icon} {charge}%")
     }
 
     fn read_sys_file(bat: &str, fname: &str) -> Option<String> {
@@ -82,8 +171,16 @@ pub mod helpers {
     ///
     /// Will return `None` if there are errors in calling `date`.
     pub fn date_text() -> Option<String> {
+        formatted_date_text("%F %R")
+    }
+
+    /// Fetch the current date and time using the provided strftime style format string via the
+    /// `date` command line program.
+    ///
+    /// Will return `None` if there are errors in calling `date`.
+    pub fn formatted_date_text(format: &str) -> Option<String> {
         Some(
-            spawn_for_output_with_args("date", &["+%F %R"])
+            spawn_for_output_with_args("date", &[&format!("+{format}")])
                 .ok()?
                 .trim()
                 .to_string(),
@@ -114,6 +211,41 @@ pub mod helpers {
         Some((interface, essid))
     }
 
+    /// Whether or not the given network interface is currently up, as reported by its
+    /// `operstate` in sysfs.
+    pub fn interface_is_up(interface: &str) -> bool {
+        fs::read_to_string(format!("/sys/class/net/{interface}/operstate"))
+            .map(|s| s.trim() == "up")
+            .unwrap_or(false)
+    }
+
+    /// Fetch the state of a given network interface, including the ESSID and signal quality if
+    /// it is a connected wireless interface.
+    ///
+    /// Wired interfaces are shown as simply being up or down. Makes use of the `iwgetid` command
+    /// line program for determining the ESSID of wireless interfaces and will return `None` if
+    /// the interface does not exist.
+    pub fn network_text(interface: &str) -> Option<String> {
+        if !PathBuf::from(format!("/sys/class/net/{interface}")).exists() {
+            return None;
+        }
+
+        if !interface_is_up(interface) {
+            return Some(format!("<{interface} down>"));
+        }
+
+        let essid = spawn_for_output_with_args("iwgetid", &["-r", interface])
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+
+        match (essid, signal_quality(interface)) {
+            (Some(essid), Some(signal)) => Some(format!("<{essid} {signal}%>")),
+            (Some(essid), None) => Some(format!("<{essid}>")),
+            _ => Some(format!("<{interface} up>")),
+        }
+    }
+
     // Parsing the format described here: https://hewlettpackard.github.io/wireless-tools/Linux.Wireless.Extensions.html
     fn signal_quality(interface: &str) -> Option<String> {
         let raw = fs::read_to_string("/proc/net/wireless").ok()?;
@@ -132,6 +264,48 @@ pub mod helpers {
         None
     }
 
+    /// Read the cumulative idle and total CPU time across all CPUs from `/proc/stat`.
+    ///
+    /// Idle time includes time spent waiting on IO. Will return `None` if `/proc/stat` can not
+    /// be read or parsed.
+    pub fn cpu_times() -> Option<(u64, u64)> {
+        let raw = fs::read_to_string("/proc/stat").ok()?;
+        let line = raw.lines().find(|l| l.starts_with("cpu "))?;
+        let fields: Vec<u64> = line
+            .split_whitespace()
+            .skip(1)
+            .map(|s| s.parse().ok())
+            .collect::<Option<_>>()?;
+
+        let idle = fields.get(3)? + fields.get(4).unwrap_or(&0);
+        let total = fields.iter().sum();
+
+        Some((idle, total))
+    }
+
+    /// The percentage of total memory currently in use as reported by `/proc/meminfo`.
+    ///
+    /// Will return `None` if `/proc/meminfo` can not be read or parsed.
+    pub fn memory_usage() -> Option<u32> {
+        let raw = fs::read_to_string("/proc/meminfo").ok()?;
+        let field = |name: &str| -> Option<u64> {
+            raw.lines()
+                .find(|l| l.starts_with(name))?
+                .split_whitespace()
+                .nth(1)?
+                .parse()
+                .ok()
+        };
+
+        let total = field("MemTotal:")?;
+        let available = field("MemAvailable:")?;
+        if total == 0 {
+            return None;
+        }
+
+        Some((100 * total.saturating_sub(available) / total) as u32)
+    }
+
     /// Parse the current volume as a percentage from amixer.
     ///
     /// Expected output format:
@@ -142,16 +316,36 @@ pub mod helpers {
     ///       Limits: Playback 0 - 127
     ///       Mono: Playback 0 [0%] [-63.50dB] [on]
     pub fn amixer_text(channel: &str) -> Option<String> {
+        let (vol, _) = amixer_status(channel)?;
+
+        Some(format!(" {vol}%"))
+    }
+
+    /// Parse the current volume as a percentage from amixer along with whether or not the
+    /// channel is currently muted.
+    ///
+    /// See [amixer_text] for details of the expected output format.
+    pub fn amixer_status(channel: &str) -> Option<(u32, bool)> {
         let raw = spawn_for_output(format!("amixer sget {channel}")).ok()?;
+        let line = raw.lines().last()?;
 
-        let vol = raw
-            .lines()
-            .last()?
+        let vol = line
             .split_whitespace()
             .find(|s| s.ends_with("%]"))?
-            .replace(|c| "[]%".contains(c), "");
+            .replace(|c| "[]%".contains(c), "")
+            .parse()
+            .ok()?;
+        let muted = line.split_whitespace().any(|s| s == "[off]");
 
-        Some(format!(" {vol}%"))
+        Some((vol, muted))
+    }
+
+    /// Format the current volume for a given amixer channel, indicating if it is muted.
+    pub fn amixer_volume_text(channel: &str) -> Option<String> {
+        match amixer_status(channel)? {
+            (vol, false) => Some(format!(" {vol}%")),
+            (vol, true) => Some(format!(" {vol}%")),
+        }
     }
 }
 
@@ -235,3 +429,701 @@ pub mod interval {
         IntervalText::new(style, move || helpers::amixer_text(channel), interval)
     }
 }
+
+// Implement [Widget] for a type by deferring drawing and theming to the widget held in `$field`,
+// along with any additional methods that are provided.
+macro_rules! delegate_widget {
+    ($ty:ty => $field:ident { $($method:item)* }) => {
+        impl<X: XConn> Widget<X> for $ty {
+            fn draw(
+                &mut self,
+                ctx: &mut Context<'_>,
+                s: usize,
+                f: bool,
+                w: u32,
+                h: u32,
+            ) -> Result<()> {
+                Widget::<X>::draw(&mut self.$field, ctx, s, f, w, h)
+            }
+
+            fn current_extent(&mut self, ctx: &mut Context<'_>, h: u32) -> Result<(u32, u32)> {
+                Widget::<X>::current_extent(&mut self.$field, ctx, h)
+            }
+
+            fn is_greedy(&self) -> bool {
+                Widget::<X>::is_greedy(&self.$field)
+            }
+
+            fn require_draw(&self) -> bool {
+                Widget::<X>::require_draw(&self.$field)
+            }
+
+            fn set_theme(&mut self, theme: &Theme) {
+                Widget::<X>::set_theme(&mut self.$field, theme)
+            }
+
+            $($method)*
+        }
+    };
+}
+
+// A text widget that is shared with an update schedule so that its content can be modified from
+// another thread.
+#[derive(Debug)]
+struct SharedText(Arc<Mutex<Text>>);
+
+impl SharedText {
+    fn new(style: TextStyle) -> Self {
+        Self(Arc::new(Mutex::new(Text::new("", style, false, false))))
+    }
+
+    fn guard(&self) -> MutexGuard<'_, Text> {
+        match self.0.lock() {
+            Ok(inner) => inner,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl<X: XConn> Widget<X> for SharedText {
+    fn draw(&mut self, ctx: &mut Context<'_>, s: usize, f: bool, w: u32, h: u32) -> Result<()> {
+        Widget::<X>::draw(&mut *self.guard(), ctx, s, f, w, h)
+    }
+
+    fn current_extent(&mut self, ctx: &mut Context<'_>, h: u32) -> Result<(u32, u32)> {
+        Widget::<X>::current_extent(&mut *self.guard(), ctx, h)
+    }
+
+    fn is_greedy(&self) -> bool {
+        Widget::<X>::is_greedy(&*self.guard())
+    }
+
+    fn require_draw(&self) -> bool {
+        Widget::<X>::require_draw(&*self.guard())
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        Widget::<X>::set_theme(&mut *self.guard(), theme)
+    }
+}
+
+/// A battery status widget that polls sysfs on an interval, showing the current charge and
+/// whether or not the battery is charging.
+///
+/// Warning thresholds can be added using [Battery::with_warning] in order to change the color of
+/// the text when the battery is discharging and its charge drops to or below a given level.
+///
+/// If the given battery is not found on this system, this widget will render as an empty string.
+///
+/// # Example
+/// ```no_run
+/// use penrose_ui::{bar::widgets::sys::Battery, core::TextStyle};
+/// use std::time::Duration;
+///
+/// # let style = TextStyle {
+/// #     fg: 0xebdbb2ff.into(),
+/// #     bg: Some(0x282828ff.into()),
+/// #     padding: (2, 2),
+/// # };
+/// let battery = Battery::new("BAT0", style, Duration::from_secs(30))
+///     .with_warning(20, 0xd79921ff)
+///     .with_warning(10, 0xcc241dff);
+/// ```
+pub struct Battery {
+    bat: String,
+    fg: Color,
+    thresholds: Vec<(u32, Color)>,
+    inner: SharedText,
+    interval: Duration,
+}
+
+impl fmt::Debug for Battery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Battery")
+            .field("bat", &self.bat)
+            .field("thresholds", &self.thresholds)
+            .field("inner", &self.inner)
+            .field("interval", &self.interval)
+            .finish()
+    }
+}
+
+impl Battery {
+    /// Construct a new [Battery] widget for the named battery (e.g. "BAT0") that will be
+    /// updated on the interval provided.
+    pub fn new(bat: impl Into<String>, style: TextStyle, interval: Duration) -> Self {
+        Self {
+            bat: bat.into(),
+            fg: style.fg,
+            thresholds: Vec::new(),
+            inner: SharedText::new(style),
+            interval,
+        }
+    }
+
+    /// Render the text of this widget using `color` while the battery is discharging and its
+    /// charge is at or below `charge` percent.
+    ///
+    /// When multiple thresholds apply, the one with the lowest charge level is used.
+    pub fn with_warning(mut self, charge: u32, color: impl Into<Color>) -> Self {
+        self.thresholds.push((charge, color.into()));
+        self.thresholds.sort_by_key(|&(charge, _)| charge);
+
+        self
+    }
+}
+
+fn battery_color(status: &BatteryStatus, fg: Color, thresholds: &[(u32, Color)]) -> Color {
+    if status.is_charging() {
+        return fg;
+    }
+
+    thresholds
+        .iter()
+        .find(|&&(charge, _)| status.charge <= charge)
+        .map(|&(_, color)| color)
+        .unwrap_or(fg)
+}
+
+delegate_widget!(Battery => inner {
+    fn update_schedule(&mut self) -> Option<UpdateSchedule> {
+        let bat = self.bat.clone();
+        let fg = self.fg;
+        let thresholds = self.thresholds.clone();
+        let inner = self.inner.0.clone();
+
+        // The color is set here before the schedule itself locks the text to update its content
+        let get_text = move || {
+            let status = helpers::battery_status(&bat);
+            let color = match &status {
+                Some(s) => battery_color(s, fg, &thresholds),
+                None => fg,
+            };
+
+            match inner.lock() {
+                Ok(mut t) => t.set_fg(color),
+                Err(poisoned) => poisoned.into_inner().set_fg(color),
+            }
+
+            Some(
+                status
+                    .map(|s| helpers::format_battery_status(&s))
+                    .unwrap_or_default(),
+            )
+        };
+
+        Some(UpdateSchedule::new(
+            self.interval,
+            Box::new(get_text),
+            self.inner.0.clone(),
+        ))
+    }
+});
+
+/// A clock widget displaying the current date and/or time using a strftime style format string,
+/// updated on the interval provided.
+///
+/// The formatting is handled by the `date` command line program so any format supported by your
+/// local version of `date` may be used. The widget will be resized as needed if the rendered width
+/// of the text changes between updates.
+///
+/// # Example
+/// ```no_run
+/// use penrose_ui::{bar::widgets::sys::Clock, core::TextStyle};
+/// use std::time::Duration;
+///
+/// # let style = TextStyle {
+/// #     fg: 0xebdbb2ff.into(),
+/// #     bg: Some(0x282828ff.into()),
+/// #     padding: (2, 2),
+/// # };
+/// let clock = Clock::new("%a %d %b %H:%M", style, Duration::from_secs(10));
+/// ```
+#[derive(Debug)]
+pub struct Clock {
+    inner: IntervalText,
+}
+
+impl Clock {
+    /// Construct a new [Clock] using the given strftime style format string.
+    pub fn new(format: impl Into<String>, style: TextStyle, interval: Duration) -> Self {
+        let format = format.into();
+
+        Self {
+            inner: IntervalText::new(
+                style,
+                move || helpers::formatted_date_text(&format),
+                interval,
+            ),
+        }
+    }
+}
+
+delegate_widget!(Clock => inner {
+    fn update_schedule(&mut self) -> Option<UpdateSchedule> {
+        Widget::<X>::update_schedule(&mut self.inner)
+    }
+});
+
+/// A network status widget showing whether or not an interface is up, along with the connected
+/// ESSID and signal quality for wireless interfaces, updated on the interval provided.
+///
+/// A command (such as a network manager UI) can be run when the widget is clicked by using
+/// [Network::with_click_command].
+///
+/// # Example
+/// ```no_run
+/// use penrose_ui::{bar::widgets::sys::Network, core::TextStyle};
+/// use std::time::Duration;
+///
+/// # let style = TextStyle {
+/// #     fg: 0xebdbb2ff.into(),
+/// #     bg: Some(0x282828ff.into()),
+/// #     padding: (2, 2),
+/// # };
+/// let wifi = Network::new("wlan0", style, Duration::from_secs(10))
+///     .with_click_command("nm-connection-editor");
+/// ```
+#[derive(Debug)]
+pub struct Network {
+    inner: IntervalText,
+    click_command: Option<String>,
+}
+
+impl Network {
+    /// Construct a new [Network] widget for the named interface.
+    pub fn new(interface: impl Into<String>, style: TextStyle, interval: Duration) -> Self {
+        let interface = interface.into();
+
+        Self {
+            inner: IntervalText::new(
+                style,
+                move || Some(helpers::network_text(&interface).unwrap_or_default()),
+                interval,
+            ),
+            click_command: None,
+        }
+    }
+
+    /// Spawn the given command when this widget is left clicked.
+    pub fn with_click_command(mut self, cmd: impl Into<String>) -> Self {
+        self.click_command = Some(cmd.into());
+
+        self
+    }
+}
+
+delegate_widget!(Network => inner {
+    fn update_schedule(&mut self) -> Option<UpdateSchedule> {
+        Widget::<X>::update_schedule(&mut self.inner)
+    }
+
+    fn clickable_regions(&self, w: u32, h: u32) -> Vec<Rect> {
+        match self.click_command {
+            Some(_) => vec![Rect::new(0, 0, w, h)],
+            None => Vec::new(),
+        }
+    }
+
+    fn on_click(
+        &mut self,
+        button: MouseButton,
+        _: Point,
+        _: usize,
+        _: &mut State<X>,
+        _: &X,
+    ) -> Result<()> {
+        match (button, &self.click_command) {
+            (MouseButton::Left, Some(cmd)) => Ok(spawn(cmd.as_str())?),
+            _ => Ok(()),
+        }
+    }
+});
+
+/// A volume widget showing the current volume level and mute state of an amixer channel, updated
+/// on the interval provided.
+///
+/// Left clicking on the widget toggles mute while scrolling up and down raises and lowers the
+/// volume. By default these actions are performed using `amixer` but the commands used can be
+/// replaced using [Volume::with_commands] (for example to use `pactl` or `wpctl` instead).
+///
+/// # Example
+/// ```no_run
+/// use penrose_ui::{bar::widgets::sys::Volume, core::TextStyle};
+/// use std::time::Duration;
+///
+/// # let style = TextStyle {
+/// #     fg: 0xebdbb2ff.into(),
+/// #     bg: Some(0x282828ff.into()),
+/// #     padding: (2, 2),
+/// # };
+/// let volume = Volume::new("Master", style, Duration::from_secs(5));
+/// ```
+#[derive(Debug)]
+pub struct Volume {
+    inner: IntervalText,
+    toggle_mute: String,
+    raise: String,
+    lower: String,
+}
+
+impl Volume {
+    /// Construct a new [Volume] widget for the given amixer channel.
+    pub fn new(channel: impl Into<String>, style: TextStyle, interval: Duration) -> Self {
+        let channel = channel.into();
+
+        Self {
+            toggle_mute: format!("amixer -q sset {channel} toggle"),
+            raise: format!("amixer -q sset {channel} 5%+"),
+            lower: format!("amixer -q sset {channel} 5%-"),
+            inner: IntervalText::new(
+                style,
+                move || helpers::amixer_volume_text(&channel),
+                interval,
+            ),
+        }
+    }
+
+    /// Set the commands run in order to toggle mute, raise the volume and lower the volume
+    /// in response to mouse events.
+    pub fn with_commands(
+        mut self,
+        toggle_mute: impl Into<String>,
+        raise: impl Into<String>,
+        lower: impl Into<String>,
+    ) -> Self {
+        self.toggle_mute = toggle_mute.into();
+        self.raise = raise.into();
+        self.lower = lower.into();
+
+        self
+    }
+}
+
+delegate_widget!(Volume => inner {
+    fn update_schedule(&mut self) -> Option<UpdateSchedule> {
+        Widget::<X>::update_schedule(&mut self.inner)
+    }
+
+    fn clickable_regions(&self, w: u32, h: u32) -> Vec<Rect> {
+        vec![Rect::new(0, 0, w, h)]
+    }
+
+    fn on_click(
+        &mut self,
+        button: MouseButton,
+        _: Point,
+        _: usize,
+        _: &mut State<X>,
+        _: &X,
+    ) -> Result<()> {
+        let cmd = match button {
+            MouseButton::Left => &self.toggle_mute,
+            MouseButton::ScrollUp => &self.raise,
+            MouseButton::ScrollDown => &self.lower,
+            _ => return Ok(()),
+        };
+
+        // The displayed volume is refreshed on the next scheduled update rather than blocking the
+        // window manager while waiting for the command to complete.
+        Ok(spawn(cmd.as_str())?)
+    }
+});
+
+// The gap left between the text of a meter and its chart
+const CHART_PADDING: u32 = 2;
+
+// Shared implementation for widgets that display a percentage usage on an interval, optionally
+// rendering a bar chart alongside the text.
+struct Meter {
+    label: String,
+    inner: SharedText,
+    usage: Arc<Mutex<u32>>,
+    interval: Duration,
+    get_usage: Option<Box<dyn Fn() -> Option<u32> + Send + 'static>>,
+    chart: Option<(u32, Color, Color)>,
+}
+
+impl fmt::Debug for Meter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Meter")
+            .field("label", &self.label)
+            .field("inner", &self.inner)
+            .field("usage", &self.usage)
+            .field("interval", &self.interval)
+            .field("chart", &self.chart)
+            .finish()
+    }
+}
+
+impl Meter {
+    fn new<F>(label: impl Into<String>, style: TextStyle, get_usage: F, interval: Duration) -> Self
+    where
+        F: Fn() -> Option<u32> + Send + 'static,
+    {
+        Self {
+            label: label.into(),
+            inner: SharedText::new(style),
+            usage: Arc::new(Mutex::new(0)),
+            interval,
+            get_usage: Some(Box::new(get_usage)),
+            chart: None,
+        }
+    }
+
+    fn usage(&self) -> u32 {
+        match self.usage.lock() {
+            Ok(usage) => *usage,
+            Err(poisoned) => *poisoned.into_inner(),
+        }
+    }
+}
+
+impl<X: XConn> Widget<X> for Meter {
+    fn draw(&mut self, ctx: &mut Context<'_>, s: usize, f: bool, w: u32, h: u32) -> Result<()> {
+        let (tw, _) = Widget::<X>::current_extent(&mut self.inner, ctx, h)?;
+        Widget::<X>::draw(&mut self.inner, ctx, s, f, w, h)?;
+
+        if let Some((cw, fill, empty)) = self.chart {
+            let ch = h / 2;
+            let filled = cw * self.usage().min(100) / 100;
+            let offset = (tw + CHART_PADDING) as i32;
+            ctx.translate(offset, 0);
+            ctx.fill_rect(Rect::new(0, h / 4, cw, ch), empty)?;
+            ctx.fill_rect(Rect::new(0, h / 4, filled, ch), fill)?;
+            ctx.translate(-offset, 0);
+        }
+
+        Ok(())
+    }
+
+    fn current_extent(&mut self, ctx: &mut Context<'_>, h: u32) -> Result<(u32, u32)> {
+        let (w, eh) = Widget::<X>::current_extent(&mut self.inner, ctx, h)?;
+
+        match self.chart {
+            Some((cw, _, _)) => Ok((w + cw + CHART_PADDING, eh)),
+            None => Ok((w, eh)),
+        }
+    }
+
+    fn is_greedy(&self) -> bool {
+        false
+    }
+
+    fn require_draw(&self) -> bool {
+        Widget::<X>::require_draw(&self.inner)
+    }
+
+    // Charts follow the theme using the highlight color for usage
+    fn set_theme(&mut self, theme: &Theme) {
+        Widget::<X>::set_theme(&mut self.inner, theme);
+        if let Some((cw, _, _)) = self.chart {
+            self.chart = Some((cw, theme.highlight, theme.muted));
+        }
+    }
+
+    fn update_schedule(&mut self) -> Option<UpdateSchedule> {
+        let get_usage = self.get_usage.take()?;
+        let label = self.label.clone();
+        let usage = self.usage.clone();
+
+        let get_text = move || {
+            let pct = get_usage()?;
+            match usage.lock() {
+                Ok(mut u) => *u = pct,
+                Err(poisoned) => *poisoned.into_inner() = pct,
+            }
+
+            Some(format!("{label} {pct}%"))
+        };
+
+        Some(UpdateSchedule::new(
+            self.interval,
+            Box::new(get_text),
+            self.inner.0.clone(),
+        ))
+    }
+}
+
+/// A CPU usage widget that reads `/proc/stat` on the interval provided, showing the percentage
+/// of CPU time that was spent busy since the previous update.
+///
+/// A small bar chart can be rendered alongside the text using [Cpu::with_chart].
+///
+/// # Example
+/// ```no_run
+/// use penrose_ui::{bar::widgets::sys::Cpu, core::TextStyle};
+/// use std::time::Duration;
+///
+/// # let style = TextStyle {
+/// #     fg: 0xebdbb2ff.into(),
+/// #     bg: Some(0x282828ff.into()),
+/// #     padding: (2, 2),
+/// # };
+/// let cpu = Cpu::new(style, Duration::from_secs(2)).with_chart(20, 0x458588ff, 0x3c3836ff);
+/// ```
+#[derive(Debug)]
+pub struct Cpu {
+    meter: Meter,
+}
+
+impl Cpu {
+    /// Construct a new [Cpu] widget.
+    pub fn new(style: TextStyle, interval: Duration) -> Self {
+        let prev = Mutex::new(None);
+        let get_usage = move || {
+            let (idle, total) = helpers::cpu_times()?;
+            let mut prev = match prev.lock() {
+                Ok(prev) => prev,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            let (prev_idle, prev_total) = prev.replace((idle, total)).unwrap_or((0, 0));
+
+            Some(cpu_percent(prev_idle, prev_total, idle, total))
+        };
+
+        Self {
+            meter: Meter::new("cpu", style, get_usage, interval),
+        }
+    }
+
+    /// Render a bar chart of the given width in pixels alongside the text of this widget, using
+    /// `fill` for the current usage and `empty` for the remainder of the chart.
+    pub fn with_chart(
+        mut self,
+        width: u32,
+        fill: impl Into<Color>,
+        empty: impl Into<Color>,
+    ) -> Self {
+        self.meter.chart = Some((width, fill.into(), empty.into()));
+
+        self
+    }
+}
+
+// The percentage of CPU time spent busy between two readings of the cumulative idle and total
+// CPU times. Counters that appear to go backwards are treated as no time having passed.
+fn cpu_percent(prev_idle: u64, prev_total: u64, idle: u64, total: u64) -> u32 {
+    let d_total = total.saturating_sub(prev_total);
+    let d_idle = idle.saturating_sub(prev_idle);
+
+    if d_total == 0 {
+        return 0;
+    }
+
+    (100 * d_total.saturating_sub(d_idle) / d_total) as u32
+}
+
+delegate_widget!(Cpu => meter {
+    fn update_schedule(&mut self) -> Option<UpdateSchedule> {
+        Widget::<X>::update_schedule(&mut self.meter)
+    }
+});
+
+/// A memory usage widget that reads `/proc/meminfo` on the interval provided, showing the
+/// percentage of total memory that is currently in use.
+///
+/// A small bar chart can be rendered alongside the text using [Memory::with_chart].
+///
+/// # Example
+/// ```no_run
+/// use penrose_ui::{bar::widgets::sys::Memory, core::TextStyle};
+/// use std::time::Duration;
+///
+/// # let style = TextStyle {
+/// #     fg: 0xebdbb2ff.into(),
+/// #     bg: Some(0x282828ff.into()),
+/// #     padding: (2, 2),
+/// # };
+/// let mem = Memory::new(style, Duration::from_secs(5));
+/// ```
+#[derive(Debug)]
+pub struct Memory {
+    meter: Meter,
+}
+
+impl Memory {
+    /// Construct a new [Memory] widget.
+    pub fn new(style: TextStyle, interval: Duration) -> Self {
+        Self {
+            meter: Meter::new("mem", style, helpers::memory_usage, interval),
+        }
+    }
+
+    /// Render a bar chart of the given width in pixels alongside the text of this widget, using
+    /// `fill` for the current usage and `empty` for the remainder of the chart.
+    pub fn with_chart(
+        mut self,
+        width: u32,
+        fill: impl Into<Color>,
+        empty: impl Into<Color>,
+    ) -> Self {
+        self.meter.chart = Some((width, fill.into(), empty.into()));
+
+        self
+    }
+}
+
+delegate_widget!(Memory => meter {
+    fn update_schedule(&mut self) -> Option<UpdateSchedule> {
+        Widget::<X>::update_schedule(&mut self.meter)
+    }
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    const FG: u32 = 0xebdbb2ff;
+    const WARN: u32 = 0xd79921ff;
+    const CRIT: u32 = 0xcc241dff;
+
+    fn style() -> TextStyle {
+        TextStyle {
+            fg: FG.into(),
+            bg: None,
+            padding: (2, 2),
+        }
+    }
+
+    fn status(charge: u32, status: &str) -> BatteryStatus {
+        BatteryStatus {
+            charge,
+            status: status.to_owned(),
+        }
+    }
+
+    #[test_case(80, "Discharging", FG; "above all thresholds")]
+    #[test_case(30, "Discharging", WARN; "at the warning threshold")]
+    #[test_case(20, "Discharging", WARN; "between thresholds")]
+    #[test_case(10, "Discharging", CRIT; "at the critical threshold")]
+    #[test_case(5, "Discharging", CRIT; "below all thresholds")]
+    #[test_case(5, "Charging", FG; "charging")]
+    #[test_case(100, "Full", FG; "full")]
+    #[test]
+    fn battery_color_uses_the_lowest_matching_threshold(charge: u32, s: &str, expected: u32) {
+        let bat = Battery::new("BAT0", style(), Duration::from_secs(1))
+            .with_warning(10, CRIT)
+            .with_warning(30, WARN);
+
+        let color = battery_color(&status(charge, s), FG.into(), &bat.thresholds);
+
+        assert_eq!(color, expected.into());
+    }
+
+    #[test_case((0, 0), (0, 0), 0; "no time passed")]
+    #[test_case((0, 0), (100, 100), 0; "fully idle")]
+    #[test_case((0, 0), (0, 100), 100; "fully busy")]
+    #[test_case((100, 400), (150, 600), 75; "busy since previous reading")]
+    #[test_case((100, 400), (101, 403), 66; "percentages are rounded down")]
+    #[test_case((200, 600), (100, 400), 0; "counters reset")]
+    #[test]
+    fn cpu_percent_uses_the_delta_between_readings(
+        prev: (u64, u64),
+        current: (u64, u64),
+        expected: u32,
+    ) {
+        assert_eq!(cpu_percent(prev.0, prev.1, current.0, current.1), expected);
+    }
+}
@@ -0,0 +1,654 @@
+//! A system tray implementing the freedesktop system tray and XEmbed specifications.
+//!
+//! Specs:
+//!   - https://specifications.freedesktop.org/systemtray-spec/systemtray-spec-0.3.html
+//!   - https://specifications.freedesktop.org/xembed-spec/xembed-spec-latest.html
+use crate::{
+    bar::widgets::{Context, Widget},
+    Result,
+};
+use penrose::{core::State, pure::geometry::Rect, x::XConn, Color};
+use std::{
+    mem::take,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread::{self, JoinHandle},
+};
+use tracing::{debug, error, info, warn};
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        xproto::{
+            Atom, AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConfigureWindowAux,
+            ConnectionExt as _, CreateWindowAux, EventMask, PropMode, SetMode, StackMode, Window,
+            WindowClass,
+        },
+        Event,
+    },
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+    COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME,
+};
+
+const PADDING: u32 = 2;
+const SYSTEM_TRAY_REQUEST_DOCK: u32 = 0;
+const XEMBED_EMBEDDED_NOTIFY: u32 = 0;
+const XEMBED_MAPPED: u32 = 1 << 0;
+
+/// A system tray for embedding the status icons of running applications into the status bar.
+///
+/// Only a single [SysTray] can be running at a time as the tray takes ownership of the
+/// `_NET_SYSTEM_TRAY_S{n}` selection for the X screen. Icons are shown on the screen of the
+/// status bar given by `on_screen` (the first screen by default) while the tray is given an
+/// empty region on all other screens.
+///
+/// Docking requests from applications are handled on a background thread with its own
+/// connection to the X server, so any failure to set up the tray is reported from the startup
+/// hook of the widget. The thread is stopped and the selection released when the [SysTray] is
+/// dropped, returning any docked icons to the root window.
+#[derive(Debug)]
+pub struct SysTray {
+    bg: Color,
+    screen: usize,
+    spacing: u32,
+    vertical: bool,
+    tray: Option<Arc<Tray>>,
+    handle: Option<JoinHandle<()>>,
+    require_draw: bool,
+}
+
+impl SysTray {
+    /// Construct a new [SysTray] using the given background color behind the tray icons.
+    pub fn new(bg: impl Into<Color>) -> Self {
+        Self {
+            bg: bg.into(),
+            screen: 0,
+            spacing: PADDING,
+            vertical: false,
+            tray: None,
+            handle: None,
+            require_draw: true,
+        }
+    }
+
+    /// Show tray icons on the status bar for the given screen index.
+    pub fn on_screen(mut self, screen: usize) -> Self {
+        self.screen = screen;
+
+        self
+    }
+
+    /// Set the spacing in pixels between tray icons.
+    pub fn with_spacing(mut self, spacing: u32) -> Self {
+        self.spacing = spacing;
+
+        self
+    }
+
+    /// Stack tray icons vertically for use in status bars placed at the side of the screen.
+    pub fn vertical(mut self) -> Self {
+        self.vertical = true;
+
+        self
+    }
+
+    /// The number of icons currently shown in the tray.
+    pub fn n_icons(&self) -> usize {
+        self.tray
+            .as_ref()
+            .map(|t| t.layout().n_mapped())
+            .unwrap_or(0)
+    }
+}
+
+impl<X: XConn> Widget<X> for SysTray {
+    fn draw(
+        &mut self,
+        ctx: &mut Context<'_>,
+        screen: usize,
+        _: bool,
+        w: u32,
+        h: u32,
+    ) -> Result<()> {
+        ctx.fill_rect(Rect::new(0, 0, w, h), self.bg)?;
+        self.require_draw = false;
+
+        if screen != self.screen {
+            return Ok(());
+        }
+
+        if let Some(tray) = &self.tray {
+            tray.changed.store(false, Ordering::SeqCst);
+            let thickness = if self.vertical { w } else { h };
+            let (dx, dy) = ctx.offset();
+            tray.place(*ctx.window(), dx, dy, thickness)?;
+        }
+
+        Ok(())
+    }
+
+    fn current_extent(&mut self, _: &mut Context<'_>, h: u32) -> Result<(u32, u32)> {
+        let n = self.n_icons() as u32;
+        let size = h.saturating_sub(2 * PADDING);
+        let len = if n == 0 {
+            0
+        } else {
+            n * (size + self.spacing) + self.spacing
+        };
+
+        Ok(if self.vertical { (h, len) } else { (len, h) })
+    }
+
+    fn require_draw(&self) -> bool {
+        self.require_draw
+            || self
+                .tray
+                .as_ref()
+                .map(|t| t.changed.load(Ordering::SeqCst))
+                .unwrap_or(false)
+    }
+
+    fn is_greedy(&self) -> bool {
+        false
+    }
+
+    fn on_startup(&mut self, _: &mut State<X>, _: &X) -> Result<()> {
+        if self.tray.is_none() {
+            let tray = Tray::try_new(self.bg, self.spacing, self.vertical)?;
+            let t = tray.clone();
+            self.handle = Some(thread::spawn(move || t.run()));
+            self.tray = Some(tray);
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for SysTray {
+    fn drop(&mut self) {
+        let tray = match self.tray.take() {
+            Some(tray) => tray,
+            None => return,
+        };
+
+        if let Err(e) = tray.stop() {
+            error!(%e, "unable to stop system tray thread");
+            return;
+        }
+
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                error!("system tray thread panicked");
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Atoms {
+    selection: Atom,
+    opcode: Atom,
+    orientation: Atom,
+    manager: Atom,
+    xembed: Atom,
+    xembed_info: Atom,
+    update: Atom,
+}
+
+impl Atoms {
+    fn try_new(conn: &RustConnection, screen: usize) -> Result<Self> {
+        let atom = |name: &str| -> Result<Atom> {
+            let reply = conn
+                .intern_atom(false, name.as_bytes())
+                .map_err(penrose::Error::from)?
+                .reply()
+                .map_err(penrose::Error::from)?;
+
+            Ok(reply.atom)
+        };
+
+        Ok(Self {
+            selection: atom(&format!("_NET_SYSTEM_TRAY_S{screen}"))?,
+            opcode: atom("_NET_SYSTEM_TRAY_OPCODE")?,
+            orientation: atom("_NET_SYSTEM_TRAY_ORIENTATION")?,
+            manager: atom("MANAGER")?,
+            xembed: atom("_XEMBED")?,
+            xembed_info: atom("_XEMBED_INFO")?,
+            update: atom("_PENROSE_SYSTRAY_UPDATE")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Icon {
+    id: Window,
+    mapped: bool,
+}
+
+// The current state of the tray shared between the widget and the event handling thread
+#[derive(Debug, Default)]
+struct Layout {
+    icons: Vec<Icon>,
+    bar: Option<Window>,
+    bar_mapped: bool,
+    pos: (i32, i32),
+    size: u32,
+}
+
+impl Layout {
+    fn n_mapped(&self) -> usize {
+        self.icons.iter().filter(|i| i.mapped).count()
+    }
+}
+
+#[derive(Debug)]
+struct Tray {
+    conn: RustConnection,
+    root: Window,
+    win: Window,
+    atoms: Atoms,
+    spacing: u32,
+    vertical: bool,
+    layout: Mutex<Layout>,
+    changed: AtomicBool,
+    running: AtomicBool,
+}
+
+impl Tray {
+    fn try_new(bg: Color, spacing: u32, vertical: bool) -> Result<Arc<Self>> {
+        let (conn, screen) = x11rb::connect(None).map_err(penrose::Error::from)?;
+        let root = conn.setup().roots[screen].root;
+        let atoms = Atoms::try_new(&conn, screen)?;
+        let win = conn.generate_id().map_err(penrose::Error::from)?;
+
+        let aux = CreateWindowAux::new()
+            .override_redirect(1)
+            .background_pixel(bg.rgb_u32())
+            .event_mask(EventMask::SUBSTRUCTURE_NOTIFY);
+
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            win,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            COPY_FROM_PARENT,
+            &aux,
+        )
+        .map_err(penrose::Error::from)?;
+
+        let orientation = if vertical { 1 } else { 0 };
+        conn.change_property32(
+            PropMode::REPLACE,
+            win,
+            atoms.orientation,
+            AtomEnum::CARDINAL,
+            &[orientation],
+        )
+        .map_err(penrose::Error::from)?;
+
+        conn.set_selection_owner(win, atoms.selection, CURRENT_TIME)
+            .map_err(penrose::Error::from)?;
+        let owner = conn
+            .get_selection_owner(atoms.selection)
+            .map_err(penrose::Error::from)?
+            .reply()
+            .map_err(penrose::Error::from)?
+            .owner;
+
+        if owner != win {
+            return Err(penrose::Error::Custom(
+                "unable to acquire the system tray selection: is another tray running?".into(),
+            )
+            .into());
+        }
+
+        info!(%win, "acquired system tray selection");
+        let data = [CURRENT_TIME, atoms.selection, win, 0, 0];
+        let msg = ClientMessageEvent::new(32, root, atoms.manager, data);
+        conn.send_event(false, root, EventMask::STRUCTURE_NOTIFY, msg)
+            .map_err(penrose::Error::from)?;
+        conn.flush().map_err(penrose::Error::from)?;
+
+        Ok(Arc::new(Self {
+            conn,
+            root,
+            win,
+            atoms,
+            spacing,
+            vertical,
+            layout: Mutex::new(Layout::default()),
+            changed: AtomicBool::new(false),
+            running: AtomicBool::new(true),
+        }))
+    }
+
+    fn layout(&self) -> MutexGuard<'_, Layout> {
+        match self.layout.lock() {
+            Ok(inner) => inner,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    // Called from the widget when drawing in order to position the tray over the region of the
+    // status bar that has been allocated to it.
+    fn place(&self, bar: Window, dx: i32, dy: i32, thickness: u32) -> Result<()> {
+        let reply = self
+            .conn
+            .translate_coordinates(bar, self.root, dx as i16, dy as i16)
+            .map_err(penrose::Error::from)?
+            .reply()
+            .map_err(penrose::Error::from)?;
+
+        let mut l = self.layout();
+        if l.bar != Some(bar) {
+            // Track the bar window so that we can follow it being hidden and revealed
+            let aux = ChangeWindowAttributesAux::new().event_mask(EventMask::STRUCTURE_NOTIFY);
+            self.conn
+                .change_window_attributes(bar, &aux)
+                .map_err(penrose::Error::from)?;
+            l.bar = Some(bar);
+        }
+
+        l.bar_mapped = true;
+        l.pos = (reply.dst_x as i32, reply.dst_y as i32);
+        l.size = thickness.saturating_sub(2 * PADDING);
+
+        self.arrange(&l)
+    }
+
+    fn arrange(&self, l: &Layout) -> Result<()> {
+        let n = l.n_mapped() as u32;
+        let step = (l.size + self.spacing) as i32;
+
+        for (i, icon) in l.icons.iter().filter(|i| i.mapped).enumerate() {
+            let offset = self.spacing as i32 + i as i32 * step;
+            let (x, y) = if self.vertical {
+                (PADDING as i32, offset)
+            } else {
+                (offset, PADDING as i32)
+            };
+            let aux = ConfigureWindowAux::new()
+                .x(x)
+                .y(y)
+                .width(l.size)
+                .height(l.size);
+            self.conn
+                .configure_window(icon.id, &aux)
+                .map_err(penrose::Error::from)?;
+        }
+
+        if n == 0 || !l.bar_mapped || l.size == 0 {
+            self.conn
+                .unmap_window(self.win)
+                .map_err(penrose::Error::from)?;
+        } else {
+            let len = n * (l.size + self.spacing) + self.spacing;
+            let thickness = l.size + 2 * PADDING;
+            let (w, h) = if self.vertical {
+                (thickness, len)
+            } else {
+                (len, thickness)
+            };
+            let aux = ConfigureWindowAux::new()
+                .x(l.pos.0)
+                .y(l.pos.1)
+                .width(w)
+                .height(h)
+                .stack_mode(StackMode::ABOVE);
+            self.conn
+                .configure_window(self.win, &aux)
+                .map_err(penrose::Error::from)?;
+            self.conn
+                .map_window(self.win)
+                .map_err(penrose::Error::from)?;
+        }
+
+        self.conn.flush().map_err(penrose::Error::from)?;
+
+        Ok(())
+    }
+
+    // Let the status bar know that the tray needs to be redrawn by sending an event that will
+    // be picked up by the window manager event loop.
+    fn notify_changed(&self) -> Result<()> {
+        self.changed.store(true, Ordering::SeqCst);
+        let msg = ClientMessageEvent::new(32, self.root, self.atoms.update, [0; 5]);
+        self.conn
+            .send_event(false, self.root, EventMask::SUBSTRUCTURE_NOTIFY, msg)
+            .map_err(penrose::Error::from)?;
+        self.conn.flush().map_err(penrose::Error::from)?;
+
+        Ok(())
+    }
+
+    fn is_mapped(&self, id: Window) -> bool {
+        let flags = self
+            .conn
+            .get_property(false, id, self.atoms.xembed_info, AtomEnum::ANY, 0, 2)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .and_then(|reply| reply.value32().and_then(|mut vals| vals.nth(1)));
+
+        // Clients that do not set _XEMBED_INFO are assumed to want to be shown
+        flags.map(|f| f & XEMBED_MAPPED != 0).unwrap_or(true)
+    }
+
+    fn dock(&self, id: Window) -> Result<()> {
+        let mut l = self.layout();
+        if l.icons.iter().any(|i| i.id == id) {
+            return Ok(());
+        }
+
+        info!(%id, "docking system tray icon");
+        let aux = ChangeWindowAttributesAux::new()
+            .event_mask(EventMask::STRUCTURE_NOTIFY | EventMask::PROPERTY_CHANGE);
+        self.conn
+            .change_window_attributes(id, &aux)
+            .map_err(penrose::Error::from)?;
+        self.conn
+            .change_save_set(SetMode::INSERT, id)
+            .map_err(penrose::Error::from)?;
+        self.conn
+            .reparent_window(id, self.win, 0, 0)
+            .map_err(penrose::Error::from)?;
+
+        let data = [CURRENT_TIME, XEMBED_EMBEDDED_NOTIFY, 0, self.win, 0];
+        let msg = ClientMessageEvent::new(32, id, self.atoms.xembed, data);
+        self.conn
+            .send_event(false, id, EventMask::NO_EVENT, msg)
+            .map_err(penrose::Error::from)?;
+
+        let mapped = self.is_mapped(id);
+        l.icons.push(Icon { id, mapped });
+        if mapped {
+            self.conn.map_window(id).map_err(penrose::Error::from)?;
+        }
+
+        self.arrange(&l)?;
+        drop(l);
+
+        self.notify_changed()
+    }
+
+    fn remove(&self, id: Window) -> Result<()> {
+        let mut l = self.layout();
+        let n = l.icons.len();
+        l.icons.retain(|i| i.id != id);
+
+        if l.icons.len() != n {
+            info!(%id, "removing system tray icon");
+            self.arrange(&l)?;
+            drop(l);
+            self.notify_changed()?;
+        }
+
+        Ok(())
+    }
+
+    fn update_mapped(&self, id: Window) -> Result<()> {
+        let mapped = self.is_mapped(id);
+        let mut l = self.layout();
+
+        match l.icons.iter_mut().find(|i| i.id == id) {
+            Some(icon) if icon.mapped != mapped => icon.mapped = mapped,
+            _ => return Ok(()),
+        }
+
+        if mapped {
+            self.conn.map_window(id).map_err(penrose::Error::from)?;
+        } else {
+            self.conn.unmap_window(id).map_err(penrose::Error::from)?;
+        }
+
+        self.arrange(&l)?;
+        drop(l);
+
+        self.notify_changed()
+    }
+
+    fn set_bar_mapped(&self, mapped: bool) -> Result<()> {
+        let mut l = self.layout();
+        l.bar_mapped = mapped;
+
+        self.arrange(&l)
+    }
+
+    // Icons are forced back to the size allocated to them by the tray if they attempt to
+    // resize themselves.
+    fn enforce_size(&self, id: Window, w: u16, h: u16) -> Result<()> {
+        let l = self.layout();
+        let is_icon = l.icons.iter().any(|i| i.id == id);
+
+        if is_icon && (w as u32 != l.size || h as u32 != l.size) {
+            self.arrange(&l)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_event(&self, event: Event) -> Result<bool> {
+        let bar = self.layout().bar;
+
+        match event {
+            Event::ClientMessage(e) if e.type_ == self.atoms.opcode => {
+                let data = e.data.as_data32();
+                if data[1] == SYSTEM_TRAY_REQUEST_DOCK {
+                    self.dock(data[2])?;
+                }
+            }
+
+            Event::DestroyNotify(e) => self.remove(e.window)?,
+            Event::ReparentNotify(e) if e.parent != self.win => self.remove(e.window)?,
+
+            Event::PropertyNotify(e) if e.atom == self.atoms.xembed_info => {
+                self.update_mapped(e.window)?
+            }
+
+            Event::ConfigureNotify(e) if e.window != self.win && Some(e.window) != bar => {
+                self.enforce_size(e.window, e.width, e.height)?
+            }
+
+            // Follow the status bar being hidden and revealed
+            Event::UnmapNotify(e) if Some(e.window) == bar => self.set_bar_mapped(false)?,
+            Event::MapNotify(e) if Some(e.window) == bar => self.set_bar_mapped(true)?,
+
+            Event::SelectionClear(e) if e.selection == self.atoms.selection => {
+                warn!("lost ownership of the system tray selection: stopping system tray");
+                self.layout().icons.clear();
+                self.conn
+                    .unmap_window(self.win)
+                    .map_err(penrose::Error::from)?;
+                self.notify_changed()?;
+
+                return Ok(false);
+            }
+
+            _ => (),
+        }
+
+        Ok(true)
+    }
+
+    // Signal the event handling thread to exit, sending an event to the tray window in order to
+    // wake it up if it is currently waiting for the next event.
+    fn stop(&self) -> Result<()> {
+        self.running.store(false, Ordering::SeqCst);
+        let msg = ClientMessageEvent::new(32, self.win, self.atoms.update, [0; 5]);
+        self.conn
+            .send_event(false, self.win, EventMask::NO_EVENT, msg)
+            .map_err(penrose::Error::from)?;
+        self.conn.flush().map_err(penrose::Error::from)?;
+
+        Ok(())
+    }
+
+    // Hand any docked icons back to the root window and give up the tray selection if we still
+    // hold it so that another system tray is able to take over.
+    fn release(&self) -> Result<()> {
+        let icons = take(&mut self.layout().icons);
+        for icon in icons {
+            self.conn
+                .unmap_window(icon.id)
+                .map_err(penrose::Error::from)?;
+            self.conn
+                .reparent_window(icon.id, self.root, 0, 0)
+                .map_err(penrose::Error::from)?;
+        }
+
+        let owner = self
+            .conn
+            .get_selection_owner(self.atoms.selection)
+            .map_err(penrose::Error::from)?
+            .reply()
+            .map_err(penrose::Error::from)?
+            .owner;
+
+        if owner == self.win {
+            info!(win = %self.win, "releasing system tray selection");
+            self.conn
+                .set_selection_owner(x11rb::NONE, self.atoms.selection, CURRENT_TIME)
+                .map_err(penrose::Error::from)?;
+        }
+
+        self.conn
+            .destroy_window(self.win)
+            .map_err(penrose::Error::from)?;
+        self.conn.flush().map_err(penrose::Error::from)?;
+
+        Ok(())
+    }
+
+    fn run(&self) {
+        while self.running.load(Ordering::SeqCst) {
+            let event = match self.conn.wait_for_event() {
+                Ok(event) => event,
+                Err(e) => {
+                    error!(%e, "system tray connection closed");
+                    return;
+                }
+            };
+
+            debug!(?event, "system tray event");
+            match self.handle_event(event) {
+                Ok(true) => (),
+                Ok(false) => return,
+                Err(e) => error!(%e, "error handling system tray event"),
+            }
+        }
+    }
+}
+
+impl Drop for Tray {
+    fn drop(&mut self) {
+        if let Err(e) = self.release() {
+            error!(%e, "error releasing system tray");
+        }
+    }
+}
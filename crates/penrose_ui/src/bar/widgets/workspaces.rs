@@ -1,17 +1,21 @@
 //! Widgets for the penrose status bar
 use crate::{
     bar::widgets::Widget,
-    core::{Context, TextStyle},
+    core::{Context, TextDecoration, TextStyle},
     Result,
 };
 use penrose::{
-    core::{ClientSpace, State},
-    pure::geometry::Rect,
-    x::XConn,
-    Color,
+    core::{bindings::MouseButton, ClientSpace, State},
+    extensions::util::theme::Theme,
+    pure::geometry::{Point, Rect},
+    x::{Atom, XConn, XConnExt, XEvent},
+    Color, Xid,
 };
+use std::collections::HashSet;
+use tracing::warn;
 
 const PADDING: u32 = 3;
+const URGENT: u32 = 0xcc241dff;
 
 /// The focus state of a given workspace being rendered within a [WorkspacesWidget].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -65,13 +69,36 @@ pub trait WorkspacesUi {
     ///
     /// The [FocusState] provided indicates the current state of the workspace itself, while
     /// `screen_has_focus` is used to indicate whether or not the screen the parent
-    /// [WorkspacesWidget] is on is currently focused or not.
+    /// [WorkspacesWidget] is on is currently focused or not. Workspaces containing clients that
+    /// are requesting attention can be identified using [WsMeta::urgent].
     fn colors_for_workspace(
         &self,
         workspace_meta: &WsMeta,
         focus_state: FocusState,
         screen_has_focus: bool,
     ) -> (Color, Color);
+
+    /// The underline and overline to be drawn for a given workspace.
+    ///
+    /// By default no decoration is drawn. See `colors_for_workspace` for details of the
+    /// arguments provided to this method.
+    #[allow(unused_variables)]
+    fn decoration_for_workspace(
+        &self,
+        workspace_meta: &WsMeta,
+        focus_state: FocusState,
+        screen_has_focus: bool,
+    ) -> TextDecoration {
+        TextDecoration::default()
+    }
+
+    /// Restyle this UI to match the given [Theme], returning `true` if a redraw is now required.
+    ///
+    /// By default the theme is ignored.
+    #[allow(unused_variables)]
+    fn set_theme(&mut self, theme: &Theme) -> bool {
+        false
+    }
 }
 
 /// The default UI style of a [WorkspacesWidget].
@@ -81,6 +108,8 @@ pub struct DefaultUi {
     fg_2: Color,
     bg_1: Color,
     bg_2: Color,
+    urgent: Color,
+    focused_decoration: TextDecoration,
 }
 
 impl DefaultUi {
@@ -90,6 +119,8 @@ impl DefaultUi {
             fg_2: empty_fg.into(),
             bg_1: highlight.into(),
             bg_2: style.bg.unwrap_or_else(|| 0x000000.into()),
+            urgent: URGENT.into(),
+            focused_decoration: TextDecoration::default(),
         }
     }
 }
@@ -101,7 +132,9 @@ impl WorkspacesUi for DefaultUi {
 
     fn colors_for_workspace(
         &self,
-        &WsMeta { occupied, .. }: &WsMeta,
+        &WsMeta {
+            occupied, urgent, ..
+        }: &WsMeta,
         focus_state: FocusState,
         screen_has_focus: bool,
     ) -> (Color, Color) {
@@ -110,12 +143,37 @@ impl WorkspacesUi for DefaultUi {
         match focus_state {
             FocusedOnThisScreen if screen_has_focus && occupied => (self.fg_1, self.bg_1),
             FocusedOnThisScreen if screen_has_focus => (self.fg_2, self.bg_1),
+            _ if urgent => (self.fg_1, self.urgent),
             FocusedOnThisScreen => (self.fg_1, self.fg_2),
             FocusedOnOtherScreen => (self.bg_1, self.fg_2),
             Unfocused if occupied => (self.fg_1, self.bg_2),
             Unfocused => (self.fg_2, self.bg_2),
         }
     }
+
+    fn decoration_for_workspace(
+        &self,
+        _: &WsMeta,
+        focus_state: FocusState,
+        _: bool,
+    ) -> TextDecoration {
+        if focus_state == FocusState::FocusedOnThisScreen {
+            self.focused_decoration
+        } else {
+            TextDecoration::default()
+        }
+    }
+
+    fn set_theme(&mut self, theme: &Theme) -> bool {
+        let prev = self.clone();
+        self.fg_1 = theme.fg;
+        self.fg_2 = theme.muted;
+        self.bg_1 = theme.highlight;
+        self.bg_2 = theme.bg;
+        self.urgent = theme.urgent;
+
+        *self != prev
+    }
 }
 
 /// Metadata around the content of a particular workspace within the current
@@ -124,6 +182,7 @@ impl WorkspacesUi for DefaultUi {
 pub struct WsMeta {
     tag: String,
     occupied: bool,
+    urgent: bool,
     extent: (u32, u32),
 }
 
@@ -138,14 +197,22 @@ impl WsMeta {
         self.occupied
     }
 
-    fn from_state<X>(state: &State<X>) -> Vec<Self>
+    /// Whether or not this workspace contains any clients that are requesting attention
+    pub fn urgent(&self) -> bool {
+        self.urgent
+    }
+
+    fn from_state<X>(state: &State<X>, urgent_clients: &HashSet<Xid>) -> Vec<Self>
     where
         X: XConn,
     {
         state
             .client_set
             .ordered_workspaces()
-            .map(WsMeta::from)
+            .map(|w| WsMeta {
+                urgent: w.clients().any(|id| urgent_clients.contains(id)),
+                ..WsMeta::from(w)
+            })
             .collect()
     }
 }
@@ -155,6 +222,7 @@ impl From<&ClientSpace> for WsMeta {
         Self {
             tag: w.tag().to_owned(),
             occupied: !w.is_empty(),
+            urgent: false,
             extent: (0, 0),
         }
     }
@@ -183,9 +251,31 @@ impl Workspaces {
     pub fn new(style: TextStyle, highlight: impl Into<Color>, empty_fg: impl Into<Color>) -> Self {
         WorkspacesWidget::new_with_ui(DefaultUi::new(style, highlight, empty_fg))
     }
+
+    /// Set the background color used to highlight workspaces containing urgent clients.
+    pub fn with_urgent_color(mut self, urgent: impl Into<Color>) -> Self {
+        self.ui.urgent = urgent.into();
+        self.require_draw = true;
+
+        self
+    }
+
+    /// Set an underline and/or overline to be drawn for the workspace focused on the screen
+    /// that the widget is rendered on.
+    pub fn with_focused_decoration(mut self, decoration: TextDecoration) -> Self {
+        self.ui.focused_decoration = decoration;
+        self.require_draw = true;
+
+        self
+    }
 }
 
 /// A simple workspace indicator for a status bar
+///
+/// Workspaces containing clients that have set the ICCCM urgency hint or the EWMH
+/// `_NET_WM_STATE_DEMANDS_ATTENTION` state are flagged as urgent until the client in question
+/// gains focus. Left clicking on a workspace tag will focus that workspace on the screen the
+/// bar was clicked on.
 #[derive(Debug, Clone, PartialEq)]
 pub struct WorkspacesWidget<U>
 where
@@ -193,6 +283,7 @@ where
 {
     workspaces: Vec<WsMeta>,
     focused_ws: Vec<String>, // focused ws per screen
+    urgent_clients: HashSet<Xid>,
     extent: Option<(u32, u32)>,
     ui: U,
     require_draw: bool,
@@ -207,6 +298,7 @@ where
         Self {
             workspaces: Vec::new(),
             focused_ws: Vec::new(), // set in startup hook
+            urgent_clients: HashSet::new(),
             extent: None,
             ui,
             require_draw: true,
@@ -221,8 +313,13 @@ where
     where
         X: XConn,
     {
+        // Urgency is cleared once the client in question is focused
+        let focused_client = state.client_set.current_client().copied();
+        self.urgent_clients
+            .retain(|id| state.client_set.contains(id) && Some(*id) != focused_client);
+
         let focused_ws = focused_workspaces(state);
-        let wss = WsMeta::from_state(state);
+        let wss = WsMeta::from_state(state, &self.urgent_clients);
 
         let ui_updated = self.ui.update_from_state(&wss, &focused_ws, state, x);
         let tags_changed = self.tags_changed(&wss);
@@ -230,7 +327,7 @@ where
         if ui_updated || tags_changed {
             self.require_draw = true;
             self.extent = None;
-        } else if self.focused_ws != focused_ws || self.occupied_changed(&wss) {
+        } else if self.focused_ws != focused_ws || self.meta_changed(&wss) {
             self.require_draw = true;
         }
 
@@ -245,28 +342,55 @@ where
     }
 
     // Called after tags_changed above so we assume that tags are matching
-    fn occupied_changed(&self, workspaces: &[WsMeta]) -> bool {
+    fn meta_changed(&self, workspaces: &[WsMeta]) -> bool {
         self.workspaces
             .iter()
             .zip(workspaces)
-            .any(|(l, r)| l.occupied != r.occupied)
+            .any(|(l, r)| l.occupied != r.occupied || l.urgent != r.urgent)
     }
 
-    fn ws_colors(&self, meta: &WsMeta, screen: usize, screen_has_focus: bool) -> (Color, Color) {
+    fn update_urgency<X>(&mut self, id: Xid, x: &X)
+    where
+        X: XConn,
+    {
+        match x.client_is_urgent(id) {
+            Ok(true) => _ = self.urgent_clients.insert(id),
+            Ok(false) => _ = self.urgent_clients.remove(&id),
+            Err(e) => warn!(%e, %id, "unable to check urgency state for client"),
+        }
+    }
+
+    fn focus_state(&self, meta: &WsMeta, screen: usize) -> FocusState {
         let focused = self.focused_ws.iter().any(|t| t == &meta.tag);
         let focused_on_this_screen = match &self.focused_ws.get(screen) {
             &Some(focused_tag) => &meta.tag == focused_tag,
             None => false,
         };
 
-        let state = match (focused, focused_on_this_screen) {
+        match (focused, focused_on_this_screen) {
             (false, _) => FocusState::Unfocused,
             (_, true) => FocusState::FocusedOnThisScreen,
             (true, false) => FocusState::FocusedOnOtherScreen,
-        };
+        }
+    }
+
+    fn ws_colors(&self, meta: &WsMeta, screen: usize, screen_has_focus: bool) -> (Color, Color) {
+        let state = self.focus_state(meta, screen);
 
         self.ui.colors_for_workspace(meta, state, screen_has_focus)
     }
+
+    fn ws_decoration(
+        &self,
+        meta: &WsMeta,
+        screen: usize,
+        screen_has_focus: bool,
+    ) -> TextDecoration {
+        let state = self.focus_state(meta, screen);
+
+        self.ui
+            .decoration_for_workspace(meta, state, screen_has_focus)
+    }
 }
 
 impl<X, U> Widget<X> for WorkspacesWidget<U>
@@ -288,8 +412,10 @@ where
 
         for ws in self.workspaces.iter() {
             let (fg, bg) = self.ws_colors(ws, screen, screen_has_focus);
+            let decoration = self.ws_decoration(ws, screen, screen_has_focus);
             ctx.fill_rect(Rect::new(0, 0, ws.extent.0, h), bg)?;
             ctx.draw_text(&self.ui.ui_tag(ws), h - eh, (PADDING, PADDING), fg)?;
+            ctx.draw_text_decoration(decoration, ws.extent.0, h)?;
             ctx.translate(ws.extent.0 as i32, 0);
         }
 
@@ -327,6 +453,12 @@ where
         self.require_draw
     }
 
+    fn set_theme(&mut self, theme: &Theme) {
+        if self.ui.set_theme(theme) {
+            self.require_draw = true;
+        }
+    }
+
     fn on_startup(&mut self, state: &mut State<X>, x: &X) -> Result<()> {
         self.update_from_state(state, x);
 
@@ -338,4 +470,59 @@ where
 
         Ok(())
     }
+
+    fn on_event(&mut self, event: &XEvent, state: &mut State<X>, x: &X) -> Result<()> {
+        if let XEvent::PropertyNotify(e) = event {
+            let is_hint = e.atom == Atom::WmHints.as_ref() || e.atom == Atom::NetWmState.as_ref();
+
+            if is_hint && state.client_set.contains(&e.id) {
+                self.update_urgency(e.id, x);
+                self.update_from_state(state, x);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_new_client(&mut self, id: Xid, state: &mut State<X>, x: &X) -> Result<()> {
+        self.update_urgency(id, x);
+        self.update_from_state(state, x);
+
+        Ok(())
+    }
+
+    // One region per workspace tag
+    fn clickable_regions(&self, _: u32, h: u32) -> Vec<Rect> {
+        let mut offset = PADDING;
+
+        self.workspaces
+            .iter()
+            .map(|ws| {
+                let r = Rect::new(offset, 0, ws.extent.0, h);
+                offset += ws.extent.0;
+                r
+            })
+            .collect()
+    }
+
+    fn on_region_click(
+        &mut self,
+        button: MouseButton,
+        region: usize,
+        _: Point,
+        screen: usize,
+        state: &mut State<X>,
+        _: &X,
+    ) -> Result<()> {
+        if button != MouseButton::Left {
+            return Ok(());
+        }
+
+        if let Some(ws) = self.workspaces.get(region) {
+            state.client_set.focus_screen(screen);
+            state.client_set.focus_tag(&ws.tag);
+        }
+
+        Ok(())
+    }
 }
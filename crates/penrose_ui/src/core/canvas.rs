@@ -0,0 +1,399 @@
+//! Software rendering of the drawing primitives supported by [Context][super::Context].
+//!
+//! Pixels are held client side as premultiplied ARGB values and uploaded to the X server when
+//! the parent surface is flushed.
+use crate::{Error, Result};
+use penrose::Color;
+use std::fmt;
+
+// X11 window dimensions are 16-bit so there is no need to support anything larger
+const MAX_DIMENSION: u32 = u16::MAX as u32;
+
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct Canvas {
+    w: u32,
+    h: u32,
+    px: Vec<u32>,
+    // An optional (x, y, w, h) region that drawing operations are further restricted to
+    clip_region: Option<(i32, i32, u32, u32)>,
+}
+
+// The pixel data is omitted as it is far too large to be useful in debug output
+impl fmt::Debug for Canvas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Canvas")
+            .field("w", &self.w)
+            .field("h", &self.h)
+            .finish()
+    }
+}
+
+impl Canvas {
+    pub(crate) fn check_size(w: u32, h: u32) -> Result<usize> {
+        if w > MAX_DIMENSION || h > MAX_DIMENSION {
+            return Err(Error::InvalidSurfaceSize { w, h });
+        }
+
+        (w as usize)
+            .checked_mul(h as usize)
+            .ok_or(Error::InvalidSurfaceSize { w, h })
+    }
+
+    pub(crate) fn new(w: u32, h: u32) -> Result<Self> {
+        let n = Self::check_size(w, h)?;
+
+        Ok(Self {
+            w,
+            h,
+            px: vec![0; n],
+            clip_region: None,
+        })
+    }
+
+    pub(crate) fn pixel(&self, x: u32, y: u32) -> u32 {
+        self.px[y as usize * self.w as usize + x as usize]
+    }
+
+    /// Restrict all future drawing operations to the given (x, y, w, h) region of the canvas, or
+    /// remove any existing restriction if `None` is provided.
+    pub(crate) fn set_clip_region(&mut self, region: Option<(i32, i32, u32, u32)>) {
+        self.clip_region = region;
+    }
+
+    // The (x0, y0, x1, y1) bounds of a region after clipping it to the canvas and clip region
+    fn clip(&self, x: i32, y: i32, w: u32, h: u32) -> Option<(u32, u32, u32, u32)> {
+        let (cx, cy, cw, ch) = self.clip_region.unwrap_or((0, 0, self.w, self.h));
+        let x0 = (x as i64).max(cx as i64).max(0);
+        let y0 = (y as i64).max(cy as i64).max(0);
+        let x1 = (x as i64 + w as i64)
+            .min(cx as i64 + cw as i64)
+            .min(self.w as i64);
+        let y1 = (y as i64 + h as i64)
+            .min(cy as i64 + ch as i64)
+            .min(self.h as i64);
+
+        if x1 <= x0 || y1 <= y0 {
+            None
+        } else {
+            Some((x0 as u32, y0 as u32, x1 as u32, y1 as u32))
+        }
+    }
+
+    fn apply(&mut self, x: i32, y: i32, w: u32, h: u32, mut f: impl FnMut(u32, u32, &mut u32)) {
+        if let Some((x0, y0, x1, y1)) = self.clip(x, y, w, h) {
+            for py in y0..y1 {
+                let row = py as usize * self.w as usize;
+                for px in x0..x1 {
+                    f(px, py, &mut self.px[row + px as usize]);
+                }
+            }
+        }
+    }
+
+    /// Replace the contents of a region with the given pixel value.
+    pub(crate) fn fill_rect(&mut self, x: i32, y: i32, w: u32, h: u32, c: u32) {
+        self.apply(x, y, w, h, |_, _, p| *p = c);
+    }
+
+    /// Composite a premultiplied color over the contents of a region.
+    pub(crate) fn blend_rect(&mut self, x: i32, y: i32, w: u32, h: u32, c: u32) {
+        self.apply(x, y, w, h, |_, _, p| *p = over(c, *p));
+    }
+
+    /// Draw a one pixel wide outline with its outer edges at x+w and y+h (matching the behaviour
+    /// of XDrawRectangle).
+    pub(crate) fn draw_rect(&mut self, x: i32, y: i32, w: u32, h: u32, c: u32) {
+        self.fill_rect(x, y, w + 1, 1, c);
+        self.fill_rect(x, y + h as i32, w + 1, 1, c);
+        self.fill_rect(x, y, 1, h + 1, c);
+        self.fill_rect(x + w as i32, y, 1, h + 1, c);
+    }
+
+    /// Fill a polygon using the even-odd rule, filling pixels whose centers lie inside of it.
+    pub(crate) fn fill_polygon(&mut self, points: &[(i32, i32)], c: u32) {
+        let (ymin, ymax) = match (
+            points.iter().map(|p| p.1).min(),
+            points.iter().map(|p| p.1).max(),
+        ) {
+            (Some(ymin), Some(ymax)) => (ymin.max(0), ymax.min(self.h as i32)),
+            _ => return,
+        };
+
+        let mut xs = Vec::with_capacity(points.len());
+        for y in ymin..ymax {
+            let sy = y as f64 + 0.5;
+            xs.clear();
+
+            for (i, &(px, py)) in points.iter().enumerate() {
+                let (qx, qy) = points[(i + 1) % points.len()];
+                if (py as f64 <= sy) != (qy as f64 <= sy) {
+                    let t = (sy - py as f64) / (qy - py) as f64;
+                    xs.push(px as f64 + t * (qx - px) as f64);
+                }
+            }
+
+            xs.sort_by(|a, b| a.total_cmp(b));
+            for span in xs.chunks_exact(2) {
+                let x0 = (span[0] - 0.5).ceil() as i32;
+                let x1 = (span[1] - 0.5).ceil() as i32;
+                if x1 > x0 {
+                    self.fill_rect(x0, y, (x1 - x0) as u32, 1, c);
+                }
+            }
+        }
+    }
+
+    /// Composite a premultiplied color over a region using an 8-bit coverage mask (as produced
+    /// when rasterizing glyphs).
+    pub(crate) fn blend_mask(&mut self, x: i32, y: i32, w: u32, h: u32, mask: &[u8], c: u32) {
+        self.apply(x, y, w, h, |px, py, p| {
+            let (mx, my) = ((px as i32 - x) as u32, (py as i32 - y) as u32);
+            let coverage = mask[my as usize * w as usize + mx as usize];
+            if coverage > 0 {
+                *p = over(scale(c, coverage as u32), *p);
+            }
+        });
+    }
+
+    /// Replace the contents of a region with premultiplied pixel data.
+    pub(crate) fn blit(&mut self, x: i32, y: i32, w: u32, h: u32, src: &[u32]) {
+        self.apply(x, y, w, h, |px, py, p| {
+            let (sx, sy) = ((px as i32 - x) as u32, (py as i32 - y) as u32);
+            *p = src[sy as usize * w as usize + sx as usize];
+        });
+    }
+
+    /// Composite a linear gradient between two colors over the contents of a region.
+    pub(crate) fn blend_gradient(
+        &mut self,
+        (x, y, w, h): (i32, i32, u32, u32),
+        from: Color,
+        to: Color,
+        vertical: bool,
+    ) {
+        let len = if vertical { h } else { w };
+        let steps = len.saturating_sub(1).max(1) as f64;
+        let colors: Vec<u32> = (0..len)
+            .map(|i| lerp(from, to, i as f64 / steps).premultiplied_argb_u32())
+            .collect();
+
+        self.apply(x, y, w, h, |px, py, p| {
+            let i = if vertical {
+                py as i32 - y
+            } else {
+                px as i32 - x
+            };
+            *p = over(colors[i as usize], *p);
+        });
+    }
+}
+
+// Linear interpolation between two colors in non-premultiplied space
+fn lerp(from: Color, to: Color, t: f64) -> Color {
+    let (r1, g1, b1, a1) = from.rgba();
+    let (r2, g2, b2, a2) = to.rgba();
+    let mix = |a: f64, b: f64| a + (b - a) * t;
+
+    Color::from((mix(r1, r2), mix(g1, g2), mix(b1, b2), mix(a1, a2)))
+}
+
+// Scale all four channels of a premultiplied pixel by n/255
+fn scale(c: u32, n: u32) -> u32 {
+    let channel = |shift: u32| ((((c >> shift) & 0xff) * n / 255) & 0xff) << shift;
+
+    channel(24) | channel(16) | channel(8) | channel(0)
+}
+
+/// Porter-Duff "over" for premultiplied ARGB pixels
+pub(crate) fn over(src: u32, dst: u32) -> u32 {
+    let inv_a = 255 - (src >> 24);
+    let channel = |shift: u32| {
+        let (s, d) = ((src >> shift) & 0xff, (dst >> shift) & 0xff);
+        ((s + d * inv_a / 255) & 0xff) << shift
+    };
+
+    channel(24) | channel(16) | channel(8) | channel(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    const RED: u32 = 0xffff0000;
+    const BLUE: u32 = 0xff0000ff;
+
+    // The coordinates of every pixel in the canvas that has been set to `c`
+    fn pixels_matching(canvas: &Canvas, c: u32) -> Vec<(u32, u32)> {
+        (0..canvas.h)
+            .flat_map(|y| (0..canvas.w).map(move |x| (x, y)))
+            .filter(|&(x, y)| canvas.pixel(x, y) == c)
+            .collect()
+    }
+
+    #[test]
+    fn oversized_canvases_are_an_error() {
+        assert!(Canvas::new(u32::MAX, u32::MAX).is_err());
+        assert!(Canvas::new(MAX_DIMENSION + 1, 1).is_err());
+        assert!(Canvas::new(3, 2).is_ok());
+    }
+
+    #[test]
+    fn fill_rect_sets_pixels_in_the_region() {
+        let mut canvas = Canvas::new(4, 4).unwrap();
+        canvas.fill_rect(1, 1, 2, 2, RED);
+
+        assert_eq!(
+            pixels_matching(&canvas, RED),
+            vec![(1, 1), (2, 1), (1, 2), (2, 2)]
+        );
+    }
+
+    #[test_case(-2, -2, 3, 3, vec![(0, 0)]; "top left")]
+    #[test_case(2, 2, 10, 10, vec![(2, 2)]; "bottom right")]
+    #[test_case(-5, 1, 100, 1, vec![(0, 1), (1, 1), (2, 1)]; "wider than the canvas")]
+    #[test_case(3, 0, 2, 2, vec![]; "outside right")]
+    #[test_case(0, -3, 2, 2, vec![]; "outside top")]
+    #[test_case(0, 0, u32::MAX, 1, vec![(0, 0), (1, 0), (2, 0)]; "huge width")]
+    #[test]
+    fn fill_rect_is_clipped_to_the_canvas(
+        x: i32,
+        y: i32,
+        w: u32,
+        h: u32,
+        expected: Vec<(u32, u32)>,
+    ) {
+        let mut canvas = Canvas::new(3, 3).unwrap();
+        canvas.fill_rect(x, y, w, h, RED);
+
+        assert_eq!(pixels_matching(&canvas, RED), expected);
+    }
+
+    #[test_case((0, 0, 2, 2), vec![(0, 0), (1, 0), (0, 1), (1, 1)]; "inside the canvas")]
+    #[test_case((-1, 2, 3, 5), vec![(0, 2), (1, 2)]; "overlapping the canvas edge")]
+    #[test_case((5, 5, 2, 2), vec![]; "outside the canvas")]
+    #[test]
+    fn drawing_is_restricted_to_the_clip_region(
+        region: (i32, i32, u32, u32),
+        expected: Vec<(u32, u32)>,
+    ) {
+        let mut canvas = Canvas::new(3, 3).unwrap();
+        canvas.set_clip_region(Some(region));
+        canvas.fill_rect(0, 0, 3, 3, RED);
+
+        assert_eq!(pixels_matching(&canvas, RED), expected);
+
+        canvas.set_clip_region(None);
+        canvas.fill_rect(0, 0, 3, 3, BLUE);
+
+        assert_eq!(pixels_matching(&canvas, BLUE).len(), 9);
+    }
+
+    #[test]
+    fn draw_rect_includes_the_far_edges() {
+        let mut canvas = Canvas::new(4, 4).unwrap();
+        canvas.draw_rect(0, 0, 2, 2, RED);
+
+        assert_eq!(
+            pixels_matching(&canvas, RED),
+            vec![
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (0, 1),
+                (2, 1),
+                (0, 2),
+                (1, 2),
+                (2, 2)
+            ]
+        );
+    }
+
+    #[test_case(RED, BLUE, RED; "opaque source replaces")]
+    #[test_case(0, BLUE, BLUE; "transparent source is a no-op")]
+    #[test_case(0x80800000, BLUE, 0xff80007f; "half transparent source mixes")]
+    #[test_case(0x80800000, 0, 0x80800000; "over transparent keeps source")]
+    #[test]
+    fn over_composites_premultiplied_pixels(src: u32, dst: u32, expected: u32) {
+        assert_eq!(over(src, dst), expected);
+    }
+
+    #[test]
+    fn blend_rect_composites_over_existing_pixels() {
+        let mut canvas = Canvas::new(2, 1).unwrap();
+        canvas.fill_rect(0, 0, 2, 1, BLUE);
+        canvas.blend_rect(1, 0, 1, 1, 0x80800000);
+
+        assert_eq!(canvas.pixel(0, 0), BLUE);
+        assert_eq!(canvas.pixel(1, 0), 0xff80007f);
+    }
+
+    #[test]
+    fn fill_polygon_fills_pixel_centers_inside_the_polygon() {
+        let mut canvas = Canvas::new(4, 4).unwrap();
+        canvas.fill_polygon(&[(0, 0), (4, 0), (0, 4)], RED);
+
+        assert_eq!(
+            pixels_matching(&canvas, RED),
+            vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (0, 2)]
+        );
+    }
+
+    #[test]
+    fn fill_polygon_is_clipped_to_the_canvas() {
+        let mut canvas = Canvas::new(2, 2).unwrap();
+        canvas.fill_polygon(&[(-10, -10), (10, -10), (10, 10), (-10, 10)], RED);
+
+        assert_eq!(pixels_matching(&canvas, RED).len(), 4);
+    }
+
+    #[test]
+    fn fill_polygon_with_no_points_is_a_no_op() {
+        let mut canvas = Canvas::new(2, 2).unwrap();
+        canvas.fill_polygon(&[], RED);
+
+        assert!(pixels_matching(&canvas, RED).is_empty());
+    }
+
+    #[test]
+    fn blend_mask_scales_the_color_by_coverage() {
+        let mut canvas = Canvas::new(3, 1).unwrap();
+        canvas.blend_mask(0, 0, 3, 1, &[0, 255, 0x80], RED);
+
+        assert_eq!(canvas.pixel(0, 0), 0);
+        assert_eq!(canvas.pixel(1, 0), RED);
+        assert_eq!(canvas.pixel(2, 0), 0x80800000);
+    }
+
+    #[test]
+    fn blend_mask_is_clipped_to_the_canvas() {
+        let mut canvas = Canvas::new(2, 2).unwrap();
+        canvas.blend_mask(-1, -1, 2, 2, &[1, 2, 3, 255], RED);
+
+        assert_eq!(pixels_matching(&canvas, RED), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn blit_copies_the_visible_part_of_the_source() {
+        let mut canvas = Canvas::new(2, 2).unwrap();
+        canvas.blit(1, 1, 2, 2, &[RED, BLUE, BLUE, BLUE]);
+
+        assert_eq!(pixels_matching(&canvas, RED), vec![(1, 1)]);
+        assert!(pixels_matching(&canvas, BLUE).is_empty());
+    }
+
+    #[test]
+    fn gradients_run_between_the_two_colors() {
+        let mut canvas = Canvas::new(3, 2).unwrap();
+        let (from, to) = (
+            Color::new_from_hex(0xff0000ff),
+            Color::new_from_hex(0x0000ffff),
+        );
+        canvas.blend_gradient((0, 0, 3, 2), from, to, false);
+
+        for y in 0..2 {
+            assert_eq!(canvas.pixel(0, y), RED);
+            assert_eq!(canvas.pixel(2, y), BLUE);
+        }
+    }
+}
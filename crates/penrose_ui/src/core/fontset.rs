@@ -1,43 +1,73 @@
-use crate::{core::SCREEN, Error, Result};
+use crate::{Error, Result};
 use fontconfig_sys::{
-    constants::{FC_CHARSET, FC_SCALABLE},
-    FcCharSetAddChar, FcCharSetCreate, FcCharSetDestroy, FcConfig, FcConfigSubstitute,
-    FcDefaultSubstitute, FcMatchPattern, FcPatternAddBool, FcPatternAddCharSet, FcPatternDestroy,
-    FcPatternDuplicate,
+    constants::{FC_CHARSET, FC_DPI, FC_FILE, FC_INDEX, FC_PIXEL_SIZE, FC_SCALABLE, FC_SIZE},
+    FcChar8, FcCharSetAddChar, FcCharSetCreate, FcCharSetDestroy, FcConfig, FcConfigSubstitute,
+    FcDefaultSubstitute, FcFontMatch, FcMatchPattern, FcNameParse, FcPattern, FcPatternAddBool,
+    FcPatternAddCharSet, FcPatternAddDouble, FcPatternDestroy, FcPatternDuplicate,
+    FcPatternGetDouble, FcPatternGetInteger, FcPatternGetString, FcResult, FcResultMatch,
 };
+use fontdue::{FontSettings, Metrics};
 use std::{
-    alloc::{alloc, dealloc, handle_alloc_error, Layout},
     collections::HashMap,
-    ffi::CString,
-};
-use tracing::error;
-use x11::{
-    xft::{
-        FcPattern, FcResult, XftCharExists, XftFont, XftFontClose, XftFontMatch, XftFontOpenName,
-        XftFontOpenPattern, XftNameParse, XftTextExtentsUtf8,
-    },
-    xlib::Display,
-    xrender::XGlyphInfo,
+    ffi::{CStr, CString},
 };
+use tracing::{error, trace};
+
+// Widget content tends to be drawn repeatedly with little variation so this is generous enough to
+// hold everything being rendered by a typical status bar while still bounding memory use for
+// content that is constantly changing (such as a clock).
+const MAX_CACHED_EXTENTS: usize = 1024;
 
 #[derive(Debug)]
 pub(crate) struct Fontset {
-    dpy: *mut Display,
+    dpi: f64,
     primary: Font,
     fallback: Vec<Font>,
     char_cache: HashMap<char, FontMatch>,
+    extent_cache: HashMap<FontMatch, HashMap<String, (u32, u32)>>,
 }
 
 impl Fontset {
-    pub(crate) fn try_new(dpy: *mut Display, fnt: &str) -> Result<Self> {
+    pub(crate) fn try_new(fnt: &str, dpi: f64) -> Result<Self> {
         Ok(Self {
-            dpy,
-            primary: Font::try_new_from_name(dpy, fnt)?,
+            dpi,
+            primary: Font::try_new_from_name(fnt, dpi)?,
             fallback: Default::default(),
             char_cache: Default::default(),
+            extent_cache: Default::default(),
         })
     }
 
+    /// The extent of a chunk of text rendered using a single font as returned by
+    /// `per_font_chunks`, caching the result to avoid repeatedly computing glyph metrics.
+    pub(crate) fn chunk_extent(&mut self, chunk: &str, fm: FontMatch) -> (u32, u32) {
+        if let Some(ext) = self.extent_cache.get(&fm).and_then(|c| c.get(chunk)) {
+            return *ext;
+        }
+
+        let ext = self.fnt(fm).get_exts(chunk);
+        let cache = self.extent_cache.entry(fm).or_default();
+        if cache.len() >= MAX_CACHED_EXTENTS {
+            trace!(?fm, "clearing text extent cache");
+            cache.clear();
+        }
+        cache.insert(chunk.to_string(), ext);
+
+        ext
+    }
+
+    /// The combined extent of the given text, making use of fallback fonts as needed.
+    pub(crate) fn text_extent(&mut self, txt: &str) -> (u32, u32) {
+        let (mut w, mut h) = (0, 0);
+        for (chunk, fm) in self.per_font_chunks(txt) {
+            let (cw, ch) = self.chunk_extent(chunk, fm);
+            w += cw;
+            h = h.max(ch);
+        }
+
+        (w, h)
+    }
+
     // Find boundaries where we need to change the font we are using for rendering utf8
     // characters from the given input.
     pub(crate) fn per_font_chunks<'a>(&mut self, txt: &'a str) -> Vec<(&'a str, FontMatch)> {
@@ -76,24 +106,31 @@ impl Fontset {
         }
     }
 
+    pub(crate) fn fnt_mut(&mut self, fm: FontMatch) -> &mut Font {
+        match fm {
+            FontMatch::Primary => &mut self.primary,
+            FontMatch::Fallback(n) => &mut self.fallback[n],
+        }
+    }
+
     fn fnt_for_char(&mut self, c: char) -> FontMatch {
         if let Some(fm) = self.char_cache.get(&c) {
             return *fm;
         }
 
-        if self.primary.contains_char(self.dpy, c) {
+        if self.primary.contains_char(c) {
             self.char_cache.insert(c, FontMatch::Primary);
             return FontMatch::Primary;
         }
 
         for (i, fnt) in self.fallback.iter().enumerate() {
-            if fnt.contains_char(self.dpy, c) {
+            if fnt.contains_char(c) {
                 self.char_cache.insert(c, FontMatch::Fallback(i));
                 return FontMatch::Fallback(i);
             }
         }
 
-        let fallback = match self.primary.fallback_for_char(self.dpy, c) {
+        let fallback = match self.primary.fallback_for_char(c, self.dpi) {
             Ok(fnt) => {
                 self.fallback.push(fnt);
                 FontMatch::Fallback(self.fallback.len() - 1)
@@ -113,154 +150,201 @@ impl Fontset {
 
 impl Drop for Fontset {
     fn drop(&mut self) {
-        // SAFETY: the Display we have a pointer to is freed by the parent draw
+        // SAFETY: the patterns being freed were created by fontconfig and are known to be non-null
         unsafe {
-            FcPatternDestroy(self.primary.pattern as _);
-            XftFontClose(self.dpy, self.primary.xfont);
+            FcPatternDestroy(self.primary.pattern);
             for f in self.fallback.drain(..) {
-                FcPatternDestroy(f.pattern as _);
-                XftFontClose(self.dpy, f.xfont);
+                FcPatternDestroy(f.pattern);
             }
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub(crate) enum FontMatch {
     Primary,
     Fallback(usize),
 }
 
-// Fonts contain a resource that requires a Display to free on Drop so they
-// are owned by their parent Draw and cleaned up when the Draw is dropped
+// Fonts are located using fontconfig and then loaded from disk for rasterizing with fontdue. The
+// fontconfig pattern used to select the font is retained so that it can be used as the base for
+// locating fallback fonts, and is freed when the parent Fontset is dropped.
 //
-// https://man.archlinux.org/man/extra/libxft/XftFontMatch.3.en
 // https://refspecs.linuxfoundation.org/fontconfig-2.6.0/index.html
 #[derive(Debug)]
 pub(crate) struct Font {
     pub(crate) h: u32,
-    pub(crate) xfont: *mut XftFont,
+    pub(crate) ascent: i32,
+    px: f32,
+    font: fontdue::Font,
     pattern: *mut FcPattern,
+    glyphs: HashMap<char, (Metrics, Vec<u8>)>,
 }
 
 impl Font {
-    fn try_new_from_name(dpy: *mut Display, name: &str) -> Result<Self> {
+    fn try_new_from_name(name: &str, dpi: f64) -> Result<Self> {
         let c_name = CString::new(name)?;
 
         // SAFETY:
         // - Null pointers are checked and explicitly converted to Rust Errors
-        // - Raw pointer dereferences are only carried out after checking for null pointers
-        let (xfont, pattern, h) = unsafe {
-            let xfont = XftFontOpenName(dpy, SCREEN, c_name.as_ptr());
-            if xfont.is_null() {
-                return Err(Error::UnableToOpenFont(name.to_string()));
-            }
-
-            let pattern = XftNameParse(c_name.as_ptr());
+        // - valid constant values from the fontconfig_sys crate are used for C string parameters
+        let pattern = unsafe {
+            let pattern = FcNameParse(c_name.as_ptr() as *const FcChar8);
             if pattern.is_null() {
-                XftFontClose(dpy, xfont);
                 return Err(Error::UnableToParseFontPattern(name.to_string()));
             }
+            FcPatternAddDouble(pattern, FC_DPI.as_ptr(), dpi);
+            // Only outline fonts can be rasterized by fontdue
+            FcPatternAddBool(pattern, FC_SCALABLE.as_ptr(), 1); // FcTrue=1
 
-            let h = (*xfont).ascent + (*xfont).descent;
-
-            (xfont, pattern, h as u32)
+            pattern
         };
 
-        Ok(Font { xfont, pattern, h })
+        match fc_font_match(pattern).and_then(|m| Font::try_new_from_match(pattern, m, dpi)) {
+            Some(fnt) => Ok(fnt),
+            None => {
+                // SAFETY: pattern is known to be non-null
+                unsafe { FcPatternDestroy(pattern) };
+                Err(Error::UnableToOpenFont(name.to_string()))
+            }
+        }
     }
 
-    fn try_new_from_pattern(dpy: *mut Display, pattern: *mut FcPattern) -> Result<Self> {
+    // Load the font file identified by a pattern returned from FcFontMatch, taking ownership of
+    // the base pattern used to locate it. The matched pattern is always freed.
+    fn try_new_from_match(pattern: *mut FcPattern, m: *mut FcPattern, dpi: f64) -> Option<Self> {
         // SAFETY:
-        // - Null pointers are checked and explicitly converted to Rust Errors
-        // - Raw pointer dereferences are only carried out after checking for null pointers
-        let (xfont, h) = unsafe {
-            let xfont = XftFontOpenPattern(dpy, pattern);
-            if xfont.is_null() {
-                return Err(Error::UnableToOpenFontPattern);
+        // - m is known to be non-null
+        // - the string returned by FcPatternGetString is owned by m so it is copied before m is
+        //   destroyed
+        let (path, index, px) = unsafe {
+            let mut file: *mut FcChar8 = std::ptr::null_mut();
+            let mut index = 0;
+            let mut px = 0.0;
+
+            let path = if FcPatternGetString(m, FC_FILE.as_ptr(), 0, &mut file) == FcResultMatch {
+                Some(
+                    CStr::from_ptr(file as *const _)
+                        .to_string_lossy()
+                        .into_owned(),
+                )
+            } else {
+                None
+            };
+            FcPatternGetInteger(m, FC_INDEX.as_ptr(), 0, &mut index);
+            if FcPatternGetDouble(m, FC_PIXEL_SIZE.as_ptr(), 0, &mut px) != FcResultMatch {
+                let mut pt = 0.0;
+                FcPatternGetDouble(m, FC_SIZE.as_ptr(), 0, &mut pt);
+                px = pt * dpi / 72.0;
             }
+            FcPatternDestroy(m);
 
-            let h = (*xfont).ascent + (*xfont).descent;
-
-            (xfont, h as u32)
+            (path?, index, px as f32)
         };
 
-        Ok(Font { xfont, pattern, h })
+        let bytes = std::fs::read(&path).ok()?;
+        let settings = FontSettings {
+            collection_index: index as u32,
+            scale: px,
+            ..Default::default()
+        };
+        let font = match fontdue::Font::from_bytes(bytes, settings) {
+            Ok(font) => font,
+            Err(e) => {
+                error!(%path, %e, "unable to load font");
+                return None;
+            }
+        };
+        let lm = font.horizontal_line_metrics(px)?;
+        let ascent = lm.ascent.ceil() as i32;
+        let h = (ascent + (-lm.descent).ceil() as i32) as u32;
+
+        Some(Font {
+            h,
+            ascent,
+            px,
+            font,
+            pattern,
+            glyphs: HashMap::new(),
+        })
     }
 
-    fn contains_char(&self, dpy: *mut Display, c: char) -> bool {
-        // SAFETY: self.xfont is known to be non-null
-        unsafe { XftCharExists(dpy, self.xfont, c as u32) == 1 }
+    fn contains_char(&self, c: char) -> bool {
+        self.font.lookup_glyph_index(c) != 0
     }
 
-    pub(crate) fn get_exts(&self, dpy: *mut Display, txt: &str) -> Result<(u32, u32)> {
-        // SAFETY:
-        // - allocation failures are explicitly handled
-        // - invalid C strings are converted to Rust Errors
-        // - self.xfont is known to be non-null
-        unsafe {
-            // https://doc.rust-lang.org/std/alloc/trait.GlobalAlloc.html#tymethod.alloc
-            let layout = Layout::new::<XGlyphInfo>();
-            let ptr = alloc(layout);
-            if ptr.is_null() {
-                handle_alloc_error(layout);
-            }
-            let ext = ptr as *mut XGlyphInfo;
-
-            let c_str = CString::new(txt)?;
-            XftTextExtentsUtf8(
-                dpy,
-                self.xfont,
-                c_str.as_ptr() as *mut u8,
-                c_str.as_bytes().len() as i32,
-                ext,
-            );
-
-            let x_off = (*ext).xOff as u32;
-            dealloc(ext as *mut u8, layout);
+    // Glyph advances are rounded to whole pixels so that each glyph is rasterized aligned to
+    // the pixel grid.
+    pub(crate) fn advance(&self, c: char) -> u32 {
+        self.font.metrics(c, self.px).advance_width.round() as u32
+    }
 
-            Ok((x_off, self.h))
-        }
+    pub(crate) fn get_exts(&self, txt: &str) -> (u32, u32) {
+        (txt.chars().map(|c| self.advance(c)).sum(), self.h)
     }
 
-    /// Find a font that can handle a given character using fontconfig and this font's pattern
-    fn fallback_for_char(&self, dpy: *mut Display, c: char) -> Result<Self> {
-        let pat = self.fc_font_match(dpy, c)?;
+    /// The metrics and coverage bitmap for a given character, rasterizing it on first use.
+    pub(crate) fn glyph(&mut self, c: char) -> &(Metrics, Vec<u8>) {
+        let (font, px) = (&self.font, self.px);
 
-        Font::try_new_from_pattern(dpy, pat)
+        self.glyphs
+            .entry(c)
+            .or_insert_with(|| font.rasterize(c, px))
     }
 
-    fn fc_font_match(&self, dpy: *mut Display, c: char) -> Result<*mut FcPattern> {
+    /// Find a font that can handle a given character using fontconfig and this font's pattern
+    fn fallback_for_char(&self, c: char, dpi: f64) -> Result<Self> {
         // SAFETY:
-        // - allocation failures are explicitly handled
-        // - Null pointers are checked and explicitly converted to Rust Errors
         // - valid constant values from the fontconfig_sys crate are used for C string parameters
-        // - null pointer parameter for FcConfigSubstutute config param (first argument) is valid
-        //   as documented here: https://man.archlinux.org/man/extra/fontconfig/FcConfigSubstitute.3.en
-        unsafe {
+        // - the charset is copied into the pattern so it can be destroyed immediately
+        let pattern = unsafe {
             let charset = FcCharSetCreate();
             FcCharSetAddChar(charset, c as u32);
 
             let pat = FcPatternDuplicate(self.pattern as *const _);
             FcPatternAddCharSet(pat, FC_CHARSET.as_ptr(), charset);
-            FcPatternAddBool(pat, FC_SCALABLE.as_ptr(), 1); // FcTrue=1
-
-            FcConfigSubstitute(std::ptr::null::<FcConfig>() as *mut _, pat, FcMatchPattern);
-            FcDefaultSubstitute(pat);
-
-            let mut res = FcResult::NoMatch;
-
-            // Passing the pointer from fontconfig_sys to x11 here
-            let fc_pattern = XftFontMatch(dpy, SCREEN, pat as *const _, &mut res as *mut _);
-
             FcCharSetDestroy(charset);
-            FcPatternDestroy(pat);
 
-            if fc_pattern.is_null() {
+            pat
+        };
+
+        match fc_font_match(pattern).and_then(|m| Font::try_new_from_match(pattern, m, dpi)) {
+            Some(fnt) if fnt.contains_char(c) => Ok(fnt),
+            Some(fnt) => {
+                // SAFETY: the font's pattern is known to be non-null
+                unsafe { FcPatternDestroy(fnt.pattern) };
                 Err(Error::NoFallbackFontForChar(c))
-            } else {
-                Ok(fc_pattern as *mut _)
             }
+            None => {
+                // SAFETY: pattern is known to be non-null
+                unsafe { FcPatternDestroy(pattern) };
+                Err(Error::NoFallbackFontForChar(c))
+            }
+        }
+    }
+}
+
+// Find the best matching font on the system for a given pattern. The returned pattern needs to
+// be freed by the caller.
+fn fc_font_match(pattern: *mut FcPattern) -> Option<*mut FcPattern> {
+    // SAFETY:
+    // - the pattern being matched is duplicated so that it is not modified by substitution
+    // - null pointer parameters for the config param (first argument) of FcConfigSubstitute and
+    //   FcFontMatch are valid as documented here:
+    //   https://man.archlinux.org/man/extra/fontconfig/FcConfigSubstitute.3.en
+    unsafe {
+        let pat = FcPatternDuplicate(pattern as *const _);
+        FcConfigSubstitute(std::ptr::null_mut::<FcConfig>(), pat, FcMatchPattern);
+        FcDefaultSubstitute(pat);
+
+        let mut res: FcResult = FcResultMatch;
+        let m = FcFontMatch(std::ptr::null_mut::<FcConfig>(), pat, &mut res);
+        FcPatternDestroy(pat);
+
+        if m.is_null() {
+            None
+        } else {
+            Some(m)
         }
     }
 }
@@ -0,0 +1,256 @@
+//! Raster images for rendering using a [Context][crate::Context].
+//!
+//! Images are held as raw ARGB pixel data which is the format used for the `_NET_WM_ICON`
+//! property of client windows. Decoding PNG files requires enabling the `png` feature of this
+//! crate.
+use super::canvas::over;
+use crate::{Error, Result};
+use penrose::Color;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+// Icons are read from properties set by arbitrary clients so we need an upper bound on the size
+// of image that we are willing to allocate and scale.
+const MAX_PIXELS: usize = 4096 * 4096;
+
+fn pixel_count(w: u32, h: u32) -> Option<usize> {
+    (w as usize)
+        .checked_mul(h as usize)
+        .filter(|&n| n > 0 && n <= MAX_PIXELS)
+}
+
+/// A raster image held as ARGB pixel data.
+///
+/// See the `draw_image` method of [Context][crate::Context] for details of how images are
+/// rendered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    w: u32,
+    h: u32,
+    argb: Vec<u32>,
+    key: u64, // used to cache the scaled pixels for this image
+}
+
+impl Image {
+    /// Construct a new [Image] from ARGB pixel data in row major order.
+    ///
+    /// ### Errors
+    /// This method will error if the number of pixels provided does not match the requested
+    /// width and height, or if the image is empty or unreasonably large.
+    pub fn from_argb(w: u32, h: u32, argb: Vec<u32>) -> Result<Self> {
+        if pixel_count(w, h) != Some(argb.len()) {
+            return Err(Error::InvalidImageData {
+                w,
+                h,
+                len: argb.len(),
+            });
+        }
+
+        let mut hasher = DefaultHasher::new();
+        (w, h, &argb).hash(&mut hasher);
+
+        Ok(Self {
+            w,
+            h,
+            argb,
+            key: hasher.finish(),
+        })
+    }
+
+    /// Select an image from the contents of a `_NET_WM_ICON` property.
+    ///
+    /// The property may contain multiple icons of different sizes: the smallest icon that is at
+    /// least `size` pixels high is selected, falling back to the largest icon available if none
+    /// are big enough. Returns `None` if the property does not contain any valid icons.
+    pub fn from_net_wm_icon(data: &[u32], size: u32) -> Option<Self> {
+        let mut icons = Vec::new();
+        let mut rest = data;
+
+        while let [w, h, tail @ ..] = rest {
+            let n = match pixel_count(*w, *h) {
+                Some(n) if tail.len() >= n => n,
+                _ => break,
+            };
+            icons.push((*w, *h, &tail[..n]));
+            rest = &tail[n..];
+        }
+
+        let (w, h, argb) = icons
+            .iter()
+            .filter(|(_, h, _)| *h >= size)
+            .min_by_key(|(_, h, _)| *h)
+            .or_else(|| icons.iter().max_by_key(|(_, h, _)| *h))?;
+
+        Self::from_argb(*w, *h, argb.to_vec()).ok()
+    }
+
+    /// Decode an [Image] from the raw bytes of a PNG file.
+    #[cfg(feature = "png")]
+    pub fn from_png_bytes(bytes: &[u8]) -> Result<Self> {
+        use png::{BitDepth, ColorType, Decoder, Transformations};
+
+        let mut decoder = Decoder::new(bytes);
+        decoder.set_transformations(Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+
+        if info.bit_depth != BitDepth::Eight {
+            return Err(Error::UnsupportedImageFormat);
+        }
+
+        let bytes = &buf[..info.buffer_size()];
+        let argb = match info.color_type {
+            ColorType::Rgba => bytes
+                .chunks(4)
+                .map(|p| argb(p[3], p[0], p[1], p[2]))
+                .collect(),
+            ColorType::Rgb => bytes
+                .chunks(3)
+                .map(|p| argb(0xff, p[0], p[1], p[2]))
+                .collect(),
+            ColorType::GrayscaleAlpha => bytes
+                .chunks(2)
+                .map(|p| argb(p[1], p[0], p[0], p[0]))
+                .collect(),
+            ColorType::Grayscale => bytes.iter().map(|&p| argb(0xff, p, p, p)).collect(),
+            ColorType::Indexed => return Err(Error::UnsupportedImageFormat),
+        };
+
+        return Self::from_argb(info.width, info.height, argb);
+
+        fn argb(a: u8, r: u8, g: u8, b: u8) -> u32 {
+            u32::from_be_bytes([a, r, g, b])
+        }
+    }
+
+    /// Decode an [Image] from a PNG file on disk.
+    #[cfg(feature = "png")]
+    pub fn from_png_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        Self::from_png_bytes(&std::fs::read(path)?)
+    }
+
+    /// The width of this image in pixels.
+    pub fn width(&self) -> u32 {
+        self.w
+    }
+
+    /// The height of this image in pixels.
+    pub fn height(&self) -> u32 {
+        self.h
+    }
+
+    pub(crate) fn key(&self) -> u64 {
+        self.key
+    }
+
+    // Scale to the requested size using nearest neighbour sampling
+    fn scaled(&self, w: u32, h: u32) -> impl Iterator<Item = u32> + '_ {
+        (0..h)
+            .flat_map(move |y| (0..w).map(move |x| (x, y)))
+            .map(move |(x, y)| {
+                let sx = x as usize * self.w as usize / w as usize;
+                let sy = y as usize * self.h as usize / h as usize;
+                self.argb[sy * self.w as usize + sx]
+            })
+    }
+
+    // Scale to the requested size, compositing the image over the provided (possibly
+    // transparent) background color to give premultiplied ARGB pixels.
+    pub(crate) fn premultiplied_argb_pixels(&self, w: u32, h: u32, bg: Color) -> Vec<u32> {
+        let bg = bg.premultiplied_argb_u32();
+
+        self.scaled(w, h)
+            .map(|p| over(premultiply(p), bg))
+            .collect()
+    }
+}
+
+fn premultiply(argb: u32) -> u32 {
+    let [a, r, g, b] = argb.to_be_bytes();
+    let mul = |c: u8| (c as u32 * a as u32 / 255) as u8;
+
+    u32::from_be_bytes([a, mul(r), mul(g), mul(b)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn icon(w: u32, h: u32, fill: u32) -> Vec<u32> {
+        let mut data = vec![w, h];
+        data.extend(std::iter::repeat_n(fill, (w * h) as usize));
+
+        data
+    }
+
+    #[test]
+    fn from_argb_rejects_mismatched_data() {
+        assert!(Image::from_argb(2, 2, vec![0; 3]).is_err());
+        assert!(Image::from_argb(2, 2, vec![0; 4]).is_ok());
+    }
+
+    #[test]
+    fn from_argb_rejects_empty_images() {
+        assert!(Image::from_argb(0, 2, vec![]).is_err());
+        assert!(Image::from_argb(2, 0, vec![]).is_err());
+    }
+
+    #[test]
+    fn from_argb_rejects_overflowing_sizes() {
+        assert!(Image::from_argb(u32::MAX, u32::MAX, vec![0; 1]).is_err());
+        assert!(Image::from_argb(1 << 16, 1 << 16, vec![0; 0]).is_err());
+    }
+
+    #[test]
+    fn truncated_icon_data_is_ignored() {
+        let mut data = icon(2, 2, 1);
+        data.extend([4, 4, 2, 2, 2]);
+
+        let img = Image::from_net_wm_icon(&data, 4).unwrap();
+
+        assert_eq!((img.width(), img.height()), (2, 2));
+        assert_eq!(Image::from_net_wm_icon(&[4, 4, 1, 2], 4), None);
+        assert_eq!(Image::from_net_wm_icon(&[4], 4), None);
+    }
+
+    #[test]
+    fn zero_sized_icons_are_ignored() {
+        assert_eq!(Image::from_net_wm_icon(&[0, 0], 16), None);
+        assert_eq!(Image::from_net_wm_icon(&[0, 4, 1, 1, 1, 1], 16), None);
+    }
+
+    #[test]
+    fn overflowing_icon_sizes_are_ignored() {
+        let data = [u32::MAX, u32::MAX, 1, 2, 3];
+        assert_eq!(Image::from_net_wm_icon(&data, 16), None);
+
+        let data = [1 << 16, 1 << 16, 1, 2, 3];
+        assert_eq!(Image::from_net_wm_icon(&data, 16), None);
+    }
+
+    #[test]
+    fn the_smallest_icon_at_least_the_requested_size_is_picked() {
+        let mut data = icon(8, 8, 1);
+        data.extend(icon(32, 32, 2));
+        data.extend(icon(16, 16, 3));
+
+        let img = Image::from_net_wm_icon(&data, 12).unwrap();
+        assert_eq!((img.width(), img.height()), (16, 16));
+
+        let img = Image::from_net_wm_icon(&data, 4).unwrap();
+        assert_eq!((img.width(), img.height()), (8, 8));
+    }
+
+    #[test]
+    fn the_largest_icon_is_picked_if_none_are_big_enough() {
+        let mut data = icon(8, 8, 1);
+        data.extend(icon(16, 16, 2));
+
+        let img = Image::from_net_wm_icon(&data, 64).unwrap();
+
+        assert_eq!((img.width(), img.height()), (16, 16));
+    }
+}
@@ -13,34 +13,39 @@
 //!   [1]: crate::bar::widgets::Widget
 use crate::{Error, Result};
 use penrose::{
+    extensions::util::theme::Theme,
     pure::geometry::{Point, Rect},
     x::{WinType, XConn},
     x11rb::RustConn,
     Color, Xid,
 };
 use std::{
-    alloc::{alloc, dealloc, handle_alloc_error, Layout},
-    cmp::max,
+    cell::RefCell,
+    cmp::{max, min},
     collections::{hash_map::Entry, HashMap},
-    ffi::CString,
 };
-use tracing::{debug, info};
-use x11::{
-    xft::{XftColor, XftColorAllocName, XftDraw, XftDrawCreate, XftDrawDestroy, XftDrawStringUtf8},
-    xlib::{
-        CapButt, Complex, CoordModeOrigin, Display, Drawable, False, JoinMiter, LineSolid, Window,
-        XCopyArea, XCreateGC, XCreatePixmap, XDefaultColormap, XDefaultDepth, XDefaultVisual,
-        XDrawRectangle, XFillPolygon, XFillRectangle, XFreeGC, XFreePixmap, XOpenDisplay, XPoint,
-        XSetForeground, XSetGraphicsExposures, XSetLineAttributes, XSync, GC,
-    },
+use tracing::{debug, error, info, warn};
+use x11rb::{
+    connection::Connection,
+    errors::ConnectionError,
+    image::Image as XImage,
+    protocol::xproto::{AtomEnum, ConnectionExt, CreateGCAux, Gcontext, VisualClass},
+    rust_connection::RustConnection,
 };
 
+mod canvas;
 mod fontset;
+pub mod image;
+
+use canvas::Canvas;
 use fontset::Fontset;
+use image::Image;
 
-// Xlib manual: https://www.x.org/releases/current/doc/libX11/libX11/libX11.pdf
+// The DPI used by Xft when it is unable to determine one from the X server
+const DEFAULT_DPI: f64 = 96.0;
 
-pub(crate) const SCREEN: i32 = 0;
+// Past this many damaged regions we copy their bounding box rather than each one individually
+const MAX_DAMAGE_RECTS: usize = 16;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 /// A set of styling options for a text string that is to be rendered using [Draw].
@@ -56,23 +61,286 @@ pub struct TextStyle {
     pub padding: (u32, u32),
 }
 
+/// Text using the foreground, background and padding of a [Theme].
+impl From<&Theme> for TextStyle {
+    fn from(theme: &Theme) -> Self {
+        Self {
+            fg: theme.fg,
+            bg: Some(theme.bg),
+            padding: theme.padding,
+        }
+    }
+}
+
+/// Strips of color drawn beneath and above rendered text, such as those commonly used to indicate
+/// the active workspace in a status bar.
+///
+/// Each strip is specified as a thickness in pixels along with the color it should be drawn in.
+/// See the `draw_text_decoration` method of [Context] for details of how they are rendered.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct TextDecoration {
+    /// A strip drawn along the bottom edge of the text.
+    pub underline: Option<(u32, Color)>,
+    /// A strip drawn along the top edge of the text.
+    pub overline: Option<(u32, Color)>,
+}
+
+impl TextDecoration {
+    /// Construct a [TextDecoration] containing only an underline.
+    pub fn underline(thickness: u32, color: impl Into<Color>) -> Self {
+        Self {
+            underline: Some((thickness, color.into())),
+            overline: None,
+        }
+    }
+
+    /// Construct a [TextDecoration] containing only an overline.
+    pub fn overline(thickness: u32, color: impl Into<Color>) -> Self {
+        Self {
+            underline: None,
+            overline: Some((thickness, color.into())),
+        }
+    }
+
+    /// Whether or not this decoration will render anything.
+    pub fn is_empty(&self) -> bool {
+        self.underline.is_none() && self.overline.is_none()
+    }
+}
+
+/// A section of text with its own styling to be rendered as part of a larger string using
+/// the `draw_rich_text` method of [Context].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    /// The text to be rendered.
+    pub txt: String,
+    /// The foreground color to be used for rendering the text itself.
+    pub fg: Color,
+    /// The background color for the region behind the text (left unfilled if None).
+    pub bg: Option<Color>,
+    /// A font name and point size to use in place of the active font of the [Draw].
+    pub font: Option<(String, u8)>,
+}
+
+impl Span {
+    /// Construct a new [Span] using the active font and no background color.
+    pub fn new(txt: impl Into<String>, fg: impl Into<Color>) -> Self {
+        Self {
+            txt: txt.into(),
+            fg: fg.into(),
+            bg: None,
+            font: None,
+        }
+    }
+
+    /// Set a background color for this span.
+    pub fn with_bg(mut self, bg: impl Into<Color>) -> Self {
+        self.bg = Some(bg.into());
+
+        self
+    }
+
+    /// Render this span using the given font rather than the active font of the [Draw].
+    ///
+    /// See the top level docs for [Draw] for details on how fonts are specified.
+    pub fn with_font(mut self, font: impl Into<String>, point_size: u8) -> Self {
+        self.font = Some((font.into(), point_size));
+
+        self
+    }
+}
+
+/// The shape of a separator drawn between segments of a UI using the `draw_separator` method of
+/// [Context].
+///
+/// Each shape is filled with the foreground color given when drawing and is intended to be placed
+/// at the boundary between two segments: the foreground color being that of the segment the
+/// separator extends from and the background color being that of the segment it extends into.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Separator {
+    /// A triangle pointing to the right (the classic powerline arrow).
+    ArrowRight,
+    /// A triangle pointing to the left.
+    ArrowLeft,
+    /// A slant running from the bottom left to the top right.
+    SlantForward,
+    /// A slant running from the top left to the bottom right.
+    SlantBackward,
+    /// A semi-elliptical cap bulging to the right.
+    RoundRight,
+    /// A semi-elliptical cap bulging to the left.
+    RoundLeft,
+}
+
+// The number of line segments used to approximate the curve of rounded separators.
+const ROUND_SEGMENTS: u32 = 16;
+
+impl Separator {
+    /// The vertices of the polygon for this separator when drawn within a region of the given
+    /// width and height, relative to the top left corner of that region.
+    pub fn points(&self, w: u32, h: u32) -> Vec<Point> {
+        match self {
+            Self::ArrowRight => vec![Point::new(0, 0), Point::new(w, h / 2), Point::new(0, h)],
+            Self::ArrowLeft => vec![Point::new(w, 0), Point::new(0, h / 2), Point::new(w, h)],
+            Self::SlantForward => vec![Point::new(0, 0), Point::new(w, 0), Point::new(0, h)],
+            Self::SlantBackward => vec![Point::new(0, 0), Point::new(w, h), Point::new(0, h)],
+            Self::RoundRight | Self::RoundLeft => {
+                let (rx, ry) = (w as f64, h as f64 / 2.0);
+                (0..=ROUND_SEGMENTS)
+                    .map(|i| {
+                        let theta = std::f64::consts::PI * i as f64 / ROUND_SEGMENTS as f64;
+                        let dx = (rx * theta.sin()).round() as u32;
+                        let y = (ry - ry * theta.cos()).round() as u32;
+                        let x = if *self == Self::RoundRight {
+                            dx
+                        } else {
+                            w - dx
+                        };
+
+                        Point::new(x, y)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Surface {
-    drawable: Drawable,
-    gc: GC,
+    id: Xid,
+    gc: Gcontext,
     r: Rect,
-    id: u64,
+    canvas: Canvas,
+    damage: RefCell<Vec<Rect>>, // regions of the canvas modified since the last flush
 }
 
 impl Surface {
-    /// SAFETY: dpy must be non-null
-    pub(crate) unsafe fn flush(&self, dpy: *mut Display) {
+    fn new(id: Xid, gc: Gcontext, r: Rect) -> Result<Self> {
+        Ok(Self {
+            id,
+            gc,
+            r,
+            canvas: Canvas::new(r.w, r.h)?,
+            damage: RefCell::new(Vec::new()),
+        })
+    }
+
+    // Record a region of the canvas as needing to be uploaded to the window on the next flush,
+    // clipped to the bounds of the surface.
+    fn damage(&self, x: i32, y: i32, w: u32, h: u32) {
+        let (x0, y0) = (max(x, 0) as i64, max(y, 0) as i64);
+        let x1 = min(x as i64 + w as i64, self.r.w as i64);
+        let y1 = min(y as i64 + h as i64, self.r.h as i64);
+        if x1 <= x0 || y1 <= y0 {
+            return;
+        }
+
+        let r = Rect::new(x0 as u32, y0 as u32, (x1 - x0) as u32, (y1 - y0) as u32);
+        let mut damage = self.damage.borrow_mut();
+        if damage.iter().any(|d| d.contains(&r)) {
+            return;
+        }
+        damage.retain(|d| !r.contains(d));
+        damage.push(r);
+
+        if damage.len() > MAX_DAMAGE_RECTS {
+            let bounds = bounding_rect(&damage);
+            damage.clear();
+            damage.push(bounds);
+        }
+    }
+
+    fn damage_all(&self) {
         let Rect { w, h, .. } = self.r;
-        XCopyArea(dpy, self.drawable, self.id, self.gc, 0, 0, w, h, 0, 0);
-        XSync(dpy, False);
+        let mut damage = self.damage.borrow_mut();
+        damage.clear();
+        damage.push(Rect::new(0, 0, w, h));
+    }
+
+    /// Upload any damaged regions of the canvas to the window.
+    fn flush(&self, conn: &RustConnection, depth: u8) -> Result<()> {
+        // Windows without an alpha channel only make use of the low 24 bits of each pixel
+        let mask = if depth == 32 { u32::MAX } else { 0x00ffffff };
+
+        for Rect { x, y, w, h } in self.damage.borrow_mut().drain(..) {
+            let mut img = XImage::allocate_native(w as u16, h as u16, depth, conn.setup())
+                .map_err(|e| penrose::Error::from(ConnectionError::from(e)))?;
+
+            for dy in 0..h {
+                for dx in 0..w {
+                    let p = self.canvas.pixel(x + dx, y + dy) & mask;
+                    img.put_pixel(dx as u16, dy as u16, p);
+                }
+            }
+
+            img.put(conn, *self.id, self.gc, x as i16, y as i16)
+                .map_err(penrose::Error::from)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Check for a 32-bit TrueColor visual that can be used for rendering with transparency
+fn has_argb_visual(conn: &RustConnection) -> bool {
+    conn.setup().roots[0]
+        .allowed_depths
+        .iter()
+        .filter(|d| d.depth == 32)
+        .flat_map(|d| d.visuals.iter())
+        .any(|v| v.class == VisualClass::TRUE_COLOR)
+}
+
+// Determine the DPI to use for rendering fonts in the same way as Xft: using the value of the
+// Xft.dpi X resource if it is set and otherwise computing it from the physical size of the screen.
+fn screen_dpi(conn: &RustConnection) -> f64 {
+    let screen = &conn.setup().roots[0];
+    let resources = conn
+        .get_property(
+            false,
+            screen.root,
+            AtomEnum::RESOURCE_MANAGER,
+            AtomEnum::STRING,
+            0,
+            u32::MAX,
+        )
+        .ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .map(|r| String::from_utf8_lossy(&r.value).into_owned())
+        .unwrap_or_default();
+
+    let xft_dpi = resources
+        .lines()
+        .find_map(|l| l.strip_prefix("Xft.dpi:"))
+        .and_then(|s| s.trim().parse().ok());
+
+    match xft_dpi {
+        Some(dpi) => dpi,
+        None if screen.height_in_millimeters > 0 => {
+            screen.height_in_pixels as f64 * 25.4 / screen.height_in_millimeters as f64
+        }
+        None => DEFAULT_DPI,
     }
 }
 
+fn bounding_rect(rects: &[Rect]) -> Rect {
+    let x0 = rects.iter().map(|r| r.x).min().unwrap_or(0);
+    let y0 = rects.iter().map(|r| r.y).min().unwrap_or(0);
+    let x1 = rects.iter().map(|r| r.x + r.w).max().unwrap_or(0);
+    let y1 = rects.iter().map(|r| r.y + r.h).max().unwrap_or(0);
+
+    Rect::new(x0, y0, x1 - x0, y1 - y0)
+}
+
+/// The direction in which a color gradient is rendered by [Context::fill_gradient].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GradientDirection {
+    /// From the left edge of the region to the right
+    Horizontal,
+    /// From the top edge of the region to the bottom
+    Vertical,
+}
+
 /// A minimal back end for rendering simple text based UIs.
 ///
 /// > **NOTE**: Your application should create a single [Draw] struct to manage the windows and
@@ -81,14 +349,14 @@ impl Surface {
 ///
 /// # Fonts
 /// ### Specifying fonts
-/// Font names need to be in a form that can be parsed by `xft`. The simplest way to find the valid
-/// font names on your system is via the `fc-list` program like so:
+/// Font names need to be in a form that can be parsed by `fontconfig`. The simplest way to find the
+/// valid font names on your system is via the `fc-list` program like so:
 /// ```sh
 /// $ fc-list -f '%{family}\n' | sort -u
 /// ```
 /// [Draw] will automtically append `:size={point_size}` to the font name when loading the font via
-/// xft. The [Arch wiki page on fonts][0] is a useful resource on how X11 fonts work if you are
-/// interested in futher reading.
+/// fontconfig. Only scalable (TrueType and OpenType) fonts are supported. The [Arch wiki page on
+/// fonts][0] is a useful resource on how X11 fonts work if you are interested in futher reading.
 ///
 /// ### Font fallback for missing glyphs
 /// [Draw] makes use of [fontconfig][1] to locate appropriate fallback fonts on your system when a
@@ -96,6 +364,13 @@ impl Surface {
 /// fonts are selected you will need to modify your [font-conf][2] (the Arch wiki has a [good page][3]
 /// on how to do this if you are looking for a reference).
 ///
+/// # Transparency
+/// If the background color provided when creating a [Draw] is not fully opaque then windows will
+/// be created using a 32-bit ARGB visual (if one is available) so that their contents can be
+/// rendered with transparency when a compositor is running. Drawing operations using colors with
+/// an alpha channel then write that alpha directly to the window: see the `fill_rect_alpha` and
+/// `fill_gradient` methods of [Context] for blending colors over existing content instead.
+///
 /// # Example usage
 /// > Please see the crate [examples directory][4] for more examples.
 /// ```no_run
@@ -132,59 +407,96 @@ impl Surface {
 #[derive(Debug)]
 pub struct Draw {
     pub(crate) conn: RustConn,
-    dpy: *mut Display,
+    depth: u8,
+    argb: bool,
+    dpi: f64,
     fss: HashMap<String, Fontset>,
     bg: Color,
     surfaces: HashMap<Xid, Surface>,
-    colors: HashMap<Color, XColor>,
+    images: HashMap<ImageKey, Vec<u32>>,
     active_font: String,
 }
 
 impl Drop for Draw {
     fn drop(&mut self) {
-        // SAFETY: all pointers being freed are known to be non-null
-        unsafe {
-            for (_, s) in self.surfaces.drain() {
-                XFreePixmap(self.dpy, s.drawable);
-                XFreeGC(self.dpy, s.gc);
-            }
+        let conn = self.conn.connection();
+        for (_, s) in self.surfaces.drain() {
+            _ = conn.free_gc(s.gc);
         }
+        _ = conn.flush();
     }
 }
 
+// An image id along with the size and background color it was rendered with
+type ImageKey = (u64, u32, u32, Color);
+
 fn font_key(font: &str, point_size: u8) -> String {
     format!("{font}:size={point_size}")
 }
 
+// Fonts requested by spans are loaded on first use and cached alongside those held by the
+// parent Draw so that they are available for future contexts.
+fn fontset<'a>(
+    fss: &'a mut HashMap<String, Fontset>,
+    active_font: &str,
+    font: Option<&(String, u8)>,
+    dpi: f64,
+) -> Result<&'a mut Fontset> {
+    let k = match font {
+        Some((font, point_size)) => font_key(font, *point_size),
+        None => return Ok(fss.get_mut(active_font).expect("active_font to be present")),
+    };
+
+    match fss.entry(k) {
+        Entry::Occupied(e) => Ok(e.into_mut()),
+        Entry::Vacant(e) => {
+            let fs = Fontset::try_new(e.key(), dpi)?;
+            Ok(e.insert(fs))
+        }
+    }
+}
+
 impl Draw {
     /// Construct a new [Draw] instance using the specified font and background color.
     ///
     /// ### Font names
     /// See the top level docs for [Draw] for details on how fonts are specified.
     ///
+    /// ### Transparency
+    /// If `bg` is not fully opaque then windows will be created using an ARGB visual if one is
+    /// available. See the top level docs for [Draw] for details.
+    ///
     /// ### Errors
     /// This method will error if it is unable to establish a connection with the X server.
     pub fn new(font: &str, point_size: u8, bg: impl Into<Color>) -> Result<Self> {
         let conn = RustConn::new()?;
-        // SAFETY:
-        //   - passing NULL as the argument here is valid as documented here: https://man.archlinux.org/man/extra/libx11/XOpenDisplay.3.en
-        let dpy = unsafe { XOpenDisplay(std::ptr::null()) };
-        let mut colors = HashMap::new();
         let bg = bg.into();
-        colors.insert(bg, XColor::try_new(dpy, &bg)?);
+
+        let argb = bg.alpha() < 0xff && has_argb_visual(conn.connection());
+        if bg.alpha() < 0xff && !argb {
+            warn!("no ARGB visual available: falling back to the default visual");
+        }
+        let depth = if argb {
+            32
+        } else {
+            conn.connection().setup().roots[0].root_depth
+        };
+        let dpi = screen_dpi(conn.connection());
 
         let k = font_key(font, point_size);
-        let fs = Fontset::try_new(dpy, &k)?;
+        let fs = Fontset::try_new(&k, dpi)?;
         let mut fss = HashMap::new();
         fss.insert(k.clone(), fs);
 
         Ok(Self {
             conn,
-            dpy,
+            depth,
+            argb,
+            dpi,
             fss,
             surfaces: HashMap::new(),
             bg,
-            colors,
+            images: HashMap::new(),
             active_font: k,
         })
     }
@@ -194,36 +506,33 @@ impl Draw {
         &self.conn
     }
 
+    /// Whether or not windows created by this [Draw] use a 32-bit ARGB visual in order to
+    /// support transparency.
+    pub fn is_argb(&self) -> bool {
+        self.argb
+    }
+
     /// Create a new X window with an initialised surface for drawing.
     ///
     /// Destroying this window should be carried out using the `destroy_window_and_surface` method
     /// so that the associated graphics state is also cleaned up correctly.
     pub fn new_window(&mut self, ty: WinType, r: Rect, managed: bool) -> Result<Xid> {
+        let ty = match ty {
+            WinType::InputOutput(a) if self.argb => WinType::InputOutputArgb(a),
+            ty => ty,
+        };
+
         info!(?ty, ?r, %managed, "creating new window");
+        Canvas::check_size(r.w, r.h)?;
         let id = self.conn.create_window(ty, r, managed)?;
 
-        debug!("initialising graphics context and pixmap");
-        let root = *self.conn.root() as Window;
-        // SAFETY: self.dpy is non-null and screen index 0 is always valid
-        let (drawable, gc) = unsafe {
-            let depth = XDefaultDepth(self.dpy, SCREEN) as u32;
-            let drawable = XCreatePixmap(self.dpy, root, r.w, r.h, depth);
-            let gc = XCreateGC(self.dpy, root, 0, std::ptr::null_mut());
-            XSetLineAttributes(self.dpy, gc, 1, LineSolid, CapButt, JoinMiter);
-            XSetGraphicsExposures(self.dpy, gc, False);
-
-            (drawable, gc)
-        };
+        debug!("initialising graphics context and surface");
+        let conn = self.conn.connection();
+        let gc = conn.generate_id().map_err(penrose::Error::from)?;
+        conn.create_gc(gc, *id, &CreateGCAux::new().graphics_exposures(0))
+            .map_err(penrose::Error::from)?;
 
-        self.surfaces.insert(
-            id,
-            Surface {
-                id: *id as u64,
-                r,
-                gc,
-                drawable,
-            },
-        );
+        self.surfaces.insert(id, Surface::new(id, gc, r)?);
 
         Ok(id)
     }
@@ -232,12 +541,11 @@ impl Draw {
     /// within this draw.
     pub fn destroy_window_and_surface(&mut self, id: Xid) -> Result<()> {
         if let Some(s) = self.surfaces.remove(&id) {
+            self.conn
+                .connection()
+                .free_gc(s.gc)
+                .map_err(penrose::Error::from)?;
             self.conn.destroy_window(id)?;
-            // SAFETY: the pointers being freed are known to be non-null
-            unsafe {
-                XFreePixmap(self.dpy, s.drawable);
-                XFreeGC(self.dpy, s.gc);
-            }
         }
 
         Ok(())
@@ -246,13 +554,22 @@ impl Draw {
     pub(crate) fn add_font(&mut self, font: &str, point_size: u8) -> Result<()> {
         let k = font_key(font, point_size);
         if let Entry::Vacant(e) = self.fss.entry(k) {
-            let fs = Fontset::try_new(self.dpy, e.key())?;
+            let fs = Fontset::try_new(e.key(), self.dpi)?;
             e.insert(fs);
         }
 
         Ok(())
     }
 
+    /// Set the background color used when clearing windows created by this [Draw].
+    ///
+    /// Whether or not windows are created using an ARGB visual is fixed when the [Draw] is
+    /// created, so transparency in `bg` is only respected if the original background color was
+    /// also transparent.
+    pub fn set_bg(&mut self, bg: impl Into<Color>) {
+        self.bg = bg.into();
+    }
+
     /// Set the font being used for rendering text and clear the existing cache of fallback fonts
     /// for characters that are not supported by the primary font.
     pub fn set_font(&mut self, font: &str, point_size: u8) -> Result<()> {
@@ -262,6 +579,28 @@ impl Draw {
         Ok(())
     }
 
+    /// Determine the width and height taken up by a given string in pixels when rendered using
+    /// the active font.
+    ///
+    /// This is equivalent to the `text_extent` method of [Context] but does not require a
+    /// window to have been created in order to use it.
+    pub fn text_extent(&mut self, txt: &str) -> Result<(u32, u32)> {
+        Ok(self
+            .fss
+            .get_mut(&self.active_font)
+            .expect("active_font to be present")
+            .text_extent(txt))
+    }
+
+    /// Free the pixel data used to cache images that have been rendered by contexts created by
+    /// this [Draw].
+    ///
+    /// Rendered images are cached for the lifetime of the [Draw] so this method can be used to
+    /// release the associated memory if your application renders many different images.
+    pub fn clear_image_cache(&mut self) {
+        self.images.clear();
+    }
+
     /// Retrieve the drawing [Context] for the given window `Xid`.
     ///
     /// This method will error if the requested id does not already have an initialised surface.
@@ -269,28 +608,48 @@ impl Draw {
     pub fn context_for(&mut self, id: Xid) -> Result<Context<'_>> {
         let s = self
             .surfaces
-            .get(&id)
+            .get_mut(&id)
             .ok_or(Error::UnintialisedSurface { id })?;
+        s.canvas.set_clip_region(None);
 
         Ok(Context {
             dx: 0,
             dy: 0,
-            dpy: self.dpy,
+            conn: self.conn.connection(),
+            depth: self.depth,
+            argb: self.argb,
+            dpi: self.dpi,
             s,
             bg: self.bg,
-            fs: self
-                .fss
-                .get_mut(&self.active_font)
-                .expect("active_font to be present"),
-            colors: &mut self.colors,
+            fss: &mut self.fss,
+            active_font: &self.active_font,
+            images: &mut self.images,
         })
     }
 
+    /// Mark the entire surface for the given window as needing to be copied to the screen on
+    /// the next call to `flush`.
+    ///
+    /// Only the regions of a surface that have been drawn to since the last flush are copied to
+    /// the window, so this should be called when the window contents need restoring after being
+    /// exposed (for example, when handling an [Expose][penrose::x::event::ExposeEvent] event).
+    pub fn damage_all(&self, id: Xid) -> Result<()> {
+        let s = self
+            .surfaces
+            .get(&id)
+            .ok_or(Error::UnintialisedSurface { id })?;
+        s.damage_all();
+
+        Ok(())
+    }
+
     /// Flush any pending requests to the X server and map the specifed window to the screen.
+    ///
+    /// Only the regions of the surface that have been drawn to since the previous flush are
+    /// copied to the window.
     pub fn flush(&self, id: Xid) -> Result<()> {
         if let Some(s) = self.surfaces.get(&id) {
-            // SAFETY: self.dpy is non-null
-            unsafe { s.flush(self.dpy) };
+            s.flush(self.conn.connection(), self.depth)?;
             self.conn.map(id)?;
             self.conn.flush();
         };
@@ -301,7 +660,7 @@ impl Draw {
 
 /// A minimal drawing context for rendering text based UI elements
 ///
-/// A [Context] provides you with a backing pixmap for rendering your UI using simple offset and
+/// A [Context] provides you with a backing surface for rendering your UI using simple offset and
 /// rendering operations. By default, the context will be positioned in the top left corner of
 /// the parent window created by your [Draw]. You can use the `translate` and `set/reset` offset
 /// methods to modify where the next drawing operation will take place.
@@ -315,11 +674,15 @@ impl Draw {
 pub struct Context<'a> {
     dx: i32,
     dy: i32,
-    dpy: *mut Display,
-    s: &'a Surface,
+    conn: &'a RustConnection,
+    depth: u8,
+    argb: bool,
+    dpi: f64,
+    s: &'a mut Surface,
     bg: Color,
-    fs: &'a mut Fontset,
-    colors: &'a mut HashMap<Color, XColor>,
+    fss: &'a mut HashMap<String, Fontset>,
+    active_font: &'a str,
+    images: &'a mut HashMap<ImageKey, Vec<u32>>,
 }
 
 impl<'a> Context<'a> {
@@ -356,221 +719,440 @@ impl<'a> Context<'a> {
         self.dy = 0;
     }
 
-    fn get_or_try_init_xcolor(&mut self, c: Color) -> Result<*mut XftColor> {
-        if let Some(xc) = self.colors.get(&c) {
-            return Ok(xc.0);
-        }
+    /// The current (x, y) offset being applied to drawing operations.
+    pub fn offset(&self) -> (i32, i32) {
+        (self.dx, self.dy)
+    }
 
-        let xc = XColor::try_new(self.dpy, &c)?;
-        let ptr = xc.0;
-        self.colors.insert(c, xc);
+    /// Restrict future drawing operations to the given region, positioned relative to the
+    /// current offset. Anything drawn outside of the region is discarded.
+    pub fn set_clip(&mut self, Rect { x, y, w, h }: Rect) {
+        let (x, y) = (self.dx + x as i32, self.dy + y as i32);
+        self.s.canvas.set_clip_region(Some((x, y, w, h)));
+    }
+
+    /// Remove any clip region set using `set_clip`.
+    pub fn reset_clip(&mut self) {
+        self.s.canvas.set_clip_region(None);
+    }
 
-        Ok(ptr)
+    /// The id of the window that this context is rendering to.
+    pub fn window(&self) -> Xid {
+        self.s.id
+    }
+
+    // The pixel value written to the surface for a color. Without an ARGB visual the alpha
+    // channel of the window is ignored so colors are written as fully opaque.
+    fn pixel(&self, c: Color) -> u32 {
+        if self.argb {
+            c.premultiplied_argb_u32()
+        } else {
+            c.argb_u32() | 0xff000000
+        }
     }
 
     /// Render a rectangular border using the supplied color.
     pub fn draw_rect(&mut self, Rect { x, y, w, h }: Rect, color: Color) -> Result<()> {
-        let xcol = self.get_or_try_init_xcolor(color)?;
         let (x, y) = (self.dx + x as i32, self.dy + y as i32);
-
-        // SAFETY:
-        //   - the pointers for self.dpy, s.drawable, s.gc are known to be non-null
-        //   - xcol is known to be non-null so dereferencing is safe
-        unsafe {
-            XSetForeground(self.dpy, self.s.gc, (*xcol).pixel);
-            XDrawRectangle(self.dpy, self.s.drawable, self.s.gc, x, y, w, h);
-        }
+        let p = self.pixel(color);
+        self.s.canvas.draw_rect(x, y, w, h, p);
+        self.s.damage(x, y, w + 1, h + 1);
 
         Ok(())
     }
 
     /// Render a filled rectangle using the supplied color.
     pub fn fill_rect(&mut self, Rect { x, y, w, h }: Rect, color: Color) -> Result<()> {
-        let xcol = self.get_or_try_init_xcolor(color)?;
         let (x, y) = (self.dx + x as i32, self.dy + y as i32);
+        let p = self.pixel(color);
+        self.s.canvas.fill_rect(x, y, w, h, p);
+        self.s.damage(x, y, w, h);
 
-        // SAFETY:
-        //   - the pointers for self.dpy, s.drawable, s.gc are known to be non-null
-        //   - xcol is known to be non-null so dereferencing is safe
-        unsafe {
-            XSetForeground(self.dpy, self.s.gc, (*xcol).pixel);
-            XFillRectangle(self.dpy, self.s.drawable, self.s.gc, x, y, w, h);
+        Ok(())
+    }
+
+    /// Render a filled rectangle by blending the supplied color over the existing contents of
+    /// the surface using its alpha channel.
+    ///
+    /// In contrast, `fill_rect` replaces the contents of the region with the supplied color which
+    /// (for a [Draw] using an ARGB visual) allows for making regions of a window transparent.
+    pub fn fill_rect_alpha(&mut self, Rect { x, y, w, h }: Rect, color: Color) -> Result<()> {
+        let (x, y) = (self.dx + x as i32, self.dy + y as i32);
+        self.s
+            .canvas
+            .blend_rect(x, y, w, h, color.premultiplied_argb_u32());
+        self.s.damage(x, y, w, h);
+
+        Ok(())
+    }
+
+    /// Render a linear gradient between two colors, blended over the existing contents of the
+    /// surface using their alpha channels.
+    pub fn fill_gradient(
+        &mut self,
+        Rect { x, y, w, h }: Rect,
+        from: Color,
+        to: Color,
+        direction: GradientDirection,
+    ) -> Result<()> {
+        if w == 0 || h == 0 {
+            return Ok(());
         }
 
+        let (x, y) = (self.dx + x as i32, self.dy + y as i32);
+        let vertical = direction == GradientDirection::Vertical;
+        self.s
+            .canvas
+            .blend_gradient((x, y, w, h), from, to, vertical);
+        self.s.damage(x, y, w, h);
+
         Ok(())
     }
 
     /// Render a filled rectangle using the supplied color.
     pub fn fill_polygon(&mut self, points: &[Point], color: Color) -> Result<()> {
-        let xcol = self.get_or_try_init_xcolor(color)?;
-        let mut xpoints: Vec<XPoint> = points
+        let points: Vec<(i32, i32)> = points
             .iter()
-            .map(|&Point { x, y }| XPoint {
-                x: self.dx as i16 + x as i16,
-                y: self.dy as i16 + y as i16,
-            })
+            .map(|&Point { x, y }| (self.dx + x as i32, self.dy + y as i32))
+            .collect();
+        let p = self.pixel(color);
+        self.s.canvas.fill_polygon(&points, p);
+
+        if let (Some(x0), Some(y0), Some(x1), Some(y1)) = (
+            points.iter().map(|p| p.0).min(),
+            points.iter().map(|p| p.1).min(),
+            points.iter().map(|p| p.0).max(),
+            points.iter().map(|p| p.1).max(),
+        ) {
+            self.s
+                .damage(x0, y0, (x1 - x0 + 1) as u32, (y1 - y0 + 1) as u32);
+        }
+
+        Ok(())
+    }
+
+    /// Render a [Separator] within the given region using the supplied color, first filling the
+    /// region with `bg` if it is provided.
+    pub fn draw_separator(
+        &mut self,
+        sep: Separator,
+        r: Rect,
+        fg: Color,
+        bg: Option<Color>,
+    ) -> Result<()> {
+        if let Some(bg) = bg {
+            self.fill_rect(r, bg)?;
+        }
+
+        let points: Vec<Point> = sep
+            .points(r.w, r.h)
+            .into_iter()
+            .map(|p| Point::new(p.x + r.x, p.y + r.y))
             .collect();
 
-        // SAFETY:
-        //   - the pointers for self.dpy, s.drawable, s.gc are known to be non-null
-        //   - xcol is known to be non-null so dereferencing is safe
-        unsafe {
-            XSetForeground(self.dpy, self.s.gc, (*xcol).pixel);
-            XFillPolygon(
-                self.dpy,
-                self.s.drawable,
-                self.s.gc,
-                &mut xpoints[0] as *mut _,
-                points.len() as i32,
-                Complex, // we could be smarter here but for now this works
-                CoordModeOrigin,
-            );
+        self.fill_polygon(&points, fg)
+    }
+
+    /// Render the underline and overline strips of a [TextDecoration] along the bottom and top
+    /// edges of a region with the given width and height at the current context offset.
+    pub fn draw_text_decoration(
+        &mut self,
+        decoration: TextDecoration,
+        w: u32,
+        h: u32,
+    ) -> Result<()> {
+        if let Some((thickness, color)) = decoration.overline {
+            self.fill_rect(Rect::new(0, 0, w, thickness.min(h)), color)?;
+        }
+
+        if let Some((thickness, color)) = decoration.underline {
+            let thickness = thickness.min(h);
+            self.fill_rect(Rect::new(0, h - thickness, w, thickness), color)?;
         }
 
         Ok(())
     }
 
+    /// Render an [Image] scaled to fill the given region.
+    ///
+    /// Transparent pixels in the image are blended against `bg`, or the background color of
+    /// this Context if None. Rendered images are cached by the parent [Draw] so subsequent calls
+    /// to render the same image at the same size are cheap.
+    pub fn draw_image(&mut self, img: &Image, r: Rect, bg: Option<Color>) -> Result<()> {
+        if r.w == 0 || r.h == 0 {
+            return Ok(());
+        }
+
+        let bg = bg.unwrap_or(self.bg);
+        let pixels = self
+            .images
+            .entry((img.key(), r.w, r.h, bg))
+            .or_insert_with(|| img.premultiplied_argb_pixels(r.w, r.h, bg));
+
+        let (x, y) = (self.dx + r.x as i32, self.dy + r.y as i32);
+        self.s.canvas.blit(x, y, r.w, r.h, pixels);
+        self.s.damage(x, y, r.w, r.h);
+
+        Ok(())
+    }
+
     /// Fill the specified area with this Context's background color
     pub fn fill_bg(&mut self, r: Rect) -> Result<()> {
         self.fill_rect(r, self.bg)
     }
 
-    /// Render the provided text at the current context offset using the supplied color.
-    pub fn draw_text(
+    fn fontset(&mut self, font: Option<&(String, u8)>) -> Result<&mut Fontset> {
+        fontset(self.fss, self.active_font, font, self.dpi)
+    }
+
+    // Render txt with its top left corner at (x, y), returning the extent of the rendered text.
+    fn render_str(
         &mut self,
         txt: &str,
-        h_offset: u32,
-        padding: (u32, u32),
+        font: Option<&(String, u8)>,
+        (mut x, y): (i32, i32),
         c: Color,
     ) -> Result<(u32, u32)> {
-        // SAFETY:
-        //   - the pointers for self.dpy and s.drawable are known to be non-null
-        //   - we wrap the returned pointer in DropXftDraw to ensure that we correctly destroy
-        //     the XftDraw we create here (see below)
-        let d = unsafe {
-            XftDrawCreate(
-                self.dpy,
-                self.s.drawable,
-                XDefaultVisual(self.dpy, SCREEN),
-                XDefaultColormap(self.dpy, SCREEN),
-            )
-        };
-
-        let _drop_draw = DropXftDraw { ptr: d };
-
-        let (lpad, rpad) = (padding.0 as i32, padding.1);
-        let (mut x, y) = (lpad + self.dx, self.dy);
-        let (mut total_w, mut total_h) = (x as u32, 0);
-        let xcol = self.get_or_try_init_xcolor(c)?;
-
-        for (chunk, fm) in self.fs.per_font_chunks(txt).into_iter() {
-            let fnt = self.fs.fnt(fm);
-            let (chunk_w, chunk_h) = fnt.get_exts(self.dpy, chunk)?;
-
-            // SAFETY: fnt pointer is non-null
-            let chunk_y = unsafe { y + h_offset as i32 + (*fnt.xfont).ascent };
-            let c_str = CString::new(chunk)?;
-
-            // SAFETY:
-            // - fnt.xfont is known to be non-null
-            // - the string character pointer and length have been obtained from a Rust CString
-            unsafe {
-                XftDrawStringUtf8(
-                    d,
-                    xcol,
-                    fnt.xfont,
-                    x,
-                    chunk_y,
-                    c_str.as_ptr() as *mut _,
-                    c_str.as_bytes().len() as i32,
-                );
+        let x0 = x;
+        let fg = c.premultiplied_argb_u32();
+        let fs = fontset(self.fss, self.active_font, font, self.dpi)?;
+        let (mut total_w, mut total_h) = (0, 0);
+
+        for (chunk, fm) in fs.per_font_chunks(txt).into_iter() {
+            let (chunk_w, chunk_h) = fs.chunk_extent(chunk, fm);
+            let fnt = fs.fnt_mut(fm);
+            let baseline = y + fnt.ascent;
+            let mut pen = x;
+
+            for ch in chunk.chars() {
+                let advance = fnt.advance(ch) as i32;
+                let (m, coverage) = fnt.glyph(ch);
+                // Glyph metrics are relative to the baseline with y increasing upwards
+                let gx = pen + m.xmin;
+                let gy = baseline - m.ymin - m.height as i32;
+                self.s
+                    .canvas
+                    .blend_mask(gx, gy, m.width as u32, m.height as u32, coverage, fg);
+                pen += advance;
             }
 
             x += chunk_w as i32;
             total_w += chunk_w;
             total_h = max(total_h, chunk_h);
         }
+        self.s.damage(x0, y, total_w, total_h);
 
-        return Ok((total_w + rpad, total_h));
+        Ok((total_w, total_h))
+    }
 
-        // There are multiple error paths here where we need to make sure that we correctly destroy
-        // the XftDraw we created. Rather than complicate the error handling we use a Drop wrapper
-        // to ensure that we run XftDrawDestroy when the function returns.
+    /// Render the provided text at the current context offset using the supplied color.
+    pub fn draw_text(
+        &mut self,
+        txt: &str,
+        h_offset: u32,
+        padding: (u32, u32),
+        c: Color,
+    ) -> Result<(u32, u32)> {
+        let (lpad, rpad) = padding;
+        let (x, y) = (lpad as i32 + self.dx, self.dy + h_offset as i32);
+        let (w, h) = self.render_str(txt, None, (x, y), c)?;
 
-        struct DropXftDraw {
-            ptr: *mut XftDraw,
-        }
+        Ok((x as u32 + w + rpad, h))
+    }
 
-        impl Drop for DropXftDraw {
-            fn drop(&mut self) {
-                // SAFETY: the pointer we have must be non-null
-                unsafe { XftDrawDestroy(self.ptr) };
+    /// Render a sequence of individually styled [Span]s at the current context offset as a
+    /// single line of text.
+    ///
+    /// The background of each span (if set) is filled from the current offset down to the
+    /// bottom of the rendered text, including the `h_offset` above it. The returned extent
+    /// matches that of calling `draw_text` with the concatenated text of all of the spans.
+    pub fn draw_rich_text(
+        &mut self,
+        spans: &[Span],
+        h_offset: u32,
+        padding: (u32, u32),
+    ) -> Result<(u32, u32)> {
+        let (_, h) = self.rich_text_extent(spans)?;
+        let (lpad, rpad) = padding;
+        let (mut x, y) = (lpad as i32 + self.dx, self.dy + h_offset as i32);
+
+        for span in spans.iter() {
+            if let Some(bg) = span.bg {
+                let (w, _) = self.span_extent(span)?;
+                let r = Rect::new((x - self.dx) as u32, 0, w, h_offset + h);
+                self.fill_rect(r, bg)?;
             }
+
+            let (w, _) = self.render_str(&span.txt, span.font.as_ref(), (x, y), span.fg)?;
+            x += w as i32;
         }
+
+        Ok((x as u32 + rpad, h))
+    }
+
+    fn span_extent(&mut self, span: &Span) -> Result<(u32, u32)> {
+        Ok(self.fontset(span.font.as_ref())?.text_extent(&span.txt))
     }
 
     /// Determine the width and height taken up by a given string in pixels.
     pub fn text_extent(&mut self, txt: &str) -> Result<(u32, u32)> {
+        Ok(self.fontset(None)?.text_extent(txt))
+    }
+
+    /// Determine the width and height taken up by a sequence of [Span]s in pixels.
+    pub fn rich_text_extent(&mut self, spans: &[Span]) -> Result<(u32, u32)> {
         let (mut w, mut h) = (0, 0);
-        for (chunk, fm) in self.fs.per_font_chunks(txt) {
-            let (cw, ch) = self.fs.fnt(fm).get_exts(self.dpy, chunk)?;
-            w += cw;
-            h = max(h, ch);
+        for span in spans.iter() {
+            let (sw, sh) = self.span_extent(span)?;
+            w += sw;
+            h = max(h, sh);
         }
 
         Ok((w, h))
     }
 
-    /// Flush pending requests to the X server.
+    /// Flush pending requests to the X server, copying any regions drawn to since the last flush
+    /// to the window.
     ///
     /// This method does not need to be called explicitly if the flush method for
     /// the parent [Draw] is being called as well.
     pub fn flush(&self) {
-        // SAFETY: self.dpy is non-null
-        unsafe { self.s.flush(self.dpy) }
+        if let Err(e) = self.s.flush(self.conn, self.depth) {
+            error!(%e, "unable to copy surface contents to window");
+        }
+        _ = self.conn.flush();
     }
 }
 
-#[derive(Debug)]
-struct XColor(*mut XftColor);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
 
-impl Drop for XColor {
-    fn drop(&mut self) {
-        let layout = Layout::new::<XftColor>();
-        // SAFETY: the memory being deallocated was allocated on creation of the XColor
-        unsafe { dealloc(self.0 as *mut u8, layout) }
+    fn pts(points: &[(u32, u32)]) -> Vec<Point> {
+        points.iter().map(|&(x, y)| Point::new(x, y)).collect()
     }
-}
 
-impl XColor {
-    fn try_new(dpy: *mut Display, c: &Color) -> Result<Self> {
-        // SAFETY: this private method is only called with a non-null dpy pointer
-        let inner = unsafe { try_xftcolor_from_name(dpy, &c.as_rgb_hex_string())? };
+    #[test_case(Separator::ArrowRight, pts(&[(0, 0), (10, 10), (0, 20)]); "arrow right")]
+    #[test_case(Separator::ArrowLeft, pts(&[(10, 0), (0, 10), (10, 20)]); "arrow left")]
+    #[test_case(Separator::SlantForward, pts(&[(0, 0), (10, 0), (0, 20)]); "slant forward")]
+    #[test_case(Separator::SlantBackward, pts(&[(0, 0), (10, 20), (0, 20)]); "slant backward")]
+    #[test]
+    fn separator_points(sep: Separator, expected: Vec<Point>) {
+        assert_eq!(sep.points(10, 20), expected);
+    }
 
-        Ok(Self(inner))
+    #[test_case(Separator::RoundRight, 0, 10; "round right")]
+    #[test_case(Separator::RoundLeft, 10, 0; "round left")]
+    #[test]
+    fn rounded_separators_run_from_top_to_bottom_through_the_far_edge(
+        sep: Separator,
+        edge_x: u32,
+        far_x: u32,
+    ) {
+        let points = sep.points(10, 20);
+
+        assert_eq!(points.len(), ROUND_SEGMENTS as usize + 1);
+        assert_eq!(points[0], Point::new(edge_x, 0));
+        assert_eq!(points[ROUND_SEGMENTS as usize / 2], Point::new(far_x, 10));
+        assert_eq!(points[ROUND_SEGMENTS as usize], Point::new(edge_x, 20));
+    }
+
+    #[test]
+    fn separator_points_are_within_the_region() {
+        let separators = [
+            Separator::ArrowRight,
+            Separator::ArrowLeft,
+            Separator::SlantForward,
+            Separator::SlantBackward,
+            Separator::RoundRight,
+            Separator::RoundLeft,
+        ];
+
+        for sep in separators {
+            for (w, h) in [(0, 0), (1, 1), (7, 13), (13, 7)] {
+                for p in sep.points(w, h) {
+                    assert!(p.x <= w && p.y <= h, "{sep:?} {w}x{h}: {p:?}");
+                }
+            }
+        }
+    }
+
+    fn surface(w: u32, h: u32) -> Surface {
+        Surface::new(Xid::from(1), 1, Rect::new(0, 0, w, h)).unwrap()
+    }
+
+    #[test_case(0, 0, 10, 10, vec![Rect::new(0, 0, 10, 10)]; "inside")]
+    #[test_case(-5, -5, 10, 10, vec![Rect::new(0, 0, 5, 5)]; "clipped top left")]
+    #[test_case(95, 45, 10, 10, vec![Rect::new(95, 45, 5, 5)]; "clipped bottom right")]
+    #[test_case(10, 10, u32::MAX, u32::MAX, vec![Rect::new(10, 10, 90, 40)]; "huge size")]
+    #[test_case(100, 0, 10, 10, vec![]; "outside right")]
+    #[test_case(0, -10, 10, 10, vec![]; "outside top")]
+    #[test_case(0, 0, 0, 10, vec![]; "zero width")]
+    #[test]
+    fn damage_is_clipped_to_the_surface(x: i32, y: i32, w: u32, h: u32, expected: Vec<Rect>) {
+        let s = surface(100, 50);
+        s.damage(x, y, w, h);
+
+        assert_eq!(*s.damage.borrow(), expected);
+    }
+
+    #[test]
+    fn damage_already_covered_is_ignored() {
+        let s = surface(100, 50);
+        s.damage(0, 0, 20, 20);
+        s.damage(5, 5, 5, 5);
+
+        assert_eq!(*s.damage.borrow(), vec![Rect::new(0, 0, 20, 20)]);
+    }
+
+    #[test]
+    fn damage_covering_existing_regions_replaces_them() {
+        let s = surface(100, 50);
+        s.damage(5, 5, 5, 5);
+        s.damage(30, 5, 5, 5);
+        s.damage(0, 0, 20, 20);
+
+        assert_eq!(
+            *s.damage.borrow(),
+            vec![Rect::new(30, 5, 5, 5), Rect::new(0, 0, 20, 20)]
+        );
+    }
+
+    #[test]
+    fn damage_is_merged_once_there_are_too_many_regions() {
+        let s = surface(100, 50);
+        for i in 0..=MAX_DAMAGE_RECTS as u32 {
+            s.damage((i * 3) as i32, i as i32, 2, 2);
+        }
+
+        let n = MAX_DAMAGE_RECTS as u32;
+        assert_eq!(*s.damage.borrow(), vec![Rect::new(0, 0, n * 3 + 2, n + 2)]);
+    }
+
+    #[test]
+    fn damage_all_covers_the_whole_surface() {
+        let s = surface(100, 50);
+        s.damage(5, 5, 5, 5);
+        s.damage_all();
+
+        assert_eq!(*s.damage.borrow(), vec![Rect::new(0, 0, 100, 50)]);
     }
-}
 
-unsafe fn try_xftcolor_from_name(dpy: *mut Display, color: &str) -> Result<*mut XftColor> {
-    // https://doc.rust-lang.org/std/alloc/trait.GlobalAlloc.html#tymethod.alloc
-    let layout = Layout::new::<XftColor>();
-    let ptr = alloc(layout);
-    if ptr.is_null() {
-        handle_alloc_error(layout);
-    }
-
-    let c_name = CString::new(color)?;
-    let res = XftColorAllocName(
-        dpy,
-        XDefaultVisual(dpy, SCREEN),
-        XDefaultColormap(dpy, SCREEN),
-        c_name.as_ptr(),
-        ptr as *mut XftColor,
-    );
-
-    if res == 0 {
-        Err(Error::UnableToAllocateColor)
-    } else {
-        Ok(ptr as *mut XftColor)
+    #[test_case(vec![], Rect::new(0, 0, 0, 0); "empty")]
+    #[test_case(vec![Rect::new(1, 2, 3, 4)], Rect::new(1, 2, 3, 4); "single")]
+    #[test_case(
+        vec![Rect::new(10, 0, 5, 5), Rect::new(0, 20, 5, 5)],
+        Rect::new(0, 0, 15, 25);
+        "disjoint"
+    )]
+    #[test_case(
+        vec![Rect::new(0, 0, 10, 10), Rect::new(5, 5, 10, 10)],
+        Rect::new(0, 0, 15, 15);
+        "overlapping"
+    )]
+    #[test]
+    fn bounding_rect_works(rects: Vec<Rect>, expected: Rect) {
+        assert_eq!(bounding_rect(&rects), expected);
     }
 }
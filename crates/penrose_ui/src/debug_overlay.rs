@@ -0,0 +1,201 @@
+//! An overlay for inspecting client positions when developing custom layouts.
+//!
+//! When enabled, a translucent labelled rectangle is drawn over each visible client showing its
+//! [Xid], WM_CLASS and position within its workspace stack. The overlay windows ignore all
+//! mouse input so the window manager can be used as normal while they are shown.
+//!
+//! ```no_run
+//! use penrose::{
+//!     core::{bindings::KeyEventHandler, Config, WindowManager},
+//!     x11rb::RustConn,
+//! };
+//! use penrose_ui::debug_overlay::{toggle_debug_overlay, DebugOverlay};
+//! use std::collections::HashMap;
+//!
+//! let mut key_bindings: HashMap<String, Box<dyn KeyEventHandler<RustConn>>> = HashMap::new();
+//! key_bindings.insert("M-S-d".to_owned(), toggle_debug_overlay());
+//!
+//! let conn = RustConn::new().unwrap();
+//! # let key_bindings = penrose::core::bindings::parse_keybindings_with_xmodmap(key_bindings).unwrap();
+//! let wm = WindowManager::new(Config::default(), key_bindings, HashMap::new(), conn).unwrap();
+//!
+//! let overlay = DebugOverlay::new("mono", 10, 0x458588aa, 0xebdbb2ff, 0x282828ff).unwrap();
+//! let wm = overlay.add_to(wm);
+//! ```
+use crate::{Draw, Result};
+use penrose::{
+    builtin::actions::key_handler,
+    core::{bindings::KeyEventHandler, State, WindowManager},
+    pure::geometry::Rect,
+    x::{Atom, ClientConfig, Prop, WinType, XConn, XConnExt},
+    Color, Xid,
+};
+use tracing::error;
+use x11rb::protocol::{
+    shape::{ConnectionExt, SK, SO},
+    xproto::ClipOrdering,
+};
+
+const PADDING: u32 = 4;
+
+/// A state extension for rendering debug information over each visible client.
+///
+/// See the module level docs for details.
+#[derive(Debug)]
+pub struct DebugOverlay {
+    draw: Draw,
+    fg: Color,
+    label_bg: Color,
+    enabled: bool,
+    windows: Vec<Xid>,
+}
+
+impl DebugOverlay {
+    /// Create a new [DebugOverlay] that is initially hidden.
+    ///
+    /// `fill` is used for the rectangle drawn over each client and should normally be partially
+    /// transparent so that the client can still be seen beneath it (this requires a compositor
+    /// to be running). Labels are drawn using `fg` on top of `label_bg`.
+    pub fn new(
+        font: &str,
+        point_size: u8,
+        fill: impl Into<Color>,
+        fg: impl Into<Color>,
+        label_bg: impl Into<Color>,
+    ) -> Result<Self> {
+        Ok(Self {
+            draw: Draw::new(font, point_size, fill)?,
+            fg: fg.into(),
+            label_bg: label_bg.into(),
+            enabled: false,
+            windows: Vec::new(),
+        })
+    }
+
+    /// Add this [DebugOverlay] to the given [WindowManager] along with the refresh hook used
+    /// to keep it up to date.
+    pub fn add_to<X>(self, mut wm: WindowManager<X>) -> WindowManager<X>
+    where
+        X: XConn + 'static,
+    {
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_refresh_hook(refresh_hook);
+
+        wm
+    }
+
+    /// Whether or not the overlay is currently being shown.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        for id in self.windows.drain(..) {
+            self.draw.destroy_window_and_surface(id)?;
+        }
+
+        Ok(())
+    }
+
+    fn render<X: XConn>(&mut self, state: &State<X>, x: &X) -> Result<()> {
+        self.clear()?;
+
+        let cs = &state.client_set;
+        for ws in cs.on_screen_workspaces() {
+            for (ix, &client) in ws.clients().enumerate() {
+                if !state.mapped_clients().contains(&client) {
+                    continue;
+                }
+
+                let r = x.client_geometry(client)?;
+                let class = match x.get_prop(client, Atom::WmClass.as_ref())? {
+                    Some(Prop::UTF8String(strs)) => strs.last().cloned().unwrap_or_default(),
+                    _ => String::new(),
+                };
+                let mut lines = vec![format!("{client} {class}"), format!("stack: {ix}")];
+                if cs.is_floating(&client) {
+                    lines.push("floating".to_owned());
+                }
+
+                self.render_client(r, &lines)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn render_client(&mut self, r: Rect, lines: &[String]) -> Result<()> {
+        if r.w == 0 || r.h == 0 {
+            return Ok(());
+        }
+
+        let id = self.draw.new_window(
+            WinType::InputOutput(Atom::NetWindowTypeNotification),
+            r,
+            false,
+        )?;
+        self.windows.push(id);
+
+        // An empty input region allows mouse events to pass through to the client beneath
+        self.draw
+            .conn
+            .connection()
+            .shape_rectangles(SO::SET, SK::INPUT, ClipOrdering::UNSORTED, *id, 0, 0, &[])
+            .map_err(penrose::Error::from)?;
+        self.draw
+            .conn
+            .set_client_config(id, &[ClientConfig::StackTop])?;
+
+        let mut extents = Vec::with_capacity(lines.len());
+        for line in lines.iter() {
+            extents.push(self.draw.text_extent(line)?);
+        }
+        let label_w = extents.iter().map(|&(w, _)| w).max().unwrap_or(0) + 2 * PADDING;
+        let line_h = extents.iter().map(|&(_, h)| h).max().unwrap_or(0);
+
+        let mut ctx = self.draw.context_for(id)?;
+        ctx.clear()?;
+        ctx.draw_rect(Rect::new(0, 0, r.w - 1, r.h - 1), self.fg)?;
+
+        let label = Rect::new(1, 1, label_w, line_h * lines.len() as u32 + 2 * PADDING);
+        ctx.fill_rect(label, self.label_bg)?;
+        for (i, line) in lines.iter().enumerate() {
+            ctx.set_offset(1, (1 + PADDING + i as u32 * line_h) as i32);
+            ctx.draw_text(line, 0, (PADDING, PADDING), self.fg)?;
+        }
+        ctx.flush();
+
+        self.draw.flush(id)
+    }
+}
+
+/// Redraw the [DebugOverlay] to match the current client positions if it is enabled.
+pub fn refresh_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> penrose::Result<()> {
+    let s = state.extension::<DebugOverlay>()?;
+    let mut overlay = s.borrow_mut();
+
+    let res = if overlay.enabled {
+        overlay.render(state, x)
+    } else {
+        overlay.clear()
+    };
+
+    if let Err(e) = res {
+        error!(%e, "error rendering debug overlay");
+    }
+
+    Ok(())
+}
+
+/// Toggle the visibility of the [DebugOverlay].
+pub fn toggle_debug_overlay<X: XConn + 'static>() -> Box<dyn KeyEventHandler<X>> {
+    key_handler(|state: &mut State<X>, x: &X| {
+        let s = state.extension::<DebugOverlay>()?;
+        let mut overlay = s.borrow_mut();
+        overlay.enabled = !overlay.enabled;
+
+        // Refreshing runs our own refresh hook so we need to release the borrow first
+        drop(overlay);
+        x.refresh(state)
+    })
+}
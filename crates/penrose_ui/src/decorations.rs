@@ -0,0 +1,312 @@
+//! Title bars for tiled clients.
+//!
+//! [Decorations] draws a thin title bar above each tiled client showing its window title along
+//! with a close button. Title bars are only added to clients positioned by layouts that have
+//! been wrapped using [Decorations::decorate], allowing decorations to be enabled per layout:
+//!
+//! ```no_run
+//! use penrose::{
+//!     builtin::layout::{MainAndStack, Monocle},
+//!     core::{layout::LayoutStack, Config, WindowManager},
+//!     stack,
+//!     x11rb::RustConn,
+//! };
+//! use penrose_ui::{decorations::Decorations, TextStyle};
+//! use std::collections::HashMap;
+//!
+//! let focused = TextStyle {
+//!     fg: 0xebdbb2ff.into(),
+//!     bg: Some(0x458588ff.into()),
+//!     padding: (4, 4),
+//! };
+//! let unfocused = TextStyle {
+//!     fg: 0xa89984ff.into(),
+//!     bg: Some(0x3c3836ff.into()),
+//!     ..focused
+//! };
+//! let decorations = Decorations::new("mono", 10, 18, focused, unfocused).unwrap();
+//!
+//! // Only clients positioned by MainAndStack will be given title bars
+//! let config = Config {
+//!     default_layouts: stack!(decorations.decorate(MainAndStack::default()), Monocle::boxed()),
+//!     ..Config::default()
+//! };
+//!
+//! let conn = RustConn::new().unwrap();
+//! let wm = WindowManager::new(config, HashMap::new(), HashMap::new(), conn).unwrap();
+//! let wm = decorations.add_to(wm);
+//! ```
+//!
+//! Left clicking on a title bar focuses its client and clicking on the close button closes it.
+use crate::{Draw, Result, TextStyle};
+use penrose::{
+    core::{
+        bindings::{MouseButton, MouseEventKind},
+        layout::{Layout, LayoutTransformer},
+        State, WindowManager,
+    },
+    pure::geometry::Rect,
+    x::{event::PropertyEvent, Atom, ClientAttr, ClientConfig, WinType, XConn, XConnExt, XEvent},
+    Xid,
+};
+use std::{
+    collections::HashMap,
+    mem,
+    sync::{Arc, Mutex},
+};
+use tracing::error;
+
+const CLOSE: &str = "×";
+
+// Title bar positions recorded by Decorated layouts during the most recent layout pass.
+type Pending = Arc<Mutex<HashMap<Xid, Rect>>>;
+
+/// A [LayoutTransformer] that reserves space above each client for a title bar drawn by
+/// [Decorations].
+///
+/// Created using [Decorations::decorate].
+#[derive(Debug, Clone)]
+pub struct Decorated {
+    inner: Box<dyn Layout>,
+    height: u32,
+    pending: Pending,
+}
+
+impl LayoutTransformer for Decorated {
+    fn transformed_name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn inner_mut(&mut self) -> &mut Box<dyn Layout> {
+        &mut self.inner
+    }
+
+    fn transform_positions(&mut self, _: Rect, positions: Vec<(Xid, Rect)>) -> Vec<(Xid, Rect)> {
+        let mut pending = match self.pending.lock() {
+            Ok(pending) => pending,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        positions
+            .into_iter()
+            .map(|(id, r)| match r.split_at_height(self.height) {
+                Some((title, client)) if client.h > self.height => {
+                    pending.insert(id, title);
+                    (id, client)
+                }
+                _ => (id, r),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TitleBar {
+    id: Xid,
+    r: Rect,
+}
+
+/// A state extension for drawing title bars above tiled clients.
+///
+/// See the module level docs for details.
+#[derive(Debug)]
+pub struct Decorations {
+    draw: Draw,
+    height: u32,
+    focused: TextStyle,
+    unfocused: TextStyle,
+    pending: Pending,
+    bars: HashMap<Xid, TitleBar>,
+}
+
+impl Decorations {
+    /// Create a new [Decorations] drawing title bars that are `height` pixels tall.
+    ///
+    /// The title bar of the focused client is drawn using the `focused` style and all other
+    /// title bars are drawn using the `unfocused` style.
+    pub fn new(
+        font: &str,
+        point_size: u8,
+        height: u32,
+        focused: TextStyle,
+        unfocused: TextStyle,
+    ) -> Result<Self> {
+        let bg = unfocused.bg.unwrap_or_else(|| 0x000000ff.into());
+
+        Ok(Self {
+            draw: Draw::new(font, point_size, bg)?,
+            height,
+            focused,
+            unfocused,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            bars: HashMap::new(),
+        })
+    }
+
+    /// Wrap the given [Layout] so that the clients it positions are given title bars.
+    pub fn decorate<L: Layout + 'static>(&self, layout: L) -> Box<dyn Layout> {
+        Box::new(Decorated {
+            inner: Box::new(layout),
+            height: self.height,
+            pending: self.pending.clone(),
+        })
+    }
+
+    /// Add this [Decorations] to the given [WindowManager] along with the hooks used to keep the
+    /// title bars up to date.
+    pub fn add_to<X>(self, mut wm: WindowManager<X>) -> WindowManager<X>
+    where
+        X: XConn + 'static,
+    {
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_event_hook(event_hook);
+        wm.state.config.compose_or_set_refresh_hook(refresh_hook);
+
+        wm
+    }
+
+    fn remove(&mut self, client: Xid) -> Result<()> {
+        if let Some(bar) = self.bars.remove(&client) {
+            self.draw.destroy_window_and_surface(bar.id)?;
+        }
+
+        Ok(())
+    }
+
+    fn update<X: XConn>(&mut self, state: &State<X>, x: &X) -> Result<()> {
+        let pending = match self.pending.lock() {
+            Ok(mut pending) => mem::take(&mut *pending),
+            Err(poisoned) => mem::take(&mut *poisoned.into_inner()),
+        };
+        let visible: HashMap<Xid, Rect> = pending
+            .into_iter()
+            .filter(|(client, _)| state.mapped_clients().contains(client))
+            .collect();
+
+        let stale: Vec<Xid> = self
+            .bars
+            .iter()
+            .filter(|(client, bar)| visible.get(client) != Some(&bar.r))
+            .map(|(&client, _)| client)
+            .collect();
+        for client in stale {
+            self.remove(client)?;
+        }
+
+        for (client, r) in visible {
+            if !self.bars.contains_key(&client) {
+                let ty = WinType::InputOutput(Atom::NetWindowTypeDock);
+                let id = self.draw.new_window(ty, r, false)?;
+                x.set_client_attributes(id, &[ClientAttr::UiEventMask])?;
+                self.bars.insert(client, TitleBar { id, r });
+            }
+
+            self.render(client, state, x)?;
+        }
+
+        Ok(())
+    }
+
+    fn render<X: XConn>(&mut self, client: Xid, state: &State<X>, x: &X) -> Result<()> {
+        let TitleBar { id, r } = match self.bars.get(&client) {
+            Some(&bar) => bar,
+            None => return Ok(()),
+        };
+        let style = if state.client_set.current_client() == Some(&client) {
+            &self.focused
+        } else {
+            &self.unfocused
+        };
+        let title = x.window_title(client)?;
+
+        let mut ctx = self.draw.context_for(id)?;
+        ctx.clear()?;
+        if let Some(bg) = style.bg {
+            ctx.fill_rect(Rect::new(0, 0, r.w, r.h), bg)?;
+        }
+
+        let (_, th) = ctx.text_extent(&title)?;
+        let (cw, ch) = ctx.text_extent(CLOSE)?;
+        let h_offset = |h: u32| r.h.saturating_sub(h) / 2;
+        ctx.draw_text(&title, h_offset(th), style.padding, style.fg)?;
+        ctx.set_x_offset(r.w.saturating_sub(r.h / 2 + cw / 2) as i32);
+        ctx.draw_text(CLOSE, h_offset(ch), (0, 0), style.fg)?;
+        ctx.flush();
+
+        self.draw.flush(id)?;
+        x.set_client_config(id, &[ClientConfig::StackAbove(client)])?;
+
+        Ok(())
+    }
+
+    fn client_for_bar(&self, id: Xid) -> Option<Xid> {
+        self.bars
+            .iter()
+            .find(|(_, bar)| bar.id == id)
+            .map(|(&client, _)| client)
+    }
+}
+
+/// Update the positions and contents of title bars to match the current layout.
+pub fn refresh_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> penrose::Result<()> {
+    let s = state.extension::<Decorations>()?;
+    let mut decorations = s.borrow_mut();
+
+    if let Err(e) = decorations.update(state, x) {
+        error!(%e, "error updating window decorations");
+    }
+
+    Ok(())
+}
+
+/// Redraw title bars when client titles change and handle clicks on title bars.
+pub fn event_hook<X: XConn + 'static>(
+    event: &XEvent,
+    state: &mut State<X>,
+    x: &X,
+) -> penrose::Result<bool> {
+    let s = state.extension::<Decorations>()?;
+    let mut decorations = s.borrow_mut();
+    let title_props = [Atom::NetWmName.as_ref(), Atom::WmName.as_ref()];
+
+    match event {
+        XEvent::PropertyNotify(PropertyEvent { id, atom, .. })
+            if decorations.bars.contains_key(id) && title_props.contains(&atom.as_ref()) =>
+        {
+            if let Err(e) = decorations.render(*id, state, x) {
+                error!(%e, "error redrawing title bar");
+            }
+        }
+
+        XEvent::Destroy(id) => {
+            if let Err(e) = decorations.remove(*id) {
+                error!(%e, "error removing title bar");
+            }
+        }
+
+        XEvent::MouseEvent(e)
+            if e.kind == MouseEventKind::Press && e.state.button == MouseButton::Left =>
+        {
+            let client = match decorations.client_for_bar(e.data.window) {
+                Some(client) => client,
+                None => return Ok(true),
+            };
+            let r = decorations.bars[&client].r;
+            let on_close_button = e.data.wpt.x + r.h >= r.w;
+
+            // Refreshing runs our own refresh hook so we need to release the borrow first
+            drop(decorations);
+            if on_close_button {
+                x.kill(client)?;
+            } else {
+                x.modify_and_refresh(state, |cs| cs.focus_client(&client))?;
+            }
+
+            return Ok(false);
+        }
+
+        _ => (),
+    }
+
+    Ok(true)
+}
@@ -0,0 +1,151 @@
+//! Rearranging tiled windows by dragging them on top of one another.
+//!
+//! [DragToSwap] wraps a [MouseDragHandler] with tiled swapping enabled so that dragging a tiled
+//! window and dropping it on top of another tiled window swaps their positions in the stack.
+//! While the drag is in progress, the window that would be swapped with is highlighted so that
+//! it is clear where the dragged window will end up. Floating windows are moved as normal.
+//!
+//! ```no_run
+//! use penrose::{
+//!     core::bindings::{ModifierKey, MouseButton, MouseEventHandler, MouseState},
+//!     x11rb::RustConn,
+//! };
+//! use penrose_ui::drag::DragToSwap;
+//! use std::collections::HashMap;
+//!
+//! let mut mouse_bindings: HashMap<MouseState, Box<dyn MouseEventHandler<RustConn>>> =
+//!     HashMap::new();
+//! mouse_bindings.insert(
+//!     MouseState::new(MouseButton::Left, vec![ModifierKey::Meta]),
+//!     DragToSwap::boxed(0x458588aa).unwrap(),
+//! );
+//! ```
+//!
+//! The highlight color should normally be partially transparent so that the window beneath it
+//! can still be seen (this requires a compositor to be running).
+use crate::{Draw, Result};
+use penrose::{
+    builtin::actions::floating::MouseDragHandler,
+    core::{
+        bindings::{MotionNotifyEvent, MouseEvent, MouseEventHandler},
+        State,
+    },
+    pure::geometry::Rect,
+    x::{Atom, ClientConfig, WinType, XConn},
+    Color, Xid,
+};
+use tracing::error;
+use x11rb::protocol::{
+    shape::{ConnectionExt, SK, SO},
+    xproto::ClipOrdering,
+};
+
+const FONT: &str = "mono";
+
+/// A [MouseEventHandler] for dragging windows that swaps tiled windows when they are dropped on
+/// top of one another, highlighting the drop target while the drag is in progress.
+///
+/// See the module level docs for details.
+#[derive(Debug)]
+pub struct DragToSwap {
+    inner: MouseDragHandler,
+    draw: Draw,
+    color: Color,
+    highlight: Option<(Xid, Rect)>,
+}
+
+impl DragToSwap {
+    /// Create a new [DragToSwap] that highlights drop targets using the given color.
+    pub fn new(color: impl Into<Color>) -> Result<Self> {
+        let color = color.into();
+
+        Ok(Self {
+            inner: MouseDragHandler::default().with_tiled_swapping(),
+            draw: Draw::new(FONT, 10, color)?,
+            color,
+            highlight: None,
+        })
+    }
+
+    /// Construct a boxed [MouseEventHandler] trait object ready to be added to your bindings
+    pub fn boxed<X: XConn>(color: impl Into<Color>) -> Result<Box<dyn MouseEventHandler<X>>> {
+        Ok(Box::new(Self::new(color)?))
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        if let Some((id, _)) = self.highlight.take() {
+            self.draw.destroy_window_and_surface(id)?;
+            self.draw.conn.flush();
+        }
+
+        Ok(())
+    }
+
+    // The highlight window is only recreated when the drop target changes, rather than on
+    // every motion event.
+    fn update_highlight(&mut self) -> Result<()> {
+        let target = self.inner.drop_target();
+        if self.highlight.map(|(_, r)| r) == target {
+            return Ok(());
+        }
+
+        self.clear()?;
+        let r = match target {
+            Some(r) if r.w > 0 && r.h > 0 => r,
+            _ => return Ok(()),
+        };
+
+        let id = self.draw.new_window(
+            WinType::InputOutput(Atom::NetWindowTypeNotification),
+            r,
+            false,
+        )?;
+        self.highlight = Some((id, r));
+
+        // An empty input region stops the highlight from generating enter events for itself
+        self.draw
+            .conn
+            .connection()
+            .shape_rectangles(SO::SET, SK::INPUT, ClipOrdering::UNSORTED, *id, 0, 0, &[])
+            .map_err(penrose::Error::from)?;
+        self.draw
+            .conn
+            .set_client_config(id, &[ClientConfig::StackTop])?;
+
+        let mut ctx = self.draw.context_for(id)?;
+        ctx.fill_rect(Rect::new(0, 0, r.w, r.h), self.color)?;
+        ctx.flush();
+
+        self.draw.flush(id)
+    }
+}
+
+impl<X: XConn> MouseEventHandler<X> for DragToSwap {
+    fn on_mouse_event(
+        &mut self,
+        evt: &MouseEvent,
+        state: &mut State<X>,
+        x: &X,
+    ) -> penrose::Result<()> {
+        let res = self.inner.on_mouse_event(evt, state, x);
+        if let Err(e) = self.update_highlight() {
+            error!(%e, "error rendering drop target highlight");
+        }
+
+        res
+    }
+
+    fn on_motion(
+        &mut self,
+        evt: &MotionNotifyEvent,
+        state: &mut State<X>,
+        x: &X,
+    ) -> penrose::Result<()> {
+        let res = self.inner.on_motion(evt, state, x);
+        if let Err(e) = self.update_highlight() {
+            error!(%e, "error rendering drop target highlight");
+        }
+
+        res
+    }
+}
@@ -0,0 +1,321 @@
+//! An overview of every client on the current screen, laid out in a grid.
+//!
+//! [Expose] covers the current screen with a grid containing a cell for each client on the
+//! workspace currently shown on that screen along with every hidden workspace. Each cell shows
+//! the tag and title of its client along with a short hint label. Typing the hint for a client
+//! or clicking on its cell jumps to the client's workspace and focuses it, while `Escape` (or
+//! `C-c` / `C-g`) closes the overview without changing focus.
+//!
+//! While the overview is open the window manager event loop is blocked, so it is intended to be
+//! run from a key binding using the [expose] helper:
+//!
+//! ```no_run
+//! use penrose::{core::bindings::KeyEventHandler, x11rb::RustConn};
+//! use penrose_ui::{expose::{expose, Expose}, TextStyle};
+//!
+//! let style = TextStyle {
+//!     fg: 0xebdbb2ff.into(),
+//!     bg: Some(0x282828ff.into()),
+//!     padding: (4, 4),
+//! };
+//!
+//! let overview = Expose::new("mono", 12, style, 0x458588ff).unwrap();
+//! let action: Box<dyn KeyEventHandler<RustConn>> = expose(overview);
+//! ```
+//!
+//! Workspaces that are currently shown on other screens are not included.
+use crate::{
+    hints::{hint_labels, parse_hint_chars, DEFAULT_HINT_CHARS},
+    prompt::{
+        grab_keyboard, keysym_to_char, Keymap, XK_BACKSPACE, XK_ESCAPE, XK_KP_ENTER, XK_RETURN,
+    },
+    Context, Draw, Result, TextStyle,
+};
+use penrose::{
+    builtin::actions::key_handler,
+    core::{
+        bindings::{KeyEventHandler, MouseButton, MouseEventKind},
+        State,
+    },
+    pure::geometry::Rect,
+    x::{Atom, ClientAttr, WinType, XConn, XConnExt, XEvent},
+    Color, Xid,
+};
+use std::collections::HashSet;
+use x11rb::{protocol::xproto::ConnectionExt as _, CURRENT_TIME};
+
+const GAP: u32 = 8;
+
+// Modifier mask for the control key from the core X protocol
+const CONTROL_MASK: u16 = 1 << 2;
+
+/// A client to be shown in an [Expose] overview.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExposeClient {
+    /// The client window
+    pub id: Xid,
+    /// The tag of the workspace the client is on
+    pub tag: String,
+    /// The title of the client window
+    pub title: String,
+}
+
+/// A full screen overview of clients that allows for selecting one using either keyboard hints
+/// or the mouse.
+///
+/// See the module level docs for details.
+#[derive(Debug)]
+pub struct Expose {
+    drw: Draw,
+    fg: Color,
+    highlight: Color,
+    hint_chars: Vec<char>,
+}
+
+impl Expose {
+    /// Construct a new [Expose] using the given font and style.
+    ///
+    /// The `highlight` color is used for the hint labels and the border of the cell containing
+    /// the currently focused client.
+    pub fn new(
+        font: &str,
+        point_size: u8,
+        style: TextStyle,
+        highlight: impl Into<Color>,
+    ) -> Result<Self> {
+        let bg = style.bg.unwrap_or_else(|| 0x000000ff.into());
+
+        Ok(Self {
+            drw: Draw::new(font, point_size, bg)?,
+            fg: style.fg,
+            highlight: highlight.into(),
+            hint_chars: DEFAULT_HINT_CHARS.chars().collect(),
+        })
+    }
+
+    /// Set the characters used for generating hint labels.
+    ///
+    /// Defaults to the home row of a qwerty keyboard. Duplicate characters are ignored and at
+    /// least two distinct characters are required: if fewer are provided then the hint
+    /// characters are left unchanged.
+    pub fn with_hint_chars(mut self, chars: &str) -> Self {
+        if let Some(chars) = parse_hint_chars(chars) {
+            self.hint_chars = chars;
+        }
+
+        self
+    }
+
+    /// Show the overview for the given clients covering the given screen region and block until
+    /// the user either selects a client or closes the overview.
+    ///
+    /// Returns `None` if there were no clients to show or the overview was closed without
+    /// selecting a client.
+    pub fn run(
+        &mut self,
+        clients: &[ExposeClient],
+        focused: Option<Xid>,
+        screen: Rect,
+    ) -> Result<Option<Xid>> {
+        if clients.is_empty() {
+            return Ok(None);
+        }
+
+        let win = self.drw.new_window(
+            WinType::InputOutput(Atom::NetWindowTypeDialog),
+            screen,
+            false,
+        )?;
+
+        let res = self.run_in(win, clients, focused, screen);
+        let conn = self.drw.conn.connection();
+        let _ = conn.ungrab_keyboard(CURRENT_TIME);
+        self.drw.destroy_window_and_surface(win)?;
+        self.drw.conn.flush();
+
+        res
+    }
+
+    fn run_in(
+        &mut self,
+        win: Xid,
+        clients: &[ExposeClient],
+        focused: Option<Xid>,
+        screen: Rect,
+    ) -> Result<Option<Xid>> {
+        self.drw
+            .conn
+            .set_client_attributes(win, &[ClientAttr::UiEventMask])?;
+        grab_keyboard(&self.drw.conn, win)?;
+        let keymap = Keymap::try_new(&self.drw.conn)?;
+
+        let cells = grid(Rect::new(0, 0, screen.w, screen.h), clients.len());
+        let hints = hint_labels(clients.len(), &self.hint_chars);
+        let mut typed = String::new();
+        self.render(win, clients, focused, &cells, &hints, &typed)?;
+
+        loop {
+            match self.drw.conn.next_event()? {
+                XEvent::KeyPress(k) => {
+                    let ctrl = k.mask & CONTROL_MASK != 0;
+                    match (keymap.keysym(k), ctrl) {
+                        (XK_ESCAPE, _) => return Ok(None),
+                        (c, true) if c == 'c' as u32 || c == 'g' as u32 => return Ok(None),
+                        (XK_RETURN | XK_KP_ENTER, _) => return Ok(focused),
+                        (XK_BACKSPACE, _) => {
+                            typed.pop();
+                        }
+                        (ks, false) => {
+                            if let Some(c) = keysym_to_char(ks) {
+                                let candidate = format!("{typed}{c}");
+                                if let Some(i) = hints.iter().position(|h| *h == candidate) {
+                                    return Ok(Some(clients[i].id));
+                                } else if hints.iter().any(|h| h.starts_with(&candidate)) {
+                                    typed = candidate;
+                                }
+                            }
+                        }
+                        _ => continue,
+                    }
+                    self.render(win, clients, focused, &cells, &hints, &typed)?;
+                }
+
+                XEvent::MouseEvent(e)
+                    if e.data.window == win
+                        && e.kind == MouseEventKind::Press
+                        && e.state.button == MouseButton::Left =>
+                {
+                    if let Some(i) = cells.iter().position(|r| r.contains_point(e.data.wpt)) {
+                        return Ok(Some(clients[i].id));
+                    }
+                }
+
+                XEvent::Expose(_) => self.render(win, clients, focused, &cells, &hints, &typed)?,
+
+                _ => (),
+            }
+        }
+    }
+
+    fn render(
+        &mut self,
+        win: Xid,
+        clients: &[ExposeClient],
+        focused: Option<Xid>,
+        cells: &[Rect],
+        hints: &[String],
+        typed: &str,
+    ) -> Result<()> {
+        let mut ctx = self.drw.context_for(win)?;
+        ctx.clear()?;
+
+        for ((c, &r), hint) in clients.iter().zip(cells).zip(hints) {
+            let border = if Some(c.id) == focused {
+                self.highlight
+            } else {
+                self.fg
+            };
+            ctx.reset_offset();
+            ctx.draw_rect(r, border)?;
+            ctx.set_offset(r.x as i32 + GAP as i32, r.y as i32 + GAP as i32);
+            let w = r.w.saturating_sub(2 * GAP);
+
+            let mut y = 0;
+            if hint.starts_with(typed) {
+                let (hw, hh) = ctx.text_extent(hint)?;
+                ctx.fill_rect(Rect::new(0, 0, hw + GAP, hh + GAP), self.highlight)?;
+                ctx.draw_text(hint, GAP / 2, (GAP / 2, 0), self.fg)?;
+                y = hh + 2 * GAP;
+            }
+
+            for line in [c.tag.as_str(), c.title.as_str()] {
+                let txt = fit_text(&mut ctx, line, w)?;
+                let (_, th) = ctx.text_extent(if txt.is_empty() { " " } else { &txt })?;
+                if y + th > r.h.saturating_sub(2 * GAP) {
+                    break;
+                }
+                ctx.draw_text(&txt, y, (0, 0), self.fg)?;
+                y += th + GAP / 2;
+            }
+        }
+
+        ctx.flush();
+        self.drw.flush(win)
+    }
+}
+
+/// Show an [Expose] overview of the clients on the current screen, focusing the one that is
+/// selected.
+///
+/// The overview contains the clients of the workspace on the current screen followed by those
+/// on each hidden workspace, ordered by workspace. Selecting a client on a hidden workspace
+/// moves that workspace to the current screen.
+pub fn expose<X: XConn>(mut overview: Expose) -> Box<dyn KeyEventHandler<X>> {
+    key_handler(move |state: &mut State<X>, x: &X| {
+        let cs = &state.client_set;
+        let current = cs.current_tag();
+        let hidden: HashSet<&str> = cs.hidden_workspaces().map(|w| w.tag()).collect();
+
+        let mut clients = Vec::new();
+        for tag in cs.ordered_tags() {
+            if tag != current && !hidden.contains(tag.as_str()) {
+                continue;
+            }
+            let ids = cs.workspace(&tag).into_iter().flat_map(|w| w.clients());
+            for &id in ids {
+                let title = x.window_title(id).unwrap_or_default();
+                clients.push(ExposeClient {
+                    id,
+                    tag: tag.clone(),
+                    title,
+                });
+            }
+        }
+
+        let focused = cs.current_client().copied();
+        let r = cs.current_screen().geometry();
+
+        match overview.run(&clients, focused, r) {
+            Ok(Some(id)) => x.modify_and_refresh(state, |cs| cs.focus_client(&id)),
+            Ok(None) => Ok(()),
+            Err(e) => Err(penrose::Error::Custom(format!("error running expose: {e}"))),
+        }
+    })
+}
+
+// Split r into a grid of at least n cells with as close to the same number of rows and columns
+// as possible, returning the first n cells in row major order.
+fn grid(r: Rect, n: usize) -> Vec<Rect> {
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let cols = (n as f64).sqrt().ceil() as u32;
+    let rows = (n as u32).div_ceil(cols);
+
+    r.shrink_in(GAP / 2)
+        .as_rows(rows)
+        .iter()
+        .flat_map(|row| row.as_columns(cols))
+        .map(|cell| cell.shrink_in(GAP / 2))
+        .take(n)
+        .collect()
+}
+
+// Truncate txt (marking it with an ellipsis) so that it fits within the given width.
+fn fit_text(ctx: &mut Context<'_>, txt: &str, w: u32) -> Result<String> {
+    if ctx.text_extent(txt)?.0 <= w {
+        return Ok(txt.to_owned());
+    }
+
+    let mut chars: Vec<char> = txt.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let s: String = chars.iter().chain(['…'].iter()).collect();
+        if ctx.text_extent(&s)?.0 <= w {
+            return Ok(s);
+        }
+    }
+
+    Ok(String::new())
+}
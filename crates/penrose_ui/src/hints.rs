@@ -0,0 +1,267 @@
+//! Keyboard driven window selection using hint labels.
+//!
+//! [WindowHints] overlays a short hint label on top of every visible client window and grabs
+//! the keyboard until either the label of one of the clients is typed (focusing that client) or
+//! the hints are dismissed using `Escape`, `C-c` or `C-g`. Labels are one character long when
+//! there are few enough clients and two characters long otherwise, making it possible to reach
+//! any visible client in at most two key presses:
+//!
+//! ```no_run
+//! use penrose::{core::bindings::KeyEventHandler, x11rb::RustConn};
+//! use penrose_ui::{hints::{focus_with_hints, WindowHints}, TextStyle};
+//!
+//! let style = TextStyle {
+//!     fg: 0x282828ff.into(),
+//!     bg: Some(0xfabd2fff.into()),
+//!     padding: (6, 4),
+//! };
+//!
+//! let hints = WindowHints::new("mono", 16, style, 0x9d0006ff).unwrap();
+//! let action: Box<dyn KeyEventHandler<RustConn>> = focus_with_hints(hints);
+//! ```
+//!
+//! While the hints are shown the window manager event loop is blocked.
+use crate::{
+    prompt::{grab_keyboard, keysym_to_char, Keymap, XK_BACKSPACE, XK_ESCAPE},
+    Draw, Result, TextStyle,
+};
+use penrose::{
+    builtin::actions::key_handler,
+    core::{bindings::KeyEventHandler, State},
+    pure::geometry::Rect,
+    x::{Atom, WinType, XConn, XConnExt, XEvent},
+    Color, Xid,
+};
+use std::collections::HashSet;
+use x11rb::{protocol::xproto::ConnectionExt as _, CURRENT_TIME};
+
+pub(crate) const DEFAULT_HINT_CHARS: &str = "asdfghjkl";
+
+// Modifier mask for the control key from the core X protocol
+const CONTROL_MASK: u16 = 1 << 2;
+
+#[derive(Debug, Clone)]
+struct Label {
+    id: Xid,
+    client: Xid,
+    hint: String,
+}
+
+/// Hint labels drawn over visible client windows for selecting one using the keyboard.
+///
+/// See the module level docs for details.
+#[derive(Debug)]
+pub struct WindowHints {
+    drw: Draw,
+    fg: Color,
+    highlight: Color,
+    padding: (u32, u32),
+    hint_chars: Vec<char>,
+}
+
+impl WindowHints {
+    /// Construct a new [WindowHints] using the given font and style.
+    ///
+    /// The `highlight` color is used for the characters of each label that have already been
+    /// typed.
+    pub fn new(
+        font: &str,
+        point_size: u8,
+        style: TextStyle,
+        highlight: impl Into<Color>,
+    ) -> Result<Self> {
+        let bg = style.bg.unwrap_or_else(|| 0x000000ff.into());
+
+        Ok(Self {
+            drw: Draw::new(font, point_size, bg)?,
+            fg: style.fg,
+            highlight: highlight.into(),
+            padding: style.padding,
+            hint_chars: DEFAULT_HINT_CHARS.chars().collect(),
+        })
+    }
+
+    /// Set the characters used for generating hint labels.
+    ///
+    /// Defaults to the home row of a qwerty keyboard. Duplicate characters are ignored and at
+    /// least two distinct characters are required: if fewer are provided then the hint
+    /// characters are left unchanged.
+    pub fn with_hint_chars(mut self, chars: &str) -> Self {
+        if let Some(chars) = parse_hint_chars(chars) {
+            self.hint_chars = chars;
+        }
+
+        self
+    }
+
+    /// Show a hint label centered over each of the given client regions and block until the
+    /// user either types one of the labels or dismisses the hints.
+    ///
+    /// Returns `None` if there were no clients or the hints were dismissed.
+    pub fn run(&mut self, clients: &[(Xid, Rect)]) -> Result<Option<Xid>> {
+        if clients.is_empty() {
+            return Ok(None);
+        }
+
+        let hints = hint_labels(clients.len(), &self.hint_chars);
+        let mut labels = Vec::with_capacity(clients.len());
+        let mut res = Ok(None);
+
+        for (&(client, r), hint) in clients.iter().zip(hints) {
+            match self.new_label(client, r, hint) {
+                Ok(label) => labels.push(label),
+                Err(e) => {
+                    res = Err(e);
+                    break;
+                }
+            }
+        }
+
+        if res.is_ok() {
+            res = self.run_with(&labels);
+        }
+
+        let conn = self.drw.conn.connection();
+        let _ = conn.ungrab_keyboard(CURRENT_TIME);
+        for label in labels {
+            self.drw.destroy_window_and_surface(label.id)?;
+        }
+        self.drw.conn.flush();
+
+        res
+    }
+
+    fn new_label(&mut self, client: Xid, r: Rect, hint: String) -> Result<Label> {
+        let (lpad, rpad) = self.padding;
+        let (w, h) = self.drw.text_extent(&hint)?;
+        let label_r = Rect::new(0, 0, w + lpad + rpad, h + lpad + rpad);
+        let label_r = label_r
+            .centered_in(&r)
+            .unwrap_or(Rect::new(r.x, r.y, label_r.w, label_r.h));
+
+        let ty = WinType::InputOutput(Atom::NetWindowTypeDock);
+        let id = self.drw.new_window(ty, label_r, false)?;
+
+        Ok(Label { id, client, hint })
+    }
+
+    fn run_with(&mut self, labels: &[Label]) -> Result<Option<Xid>> {
+        let mut typed = String::new();
+        self.render(labels, &typed)?;
+        grab_keyboard(&self.drw.conn, labels[0].id)?;
+        let keymap = Keymap::try_new(&self.drw.conn)?;
+
+        loop {
+            match self.drw.conn.next_event()? {
+                XEvent::KeyPress(k) => {
+                    let ctrl = k.mask & CONTROL_MASK != 0;
+                    match (keymap.keysym(k), ctrl) {
+                        (XK_ESCAPE, _) => return Ok(None),
+                        (c, true) if c == 'c' as u32 || c == 'g' as u32 => return Ok(None),
+                        (XK_BACKSPACE, _) => {
+                            typed.pop();
+                        }
+                        (ks, false) => {
+                            if let Some(c) = keysym_to_char(ks) {
+                                let candidate = format!("{typed}{c}");
+                                if let Some(l) = labels.iter().find(|l| l.hint == candidate) {
+                                    return Ok(Some(l.client));
+                                } else if labels.iter().any(|l| l.hint.starts_with(&candidate)) {
+                                    typed = candidate;
+                                }
+                            }
+                        }
+                        _ => continue,
+                    }
+                    self.render(labels, &typed)?;
+                }
+
+                XEvent::Expose(_) => self.render(labels, &typed)?,
+
+                _ => (),
+            }
+        }
+    }
+
+    // Labels that no longer match the typed prefix are hidden, with the typed characters of the
+    // remaining labels drawn using the highlight color.
+    fn render(&mut self, labels: &[Label], typed: &str) -> Result<()> {
+        let (lpad, _) = self.padding;
+
+        for label in labels {
+            if !label.hint.starts_with(typed) {
+                self.drw.conn.unmap(label.id)?;
+                continue;
+            }
+
+            let mut ctx = self.drw.context_for(label.id)?;
+            ctx.clear()?;
+            let (x, _) = ctx.draw_text(typed, lpad, (lpad, 0), self.highlight)?;
+            ctx.set_x_offset(x as i32);
+            ctx.draw_text(&label.hint[typed.len()..], lpad, (0, 0), self.fg)?;
+            ctx.flush();
+            self.drw.flush(label.id)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Show [WindowHints] over every visible client, focusing the client whose label is typed.
+pub fn focus_with_hints<X: XConn>(mut hints: WindowHints) -> Box<dyn KeyEventHandler<X>> {
+    key_handler(move |state: &mut State<X>, x: &X| {
+        let cs = &state.client_set;
+        let mapped = state.mapped_clients();
+        let mut clients = Vec::new();
+
+        for w in cs.on_screen_workspaces() {
+            for &id in w.clients().filter(|id| mapped.contains(id)) {
+                clients.push((id, x.client_geometry(id)?));
+            }
+        }
+
+        match hints.run(&clients) {
+            Ok(Some(id)) => x.modify_and_refresh(state, |cs| cs.focus_client(&id)),
+            Ok(None) => Ok(()),
+            Err(e) => Err(penrose::Error::Custom(format!(
+                "error showing window hints: {e}"
+            ))),
+        }
+    })
+}
+
+// Parse a set of hint characters, ignoring duplicates. At least two distinct characters are
+// required in order to generate hints for more than one client.
+pub(crate) fn parse_hint_chars(chars: &str) -> Option<Vec<char>> {
+    let mut seen = HashSet::new();
+    let chars: Vec<char> = chars.chars().filter(|c| seen.insert(*c)).collect();
+
+    (chars.len() >= 2).then_some(chars)
+}
+
+// Generate n hint labels of equal length from the given characters so that no label is a
+// prefix of another.
+pub(crate) fn hint_labels(n: usize, chars: &[char]) -> Vec<String> {
+    let base = chars.len();
+    if n == 0 || base < 2 {
+        return Vec::new();
+    }
+
+    let mut len = 1;
+    let mut capacity = base;
+    while capacity < n {
+        len += 1;
+        capacity = capacity.saturating_mul(base);
+    }
+
+    (0..n)
+        .map(|mut i| {
+            let mut label = vec![chars[0]; len];
+            for slot in label.iter_mut().rev() {
+                *slot = chars[i % base];
+                i /= base;
+            }
+            label.into_iter().collect()
+        })
+        .collect()
+}
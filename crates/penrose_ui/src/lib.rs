@@ -9,15 +9,18 @@
 //!
 //! ## Getting started
 //! The main functionality of this crate is provided through the [`Draw`] nad [`Context`] structs
-//! which allow for simple graphics rendering backed by the xlib and fontconfig libraries.
+//! which allow for simple graphics rendering on top of [x11rb][1], with text rendered using
+//! fontconfig and the [fontdue][2] font rasterizer.
 //!
 //! ## A note on the use of unsafe code
 //! Given the aims of this crate and the desire to pull in as few dependencies as possible, it
-//! makes heavy use of `unsafe` to wrap C FFI calls. Please make sure that you read the available
+//! makes use of `unsafe` to wrap the C FFI calls used for locating fonts via fontconfig. Please make sure that you read the available
 //! documentation and `SAFETY` comments in the source code to understand what is happening under
 //! the hood if you have any concerns about this.
 //!
 //! [0]: https://github.com/sminez/penrose
+//! [1]: https://github.com/psychon/x11rb
+//! [2]: https://github.com/mooman219/fontdue
 #![warn(
     clippy::complexity,
     clippy::correctness,
@@ -39,16 +42,29 @@ use std::ffi::NulError;
 
 pub mod bar;
 pub mod core;
+pub mod debug_overlay;
+pub mod decorations;
+pub mod drag;
+pub mod expose;
+pub mod hints;
 pub mod layout_viewer;
+pub mod popup;
+pub mod prompt;
 
-pub use crate::core::{Context, Draw, TextStyle};
-pub use bar::{Position, StatusBar};
+pub use crate::core::{
+    image::Image, Context, Draw, GradientDirection, Separator, Span, TextDecoration, TextStyle,
+};
+pub use bar::{toggle_bar, Position, StatusBar};
 
 use bar::widgets::{ActiveWindowName, CurrentLayout, RootWindowName, Workspaces};
 
 /// Error variants from penrose_ui library.
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
+    /// A [`StatusBar`] was created without any widget arrangements
+    #[error("at least one widget arrangement is required to create a status bar")]
+    NoWidgetArrangements,
+
     /// Creation of a [`Color`] from a string hex code was invalid
     #[error("Invalid Hex color code: {code}")]
     InvalidHexColor {
@@ -56,6 +72,30 @@ pub enum Error {
         code: String,
     },
 
+    /// The pixel data provided for an image did not match its dimensions
+    #[error("{len} pixels provided for a {w}x{h} image")]
+    InvalidImageData {
+        /// The requested width of the image
+        w: u32,
+        /// The requested height of the image
+        h: u32,
+        /// The number of pixels provided
+        len: usize,
+    },
+
+    /// A window was requested with a size that can not be drawn to
+    #[error("unable to create a {w}x{h} surface")]
+    InvalidSurfaceSize {
+        /// The requested width of the surface
+        w: u32,
+        /// The requested height of the surface
+        h: u32,
+    },
+
+    /// An IO error was encountered
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
     /// The specified character can not be rendered by any font on this system
     #[error("Unable to find a fallback font for '{0}'")]
     NoFallbackFontForChar(char),
@@ -72,22 +112,31 @@ pub enum Error {
     #[error(transparent)]
     Penrose(#[from] penrose::Error),
 
-    /// Unable to allocate a requested color
-    #[error("Unable to allocate the requested color using Xft")]
-    UnableToAllocateColor,
+    /// Unable to decode a PNG image
+    #[cfg(feature = "png")]
+    #[error(transparent)]
+    Png(#[from] png::DecodingError),
+
+    /// The background thread used for rendering popups is no longer running
+    #[error("The popup rendering thread is no longer running")]
+    PopupThreadStopped,
+
+    /// Unable to grab the keyboard in order to read user input
+    #[error("Unable to grab the keyboard")]
+    UnableToGrabKeyboard,
 
     /// Unable to open a requested font
-    #[error("Unable to open '{0}' as a font using Xft")]
+    #[error("Unable to open '{0}' as a font")]
     UnableToOpenFont(String),
 
-    /// Unable to open a font using an Xft font pattern
-    #[error("Unable to open font from FcPattern using Xft")]
-    UnableToOpenFontPattern,
-
-    /// Unable to parse an Xft font pattern
-    #[error("Unable to parse '{0}' as an Xft font patten")]
+    /// Unable to parse a fontconfig font pattern
+    #[error("Unable to parse '{0}' as a fontconfig font patten")]
     UnableToParseFontPattern(String),
 
+    /// The image being decoded uses a pixel format that is not supported
+    #[error("Unsupported image format")]
+    UnsupportedImageFormat,
+
     /// An attempt was made to work with a surface for a window that was not initialised
     /// by the [`Draw`] instance being used.
     #[error("no surface for {id}")]
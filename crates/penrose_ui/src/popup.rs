@@ -0,0 +1,468 @@
+//! Transient popup windows for flashing short messages on screen.
+//!
+//! Popups are rendered on a background thread so that they can be dismissed once their timeout
+//! expires without depending on the window manager event loop. Call [Popups::spawn] to start the
+//! thread and then use the returned [PopupHandle] to show messages from key bindings, hooks or
+//! extensions:
+//!
+//! ```no_run
+//! use penrose::core::bindings::KeyEventHandler;
+//! use penrose::x11rb::RustConn;
+//! use penrose_ui::{
+//!     core::TextStyle,
+//!     popup::{popup, Corner, Popups},
+//! };
+//! use std::time::Duration;
+//!
+//! let style = TextStyle {
+//!     fg: 0xebdbb2ff.into(),
+//!     bg: Some(0x282828ff.into()),
+//!     padding: (6, 6),
+//! };
+//!
+//! let handle = Popups::new("mono", 12, style)
+//!     .with_corner(Corner::BottomRight)
+//!     .with_timeout(Duration::from_secs(2))
+//!     .spawn()
+//!     .unwrap();
+//!
+//! let action: Box<dyn KeyEventHandler<RustConn>> = popup(handle, "Mode: resize");
+//! ```
+//!
+//! Popups can also be styled using a [Theme] by creating them with [Popups::from_theme], and
+//! can be kept in sync with the active theme using [PopupHandle::follow_active_theme].
+//! [PopupHandle::show_errors] uses popups to show errors returned from key bindings and hooks
+//! that would otherwise only be logged.
+use crate::{Draw, Error, Result, TextStyle};
+use penrose::{
+    builtin::actions::key_handler,
+    core::{bindings::KeyEventHandler, State, WindowManager},
+    extensions::util::theme::{current_theme, Theme},
+    pure::geometry::Rect,
+    x::{Atom, ClientConfig, WinType, XConn},
+    Color, Xid,
+};
+use std::{
+    sync::mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::error;
+
+const V_PADDING: u32 = 4;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+const DEFAULT_MARGIN: u32 = 10;
+const DEFAULT_MAX_VISIBLE: usize = 5;
+
+/// The corner of the screen that popups are placed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    /// The top left corner of the screen
+    TopLeft,
+    /// The top right corner of the screen
+    TopRight,
+    /// The bottom left corner of the screen
+    BottomLeft,
+    /// The bottom right corner of the screen
+    BottomRight,
+}
+
+/// Configuration for rendering transient popup windows.
+///
+/// Multiple popups shown on the same screen are stacked away from the configured [Corner] in
+/// the order they were shown, with each being dismissed once its timeout expires.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Popups {
+    font: String,
+    point_size: u8,
+    fg: Color,
+    bg: Color,
+    padding: (u32, u32),
+    corner: Corner,
+    timeout: Duration,
+    margin: u32,
+    max_visible: usize,
+}
+
+impl Popups {
+    /// Construct a new [Popups] config using the given font and style.
+    pub fn new(font: impl Into<String>, point_size: u8, style: TextStyle) -> Self {
+        Self {
+            font: font.into(),
+            point_size,
+            fg: style.fg,
+            bg: style.bg.unwrap_or_else(|| 0x000000ff.into()),
+            padding: style.padding,
+            corner: Corner::TopRight,
+            timeout: DEFAULT_TIMEOUT,
+            margin: DEFAULT_MARGIN,
+            max_visible: DEFAULT_MAX_VISIBLE,
+        }
+    }
+
+    /// Construct a new [Popups] config using the font, colors and padding of the given [Theme].
+    pub fn from_theme(theme: &Theme) -> Self {
+        Self::new(theme.font.clone(), theme.point_size, theme.into())
+    }
+
+    /// Set the screen corner that popups are placed in (top right by default).
+    pub fn with_corner(mut self, corner: Corner) -> Self {
+        self.corner = corner;
+
+        self
+    }
+
+    /// Set the default duration that popups are shown for.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+
+        self
+    }
+
+    /// Set the gap in pixels between popups and the edges of the screen.
+    pub fn with_margin(mut self, margin: u32) -> Self {
+        self.margin = margin;
+
+        self
+    }
+
+    /// Set the maximum number of popups to show on a single screen at once: older popups are
+    /// dismissed early if this limit is exceeded.
+    pub fn with_max_visible(mut self, max_visible: usize) -> Self {
+        self.max_visible = std::cmp::max(max_visible, 1);
+
+        self
+    }
+
+    /// Start the background thread used for rendering popups, returning a handle for showing
+    /// new popups.
+    ///
+    /// ### Errors
+    /// This method will error if the thread is unable to connect to the X server or load the
+    /// requested font.
+    pub fn spawn(self) -> Result<PopupHandle> {
+        let (tx, rx) = channel();
+        let (init_tx, init_rx) = sync_channel(1);
+
+        thread::spawn(move || {
+            let mut state = match PopupState::try_new(self) {
+                Ok(state) => {
+                    _ = init_tx.send(Ok(()));
+                    state
+                }
+                Err(e) => {
+                    _ = init_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            state.run(rx);
+        });
+
+        match init_rx.recv() {
+            Ok(Ok(())) => Ok(PopupHandle { tx }),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(Error::PopupThreadStopped),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Msg {
+    Show {
+        screen: usize,
+        txt: String,
+        timeout: Option<Duration>,
+    },
+    DismissAll,
+    SetTheme(Box<Theme>),
+}
+
+/// A handle for showing popups that are rendered by a background thread started using
+/// [Popups::spawn].
+#[derive(Debug, Clone)]
+pub struct PopupHandle {
+    tx: Sender<Msg>,
+}
+
+impl PopupHandle {
+    fn send(&self, msg: Msg) -> Result<()> {
+        self.tx.send(msg).map_err(|_| Error::PopupThreadStopped)
+    }
+
+    /// Show a popup containing the given text on the specified screen using the default
+    /// timeout. Multiple lines of text can be shown by including newlines.
+    pub fn show(&self, screen: usize, txt: impl Into<String>) -> Result<()> {
+        self.send(Msg::Show {
+            screen,
+            txt: txt.into(),
+            timeout: None,
+        })
+    }
+
+    /// Show a popup containing the given text on the specified screen for the given duration.
+    pub fn show_for(&self, screen: usize, txt: impl Into<String>, timeout: Duration) -> Result<()> {
+        self.send(Msg::Show {
+            screen,
+            txt: txt.into(),
+            timeout: Some(timeout),
+        })
+    }
+
+    /// Show a popup on the currently focused screen using the default timeout.
+    pub fn show_on_focused<X: XConn>(
+        &self,
+        state: &State<X>,
+        txt: impl Into<String>,
+    ) -> Result<()> {
+        self.show(state.client_set.current_screen().index(), txt)
+    }
+
+    /// Dismiss all currently visible popups.
+    pub fn dismiss_all(&self) -> Result<()> {
+        self.send(Msg::DismissAll)
+    }
+
+    /// Render new popups using the font, colors and padding of the given [Theme]. Popups that
+    /// are already visible keep their current style.
+    pub fn set_theme(&self, theme: &Theme) -> Result<()> {
+        self.send(Msg::SetTheme(Box::new(theme.clone())))
+    }
+
+    /// Add a refresh hook to the given [WindowManager] that restyles popups to match the active
+    /// [Theme] whenever it is changed using [set_theme][0].
+    ///
+    ///   [0]: penrose::extensions::util::theme::set_theme
+    pub fn follow_active_theme<X>(&self, mut wm: WindowManager<X>) -> WindowManager<X>
+    where
+        X: XConn + 'static,
+    {
+        let handle = self.clone();
+        let mut applied: Option<Theme> = None;
+
+        wm.state
+            .config
+            .compose_or_set_refresh_hook(move |state: &mut State<X>, _: &X| {
+                match current_theme(state) {
+                    Some(theme) if applied.as_ref() != Some(&theme) => {
+                        handle
+                            .set_theme(&theme)
+                            .map_err(|e| penrose::Error::Custom(e.to_string()))?;
+                        applied = Some(theme);
+                    }
+                    _ => (),
+                }
+
+                Ok(())
+            });
+
+        wm
+    }
+
+    /// Add an error hook to the given [WindowManager] that shows a popup on the focused screen
+    /// for each error returned from a key binding, mouse binding or hook, rather than the error
+    /// only being logged.
+    pub fn show_errors<X>(&self, mut wm: WindowManager<X>) -> WindowManager<X>
+    where
+        X: XConn + 'static,
+    {
+        let handle = self.clone();
+
+        wm.state.config.compose_or_set_error_hook(
+            move |error: &penrose::Error, state: &mut State<X>, _: &X| {
+                handle
+                    .show_on_focused(state, format!("Error: {error}"))
+                    .map_err(|e| penrose::Error::Custom(e.to_string()))
+            },
+        );
+
+        wm
+    }
+}
+
+/// Show a popup containing the given text on the currently focused screen.
+pub fn popup<X: XConn>(handle: PopupHandle, txt: impl Into<String>) -> Box<dyn KeyEventHandler<X>> {
+    let txt = txt.into();
+
+    key_handler(move |state: &mut State<X>, _: &X| {
+        handle
+            .show_on_focused(state, txt.clone())
+            .map_err(|e| penrose::Error::Custom(e.to_string()))
+    })
+}
+
+#[derive(Debug)]
+struct Popup {
+    id: Xid,
+    screen: usize,
+    w: u32,
+    h: u32,
+    expires: Instant,
+}
+
+#[derive(Debug)]
+struct PopupState {
+    cfg: Popups,
+    drw: Draw,
+    popups: Vec<Popup>,
+}
+
+impl PopupState {
+    fn try_new(cfg: Popups) -> Result<Self> {
+        let drw = Draw::new(&cfg.font, cfg.point_size, cfg.bg)?;
+
+        Ok(Self {
+            cfg,
+            drw,
+            popups: Vec::new(),
+        })
+    }
+
+    fn run(&mut self, rx: Receiver<Msg>) {
+        loop {
+            let next_expiry = self.popups.iter().map(|p| p.expires).min();
+            let res = match next_expiry {
+                Some(t) => rx.recv_timeout(t.saturating_duration_since(Instant::now())),
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            let res = match res {
+                Ok(Msg::Show {
+                    screen,
+                    txt,
+                    timeout,
+                }) => self.show(screen, &txt, timeout.unwrap_or(self.cfg.timeout)),
+                Ok(Msg::DismissAll) => self.dismiss(|_| true),
+                Ok(Msg::SetTheme(theme)) => self.set_theme(&theme),
+                Err(RecvTimeoutError::Timeout) => {
+                    let now = Instant::now();
+                    self.dismiss(|p| p.expires <= now)
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    _ = self.dismiss(|_| true);
+                    return;
+                }
+            };
+
+            if let Err(e) = res {
+                error!(%e, "error updating popups");
+            }
+        }
+    }
+
+    fn set_theme(&mut self, theme: &Theme) -> Result<()> {
+        self.drw.set_font(&theme.font, theme.point_size)?;
+        self.drw.set_bg(theme.bg);
+        self.cfg.font = theme.font.clone();
+        self.cfg.point_size = theme.point_size;
+        self.cfg.fg = theme.fg;
+        self.cfg.bg = theme.bg;
+        self.cfg.padding = theme.padding;
+
+        Ok(())
+    }
+
+    fn show(&mut self, screen: usize, txt: &str, timeout: Duration) -> Result<()> {
+        let lines: Vec<&str> = txt.lines().collect();
+        let (mut w, mut line_h) = (0, 0);
+        for line in lines.iter() {
+            let (lw, lh) = self.drw.text_extent(line)?;
+            w = std::cmp::max(w, lw);
+            line_h = std::cmp::max(line_h, lh);
+        }
+
+        let (lpad, rpad) = self.cfg.padding;
+        let w = w + lpad + rpad;
+        let h = line_h * lines.len() as u32 + 2 * V_PADDING;
+        let id = self.drw.new_window(
+            WinType::InputOutput(Atom::NetWindowTypeNotification),
+            Rect::new(0, 0, w, h),
+            false,
+        )?;
+
+        let mut ctx = self.drw.context_for(id)?;
+        ctx.clear()?;
+        for (i, line) in lines.iter().enumerate() {
+            ctx.set_y_offset((V_PADDING + i as u32 * line_h) as i32);
+            ctx.draw_text(line, 0, self.cfg.padding, self.cfg.fg)?;
+        }
+        ctx.flush();
+
+        self.popups.push(Popup {
+            id,
+            screen,
+            w,
+            h,
+            expires: Instant::now() + timeout,
+        });
+
+        let n_on_screen = self.popups.iter().filter(|p| p.screen == screen).count();
+        if n_on_screen > self.cfg.max_visible {
+            let oldest = self.popups.iter().position(|p| p.screen == screen);
+            if let Some(ix) = oldest {
+                let p = self.popups.remove(ix);
+                self.drw.destroy_window_and_surface(p.id)?;
+            }
+        }
+
+        self.restack()?;
+        self.drw.flush(id)
+    }
+
+    fn dismiss(&mut self, pred: impl Fn(&Popup) -> bool) -> Result<()> {
+        let (expired, remaining): (Vec<_>, Vec<_>) = self.popups.drain(..).partition(|p| pred(p));
+        self.popups = remaining;
+
+        for p in expired.iter() {
+            self.drw.destroy_window_and_surface(p.id)?;
+        }
+
+        if !expired.is_empty() {
+            self.restack()?;
+        }
+
+        Ok(())
+    }
+
+    // Position popups on each screen, stacking them away from the configured corner in the order
+    // that they were shown.
+    fn restack(&mut self) -> Result<()> {
+        let screens = self.drw.conn.screen_details()?;
+        let margin = self.cfg.margin;
+        let mut offsets = vec![margin; screens.len()];
+
+        for p in self.popups.iter() {
+            let s = match screens.get(p.screen).or(screens.first()) {
+                Some(s) => *s,
+                None => continue,
+            };
+            let ix = if p.screen < screens.len() {
+                p.screen
+            } else {
+                0
+            };
+            let offset = offsets[ix];
+
+            let x = match self.cfg.corner {
+                Corner::TopLeft | Corner::BottomLeft => s.x + margin,
+                Corner::TopRight | Corner::BottomRight => (s.x + s.w).saturating_sub(margin + p.w),
+            };
+            let y = match self.cfg.corner {
+                Corner::TopLeft | Corner::TopRight => s.y + offset,
+                Corner::BottomLeft | Corner::BottomRight => {
+                    (s.y + s.h).saturating_sub(offset + p.h)
+                }
+            };
+
+            let r = Rect::new(x, y, p.w, p.h);
+            self.drw
+                .conn
+                .set_client_config(p.id, &[ClientConfig::Position(r), ClientConfig::StackTop])?;
+            offsets[ix] += p.h + margin;
+        }
+
+        self.drw.conn.flush();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,650 @@
+//! A simple text input prompt for reading user input from within the window manager.
+//!
+//! A [Prompt] renders a single line input window along the top of a screen and grabs the
+//! keyboard until the input is either accepted (`Return`) or cancelled (`Escape`, `C-c` or
+//! `C-g`). While the prompt is open the window manager event loop is blocked, so prompts are
+//! intended to be run from key bindings using the [prompt] and [spawn_prompt] helpers.
+//!
+//! # Editing
+//! - `Left` / `Right`, `Home` / `End`, `C-a` / `C-e`: move the cursor
+//! - `BackSpace` / `Delete`: delete the character before / after the cursor
+//! - `C-w`: delete the word before the cursor
+//! - `C-u` / `C-k`: delete to the start / end of the line
+//! - `Up` / `Down`, `C-p` / `C-n`: move through previously accepted input
+//! - `Tab` / `Shift-Tab`: cycle through the current completions (if any)
+use crate::{Draw, Error, Result, TextStyle};
+use penrose::{
+    builtin::actions::key_handler,
+    core::{
+        bindings::{KeyCode, KeyEventHandler},
+        State,
+    },
+    pure::geometry::Rect,
+    util::spawn,
+    x::{Atom, WinType, XConn, XEvent},
+    x11rb::RustConn,
+    Color, Xid,
+};
+use std::{cmp::max, fmt, thread::sleep, time::Duration};
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{ConnectionExt as _, GrabMode, GrabStatus},
+    CURRENT_TIME,
+};
+
+const PADDING: u32 = 4;
+const MAX_HISTORY: usize = 100;
+const GRAB_ATTEMPTS: usize = 100;
+const GRAB_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+// Modifier masks from the core X protocol
+const SHIFT_MASK: u16 = 1 << 0;
+const LOCK_MASK: u16 = 1 << 1;
+const CONTROL_MASK: u16 = 1 << 2;
+
+// Keysyms used for editing (see X11/keysymdef.h)
+pub(crate) const XK_BACKSPACE: u32 = 0xff08;
+const XK_TAB: u32 = 0xff09;
+const XK_ISO_LEFT_TAB: u32 = 0xfe20;
+pub(crate) const XK_RETURN: u32 = 0xff0d;
+pub(crate) const XK_ESCAPE: u32 = 0xff1b;
+const XK_HOME: u32 = 0xff50;
+const XK_LEFT: u32 = 0xff51;
+const XK_UP: u32 = 0xff52;
+const XK_RIGHT: u32 = 0xff53;
+const XK_DOWN: u32 = 0xff54;
+const XK_END: u32 = 0xff57;
+pub(crate) const XK_KP_ENTER: u32 = 0xff8d;
+const XK_DELETE: u32 = 0xffff;
+
+/// A function for generating completions for the current input of a [Prompt].
+pub type CompletionFn = Box<dyn Fn(&str) -> Vec<String>>;
+
+/// A single line text input prompt with optional completions and input history.
+///
+/// See the module level docs for details of the supported key bindings.
+pub struct Prompt {
+    drw: Draw,
+    fg: Color,
+    bg: Color,
+    highlight: Color,
+    completions: Option<CompletionFn>,
+    history: Vec<String>,
+    max_history: usize,
+}
+
+impl fmt::Debug for Prompt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Prompt")
+            .field("drw", &self.drw)
+            .field("fg", &self.fg)
+            .field("bg", &self.bg)
+            .field("highlight", &self.highlight)
+            .field("history", &self.history)
+            .field("max_history", &self.max_history)
+            .finish()
+    }
+}
+
+impl Prompt {
+    /// Construct a new [Prompt] using the given font and style.
+    ///
+    /// The `highlight` color is used as the background for the prompt label and the currently
+    /// selected completion.
+    pub fn new(
+        font: &str,
+        point_size: u8,
+        style: TextStyle,
+        highlight: impl Into<Color>,
+    ) -> Result<Self> {
+        let bg = style.bg.unwrap_or_else(|| 0x000000ff.into());
+
+        Ok(Self {
+            drw: Draw::new(font, point_size, bg)?,
+            fg: style.fg,
+            bg,
+            highlight: highlight.into(),
+            completions: None,
+            history: Vec::new(),
+            max_history: MAX_HISTORY,
+        })
+    }
+
+    /// Generate completions for the current input using the provided function.
+    ///
+    /// Completions are recomputed each time the input changes and are shown in the order they
+    /// are returned.
+    pub fn with_completions<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str) -> Vec<String> + 'static,
+    {
+        self.completions = Some(Box::new(f));
+
+        self
+    }
+
+    /// Set the maximum number of previously accepted inputs to remember.
+    pub fn with_max_history(mut self, max_history: usize) -> Self {
+        self.max_history = max_history;
+        self.history.truncate(max_history);
+
+        self
+    }
+
+    /// Previously accepted input for this prompt, oldest first.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Show the prompt along the top of the given screen region and block until the user
+    /// either accepts or cancels their input.
+    ///
+    /// Returns `None` if the input was cancelled.
+    pub fn run(&mut self, label: &str, screen: Rect) -> Result<Option<String>> {
+        let (_, th) = self
+            .drw
+            .text_extent(if label.is_empty() { " " } else { label })?;
+        let r = Rect::new(screen.x, screen.y, screen.w, th + 2 * PADDING);
+        let win = self
+            .drw
+            .new_window(WinType::InputOutput(Atom::NetWindowTypeDialog), r, false)?;
+
+        let res = self.run_in(win, label, r);
+        let conn = self.drw.conn.connection();
+        let _ = conn.ungrab_keyboard(CURRENT_TIME);
+        self.drw.destroy_window_and_surface(win)?;
+        self.drw.conn.flush();
+
+        let accepted = res?;
+        if let Some(s) = &accepted {
+            self.push_history(s);
+        }
+
+        Ok(accepted)
+    }
+
+    fn push_history(&mut self, s: &str) {
+        if s.is_empty() || self.max_history == 0 {
+            return;
+        }
+
+        self.history.retain(|h| h != s);
+        self.history.push(s.to_owned());
+        if self.history.len() > self.max_history {
+            self.history.remove(0);
+        }
+    }
+
+    fn run_in(&mut self, win: Xid, label: &str, r: Rect) -> Result<Option<String>> {
+        grab_keyboard(&self.drw.conn, win)?;
+        let keymap = Keymap::try_new(&self.drw.conn)?;
+        let mut st = PromptState::default();
+        self.update_completions(&mut st);
+        self.render(win, label, &st, r)?;
+
+        loop {
+            match self.drw.conn.next_event()? {
+                XEvent::KeyPress(k) => match self.handle_key(keymap.keysym(k), k.mask, &mut st) {
+                    Outcome::Continue => self.render(win, label, &st, r)?,
+                    Outcome::Accept(s) => return Ok(Some(s)),
+                    Outcome::Cancel => return Ok(None),
+                },
+
+                XEvent::Expose(_) => self.render(win, label, &st, r)?,
+
+                _ => (),
+            }
+        }
+    }
+
+    fn update_completions(&self, st: &mut PromptState) {
+        st.selected = None;
+        st.completions = match &self.completions {
+            Some(f) => f(&st.input.txt),
+            None => Vec::new(),
+        };
+    }
+
+    fn set_from_history(&self, st: &mut PromptState, ix: Option<usize>) {
+        st.history_ix = ix;
+        match ix {
+            Some(i) => st.input.set(&self.history[i]),
+            None => st.input.set(""),
+        }
+        self.update_completions(st);
+    }
+
+    fn handle_key(&self, keysym: u32, mask: u16, st: &mut PromptState) -> Outcome {
+        let ctrl = mask & CONTROL_MASK != 0;
+        let n_history = self.history.len();
+        let n_completions = st.completions.len();
+        let before = st.input.txt.clone();
+
+        match (keysym, ctrl) {
+            (XK_ESCAPE, _) => return Outcome::Cancel,
+            (c, true) if c == 'c' as u32 || c == 'g' as u32 => return Outcome::Cancel,
+
+            (XK_RETURN | XK_KP_ENTER, _) => {
+                let s = match st.selected {
+                    Some(i) => st.completions[i].clone(),
+                    None => st.input.txt.clone(),
+                };
+                return Outcome::Accept(s);
+            }
+
+            (XK_TAB, _) if n_completions > 0 => {
+                st.selected = Some(st.selected.map(|i| (i + 1) % n_completions).unwrap_or(0));
+            }
+            (XK_ISO_LEFT_TAB, _) if n_completions > 0 => {
+                st.selected = Some(
+                    st.selected
+                        .map(|i| (i + n_completions - 1) % n_completions)
+                        .unwrap_or(n_completions - 1),
+                );
+            }
+
+            (XK_UP, _) | (0x70, true) if n_history > 0 => {
+                let ix = st
+                    .history_ix
+                    .map(|i| i.saturating_sub(1))
+                    .unwrap_or(n_history - 1);
+                self.set_from_history(st, Some(ix));
+                return Outcome::Continue;
+            }
+            (XK_DOWN, _) | (0x6e, true) if st.history_ix.is_some() => {
+                let ix = st
+                    .history_ix
+                    .and_then(|i| (i + 1 < n_history).then_some(i + 1));
+                self.set_from_history(st, ix);
+                return Outcome::Continue;
+            }
+
+            (XK_BACKSPACE, _) => st.input.backspace(),
+            (XK_DELETE, _) => st.input.delete(),
+            (XK_LEFT, _) => st.input.left(),
+            (XK_RIGHT, _) => st.input.right(),
+            (XK_HOME, _) | (0x61, true) => st.input.home(),
+            (XK_END, _) | (0x65, true) => st.input.end(),
+            (0x77, true) => st.input.delete_word(),
+            (0x75, true) => st.input.kill_to_start(),
+            (0x6b, true) => st.input.kill_to_end(),
+
+            (ks, false) => {
+                if let Some(c) = keysym_to_char(ks) {
+                    st.input.insert(c);
+                }
+            }
+
+            _ => (),
+        }
+
+        if st.input.txt != before {
+            st.history_ix = None;
+            self.update_completions(st);
+        }
+
+        Outcome::Continue
+    }
+
+    fn render(&mut self, win: Xid, label: &str, st: &PromptState, r: Rect) -> Result<()> {
+        let (w, h) = (r.w, r.h);
+        let mut ctx = self.drw.context_for(win)?;
+        ctx.clear()?;
+
+        let mut x = 0;
+        if !label.is_empty() {
+            let (lw, _) = ctx.text_extent(label)?;
+            ctx.fill_rect(Rect::new(0, 0, lw + 2 * PADDING, h), self.highlight)?;
+            ctx.draw_text(label, PADDING, (PADDING, PADDING), self.fg)?;
+            x = lw + 2 * PADDING;
+        }
+
+        ctx.set_x_offset(x as i32);
+        let (cw, _) = ctx.text_extent(&st.input.txt[..st.input.cursor])?;
+        let (iw, _) = ctx.text_extent(&st.input.txt)?;
+        ctx.draw_text(&st.input.txt, PADDING, (PADDING, PADDING), self.fg)?;
+        ctx.fill_rect(
+            Rect::new(PADDING + cw, PADDING, 2, h - 2 * PADDING),
+            self.fg,
+        )?;
+        x += max(iw + 2 * PADDING, w / 3);
+
+        for (i, c) in st.completions.iter().enumerate() {
+            let (cw, _) = ctx.text_extent(c)?;
+            if x + cw + 2 * PADDING > w {
+                break;
+            }
+
+            ctx.set_x_offset(x as i32);
+            let bg = if st.selected == Some(i) {
+                self.highlight
+            } else {
+                self.bg
+            };
+            ctx.fill_rect(Rect::new(0, 0, cw + 2 * PADDING, h), bg)?;
+            ctx.draw_text(c, PADDING, (PADDING, PADDING), self.fg)?;
+            x += cw + 2 * PADDING;
+        }
+
+        ctx.flush();
+        self.drw.flush(win)
+    }
+}
+
+/// Show a [Prompt] on the currently focused screen, passing the accepted input to `on_accept`.
+///
+/// Nothing is run if the prompt is cancelled.
+pub fn prompt<X, F>(
+    mut prompt: Prompt,
+    label: impl Into<String>,
+    mut on_accept: F,
+) -> Box<dyn KeyEventHandler<X>>
+where
+    X: XConn,
+    F: FnMut(String, &mut State<X>, &X) -> penrose::Result<()> + 'static,
+{
+    let label = label.into();
+
+    key_handler(move |state: &mut State<X>, x: &X| {
+        let r = state.client_set.current_screen().geometry();
+        match prompt.run(&label, r) {
+            Ok(Some(s)) => on_accept(s, state, x),
+            Ok(None) => Ok(()),
+            Err(e) => Err(penrose::Error::Custom(format!("error running prompt: {e}"))),
+        }
+    })
+}
+
+/// A simple run dialog: show a [Prompt] and spawn the accepted input as a command.
+pub fn spawn_prompt<X: XConn>(p: Prompt) -> Box<dyn KeyEventHandler<X>> {
+    prompt(p, "run", |cmd: String, _: &mut State<X>, _: &X| {
+        if cmd.trim().is_empty() {
+            Ok(())
+        } else {
+            spawn(cmd)
+        }
+    })
+}
+
+enum Outcome {
+    Continue,
+    Accept(String),
+    Cancel,
+}
+
+#[derive(Debug, Default)]
+struct PromptState {
+    input: Input,
+    completions: Vec<String>,
+    selected: Option<usize>,
+    history_ix: Option<usize>,
+}
+
+// The current input line along with the byte offset of the cursor
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct Input {
+    txt: String,
+    cursor: usize,
+}
+
+impl Input {
+    fn set(&mut self, s: &str) {
+        self.txt = s.to_owned();
+        self.cursor = self.txt.len();
+    }
+
+    fn insert(&mut self, c: char) {
+        self.txt.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    fn backspace(&mut self) {
+        if let Some(c) = self.txt[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.txt.remove(self.cursor);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.txt.len() {
+            self.txt.remove(self.cursor);
+        }
+    }
+
+    fn left(&mut self) {
+        if let Some(c) = self.txt[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    fn right(&mut self) {
+        if let Some(c) = self.txt[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    fn end(&mut self) {
+        self.cursor = self.txt.len();
+    }
+
+    fn delete_word(&mut self) {
+        let before = self.txt[..self.cursor].trim_end();
+        let start = before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or(0);
+
+        self.txt.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    fn kill_to_start(&mut self) {
+        self.txt.replace_range(..self.cursor, "");
+        self.cursor = 0;
+    }
+
+    fn kill_to_end(&mut self) {
+        self.txt.truncate(self.cursor);
+    }
+}
+
+pub(crate) fn grab_keyboard(conn: &RustConn, win: Xid) -> Result<()> {
+    let conn = conn.connection();
+
+    // The key binding used to open the prompt may still be held, in which case the window
+    // manager will have an active grab on the keyboard until it is released.
+    for _ in 0..GRAB_ATTEMPTS {
+        let reply = conn
+            .grab_keyboard(true, *win, CURRENT_TIME, GrabMode::ASYNC, GrabMode::ASYNC)
+            .map_err(penrose::Error::from)?
+            .reply()
+            .map_err(penrose::Error::from)?;
+
+        if reply.status == GrabStatus::SUCCESS {
+            return Ok(());
+        }
+
+        sleep(GRAB_RETRY_DELAY);
+    }
+
+    Err(Error::UnableToGrabKeyboard)
+}
+
+// The keysyms for each keycode as reported by the X server
+pub(crate) struct Keymap {
+    min_keycode: u8,
+    per_keycode: usize,
+    keysyms: Vec<u32>,
+}
+
+impl Keymap {
+    pub(crate) fn try_new(conn: &RustConn) -> Result<Self> {
+        let conn = conn.connection();
+        let setup = conn.setup();
+        let (min_keycode, max_keycode) = (setup.min_keycode, setup.max_keycode);
+        let reply = conn
+            .get_keyboard_mapping(min_keycode, max_keycode - min_keycode + 1)
+            .map_err(penrose::Error::from)?
+            .reply()
+            .map_err(penrose::Error::from)?;
+
+        Ok(Self {
+            min_keycode,
+            per_keycode: reply.keysyms_per_keycode as usize,
+            keysyms: reply.keysyms,
+        })
+    }
+
+    pub(crate) fn keysym(&self, k: KeyCode) -> u32 {
+        let ix = k.code.saturating_sub(self.min_keycode) as usize * self.per_keycode;
+        let syms = match self.keysyms.get(ix..ix + self.per_keycode) {
+            Some(syms) if !syms.is_empty() => syms,
+            _ => return 0,
+        };
+
+        let lower = syms[0];
+        let upper = match syms.get(1) {
+            Some(&ks) if ks != 0 => ks,
+            _ => keysym_to_char(lower)
+                .and_then(|c| c.to_uppercase().next())
+                .map(|c| c as u32)
+                .unwrap_or(lower),
+        };
+
+        let shift = k.mask & SHIFT_MASK != 0;
+        let caps =
+            k.mask & LOCK_MASK != 0 && keysym_to_char(lower).is_some_and(char::is_alphabetic);
+
+        if shift ^ caps {
+            upper
+        } else {
+            lower
+        }
+    }
+}
+
+// Latin-1 keysyms map directly to their unicode code points while other unicode characters are
+// encoded with a 0x01000000 prefix.
+pub(crate) fn keysym_to_char(ks: u32) -> Option<char> {
+    match ks {
+        0x20..=0x7e | 0xa0..=0xff => char::from_u32(ks),
+        0x0100_0100..=0x0110_ffff => char::from_u32(ks - 0x0100_0000),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    fn input(txt: &str, cursor: usize) -> Input {
+        Input {
+            txt: txt.to_owned(),
+            cursor,
+        }
+    }
+
+    #[test]
+    fn set_moves_the_cursor_to_the_end() {
+        let mut i = input("foo", 1);
+        i.set("héllo");
+
+        assert_eq!(i, input("héllo", 6));
+    }
+
+    #[test_case(input("", 0), 'a', input("a", 1); "empty")]
+    #[test_case(input("ac", 1), 'b', input("abc", 2); "middle")]
+    #[test_case(input("ab", 2), 'é', input("abé", 4); "multibyte")]
+    #[test_case(input("é", 0), 'a', input("aé", 1); "before multibyte")]
+    #[test]
+    fn insert(mut i: Input, c: char, expected: Input) {
+        i.insert(c);
+
+        assert_eq!(i, expected);
+    }
+
+    #[test_case(input("", 0), input("", 0); "empty")]
+    #[test_case(input("abc", 0), input("abc", 0); "at start")]
+    #[test_case(input("abc", 2), input("ac", 1); "middle")]
+    #[test_case(input("aé", 3), input("a", 1); "multibyte")]
+    #[test]
+    fn backspace(mut i: Input, expected: Input) {
+        i.backspace();
+
+        assert_eq!(i, expected);
+    }
+
+    #[test_case(input("abc", 3), input("abc", 3); "at end")]
+    #[test_case(input("abc", 1), input("ac", 1); "middle")]
+    #[test_case(input("éa", 0), input("a", 0); "multibyte")]
+    #[test]
+    fn delete(mut i: Input, expected: Input) {
+        i.delete();
+
+        assert_eq!(i, expected);
+    }
+
+    #[test]
+    fn cursor_movement_respects_char_boundaries() {
+        let mut i = input("aé日", 0);
+
+        let mut positions = vec![];
+        for _ in 0..4 {
+            i.right();
+            positions.push(i.cursor);
+        }
+        assert_eq!(positions, vec![1, 3, 6, 6]);
+
+        positions.clear();
+        for _ in 0..4 {
+            i.left();
+            positions.push(i.cursor);
+        }
+        assert_eq!(positions, vec![3, 1, 0, 0]);
+
+        i.end();
+        assert_eq!(i.cursor, 6);
+        i.home();
+        assert_eq!(i.cursor, 0);
+    }
+
+    #[test_case(input("foo bar", 7), input("foo ", 4); "last word")]
+    #[test_case(input("foo bar  ", 9), input("foo ", 4); "trailing whitespace")]
+    #[test_case(input("foo bar baz", 7), input("foo  baz", 4); "middle word")]
+    #[test_case(input("foo", 3), input("", 0); "single word")]
+    #[test_case(input("héllo wörld", 13), input("héllo ", 7); "multibyte")]
+    #[test_case(input("", 0), input("", 0); "empty")]
+    #[test]
+    fn delete_word(mut i: Input, expected: Input) {
+        i.delete_word();
+
+        assert_eq!(i, expected);
+    }
+
+    #[test]
+    fn kill_to_start_and_end() {
+        let mut i = input("foo bar", 3);
+        i.kill_to_end();
+        assert_eq!(i, input("foo", 3));
+
+        let mut i = input("foo bar", 3);
+        i.kill_to_start();
+        assert_eq!(i, input(" bar", 0));
+    }
+
+    #[test_case(0x61, Some('a'); "ascii")]
+    #[test_case(0xe9, Some('é'); "latin 1")]
+    #[test_case(0x0100_65e5, Some('日'); "unicode")]
+    #[test_case(0xff0d, None; "return key")]
+    #[test]
+    fn keysym_to_char_works(ks: u32, expected: Option<char>) {
+        assert_eq!(keysym_to_char(ks), expected);
+    }
+}
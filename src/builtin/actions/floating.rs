@@ -1,6 +1,9 @@
 //! Actions for manipulating floating windows.
 use crate::{
-    builtin::actions::{key_handler, modify_with},
+    builtin::{
+        actions::{key_handler, modify_with},
+        layout::messages::{MoveFocused, ResizeFocused, ResizeMain},
+    },
     core::{
         bindings::{
             KeyEventHandler, MotionNotifyEvent, MouseEvent, MouseEventHandler, MouseEventKind,
@@ -16,7 +19,12 @@ use tracing::error;
 
 /// Resize a currently floating window by a given (width, height) delta
 ///
+/// If the focused window is not floating then a [ResizeFocused] message is sent to the active
+/// layout instead, allowing windows placed by the [Floating][0] layout to be resized.
+///
 /// Screen coordinates are 0-indexed from the top left corner of the sceen.
+///
+///   [0]: crate::builtin::layout::Floating
 pub fn resize<X: XConn>(dw: i32, dh: i32) -> Box<dyn KeyEventHandler<X>> {
     modify_with(move |cs| {
         let id = match cs.current_client() {
@@ -24,6 +32,12 @@ pub fn resize<X: XConn>(dw: i32, dh: i32) -> Box<dyn KeyEventHandler<X>> {
             None => return,
         };
 
+        if !cs.is_floating(&id) {
+            cs.current_workspace_mut()
+                .handle_message(ResizeFocused(dw, dh));
+            return;
+        }
+
         cs.floating.entry(id).and_modify(|r| {
             *r = r.apply_as_rect(&cs.screens.focus.r, |mut r| {
                 r.resize(dw, dh);
@@ -35,7 +49,12 @@ pub fn resize<X: XConn>(dw: i32, dh: i32) -> Box<dyn KeyEventHandler<X>> {
 
 /// Move a currently floating window by a given (x, y) delta
 ///
+/// If the focused window is not floating then a [MoveFocused] message is sent to the active
+/// layout instead, allowing windows placed by the [Floating][0] layout to be moved.
+///
 /// Screen coordinates are 0-indexed from the top left corner of the sceen.
+///
+///   [0]: crate::builtin::layout::Floating
 pub fn reposition<X: XConn>(dx: i32, dy: i32) -> Box<dyn KeyEventHandler<X>> {
     modify_with(move |cs| {
         let id = match cs.current_client() {
@@ -43,6 +62,12 @@ pub fn reposition<X: XConn>(dx: i32, dy: i32) -> Box<dyn KeyEventHandler<X>> {
             None => return,
         };
 
+        if !cs.is_floating(&id) {
+            cs.current_workspace_mut()
+                .handle_message(MoveFocused(dx, dy));
+            return;
+        }
+
         cs.floating.entry(id).and_modify(|r| {
             *r = r.apply_as_rect(&cs.screens.focus.r, |mut r| {
                 r.reposition(dx, dy);
@@ -149,7 +174,9 @@ impl ClickData {
         //    mouse button is released and the default position_clients logic
         //    runs using the Rect that we store above.
         let border = state.config.border_width;
-        x.position_client(id, r.shrink_in(border))?;
+        let r = r.shrink_in(border);
+        x.position_client(id, r)?;
+        state.applied_positions.borrow_mut().remove(&id);
 
         Ok(())
     }
@@ -198,10 +225,71 @@ trait ClickWrapper {
     }
 }
 
+#[derive(Debug, Clone)]
+struct TiledDragData {
+    id: Xid,
+    tag: String,
+    target: Option<(Xid, Rect)>,
+}
+
+impl TiledDragData {
+    fn new<X: XConn>(id: Xid, state: &State<X>) -> Option<Self> {
+        let tag = state.client_set.tag_for_client(&id)?;
+
+        Some(Self {
+            id,
+            tag: tag.to_string(),
+            target: None,
+        })
+    }
+
+    // Drop targets are the other tiled clients on the same workspace, using the positions they
+    // were given by the layout when the window manager last refreshed.
+    fn on_motion<X: XConn>(&mut self, rpt: Point, state: &State<X>) {
+        let cs = &state.client_set;
+
+        self.target = state
+            .client_at_point(rpt)
+            .filter(|c| {
+                *c != self.id
+                    && cs.tag_for_client(c) == Some(self.tag.as_str())
+                    && !cs.is_floating(c)
+            })
+            .and_then(|c| Some((c, state.client_rect(c)?)));
+    }
+
+    fn on_release<X: XConn>(&self, state: &mut State<X>, x: &X) -> Result<()> {
+        let target = match self.target {
+            Some((target, _)) => target,
+            None => return Ok(()),
+        };
+
+        let (id, tag) = (self.id, &self.tag);
+        x.modify_and_refresh(state, |cs| {
+            if let Some(stack) = cs.workspace_mut(tag).and_then(|ws| ws.stack.as_mut()) {
+                let i = stack.iter().position(|&c| c == id);
+                let j = stack.iter().position(|&c| c == target);
+                if let (Some(i), Some(j)) = (i, j) {
+                    stack.swap_nth(i, j);
+                }
+            }
+        })
+    }
+}
+
 /// A simple mouse event handler for dragging a window
+///
+/// By default, clicking on a tiled window will float it before it is moved. If tiled swapping
+/// is enabled then dragging a tiled window and dropping it on top of another tiled window on
+/// the same workspace will instead swap the positions of the two windows in the stack, leaving
+/// both of them tiled. The window currently under the pointer is available from
+/// [drop_target][MouseDragHandler::drop_target] while the drag is in progress so that it can be
+/// highlighted.
 #[derive(Debug, Default, Clone)]
 pub struct MouseDragHandler {
     data: Option<ClickData>,
+    tiled: Option<TiledDragData>,
+    swap_tiled: bool,
 }
 
 impl MouseDragHandler {
@@ -209,6 +297,24 @@ impl MouseDragHandler {
     pub fn boxed_default<X: XConn>() -> Box<dyn MouseEventHandler<X>> {
         Box::<MouseDragHandler>::default()
     }
+
+    /// Construct a boxed [MouseEventHandler] trait object that swaps tiled windows by dropping
+    /// them on top of one another rather than floating them.
+    pub fn boxed_with_tiled_swapping<X: XConn>() -> Box<dyn MouseEventHandler<X>> {
+        Box::new(MouseDragHandler::default().with_tiled_swapping())
+    }
+
+    /// Swap tiled windows by dropping them on top of one another rather than floating them.
+    pub fn with_tiled_swapping(mut self) -> Self {
+        self.swap_tiled = true;
+        self
+    }
+
+    /// The on screen position of the tiled window that the window currently being dragged
+    /// would be swapped with if it was dropped now.
+    pub fn drop_target(&self) -> Option<Rect> {
+        self.tiled.as_ref()?.target.map(|(_, r)| r)
+    }
 }
 
 impl ClickWrapper for MouseDragHandler {
@@ -223,18 +329,108 @@ impl ClickWrapper for MouseDragHandler {
 
 impl<X: XConn> MouseEventHandler<X> for MouseDragHandler {
     fn on_mouse_event(&mut self, evt: &MouseEvent, state: &mut State<X>, x: &X) -> Result<()> {
+        let id = evt.data.id;
+
+        match evt.kind {
+            MouseEventKind::Press
+                if self.swap_tiled
+                    && state.client_set.contains(&id)
+                    && !state.client_set.is_floating(&id) =>
+            {
+                self.tiled = TiledDragData::new(id, state);
+                if self.tiled.is_some() {
+                    return Ok(());
+                }
+            }
+
+            MouseEventKind::Release => {
+                if let Some(data) = self.tiled.take() {
+                    return data.on_release(state, x);
+                }
+            }
+
+            _ => (),
+        }
+
         ClickWrapper::on_mouse_event(self, evt, state, x)
     }
 
     fn on_motion(&mut self, evt: &MotionNotifyEvent, state: &mut State<X>, x: &X) -> Result<()> {
-        ClickWrapper::on_motion(self, evt, state, x)
+        match self.tiled.as_mut() {
+            Some(data) => {
+                data.on_motion(evt.data.rpt, state);
+                Ok(())
+            }
+            None => ClickWrapper::on_motion(self, evt, state, x),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TiledResizeData {
+    tag: String,
+    last: Point,
+    area: Rect,
+}
+
+impl TiledResizeData {
+    // The area used to convert pointer motion into layout ratios is the bounding box of the
+    // tiled clients on the workspace as they were last positioned, so that any gaps applied
+    // by the layout are taken into account.
+    fn new<X: XConn>(id: Xid, rpt: Point, state: &State<X>) -> Option<Self> {
+        let cs = &state.client_set;
+        let tag = cs.tag_for_client(&id)?;
+
+        let mut rects = state.client_rects().filter_map(|(c, r)| {
+            (cs.tag_for_client(&c) == Some(tag) && !cs.is_floating(&c)).then_some(r)
+        });
+
+        let first = rects.next()?;
+        let (mut x1, mut y1) = (first.x, first.y);
+        let (mut x2, mut y2) = (first.x + first.w, first.y + first.h);
+        for r in rects {
+            x1 = x1.min(r.x);
+            y1 = y1.min(r.y);
+            x2 = x2.max(r.x + r.w);
+            y2 = y2.max(r.y + r.h);
+        }
+
+        if x2 == x1 || y2 == y1 {
+            return None;
+        }
+
+        Some(Self {
+            tag: tag.to_string(),
+            last: rpt,
+            area: Rect::new(x1, y1, x2 - x1, y2 - y1),
+        })
+    }
+
+    fn on_motion<X: XConn>(&mut self, rpt: Point, state: &mut State<X>, x: &X) -> Result<()> {
+        let dx = (rpt.x as i32 - self.last.x as i32) as f32 / self.area.w as f32;
+        let dy = (rpt.y as i32 - self.last.y as i32) as f32 / self.area.h as f32;
+        self.last = rpt;
+
+        let tag = &self.tag;
+        x.modify_and_refresh(state, |cs| {
+            if let Some(ws) = cs.workspace_mut(tag) {
+                ws.handle_message(ResizeMain { dx, dy });
+            }
+        })
     }
 }
 
 /// A simple mouse event handler for resizing a window
+///
+/// By default, clicking on a tiled window will float it before it is resized. If tiled
+/// resizing is enabled then dragging a tiled window will instead send [ResizeMain] messages to
+/// the layout of its workspace, moving the boundary between the main and secondary regions
+/// along with the mouse.
 #[derive(Debug, Default, Clone)]
 pub struct MouseResizeHandler {
     data: Option<ClickData>,
+    tiled: Option<TiledResizeData>,
+    resize_tiled: bool,
 }
 
 impl MouseResizeHandler {
@@ -242,6 +438,19 @@ impl MouseResizeHandler {
     pub fn boxed_default<X: XConn>() -> Box<dyn MouseEventHandler<X>> {
         Box::<MouseResizeHandler>::default()
     }
+
+    /// Construct a boxed [MouseEventHandler] trait object that resizes tiled windows by
+    /// adjusting the active layout rather than floating them.
+    pub fn boxed_with_tiled_resizing<X: XConn>() -> Box<dyn MouseEventHandler<X>> {
+        Box::new(MouseResizeHandler::default().with_tiled_resizing())
+    }
+
+    /// Resize tiled windows by sending [ResizeMain] messages to the active layout rather
+    /// than floating them.
+    pub fn with_tiled_resizing(mut self) -> Self {
+        self.resize_tiled = true;
+        self
+    }
 }
 
 impl ClickWrapper for MouseResizeHandler {
@@ -256,10 +465,32 @@ impl ClickWrapper for MouseResizeHandler {
 
 impl<X: XConn> MouseEventHandler<X> for MouseResizeHandler {
     fn on_mouse_event(&mut self, evt: &MouseEvent, state: &mut State<X>, x: &X) -> Result<()> {
+        let id = evt.data.id;
+
+        match evt.kind {
+            MouseEventKind::Press
+                if self.resize_tiled
+                    && state.client_set.contains(&id)
+                    && !state.client_set.is_floating(&id) =>
+            {
+                self.tiled = TiledResizeData::new(id, evt.data.rpt, state);
+                if self.tiled.is_some() {
+                    return Ok(());
+                }
+            }
+
+            MouseEventKind::Release => self.tiled = None,
+
+            _ => (),
+        }
+
         ClickWrapper::on_mouse_event(self, evt, state, x)
     }
 
     fn on_motion(&mut self, evt: &MotionNotifyEvent, state: &mut State<X>, x: &X) -> Result<()> {
-        ClickWrapper::on_motion(self, evt, state, x)
+        match self.tiled.as_mut() {
+            Some(data) => data.on_motion(evt.data.rpt, state, x),
+            None => ClickWrapper::on_motion(self, evt, state, x),
+        }
     }
 }
@@ -1,11 +1,13 @@
 //! Helpers and pre-defined actions for use in user defined key bindings
 use crate::{
-    core::{bindings::KeyEventHandler, layout::IntoMessage, ClientSet, State},
-    util,
+    builtin::layout::messages::{Balance, IncGaps, SetGaps, ToggleGaps},
+    core::{bindings::KeyEventHandler, layout::IntoMessage, BorderStyle, ClientSet, State},
+    util::SpawnSpec,
     x::{XConn, XConnExt},
-    Result,
+    Result, Xid,
 };
-use tracing::info;
+use std::collections::HashMap;
+use tracing::{debug_span, info, span::EnteredSpan};
 
 pub mod floating;
 
@@ -31,6 +33,12 @@ where
     Box::new(move |s: &mut State<X>, x: &X| x.modify_and_refresh(s, f.clone()))
 }
 
+// Layout messages that are not recognised by any layout are logged by the LayoutStack they
+// were sent to: running inside of this span attaches the event that triggered the message.
+fn layout_message_span<X: XConn>(s: &State<X>) -> EnteredSpan {
+    debug_span!("layout_message", event = ?s.current_event()).entered()
+}
+
 /// Send a message to the currently active layout
 pub fn send_layout_message<F, M, X>(f: F) -> Box<dyn KeyEventHandler<X>>
 where
@@ -39,6 +47,7 @@ where
     X: XConn,
 {
     key_handler(move |s: &mut State<X>, x: &X| {
+        let _span = layout_message_span(s);
         x.modify_and_refresh(s, |cs| {
             cs.current_workspace_mut().handle_message(f());
         })
@@ -53,18 +62,171 @@ where
     X: XConn,
 {
     key_handler(move |s: &mut State<X>, x: &X| {
+        let _span = layout_message_span(s);
         x.modify_and_refresh(s, |cs| {
             cs.current_workspace_mut().broadcast_message(f());
         })
     })
 }
 
+/// Send a message to all layouts available to every workspace, including those that are not
+/// currently visible.
+///
+/// This is useful for global toggles such as disabling gaps everywhere:
+/// ```no_run
+/// # use penrose::{
+/// #     builtin::{
+/// #         actions::broadcast_layout_message_to_all_workspaces, layout::messages::ToggleGaps,
+/// #     },
+/// #     core::bindings::KeyEventHandler, x11rb::RustConn,
+/// # };
+/// let handler: Box<dyn KeyEventHandler<RustConn>> =
+///     broadcast_layout_message_to_all_workspaces(|| ToggleGaps);
+/// ```
+pub fn broadcast_layout_message_to_all_workspaces<F, M, X>(f: F) -> Box<dyn KeyEventHandler<X>>
+where
+    F: Fn() -> M + 'static,
+    M: IntoMessage,
+    X: XConn,
+{
+    key_handler(move |s: &mut State<X>, x: &X| {
+        let _span = layout_message_span(s);
+        x.modify_and_refresh(s, |cs| cs.broadcast_message(&f))
+    })
+}
+
+/// Toggle whether or not gaps are applied by any [Gaps][0] transformers in the layouts
+/// available to the current workspace.
+///
+///   [0]: crate::builtin::layout::transformers::Gaps
+pub fn toggle_gaps<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
+    broadcast_layout_message(|| ToggleGaps)
+}
+
+/// Alter the outer and inner gap sizes of any [Gaps][0] transformers in the layouts
+/// available to the current workspace by `px` pixels.
+///
+///   [0]: crate::builtin::layout::transformers::Gaps
+pub fn inc_gaps<X: XConn>(px: i32) -> Box<dyn KeyEventHandler<X>> {
+    broadcast_layout_message(move || IncGaps(px))
+}
+
+/// Set the outer and inner gap sizes of any [Gaps][0] transformers in the layouts
+/// available to the current workspace.
+///
+///   [0]: crate::builtin::layout::transformers::Gaps
+pub fn set_gaps<X: XConn>(outer: u32, inner: u32) -> Box<dyn KeyEventHandler<X>> {
+    broadcast_layout_message(move || SetGaps { outer, inner })
+}
+
+/// Reset the ratios of the active layout for the current workspace so that its regions are
+/// evenly sized.
+pub fn balance_layout<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
+    send_layout_message(|| Balance)
+}
+
+/// Set the width and colors used for client window borders, applying them to all managed
+/// clients immediately.
+pub fn set_border_style<X: XConn>(style: BorderStyle) -> Box<dyn KeyEventHandler<X>> {
+    key_handler(move |s: &mut State<X>, x: &X| {
+        s.config.set_border_style(style);
+        x.update_borders(s)?;
+
+        x.refresh(s)
+    })
+}
+
+/// Toggle zooming the focused tiled client so that it temporarily fills the area of its
+/// workspace without modifying the current layout.
+///
+/// The client is restored to its normal position when this action is run again or when
+/// focus moves to another client.
+pub fn toggle_zoom_focused<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
+    key_handler(|s: &mut State<X>, x: &X| {
+        let id = match s.client_set.current_client() {
+            Some(&id) if !s.client_set.is_floating(&id) => id,
+            _ => return Ok(()),
+        };
+
+        s.zoomed = if s.zoomed == Some(id) { None } else { Some(id) };
+
+        x.refresh(s)
+    })
+}
+
+/// Move the focused client into the main position of the current workspace, keeping focus on
+/// it. If the focused client is already main then it is swapped with the next client instead.
+///
+/// See [StackSet::promote_focused_to_main][0] for details.
+///
+///   [0]: crate::pure::StackSet::promote_focused_to_main
+pub fn promote_focused_to_main<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
+    modify_with(|cs| cs.promote_focused_to_main())
+}
+
+/// Rotate all clients on the current workspace forward, moving the current main client to the
+/// end of the stack and promoting the next client in its place.
+pub fn rotate_all_clients<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
+    modify_with(|cs| cs.rotate_up())
+}
+
+/// Focus the client at position `n` (counting from zero) in the stack of the current workspace.
+///
+/// This is a no-op if there are fewer than `n + 1` clients on the current workspace.
+pub fn focus_nth_client<X: XConn>(n: usize) -> Box<dyn KeyEventHandler<X>> {
+    modify_with(move |cs| cs.focus_nth_client(n))
+}
+
+/// Move the focused client to position `n` (counting from zero) in the stack of the current
+/// workspace, keeping it focused.
+///
+/// This is a no-op if there are fewer than `n + 1` clients on the current workspace.
+pub fn move_focused_client_to_index<X: XConn>(n: usize) -> Box<dyn KeyEventHandler<X>> {
+    modify_with(move |cs| cs.move_focused_to_index(n))
+}
+
+/// Sort the clients on the current workspace by the key returned from the given function,
+/// keeping the currently focused client focused.
+///
+/// ```no_run
+/// # use penrose::{
+/// #     builtin::actions::sort_clients_by_key, core::bindings::KeyEventHandler,
+/// #     x::XConnExt, x11rb::RustConn,
+/// # };
+/// // Sort clients by their window title, falling back to an empty title if it is not set.
+/// let handler: Box<dyn KeyEventHandler<RustConn>> =
+///     sort_clients_by_key(|id, x: &RustConn| x.window_title(id).unwrap_or_default());
+/// ```
+pub fn sort_clients_by_key<X, F, K>(f: F) -> Box<dyn KeyEventHandler<X>>
+where
+    X: XConn,
+    F: Fn(Xid, &X) -> K + 'static,
+    K: Ord,
+{
+    key_handler(move |state: &mut State<X>, x: &X| {
+        let keys: HashMap<Xid, K> = state
+            .client_set
+            .current_workspace()
+            .clients()
+            .map(|&id| (id, f(id, x)))
+            .collect();
+
+        x.modify_and_refresh(state, |cs| {
+            cs.current_workspace_mut()
+                .sort_clients_by(|a, b| keys[a].cmp(&keys[b]))
+        })
+    })
+}
+
 /// Spawn an external program as part of a key binding
-pub fn spawn<X>(program: &'static str) -> Box<dyn KeyEventHandler<X>>
+///
+/// Either a string containing the program and its arguments or a [SpawnSpec] can be provided.
+pub fn spawn<X>(spec: impl Into<SpawnSpec>) -> Box<dyn KeyEventHandler<X>>
 where
     X: XConn,
 {
-    key_handler(move |_, _| util::spawn(program))
+    let spec = spec.into();
+    key_handler(move |_, _| spec.spawn())
 }
 
 /// Exit penrose
@@ -85,6 +247,72 @@ pub fn log_current_state<X: XConn + std::fmt::Debug>() -> Box<dyn KeyEventHandle
     })
 }
 
+/// Write a JSON [StateSnapshot] of the current window manager state to the given file, or to
+/// stdout if no path is provided.
+///
+/// Unlike [log_current_state], the output of this action is intended for consumption by
+/// external tooling.
+///
+///   [StateSnapshot]: crate::core::introspection::StateSnapshot
+#[cfg(feature = "serde")]
+pub fn dump_state_as_json<X: XConn>(
+    path: Option<std::path::PathBuf>,
+) -> Box<dyn KeyEventHandler<X>> {
+    key_handler(move |s: &mut State<X>, x: &X| {
+        let json = serde_json::to_string_pretty(&s.state_snapshot(x)?)?;
+        match &path {
+            Some(path) => std::fs::write(path, json)?,
+            None => println!("{json}"),
+        }
+
+        Ok(())
+    })
+}
+
+/// Switch to the next configured keyboard layout (XKB group), wrapping around to the first.
+pub fn next_keyboard_layout<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
+    cycle_keyboard_layout(1)
+}
+
+/// Switch to the previous configured keyboard layout (XKB group), wrapping around to the last.
+pub fn previous_keyboard_layout<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
+    cycle_keyboard_layout(-1)
+}
+
+fn cycle_keyboard_layout<X: XConn>(step: i32) -> Box<dyn KeyEventHandler<X>> {
+    key_handler(move |_, x: &X| {
+        let n = x.keyboard_layouts()?.len() as i32;
+        if n == 0 {
+            return Ok(());
+        }
+        let current = x.keyboard_layout()? as i32;
+
+        x.set_keyboard_layout((current + step).rem_euclid(n) as u8)
+    })
+}
+
+/// Switch to the keyboard layout (XKB group) with the given index.
+///
+/// The indices of the available layouts match the order returned by
+/// [XConn::keyboard_layouts].
+pub fn set_keyboard_layout<X: XConn>(index: u8) -> Box<dyn KeyEventHandler<X>> {
+    key_handler(move |_, x: &X| x.set_keyboard_layout(index))
+}
+
+/// Mark the currently focused screen as the primary screen.
+///
+/// The primary screen is preferred for the first workspace on startup and for showing newly
+/// created workspaces.
+pub fn set_primary_screen<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
+    key_handler(|s: &mut State<X>, x: &X| {
+        let index = s.client_set.current_screen().index();
+        x.set_primary_screen(index)?;
+        s.client_set.set_primary_screen(index);
+
+        Ok(())
+    })
+}
+
 /// Remove the currently focused client from state and unmap it WITHOUT
 /// closing the client program.
 /// This is provided for removing clients that have been accidentally tiled when
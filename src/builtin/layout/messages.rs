@@ -48,6 +48,47 @@ impl_message!(ExpandMain);
 pub struct ShrinkMain;
 impl_message!(ShrinkMain);
 
+/// Reset the ratios used by the [Layout][0] so that its regions are given an equal share of the
+/// available space, restoring a layout that has been resized using [ExpandMain] and
+/// [ShrinkMain].
+///
+///   [0]: crate::core::layout::Layout
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Balance;
+impl_message!(Balance);
+
+/// Move the boundary between the main and secondary regions of the [Layout][0] by the given
+/// fractions of the width (`dx`) and height (`dy`) of the area being laid out. Layouts use
+/// whichever of the two runs along the axis that they split their main region on.
+///
+/// This is sent by [MouseResizeHandler][1] when resizing tiled clients using the mouse.
+///
+///   [0]: crate::core::layout::Layout
+///   [1]: crate::builtin::actions::floating::MouseResizeHandler
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ResizeMain {
+    /// The horizontal distance to move the boundary as a fraction of the available width
+    pub dx: f32,
+    /// The vertical distance to move the boundary as a fraction of the available height
+    pub dy: f32,
+}
+impl_message!(ResizeMain);
+
+/// Move the focused client of a [Floating][0] layout by the given (x, y) delta in pixels.
+///
+///   [0]: crate::builtin::layout::Floating
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MoveFocused(pub i32, pub i32);
+impl_message!(MoveFocused);
+
+/// Resize the focused client of a [Floating][0] layout by the given (width, height) delta in
+/// pixels.
+///
+///   [0]: crate::builtin::layout::Floating
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ResizeFocused(pub i32, pub i32);
+impl_message!(ResizeFocused);
+
 /// Rotate the [Layout][0] to a new orientation
 ///
 ///   [0]: crate::core::layout::Layout
@@ -81,3 +122,67 @@ impl_message!(UnwrapTransformer);
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Hide;
 impl_message!(Hide);
+
+/// Set the outer and inner gap sizes (in pixels) used by the [Gaps][0] transformer.
+///
+///   [0]: crate::builtin::layout::transformers::Gaps
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SetGaps {
+    /// The new outer gap size in pixels
+    pub outer: u32,
+    /// The new inner gap size in pixels
+    pub inner: u32,
+}
+impl_message!(SetGaps);
+
+/// Alter both the outer and inner gap sizes used by the [Gaps][0] transformer by the given
+/// number of pixels. Gap sizes will not be reduced below zero.
+///
+///   [0]: crate::builtin::layout::transformers::Gaps
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IncGaps(pub i32);
+impl_message!(IncGaps);
+
+/// Toggle whether or not the [Gaps][0] transformer applies gaps around clients, retaining
+/// the configured gap sizes for when gaps are toggled back on.
+///
+///   [0]: crate::builtin::layout::transformers::Gaps
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ToggleGaps;
+impl_message!(ToggleGaps);
+
+/// Reset the active [Layout][0] of a [Workspace][1] back to the state it was in when it was first
+/// added to that workspace. If this message is broadcast then all layouts for the workspace are
+/// reset.
+///
+/// Handling of this message is provided automatically by [Workspace][1] and it will not be seen
+/// by individual layouts.
+///
+///   [0]: crate::core::layout::Layout
+///   [1]: crate::pure::Workspace
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ResetLayout;
+impl_message!(ResetLayout);
+
+/// Set whether or not the reserved space transformers ([ReserveTop][0], [ReserveBottom][1],
+/// [ReserveLeft][2] and [ReserveRight][3]) reserve their configured space, retaining the size of
+/// the reserved area for when it is enabled again.
+///
+///   [0]: crate::builtin::layout::transformers::ReserveTop
+///   [1]: crate::builtin::layout::transformers::ReserveBottom
+///   [2]: crate::builtin::layout::transformers::ReserveLeft
+///   [3]: crate::builtin::layout::transformers::ReserveRight
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SetReservedSpace(pub bool);
+impl_message!(SetReservedSpace);
+
+/// Toggle whether or not the reserved space transformers ([ReserveTop][0], [ReserveBottom][1],
+/// [ReserveLeft][2] and [ReserveRight][3]) reserve their configured space.
+///
+///   [0]: crate::builtin::layout::transformers::ReserveTop
+///   [1]: crate::builtin::layout::transformers::ReserveBottom
+///   [2]: crate::builtin::layout::transformers::ReserveLeft
+///   [3]: crate::builtin::layout::transformers::ReserveRight
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ToggleReservedSpace;
+impl_message!(ToggleReservedSpace);
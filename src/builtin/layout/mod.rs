@@ -1,10 +1,17 @@
 //! Built-in layouts.
 use crate::{
-    builtin::layout::messages::{ExpandMain, IncMain, Mirror, Rotate, ShrinkMain},
+    builtin::layout::messages::{
+        Balance, ExpandMain, IncMain, Mirror, MoveFocused, ResizeFocused, ResizeMain, Rotate,
+        ShrinkMain,
+    },
     core::layout::{Layout, Message},
-    pure::{geometry::Rect, Stack},
+    pure::{
+        geometry::{Rect, RelativeRect},
+        Stack,
+    },
     Xid,
 };
+use std::collections::HashMap;
 
 pub mod messages;
 pub mod transformers;
@@ -35,9 +42,10 @@ impl StackPosition {
 /// - `MainAndStack::bottom` give a main region to the top and remaining clients to the bottom.
 ///
 /// The ratio between the main and secondary stack regions can be adjusted by sending [ShrinkMain]
-/// and [ExpandMain] messages to this layout. The number of clients in the main area can be
-/// increased or decreased by sending an [IncMain] message. To flip between the side and bottom
-/// behaviours you can send a [Rotate] message.
+/// and [ExpandMain] messages to this layout (or by dragging with the mouse using [ResizeMain]) and
+/// reset to an even split by sending a [Balance] message. The number of clients in the main area
+/// can be increased or decreased by sending an [IncMain] message. To flip between the side and
+/// bottom behaviours you can send a [Rotate] message.
 ///
 /// ```text
 /// ..................................
@@ -242,6 +250,15 @@ impl Layout for MainAndStack {
             if self.ratio < 0.0 {
                 self.ratio = 0.0;
             }
+        } else if let Some(&Balance) = m.downcast_ref() {
+            self.ratio = 0.5;
+        } else if let Some(&ResizeMain { dx, dy }) = m.downcast_ref() {
+            let d = match self.pos {
+                StackPosition::Side => dx,
+                StackPosition::Bottom => dy,
+            };
+            let d = if self.mirrored { -d } else { d };
+            self.ratio = (self.ratio + d).clamp(0.0, 1.0);
         } else if let Some(&IncMain(n)) = m.downcast_ref() {
             if n < 0 {
                 self.max_main = self.max_main.saturating_sub((-n) as u32);
@@ -264,9 +281,11 @@ impl Layout for MainAndStack {
 /// - `CenteredMain::horizontal` places the secondary regions to the top and bottom.
 ///
 /// The ratio between the main and secondary stack regions can be adjusted by sending [ShrinkMain]
-/// and [ExpandMain] messages to this layout. The number of clients in the main area can be
+/// and [ExpandMain] messages to this layout and reset so that the main and secondary regions are
+/// the same width by sending a [Balance] message. The number of clients in the main area can be
 /// increased or decreased by sending an [IncMain] message. To flip between the vertical and
-/// horizontal behaviours you can send a [Rotate] message.
+/// horizontal behaviours you can send a [Rotate] message. By default, the first side region is
+/// filled before the second: use [CenteredMain::alternating] to alternate between them instead.
 ///
 /// ```text
 /// ...................................
@@ -291,6 +310,7 @@ pub struct CenteredMain {
     max_main: u32,
     ratio: f32,
     ratio_step: f32,
+    alternate: bool,
 }
 
 impl Default for CenteredMain {
@@ -300,6 +320,7 @@ impl Default for CenteredMain {
             max_main: 1,
             ratio: 0.6,
             ratio_step: 0.1,
+            alternate: false,
         }
     }
 }
@@ -334,9 +355,16 @@ impl CenteredMain {
             max_main,
             ratio,
             ratio_step,
+            alternate: false,
         }
     }
 
+    /// Create a new [CenteredMain] [Layout] with a vertical main area and remaining windows
+    /// placed alternately to the left and right.
+    pub fn vertical_alternating(max_main: u32, ratio: f32, ratio_step: f32) -> Box<dyn Layout> {
+        Box::new(Self::vertical_unboxed(max_main, ratio, ratio_step).alternating())
+    }
+
     /// Create a new [CenteredMain] [Layout] with a horizontal main area and remaining windows
     /// tiled above and below.
     pub fn horizontal(max_main: u32, ratio: f32, ratio_step: f32) -> Box<dyn Layout> {
@@ -351,9 +379,23 @@ impl CenteredMain {
             max_main,
             ratio,
             ratio_step,
+            alternate: false,
         }
     }
 
+    /// Create a new [CenteredMain] [Layout] with a horizontal main area and remaining windows
+    /// placed alternately above and below.
+    pub fn horizontal_alternating(max_main: u32, ratio: f32, ratio_step: f32) -> Box<dyn Layout> {
+        Box::new(Self::horizontal_unboxed(max_main, ratio, ratio_step).alternating())
+    }
+
+    /// Place the secondary clients alternately on either side of the main area rather than
+    /// filling the first side before the second.
+    pub fn alternating(mut self) -> Self {
+        self.alternate = true;
+        self
+    }
+
     /// Rotate the main axis of this layout
     pub fn rotate(&mut self) {
         self.pos = self.pos.rotate();
@@ -363,6 +405,24 @@ impl CenteredMain {
         n <= self.max_main || self.ratio == 1.0 || self.ratio == 0.0
     }
 
+    // Order the regions for the two secondary stacks either one side after the other or
+    // alternating between them.
+    fn secondary_regions(&self, first: Vec<Rect>, second: Vec<Rect>) -> Vec<Rect> {
+        if !self.alternate {
+            return first.into_iter().chain(second).collect();
+        }
+
+        let mut regions = Vec::with_capacity(first.len() + second.len());
+        let mut second = second.into_iter();
+        for r in first {
+            regions.push(r);
+            regions.extend(second.next());
+        }
+        regions.extend(second);
+
+        regions
+    }
+
     // NOTE: There are subtle differences between this method and layout_horizontal
     // >> Be careful when refactoring!
     fn layout_vertical(&self, s: &Stack<Xid>, r: Rect) -> Vec<(Xid, Rect)> {
@@ -393,8 +453,7 @@ impl CenteredMain {
 
             main.as_rows(self.max_main)
                 .into_iter()
-                .chain(left.as_rows(n_left))
-                .chain(right.as_rows(n_right))
+                .chain(self.secondary_regions(left.as_rows(n_left), right.as_rows(n_right)))
                 .zip(s)
                 .map(|(r, c)| (*c, r))
                 .collect()
@@ -435,8 +494,7 @@ impl CenteredMain {
 
             main.as_columns(self.max_main)
                 .into_iter()
-                .chain(top.as_columns(n_top))
-                .chain(bottom.as_columns(n_bottom))
+                .chain(self.secondary_regions(top.as_columns(n_top), bottom.as_columns(n_bottom)))
                 .zip(s)
                 .map(|(r, c)| (*c, r))
                 .collect()
@@ -476,6 +534,8 @@ impl Layout for CenteredMain {
             if self.ratio < 0.0 {
                 self.ratio = 0.0;
             }
+        } else if let Some(&Balance) = m.downcast_ref() {
+            self.ratio = 1.0 / 3.0;
         } else if let Some(&IncMain(n)) = m.downcast_ref() {
             if n < 0 {
                 self.max_main = self.max_main.saturating_sub((-n) as u32);
@@ -616,11 +676,126 @@ impl Layout for Grid {
     }
 }
 
+/// A [Layout] that places each client at a remembered position, allowing a workspace to behave
+/// like a traditional stacking desktop while others remain tiled.
+///
+/// New clients are centered on the screen and the focused client is always stacked on top of
+/// the others. The focused client can be moved and resized using the keyboard by sending
+/// [MoveFocused] and [ResizeFocused] messages to this layout. (The [reposition][0] and
+/// [resize][1] floating actions will do this for you when the focused client is not floating.)
+///
+/// Positions are stored relative to the screen so they are preserved when the workspace is moved
+/// to a screen of a different size. Unlike floating clients, clients placed by this layout are
+/// still tiled as far as the rest of the window manager is concerned: switching to another layout
+/// will arrange them as normal and switching back will restore their previous positions.
+///
+///   [0]: crate::builtin::actions::floating::reposition
+///   [1]: crate::builtin::actions::floating::resize
+#[derive(Debug, Clone)]
+pub struct Floating {
+    initial: RelativeRect,
+    positions: HashMap<Xid, RelativeRect>,
+    stacking: Vec<Xid>,
+    focus: Option<Xid>,
+    r: Rect,
+}
+
+impl Default for Floating {
+    fn default() -> Self {
+        Self::new(0.5, 0.5)
+    }
+}
+
+impl Floating {
+    fn new(w: f64, h: f64) -> Self {
+        Self {
+            initial: RelativeRect::centered(w, h),
+            positions: HashMap::new(),
+            stacking: Vec::new(),
+            focus: None,
+            r: Rect::default(),
+        }
+    }
+
+    /// Create a new [Floating] [Layout] as a boxed trait object, placing new clients in the
+    /// center of the screen at half its width and height.
+    pub fn boxed() -> Box<dyn Layout> {
+        Box::<Floating>::default()
+    }
+
+    /// Create a new [Floating] [Layout] as a boxed trait object, placing new clients in the
+    /// center of the screen with the given width and height as a proportion of the screen size.
+    pub fn boxed_with_size(w: f64, h: f64) -> Box<dyn Layout> {
+        Box::new(Floating::new(w, h))
+    }
+
+    fn modify_focused(&mut self, f: impl Fn(&mut Rect)) {
+        let rr = match self.focus.and_then(|id| self.positions.get_mut(&id)) {
+            Some(rr) => rr,
+            None => return,
+        };
+
+        *rr = rr.apply_as_rect(&self.r, |mut r| {
+            f(&mut r);
+            r
+        });
+    }
+}
+
+impl Layout for Floating {
+    fn name(&self) -> String {
+        "Float".to_owned()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Layout> {
+        Box::new(self.clone())
+    }
+
+    fn layout(&mut self, s: &Stack<Xid>, r: Rect) -> (Option<Box<dyn Layout>>, Vec<(Xid, Rect)>) {
+        self.r = r;
+        self.focus = Some(s.focus);
+        self.positions.retain(|id, _| s.contains(id));
+        self.stacking.retain(|id| s.contains(id) && *id != s.focus);
+        self.stacking.insert(0, s.focus);
+
+        for &id in s.iter() {
+            if !self.positions.contains_key(&id) {
+                self.positions.insert(id, self.initial);
+                if id != s.focus {
+                    self.stacking.insert(1, id);
+                }
+            }
+        }
+
+        let positions = self
+            .stacking
+            .iter()
+            .map(|id| (*id, self.positions[id].applied_to(&r)))
+            .collect();
+
+        (None, positions)
+    }
+
+    fn handle_message(&mut self, m: &Message) -> Option<Box<dyn Layout>> {
+        if let Some(&MoveFocused(dx, dy)) = m.downcast_ref() {
+            self.modify_focused(|r| r.reposition(dx, dy));
+        } else if let Some(&ResizeFocused(dw, dh)) = m.downcast_ref() {
+            self.modify_focused(|r| r.resize(dw, dh));
+        }
+
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
-        builtin::layout::{messages::IncMain, *},
+        builtin::layout::{
+            messages::{IncMain, MoveFocused, ResizeFocused},
+            *,
+        },
         core::layout::IntoMessage,
+        stack,
     };
 
     #[test]
@@ -631,4 +806,30 @@ mod tests {
 
         assert_eq!(l.max_main, 3);
     }
+
+    #[test]
+    fn floating_remembers_positions_and_stacks_focus_on_top() {
+        let r = Rect::new(0, 0, 1000, 800);
+        let mut l = Floating::default();
+
+        let (_, positions) = l.layout(&stack!([Xid(1)], Xid(2)), r);
+        assert_eq!(
+            positions,
+            vec![
+                (Xid(2), Rect::new(250, 200, 500, 400)),
+                (Xid(1), Rect::new(250, 200, 500, 400)),
+            ]
+        );
+
+        l.handle_message(&MoveFocused(-100, 50).into_message());
+        l.handle_message(&ResizeFocused(100, 100).into_message());
+        let (_, positions) = l.layout(&stack!(Xid(1), [Xid(2)]), r);
+        assert_eq!(
+            positions,
+            vec![
+                (Xid(1), Rect::new(250, 200, 500, 400)),
+                (Xid(2), Rect::new(150, 250, 600, 500)),
+            ]
+        );
+    }
 }
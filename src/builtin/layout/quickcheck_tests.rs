@@ -6,7 +6,7 @@
 use crate::{
     builtin::layout::{
         transformers::{ReflectHorizontal, ReflectVertical},
-        CenteredMain, Grid, MainAndStack, Monocle,
+        CenteredMain, Floating, Grid, MainAndStack, Monocle,
     },
     core::layout::Layout,
     pure::{geometry::Rect, Stack},
@@ -66,6 +66,13 @@ fn grid_doesnt_panic(r: Rect, stack: Stack<Xid>) -> bool {
     !positions.is_empty()
 }
 
+#[quickcheck]
+fn floating_doesnt_panic(r: Rect, stack: Stack<Xid>) -> bool {
+    let (_, positions) = Floating::default().layout(&stack, r);
+
+    positions.len() == stack.len()
+}
+
 mod main_and_stack {
     use super::*;
 
@@ -104,6 +111,17 @@ mod main_and_stack {
 
 mod centered_main {
     use super::*;
+    use crate::{builtin::layout::messages::Balance, core::layout::IntoMessage};
+
+    #[quickcheck]
+    fn balanced_doesnt_panic(r: Rect, stack: Stack<Xid>, n: u32, ratio: u8) -> bool {
+        let ratio = ((ratio % 10) as f32) / 10.0;
+        let mut l = CenteredMain::vertical_unboxed(n, ratio, 0.1);
+        l.handle_message(&Balance.into_message());
+        let (_, positions) = l.layout(&stack, r);
+
+        !positions.is_empty()
+    }
 
     #[quickcheck]
     fn vertical_doesnt_panic(r: Rect, stack: Stack<Xid>, n: u32, ratio: u8) -> bool {
@@ -120,6 +138,26 @@ mod centered_main {
 
         !positions.is_empty()
     }
+
+    #[quickcheck]
+    fn vertical_alternating_doesnt_panic(r: Rect, stack: Stack<Xid>, n: u32, ratio: u8) -> bool {
+        let ratio = ((ratio % 10) as f32) / 10.0;
+        let (_, positions) = CenteredMain::vertical_unboxed(n, ratio, 0.1)
+            .alternating()
+            .layout(&stack, r);
+
+        positions.len() == stack.len()
+    }
+
+    #[quickcheck]
+    fn horizontal_alternating_doesnt_panic(r: Rect, stack: Stack<Xid>, n: u32, ratio: u8) -> bool {
+        let ratio = ((ratio % 10) as f32) / 10.0;
+        let (_, positions) = CenteredMain::horizontal_unboxed(n, ratio, 0.1)
+            .alternating()
+            .layout(&stack, r);
+
+        positions.len() == stack.len()
+    }
 }
 
 mod transformers {
@@ -1,6 +1,9 @@
 //! Built-in layout transformers.
 use crate::{
-    core::layout::{Layout, LayoutTransformer},
+    builtin::layout::messages::{
+        IncGaps, SetGaps, SetReservedSpace, ToggleGaps, ToggleReservedSpace,
+    },
+    core::layout::{Layout, LayoutTransformer, Message},
     pure::geometry::Rect,
     simple_transformer, Xid,
 };
@@ -48,6 +51,9 @@ fn reflect_vertical(r: Rect, positions: Vec<(Xid, Rect)>) -> Vec<(Xid, Rect)> {
 /// `outer_px` controls the width of the gap around the edge of the screen and `inner_px`
 /// controls the gap around each individual window. Set both equal to one another to have
 /// a consistant gap size in all places.
+///
+/// Gap sizes can be modified at runtime by sending [SetGaps], [IncGaps] and [ToggleGaps]
+/// messages to the layout.
 #[derive(Debug, Clone)]
 pub struct Gaps {
     /// The inner [Layout] having gaps applied to it.
@@ -56,6 +62,7 @@ pub struct Gaps {
     pub outer_px: u32,
     /// The desired inner gap size in pixels
     pub inner_px: u32,
+    enabled: bool,
 }
 
 impl Gaps {
@@ -65,20 +72,31 @@ impl Gaps {
             layout,
             outer_px,
             inner_px,
+            enabled: true,
         })
     }
-}
 
-fn shrink(r: Rect, px: u32) -> Rect {
-    if r.w == 0 || r.h == 0 {
-        return r;
+    /// Whether or not gaps are currently being applied.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Set whether or not gaps should be applied.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Toggle whether or not gaps are applied.
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
     }
 
-    Rect {
-        x: r.x + px,
-        y: r.y + px,
-        w: r.w - 2 * px,
-        h: r.h - 2 * px,
+    fn active_px(&self, px: u32) -> u32 {
+        if self.enabled {
+            px
+        } else {
+            0
+        }
     }
 }
 
@@ -92,59 +110,178 @@ impl LayoutTransformer for Gaps {
     }
 
     fn transform_initial(&self, r: Rect) -> Rect {
-        shrink(r, self.outer_px)
+        r.apply_gaps(self.active_px(self.outer_px))
     }
 
     fn transform_positions(&mut self, _: Rect, positions: Vec<(Xid, Rect)>) -> Vec<(Xid, Rect)> {
+        let px = self.active_px(self.inner_px);
+
         positions
             .into_iter()
-            .map(|(id, r)| (id, shrink(r, self.inner_px)))
+            .map(|(id, r)| (id, r.apply_gaps(px)))
             .collect()
     }
-}
 
-/// Reserve `px` pixels at the top of the screen.
-///
-/// Typically used for providing space for a status bar.
-#[derive(Debug, Clone)]
-pub struct ReserveTop {
-    /// The wrapped inner layout
-    pub layout: Box<dyn Layout>,
-    /// The number of pixels to reserve at the top of the screen
-    pub px: u32,
-}
+    fn passthrough_message(&mut self, m: &Message) -> Option<Box<dyn Layout>> {
+        if let Some(&SetGaps { outer, inner }) = m.downcast_ref() {
+            self.outer_px = outer;
+            self.inner_px = inner;
+        } else if let Some(&IncGaps(d)) = m.downcast_ref() {
+            self.outer_px = self.outer_px.saturating_add_signed(d);
+            self.inner_px = self.inner_px.saturating_add_signed(d);
+        } else if let Some(&ToggleGaps) = m.downcast_ref() {
+            self.toggle();
+        } else if let Some(new) = self.layout.handle_message(m) {
+            self.layout = new;
+        }
 
-impl ReserveTop {
-    /// Wrap an existing [Layout] with the given reserved area.
-    pub fn wrap(layout: Box<dyn Layout>, px: u32) -> Box<dyn Layout> {
-        Box::new(Self { layout, px })
+        None
     }
 }
 
-impl LayoutTransformer for ReserveTop {
-    fn transformed_name(&self) -> String {
-        self.layout.name()
-    }
+macro_rules! reserve_transformer {
+    (
+        $(#[$struct_docs:meta])*
+        $t:ident,
+        $side:literal,
+        $reserve:ident
+    ) => {
+        $(#[$struct_docs])*
+        #[derive(Debug, Clone)]
+        pub struct $t {
+            /// The wrapped inner layout
+            pub layout: Box<dyn Layout>,
+            #[doc = concat!("The number of pixels to reserve at the ", $side, " of the screen")]
+            pub px: u32,
+            enabled: bool,
+        }
 
-    fn inner_mut(&mut self) -> &mut Box<dyn Layout> {
-        &mut self.layout
-    }
+        impl $t {
+            /// Wrap an existing [Layout] with the given reserved area.
+            pub fn wrap(layout: Box<dyn Layout>, px: u32) -> Box<dyn Layout> {
+                Box::new(Self {
+                    layout,
+                    px,
+                    enabled: true,
+                })
+            }
+
+            /// Whether or not space is currently being reserved.
+            pub fn is_enabled(&self) -> bool {
+                self.enabled
+            }
 
-    fn transform_initial(&self, mut r: Rect) -> Rect {
-        if r.w == 0 || r.h == 0 {
-            return r;
+            /// Set whether or not space should be reserved.
+            pub fn set_enabled(&mut self, enabled: bool) {
+                self.enabled = enabled;
+            }
+
+            /// Toggle whether or not space is reserved.
+            pub fn toggle(&mut self) {
+                self.enabled = !self.enabled;
+            }
         }
 
-        r.y += self.px;
-        r.h -= self.px;
+        impl LayoutTransformer for $t {
+            fn transformed_name(&self) -> String {
+                self.layout.name()
+            }
 
-        r
-    }
+            fn inner_mut(&mut self) -> &mut Box<dyn Layout> {
+                &mut self.layout
+            }
+
+            fn transform_initial(&self, r: Rect) -> Rect {
+                if r.w == 0 || r.h == 0 || !self.enabled {
+                    return r;
+                }
+
+                $reserve(r, self.px)
+            }
+
+            fn passthrough_message(&mut self, m: &Message) -> Option<Box<dyn Layout>> {
+                if let Some(&SetReservedSpace(enabled)) = m.downcast_ref() {
+                    self.set_enabled(enabled);
+                } else if let Some(&ToggleReservedSpace) = m.downcast_ref() {
+                    self.toggle();
+                } else if let Some(new) = self.layout.handle_message(m) {
+                    self.layout = new;
+                }
+
+                None
+            }
+        }
+    };
+}
+
+reserve_transformer!(
+    /// Reserve `px` pixels at the top of the screen.
+    ///
+    /// Typically used for providing space for a status bar.
+    ReserveTop,
+    "top",
+    reserve_top
+);
+
+reserve_transformer!(
+    /// Reserve `px` pixels at the bottom of the screen.
+    ///
+    /// Typically used for providing space for a status bar.
+    ReserveBottom,
+    "bottom",
+    reserve_bottom
+);
+
+reserve_transformer!(
+    /// Reserve `px` pixels at the left hand side of the screen.
+    ///
+    /// Typically used for providing space for a vertical status bar.
+    ReserveLeft,
+    "left",
+    reserve_left
+);
+
+reserve_transformer!(
+    /// Reserve `px` pixels at the right hand side of the screen.
+    ///
+    /// Typically used for providing space for a vertical status bar.
+    ReserveRight,
+    "right",
+    reserve_right
+);
+
+fn reserve_top(mut r: Rect, px: u32) -> Rect {
+    let px = px.min(r.h);
+    r.y += px;
+    r.h -= px;
+
+    r
+}
+
+fn reserve_bottom(mut r: Rect, px: u32) -> Rect {
+    r.h -= px.min(r.h);
+
+    r
+}
+
+fn reserve_left(mut r: Rect, px: u32) -> Rect {
+    let px = px.min(r.w);
+    r.x += px;
+    r.w -= px;
+
+    r
+}
+
+fn reserve_right(mut r: Rect, px: u32) -> Rect {
+    r.w -= px.min(r.w);
+
+    r
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{builtin::layout::Monocle, core::layout::IntoMessage, stack};
     use simple_test_case::test_case;
 
     #[test_case(Rect::new(0, 0, 100, 200), Rect::new(0, 0, 100, 200); "fullscreen is idempotent")]
@@ -172,4 +309,89 @@ mod tests {
 
         assert_eq!(transformed, vec![(Xid(1), expected)]);
     }
+
+    #[test_case(SetGaps { outer: 1, inner: 2 }.into_message(), 1, 2, true; "set")]
+    #[test_case(IncGaps(3).into_message(), 8, 8, true; "inc")]
+    #[test_case(IncGaps(-3).into_message(), 2, 2, true; "dec")]
+    #[test_case(IncGaps(-10).into_message(), 0, 0, true; "dec saturates at zero")]
+    #[test_case(ToggleGaps.into_message(), 5, 5, false; "toggle")]
+    #[test]
+    fn gaps_messages(m: Message, outer_px: u32, inner_px: u32, enabled: bool) {
+        let mut l = Gaps {
+            layout: Box::new(Monocle),
+            outer_px: 5,
+            inner_px: 5,
+            enabled: true,
+        };
+
+        assert!(l.handle_message(&m).is_none());
+        assert_eq!(
+            (l.outer_px, l.inner_px, l.is_enabled()),
+            (outer_px, inner_px, enabled)
+        );
+    }
+
+    #[test_case(true, Rect::new(10, 10, 80, 80); "enabled")]
+    #[test_case(false, Rect::new(0, 0, 100, 100); "disabled")]
+    #[test]
+    fn toggled_gaps_are_not_applied(enabled: bool, expected: Rect) {
+        let mut l = Gaps {
+            layout: Box::new(Monocle),
+            outer_px: 5,
+            inner_px: 5,
+            enabled: true,
+        };
+        l.set_enabled(enabled);
+
+        let (_, positions) = l.layout(&stack!(Xid(1)), Rect::new(0, 0, 100, 100));
+
+        assert_eq!(positions, vec![(Xid(1), expected)]);
+    }
+
+    #[test]
+    fn oversized_gaps_do_not_underflow() {
+        let r = Rect::new(0, 0, 10, 20).apply_gaps(50);
+
+        assert_eq!(r, Rect::new(4, 4, 2, 12));
+    }
+
+    #[test_case(reserve_top, Rect::new(10, 20, 90, 180); "top")]
+    #[test_case(reserve_bottom, Rect::new(10, 10, 90, 180); "bottom")]
+    #[test_case(reserve_left, Rect::new(20, 10, 80, 190); "left")]
+    #[test_case(reserve_right, Rect::new(10, 10, 80, 190); "right")]
+    #[test]
+    fn reserve_space(reserve: fn(Rect, u32) -> Rect, expected: Rect) {
+        let r = reserve(Rect::new(10, 10, 90, 190), 10);
+
+        assert_eq!(r, expected);
+    }
+
+    #[test_case(reserve_top; "top")]
+    #[test_case(reserve_bottom; "bottom")]
+    #[test_case(reserve_left; "left")]
+    #[test_case(reserve_right; "right")]
+    #[test]
+    fn reserve_space_larger_than_screen_does_not_underflow(reserve: fn(Rect, u32) -> Rect) {
+        let r = reserve(Rect::new(0, 0, 100, 100), 200);
+
+        assert!(r.w == 0 || r.h == 0, "{r:?}");
+    }
+
+    #[test]
+    fn reserved_space_can_be_toggled() {
+        let mut l = ReserveTop::wrap(Box::new(Monocle), 10);
+        let r = Rect::new(0, 0, 100, 100);
+        let s = stack!(Xid(1));
+
+        let (_, positions) = l.layout(&s, r);
+        assert_eq!(positions, vec![(Xid(1), Rect::new(0, 10, 100, 90))]);
+
+        l.handle_message(&ToggleReservedSpace.into_message());
+        let (_, positions) = l.layout(&s, r);
+        assert_eq!(positions, vec![(Xid(1), r)]);
+
+        l.handle_message(&SetReservedSpace(true).into_message());
+        let (_, positions) = l.layout(&s, r);
+        assert_eq!(positions, vec![(Xid(1), Rect::new(0, 10, 100, 90))]);
+    }
 }
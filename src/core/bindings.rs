@@ -43,7 +43,7 @@ pub fn keycodes_from_xmodmap() -> Result<HashMap<String, u8>> {
     Ok(m)
 }
 
-fn parse_binding(pattern: &str, known_codes: &HashMap<String, u8>) -> Result<KeyCode> {
+pub(crate) fn parse_binding(pattern: &str, known_codes: &HashMap<String, u8>) -> Result<KeyCode> {
     let mut parts: Vec<&str> = pattern.split('-').collect();
     let name = parts.remove(parts.len() - 1);
 
@@ -404,17 +404,41 @@ pub enum MouseEventKind {
 }
 
 /// Data from a button press or motion-notify event
+///
+/// New fields may be added to this struct as more details of mouse events are exposed so it
+/// can not be constructed directly outside of penrose: use [MouseEventData::new] instead.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub struct MouseEventData {
     /// The ID of the window that was contained the click
     pub id: Xid,
+    /// The ID of the window that the event was reported to.
+    ///
+    /// For events coming from the button grabs on the root window this will be the root window
+    /// itself (with the window under the pointer available as `id`), while for windows that
+    /// select for button events directly or have their own button grabs (such as status bars or
+    /// click to focus) this is the window that was clicked.
+    pub window: Xid,
     /// Absolute coordinate of the event
     pub rpt: Point,
     /// Coordinate of the event relative to top-left of the window itself
     pub wpt: Point,
 }
 
+impl MouseEventData {
+    /// Construct a new [MouseEventData] for an event that was reported to `window` while the
+    /// pointer was over `id`.
+    pub fn new(id: Xid, window: Xid, rpt: Point, wpt: Point) -> Self {
+        Self {
+            id,
+            window,
+            rpt,
+            wpt,
+        }
+    }
+}
+
 /// A mouse movement or button event
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -439,15 +463,24 @@ impl MouseEvent {
         kind: MouseEventKind,
     ) -> Self {
         MouseEvent {
-            data: MouseEventData {
+            data: MouseEventData::new(
+                id,
                 id,
-                rpt: Point::new(rx as u32, ry as u32),
-                wpt: Point::new(ex as u32, ey as u32),
-            },
+                Point::new(rx as u32, ry as u32),
+                Point::new(ex as u32, ey as u32),
+            ),
             state,
             kind,
         }
     }
+
+    /// Set the window that this event was reported to if it differs from the window that
+    /// contained the click.
+    pub fn with_window(mut self, window: Xid) -> Self {
+        self.data.window = window;
+
+        self
+    }
 }
 
 /// Mouse motion with a held button and optional modifiers
@@ -464,12 +497,21 @@ impl MotionNotifyEvent {
     /// Construct a new [MotionNotifyEvent] from raw data
     pub fn new(id: Xid, rx: i16, ry: i16, ex: i16, ey: i16, modifiers: Vec<ModifierKey>) -> Self {
         MotionNotifyEvent {
-            data: MouseEventData {
+            data: MouseEventData::new(
+                id,
                 id,
-                rpt: Point::new(rx as u32, ry as u32),
-                wpt: Point::new(ex as u32, ey as u32),
-            },
+                Point::new(rx as u32, ry as u32),
+                Point::new(ex as u32, ey as u32),
+            ),
             modifiers,
         }
     }
+
+    /// Set the window that this event was reported to if it differs from the window that
+    /// contained the pointer.
+    pub fn with_window(mut self, window: Xid) -> Self {
+        self.data.window = window;
+
+        self
+    }
 }
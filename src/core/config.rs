@@ -0,0 +1,444 @@
+//! Building a [Config] while validating the options provided.
+//!
+//! A [Config] is a plain struct so it is possible to construct one that the window manager is
+//! unable to run with (for example, one with duplicate workspace tags). [ConfigBuilder] checks
+//! the options it is given when [ConfigBuilder::build] is called and reports _every_ problem it
+//! finds as a [ConfigProblem], rather than failing on the first one.
+//!
+//! ```
+//! use penrose::{core::Config, x11rb::RustConn, Error};
+//!
+//! let res = Config::<RustConn>::builder()
+//!     .tags(["1", "2", "2", ""])
+//!     .focused_border("#not-a-color")
+//!     .build();
+//!
+//! match res {
+//!     Err(Error::InvalidConfig { problems }) => assert_eq!(problems.len(), 3),
+//!     _ => panic!("expected the config to be invalid"),
+//! }
+//! ```
+use crate::{
+    core::{
+        bindings::{keycodes_from_xmodmap, parse_binding, KeyBindings, KeyEventHandler},
+        layout::LayoutStack,
+        Config,
+    },
+    x::XConn,
+    Color, Error, Result,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+/// A single problem found with the options given to a [ConfigBuilder].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ConfigProblem {
+    /// No workspace tags were provided
+    #[error("at least one workspace tag is required")]
+    NoTags,
+
+    /// A workspace tag was empty or only contained whitespace
+    #[error("the workspace tag at index {index} is empty")]
+    EmptyTag {
+        /// The position of the tag in the list of tags provided
+        index: usize,
+    },
+
+    /// A workspace tag was used more than once
+    #[error("the workspace tag '{tag}' is used more than once")]
+    DuplicateTag {
+        /// The duplicated tag
+        tag: String,
+    },
+
+    /// A color could not be parsed
+    #[error("invalid color for {field}: {reason}")]
+    InvalidColor {
+        /// The config field the color was provided for
+        field: &'static str,
+        /// Why the color was rejected
+        reason: String,
+    },
+
+    /// The requested initial layout is not in the layout stack
+    #[error("'{name}' is not the name of a layout in the default layouts")]
+    UnknownLayout {
+        /// The name of the requested layout
+        name: String,
+    },
+
+    /// A key binding could not be parsed
+    #[error("unable to parse key binding '{binding}': {reason}")]
+    InvalidKeyBinding {
+        /// The binding as it was provided
+        binding: String,
+        /// Why parsing failed
+        reason: String,
+    },
+}
+
+/// A builder for a [Config] that validates the options provided.
+///
+/// See the module level docs for details.
+pub struct ConfigBuilder<X>
+where
+    X: XConn,
+{
+    inner: Config<X>,
+    initial_layout: Option<String>,
+    key_codes: Option<HashMap<String, u8>>,
+    problems: Vec<ConfigProblem>,
+}
+
+impl<X> fmt::Debug for ConfigBuilder<X>
+where
+    X: XConn,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConfigBuilder")
+            .field("inner", &self.inner)
+            .field("initial_layout", &self.initial_layout)
+            .field("problems", &self.problems)
+            .finish()
+    }
+}
+
+impl<X> Default for ConfigBuilder<X>
+where
+    X: XConn,
+{
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+impl<X> ConfigBuilder<X>
+where
+    X: XConn,
+{
+    /// Create a new [ConfigBuilder] using the given [Config] for any options not explicitly set.
+    pub fn new(config: Config<X>) -> Self {
+        Self {
+            inner: config,
+            initial_layout: None,
+            key_codes: None,
+            problems: Vec::new(),
+        }
+    }
+
+    fn color<C>(&mut self, field: &'static str, c: C) -> Option<Color>
+    where
+        C: TryInto<Color>,
+        C::Error: fmt::Display,
+    {
+        match c.try_into() {
+            Ok(c) => Some(c),
+            Err(e) => {
+                let reason = e.to_string();
+                self.problems
+                    .push(ConfigProblem::InvalidColor { field, reason });
+                None
+            }
+        }
+    }
+
+    /// Set the color used for normal (unfocused) window borders.
+    pub fn normal_border<C>(mut self, c: C) -> Self
+    where
+        C: TryInto<Color>,
+        C::Error: fmt::Display,
+    {
+        if let Some(c) = self.color("normal_border", c) {
+            self.inner.normal_border = c;
+        }
+
+        self
+    }
+
+    /// Set the color used for the focused window border.
+    pub fn focused_border<C>(mut self, c: C) -> Self
+    where
+        C: TryInto<Color>,
+        C::Error: fmt::Display,
+    {
+        if let Some(c) = self.color("focused_border", c) {
+            self.inner.focused_border = c;
+        }
+
+        self
+    }
+
+    /// Set the color used for the border of windows requesting attention.
+    pub fn urgent_border<C>(mut self, c: C) -> Self
+    where
+        C: TryInto<Color>,
+        C::Error: fmt::Display,
+    {
+        if let Some(c) = self.color("urgent_border", c) {
+            self.inner.urgent_border = c;
+        }
+
+        self
+    }
+
+    /// Set the width in pixels of window borders.
+    pub fn border_width(mut self, width: u32) -> Self {
+        self.inner.border_width = width;
+
+        self
+    }
+
+    /// Set the ordered workspace tags to use on startup.
+    pub fn tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.inner.tags = tags.into_iter().map(Into::into).collect();
+
+        self
+    }
+
+    /// Set the stack of layouts to use for each workspace.
+    pub fn default_layouts(mut self, layouts: LayoutStack) -> Self {
+        self.inner.default_layouts = layouts;
+
+        self
+    }
+
+    /// Focus the layout with the given name in the default layouts on startup.
+    pub fn initial_layout(mut self, name: impl Into<String>) -> Self {
+        self.initial_layout = Some(name.into());
+
+        self
+    }
+
+    /// Set the window classes that should always be floated.
+    pub fn floating_classes<I, S>(mut self, classes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.inner.floating_classes = classes.into_iter().map(Into::into).collect();
+
+        self
+    }
+
+    /// Make arbitrary modifications to the underlying [Config] for options that do not need
+    /// validating, such as hooks.
+    pub fn modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut Config<X>),
+    {
+        (f)(&mut self.inner);
+
+        self
+    }
+
+    /// Use the given mapping of key names to key codes when parsing key bindings in
+    /// [ConfigBuilder::build_with_key_bindings] rather than running `xmodmap`.
+    pub fn key_codes(mut self, codes: HashMap<String, u8>) -> Self {
+        self.key_codes = Some(codes);
+
+        self
+    }
+
+    fn check(&mut self) -> Vec<ConfigProblem> {
+        let mut problems = std::mem::take(&mut self.problems);
+
+        if self.inner.tags.is_empty() {
+            problems.push(ConfigProblem::NoTags);
+        }
+
+        let mut seen = HashSet::new();
+        let mut duplicates = Vec::new();
+        for (index, tag) in self.inner.tags.iter().enumerate() {
+            if tag.trim().is_empty() {
+                problems.push(ConfigProblem::EmptyTag { index });
+            } else if !seen.insert(tag) && !duplicates.contains(&tag) {
+                duplicates.push(tag);
+            }
+        }
+        problems.extend(
+            duplicates
+                .into_iter()
+                .map(|tag| ConfigProblem::DuplicateTag { tag: tag.clone() }),
+        );
+
+        if let Some(name) = self.initial_layout.take() {
+            let layouts = &mut self.inner.default_layouts;
+            if layouts.iter().any(|l| l.name() == name) {
+                layouts.focus_element_by(|l| l.name() == name);
+            } else {
+                problems.push(ConfigProblem::UnknownLayout { name });
+            }
+        }
+
+        problems
+    }
+
+    /// Validate the options provided and build the resulting [Config].
+    ///
+    /// If any problems are found they are all returned as an [Error::InvalidConfig].
+    pub fn build(mut self) -> Result<Config<X>> {
+        let problems = self.check();
+        if !problems.is_empty() {
+            return Err(Error::InvalidConfig { problems });
+        }
+
+        Ok(self.inner)
+    }
+
+    /// Validate the options provided along with the given string format key bindings, returning
+    /// the resulting [Config] and parsed [KeyBindings].
+    ///
+    /// Key names are resolved using the codes set with [ConfigBuilder::key_codes] if there are
+    /// any, otherwise `xmodmap` is used as described in [keycodes_from_xmodmap]. Problems with
+    /// the key bindings are reported alongside any problems with the rest of the config.
+    pub fn build_with_key_bindings<S>(
+        mut self,
+        str_bindings: HashMap<S, Box<dyn KeyEventHandler<X>>>,
+    ) -> Result<(Config<X>, KeyBindings<X>)>
+    where
+        S: AsRef<str>,
+    {
+        let mut problems = self.check();
+        let codes = match self.key_codes.take() {
+            Some(codes) => codes,
+            None => keycodes_from_xmodmap()?,
+        };
+
+        let mut bindings = HashMap::new();
+        for (s, handler) in str_bindings {
+            match parse_binding(s.as_ref(), &codes) {
+                Ok(k) => _ = bindings.insert(k, handler),
+                Err(e) => problems.push(ConfigProblem::InvalidKeyBinding {
+                    binding: s.as_ref().to_owned(),
+                    reason: e.to_string(),
+                }),
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(Error::InvalidConfig { problems });
+        }
+
+        Ok((self.inner, bindings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builtin::layout::{MainAndStack, Monocle},
+        stack,
+        x::TestConn,
+    };
+    use simple_test_case::test_case;
+
+    fn problems(res: Result<Config<TestConn>>) -> Vec<ConfigProblem> {
+        match res {
+            Err(Error::InvalidConfig { problems }) => problems,
+            Err(e) => panic!("unexpected error: {e}"),
+            Ok(_) => vec![],
+        }
+    }
+
+    #[test]
+    fn default_builder_is_valid() {
+        assert!(ConfigBuilder::<TestConn>::default().build().is_ok());
+    }
+
+    #[test_case(vec![], vec![ConfigProblem::NoTags]; "no tags")]
+    #[test_case(vec!["1", " "], vec![ConfigProblem::EmptyTag { index: 1 }]; "empty tag")]
+    #[test_case(
+        vec!["1", "2", "1", "2", "1"],
+        vec![
+            ConfigProblem::DuplicateTag { tag: "1".to_string() },
+            ConfigProblem::DuplicateTag { tag: "2".to_string() },
+        ];
+        "duplicates reported once"
+    )]
+    #[test]
+    fn tags_are_validated(tags: Vec<&str>, expected: Vec<ConfigProblem>) {
+        let res = Config::<TestConn>::builder().tags(tags).build();
+
+        assert_eq!(problems(res), expected);
+    }
+
+    #[test]
+    fn all_problems_are_reported() {
+        let res = Config::<TestConn>::builder()
+            .tags(["1", "1"])
+            .normal_border("#12")
+            .urgent_border("#zzzzzz")
+            .initial_layout("missing")
+            .build();
+
+        let ps = problems(res);
+
+        assert_eq!(ps.len(), 4);
+        assert!(ps.contains(&ConfigProblem::UnknownLayout {
+            name: "missing".to_string()
+        }));
+        assert!(ps.iter().any(
+            |p| matches!(p, ConfigProblem::InvalidColor { field, .. } if *field == "normal_border")
+        ));
+    }
+
+    #[test]
+    fn valid_colors_are_set() {
+        let config = Config::<TestConn>::builder()
+            .focused_border("#112233")
+            .normal_border(0x445566ff)
+            .build()
+            .unwrap();
+
+        assert_eq!(config.focused_border, Color::new_from_hex(0x112233ff));
+        assert_eq!(config.normal_border, Color::new_from_hex(0x445566ff));
+    }
+
+    #[test]
+    fn initial_layout_is_focused() {
+        let layouts: LayoutStack = stack!(Box::<MainAndStack>::default(), Monocle::boxed());
+        let config = Config::<TestConn>::builder()
+            .default_layouts(layouts)
+            .initial_layout("Mono")
+            .build()
+            .unwrap();
+
+        assert_eq!(config.default_layouts.focus.name(), "Mono");
+    }
+
+    #[test]
+    fn key_bindings_are_validated() {
+        let codes: HashMap<String, u8> = [("a".to_string(), 38)].into_iter().collect();
+        let mut str_bindings: HashMap<&str, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        str_bindings.insert("M-a", Box::new(|_: &mut _, _: &_| Ok(())));
+        str_bindings.insert("M-b", Box::new(|_: &mut _, _: &_| Ok(())));
+        str_bindings.insert("Q-a", Box::new(|_: &mut _, _: &_| Ok(())));
+
+        let res = Config::<TestConn>::builder()
+            .key_codes(codes)
+            .build_with_key_bindings(str_bindings);
+
+        let mut ps = match res {
+            Err(Error::InvalidConfig { problems }) => problems,
+            _ => panic!("expected the bindings to be invalid"),
+        };
+        ps.sort_by_key(|p| p.to_string());
+
+        let bindings: Vec<_> = ps
+            .iter()
+            .map(|p| match p {
+                ConfigProblem::InvalidKeyBinding { binding, .. } => binding.as_str(),
+                _ => panic!("unexpected problem: {p}"),
+            })
+            .collect();
+
+        assert_eq!(bindings, vec!["M-b", "Q-a"]);
+    }
+}
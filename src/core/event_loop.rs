@@ -0,0 +1,391 @@
+//! An event loop that multiplexes the X connection with timers, signals and other file
+//! descriptors.
+//!
+//! By default [WindowManager::run][0] only waits for events from the X server (and for child
+//! processes exiting), so any work happening outside of the window manager (an IPC socket, a
+//! timer, a widget that needs redrawing) has to be run on another thread that wakes the window
+//! manager up. Running the
+//! window manager with [WindowManager::run_with_event_loop][1] instead polls the X connection
+//! alongside the sources registered with an [EventLoop], running the hooks for each source on
+//! the main thread with full access to the window manager [State]:
+//!
+//! ```no_run
+//! use penrose::{
+//!     core::{event_loop::{EventLoop, Signal}, Config, State, WindowManager},
+//!     x::XConnExt,
+//!     x11rb::RustConn,
+//! };
+//! use std::{collections::HashMap, time::Duration};
+//!
+//! let event_loop = EventLoop::new()
+//!     .with_timer(Duration::from_secs(60), |state: &mut State<RustConn>, x: &RustConn| {
+//!         x.refresh(state)
+//!     })
+//!     .with_signal(Signal::SIGUSR1, |state: &mut State<RustConn>, _: &RustConn| {
+//!         println!("{} clients are being managed", state.client_set.clients().count());
+//!         Ok(())
+//!     });
+//!
+//! let conn = RustConn::new()?;
+//! let wm = WindowManager::new(Config::default(), HashMap::new(), HashMap::new(), conn)?;
+//! wm.run_with_event_loop(event_loop)?;
+//! # Ok::<(), penrose::Error>(())
+//! ```
+//!
+//! Hooks are run using the same [PanicPolicy][2] as other user hooks and any errors they return
+//! are passed to the [ErrorHook][3] if one is set.
+//!
+//!   [0]: crate::core::WindowManager::run
+//!   [1]: crate::core::WindowManager::run_with_event_loop
+//!   [2]: crate::core::PanicPolicy
+//!   [3]: crate::core::hooks::ErrorHook
+use crate::{
+    core::{hooks::StateHook, PanicPolicy, State},
+    x::XConn,
+    Error, Result,
+};
+use nix::{
+    errno::Errno,
+    poll::{poll, PollFd, PollFlags, PollTimeout},
+    sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet},
+    unistd::write,
+};
+use std::{
+    fmt,
+    io::{self, Read},
+    os::{
+        fd::{AsFd, AsRawFd, BorrowedFd, RawFd},
+        unix::net::UnixStream,
+    },
+    sync::atomic::{AtomicI32, AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+use tracing::{error, trace};
+
+#[doc(no_inline)]
+pub use nix::sys::signal::Signal;
+
+// Signals received since the event loop last checked, stored as a bit set indexed by signal
+// number along with the write end of a pipe used to wake the event loop when one arrives.
+static PENDING_SIGNALS: AtomicU64 = AtomicU64::new(0);
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn handle_signal(signal: i32) {
+    PENDING_SIGNALS.fetch_or(1u64 << (signal % 64), Ordering::SeqCst);
+
+    let fd = SIGNAL_PIPE.load(Ordering::SeqCst);
+    if fd >= 0 {
+        // SAFETY: the pipe is only closed after the fd has been reset to -1 and write is
+        //         async-signal-safe. If the pipe is full then the event loop is already awake.
+        _ = write(unsafe { BorrowedFd::borrow_raw(fd) }, &[0]);
+    }
+}
+
+struct Timer<X: XConn> {
+    interval: Duration,
+    next: Instant,
+    hook: Box<dyn StateHook<X>>,
+}
+
+struct FdSource<X: XConn> {
+    fd: RawFd,
+    hook: Box<dyn StateHook<X>>,
+}
+
+struct SignalSource<X: XConn> {
+    signal: Signal,
+    hook: Box<dyn StateHook<X>>,
+}
+
+/// A set of timers, signals and file descriptors to poll alongside the X connection when
+/// running the window manager using [WindowManager::run_with_event_loop][0].
+///
+/// See the module level docs for details.
+///
+///   [0]: crate::core::WindowManager::run_with_event_loop
+pub struct EventLoop<X: XConn> {
+    timers: Vec<Timer<X>>,
+    fds: Vec<FdSource<X>>,
+    signals: Vec<SignalSource<X>>,
+    pipe: Option<(UnixStream, UnixStream)>,
+}
+
+impl<X: XConn> fmt::Debug for EventLoop<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventLoop")
+            .field(
+                "timers",
+                &self.timers.iter().map(|t| t.interval).collect::<Vec<_>>(),
+            )
+            .field("fds", &self.fds.iter().map(|s| s.fd).collect::<Vec<_>>())
+            .field(
+                "signals",
+                &self.signals.iter().map(|s| s.signal).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl<X: XConn> Default for EventLoop<X> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<X: XConn> Drop for EventLoop<X> {
+    fn drop(&mut self) {
+        if self.pipe.is_some() {
+            SIGNAL_PIPE.store(-1, Ordering::SeqCst);
+        }
+    }
+}
+
+impl<X: XConn> EventLoop<X> {
+    /// Create a new [EventLoop] with no additional sources.
+    pub fn new() -> Self {
+        Self {
+            timers: Vec::new(),
+            fds: Vec::new(),
+            signals: Vec::new(),
+            pipe: None,
+        }
+    }
+
+    /// Run the given hook every `interval`, with the first run taking place one interval after
+    /// the event loop starts.
+    pub fn with_timer<H>(mut self, interval: Duration, hook: H) -> Self
+    where
+        H: StateHook<X> + 'static,
+    {
+        self.timers.push(Timer {
+            interval,
+            next: Instant::now() + interval,
+            hook: hook.boxed(),
+        });
+
+        self
+    }
+
+    /// Run the given hook each time `fd` becomes readable.
+    ///
+    /// The hook is responsible for reading from `fd` (otherwise it will be run again
+    /// immediately) and `fd` must remain open for as long as the event loop is running.
+    pub fn with_fd<H>(mut self, fd: RawFd, hook: H) -> Self
+    where
+        H: StateHook<X> + 'static,
+    {
+        self.fds.push(FdSource {
+            fd,
+            hook: hook.boxed(),
+        });
+
+        self
+    }
+
+    /// Run the given hook each time the window manager receives `signal`.
+    ///
+    /// `SIGCHLD` is handled by the event loop itself in order to reap child processes (see
+    /// [ChildExitHook][0]) so hooks registered for it will be run in addition to this.
+    ///
+    ///   [0]: crate::core::hooks::ChildExitHook
+    pub fn with_signal<H>(mut self, signal: Signal, hook: H) -> Self
+    where
+        H: StateHook<X> + 'static,
+    {
+        self.signals.push(SignalSource {
+            signal,
+            hook: hook.boxed(),
+        });
+
+        self
+    }
+
+    // Set up the pipe used for waking the event loop and install signal handlers for SIGCHLD
+    // and each of the registered signals.
+    pub(crate) fn install_signal_handlers(&mut self) -> Result<()> {
+        let (rx, tx) = UnixStream::pair()?;
+        rx.set_nonblocking(true)?;
+        tx.set_nonblocking(true)?;
+        SIGNAL_PIPE.store(tx.as_raw_fd(), Ordering::SeqCst);
+        self.pipe = Some((rx, tx));
+
+        let action = SigAction::new(
+            SigHandler::Handler(handle_signal),
+            SaFlags::SA_RESTART | SaFlags::SA_NOCLDSTOP,
+            SigSet::empty(),
+        );
+
+        let signals = self.signals.iter().map(|s| s.signal);
+        for signal in std::iter::once(Signal::SIGCHLD).chain(signals) {
+            // SAFETY: the handler only stores to an atomic and writes to a pipe, both of which
+            //         are async-signal-safe.
+            unsafe { sigaction(signal, &action) }
+                .map_err(|e| Error::Custom(format!("unable to set {signal} handler: {e}")))?;
+        }
+
+        Ok(())
+    }
+
+    // How long to wait before the next timer is due.
+    fn timeout(&self, now: Instant) -> PollTimeout {
+        match self.timers.iter().map(|t| t.next).min() {
+            Some(next) => PollTimeout::try_from(next.saturating_duration_since(now))
+                .unwrap_or(PollTimeout::MAX),
+            None => PollTimeout::NONE,
+        }
+    }
+
+    /// Block until the X connection, one of the registered file descriptors or the signal pipe
+    /// is readable, or until the next timer is due. Returns the indices of the registered file
+    /// descriptors that are ready to be read.
+    pub(crate) fn wait(&mut self, x_fd: Option<RawFd>) -> Result<Vec<usize>> {
+        let flags = PollFlags::POLLIN;
+        let timeout = self.timeout(Instant::now());
+
+        // SAFETY: the X connection fd is owned by the XConn and user fds are required to stay
+        //         open while the event loop is running.
+        let borrow = |fd: RawFd| unsafe { BorrowedFd::borrow_raw(fd) };
+
+        let mut fds: Vec<PollFd<'_>> = self
+            .fds
+            .iter()
+            .map(|s| PollFd::new(borrow(s.fd), flags))
+            .collect();
+        if let Some((rx, _)) = &self.pipe {
+            fds.push(PollFd::new(rx.as_fd(), flags));
+        }
+        if let Some(fd) = x_fd {
+            fds.push(PollFd::new(borrow(fd), flags));
+        }
+
+        match poll(&mut fds, timeout) {
+            Ok(_) | Err(Errno::EINTR) => (),
+            Err(e) => return Err(Error::Custom(format!("unable to poll event sources: {e}"))),
+        }
+
+        let ready = fds
+            .iter()
+            .take(self.fds.len())
+            .enumerate()
+            .filter(|(_, fd)| fd.revents().is_some_and(|r| !r.is_empty()))
+            .map(|(i, _)| i)
+            .collect();
+
+        Ok(ready)
+    }
+
+    // Drain the signal pipe and return the set of signals received since the last call.
+    fn take_signals(&mut self) -> u64 {
+        if let Some((rx, _)) = &mut self.pipe {
+            let mut buf = [0; 64];
+            loop {
+                match rx.read(&mut buf) {
+                    Ok(n) if n > 0 => continue,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    _ => break,
+                }
+            }
+        }
+
+        PENDING_SIGNALS.swap(0, Ordering::SeqCst)
+    }
+
+    /// Run the hooks for any received signals, ready file descriptors and due timers, returning
+    /// `true` if a SIGCHLD was received.
+    pub(crate) fn dispatch(&mut self, ready: &[usize], state: &mut State<X>, x: &X) -> bool {
+        let policy = state.config.panic_policy;
+        let pending = self.take_signals();
+        let is_pending = |signal: Signal| pending & (1u64 << (signal as i32 % 64)) != 0;
+
+        self.signals.retain_mut(|s| {
+            !is_pending(s.signal) || run_hook(policy, "signal hook", &mut s.hook, state, x)
+        });
+
+        let mut i = 0;
+        self.fds.retain_mut(|s| {
+            let keep = !ready.contains(&i) || run_hook(policy, "fd hook", &mut s.hook, state, x);
+            i += 1;
+            keep
+        });
+
+        let now = Instant::now();
+        self.timers.retain_mut(|t| {
+            if t.next > now {
+                return true;
+            }
+            t.next += t.interval;
+            if t.next <= now {
+                t.next = now + t.interval;
+            }
+
+            run_hook(policy, "timer hook", &mut t.hook, state, x)
+        });
+
+        is_pending(Signal::SIGCHLD)
+    }
+}
+
+// Run an event loop hook, returning false if it should be disabled.
+fn run_hook<X: XConn>(
+    policy: PanicPolicy,
+    what: &str,
+    hook: &mut Box<dyn StateHook<X>>,
+    state: &mut State<X>,
+    x: &X,
+) -> bool {
+    trace!(%what, "running event loop hook");
+    match state.catch_panic(what, x, true, |state| hook.call(state, x)) {
+        Ok(()) => true,
+        Err(e @ Error::Panicked { .. }) if policy == PanicPolicy::Disable => {
+            error!(%e, "disabling event loop hook");
+            state.run_error_hook(&e, x);
+            false
+        }
+        Err(e) => {
+            error!(%e, "error returned from event loop hook");
+            state.run_error_hook(&e, x);
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::x::TestConn;
+    use std::io::Write;
+
+    #[test]
+    fn the_timeout_is_until_the_next_timer() {
+        let now = Instant::now();
+        let mut el: EventLoop<TestConn> = EventLoop::new();
+        assert_eq!(el.timeout(now), PollTimeout::NONE);
+
+        el = el
+            .with_timer(
+                Duration::from_secs(10),
+                |_: &mut State<TestConn>, _: &TestConn| Ok(()),
+            )
+            .with_timer(
+                Duration::from_millis(50),
+                |_: &mut State<TestConn>, _: &TestConn| Ok(()),
+            );
+        let timeout: i32 = el.timeout(now).into();
+
+        assert!(timeout <= 50 && timeout > 0, "{timeout}");
+    }
+
+    #[test]
+    fn readable_fds_are_reported_as_ready() {
+        let (mut a, b) = UnixStream::pair().unwrap();
+        let (_c, d) = UnixStream::pair().unwrap();
+        let noop = |_: &mut State<TestConn>, _: &TestConn| Ok(());
+        let mut el: EventLoop<TestConn> = EventLoop::new()
+            .with_fd(d.as_raw_fd(), noop)
+            .with_fd(b.as_raw_fd(), noop);
+
+        a.write_all(b"hello").unwrap();
+
+        assert_eq!(el.wait(None).unwrap(), vec![1]);
+    }
+}
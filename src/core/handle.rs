@@ -4,16 +4,16 @@ use crate::{
         bindings::{
             KeyBindings, KeyCode, MotionNotifyEvent, MouseBindings, MouseEvent, MouseEventKind,
         },
-        State, Xid,
+        PanicPolicy, State, Xid,
     },
-    pure::geometry::Point,
+    pure::{geometry::Point, Position, Stack},
     x::{
         atom::Atom,
-        event::{ClientMessage, ClientMessageKind, ConfigureEvent, PointerChange},
+        event::{ClientMessage, ClientMessageKind, ConfigureEvent, PointerChange, PropertyEvent},
         property::{Prop, WmHints},
         ClientConfig, XConn, XConnExt,
     },
-    Result,
+    Error, Result,
 };
 use tracing::{error, info, trace};
 
@@ -44,10 +44,17 @@ pub(crate) fn keypress<X: XConn>(
     state: &mut State<X>,
     x: &X,
 ) -> Result<()> {
+    let policy = state.config.panic_policy;
     if let Some(action) = bindings.get_mut(&key) {
         trace!(?key, "running user keybinding");
-        if let Err(error) = action.call(state, x) {
+        if let Err(error) = state.catch_panic("key binding", x, true, |state| action.call(state, x))
+        {
             error!(%error, ?key, "error running user keybinding");
+            if matches!(error, Error::Panicked { .. }) && policy == PanicPolicy::Disable {
+                error!(?key, "disabling user keybinding");
+                bindings.remove(&key);
+            }
+            state.run_error_hook(&error, x);
             return Err(error);
         }
     }
@@ -61,9 +68,19 @@ pub(crate) fn mouse_event<X: XConn>(
     state: &mut State<X>,
     x: &X,
 ) -> Result<()> {
+    let policy = state.config.panic_policy;
     if let Some(action) = bindings.get_mut(&e.state) {
-        if let Err(error) = action.on_mouse_event(&e, state, x) {
+        let res = state.catch_panic("mouse binding", x, true, |state| {
+            action.on_mouse_event(&e, state, x)
+        });
+        if let Err(error) = res {
             error!(%error, ?e, "error running user mouse binding");
+            if matches!(error, Error::Panicked { .. }) && policy == PanicPolicy::Disable {
+                error!(?e, "disabling user mouse binding");
+                bindings.remove(&e.state);
+                state.held_mouse_state = None;
+            }
+            state.run_error_hook(&error, x);
             return Err(error);
         }
 
@@ -71,11 +88,46 @@ pub(crate) fn mouse_event<X: XConn>(
             MouseEventKind::Press => state.held_mouse_state = Some(e.state),
             MouseEventKind::Release => state.held_mouse_state = None,
         }
+    } else if e.kind == MouseEventKind::Press && state.config.click_to_focus {
+        click_to_focus(e.data.window, state, x)?;
     }
 
     Ok(())
 }
 
+// Clicks on unfocused clients are intercepted using a button grab that freezes the pointer, so
+// the pointer needs to be released once the client has been focused.
+fn click_to_focus<X: XConn>(id: Xid, state: &mut State<X>, x: &X) -> Result<()> {
+    let res = if state.client_set.contains(&id) {
+        trace!(%id, "focusing clicked client");
+        let raise = state.config.raise_floating_on_click && state.client_set.is_floating(&id);
+        x.modify_and_refresh(state, |cs| {
+            cs.focus_client(&id);
+            if raise {
+                cs.modify_occupied(move_focus_to_head);
+            }
+        })
+    } else {
+        Ok(())
+    };
+
+    x.allow_pointer_events(state.config.pass_through_focus_click)?;
+
+    res
+}
+
+// Floating clients are stacked in reverse order of their position in the workspace stack so
+// moving a client to the head of the stack raises it above all other floating clients.
+fn move_focus_to_head(s: Stack<Xid>) -> Stack<Xid> {
+    match s.remove_focused() {
+        (id, Some(mut rest)) => {
+            rest.insert_at(Position::Head, id).focus_head();
+            rest
+        }
+        (id, None) => Stack::new([], id, []),
+    }
+}
+
 pub(crate) fn motion_event<X: XConn>(
     e: MotionNotifyEvent,
     bindings: &mut MouseBindings<X>,
@@ -106,9 +158,27 @@ pub(crate) fn configure_request<X: XConn>(
         return Ok(()); // Managed tiled clients aren't allowed to configure themselves
     }
 
+    // Floating clients are moved back to their managed position on the next refresh
+    state.applied_positions.borrow_mut().remove(id);
     x.set_client_config(*id, &[ClientConfig::Position(*r)])
 }
 
+// Clients that have been moved or resized other than by position_clients need to be repositioned
+// on the next refresh
+pub(crate) fn configure_notify<X: XConn>(
+    ConfigureEvent { id, r, .. }: &ConfigureEvent,
+    state: &mut State<X>,
+) {
+    let mut applied_positions = state.applied_positions.borrow_mut();
+    if applied_positions
+        .get_mut(id)
+        .is_some_and(|p| !p.confirm(*r))
+    {
+        trace!(%id, ?r, "client geometry changed externally");
+        applied_positions.remove(id);
+    }
+}
+
 pub(crate) fn map_request<X: XConn>(client: Xid, state: &mut State<X>, x: &X) -> Result<()> {
     trace!(?client, "handling new map request");
     let attrs = x.get_window_attributes(client)?;
@@ -170,6 +240,26 @@ pub(crate) fn focus_in<X: XConn>(client: Xid, state: &mut State<X>, x: &X) -> Re
     Ok(())
 }
 
+// Changes to the urgency of a client are reflected in its border color
+pub(crate) fn property_notify<X: XConn>(
+    PropertyEvent { id, atom, is_root }: &PropertyEvent,
+    state: &mut State<X>,
+    x: &X,
+) -> Result<()> {
+    // Size hints are applied when a client is positioned so they need to be reapplied on the
+    // next refresh
+    if !*is_root && atom == Atom::WmNormalHints.as_ref() {
+        state.applied_positions.borrow_mut().remove(id);
+    }
+
+    let urgency_props = [Atom::WmHints.as_ref(), Atom::NetWmState.as_ref()];
+    if *is_root || !state.client_set.contains(id) || !urgency_props.contains(&atom.as_str()) {
+        return Ok(());
+    }
+
+    x.set_client_border_color(*id, x.border_color_for(*id, state)?)
+}
+
 pub(crate) fn enter<X: XConn>(p: PointerChange, state: &mut State<X>, x: &X) -> Result<()> {
     if state.config.focus_follow_mouse {
         x.modify_and_refresh(state, |cs| {
@@ -194,7 +284,11 @@ pub(crate) fn detect_screens<X: XConn>(state: &mut State<X>, x: &X) -> Result<()
     let rects = x.screen_details()?;
     info!(?rects, "found screens");
 
-    state.client_set.update_screens(rects)
+    state.client_set.update_screens(rects)?;
+    let primary = x.primary_screen()?.unwrap_or(0);
+    state.client_set.set_primary_screen(primary);
+
+    x.refresh(state)
 }
 
 pub(crate) fn screen_change<X: XConn>(state: &mut State<X>, x: &X) -> Result<()> {
@@ -204,10 +298,7 @@ pub(crate) fn screen_change<X: XConn>(state: &mut State<X>, x: &X) -> Result<()>
 
 fn set_screen_from_point<X: XConn>(p: Point, state: &mut State<X>, x: &X) -> Result<()> {
     x.modify_and_refresh(state, |cs| {
-        let index = cs
-            .screens()
-            .find(|s| s.r.contains_point(p))
-            .map(|s| s.index());
+        let index = cs.screen_containing_point(p).map(|s| s.index());
 
         if let Some(index) = index {
             cs.focus_screen(index);
@@ -76,6 +76,31 @@
 //! This is one of the more general purpose hooks available for you to make use of and can be
 //! used to run code any time something changes in the internal state of your window manager.
 //!
+//! ### Child Exit Hooks
+//!
+//! [`ChildExitHook`]s are run each time a child process of the window manager (such as a
+//! program started using [spawn][3]) exits and is reaped, along with the process ID and
+//! [`ChildExitStatus`] of the process. This allows you to track programs that you have started
+//! and react to them exiting (or crashing).
+//!
+//! Only programs started without waiting for them to exit (using [spawn][3] or
+//! [SpawnSpec::spawn_for_pid][5]) have their exit status passed to this hook. Any other child
+//! processes that exit are still reaped by the window manager so that they do not become
+//! zombies, unless penrose is itself waiting on a child process at the time (for example when
+//! running [spawn_for_output][6]). If you wait on child processes from a thread of your own then
+//! the window manager may reap them first and their exit status will be lost.
+//!
+//! ### Error Hooks
+//!
+//! Finally, [`ErrorHook`]s are run with the [`Error`] returned from a key binding, mouse
+//! binding or any of the other hooks (including panics caught according to the configured
+//! [PanicPolicy][4]). Without an error hook these errors are only logged: an error hook
+//! lets you surface them to the user instead, for example by showing them in a popup.
+//!
+//! > **NOTE**: Error hooks may be run from inside of a refresh so they should _not_ trigger
+//! >           a refresh of the X state themselves. Errors returned from an error hook are
+//! >           logged rather than being passed back to the hook.
+//!
 //! ## Setting and composing hooks
 //!
 //! Each kind of hook has a corresponding `compose_or_set_*_hook` method on the [Config][2]
@@ -86,12 +111,16 @@
 //!   [0]: crate::extensions::hooks::manage
 //!   [1]: crate::core::layout::Layout
 //!   [2]: crate::core::Config
+//!   [3]: crate::util::spawn
+//!   [4]: crate::core::PanicPolicy
+//!   [5]: crate::util::SpawnSpec::spawn_for_pid
+//!   [6]: crate::util::spawn_for_output
 
 use crate::{
     core::{layout::LayoutTransformer, State},
     pure::geometry::Rect,
     x::{XConn, XEvent},
-    Result, Xid,
+    Error, Result, Xid,
 };
 use std::fmt;
 
@@ -380,6 +409,219 @@ where
     }
 }
 
+/// How a child process of the window manager exited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChildExitStatus {
+    /// The process exited with the given exit code
+    Exited(i32),
+    /// The process was terminated by the given signal number
+    Signaled(i32),
+}
+
+/// Action to run when a child process of the window manager exits.
+pub trait ChildExitHook<X>
+where
+    X: XConn,
+{
+    /// Run this hook
+    fn call(
+        &mut self,
+        pid: u32,
+        status: ChildExitStatus,
+        state: &mut State<X>,
+        x: &X,
+    ) -> Result<()>;
+
+    /// Convert to a trait object
+    fn boxed(self) -> Box<dyn ChildExitHook<X>>
+    where
+        Self: Sized + 'static,
+    {
+        Box::new(self)
+    }
+
+    /// Compose this hook with another [ChildExitHook].
+    fn then<H>(self, next: H) -> ComposedChildExitHook<X>
+    where
+        H: ChildExitHook<X> + 'static,
+        Self: Sized + 'static,
+    {
+        ComposedChildExitHook {
+            first: Box::new(self),
+            second: Box::new(next),
+        }
+    }
+
+    /// Compose this hook with a boxed [ChildExitHook].
+    fn then_boxed(self, next: Box<dyn ChildExitHook<X>>) -> Box<dyn ChildExitHook<X>>
+    where
+        Self: Sized + 'static,
+        X: 'static,
+    {
+        Box::new(ComposedChildExitHook {
+            first: Box::new(self),
+            second: next,
+        })
+    }
+}
+
+impl<X> ChildExitHook<X> for Vec<Box<dyn ChildExitHook<X>>>
+where
+    X: XConn,
+{
+    fn call(
+        &mut self,
+        pid: u32,
+        status: ChildExitStatus,
+        state: &mut State<X>,
+        x: &X,
+    ) -> Result<()> {
+        for hook in self.iter_mut() {
+            hook.call(pid, status, state, x)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<X: XConn> fmt::Debug for Box<dyn ChildExitHook<X>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChildExitHook").finish()
+    }
+}
+
+/// The result of composing two child exit hooks using `then`
+#[derive(Debug)]
+pub struct ComposedChildExitHook<X>
+where
+    X: XConn,
+{
+    first: Box<dyn ChildExitHook<X>>,
+    second: Box<dyn ChildExitHook<X>>,
+}
+
+impl<X> ChildExitHook<X> for ComposedChildExitHook<X>
+where
+    X: XConn,
+{
+    fn call(
+        &mut self,
+        pid: u32,
+        status: ChildExitStatus,
+        state: &mut State<X>,
+        x: &X,
+    ) -> Result<()> {
+        self.first.call(pid, status, state, x)?;
+        self.second.call(pid, status, state, x)
+    }
+}
+
+impl<F, X> ChildExitHook<X> for F
+where
+    F: FnMut(u32, ChildExitStatus, &mut State<X>, &X) -> Result<()>,
+    X: XConn,
+{
+    fn call(
+        &mut self,
+        pid: u32,
+        status: ChildExitStatus,
+        state: &mut State<X>,
+        x: &X,
+    ) -> Result<()> {
+        (self)(pid, status, state, x)
+    }
+}
+
+/// Action to run when a key binding, mouse binding or hook returns an [Error].
+pub trait ErrorHook<X>
+where
+    X: XConn,
+{
+    /// Run this hook
+    fn call(&mut self, error: &Error, state: &mut State<X>, x: &X) -> Result<()>;
+
+    /// Convert to a trait object
+    fn boxed(self) -> Box<dyn ErrorHook<X>>
+    where
+        Self: Sized + 'static,
+    {
+        Box::new(self)
+    }
+
+    /// Compose this hook with another [ErrorHook].
+    fn then<H>(self, next: H) -> ComposedErrorHook<X>
+    where
+        H: ErrorHook<X> + 'static,
+        Self: Sized + 'static,
+    {
+        ComposedErrorHook {
+            first: Box::new(self),
+            second: Box::new(next),
+        }
+    }
+
+    /// Compose this hook with a boxed [ErrorHook].
+    fn then_boxed(self, next: Box<dyn ErrorHook<X>>) -> Box<dyn ErrorHook<X>>
+    where
+        Self: Sized + 'static,
+        X: 'static,
+    {
+        Box::new(ComposedErrorHook {
+            first: Box::new(self),
+            second: next,
+        })
+    }
+}
+
+impl<X> ErrorHook<X> for Vec<Box<dyn ErrorHook<X>>>
+where
+    X: XConn,
+{
+    fn call(&mut self, error: &Error, state: &mut State<X>, x: &X) -> Result<()> {
+        for hook in self.iter_mut() {
+            hook.call(error, state, x)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<X: XConn> fmt::Debug for Box<dyn ErrorHook<X>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorHook").finish()
+    }
+}
+
+/// The result of composing two error hooks using `then`
+#[derive(Debug)]
+pub struct ComposedErrorHook<X>
+where
+    X: XConn,
+{
+    first: Box<dyn ErrorHook<X>>,
+    second: Box<dyn ErrorHook<X>>,
+}
+
+impl<X> ErrorHook<X> for ComposedErrorHook<X>
+where
+    X: XConn,
+{
+    fn call(&mut self, error: &Error, state: &mut State<X>, x: &X) -> Result<()> {
+        self.first.call(error, state, x)?;
+        self.second.call(error, state, x)
+    }
+}
+
+impl<F, X> ErrorHook<X> for F
+where
+    F: FnMut(&Error, &mut State<X>, &X) -> Result<()>,
+    X: XConn,
+{
+    fn call(&mut self, error: &Error, state: &mut State<X>, x: &X) -> Result<()> {
+        (self)(error, state, x)
+    }
+}
+
 /// Logic to run before and after laying out clients
 pub trait LayoutHook<X>
 where
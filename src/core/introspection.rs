@@ -0,0 +1,188 @@
+//! Structured summaries of the current window manager state for use by external tooling.
+//!
+//! A [StateSnapshot] is a plain data representation of the screens, workspaces and clients
+//! known to the window manager that (with the `serde` feature enabled) can be serialized and
+//! consumed by scripts, status bars and debugging tools. See [dump_state_as_json] for a key
+//! binding action that writes the current snapshot out as JSON.
+//!
+//!   [dump_state_as_json]: crate::builtin::actions::dump_state_as_json
+use crate::{
+    core::State,
+    pure::geometry::Rect,
+    x::{Atom, Prop, XConn, XConnExt},
+    Result, Xid,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A summary of the current window manager state.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateSnapshot {
+    /// The screens currently known to the window manager, in index order
+    pub screens: Vec<ScreenSnapshot>,
+    /// All workspaces ordered by their ID
+    pub workspaces: Vec<WorkspaceSnapshot>,
+    /// The index of the screen that currently has focus
+    pub focused_screen: usize,
+    /// The tag of the workspace that currently has focus
+    pub focused_tag: String,
+    /// The client that currently has focus, if there is one
+    pub focused_client: Option<Xid>,
+}
+
+/// A summary of a single screen.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenSnapshot {
+    /// The index of this screen
+    pub index: usize,
+    /// The geometry of this screen
+    pub geometry: Rect,
+    /// The tag of the workspace currently displayed on this screen
+    pub tag: String,
+}
+
+/// A summary of a single workspace and the clients it contains.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceSnapshot {
+    /// The ID of this workspace
+    pub id: usize,
+    /// The tag of this workspace
+    pub tag: String,
+    /// The name of the active layout for this workspace
+    pub layout: String,
+    /// The index of the screen this workspace is displayed on, if it is visible
+    pub screen: Option<usize>,
+    /// The focused client within this workspace, if there is one
+    pub focus: Option<Xid>,
+    /// The clients on this workspace in stack order
+    pub clients: Vec<ClientSnapshot>,
+}
+
+/// A summary of a single client window.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientSnapshot {
+    /// The ID of this client
+    pub id: Xid,
+    /// The title of this client
+    pub title: String,
+    /// The WM_CLASS of this client
+    pub class: String,
+    /// The on screen position of this client if it is currently mapped
+    pub geometry: Option<Rect>,
+    /// Whether or not this client is floating
+    pub floating: bool,
+    /// Whether or not this client is fullscreen
+    pub fullscreen: bool,
+}
+
+impl<X: XConn> State<X> {
+    /// Generate a [StateSnapshot] summarising the current state of the window manager.
+    ///
+    /// Client titles, classes and geometry are queried from the X server so this should not be
+    /// called on every event if you have a large number of clients.
+    pub fn state_snapshot(&self, x: &X) -> Result<StateSnapshot> {
+        let cs = &self.client_set;
+
+        let screens = cs
+            .screens()
+            .map(|s| ScreenSnapshot {
+                index: s.index(),
+                geometry: s.geometry(),
+                tag: s.workspace.tag().to_owned(),
+            })
+            .collect();
+
+        let mut workspaces = Vec::new();
+        for w in cs.ordered_workspaces() {
+            let clients = w
+                .clients()
+                .map(|&id| self.client_snapshot(id, x))
+                .collect::<Result<Vec<_>>>()?;
+
+            workspaces.push(WorkspaceSnapshot {
+                id: w.id(),
+                tag: w.tag().to_owned(),
+                layout: w.layout_name(),
+                screen: cs
+                    .screens()
+                    .find(|s| s.workspace.id() == w.id())
+                    .map(|s| s.index()),
+                focus: w.focus().copied(),
+                clients,
+            });
+        }
+
+        Ok(StateSnapshot {
+            screens,
+            workspaces,
+            focused_screen: cs.current_screen().index(),
+            focused_tag: cs.current_tag().to_owned(),
+            focused_client: cs.current_client().copied(),
+        })
+    }
+
+    fn client_snapshot(&self, id: Xid, x: &X) -> Result<ClientSnapshot> {
+        let class = match x.get_prop(id, Atom::WmClass.as_ref())? {
+            Some(Prop::UTF8String(strs)) => strs.last().cloned().unwrap_or_default(),
+            _ => String::new(),
+        };
+        let geometry = if self.mapped.contains(&id) {
+            Some(x.client_geometry(id)?)
+        } else {
+            None
+        };
+
+        Ok(ClientSnapshot {
+            id,
+            title: x.window_title(id)?,
+            class,
+            geometry,
+            floating: self.client_set.is_floating(&id),
+            fullscreen: self.is_fullscreen(&id),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{Config, WindowManager},
+        x::TestConn,
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn state_snapshot_summarises_clients() {
+        let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+        let mut wm =
+            WindowManager::new(Config::default(), HashMap::new(), HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+        let id = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        let snapshot = wm.state_snapshot().unwrap();
+
+        assert_eq!(snapshot.screens.len(), 1);
+        assert_eq!(snapshot.screens[0].tag, "1");
+        assert_eq!(snapshot.focused_client, Some(id));
+        assert_eq!(snapshot.workspaces.len(), 9);
+        assert_eq!(snapshot.workspaces[0].screen, Some(0));
+        assert_eq!(snapshot.workspaces[1].screen, None);
+        assert_eq!(
+            snapshot.workspaces[0].clients,
+            vec![ClientSnapshot {
+                id,
+                title: String::new(),
+                class: "st".to_owned(),
+                geometry: Some(Rect::new(0, 0, 1000, 800)),
+                floating: false,
+                fullscreen: false,
+            }]
+        );
+    }
+}
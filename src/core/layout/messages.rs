@@ -1,23 +1,58 @@
-use std::any::Any;
-use std::fmt;
+use std::{any::Any, cell::Cell, fmt};
 
 /// A dynamically typed message to be sent to a [Layout][0] for processing.
 ///
 /// See the [IntoMessage] trait for how to mark a type as being usable as a [Message].
 ///
+/// A message keeps track of whether or not it has been recognised by a layout: any call to
+/// [Message::downcast_ref] or [Message::is] for the correct type marks the message as having
+/// been recognised. Messages that are sent to a [LayoutStack][1] without being recognised by
+/// any of the layouts they were sent to are logged at `DEBUG` level along with the type of the
+/// message, which can be useful when trying to work out why a custom layout is not responding
+/// to a given message.
+///
 ///   [0]: crate::core::layout::Layout
-pub struct Message(Box<dyn Any>);
+///   [1]: crate::core::layout::LayoutStack
+pub struct Message {
+    inner: Box<dyn Any>,
+    type_name: &'static str,
+    recognised: Cell<bool>,
+}
 
 impl fmt::Debug for Message {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Message").finish()
+        f.debug_struct("Message")
+            .field("type_name", &self.type_name)
+            .finish()
     }
 }
 
 impl Message {
     /// Check to see whether this [Message] is a particular type
     pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
-        self.0.downcast_ref()
+        let t = self.inner.downcast_ref();
+        if t.is_some() {
+            self.recognised.set(true);
+        }
+
+        t
+    }
+
+    /// Returns `true` if this [Message] is of type `T`.
+    pub fn is<T: 'static>(&self) -> bool {
+        self.downcast_ref::<T>().is_some()
+    }
+
+    /// The name of the type wrapped by this [Message].
+    ///
+    /// This is intended for diagnostic purposes only: see [std::any::type_name] for details.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Whether or not this [Message] has been recognised by a layout it was sent to.
+    pub fn was_recognised(&self) -> bool {
+        self.recognised.get()
     }
 }
 
@@ -37,6 +72,67 @@ pub trait IntoMessage: Any {
     where
         Self: Sized,
     {
-        Message(Box::new(self))
+        Message {
+            inner: Box::new(self),
+            type_name: std::any::type_name::<Self>(),
+            recognised: Cell::new(false),
+        }
+    }
+}
+
+// Messages that have already been wrapped are passed through as is rather than being wrapped a
+// second time.
+impl IntoMessage for Message {
+    fn into_message(self) -> Message {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builtin::layout::{
+            messages::{IncMain, ToggleGaps},
+            MainAndStack,
+        },
+        core::layout::{Layout, LayoutStack},
+        stack,
+    };
+
+    #[test]
+    fn messages_are_only_recognised_when_downcast_to_their_type() {
+        let m = IncMain(1).into_message();
+
+        assert!(!m.is::<ToggleGaps>());
+        assert!(!m.was_recognised());
+        assert!(m.is::<IncMain>());
+        assert!(m.was_recognised());
+        assert!(m.type_name().ends_with("IncMain"));
+    }
+
+    #[test]
+    fn wrapped_messages_are_not_wrapped_again() {
+        let m = IncMain(1).into_message().into_message();
+
+        assert_eq!(m.downcast_ref::<IncMain>(), Some(&IncMain(1)));
+    }
+
+    #[test]
+    fn layouts_recognise_the_messages_they_handle() {
+        let mut l = MainAndStack::default();
+
+        let m = IncMain(1).into_message();
+        l.handle_message(&m);
+        assert!(m.was_recognised());
+
+        let m = ToggleGaps.into_message();
+        l.handle_message(&m);
+        assert!(!m.was_recognised());
+
+        // Unrecognised messages are logged rather than panicking or erroring
+        let mut ls: LayoutStack = stack!(Box::new(l));
+        ls.handle_message(ToggleGaps);
+        ls.broadcast_message(ToggleGaps);
     }
 }
@@ -5,6 +5,7 @@ use crate::{
     stack, Xid,
 };
 use std::{fmt, mem::swap};
+use tracing::debug;
 
 mod messages;
 mod transformers;
@@ -153,10 +154,16 @@ impl LayoutStack {
         M: IntoMessage,
     {
         let m = m.into_message();
+        let name = self.focus.name();
 
         if let Some(mut new) = self.focus.handle_message(&m) {
             swap(&mut self.focus, &mut new);
         }
+
+        if !m.was_recognised() {
+            let message = m.type_name();
+            debug!(message, layout = %name, "message not recognised by the active layout");
+        }
     }
 
     /// Send the given [Message] to every [Layout] in this stack rather that just the
@@ -172,6 +179,13 @@ impl LayoutStack {
                 swap(l, &mut new);
             }
         }
+
+        if !m.was_recognised() {
+            debug!(
+                message = m.type_name(),
+                "message not recognised by any layout"
+            );
+        }
     }
 }
 
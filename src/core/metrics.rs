@@ -0,0 +1,188 @@
+//! Timing information about the main event loop for tracking down slow hooks and handlers.
+//!
+//! The [WindowManager][0] records how long it spends handling each type of [XEvent][1], how many
+//! times the on screen state has been refreshed and how long each of the user hooks set in the
+//! [Config][2] take to run. The current [Metrics] can be accessed from the [State][3]:
+//!
+//! ```no_run
+//! # use penrose::{core::{metrics::HookKind, State}, x::XConn};
+//! fn log_slow_refreshes<X: XConn>(state: &State<X>) {
+//!     let metrics = state.metrics();
+//!     if let Some(t) = metrics.hook(HookKind::Refresh) {
+//!         println!("{} refresh hook calls: mean={:?} max={:?}", t.count, t.mean(), t.max);
+//!     }
+//! }
+//! ```
+//!
+//!   [0]: crate::core::WindowManager
+//!   [1]: crate::x::XEvent
+//!   [2]: crate::core::Config
+//!   [3]: crate::core::State
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// The user hooks that can be set in a [Config][crate::core::Config].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HookKind {
+    /// The startup_hook
+    Startup,
+    /// The event_hook
+    Event,
+    /// The manage_hook
+    Manage,
+    /// The refresh_hook
+    Refresh,
+    /// The layout_hook
+    Layout,
+    /// The shutdown_hook
+    Shutdown,
+    /// The child_exit_hook
+    ChildExit,
+    /// The error_hook
+    Error,
+}
+
+impl fmt::Display for HookKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Startup => "startup",
+            Self::Event => "event",
+            Self::Manage => "manage",
+            Self::Refresh => "refresh",
+            Self::Layout => "layout",
+            Self::Shutdown => "shutdown",
+            Self::ChildExit => "child_exit",
+            Self::Error => "error",
+        };
+
+        write!(f, "{s}")
+    }
+}
+
+/// Aggregated timings for a repeated operation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Timing {
+    /// The number of times the operation has been run
+    pub count: u64,
+    /// The total time spent running the operation
+    pub total: Duration,
+    /// The longest single run of the operation
+    pub max: Duration,
+}
+
+impl Timing {
+    /// The mean time taken to run the operation.
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.count as u32
+        }
+    }
+
+    fn record(&mut self, d: Duration) {
+        self.count += 1;
+        self.total += d;
+        self.max = self.max.max(d);
+    }
+}
+
+/// Timings recorded while running the main event loop.
+///
+/// See the module level docs for details.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Metrics {
+    events: HashMap<String, Timing>,
+    hooks: HashMap<HookKind, Timing>,
+    refreshes: Timing,
+    client_set_copies: Timing,
+}
+
+impl Metrics {
+    /// Timings for handling each type of event, keyed by the name of the event type.
+    ///
+    /// This includes the time spent running the event hook along with the default handling for
+    /// the event.
+    pub fn events(&self) -> &HashMap<String, Timing> {
+        &self.events
+    }
+
+    /// Timings for handling a given type of event, if any have been seen.
+    pub fn event(&self, name: &str) -> Option<Timing> {
+        self.events.get(name).copied()
+    }
+
+    /// Timings for each of the user hooks that have been run.
+    pub fn hooks(&self) -> &HashMap<HookKind, Timing> {
+        &self.hooks
+    }
+
+    /// Timings for a given user hook, if it has been run.
+    pub fn hook(&self, kind: HookKind) -> Option<Timing> {
+        self.hooks.get(&kind).copied()
+    }
+
+    /// Timings for refreshing the on screen state, including running the refresh hook.
+    pub fn refreshes(&self) -> Timing {
+        self.refreshes
+    }
+
+    /// Timings for copying the [ClientSet][0] before running a binding or hook so that it can be
+    /// restored if the binding or hook panics.
+    ///
+    ///   [0]: crate::core::ClientSet
+    pub fn client_set_copies(&self) -> Timing {
+        self.client_set_copies
+    }
+
+    /// The hook with the largest mean run time, if any hooks have been run.
+    pub fn slowest_hook(&self) -> Option<(HookKind, Timing)> {
+        self.hooks
+            .iter()
+            .max_by_key(|(_, t)| t.mean())
+            .map(|(&k, &t)| (k, t))
+    }
+
+    /// Clear all recorded timings.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub(crate) fn record_event(&mut self, name: String, start: Instant) {
+        self.events.entry(name).or_default().record(start.elapsed());
+    }
+
+    pub(crate) fn record_hook(&mut self, kind: HookKind, start: Instant) {
+        self.hooks.entry(kind).or_default().record(start.elapsed());
+    }
+
+    pub(crate) fn record_refresh(&mut self, start: Instant) {
+        self.refreshes.record(start.elapsed());
+    }
+
+    pub(crate) fn record_client_set_copy(&mut self, start: Instant) {
+        self.client_set_copies.record(start.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timing_tracks_count_total_and_max() {
+        let mut t = Timing::default();
+        assert_eq!(t.mean(), Duration::ZERO);
+
+        t.record(Duration::from_millis(2));
+        t.record(Duration::from_millis(4));
+
+        assert_eq!(t.count, 2);
+        assert_eq!(t.total, Duration::from_millis(6));
+        assert_eq!(t.max, Duration::from_millis(4));
+        assert_eq!(t.mean(), Duration::from_millis(3));
+    }
+}
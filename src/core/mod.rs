@@ -1,15 +1,29 @@
 //! Core data structures and user facing functionality for the window manager
 use crate::{
-    pure::{geometry::Rect, Diff, ScreenClients, Snapshot, StackSet, Workspace},
+    pure::{
+        geometry::{Point, Rect, RelativeRect},
+        Diff, Position, ScreenClients, Snapshot, Stack, StackSet, Workspace,
+    },
+    stack,
+    util::{untrack_children, waiting_for_children},
     x::{
+        atom::AUTO_FLOAT_WINDOW_TYPES,
         manage_without_refresh,
         property::{MapState, WmState},
-        Atom, Prop, WindowAttributes, XConn, XConnExt, XEvent,
+        query::{FixedSize, IsTransient, WindowType},
+        Atom, Prop, Query, TestConn, WindowAttributes, XConn, XConnExt, XEvent,
     },
     Color, Error, Result,
 };
 use anymap::{any::Any, AnyMap};
-use nix::sys::signal::{signal, SigHandler, Signal};
+use nix::{
+    errno::Errno,
+    sys::{
+        signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal},
+        wait::{waitpid, WaitPidFlag, WaitStatus},
+    },
+    unistd::Pid,
+};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::{
@@ -18,18 +32,40 @@ use std::{
     collections::{HashMap, HashSet},
     fmt,
     ops::Deref,
-    sync::Arc,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
 };
 use tracing::{debug, error, info, span, trace, warn, Level};
 
 pub mod bindings;
+pub mod config;
+pub mod event_loop;
 pub(crate) mod handle;
 pub mod hooks;
+pub mod introspection;
 pub mod layout;
+pub mod metrics;
+pub mod window_system;
 
 use bindings::{KeyBindings, MouseBindings, MouseState};
-use hooks::{EventHook, LayoutHook, ManageHook, StateHook};
+use event_loop::EventLoop;
+use hooks::{
+    ChildExitHook, ChildExitStatus, ErrorHook, EventHook, LayoutHook, ManageHook, StateHook,
+};
 use layout::{Layout, LayoutStack};
+use metrics::{HookKind, Metrics};
+
+// Set from the SIGCHLD handler when a child process exits so that it can be reaped from the
+// main event loop.
+static CHILD_EXITED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_sigchld(_: i32) {
+    CHILD_EXITED.store(true, Ordering::SeqCst);
+}
 
 /// An X11 ID for a given resource
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -68,6 +104,64 @@ pub type ClientSet = StackSet<Xid>;
 /// The pure client state information for a single [Workspace]
 pub type ClientSpace = Workspace<Xid>;
 
+/// The directions in which a client is maximized.
+///
+/// Maximized clients are floated over the work area of their screen in the directions that
+/// they are maximized in, retaining their border (unlike fullscreen clients).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Maximized {
+    /// Whether or not the client fills the width of the work area
+    pub horizontal: bool,
+    /// Whether or not the client fills the height of the work area
+    pub vertical: bool,
+}
+
+impl Maximized {
+    /// Whether or not the client is maximized in either direction.
+    pub fn is_maximized(&self) -> bool {
+        self.horizontal || self.vertical
+    }
+}
+
+// The geometry most recently sent to the X server for a client, both before and after applying
+// size hints, along with any changes that the X server has not yet told us have been applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AppliedPosition {
+    pub(crate) r: Rect,
+    applied: Rect,
+    unconfirmed: Vec<Rect>,
+}
+
+impl AppliedPosition {
+    pub(crate) fn new(r: Rect, applied: Rect) -> Self {
+        Self {
+            r,
+            applied,
+            unconfirmed: vec![applied],
+        }
+    }
+
+    pub(crate) fn update(&mut self, r: Rect, applied: Rect) {
+        if applied != self.applied {
+            self.unconfirmed.push(applied);
+        }
+        self.r = r;
+        self.applied = applied;
+    }
+
+    // Returns false if the geometry reported by the X server was set by something other than
+    // the window manager.
+    pub(crate) fn confirm(&mut self, actual: Rect) -> bool {
+        match self.unconfirmed.iter().position(|&r| r == actual) {
+            Some(i) => {
+                self.unconfirmed.drain(..=i);
+                true
+            }
+            None => actual == self.applied,
+        }
+    }
+}
+
 /// Mutable internal state for the window manager
 #[derive(Debug)]
 pub struct State<X>
@@ -86,6 +180,21 @@ where
     pub(crate) diff: Diff<Xid>,
     pub(crate) running: bool,
     pub(crate) held_mouse_state: Option<MouseState>,
+    // Fullscreen clients along with their floating position (if any) before entering fullscreen
+    pub(crate) fullscreen: HashMap<Xid, Option<RelativeRect>>,
+    pub(crate) fake_fullscreen: HashSet<Xid>,
+    // Maximized clients along with their floating position (if any) before being maximized
+    pub(crate) maximized: HashMap<Xid, (Maximized, Option<RelativeRect>)>,
+    pub(crate) zoomed: Option<Xid>,
+    // Transient clients along with their parent and the tag the parent was last seen on
+    pub(crate) transients: HashMap<Xid, (Xid, String)>,
+    pub(crate) positioning_suspended: HashSet<Xid>,
+    // Clients that currently have their border removed due to smart borders
+    pub(crate) borderless: HashSet<Xid>,
+    // The geometry and stacking order most recently sent to the X server for visible clients
+    pub(crate) applied_positions: RefCell<HashMap<Xid, AppliedPosition>>,
+    pub(crate) applied_stack: RefCell<Vec<Xid>>,
+    pub(crate) metrics: Metrics,
 }
 
 impl<X> State<X>
@@ -99,6 +208,10 @@ where
             x.screen_details()?,
         )?;
 
+        if let Some(index) = x.primary_screen()? {
+            client_set.start_on_primary_screen(index);
+        }
+
         let ss = client_set.snapshot(vec![]);
         let diff = Diff::new(ss.clone(), ss);
 
@@ -113,6 +226,16 @@ where
             diff,
             running: false,
             held_mouse_state: None,
+            fullscreen: HashMap::new(),
+            fake_fullscreen: HashSet::new(),
+            maximized: HashMap::new(),
+            zoomed: None,
+            transients: HashMap::new(),
+            positioning_suspended: HashSet::new(),
+            borderless: HashSet::new(),
+            applied_positions: RefCell::new(HashMap::new()),
+            applied_stack: RefCell::new(Vec::new()),
+            metrics: Metrics::default(),
         })
     }
 
@@ -131,6 +254,200 @@ where
         self.current_event.as_ref()
     }
 
+    /// Timings recorded while running the main event loop.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Clear all timings recorded while running the main event loop.
+    pub fn reset_metrics(&mut self) {
+        self.metrics.reset();
+    }
+
+    /// Check whether or not the given client is currently in a fullscreen state.
+    pub fn is_fullscreen(&self, id: &Xid) -> bool {
+        self.fullscreen.contains_key(id)
+    }
+
+    /// The set of all clients currently in a fullscreen state.
+    pub fn fullscreen_clients(&self) -> impl Iterator<Item = &Xid> {
+        self.fullscreen.keys()
+    }
+
+    /// Check whether or not the given client is currently in a "fake" fullscreen state.
+    ///
+    /// Fake fullscreen clients believe that they are fullscreen but remain in their normal
+    /// tiled or floating position.
+    pub fn is_fake_fullscreen(&self, id: &Xid) -> bool {
+        self.fake_fullscreen.contains(id)
+    }
+
+    /// The directions in which the given client is currently maximized, if it is maximized.
+    pub fn maximized_state(&self, id: &Xid) -> Option<Maximized> {
+        self.maximized.get(id).map(|&(m, _)| m)
+    }
+
+    /// The tiled client currently zoomed to fill its workspace, if there is one.
+    pub fn zoomed_client(&self) -> Option<Xid> {
+        self.zoomed
+    }
+
+    /// Stop positioning and restacking the given client when refreshing the X state, leaving its
+    /// geometry and stacking order under the control of the client itself.
+    ///
+    /// The client remains managed (it can still be focused and moved between workspaces)
+    /// until positioning is resumed using [State::resume_positioning].
+    pub fn suspend_positioning(&mut self, id: Xid) {
+        self.positioning_suspended.insert(id);
+    }
+
+    /// Resume positioning a client that was previously passed to [State::suspend_positioning].
+    /// Returns `false` if positioning was not suspended for the client.
+    pub fn resume_positioning(&mut self, id: &Xid) -> bool {
+        self.positioning_suspended.remove(id)
+    }
+
+    /// Check whether or not positioning is currently suspended for the given client.
+    pub fn is_positioning_suspended(&self, id: &Xid) -> bool {
+        self.positioning_suspended.contains(id)
+    }
+
+    /// The position assigned to the given client by the most recent refresh, if it is visible.
+    ///
+    /// The returned [Rect] is the full area given to the client (including its border) by
+    /// the active layout or its floating position. Clients that have had their positioning
+    /// suspended are not included.
+    pub fn client_rect(&self, id: Xid) -> Option<Rect> {
+        self.client_rects().find(|&(c, _)| c == id).map(|(_, r)| r)
+    }
+
+    /// The positions assigned to all visible clients by the most recent refresh in stacking
+    /// order (from bottom to top). See [State::client_rect] for details.
+    pub fn client_rects(&self) -> impl Iterator<Item = (Xid, Rect)> + '_ {
+        self.diff
+            .after
+            .positions
+            .iter()
+            .filter(|(id, _)| !self.is_positioning_suspended(id))
+            .copied()
+    }
+
+    /// The topmost visible client whose position from the most recent refresh contains the
+    /// given point, if there is one.
+    ///
+    /// This uses the positions assigned to clients by penrose rather than querying the X
+    /// server, so it can be used for hit-testing (e.g. to find a drop target when dragging a
+    /// client) without a round trip. Clients that have had their positioning suspended are
+    /// not included.
+    pub fn client_at_point<P>(&self, p: P) -> Option<Xid>
+    where
+        P: Into<Point>,
+    {
+        let p = p.into();
+        let rects: Vec<(Xid, Rect)> = self.client_rects().collect();
+
+        rects
+            .into_iter()
+            .rev()
+            .find(|(_, r)| r.contains_point(p))
+            .map(|(id, _)| id)
+    }
+
+    /// Float the given client over the full area of the screen it is on, recording its
+    /// existing floating position (if any) so that it can be restored when the client
+    /// leaves fullscreen.
+    pub(crate) fn enter_fullscreen(&mut self, id: Xid) -> Result<()> {
+        let r = self
+            .client_set
+            .screen_for_client(&id)
+            .ok_or(Error::UnknownClient(id))?
+            .r;
+        let previous = self.client_set.floating.get(&id).copied();
+        self.client_set.float(id, r)?;
+        self.fullscreen.entry(id).or_insert(previous);
+
+        Ok(())
+    }
+
+    /// Restore the given client to the floating or tiled position it had before entering
+    /// fullscreen. Returns `false` if the client was not fullscreen.
+    pub(crate) fn exit_fullscreen(&mut self, id: Xid) -> bool {
+        match self.fullscreen.remove(&id) {
+            Some(Some(rr)) => {
+                self.client_set.floating.insert(id, rr);
+            }
+            Some(None) => {
+                self.client_set.sink(&id);
+            }
+            None => return false,
+        }
+
+        true
+    }
+
+    /// Maximize the given client in the requested directions, floating it over the work area of
+    /// the screen it is on. The existing floating position of the client (if any) is recorded
+    /// the first time it is maximized so that it can be restored when it is no longer maximized
+    /// in either direction.
+    ///
+    /// The work area of a screen is currently its full geometry.
+    pub(crate) fn set_maximized(&mut self, id: Xid, m: Maximized) -> Result<()> {
+        if !m.is_maximized() {
+            self.exit_maximized(id);
+            return Ok(());
+        }
+
+        let s = self
+            .client_set
+            .screen_for_client(&id)
+            .ok_or(Error::UnknownClient(id))?
+            .r;
+        let previous = match self.maximized.get(&id) {
+            Some(&(_, previous)) => previous,
+            None => self.client_set.floating.get(&id).copied(),
+        };
+
+        // Directions that are not maximized keep the original position of the client
+        let mut r = match previous {
+            Some(rr) => rr.applied_to(&s),
+            None => self
+                .diff
+                .after
+                .positions
+                .iter()
+                .find(|&&(c, _)| c == id)
+                .map(|&(_, r)| r)
+                .unwrap_or(s),
+        };
+        if m.horizontal {
+            (r.x, r.w) = (s.x, s.w);
+        }
+        if m.vertical {
+            (r.y, r.h) = (s.y, s.h);
+        }
+
+        self.client_set.float(id, r)?;
+        self.maximized.insert(id, (m, previous));
+
+        Ok(())
+    }
+
+    /// Restore the given client to the floating or tiled position it had before being
+    /// maximized. Returns `false` if the client was not maximized.
+    pub(crate) fn exit_maximized(&mut self, id: Xid) -> bool {
+        match self.maximized.remove(&id) {
+            Some((_, Some(rr))) => {
+                self.client_set.floating.insert(id, rr);
+            }
+            Some((_, None)) => {
+                self.client_set.sink(&id);
+            }
+            None => return false,
+        }
+
+        true
+    }
+
     /// Get access to a shared state extension.
     ///
     /// To add an extension to [State] before starting the Window Manager, see the
@@ -195,6 +512,7 @@ where
 
         // pop the layout hook off of `state` so that we can pass state into it
         let mut hook = self.config.layout_hook.take();
+        let policy = self.config.panic_policy;
 
         let scs: Vec<ScreenClients> = self
             .client_set
@@ -217,17 +535,35 @@ where
             }
 
             // Next run layout functions for each workspace on a visible screen
-            let stack_positions = match hook {
+            let res = match hook {
                 Some(ref mut h) => {
-                    let r_s = h.transform_initial_for_screen(i, r_s, self, x);
-                    let s = self.client_set.screens.iter_mut().nth(i).unwrap();
-                    let (_, initial) = s.workspace.layouts.layout_workspace(&tag, &tiling, r_s);
-                    h.transform_positions_for_screen(i, r_s, initial, self, x)
+                    let start = Instant::now();
+                    let res = catch_panic(policy, "layout hook", || {
+                        Ok(h.transform_initial_for_screen(i, r_s, self, x))
+                    })
+                    .and_then(|r_s| {
+                        let initial = self.layout_screen(i, &tag, &tiling, r_s);
+                        catch_panic(policy, "layout hook", || {
+                            Ok(h.transform_positions_for_screen(i, r_s, initial, self, x))
+                        })
+                    });
+                    self.metrics.record_hook(HookKind::Layout, start);
+
+                    res
                 }
-                None => {
-                    let s = self.client_set.screens.iter_mut().nth(i).unwrap();
-                    let (_, positions) = s.workspace.layouts.layout_workspace(&tag, &tiling, r_s);
-                    positions
+                None => Ok(self.layout_screen(i, &tag, &tiling, r_s)),
+            };
+
+            let stack_positions = match res {
+                Ok(positions) => positions,
+                Err(e) => {
+                    error!(%e, "error running user layout hook");
+                    self.run_error_hook(&e, x);
+                    if policy == PanicPolicy::Disable {
+                        error!("disabling user layout hook");
+                        hook = None;
+                    }
+                    self.layout_screen(i, &tag, &tiling, r_s)
                 }
             };
 
@@ -242,6 +578,180 @@ where
 
         positions
     }
+
+    /// Run a user provided binding or hook that has access to the [State] using [catch_panic].
+    ///
+    /// If `restore` is true and `f` panics, the [ClientSet] is restored to how it was before `f`
+    /// was called and the X server is refreshed to match, rather than leaving it partially
+    /// modified. Restoring requires a copy of the [ClientSet] to be taken before running `f` so
+    /// it should only be requested for bindings and hooks that are expected to modify it. No copy
+    /// is taken if panics are being propagated.
+    pub(crate) fn catch_panic<T>(
+        &mut self,
+        what: &str,
+        x: &X,
+        restore: bool,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let policy = self.config.panic_policy;
+        let saved = if restore && policy != PanicPolicy::Propagate {
+            let start = Instant::now();
+            let client_set = self.client_set.clone();
+            self.metrics.record_client_set_copy(start);
+            Some(client_set)
+        } else {
+            None
+        };
+
+        let res = catch_panic(policy, what, || f(self));
+
+        if let (Err(Error::Panicked { .. }), Some(client_set)) = (&res, saved) {
+            warn!(%what, "restoring client set after panic");
+            self.client_set = client_set;
+            if let Err(e) = x.refresh(self) {
+                error!(%e, "unable to refresh after restoring client set");
+            }
+        }
+
+        res
+    }
+
+    /// Pass an error returned from a key binding, mouse binding or hook to the user error hook
+    /// (if one is set).
+    pub(crate) fn run_error_hook(&mut self, error: &Error, x: &X) {
+        let policy = self.config.panic_policy;
+        if let Some(mut h) = self.config.error_hook.take() {
+            trace!("running user error hook");
+            let start = Instant::now();
+            let res = catch_panic(policy, "error hook", || h.call(error, self, x));
+            self.metrics.record_hook(HookKind::Error, start);
+            match res {
+                Err(e @ Error::Panicked { .. }) if policy == PanicPolicy::Disable => {
+                    error!(%e, "disabling user error hook");
+                    return;
+                }
+                Err(e) => error!(%e, "error returned from user error hook"),
+                Ok(()) => (),
+            }
+            self.config.error_hook = Some(h);
+        }
+    }
+
+    // Run the active layout for the workspace on screen i. If the zoomed client is being
+    // laid out then it is positioned as if it were the only client on the workspace and
+    // stacked above the other tiled clients.
+    fn layout_screen(
+        &mut self,
+        i: usize,
+        tag: &str,
+        tiling: &Option<Stack<Xid>>,
+        r: Rect,
+    ) -> Vec<(Xid, Rect)> {
+        let zoomed = self.zoomed;
+        let policy = self.config.panic_policy;
+        let s = self.client_set.screens.iter_mut().nth(i).unwrap();
+        let res = catch_panic(policy, "layout", || {
+            Ok(s.workspace.layouts.layout_workspace(tag, tiling, r))
+        });
+
+        // Layouts can't be disabled so we leave the clients where they were before
+        let mut positions = match res {
+            Ok((_, positions)) => positions,
+            Err(e) => {
+                error!(%e, %tag, "error running layout: keeping current positions");
+                let applied = self.applied_positions.borrow();
+                return tiling
+                    .iter()
+                    .flat_map(|st| st.iter())
+                    .flat_map(|c| applied.get(c).map(|p| (*c, p.r)))
+                    .collect();
+            }
+        };
+
+        if let Some(id) = zoomed {
+            if let Some(ix) = positions.iter().position(|&(c, _)| c == id) {
+                let mut l = s.workspace.layouts.focus.boxed_clone();
+                let (_, single) = l.layout(&stack!(id), r);
+                positions.remove(ix);
+                if let Some(&(_, r_zoomed)) = single.first() {
+                    positions.insert(0, (id, r_zoomed));
+                }
+            }
+        }
+
+        positions
+    }
+}
+
+#[cfg(test)]
+impl<X> State<X>
+where
+    X: XConn,
+{
+    /// Construct a default State wrapping the given [ClientSet] for use in tests.
+    pub(crate) fn new_with_client_set(client_set: ClientSet) -> Self {
+        Self {
+            config: Default::default(),
+            client_set,
+            extensions: AnyMap::new(),
+            root: Xid(0),
+            mapped: Default::default(),
+            pending_unmap: Default::default(),
+            current_event: None,
+            diff: Default::default(),
+            running: false,
+            held_mouse_state: None,
+            fullscreen: Default::default(),
+            fake_fullscreen: Default::default(),
+            maximized: Default::default(),
+            zoomed: None,
+            transients: Default::default(),
+            positioning_suspended: Default::default(),
+            borderless: Default::default(),
+            applied_positions: Default::default(),
+            applied_stack: Default::default(),
+            metrics: Default::default(),
+        }
+    }
+}
+
+/// The width and colors used when drawing client window borders.
+///
+/// See [Config::border_style] and [Config::set_border_style].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BorderStyle {
+    /// The width of the border in pixels
+    pub width: u32,
+    /// The color of the border for the focused client
+    pub focused: Color,
+    /// The color of the border for unfocused clients
+    pub normal: Color,
+    /// The color of the border for unfocused clients that are requesting attention
+    pub urgent: Color,
+}
+
+/// What to do when a user provided hook, layout or binding panics.
+///
+/// By default a panic is logged and the hook or binding that raised it is disabled so that a
+/// single misbehaving extension is not able to bring down the window manager (and with it the
+/// rest of the X session). Layouts can not be disabled: if a layout panics then the tiled
+/// clients on that screen are left in their current positions.
+///
+/// Unless the panic is propagated, any changes made to the [ClientSet] by a binding or hook that
+/// panics are rolled back so that it is never left in a partially modified state. The exception
+/// to this is the event hook when it is handling a [MotionNotify][XEvent::MotionNotify] event:
+/// these are received too frequently to copy the [ClientSet] each time.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PanicPolicy {
+    /// Log the panic and disable the hook or binding that raised it
+    #[default]
+    Disable,
+    /// Log the panic and continue running the hook or binding as normal
+    LogAndContinue,
+    /// Continue unwinding, exiting the window manager
+    Propagate,
 }
 
 /// The user specified config options for how the window manager should run
@@ -253,18 +763,72 @@ where
     pub normal_border: Color,
     /// The RGBA color to use for the focused window border
     pub focused_border: Color,
+    /// The RGBA color to use for the border of unfocused windows that are requesting attention
+    pub urgent_border: Color,
     /// The width in pixels to use for drawing window borders
     pub border_width: u32,
+    /// Whether or not to remove the border from a tiled client when it is the only tiled client
+    /// visible on its screen
+    pub smart_borders: bool,
     /// Whether or not the mouse entering a new window should set focus
     pub focus_follow_mouse: bool,
+    /// Whether or not clicking on an unfocused window should set focus.
+    ///
+    /// Enabling this grabs mouse button presses on every unfocused client so that the click can
+    /// be intercepted before it is passed on (or not) to the client.
+    pub click_to_focus: bool,
+    /// Whether or not clicking on an unfocused floating window should also raise it above other
+    /// floating windows (requires `click_to_focus`)
+    pub raise_floating_on_click: bool,
+    /// Whether or not a click used to focus a window should also be passed through to that
+    /// window (requires `click_to_focus`)
+    pub pass_through_focus_click: bool,
+    /// Whether or not a fullscreen client should leave fullscreen when focus moves to another
+    /// client on the same workspace
+    pub exit_fullscreen_on_focus_change: bool,
+    /// The [Position] within the stack of its workspace that a newly managed client is inserted
+    /// at.
+    ///
+    /// New clients are always given focus on their workspace. This can be overridden for
+    /// specific clients using the [InsertAt][crate::extensions::hooks::manage::InsertAt] manage
+    /// hook.
+    pub insert_point: Position,
+    /// Whether or not newly managed clients should be prevented from taking focus away from the
+    /// currently focused client.
+    ///
+    /// When enabled, a new client is only focused if it matches one of the
+    /// [allow_focus_rules][Config::allow_focus_rules], is a transient of the focused client or
+    /// has a `_NET_WM_USER_TIME` that is not older than that of the focused client. Clients
+    /// matching any of the [deny_focus_rules][Config::deny_focus_rules] or that set a user time
+    /// of 0 are never focused. Clients that are prevented from taking focus are marked as
+    /// urgent instead.
+    pub prevent_focus_stealing: bool,
+    /// Rules for identifying new clients that are always allowed to take focus when
+    /// [prevent_focus_stealing][Config::prevent_focus_stealing] is enabled
+    pub allow_focus_rules: Vec<Box<dyn Query<X>>>,
+    /// Rules for identifying new clients that are never allowed to take focus when
+    /// [prevent_focus_stealing][Config::prevent_focus_stealing] is enabled
+    pub deny_focus_rules: Vec<Box<dyn Query<X>>>,
     /// The stack of layouts to use for each workspace
     pub default_layouts: LayoutStack,
     /// The ordered set of workspace tags to use on window manager startup
     pub tags: Vec<String>,
     /// Window classes that should always be assigned floating positions rather than tiled
     pub floating_classes: Vec<String>,
+    /// Rules for identifying newly managed windows that should be assigned floating positions.
+    ///
+    /// A window is floated if any of the rules match. By default this floats transient windows,
+    /// windows with a [floating window type][AUTO_FLOAT_WINDOW_TYPES] (such as dialogs, splash
+    /// screens and utility windows) and windows with a fixed size. Rules can be combined using
+    /// the methods provided by [Query] in order to customise this behaviour.
+    pub float_rules: Vec<Box<dyn Query<X>>>,
+    /// How to handle panics raised by user provided hooks, layouts and bindings
+    pub panic_policy: PanicPolicy,
     /// A [StateHook] to run before entering the main event loop
     pub startup_hook: Option<Box<dyn StateHook<X>>>,
+    /// A [StateHook] to run when exiting the main event loop, either because the window manager
+    /// was told to exit or because the connection to the X server was lost
+    pub shutdown_hook: Option<Box<dyn StateHook<X>>>,
     /// A [StateHook] to run before processing each [XEvent]
     pub event_hook: Option<Box<dyn EventHook<X>>>,
     /// A [ManageHook] to run after each new window becomes managed by the window manager
@@ -273,6 +837,10 @@ where
     pub refresh_hook: Option<Box<dyn StateHook<X>>>,
     /// A [LayoutHook] to run when positioning clients on the screen
     pub layout_hook: Option<Box<dyn LayoutHook<X>>>,
+    /// A [ChildExitHook] to run each time a child process of the window manager exits
+    pub child_exit_hook: Option<Box<dyn ChildExitHook<X>>>,
+    /// An [ErrorHook] to run each time a key binding, mouse binding or hook returns an error
+    pub error_hook: Option<Box<dyn ErrorHook<X>>>,
 }
 
 impl<X> fmt::Debug for Config<X>
@@ -283,11 +851,26 @@ where
         f.debug_struct("Config")
             .field("normal_border", &self.normal_border)
             .field("focused_border", &self.focused_border)
+            .field("urgent_border", &self.urgent_border)
             .field("border_width", &self.border_width)
+            .field("smart_borders", &self.smart_borders)
             .field("focus_follow_mouse", &self.focus_follow_mouse)
+            .field("click_to_focus", &self.click_to_focus)
+            .field("raise_floating_on_click", &self.raise_floating_on_click)
+            .field("pass_through_focus_click", &self.pass_through_focus_click)
+            .field(
+                "exit_fullscreen_on_focus_change",
+                &self.exit_fullscreen_on_focus_change,
+            )
+            .field("insert_point", &self.insert_point)
+            .field("prevent_focus_stealing", &self.prevent_focus_stealing)
+            .field("allow_focus_rules", &self.allow_focus_rules)
+            .field("deny_focus_rules", &self.deny_focus_rules)
             .field("default_layouts", &self.default_layouts)
             .field("tags", &self.tags)
             .field("floating_classes", &self.floating_classes)
+            .field("float_rules", &self.float_rules)
+            .field("panic_policy", &self.panic_policy)
             .finish()
     }
 }
@@ -302,16 +885,35 @@ where
         Config {
             normal_border: "#3c3836ff".try_into().expect("valid hex code"),
             focused_border: "#cc241dff".try_into().expect("valid hex code"),
+            urgent_border: "#d79921ff".try_into().expect("valid hex code"),
             border_width: 2,
+            smart_borders: false,
             focus_follow_mouse: true,
+            click_to_focus: false,
+            raise_floating_on_click: false,
+            pass_through_focus_click: false,
+            exit_fullscreen_on_focus_change: false,
+            insert_point: Position::Focus,
+            prevent_focus_stealing: false,
+            allow_focus_rules: Vec::new(),
+            deny_focus_rules: Vec::new(),
             default_layouts: LayoutStack::default(),
             tags: strings(&["1", "2", "3", "4", "5", "6", "7", "8", "9"]),
             floating_classes: strings(&["dmenu", "dunst"]),
+            float_rules: vec![
+                Box::new(IsTransient),
+                Box::new(WindowType(AUTO_FLOAT_WINDOW_TYPES)),
+                Box::new(FixedSize),
+            ],
+            panic_policy: PanicPolicy::default(),
             startup_hook: None,
+            shutdown_hook: None,
             event_hook: None,
             manage_hook: None,
             refresh_hook: None,
             layout_hook: None,
+            child_exit_hook: None,
+            error_hook: None,
         }
     }
 }
@@ -320,6 +922,35 @@ impl<X> Config<X>
 where
     X: XConn,
 {
+    /// Create a [ConfigBuilder][config::ConfigBuilder] starting from the default config that
+    /// validates the options it is given when it is built.
+    pub fn builder() -> config::ConfigBuilder<X> {
+        config::ConfigBuilder::default()
+    }
+
+    /// The current width and colors used for client window borders.
+    pub fn border_style(&self) -> BorderStyle {
+        BorderStyle {
+            width: self.border_width,
+            focused: self.focused_border,
+            normal: self.normal_border,
+            urgent: self.urgent_border,
+        }
+    }
+
+    /// Update the width and colors used for client window borders.
+    ///
+    /// This only updates the config: to apply the new style to clients that are already being
+    /// managed see [XConnExt::update_borders] or the [set_border_style][0] action.
+    ///
+    ///   [0]: crate::builtin::actions::set_border_style
+    pub fn set_border_style(&mut self, style: BorderStyle) {
+        self.border_width = style.width;
+        self.focused_border = style.focused;
+        self.normal_border = style.normal;
+        self.urgent_border = style.urgent;
+    }
+
     /// Set the startup_hook or compose it with what is already set.
     ///
     /// The new hook will run before what was there before.
@@ -334,6 +965,20 @@ where
         };
     }
 
+    /// Set the shutdown_hook or compose it with what is already set.
+    ///
+    /// The new hook will run before what was there before.
+    pub fn compose_or_set_shutdown_hook<H>(&mut self, hook: H)
+    where
+        H: StateHook<X> + 'static,
+        X: 'static,
+    {
+        self.shutdown_hook = match self.shutdown_hook.take() {
+            Some(h) => Some(hook.then_boxed(h)),
+            None => Some(hook.boxed()),
+        };
+    }
+
     /// Set the event_hook or compose it with what is already set.
     ///
     /// The new hook will run before what was there before.
@@ -389,6 +1034,34 @@ where
             None => Some(hook.boxed()),
         };
     }
+
+    /// Set the child_exit_hook or compose it with what is already set.
+    ///
+    /// The new hook will run before what was there before.
+    pub fn compose_or_set_child_exit_hook<H>(&mut self, hook: H)
+    where
+        H: ChildExitHook<X> + 'static,
+        X: 'static,
+    {
+        self.child_exit_hook = match self.child_exit_hook.take() {
+            Some(h) => Some(hook.then_boxed(h)),
+            None => Some(hook.boxed()),
+        };
+    }
+
+    /// Set the error_hook or compose it with what is already set.
+    ///
+    /// The new hook will run before what was there before.
+    pub fn compose_or_set_error_hook<H>(&mut self, hook: H)
+    where
+        H: ErrorHook<X> + 'static,
+        X: 'static,
+    {
+        self.error_hook = match self.error_hook.take() {
+            Some(h) => Some(hook.then_boxed(h)),
+            None => Some(hook.boxed()),
+        };
+    }
 }
 
 /// A top level struct holding all of the state required to run as an X11 window manager.
@@ -436,12 +1109,22 @@ where
         self.state.add_extension(extension);
     }
 
+    /// Generate a [StateSnapshot][introspection::StateSnapshot] summarising the current state
+    /// of this WindowManager.
+    pub fn state_snapshot(&self) -> Result<introspection::StateSnapshot> {
+        self.state.state_snapshot(&self.x)
+    }
+
     /// Start the WindowManager and run it until told to exit.
     ///
     /// Any provided startup hooks will be run after setting signal handlers and grabbing
     /// key / mouse bindings from the X server. Any set up you need to do should be run
     /// explicitly before calling this method or as part of a startup hook.
     ///
+    /// If the [XConn] being used provides a [connection_fd][XConn::connection_fd] then this is
+    /// the same as running with an empty [EventLoop], so that exited child processes are reaped
+    /// as soon as they exit rather than when the next event arrives from the X server.
+    ///
     /// ## Existing clients
     /// An attempt will be made to pull any existing clients already present into the current
     /// WindowManager state. This is done on a "best effort" basis to manage existing clients on
@@ -456,49 +1139,220 @@ where
     /// > clients that were on invisible workspaces / workspaces that no longer exist and that the
     /// > workspace containing the previously active client will be placed on the first available
     /// > screen.
+    ///
+    /// ## Losing the connection to the X server
+    /// Penrose does not attempt to reconnect to the X server: if the connection is lost then the
+    /// main event loop exits, any shutdown hooks are run and an [Error::ConnectionLost] is
+    /// returned.
     pub fn run(mut self) -> Result<()> {
+        if self.x.connection_fd().is_some() {
+            return self.run_with_event_loop(EventLoop::new());
+        }
+
         info!("registering SIGCHILD signal handler");
+        let action = SigAction::new(
+            SigHandler::Handler(handle_sigchld),
+            SaFlags::SA_RESTART | SaFlags::SA_NOCLDSTOP,
+            SigSet::empty(),
+        );
         // SAFETY: there is no previous signal handler so we are safe to set our own without needing
-        //         to worry about UB from the previous handler being invalid.
-        if let Err(e) = unsafe { signal(Signal::SIGCHLD, SigHandler::SigIgn) } {
+        //         to worry about UB from the previous handler being invalid. The handler itself
+        //         only stores to an atomic which is async-signal-safe.
+        if let Err(e) = unsafe { sigaction(Signal::SIGCHLD, &action) } {
             panic!("unable to set signal handler: {}", e);
         }
 
-        handle::mapping_notify(&self.key_bindings, &self.mouse_bindings, &self.x)?;
+        self.start()?;
+        let res = self.event_loop();
+        self.shutdown();
 
-        if let Some(mut h) = self.state.config.startup_hook.take() {
-            trace!("running user startup hook");
-            if let Err(e) = h.call(&mut self.state, &self.x) {
-                error!(%e, "error returned from user startup hook");
-            }
+        res
+    }
+
+    /// Start the WindowManager and run it until told to exit, polling the X connection alongside
+    /// the timers, signals and file descriptors registered with the given [EventLoop].
+    ///
+    /// Startup and handling of existing clients is the same as for [WindowManager::run]. See the
+    /// [event_loop] module docs for details.
+    ///
+    /// # Errors
+    /// Returns an error if the [XConn] being used does not provide a
+    /// [connection_fd][XConn::connection_fd] that can be polled for new events.
+    pub fn run_with_event_loop(mut self, mut event_loop: EventLoop<X>) -> Result<()> {
+        if self.x.connection_fd().is_none() {
+            return Err(Error::Custom(
+                "the XConn being used does not provide a connection fd to poll".to_owned(),
+            ));
         }
 
-        manage_existing_clients(&mut self.state, &self.x)?;
-        self.state.running = true;
+        info!("registering signal handlers");
+        event_loop.install_signal_handlers()?;
+
+        self.start()?;
+        let res = self.poll_loop(&mut event_loop);
+        self.shutdown();
+
+        res
+    }
 
+    // Process events until the window manager is told to exit or the connection to the X server
+    // is lost.
+    fn event_loop(&mut self) -> Result<()> {
         while self.state.running {
             match self.x.next_event() {
                 Ok(event) => {
-                    let span = span!(target: "penrose", Level::INFO, "XEvent", %event);
-                    let _enter = span.enter();
-                    trace!(details = ?event, "event details");
-                    self.state.current_event = Some(event.clone());
-
-                    if let Err(e) = self.handle_xevent(event) {
-                        error!(%e, "Error handling XEvent");
+                    self.process_event(event);
+                    if CHILD_EXITED.swap(false, Ordering::SeqCst) {
+                        self.reap_children();
                     }
-                    self.x.flush();
+                }
+
+                Err(e) if e.is_connection_error() => return Err(connection_lost(e)),
 
-                    self.state.current_event = None;
+                Err(e) => self.handle_error(e),
+            }
+        }
+
+        Ok(())
+    }
+
+    // Process events from the X server and the sources registered with the given EventLoop
+    // until the window manager is told to exit or the connection to the X server is lost.
+    fn poll_loop(&mut self, event_loop: &mut EventLoop<X>) -> Result<()> {
+        while self.state.running {
+            // Events may already have been read from the connection so we need to process
+            // everything that is pending before polling for new ones.
+            match self.x.poll_next_event() {
+                Ok(Some(event)) => {
+                    self.process_event(event);
+                    continue;
                 }
 
+                Ok(None) => (),
+
+                Err(e) if e.is_connection_error() => return Err(connection_lost(e)),
+
                 Err(e) => self.handle_error(e),
             }
+
+            self.x.flush();
+            let ready = event_loop.wait(self.x.connection_fd())?;
+            if event_loop.dispatch(&ready, &mut self.state, &self.x) {
+                self.reap_children();
+            }
+            self.x.flush();
+        }
+
+        Ok(())
+    }
+
+    fn shutdown(&mut self) {
+        self.state.running = false;
+
+        let policy = self.state.config.panic_policy;
+        if let Some(mut h) = self.state.config.shutdown_hook.take() {
+            trace!("running user shutdown hook");
+            let start = Instant::now();
+            let res = catch_panic(policy, "shutdown hook", || h.call(&mut self.state, &self.x));
+            self.state.metrics.record_hook(HookKind::Shutdown, start);
+            match res {
+                Err(e @ Error::Panicked { .. }) if policy == PanicPolicy::Disable => {
+                    error!(%e, "disabling user shutdown hook");
+                    self.state.run_error_hook(&e, &self.x);
+                    return;
+                }
+                Err(e) => {
+                    error!(%e, "error returned from user shutdown hook");
+                    self.state.run_error_hook(&e, &self.x);
+                }
+                Ok(()) => (),
+            }
+            self.state.config.shutdown_hook = Some(h);
+        }
+    }
+
+    // Reap any child processes that have exited so that they do not accumulate as zombies,
+    // passing the exit status of those spawned by penrose to the user child_exit_hook (if one is
+    // set). While penrose is waiting on a child process itself only the children it spawned are
+    // reaped so that the exit status of the child being waited on is not lost.
+    fn reap_children(&mut self) {
+        let exited = if waiting_for_children() {
+            reap_spawned_children()
+        } else {
+            reap_all_children()
+        };
+
+        for (pid, status) in exited {
+            debug!(%pid, ?status, "reaped child process");
+
+            let policy = self.state.config.panic_policy;
+            if let Some(mut h) = self.state.config.child_exit_hook.take() {
+                trace!("running user child exit hook");
+                let start = Instant::now();
+                let res = catch_panic(policy, "child exit hook", || {
+                    h.call(pid, status, &mut self.state, &self.x)
+                });
+                self.state.metrics.record_hook(HookKind::ChildExit, start);
+                match res {
+                    Err(e @ Error::Panicked { .. }) if policy == PanicPolicy::Disable => {
+                        error!(%e, "disabling user child exit hook");
+                        self.state.run_error_hook(&e, &self.x);
+                        continue;
+                    }
+                    Err(e) => {
+                        error!(%e, "error returned from user child exit hook");
+                        self.state.run_error_hook(&e, &self.x);
+                    }
+                    Ok(()) => (),
+                }
+                self.state.config.child_exit_hook = Some(h);
+            }
+        }
+    }
+
+    // Grab bindings, run startup hooks and manage any existing clients ahead of processing
+    // events from the X server.
+    fn start(&mut self) -> Result<()> {
+        handle::mapping_notify(&self.key_bindings, &self.mouse_bindings, &self.x)?;
+
+        if let Some(mut h) = self.state.config.startup_hook.take() {
+            trace!("running user startup hook");
+            let start = Instant::now();
+            let res = self
+                .state
+                .catch_panic("startup hook", &self.x, true, |state| {
+                    h.call(state, &self.x)
+                });
+            if let Err(e) = res {
+                error!(%e, "error returned from user startup hook");
+                self.state.run_error_hook(&e, &self.x);
+            }
+            self.state.metrics.record_hook(HookKind::Startup, start);
         }
 
+        manage_existing_clients(&mut self.state, &self.x)?;
+        self.state.running = true;
+
         Ok(())
     }
 
+    fn process_event(&mut self, event: XEvent) {
+        let span = span!(target: "penrose", Level::INFO, "XEvent", %event);
+        let _enter = span.enter();
+        trace!(details = ?event, "event details");
+        self.state.current_event = Some(event.clone());
+
+        let name = event.to_string();
+        let start = Instant::now();
+        if let Err(e) = self.handle_xevent(event) {
+            error!(%e, "Error handling XEvent");
+        }
+        self.state.metrics.record_event(name, start);
+        self.x.flush();
+
+        self.state.current_event = None;
+    }
+
     fn handle_xevent(&mut self, event: XEvent) -> Result<()> {
         use XEvent::*;
 
@@ -509,14 +1363,29 @@ where
             mouse_bindings,
         } = self;
 
+        let policy = state.config.panic_policy;
         let mut hook = state.config.event_hook.take();
         let should_run = match hook {
             Some(ref mut h) => {
                 trace!("running user event hook");
-                match h.call(&event, state, x) {
+                let start = Instant::now();
+                // Motion events arrive far too often to copy the client set for each of them
+                let restore = !matches!(event, MotionNotify(_));
+                let res =
+                    state.catch_panic("event hook", x, restore, |state| h.call(&event, state, x));
+                state.metrics.record_hook(HookKind::Event, start);
+
+                match res {
                     Ok(should_run) => should_run,
+                    Err(e @ Error::Panicked { .. }) if policy == PanicPolicy::Disable => {
+                        error!(%e, "disabling user event hook");
+                        state.run_error_hook(&e, x);
+                        hook = None;
+                        true
+                    }
                     Err(e) => {
                         error!(%e, "error returned from user event hook");
+                        state.run_error_hook(&e, x);
                         true
                     }
                 }
@@ -534,7 +1403,7 @@ where
         match &event {
             ClientMessage(m) => handle::client_message(m.clone(), state, x)?,
             ConfigureNotify(e) if e.is_root => handle::detect_screens(state, x)?,
-            ConfigureNotify(_) => (), // Not currently handled
+            ConfigureNotify(e) => handle::configure_notify(e, state),
             ConfigureRequest(e) => handle::configure_request(e, state, x)?,
             Enter(p) => handle::enter(*p, state, x)?,
             Expose(_) => (), // Not currently handled
@@ -544,12 +1413,17 @@ where
             Leave(p) => handle::leave(*p, state, x)?,
             MappingNotify => handle::mapping_notify(key_bindings, mouse_bindings, x)?,
             MapRequest(xid) => handle::map_request(*xid, state, x)?,
+            MapNotify(_) => (), // Not currently handled
             MouseEvent(e) => handle::mouse_event(e.clone(), mouse_bindings, state, x)?,
             MotionNotify(e) => handle::motion_event(e.clone(), mouse_bindings, state, x)?,
-            PropertyNotify(_) => (), // Not currently handled
+            PropertyNotify(e) => handle::property_notify(e, state, x)?,
             RandrNotify => handle::detect_screens(state, x)?,
             ScreenChange => handle::screen_change(state, x)?,
             UnmapNotify(xid) => handle::unmap_notify(*xid, state, x)?,
+            WmReplaced => {
+                info!("another window manager has taken over: exiting");
+                state.running = false;
+            }
 
             _ => (), // XEvent is non-exhaustive
         }
@@ -571,6 +1445,37 @@ where
     }
 }
 
+impl WindowManager<TestConn> {
+    /// Run any startup hooks and manage existing clients without entering the main event loop.
+    ///
+    /// Queued events can then be processed using [WindowManager::run_pending_events].
+    pub fn start_headless(&mut self) -> Result<()> {
+        self.start()
+    }
+
+    /// Stop the window manager and run any shutdown hooks, as if the main event loop had exited.
+    pub fn shutdown_headless(&mut self) {
+        self.shutdown()
+    }
+
+    /// Process all events currently queued on the underlying [TestConn], including any that are
+    /// generated while doing so, returning once the queue is empty or the window manager has
+    /// been told to exit.
+    pub fn run_pending_events(&mut self) {
+        while self.state.running {
+            match self.x.pop_event() {
+                Some(event) => self.process_event(event),
+                None => break,
+            }
+        }
+    }
+
+    /// A reference to the underlying [TestConn] for inspecting the simulated X server state.
+    pub fn conn(&self) -> &TestConn {
+        &self.x
+    }
+}
+
 // A "best effort" attempt to manage existing clients on the workspaces they were present
 // on previously. This is not guaranteed to preserve the stack order or correctly handle
 // any clients that were on invisible workspaces / workspaces that no longer exist.
@@ -664,10 +1569,103 @@ fn client_should_be_manged<X: XConn>(id: Xid, x: &X) -> bool {
     !override_redirect && (viewable || iconic)
 }
 
+// Reap the exited children that were spawned by penrose without waiting for them to exit.
+fn reap_spawned_children() -> Vec<(u32, ChildExitStatus)> {
+    let mut exited = Vec::new();
+    untrack_children(|pid| {
+        let status = match waitpid(Pid::from_raw(pid as i32), Some(WaitPidFlag::WNOHANG)) {
+            Ok(WaitStatus::Exited(_, code)) => ChildExitStatus::Exited(code),
+            Ok(WaitStatus::Signaled(_, sig, _)) => ChildExitStatus::Signaled(sig as i32),
+            Err(Errno::ECHILD) => return true,
+            Ok(_) => return false,
+            Err(e) => {
+                error!(%e, %pid, "unable to reap child process");
+                return false;
+            }
+        };
+        exited.push((pid, status));
+
+        true
+    });
+
+    exited
+}
+
+// Reap every exited child, returning the exit status of those that were spawned by penrose.
+fn reap_all_children() -> Vec<(u32, ChildExitStatus)> {
+    let mut exited = Vec::new();
+
+    loop {
+        let (pid, status) = match waitpid(None, Some(WaitPidFlag::WNOHANG)) {
+            Ok(WaitStatus::Exited(pid, code)) => (pid, ChildExitStatus::Exited(code)),
+            Ok(WaitStatus::Signaled(pid, sig, _)) => (pid, ChildExitStatus::Signaled(sig as i32)),
+            Ok(_) | Err(Errno::ECHILD) => break,
+            Err(e) => {
+                error!(%e, "unable to reap child processes");
+                break;
+            }
+        };
+
+        let pid = pid.as_raw() as u32;
+        if untrack_children(|p| p == pid).is_empty() {
+            debug!(%pid, ?status, "reaped child process not spawned by penrose");
+        } else {
+            exited.push((pid, status));
+        }
+    }
+
+    exited
+}
+
+// Connection errors from the X server are not recoverable as the connection is not re-established
+fn connection_lost(e: Error) -> Error {
+    match e {
+        Error::ConnectionLost(_) => e,
+        e => {
+            error!(%e, "connection to the X server lost: exiting");
+            Error::ConnectionLost(e.to_string())
+        }
+    }
+}
+
+/// Run a user provided hook, layout or binding, converting any panic it raises into an
+/// [Error::Panicked] unless the [PanicPolicy] is to propagate it.
+pub(crate) fn catch_panic<T>(
+    policy: PanicPolicy,
+    what: &str,
+    f: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(res) => res,
+        Err(payload) if policy == PanicPolicy::Propagate => panic::resume_unwind(payload),
+        Err(payload) => {
+            let message = match payload.downcast::<String>() {
+                Ok(s) => *s,
+                Err(payload) => match payload.downcast::<&str>() {
+                    Ok(s) => s.to_string(),
+                    Err(_) => "unknown panic payload".to_owned(),
+                },
+            };
+
+            Err(Error::Panicked {
+                what: what.to_owned(),
+                message,
+            })
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::pure::{test_xid_stack_set, Position};
+    use crate::{
+        core::bindings::MotionNotifyEvent,
+        pure::test_xid_stack_set,
+        util::{SpawnSpec, WaitingForChild},
+        x::StubXConn,
+    };
+    use simple_test_case::test_case;
+    use std::{cell::Cell, rc::Rc, thread, time::Duration};
 
     fn stack_order(cs: &ClientSet) -> Vec<u32> {
         let positions = cs.visible_client_positions();
@@ -772,4 +1770,470 @@ mod tests {
             assert_eq!(stack_order(&s), expected, "{:?}", s.current_stack());
         }
     }
+
+    #[test_case(None; "tiled")]
+    #[test_case(Some(Rect::new(10, 10, 20, 20)); "floating")]
+    #[test]
+    fn exit_fullscreen_restores_previous_position(floating: Option<Rect>) {
+        let mut cs = test_xid_stack_set(5, 2);
+        for n in 0..4 {
+            cs.insert(Xid(n));
+        }
+        if let Some(r) = floating {
+            cs.float_unchecked(Xid(2), r);
+        }
+        let original = cs.floating.get(&Xid(2)).copied();
+
+        let mut s: State<StubXConn> = State::new_with_client_set(cs);
+        s.enter_fullscreen(Xid(2)).unwrap();
+
+        assert!(s.is_fullscreen(&Xid(2)));
+        assert!(s.client_set.is_floating(&Xid(2)));
+
+        assert!(s.exit_fullscreen(Xid(2)));
+
+        assert!(!s.is_fullscreen(&Xid(2)));
+        assert_eq!(s.client_set.floating.get(&Xid(2)).copied(), original);
+    }
+
+    #[test]
+    fn entering_fullscreen_twice_retains_original_position() {
+        let mut cs = test_xid_stack_set(5, 2);
+        for n in 0..4 {
+            cs.insert(Xid(n));
+        }
+
+        let mut s: State<StubXConn> = State::new_with_client_set(cs);
+        s.enter_fullscreen(Xid(2)).unwrap();
+        s.enter_fullscreen(Xid(2)).unwrap();
+        s.exit_fullscreen(Xid(2));
+
+        assert!(!s.client_set.is_floating(&Xid(2)));
+    }
+
+    #[test_case(None; "tiled")]
+    #[test_case(Some(Rect::new(10, 10, 20, 20)); "floating")]
+    #[test]
+    fn unmaximizing_restores_previous_position(floating: Option<Rect>) {
+        let mut cs = test_xid_stack_set(5, 2);
+        for n in 0..4 {
+            cs.insert(Xid(n));
+        }
+        if let Some(r) = floating {
+            cs.float_unchecked(Xid(2), r);
+        }
+        let original = cs.floating.get(&Xid(2)).copied();
+
+        let mut s: State<StubXConn> = State::new_with_client_set(cs);
+        let both = Maximized {
+            horizontal: true,
+            vertical: true,
+        };
+        s.set_maximized(Xid(2), both).unwrap();
+
+        assert_eq!(s.maximized_state(&Xid(2)), Some(both));
+        assert!(s.client_set.is_floating(&Xid(2)));
+
+        s.set_maximized(Xid(2), Maximized::default()).unwrap();
+
+        assert_eq!(s.maximized_state(&Xid(2)), None);
+        assert_eq!(s.client_set.floating.get(&Xid(2)).copied(), original);
+    }
+
+    #[test]
+    fn maximizing_in_one_direction_keeps_the_other() {
+        let mut cs = test_xid_stack_set(5, 2);
+        for n in 0..4 {
+            cs.insert(Xid(n));
+        }
+        let screen = cs.screens.focus.r;
+        let r = Rect::new(screen.x + 10, screen.y + 20, 30, 40);
+        cs.float_unchecked(Xid(2), r);
+
+        let mut s: State<StubXConn> = State::new_with_client_set(cs);
+        let horizontal = Maximized {
+            horizontal: true,
+            vertical: false,
+        };
+        s.set_maximized(Xid(2), horizontal).unwrap();
+
+        let rr = s.client_set.floating[&Xid(2)].applied_to(&screen);
+        assert_eq!((rr.x, rr.w), (screen.x, screen.w));
+        assert_eq!((rr.y, rr.h), (r.y, r.h));
+    }
+
+    #[test]
+    fn exit_fullscreen_for_unknown_client_is_false() {
+        let mut s: State<StubXConn> = State::new_with_client_set(test_xid_stack_set(5, 2));
+
+        assert!(!s.exit_fullscreen(Xid(42)));
+    }
+
+    #[test]
+    fn zoomed_client_fills_the_screen_and_is_stacked_on_top() {
+        let mut cs = test_xid_stack_set(5, 2);
+        for n in 0..4 {
+            cs.insert(Xid(n));
+        }
+        let r_screen = cs.screens.focus.r;
+
+        let mut s: State<StubXConn> = State::new_with_client_set(cs);
+        s.zoomed = Some(Xid(2));
+        let positions = s.visible_client_positions(&StubXConn);
+
+        assert_eq!(positions.last(), Some(&(Xid(2), r_screen)));
+        assert_eq!(positions.len(), 4);
+    }
+
+    #[test]
+    fn zoom_is_ignored_for_clients_that_are_not_tiled() {
+        let mut cs = test_xid_stack_set(5, 2);
+        for n in 0..4 {
+            cs.insert(Xid(n));
+        }
+        let expected = cs.visible_client_positions();
+
+        let mut s: State<StubXConn> = State::new_with_client_set(cs);
+        s.zoomed = Some(Xid(42));
+
+        assert_eq!(s.visible_client_positions(&StubXConn), expected);
+    }
+
+    fn shutdown_counting_wm(calls: Rc<Cell<usize>>) -> WindowManager<TestConn> {
+        let mut config = Config::default();
+        config.compose_or_set_shutdown_hook(move |_: &mut State<TestConn>, _: &TestConn| {
+            calls.set(calls.get() + 1);
+            Ok(())
+        });
+        let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+        let mut wm = WindowManager::new(config, HashMap::new(), HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+
+        wm
+    }
+
+    #[test]
+    fn losing_the_connection_exits_the_event_loop_with_an_error() {
+        let calls = Rc::new(Cell::new(0));
+        let mut wm = shutdown_counting_wm(calls.clone());
+
+        let id = wm.conn().spawn_client("st");
+        wm.conn().disconnect();
+        let res = wm.event_loop();
+        wm.shutdown();
+
+        assert!(matches!(res, Err(Error::ConnectionLost(_))));
+        assert!(wm.state.client_set.contains(&id));
+        assert!(!wm.state.running);
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn exiting_the_event_loop_runs_the_shutdown_hook() {
+        let calls = Rc::new(Cell::new(0));
+        let mut wm = shutdown_counting_wm(calls.clone());
+
+        wm.state.running = false;
+        let res = wm.event_loop();
+        wm.shutdown();
+
+        assert!(res.is_ok());
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn event_handling_and_hooks_are_timed() {
+        let mut config = Config::default();
+        config.compose_or_set_manage_hook(|_: Xid, _: &mut State<TestConn>, _: &TestConn| Ok(()));
+        let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+        let mut wm = WindowManager::new(config, HashMap::new(), HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+
+        wm.conn().spawn_client("st");
+        wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        let metrics = wm.state.metrics();
+        assert_eq!(metrics.event("MapRequest").map(|t| t.count), Some(2));
+        assert_eq!(metrics.hook(HookKind::Manage).map(|t| t.count), Some(2));
+        assert_eq!(metrics.hook(HookKind::Refresh), None);
+        assert!(metrics.refreshes().count >= 2);
+
+        wm.state.reset_metrics();
+        assert_eq!(wm.state.metrics(), &Metrics::default());
+    }
+
+    #[test]
+    fn being_replaced_exits_the_event_loop_and_runs_the_shutdown_hook() {
+        let calls = Rc::new(Cell::new(0));
+        let mut wm = shutdown_counting_wm(calls.clone());
+
+        wm.conn().push_event(XEvent::WmReplaced);
+        let res = wm.event_loop();
+        wm.shutdown();
+
+        assert!(res.is_ok());
+        assert!(!wm.state.running);
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn exited_children_are_reaped_and_passed_to_the_child_exit_hook() {
+        let exited = Rc::new(RefCell::new(Vec::new()));
+        let mut config = Config::default();
+        let e = exited.clone();
+        config.compose_or_set_child_exit_hook(
+            move |pid: u32, status: ChildExitStatus, _: &mut State<TestConn>, _: &TestConn| {
+                e.borrow_mut().push((pid, status));
+                Ok(())
+            },
+        );
+        let mut wm =
+            WindowManager::new(config, HashMap::new(), HashMap::new(), TestConn::new()).unwrap();
+
+        // Waiting on a child also stops other tests reaping the child spawned below
+        let waiting = WaitingForChild::new();
+        let pid = SpawnSpec::new("sh")
+            .with_args(["-c", "exit 3"])
+            .spawn_for_pid()
+            .unwrap();
+        let mut other = std::process::Command::new("sh")
+            .args(["-c", "exit 4"])
+            .spawn()
+            .unwrap();
+
+        for _ in 0..100 {
+            wm.reap_children();
+            if exited.borrow().iter().any(|&(p, _)| p == pid) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(exited.borrow().contains(&(pid, ChildExitStatus::Exited(3))));
+        assert!(wm.state.metrics().hook(HookKind::ChildExit).is_some());
+
+        // Children that are being waited on are left for whatever is waiting on them
+        assert_eq!(other.wait().unwrap().code(), Some(4));
+        drop(waiting);
+    }
+
+    #[test]
+    fn exited_children_that_are_not_being_waited_on_are_reaped() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            HashMap::new(),
+            HashMap::new(),
+            TestConn::new(),
+        )
+        .unwrap();
+
+        let pid = std::process::Command::new("sh")
+            .args(["-c", "exit 5"])
+            .spawn()
+            .unwrap()
+            .id();
+        // Zombie processes remain visible in /proc until they have been reaped
+        let proc_dir = format!("/proc/{pid}");
+
+        let mut reaped = false;
+        for _ in 0..500 {
+            wm.reap_children();
+            if !std::path::Path::new(&proc_dir).exists() {
+                reaped = true;
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(reaped, "child process was not reaped");
+    }
+
+    const PANIC: bindings::KeyCode = bindings::KeyCode { mask: 0, code: 10 };
+
+    #[test_case(PanicPolicy::Disable, 1; "disable")]
+    #[test_case(PanicPolicy::LogAndContinue, 2; "log and continue")]
+    #[test]
+    fn panicking_key_bindings_are_handled_using_the_panic_policy(
+        policy: PanicPolicy,
+        expected: usize,
+    ) {
+        let calls = Rc::new(Cell::new(0));
+        let c = calls.clone();
+        let mut bindings: KeyBindings<TestConn> = HashMap::new();
+        bindings.insert(
+            PANIC,
+            crate::builtin::actions::key_handler(move |_: &mut State<TestConn>, _: &TestConn| {
+                c.set(c.get() + 1);
+                panic!("boom")
+            }),
+        );
+        let config = Config {
+            panic_policy: policy,
+            ..Config::default()
+        };
+        let mut wm = WindowManager::new(config, bindings, HashMap::new(), TestConn::new()).unwrap();
+        wm.start_headless().unwrap();
+
+        for _ in 0..2 {
+            wm.conn().press_key(PANIC);
+            wm.run_pending_events();
+        }
+
+        assert_eq!(calls.get(), expected);
+        assert_eq!(wm.key_bindings.contains_key(&PANIC), expected == 2);
+    }
+
+    #[test]
+    fn client_set_is_restored_when_a_key_binding_panics() {
+        let mut bindings: KeyBindings<TestConn> = HashMap::new();
+        bindings.insert(
+            PANIC,
+            crate::builtin::actions::key_handler(|state: &mut State<TestConn>, x: &TestConn| {
+                x.modify_and_refresh(state, |cs| {
+                    cs.focus_tag("2");
+                    panic!("boom")
+                })
+            }),
+        );
+        let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+        let mut wm = WindowManager::new(Config::default(), bindings, HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+
+        let id = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+        wm.conn().press_key(PANIC);
+        wm.run_pending_events();
+
+        assert_eq!(wm.state.client_set.current_tag(), "1");
+        assert_eq!(wm.state.client_set.current_client(), Some(&id));
+    }
+
+    #[test]
+    fn motion_events_do_not_copy_the_client_set_for_the_event_hook() {
+        let mut config = Config::default();
+        config.compose_or_set_event_hook(|_: &XEvent, _: &mut State<TestConn>, _: &TestConn| {
+            Ok(true)
+        });
+        let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+        let mut wm = WindowManager::new(config, HashMap::new(), HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+        wm.state.reset_metrics();
+
+        for x in 0..10 {
+            let e = MotionNotifyEvent::new(Xid(0), x, 10, x, 10, vec![]);
+            wm.conn().push_event(XEvent::MotionNotify(e));
+        }
+        wm.run_pending_events();
+
+        assert_eq!(
+            wm.state.metrics().hook(HookKind::Event).map(|t| t.count),
+            Some(10)
+        );
+        assert_eq!(wm.state.metrics().client_set_copies().count, 0);
+
+        wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        assert!(wm.state.metrics().client_set_copies().count > 0);
+    }
+
+    #[test]
+    fn panicking_hooks_are_disabled_without_stopping_the_window_manager() {
+        let mut config = Config::default();
+        config.compose_or_set_manage_hook(|_: Xid, _: &mut State<TestConn>, _: &TestConn| {
+            panic!("boom")
+        });
+        let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+        let mut wm = WindowManager::new(config, HashMap::new(), HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+
+        let id = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        assert!(wm.state.client_set.contains(&id));
+        assert!(wm.state.config.manage_hook.is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "boom")]
+    fn panics_are_propagated_when_requested() {
+        let mut config = Config {
+            panic_policy: PanicPolicy::Propagate,
+            ..Config::default()
+        };
+        config.compose_or_set_startup_hook(|_: &mut State<TestConn>, _: &TestConn| panic!("boom"));
+        let mut wm =
+            WindowManager::new(config, HashMap::new(), HashMap::new(), TestConn::new()).unwrap();
+
+        _ = wm.start_headless();
+    }
+
+    #[test]
+    fn errors_from_key_bindings_and_hooks_are_passed_to_the_error_hook() {
+        let errors = Rc::new(RefCell::new(Vec::new()));
+        let e = errors.clone();
+        let mut config = Config::default();
+        config.compose_or_set_error_hook(
+            move |error: &Error, _: &mut State<TestConn>, _: &TestConn| {
+                e.borrow_mut().push(error.to_string());
+                Ok(())
+            },
+        );
+        config.compose_or_set_manage_hook(|_: Xid, _: &mut State<TestConn>, _: &TestConn| {
+            Err(Error::Custom("manage".to_owned()))
+        });
+        let mut bindings: KeyBindings<TestConn> = HashMap::new();
+        bindings.insert(
+            PANIC,
+            crate::builtin::actions::key_handler(|_: &mut State<TestConn>, _: &TestConn| {
+                Err(Error::Custom("key binding".to_owned()))
+            }),
+        );
+        let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+        let mut wm = WindowManager::new(config, bindings, HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+
+        wm.conn().spawn_client("st");
+        wm.conn().press_key(PANIC);
+        wm.run_pending_events();
+
+        assert_eq!(*errors.borrow(), vec!["manage", "key binding"]);
+        assert_eq!(
+            wm.state.metrics().hook(HookKind::Error).map(|t| t.count),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn the_poll_loop_runs_timers_alongside_x_events() {
+        let calls = Rc::new(Cell::new(0));
+        let c = calls.clone();
+        let mut bindings: KeyBindings<TestConn> = HashMap::new();
+        bindings.insert(
+            PANIC,
+            crate::builtin::actions::key_handler(move |_: &mut State<TestConn>, _: &TestConn| {
+                c.set(c.get() + 1);
+                Ok(())
+            }),
+        );
+        let mut event_loop = EventLoop::new().with_timer(
+            Duration::from_millis(10),
+            |state: &mut State<TestConn>, _: &TestConn| {
+                state.running = false;
+                Ok(())
+            },
+        );
+        let mut wm =
+            WindowManager::new(Config::default(), bindings, HashMap::new(), TestConn::new())
+                .unwrap();
+        wm.start_headless().unwrap();
+
+        wm.conn().press_key(PANIC);
+        let res = wm.poll_loop(&mut event_loop);
+
+        assert!(res.is_ok());
+        assert!(!wm.state.running);
+        assert_eq!(calls.get(), 1);
+    }
 }
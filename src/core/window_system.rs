@@ -0,0 +1,188 @@
+//! An experimental, reduced interface to the underlying window system.
+//!
+//! [XConn] covers the full set of X11 requests made by penrose. [WindowSystem] is the much
+//! smaller subset needed to display a [StackSet]: querying the available outputs, discovering,
+//! mapping, positioning and focusing windows, and receiving input. It exists as a boundary for
+//! experimenting with display servers other than X (such as a Wayland compositor) while reusing
+//! the pure [StackSet] and [Layout] engine.
+//!
+//! Every [XConn] implementation is a [WindowSystem], so [render] can be used to check that an
+//! alternative backend lays out a [StackSet] in the same way as the X11 backend does. The
+//! methods of [WindowSystem] are named so that they do not clash with those of [XConn] when both
+//! traits are in scope.
+//!
+//! > **NOTE**: this interface is not used by the [WindowManager][crate::core::WindowManager]
+//! > itself and there is currently no backend other than X: a Wayland backend (for example one
+//! > built on smithay) has not been implemented yet. Expect breaking changes as other backends
+//! > are explored.
+use crate::{
+    core::{
+        bindings::{KeyCode, MouseEvent},
+        layout::Layout,
+    },
+    pure::{geometry::Rect, ScreenClients, StackSet},
+    x::{ClientConfig, XConn, XEvent},
+    Result, Xid,
+};
+
+/// The input events that a [WindowSystem] needs to report in order to drive a [StackSet].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputEvent {
+    /// A bound key combination was pressed
+    KeyPress(KeyCode),
+    /// A bound mouse button was pressed or released
+    Mouse(MouseEvent),
+    /// A new window would like to be displayed
+    NewWindow(Xid),
+    /// A window has been closed
+    WindowClosed(Xid),
+    /// The available outputs have changed
+    OutputsChanged,
+    /// An event that is not needed to drive a [StackSet]
+    Other,
+}
+
+/// A reduced interface to a window system that is sufficient for displaying a [StackSet].
+///
+/// See the module level docs for details.
+pub trait WindowSystem {
+    /// The dimensions of each currently available output.
+    fn outputs(&self) -> Result<Vec<Rect>>;
+    /// The windows that already exist and should be managed.
+    fn existing_windows(&self) -> Result<Vec<Xid>>;
+    /// Show the given window.
+    fn show_window(&self, id: Xid) -> Result<()>;
+    /// Hide the given window.
+    fn hide_window(&self, id: Xid) -> Result<()>;
+    /// Move and resize the given window.
+    fn position_window(&self, id: Xid, r: Rect) -> Result<()>;
+    /// Give input focus to the given window.
+    fn focus_window(&self, id: Xid) -> Result<()>;
+    /// Block until the next [InputEvent] is available.
+    fn next_input(&self) -> Result<InputEvent>;
+}
+
+impl<X: XConn> WindowSystem for X {
+    fn outputs(&self) -> Result<Vec<Rect>> {
+        self.screen_details()
+    }
+
+    fn existing_windows(&self) -> Result<Vec<Xid>> {
+        self.existing_clients()
+    }
+
+    fn show_window(&self, id: Xid) -> Result<()> {
+        self.map(id)
+    }
+
+    fn hide_window(&self, id: Xid) -> Result<()> {
+        self.unmap(id)
+    }
+
+    fn position_window(&self, id: Xid, r: Rect) -> Result<()> {
+        self.set_client_config(id, &[ClientConfig::Position(r)])
+    }
+
+    fn focus_window(&self, id: Xid) -> Result<()> {
+        self.focus(id)
+    }
+
+    fn next_input(&self) -> Result<InputEvent> {
+        let evt = match self.next_event()? {
+            XEvent::KeyPress(k) => InputEvent::KeyPress(k),
+            XEvent::MouseEvent(e) => InputEvent::Mouse(e),
+            XEvent::MapRequest(id) => InputEvent::NewWindow(id),
+            XEvent::Destroy(id) => InputEvent::WindowClosed(id),
+            XEvent::RandrNotify => InputEvent::OutputsChanged,
+            _ => InputEvent::Other,
+        };
+
+        Ok(evt)
+    }
+}
+
+/// Lay out the visible workspaces of a [StackSet] using their active layouts, displaying them
+/// with the given [WindowSystem].
+///
+/// Clients on hidden workspaces are unmapped and the focused client (if there is one) is given
+/// input focus.
+pub fn render<W: WindowSystem>(cs: &mut StackSet<Xid>, w: &W) -> Result<()> {
+    let StackSet {
+        screens, floating, ..
+    } = cs;
+    let mut positions = Vec::new();
+
+    for s in screens.iter_mut() {
+        let ScreenClients {
+            floating,
+            tiling,
+            tag,
+            r_s,
+        } = s.screen_clients(floating);
+
+        let (_, tiled) = s.workspace.layouts.layout_workspace(&tag, &tiling, r_s);
+        positions.extend(tiled);
+        positions.extend(floating.iter().map(|(id, r)| (*id, r.applied_to(&r_s))));
+    }
+
+    for (id, r) in positions {
+        w.position_window(id, r)?;
+        w.show_window(id)?;
+    }
+
+    for &id in cs.hidden_workspace_clients() {
+        w.hide_window(id)?;
+    }
+
+    if let Some(&id) = cs.current_client() {
+        w.focus_window(id)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::layout::LayoutStack, x::TestConn};
+
+    #[test]
+    fn render_displays_visible_clients_and_hides_the_rest() {
+        let conn = TestConn::new();
+        let (a, b, c) = (
+            conn.create_client("a"),
+            conn.create_client("b"),
+            conn.create_client("c"),
+        );
+        let mut cs =
+            StackSet::try_new(LayoutStack::default(), ["1", "2"], conn.outputs().unwrap()).unwrap();
+        cs.insert(a);
+        cs.insert(c);
+        cs.move_focused_to_tag("2");
+        cs.insert(b);
+
+        render(&mut cs, &conn).unwrap();
+
+        assert!(conn.is_mapped(a) && conn.is_mapped(b));
+        assert!(!conn.is_mapped(c));
+        assert_eq!(conn.focused_client(), Some(b));
+
+        let screen = conn.outputs().unwrap()[0];
+        let ra = conn.client_geometry(a).unwrap();
+        let rb = conn.client_geometry(b).unwrap();
+        assert!(screen.contains(&ra) && screen.contains(&rb));
+        assert_ne!(ra, rb);
+    }
+
+    #[test]
+    fn window_system_methods_do_not_clash_with_xconn() {
+        let conn = TestConn::new();
+        let id = conn.create_client("a");
+
+        conn.show_window(id).unwrap();
+        conn.unmap(id).unwrap();
+
+        assert!(!conn.is_mapped(id));
+    }
+}
@@ -13,7 +13,7 @@ use std::collections::HashMap;
 ///
 /// # Arguments
 /// * `config` users custom DMenuConfig, the dmenu instance that is launched will
-///    obey colorscheme, postion, custom font, custom prompt etc...
+///   obey colorscheme, postion, custom font, custom prompt etc...
 pub fn dmenu_focus_client<X: XConn>(mut config: DMenuConfig) -> Box<dyn KeyEventHandler<X>> {
     key_handler(move |state: &mut State<X>, x: &X| {
         let choices: HashMap<String, Xid> = state
@@ -51,7 +51,7 @@ pub fn dmenu_focus_client<X: XConn>(mut config: DMenuConfig) -> Box<dyn KeyEvent
 ///
 /// # Arguments
 /// * `config` users custom DMenuConfig, the dmenu instance that is launched will
-///    obey colorscheme, postion, custom font, custom prompt etc...
+///   obey colorscheme, postion, custom font, custom prompt etc...
 pub fn dmenu_focus_tag<X: XConn>(mut config: DMenuConfig) -> Box<dyn KeyEventHandler<X>> {
     key_handler(move |state: &mut State<X>, x: &X| {
         let choices = state.client_set.ordered_tags();
@@ -75,7 +75,7 @@ pub fn dmenu_focus_tag<X: XConn>(mut config: DMenuConfig) -> Box<dyn KeyEventHan
 ///
 /// # Arguments
 /// * `config` users custom DMenuConfig, the dmenu instance that is launched will
-///    obey colorscheme, postion, custom font, custom prompt etc...
+///   obey colorscheme, postion, custom font, custom prompt etc...
 pub fn launch_dmenu<X: XConn>(mut config: DMenuConfig) -> Box<dyn KeyEventHandler<X>> {
     key_handler(move |state, _| {
         let screen = state.client_set.current_screen().index();
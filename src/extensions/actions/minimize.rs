@@ -0,0 +1,299 @@
+//! Minimizing clients so that they are hidden without being closed.
+use crate::{
+    builtin::actions::key_handler,
+    core::{bindings::KeyEventHandler, State, WindowManager},
+    custom_error,
+    extensions::util::dmenu::{DMenu, DMenuConfig, MenuMatch},
+    x::{Atom, Prop, XConn, XConnExt},
+    Result, Xid,
+};
+use std::collections::HashMap;
+
+/// The tag of the invisible workspace used to hold minimized clients.
+pub const MINIMIZED_TAG: &str = "MIN";
+
+/// A state extension for minimizing clients.
+///
+/// Minimized clients are moved to an invisible workspace so that they remain managed but are
+/// removed from the layout of the workspace they were on and hidden from the screen. They are
+/// marked with the EWMH `_NET_WM_STATE_HIDDEN` state while minimized so that external taskbars
+/// can display them appropriately. When a client is restored it is returned to the workspace it
+/// was minimized from and focused.
+///
+/// ```no_run
+/// use penrose::{
+///     core::{bindings::KeyEventHandler, Config, WindowManager},
+///     extensions::actions::{minimize_focused, restore_last_minimized, MinimizedClients},
+///     x11rb::RustConn,
+/// };
+/// use std::collections::HashMap;
+///
+/// let mut raw_bindings: HashMap<String, Box<dyn KeyEventHandler<RustConn>>> = HashMap::new();
+/// raw_bindings.insert("M-m".to_owned(), minimize_focused());
+/// raw_bindings.insert("M-S-m".to_owned(), restore_last_minimized());
+///
+/// # let key_bindings = penrose::core::bindings::parse_keybindings_with_xmodmap(raw_bindings)?;
+/// let conn = RustConn::new()?;
+/// let wm = WindowManager::new(Config::default(), key_bindings, HashMap::new(), conn)?;
+/// let wm = MinimizedClients::default().add_to(wm);
+/// # Ok::<(), penrose::Error>(())
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MinimizedClients {
+    clients: Vec<(Xid, String)>,
+}
+
+impl MinimizedClients {
+    /// Add this [MinimizedClients] to the given [WindowManager] along with the invisible
+    /// workspace and refresh hook required to run it.
+    pub fn add_to<X>(self, mut wm: WindowManager<X>) -> WindowManager<X>
+    where
+        X: XConn + 'static,
+    {
+        wm.state
+            .client_set
+            .add_invisible_workspace(MINIMIZED_TAG)
+            .expect("minimized tag to be unique");
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_refresh_hook(refresh_hook);
+
+        wm
+    }
+
+    /// The currently minimized clients, ordered from least to most recently minimized.
+    pub fn clients(&self) -> impl Iterator<Item = Xid> + '_ {
+        self.clients.iter().map(|&(id, _)| id)
+    }
+
+    /// Whether or not the given client is currently minimized.
+    pub fn is_minimized(&self, client: &Xid) -> bool {
+        self.clients.iter().any(|(id, _)| id == client)
+    }
+}
+
+// Add or remove the EWMH hidden state in the _NET_WM_STATE property of a client.
+fn set_hidden_prop<X: XConn>(x: &X, id: Xid, hidden: bool) -> Result<()> {
+    let net_wm_state = Atom::NetWmState.as_ref();
+    let hidden_state = Atom::NetWmStateHidden.as_ref();
+
+    let mut wstate = match x.get_prop(id, net_wm_state) {
+        Ok(Some(Prop::Atom(atoms))) => atoms,
+        _ => vec![],
+    };
+    wstate.retain(|a| a != hidden_state);
+    if hidden {
+        wstate.push(hidden_state.to_owned());
+    }
+
+    x.set_prop(id, net_wm_state, Prop::Atom(wstate))
+}
+
+/// Minimize the given client, hiding it and removing it from the layout of its workspace.
+///
+/// This requires [MinimizedClients] to have been added to your window manager.
+pub fn minimize_client<X: XConn + 'static>(id: Xid, state: &mut State<X>, x: &X) -> Result<()> {
+    let tag = match state.client_set.tag_for_client(&id) {
+        Some(tag) if tag != MINIMIZED_TAG => tag.to_owned(),
+        _ => return Ok(()),
+    };
+
+    state
+        .extension::<MinimizedClients>()?
+        .borrow_mut()
+        .clients
+        .push((id, tag));
+    set_hidden_prop(x, id, true)?;
+
+    x.modify_and_refresh(state, |cs| cs.move_client_to_tag(&id, MINIMIZED_TAG))
+}
+
+/// Restore the given client if it is minimized, returning it to the workspace it was
+/// minimized from and focusing it.
+///
+/// If that workspace no longer exists then the client is restored to the current workspace.
+pub fn restore_client<X: XConn + 'static>(id: Xid, state: &mut State<X>, x: &X) -> Result<()> {
+    let s = state.extension::<MinimizedClients>()?;
+    let tag = {
+        let mut minimized = s.borrow_mut();
+        match minimized.clients.iter().position(|&(c, _)| c == id) {
+            Some(ix) => minimized.clients.remove(ix).1,
+            None => return Ok(()),
+        }
+    };
+    set_hidden_prop(x, id, false)?;
+
+    x.modify_and_refresh(state, |cs| {
+        if cs.contains_tag(&tag) {
+            cs.move_client_to_tag(&id, &tag);
+        } else {
+            cs.move_client_to_current_tag(&id);
+        }
+        cs.focus_client(&id);
+    })
+}
+
+/// Minimize the currently focused client.
+///
+/// See [MinimizedClients] for details.
+pub fn minimize_focused<X: XConn + 'static>() -> Box<dyn KeyEventHandler<X>> {
+    key_handler(
+        |state: &mut State<X>, x: &X| match state.client_set.current_client() {
+            Some(&id) => minimize_client(id, state, x),
+            None => Ok(()),
+        },
+    )
+}
+
+/// Restore the most recently minimized client.
+///
+/// See [MinimizedClients] for details.
+pub fn restore_last_minimized<X: XConn + 'static>() -> Box<dyn KeyEventHandler<X>> {
+    key_handler(|state: &mut State<X>, x: &X| {
+        let last = state
+            .extension::<MinimizedClients>()?
+            .borrow()
+            .clients()
+            .last();
+
+        match last {
+            Some(id) => restore_client(id, state, x),
+            None => Ok(()),
+        }
+    })
+}
+
+/// Use [DMenu] to select a minimized client to restore.
+///
+/// # Arguments
+/// * `config` users custom DMenuConfig, the dmenu instance that is launched will
+///   obey colorscheme, postion, custom font, custom prompt etc...
+pub fn dmenu_restore_minimized<X: XConn + 'static>(
+    mut config: DMenuConfig,
+) -> Box<dyn KeyEventHandler<X>> {
+    key_handler(move |state: &mut State<X>, x: &X| {
+        let choices: HashMap<String, Xid> = state
+            .extension::<MinimizedClients>()?
+            .borrow()
+            .clients
+            .iter()
+            .map(|&(id, ref tag)| {
+                let title = x.window_title(id).unwrap_or_else(|_| (*id).to_string());
+
+                (format!("{tag}: {title}"), id)
+            })
+            .collect();
+
+        if choices.is_empty() {
+            return Ok(());
+        }
+
+        let screen = state.client_set.current_screen().index();
+        if config.custom_prompt.is_none() {
+            config.custom_prompt = Some("Restore: ".to_owned());
+        }
+        let dmenu = DMenu::new(&config, screen);
+
+        if let MenuMatch::Line(_, s) = dmenu.build_menu(choices.keys().collect())? {
+            let &id = choices
+                .get(&s)
+                .ok_or_else(|| custom_error!("unexpected dmenu output: {}", s))?;
+
+            restore_client(id, state, x)?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Forget about minimized clients that have been closed or moved off of the minimized
+/// workspace by something other than [restore_client].
+pub fn refresh_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> Result<()> {
+    let s = state.extension::<MinimizedClients>()?;
+    let mut minimized = s.borrow_mut();
+    let cs = &state.client_set;
+
+    let (still_minimized, moved): (Vec<_>, Vec<_>) = minimized
+        .clients
+        .drain(..)
+        .filter(|(id, _)| cs.contains(id))
+        .partition(|(id, _)| cs.tag_for_client(id) == Some(MINIMIZED_TAG));
+    minimized.clients = still_minimized;
+
+    for (id, _) in moved {
+        set_hidden_prop(x, id, false)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::bindings::KeyCode, pure::geometry::Rect, x::TestConn};
+
+    const MINIMIZE: KeyCode = KeyCode { mask: 0, code: 10 };
+    const RESTORE: KeyCode = KeyCode { mask: 0, code: 11 };
+
+    fn wm() -> WindowManager<TestConn> {
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(MINIMIZE, minimize_focused());
+        bindings.insert(RESTORE, restore_last_minimized());
+
+        TestConn::new()
+            .with_screens(vec![Rect::new(0, 0, 1000, 800)])
+            .start_headless_wm(bindings, |wm| MinimizedClients::default().add_to(wm))
+    }
+
+    fn is_hidden(wm: &WindowManager<TestConn>, id: Xid) -> bool {
+        let hidden = Atom::NetWmStateHidden.as_ref().to_owned();
+
+        match wm.conn().get_prop(id, Atom::NetWmState.as_ref()).unwrap() {
+            Some(Prop::Atom(atoms)) => atoms.contains(&hidden),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn minimized_clients_are_hidden_and_restored_in_reverse_order() {
+        let mut wm = wm();
+        let first = wm.conn().spawn_client("st");
+        let second = wm.conn().spawn_client("st");
+        let third = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        wm.conn().press_key(MINIMIZE);
+        wm.conn().press_key(MINIMIZE);
+        wm.run_pending_events();
+
+        for id in [second, third] {
+            assert!(wm.state.client_set.contains(&id));
+            assert!(!wm.conn().is_mapped(id));
+            assert!(is_hidden(&wm, id));
+        }
+        assert_eq!(wm.state.client_set.current_client(), Some(&first));
+
+        wm.conn().press_key(RESTORE);
+        wm.run_pending_events();
+
+        assert!(wm.conn().is_mapped(second));
+        assert!(!is_hidden(&wm, second));
+        assert_eq!(wm.state.client_set.current_client(), Some(&second));
+        assert_eq!(wm.state.client_set.tag_for_client(&second), Some("1"));
+        assert!(!wm.conn().is_mapped(third));
+    }
+
+    #[test]
+    fn closed_clients_are_forgotten() {
+        let mut wm = wm();
+        let id = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        wm.conn().press_key(MINIMIZE);
+        wm.run_pending_events();
+        wm.conn().close_client(id);
+        wm.run_pending_events();
+
+        let s = wm.state.extension::<MinimizedClients>().unwrap();
+        assert_eq!(s.borrow().clients().count(), 0);
+    }
+}
@@ -3,17 +3,26 @@ use crate::{
     builtin::actions::{key_handler, modify_with},
     core::{bindings::KeyEventHandler, layout::LayoutStack, State},
     util::spawn,
-    x::{atom::Atom, property::Prop, ClientConfig, XConn, XConnExt},
-    Error, Result, Xid,
+    x::{
+        atom::Atom, property::Prop, set_fullscreen_prop, set_fullscreen_props, set_maximized_prop,
+        XConn, XConnExt,
+    },
+    Result, Xid,
 };
 use tracing::{debug, error};
 
 mod dynamic_select;
+mod minimize;
+mod tag_groups;
 
 #[doc(inline)]
 pub use dynamic_select::*;
+#[doc(inline)]
+pub use minimize::*;
+#[doc(inline)]
+pub use tag_groups::*;
 
-/// The possible valid actions to use when manipulating full screen state
+/// The possible valid actions to use when manipulating full screen (or maximized) state
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum FullScreenAction {
     /// Force the window out of fullscreen state
@@ -25,6 +34,13 @@ pub enum FullScreenAction {
 }
 
 /// Set the fullscreen state of a particular client
+///
+/// Fullscreen state is tracked per client by [State] so that the floating or tiled position
+/// held by a client before entering fullscreen is restored when it leaves fullscreen. See
+/// [Config::exit_fullscreen_on_focus_change][0] for leaving fullscreen automatically when
+/// another client is focused.
+///
+///   [0]: crate::core::Config::exit_fullscreen_on_focus_change
 pub fn set_fullscreen_state<X: XConn>(
     id: Xid,
     action: FullScreenAction,
@@ -36,31 +52,24 @@ pub fn set_fullscreen_state<X: XConn>(
     let net_wm_state = Atom::NetWmState.as_ref();
     let full_screen = x.intern_atom(Atom::NetWmStateFullscreen.as_ref())?;
 
-    let mut wstate = match x.get_prop(id, net_wm_state) {
+    let wstate = match x.get_prop(id, net_wm_state) {
         Ok(Some(Prop::Cardinal(vals))) => vals,
         _ => vec![],
     };
 
-    let currently_fullscreen = wstate.contains(&full_screen);
+    let currently_fullscreen = state.is_fullscreen(&id) || wstate.contains(&full_screen);
     debug!(%currently_fullscreen, ?action, %id, "setting fullscreen state");
 
     if action == Add || (action == Toggle && !currently_fullscreen) {
-        let r = state
-            .client_set
-            .screen_for_client(&id)
-            .ok_or_else(|| Error::UnknownClient(id))?
-            .r;
-        state.client_set.float(id, r)?;
-        wstate.push(*full_screen);
-        x.set_client_config(id, &[ClientConfig::BorderPx(0)])?; // remove borders
+        state.enter_fullscreen(id)?;
+        set_fullscreen_props(x, id, true, state.config.border_width)?;
     } else if currently_fullscreen && (action == Remove || action == Toggle) {
-        state.client_set.sink(&id);
-        wstate.retain(|&val| val != *full_screen);
-        // replace borders
-        x.set_client_config(id, &[ClientConfig::BorderPx(state.config.border_width)])?;
+        if !state.exit_fullscreen(id) {
+            state.client_set.sink(&id);
+        }
+        set_fullscreen_props(x, id, false, state.config.border_width)?;
     }
 
-    x.set_prop(id, net_wm_state, Prop::Cardinal(wstate))?;
     x.refresh(state)
 }
 
@@ -81,13 +90,136 @@ pub fn toggle_fullscreen<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
     })
 }
 
+/// Set the "fake" fullscreen state of a particular client.
+///
+/// A client in fake fullscreen has the EWMH fullscreen property set (so it believes that it is
+/// fullscreen and will typically hide any window decorations or browser chrome) but it remains in
+/// its normal tiled or floating position. While in fake fullscreen, any fullscreen requests made
+/// by the client itself only update the property rather than resizing the client. If the client
+/// is currently fullscreen it will first be restored to its previous position.
+pub fn set_fake_fullscreen_state<X: XConn>(
+    id: Xid,
+    action: FullScreenAction,
+    state: &mut State<X>,
+    x: &X,
+) -> Result<()> {
+    use FullScreenAction::*;
+
+    let currently_fake = state.is_fake_fullscreen(&id);
+    debug!(%currently_fake, ?action, %id, "setting fake fullscreen state");
+
+    if action == Add || (action == Toggle && !currently_fake) {
+        if state.exit_fullscreen(id) {
+            set_fullscreen_props(x, id, false, state.config.border_width)?;
+        }
+        state.fake_fullscreen.insert(id);
+        set_fullscreen_prop(x, id, true)?;
+    } else if currently_fake && (action == Remove || action == Toggle) {
+        state.fake_fullscreen.remove(&id);
+        set_fullscreen_prop(x, id, false)?;
+    }
+
+    x.refresh(state)
+}
+
+/// Toggle the "fake" fullscreen state of the currently focused window.
+///
+/// See [set_fake_fullscreen_state] for details.
+///
+/// **NOTE**: You will need to make use of [add_ewmh_hooks][0] for this action to
+///           work correctly.
+///
+///   [0]: crate::extensions::hooks::add_ewmh_hooks
+pub fn toggle_fake_fullscreen<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
+    key_handler(|state, x: &X| {
+        let id = match state.client_set.current_client() {
+            Some(&id) => id,
+            None => return Ok(()),
+        };
+
+        set_fake_fullscreen_state(id, FullScreenAction::Toggle, state, x)
+    })
+}
+
+/// The directions in which a client can be maximized.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MaximizeDirection {
+    /// Fill the width of the work area
+    Horizontal,
+    /// Fill the height of the work area
+    Vertical,
+    /// Fill the entire work area
+    Both,
+}
+
+/// Set the maximized state of a particular client in the given direction.
+///
+/// Maximized clients are floated over the work area of their screen but (unlike fullscreen
+/// clients) keep their border. The floating or tiled position held by the client before it was
+/// maximized is restored once it is no longer maximized in either direction. If the client is
+/// currently fullscreen it will first be restored to its previous position.
+pub fn set_maximized_state<X: XConn>(
+    id: Xid,
+    direction: MaximizeDirection,
+    action: FullScreenAction,
+    state: &mut State<X>,
+    x: &X,
+) -> Result<()> {
+    use FullScreenAction::*;
+
+    let current = state.maximized_state(&id).unwrap_or_default();
+    let (horizontal, vertical) = match direction {
+        MaximizeDirection::Horizontal => (true, false),
+        MaximizeDirection::Vertical => (false, true),
+        MaximizeDirection::Both => (true, true),
+    };
+    let currently_maximized =
+        (!horizontal || current.horizontal) && (!vertical || current.vertical);
+    let maximize = action == Add || (action == Toggle && !currently_maximized);
+    debug!(?current, ?direction, ?action, %id, "setting maximized state");
+
+    let mut m = current;
+    if horizontal {
+        m.horizontal = maximize;
+    }
+    if vertical {
+        m.vertical = maximize;
+    }
+    if m == current {
+        return Ok(());
+    }
+
+    if state.exit_fullscreen(id) {
+        set_fullscreen_props(x, id, false, state.config.border_width)?;
+    }
+    state.set_maximized(id, m)?;
+    set_maximized_prop(x, id, m)?;
+
+    x.refresh(state)
+}
+
+/// Toggle the maximized state of the currently focused window in the given direction.
+///
+/// See [set_maximized_state] for details.
+pub fn toggle_maximized<X: XConn>(direction: MaximizeDirection) -> Box<dyn KeyEventHandler<X>> {
+    key_handler(move |state, x: &X| {
+        let id = match state.client_set.current_client() {
+            Some(&id) => id,
+            None => return Ok(()),
+        };
+
+        set_maximized_state(id, direction, FullScreenAction::Toggle, state, x)
+    })
+}
+
 /// Jump to, or create a [Workspace][0].
 ///
 /// Call 'get_name' to obtain a Workspace name and check to see if there is currently a Workspace
 /// with that name being managed by the WindowManager. If there is no existing workspace with the
-/// given name, create it with the supplied available layouts. If a matching Workspace _does_
-/// already exist then simply switch focus to it. This action is most useful when combined with the
-/// DefaultWorkspace hook that allows for auto populating named Workspaces when first focusing them.
+/// given name, create it with the supplied available layouts and show it on the primary screen.
+/// If a matching Workspace _does_ already exist then simply switch focus to it. This action is
+/// most useful when combined with the DefaultWorkspace hook that allows for auto populating named
+/// Workspaces when first focusing them.
 ///
 /// > If you just want to dynamically select an existing workspace then you can use
 /// > [switch_to_workspace] to select from known workspace names.
@@ -104,7 +236,9 @@ where
         if let Some(name) = get_name() {
             // if this errors it's because the tag is already present in the stackset
             // so we can just focus it.
-            _ = cs.add_workspace(&name, layouts.clone());
+            if cs.add_workspace(&name, layouts.clone()).is_ok() {
+                cs.focus_screen(cs.primary_screen().index());
+            }
 
             cs.focus_tag(&name);
         }
@@ -0,0 +1,288 @@
+//! Grouping workspace tags into switchable sets.
+use crate::{
+    builtin::actions::key_handler,
+    core::{bindings::KeyEventHandler, State, WindowManager},
+    x::{XConn, XConnExt},
+    Error, Result,
+};
+
+/// A state extension for splitting workspaces into named groups of tags, only one of which is
+/// active at a time.
+///
+/// Key bindings can then refer to tags by their index within the active group rather than
+/// directly, so that a single set of bindings can be used to reach every workspace:
+///
+/// ```no_run
+/// use penrose::{
+///     core::{bindings::KeyEventHandler, Config, WindowManager},
+///     extensions::actions::{
+///         focus_tag_in_group, move_client_to_tag_in_group, next_tag_group, TagGroups,
+///     },
+///     x11rb::RustConn,
+/// };
+/// use std::collections::HashMap;
+///
+/// let mut raw_bindings: HashMap<String, Box<dyn KeyEventHandler<RustConn>>> = HashMap::new();
+/// raw_bindings.insert("M-Tab".to_owned(), next_tag_group());
+/// for ix in 0..5 {
+///     raw_bindings.insert(format!("M-{}", ix + 1), focus_tag_in_group(ix));
+///     raw_bindings.insert(format!("M-S-{}", ix + 1), move_client_to_tag_in_group(ix));
+/// }
+///
+/// let config = Config {
+///     tags: (1..=10).map(|n| n.to_string()).collect(),
+///     ..Config::default()
+/// };
+/// # let key_bindings = penrose::core::bindings::parse_keybindings_with_xmodmap(raw_bindings)?;
+/// let wm = WindowManager::new(config, key_bindings, HashMap::new(), RustConn::new()?)?;
+///
+/// let wm = TagGroups::new()
+///     .with_group("work", ["1", "2", "3", "4", "5"])
+///     .with_group("personal", ["6", "7", "8", "9", "10"])
+///     .add_to(wm)?;
+/// # Ok::<(), penrose::Error>(())
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TagGroups {
+    groups: Vec<TagGroup>,
+    active: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TagGroup {
+    name: String,
+    tags: Vec<String>,
+    last_focused: usize,
+}
+
+impl TagGroups {
+    /// Create a new [TagGroups] with no groups.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a named group containing the given tags.
+    ///
+    /// The first group added is active when the window manager starts.
+    pub fn with_group<I, S>(mut self, name: impl Into<String>, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.groups.push(TagGroup {
+            name: name.into(),
+            tags: tags.into_iter().map(Into::into).collect(),
+            last_focused: 0,
+        });
+
+        self
+    }
+
+    /// Add this [TagGroups] to the given [WindowManager].
+    ///
+    /// Returns an error if any of the tags in a group are not known to the window manager.
+    pub fn add_to<X>(self, mut wm: WindowManager<X>) -> Result<WindowManager<X>>
+    where
+        X: XConn + 'static,
+    {
+        for tag in self.groups.iter().flat_map(|g| g.tags.iter()) {
+            if !wm.state.client_set.contains_tag(tag) {
+                return Err(Error::UnknownTag { tag: tag.clone() });
+            }
+        }
+
+        wm.state.add_extension(self);
+
+        Ok(wm)
+    }
+
+    /// The name of the currently active group.
+    pub fn active_group(&self) -> Option<&str> {
+        self.groups.get(self.active).map(|g| g.name.as_str())
+    }
+
+    /// The tags belonging to the currently active group.
+    pub fn active_tags(&self) -> &[String] {
+        self.groups
+            .get(self.active)
+            .map(|g| g.tags.as_slice())
+            .unwrap_or_default()
+    }
+
+    fn tag_in_group(&mut self, ix: usize) -> Option<String> {
+        let group = self.groups.get_mut(self.active)?;
+        let tag = group.tags.get(ix)?.clone();
+        group.last_focused = ix;
+
+        Some(tag)
+    }
+
+    fn set_active(&mut self, ix: usize) -> Option<String> {
+        self.active = ix;
+        let group = self.groups.get(ix)?;
+
+        group.tags.get(group.last_focused).cloned()
+    }
+}
+
+/// Focus the tag at index `ix` within the active [TagGroups] group.
+pub fn focus_tag_in_group<X: XConn + 'static>(ix: usize) -> Box<dyn KeyEventHandler<X>> {
+    key_handler(move |state: &mut State<X>, x: &X| {
+        let tag = state
+            .extension::<TagGroups>()?
+            .borrow_mut()
+            .tag_in_group(ix);
+        match tag {
+            Some(tag) => x.modify_and_refresh(state, |cs| cs.focus_tag(&tag)),
+            None => Ok(()),
+        }
+    })
+}
+
+/// Move the focused client to the tag at index `ix` within the active [TagGroups] group.
+pub fn move_client_to_tag_in_group<X: XConn + 'static>(ix: usize) -> Box<dyn KeyEventHandler<X>> {
+    key_handler(move |state: &mut State<X>, x: &X| {
+        let s = state.extension::<TagGroups>()?;
+        let tag = s.borrow().active_tags().get(ix).cloned();
+        match tag {
+            Some(tag) => x.modify_and_refresh(state, |cs| cs.move_focused_to_tag(&tag)),
+            None => Ok(()),
+        }
+    })
+}
+
+/// Make the named [TagGroups] group active, focusing the tag that was last focused within it.
+pub fn set_tag_group<X: XConn + 'static>(name: impl Into<String>) -> Box<dyn KeyEventHandler<X>> {
+    let name = name.into();
+
+    key_handler(move |state: &mut State<X>, x: &X| {
+        let s = state.extension::<TagGroups>()?;
+        let mut groups = s.borrow_mut();
+        let tag = match groups.groups.iter().position(|g| g.name == name) {
+            Some(ix) => groups.set_active(ix),
+            None => return Ok(()),
+        };
+        drop(groups);
+
+        focus_if_some(tag, state, x)
+    })
+}
+
+/// Make the next [TagGroups] group active, focusing the tag that was last focused within it.
+pub fn next_tag_group<X: XConn + 'static>() -> Box<dyn KeyEventHandler<X>> {
+    cycle_tag_group(1)
+}
+
+/// Make the previous [TagGroups] group active, focusing the tag that was last focused within it.
+pub fn previous_tag_group<X: XConn + 'static>() -> Box<dyn KeyEventHandler<X>> {
+    cycle_tag_group(-1)
+}
+
+fn cycle_tag_group<X: XConn + 'static>(step: isize) -> Box<dyn KeyEventHandler<X>> {
+    key_handler(move |state: &mut State<X>, x: &X| {
+        let s = state.extension::<TagGroups>()?;
+        let mut groups = s.borrow_mut();
+        let n = groups.groups.len() as isize;
+        if n == 0 {
+            return Ok(());
+        }
+        let ix = (groups.active as isize + step).rem_euclid(n) as usize;
+        let tag = groups.set_active(ix);
+        drop(groups);
+
+        focus_if_some(tag, state, x)
+    })
+}
+
+fn focus_if_some<X: XConn>(tag: Option<String>, state: &mut State<X>, x: &X) -> Result<()> {
+    match tag {
+        Some(tag) => x.modify_and_refresh(state, |cs| cs.focus_tag(&tag)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{bindings::KeyCode, Config},
+        x::TestConn,
+    };
+    use std::collections::HashMap;
+
+    const FOCUS_2: KeyCode = KeyCode { mask: 0, code: 10 };
+    const MOVE_1: KeyCode = KeyCode { mask: 0, code: 11 };
+    const NEXT: KeyCode = KeyCode { mask: 0, code: 12 };
+
+    fn wm() -> WindowManager<TestConn> {
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(FOCUS_2, focus_tag_in_group(1));
+        bindings.insert(MOVE_1, move_client_to_tag_in_group(0));
+        bindings.insert(NEXT, next_tag_group());
+
+        TestConn::new().start_headless_wm(bindings, |wm| {
+            TagGroups::new()
+                .with_group("work", ["1", "2", "3"])
+                .with_group("personal", ["7", "8", "9"])
+                .add_to(wm)
+                .unwrap()
+        })
+    }
+
+    #[test]
+    fn unknown_tags_are_an_error() {
+        let wm = WindowManager::new(
+            Config::default(),
+            HashMap::new(),
+            HashMap::new(),
+            TestConn::new(),
+        )
+        .unwrap();
+        let res = TagGroups::new().with_group("bad", ["1", "42"]).add_to(wm);
+
+        assert!(matches!(res, Err(Error::UnknownTag { tag }) if tag == "42"));
+    }
+
+    #[test]
+    fn tags_are_relative_to_the_active_group() {
+        let mut wm = wm();
+
+        wm.conn().press_key(FOCUS_2);
+        wm.run_pending_events();
+        assert_eq!(wm.state.client_set.current_tag(), "2");
+
+        wm.conn().press_key(NEXT);
+        wm.run_pending_events();
+        assert_eq!(wm.state.client_set.current_tag(), "7");
+
+        wm.conn().press_key(FOCUS_2);
+        wm.run_pending_events();
+        assert_eq!(wm.state.client_set.current_tag(), "8");
+    }
+
+    #[test]
+    fn switching_back_restores_the_last_focused_tag() {
+        let mut wm = wm();
+
+        for k in [FOCUS_2, NEXT, NEXT] {
+            wm.conn().press_key(k);
+        }
+        wm.run_pending_events();
+
+        assert_eq!(wm.state.client_set.current_tag(), "2");
+    }
+
+    #[test]
+    fn move_client_uses_the_active_group() {
+        let mut wm = wm();
+        let id = wm.conn().spawn_client("st");
+        wm.conn().press_key(NEXT);
+        wm.run_pending_events();
+
+        wm.state.client_set.focus_tag("1");
+        wm.conn().press_key(MOVE_1);
+        wm.run_pending_events();
+
+        assert_eq!(wm.state.client_set.tag_for_client(&id), Some("7"));
+    }
+}
@@ -0,0 +1,630 @@
+//! Light customisation of penrose from a TOML or RON config file.
+//!
+//! Enabling the `configfile` feature provides [ConfigFile], a declarative subset of [Config]
+//! that can be loaded at startup from a well-known path (see [ConfigFile::default_path]), along
+//! with [ConfigFileWatcher], a state extension that reloads the file whenever it changes so that
+//! simple tweaks do not require recompiling your window manager.
+//!
+//! ```toml
+//! tags = ["web", "dev", "chat", "4", "5"]
+//! border_width = 3
+//! focused_border = "#cc241d"
+//! floating_classes = ["pavucontrol"]
+//!
+//! [gaps]
+//! outer = 5
+//! inner = 5
+//!
+//! [spawn]
+//! "M-Return" = "st"
+//! "M-S-b" = "firefox"
+//!
+//! [[rules]]
+//! class = "discord"
+//! tag = "chat"
+//! ```
+//!
+//! Options that are not present in the file are left as they are in the [Config] that the file
+//! is applied to. Gaps are only applied to layouts that have been wrapped in the [Gaps][0]
+//! transformer.
+//!
+//! ```no_run
+//! use penrose::{
+//!     core::{bindings::parse_keybindings_with_xmodmap, Config, WindowManager},
+//!     extensions::configfile::{ConfigFile, ConfigFileWatcher},
+//!     x11rb::RustConn,
+//! };
+//! use std::collections::HashMap;
+//!
+//! let path = ConfigFile::default_path().expect("a config file to exist");
+//! let file = ConfigFile::from_path(&path)?;
+//! let config = file.apply_to(Config::default())?;
+//! let key_bindings = parse_keybindings_with_xmodmap(file.spawn_bindings())?;
+//!
+//! let wm = WindowManager::new(config, key_bindings, HashMap::new(), RustConn::new()?)?;
+//! let wm = ConfigFileWatcher::new(path, file).watch(wm)?;
+//!
+//! wm.run()
+//! # ;Ok::<(), penrose::Error>(())
+//! ```
+//!
+//! Most options take effect as soon as the file is reloaded. Changes to the workspace tags and
+//! new spawn key bindings require penrose to be restarted as the key bindings that are grabbed
+//! and the workspaces that exist are fixed when the window manager starts.
+//!
+//!   [0]: crate::builtin::layout::transformers::Gaps
+use crate::{
+    builtin::{actions::key_handler, layout::messages::SetGaps},
+    core::{
+        bindings::KeyEventHandler, config::ConfigBuilder, BorderStyle, Config, State, WindowManager,
+    },
+    util::spawn,
+    x::{Atom, Prop, XConn, XConnExt, XEvent},
+    x11rb::Waker,
+    Color, Error, Result, Xid,
+};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+use tracing::{debug, warn};
+
+/// The type of the client message used to wake up the window manager event loop when the
+/// config file has changed.
+pub const WAKEUP_ATOM: &str = "_PENROSE_CONFIGFILE_WAKEUP";
+
+/// The supported config file formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// [TOML](https://toml.io)
+    Toml,
+    /// [RON](https://github.com/ron-rs/ron)
+    Ron,
+}
+
+impl Format {
+    /// Determine the format of a config file from its extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Ok(Self::Toml),
+            Some("ron") => Ok(Self::Ron),
+            _ => Err(Error::UnknownConfigFileFormat {
+                path: path.display().to_string(),
+            }),
+        }
+    }
+}
+
+/// The outer and inner gap sizes to set for layouts wrapped in the [Gaps][0] transformer.
+///
+///   [0]: crate::builtin::layout::transformers::Gaps
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GapSizes {
+    /// The outer gap size in pixels
+    pub outer: u32,
+    /// The inner gap size in pixels
+    pub inner: u32,
+}
+
+/// A rule for moving newly managed clients to a given workspace.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// The `WM_CLASS` (either the instance or class name) of the clients this rule applies to
+    pub class: String,
+    /// The tag of the workspace to move matching clients to
+    pub tag: String,
+}
+
+/// The subset of [Config] options that can be set from a config file.
+///
+/// See the module level docs for details.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    /// The ordered set of workspace tags to use on startup
+    pub tags: Option<Vec<String>>,
+    /// The gap sizes to set on layouts wrapped in the `Gaps` transformer
+    pub gaps: Option<GapSizes>,
+    /// The width in pixels of window borders
+    pub border_width: Option<u32>,
+    /// The color of normal (unfocused) window borders
+    pub normal_border: Option<String>,
+    /// The color of the focused window border
+    pub focused_border: Option<String>,
+    /// The color of the border of windows requesting attention
+    pub urgent_border: Option<String>,
+    /// Window classes that should always be floated, in addition to those in the [Config]
+    pub floating_classes: Vec<String>,
+    /// Key bindings for spawning programs, mapping the binding to the program to run
+    pub spawn: HashMap<String, String>,
+    /// Rules for moving new clients to specific workspaces
+    pub rules: Vec<Rule>,
+}
+
+impl ConfigFile {
+    /// Parse a [ConfigFile] from a string in the given [Format].
+    pub fn parse(s: &str, format: Format) -> Result<Self> {
+        match format {
+            Format::Toml => Ok(toml::from_str(s)?),
+            Format::Ron => Ok(ron::from_str(s)?),
+        }
+    }
+
+    /// Read and parse the [ConfigFile] at the given path, using the file extension to determine
+    /// its [Format].
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let format = Format::from_path(path)?;
+
+        Self::parse(&fs::read_to_string(path)?, format)
+    }
+
+    /// The path to the user's config file if one exists.
+    ///
+    /// This is `penrose/config.toml` or `penrose/config.ron` in `$XDG_CONFIG_HOME` (falling back
+    /// to `$HOME/.config` if it is not set), with the TOML file taking precedence if both exist.
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+
+        ["config.toml", "config.ron"]
+            .iter()
+            .map(|name| dir.join("penrose").join(name))
+            .find(|path| path.is_file())
+    }
+
+    /// Apply the options set in this file to the given [Config], validating them as described
+    /// in [ConfigBuilder::build].
+    pub fn apply_to<X: XConn>(&self, config: Config<X>) -> Result<Config<X>> {
+        let mut builder = ConfigBuilder::new(config);
+
+        if let Some(tags) = &self.tags {
+            builder = builder.tags(tags.iter().cloned());
+        }
+        if let Some(width) = self.border_width {
+            builder = builder.border_width(width);
+        }
+        if let Some(c) = &self.normal_border {
+            builder = builder.normal_border(c.as_str());
+        }
+        if let Some(c) = &self.focused_border {
+            builder = builder.focused_border(c.as_str());
+        }
+        if let Some(c) = &self.urgent_border {
+            builder = builder.urgent_border(c.as_str());
+        }
+
+        builder
+            .modify(|c| {
+                c.floating_classes
+                    .extend(self.floating_classes.iter().cloned());
+                if let Some(GapSizes { outer, inner }) = self.gaps {
+                    c.default_layouts
+                        .broadcast_message(SetGaps { outer, inner });
+                }
+            })
+            .build()
+    }
+
+    /// String format key bindings for the programs in [ConfigFile::spawn], ready to be merged
+    /// with your own bindings and parsed.
+    ///
+    /// If a [ConfigFileWatcher] has been added to the window manager then the program run by
+    /// each binding is looked up from the most recently loaded version of the file.
+    pub fn spawn_bindings<X>(&self) -> HashMap<String, Box<dyn KeyEventHandler<X>>>
+    where
+        X: XConn + 'static,
+    {
+        self.spawn
+            .iter()
+            .map(|(binding, prog)| {
+                let handler = spawn_from_file(binding.clone(), prog.clone());
+                (binding.clone(), handler)
+            })
+            .collect()
+    }
+
+    fn border_style(&self, current: BorderStyle) -> Result<BorderStyle> {
+        let color = |c: &Option<String>, default: Color| match c {
+            Some(s) => Color::try_from(s.as_str()),
+            None => Ok(default),
+        };
+
+        Ok(BorderStyle {
+            width: self.border_width.unwrap_or(current.width),
+            focused: color(&self.focused_border, current.focused)?,
+            normal: color(&self.normal_border, current.normal)?,
+            urgent: color(&self.urgent_border, current.urgent)?,
+        })
+    }
+}
+
+fn spawn_from_file<X>(binding: String, prog: String) -> Box<dyn KeyEventHandler<X>>
+where
+    X: XConn + 'static,
+{
+    key_handler(move |state: &mut State<X>, _: &X| {
+        let prog = match state.extension::<ConfigFileWatcher>() {
+            Ok(w) => w.borrow().file.spawn.get(&binding).cloned(),
+            Err(_) => Some(prog.clone()),
+        };
+
+        match prog {
+            Some(prog) => spawn(prog),
+            None => {
+                debug!(%binding, "binding has been removed from the config file");
+                Ok(())
+            }
+        }
+    })
+}
+
+/// A state extension for reloading a [ConfigFile] when it changes.
+///
+/// See the module level docs for details.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFileWatcher {
+    path: PathBuf,
+    file: ConfigFile,
+    bound: HashSet<String>,
+    poll_interval: Duration,
+}
+
+impl ConfigFileWatcher {
+    /// Create a new [ConfigFileWatcher] for the file at the given path, which was used to
+    /// create the [Config] and key bindings that the window manager is starting with.
+    pub fn new(path: impl Into<PathBuf>, file: ConfigFile) -> Self {
+        Self {
+            path: path.into(),
+            bound: file.spawn.keys().cloned().collect(),
+            file,
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Set how often the config file is checked for changes.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+
+        self
+    }
+
+    /// The most recently loaded version of the config file.
+    pub fn file(&self) -> &ConfigFile {
+        &self.file
+    }
+
+    /// Add this [ConfigFileWatcher] to the given [WindowManager] along with the event and
+    /// manage hooks required to run it, without watching the file for changes.
+    ///
+    /// The file can be reloaded manually using [reload].
+    pub fn add_to<X: XConn + 'static>(self, mut wm: WindowManager<X>) -> WindowManager<X> {
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_event_hook(event_hook);
+        wm.state.config.compose_or_set_manage_hook(manage_hook);
+
+        wm
+    }
+
+    /// Start watching the config file for changes and add this [ConfigFileWatcher] to the given
+    /// [WindowManager] along with the hooks required to run it.
+    pub fn watch<X: XConn + 'static>(self, wm: WindowManager<X>) -> Result<WindowManager<X>> {
+        let waker = Waker::new(WAKEUP_ATOM)?;
+        let path = self.path.clone();
+        let interval = self.poll_interval;
+        thread::spawn(move || poll_for_changes(path, interval, waker));
+
+        Ok(self.add_to(wm))
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn poll_for_changes(path: PathBuf, interval: Duration, waker: Waker) {
+    let mut last_modified = modified(&path);
+
+    loop {
+        thread::sleep(interval);
+        let m = modified(&path);
+        if m != last_modified {
+            debug!(?path, "config file has changed");
+            last_modified = m;
+            waker.wake();
+        }
+    }
+}
+
+/// Reload the config file being tracked by the [ConfigFileWatcher] and apply any changes.
+///
+/// If the file is unable to be read or contains invalid options then the error is logged and
+/// the current config is left unchanged.
+pub fn reload<X: XConn + 'static>(state: &mut State<X>, x: &X) -> Result<()> {
+    let s = state.extension::<ConfigFileWatcher>()?;
+    let mut watcher = s.borrow_mut();
+
+    let res = ConfigFile::from_path(&watcher.path).and_then(|file| {
+        file.border_style(state.config.border_style())
+            .map(|s| (file, s))
+    });
+    let (file, style) = match res {
+        Ok(res) => res,
+        Err(e) => {
+            warn!(%e, path = ?watcher.path, "unable to reload config file");
+            return Ok(());
+        }
+    };
+
+    if file.tags.is_some() && file.tags != watcher.file.tags {
+        warn!("changes to tags in the config file require penrose to be restarted");
+    }
+    for binding in file.spawn.keys().filter(|b| !watcher.bound.contains(*b)) {
+        warn!(%binding, "new key bindings in the config file require penrose to be restarted");
+    }
+
+    let classes = &mut state.config.floating_classes;
+    classes.retain(|c| !watcher.file.floating_classes.contains(c));
+    classes.extend(file.floating_classes.iter().cloned());
+
+    if let Some(GapSizes { outer, inner }) = file.gaps {
+        state
+            .client_set
+            .broadcast_message(|| SetGaps { outer, inner });
+    }
+
+    watcher.file = file;
+    drop(watcher);
+
+    state.config.set_border_style(style);
+    x.update_borders(state)?;
+
+    x.refresh(state)
+}
+
+/// Reload the config file when it has changed.
+pub fn event_hook<X: XConn + 'static>(event: &XEvent, state: &mut State<X>, x: &X) -> Result<bool> {
+    match event {
+        XEvent::ClientMessage(m) if m.dtype == WAKEUP_ATOM => {
+            reload(state, x)?;
+            Ok(false)
+        }
+        _ => Ok(true),
+    }
+}
+
+/// Move new clients to the workspace given by the first [Rule] in the config file that
+/// matches them.
+pub fn manage_hook<X: XConn + 'static>(id: Xid, state: &mut State<X>, x: &X) -> Result<()> {
+    let s = state.extension::<ConfigFileWatcher>()?;
+    let watcher = s.borrow();
+    if watcher.file.rules.is_empty() {
+        return Ok(());
+    }
+
+    let classes = match x.get_prop(id, Atom::WmClass.as_ref())? {
+        Some(Prop::UTF8String(strs)) => strs,
+        _ => return Ok(()),
+    };
+
+    if let Some(rule) = watcher
+        .file
+        .rules
+        .iter()
+        .find(|r| classes.contains(&r.class))
+    {
+        debug!(%id, class = %rule.class, tag = %rule.tag, "moving client to tag from config file rule");
+        state.client_set.move_client_to_tag(&id, &rule.tag);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builtin::layout::{transformers::Gaps, MainAndStack},
+        core::config::ConfigProblem,
+        stack,
+        x::{
+            event::{ClientEventMask, ClientMessage},
+            TestConn,
+        },
+    };
+
+    const TOML: &str = r##"
+tags = ["1", "2", "3"]
+border_width = 5
+focused_border = "#112233"
+floating_classes = ["calc"]
+
+[gaps]
+outer = 4
+inner = 2
+
+[spawn]
+"M-Return" = "st"
+
+[[rules]]
+class = "chat"
+tag = "3"
+"##;
+
+    const RON: &str = r##"(
+    tags: Some(["1", "2", "3"]),
+    border_width: Some(5),
+    focused_border: Some("#112233"),
+    floating_classes: ["calc"],
+    gaps: Some((outer: 4, inner: 2)),
+    spawn: { "M-Return": "st" },
+    rules: [(class: "chat", tag: "3")],
+)"##;
+
+    fn expected() -> ConfigFile {
+        ConfigFile {
+            tags: Some(vec!["1".to_owned(), "2".to_owned(), "3".to_owned()]),
+            gaps: Some(GapSizes { outer: 4, inner: 2 }),
+            border_width: Some(5),
+            focused_border: Some("#112233".to_owned()),
+            floating_classes: vec!["calc".to_owned()],
+            spawn: [("M-Return".to_owned(), "st".to_owned())].into(),
+            rules: vec![Rule {
+                class: "chat".to_owned(),
+                tag: "3".to_owned(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    // A unique path in the temp dir for each test so that they can run in parallel
+    fn write_file(name: &str, contents: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("penrose-{}-{name}", std::process::id()));
+        fs::write(&path, contents).unwrap();
+
+        path
+    }
+
+    fn wm_with_file(name: &str, contents: &str) -> (PathBuf, WindowManager<TestConn>) {
+        let path = write_file(name, contents);
+        let file = ConfigFile::from_path(&path).unwrap();
+        let config = Config {
+            default_layouts: stack!(Gaps::wrap(Box::<MainAndStack>::default(), 0, 0)),
+            ..Default::default()
+        };
+        let config = file.apply_to(config).unwrap();
+
+        let conn = TestConn::new();
+        let wm = WindowManager::new(config, HashMap::new(), HashMap::new(), conn).unwrap();
+        let mut wm = ConfigFileWatcher::new(&path, file).add_to(wm);
+        wm.start_headless().unwrap();
+
+        (path, wm)
+    }
+
+    fn wake(wm: &mut WindowManager<TestConn>) {
+        let wakeup = ClientMessage::new(
+            wm.state.root(),
+            ClientEventMask::SubstructureNotify,
+            WAKEUP_ATOM,
+            [0u32; 5].into(),
+        );
+        wm.conn().push_event(XEvent::ClientMessage(wakeup));
+        wm.run_pending_events();
+    }
+
+    #[test]
+    fn toml_files_can_be_parsed() {
+        assert_eq!(ConfigFile::parse(TOML, Format::Toml).unwrap(), expected());
+    }
+
+    #[test]
+    fn ron_files_can_be_parsed() {
+        assert_eq!(ConfigFile::parse(RON, Format::Ron).unwrap(), expected());
+    }
+
+    #[test]
+    fn unknown_fields_are_an_error() {
+        assert!(ConfigFile::parse("tagz = [\"1\"]", Format::Toml).is_err());
+    }
+
+    #[test]
+    fn unknown_file_extensions_are_an_error() {
+        let res = Format::from_path("config.yaml");
+
+        assert!(matches!(res, Err(Error::UnknownConfigFileFormat { .. })));
+    }
+
+    #[test]
+    fn apply_to_sets_only_the_given_options() {
+        let file = ConfigFile::parse(TOML, Format::Toml).unwrap();
+        let config = file.apply_to(Config::<TestConn>::default()).unwrap();
+
+        assert_eq!(config.tags, vec!["1", "2", "3"]);
+        assert_eq!(config.border_width, 5);
+        assert_eq!(config.focused_border, Color::new_from_hex(0x112233ff));
+        assert_eq!(
+            config.normal_border,
+            Config::<TestConn>::default().normal_border
+        );
+        assert!(config.floating_classes.contains(&"calc".to_owned()));
+    }
+
+    #[test]
+    fn apply_to_validates_the_file() {
+        let file = ConfigFile::parse("tags = [\"1\", \"1\"]", Format::Toml).unwrap();
+
+        match file.apply_to(Config::<TestConn>::default()) {
+            Err(Error::InvalidConfig { problems }) => assert_eq!(
+                problems,
+                vec![ConfigProblem::DuplicateTag {
+                    tag: "1".to_owned()
+                }]
+            ),
+            _ => panic!("expected duplicate tags to be reported"),
+        }
+    }
+
+    #[test]
+    fn rules_move_clients_to_their_tag() {
+        let (_, mut wm) = wm_with_file("rules.toml", TOML);
+
+        let id = wm.conn().spawn_client("chat");
+        wm.run_pending_events();
+
+        assert_eq!(wm.state.client_set.tag_for_client(&id), Some("3"));
+    }
+
+    #[test]
+    fn reloading_applies_changes() {
+        let (path, mut wm) = wm_with_file("reload.toml", TOML);
+
+        fs::write(
+            &path,
+            "border_width = 1\nfloating_classes = [\"mpv\"]\n[gaps]\nouter = 10\ninner = 10",
+        )
+        .unwrap();
+        wake(&mut wm);
+
+        let classes = &wm.state.config.floating_classes;
+        assert_eq!(wm.state.config.border_width, 1);
+        assert!(classes.contains(&"mpv".to_owned()));
+        assert!(!classes.contains(&"calc".to_owned()));
+        assert!(wm
+            .state
+            .extension::<ConfigFileWatcher>()
+            .unwrap()
+            .borrow()
+            .file()
+            .rules
+            .is_empty());
+    }
+
+    #[test]
+    fn invalid_files_are_not_applied_on_reload() {
+        let (path, mut wm) = wm_with_file("invalid.toml", TOML);
+
+        fs::write(&path, "border_width = 1\nfocused_border = \"nope\"").unwrap();
+        wake(&mut wm);
+
+        assert_eq!(wm.state.config.border_width, 5);
+        assert_eq!(
+            wm.state
+                .extension::<ConfigFileWatcher>()
+                .unwrap()
+                .borrow()
+                .file(),
+            &expected()
+        );
+    }
+}
@@ -0,0 +1,323 @@
+//! Control of the window manager over D-Bus.
+//!
+//! Enabling the `dbus` feature provides [DBusServer], a state extension that exposes the
+//! `org.penrose.WindowManager` interface on the session bus at `/org/penrose/WindowManager` so
+//! that external tooling can interact with penrose:
+//!
+//! | Method                           | Description                                                |
+//! | -------------------------------- | ---------------------------------------------------------- |
+//! | `FocusTag(tag: s)`               | Focus the workspace with the given tag                     |
+//! | `MoveWindow(id: u, tag: s)`      | Move the given client to the workspace with the given tag  |
+//! | `GetState() -> s`                | The current [StateSnapshot] as JSON                        |
+//! | `RunAction(name: s)`             | Run a named action registered with [DBusServer::with_action] |
+//!
+//! | Signal                           | Description                                                |
+//! | -------------------------------- | ---------------------------------------------------------- |
+//! | `FocusChanged(id: u)`            | The focused client has changed (0 if there is none)        |
+//! | `TagChanged(tag: s)`             | The focused workspace has changed                          |
+//!
+//! ```no_run
+//! use penrose::{
+//!     builtin::actions::spawn,
+//!     core::{Config, WindowManager},
+//!     extensions::dbus::DBusServer,
+//!     x11rb::RustConn,
+//! };
+//! use std::collections::HashMap;
+//!
+//! let conn = RustConn::new()?;
+//! let wm = WindowManager::new(Config::default(), HashMap::new(), HashMap::new(), conn)?;
+//!
+//! let wm = DBusServer::new()
+//!     .with_action("terminal", spawn("st"))
+//!     .add_to(wm)?;
+//!
+//! wm.run()
+//! # ;Ok::<(), penrose::Error>(())
+//! ```
+//!
+//! Methods are called from a background thread and are passed to the window manager event loop
+//! to be run, so they do not return errors to the caller: any errors are logged instead. The
+//! snapshot returned by `GetState` is updated each time the window manager refreshes the screen.
+//!
+//!   [StateSnapshot]: crate::core::introspection::StateSnapshot
+use crate::{
+    core::{bindings::KeyEventHandler, State, WindowManager},
+    x::{XConn, XConnExt, XEvent},
+    x11rb::Waker,
+    Result, Xid,
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+};
+use tracing::{error, warn};
+use zbus::{blocking::connection::Builder, interface};
+
+/// The well known name claimed on the session bus.
+pub const BUS_NAME: &str = "org.penrose.WindowManager";
+/// The object path the window manager interface is served at.
+pub const OBJECT_PATH: &str = "/org/penrose/WindowManager";
+/// The name of the D-Bus interface used for methods and signals.
+pub const INTERFACE: &str = "org.penrose.WindowManager";
+
+// The type of the client message sent to the root window in order to wake up the window manager
+// event loop when a method call is received.
+const WAKEUP_ATOM: &str = "_PENROSE_DBUS_WAKEUP";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Request {
+    FocusTag(String),
+    MoveWindow(Xid, String),
+    RunAction(String),
+}
+
+/// A state extension serving the `org.penrose.WindowManager` D-Bus interface.
+///
+/// See the module level docs for details.
+pub struct DBusServer<X: XConn> {
+    actions: HashMap<String, Box<dyn KeyEventHandler<X>>>,
+    conn: Option<zbus::blocking::Connection>,
+    rx: Option<Receiver<Request>>,
+    snapshot: Arc<Mutex<String>>,
+    focus: Option<Xid>,
+    tag: String,
+}
+
+impl<X: XConn> fmt::Debug for DBusServer<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DBusServer")
+            .field("actions", &self.actions.keys().collect::<Vec<_>>())
+            .field("focus", &self.focus)
+            .field("tag", &self.tag)
+            .finish()
+    }
+}
+
+impl<X: XConn> Default for DBusServer<X> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<X: XConn> DBusServer<X> {
+    /// Create a new [DBusServer] with no named actions.
+    pub fn new() -> Self {
+        Self {
+            actions: HashMap::new(),
+            conn: None,
+            rx: None,
+            snapshot: Arc::new(Mutex::new(String::new())),
+            focus: None,
+            tag: String::new(),
+        }
+    }
+
+    /// Register an action that can be run using the `RunAction` method.
+    pub fn with_action(
+        mut self,
+        name: impl Into<String>,
+        action: Box<dyn KeyEventHandler<X>>,
+    ) -> Self {
+        self.actions.insert(name.into(), action);
+
+        self
+    }
+}
+
+impl<X: XConn + 'static> DBusServer<X> {
+    /// Connect to the session bus and add this [DBusServer] to the given [WindowManager] along
+    /// with the hooks required to run it.
+    pub fn add_to(mut self, mut wm: WindowManager<X>) -> Result<WindowManager<X>> {
+        let (tx, rx) = channel();
+        let iface = Interface {
+            tx,
+            waker: Waker::new(WAKEUP_ATOM)?,
+            snapshot: self.snapshot.clone(),
+        };
+
+        let conn = Builder::session()?
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, iface)?
+            .build()?;
+
+        self.conn = Some(conn);
+        self.rx = Some(rx);
+
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_event_hook(event_hook);
+        wm.state.config.compose_or_set_refresh_hook(refresh_hook);
+
+        Ok(wm)
+    }
+
+    fn emit<B>(&self, signal: &str, body: &B)
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        if let Some(conn) = &self.conn {
+            if let Err(e) = conn.emit_signal(None::<&str>, OBJECT_PATH, INTERFACE, signal, body) {
+                error!(%e, %signal, "unable to emit D-Bus signal");
+            }
+        }
+    }
+}
+
+/// Run any method calls received over D-Bus.
+pub fn event_hook<X: XConn + 'static>(event: &XEvent, state: &mut State<X>, x: &X) -> Result<bool> {
+    match event {
+        XEvent::ClientMessage(m) if m.dtype == WAKEUP_ATOM => (),
+        _ => return Ok(true),
+    }
+
+    let s = state.extension::<DBusServer<X>>()?;
+    let requests: Vec<Request> = match &s.borrow().rx {
+        Some(rx) => rx.try_iter().collect(),
+        None => return Ok(false),
+    };
+
+    for r in requests {
+        match r {
+            Request::FocusTag(tag) if state.client_set.contains_tag(&tag) => {
+                x.modify_and_refresh(state, |cs| cs.focus_tag(&tag))?;
+            }
+
+            Request::MoveWindow(id, tag) if state.client_set.contains_tag(&tag) => {
+                x.modify_and_refresh(state, |cs| cs.move_client_to_tag(&id, &tag))?;
+            }
+
+            Request::RunAction(name) => {
+                // Actions are removed while they run so that they are free to make use of the
+                // extension themselves (e.g. by refreshing the window manager state).
+                let action = s.borrow_mut().actions.remove(&name);
+                match action {
+                    Some(mut action) => {
+                        let res = action.call(state, x);
+                        s.borrow_mut().actions.insert(name, action);
+                        res?;
+                    }
+                    None => warn!(%name, "unknown D-Bus action"),
+                }
+            }
+
+            r => warn!(?r, "unknown tag in D-Bus request"),
+        }
+    }
+
+    Ok(false)
+}
+
+/// Update the snapshot returned by `GetState` and emit signals for changes in focus.
+pub fn refresh_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> Result<()> {
+    let s = state.extension::<DBusServer<X>>()?;
+    let mut server = s.borrow_mut();
+
+    let json = serde_json::to_string(&state.state_snapshot(x)?)?;
+    match server.snapshot.lock() {
+        Ok(mut snapshot) => *snapshot = json,
+        Err(poisoned) => *poisoned.into_inner() = json,
+    }
+
+    let focus = state.client_set.current_client().copied();
+    if focus != server.focus {
+        server.focus = focus;
+        server.emit("FocusChanged", &(focus.map(|id| *id).unwrap_or(0),));
+    }
+
+    let tag = state.client_set.current_tag();
+    if tag != server.tag {
+        server.tag = tag.to_owned();
+        server.emit("TagChanged", &(tag,));
+    }
+
+    Ok(())
+}
+
+struct Interface {
+    tx: Sender<Request>,
+    waker: Waker,
+    snapshot: Arc<Mutex<String>>,
+}
+
+impl Interface {
+    fn send(&self, r: Request) {
+        if self.tx.send(r).is_err() {
+            warn!("window manager is no longer running");
+            return;
+        }
+        self.waker.wake();
+    }
+}
+
+#[interface(name = "org.penrose.WindowManager")]
+impl Interface {
+    fn focus_tag(&self, tag: String) {
+        self.send(Request::FocusTag(tag));
+    }
+
+    fn move_window(&self, id: u32, tag: String) {
+        self.send(Request::MoveWindow(Xid(id), tag));
+    }
+
+    fn get_state(&self) -> String {
+        match self.snapshot.lock() {
+            Ok(snapshot) => snapshot.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    fn run_action(&self, name: String) {
+        self.send(Request::RunAction(name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::Config,
+        x::{
+            event::{ClientEventMask, ClientMessage},
+            TestConn,
+        },
+    };
+
+    #[test]
+    fn requests_are_run_on_wakeup() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            HashMap::new(),
+            HashMap::new(),
+            TestConn::new(),
+        )
+        .unwrap();
+        wm.start_headless().unwrap();
+        let id = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        let (tx, rx) = channel();
+        let mut server = DBusServer::<TestConn>::new();
+        server.rx = Some(rx);
+        wm.state.add_extension(server);
+        wm.state.config.compose_or_set_event_hook(event_hook);
+
+        tx.send(Request::MoveWindow(id, "3".to_owned())).unwrap();
+        tx.send(Request::FocusTag("3".to_owned())).unwrap();
+        tx.send(Request::FocusTag("unknown".to_owned())).unwrap();
+        let wakeup = ClientMessage::new(
+            wm.state.root(),
+            ClientEventMask::SubstructureNotify,
+            WAKEUP_ATOM,
+            [0u32; 5].into(),
+        );
+        wm.conn().push_event(XEvent::ClientMessage(wakeup));
+        wm.run_pending_events();
+
+        assert_eq!(wm.state.client_set.current_tag(), "3");
+        assert_eq!(wm.state.client_set.current_client(), Some(&id));
+    }
+}
@@ -7,12 +7,14 @@
 //!   <https://specifications.freedesktop.org/wm-spec/wm-spec-latest.html>
 use crate::{
     core::{ClientSet, Config, State},
-    extensions::actions::{set_fullscreen_state, FullScreenAction},
+    extensions::actions::{
+        set_fullscreen_state, set_maximized_state, FullScreenAction, MaximizeDirection,
+    },
     x::{
         atom::Atom,
         event::{ClientMessage, ClientMessageData},
         property::Prop,
-        XConn, XConnExt, XEvent,
+        set_fullscreen_prop, XConn, XConnExt, XEvent,
     },
     Result, Xid,
 };
@@ -24,6 +26,8 @@ use tracing::{debug, warn};
 pub const EWMH_SUPPORTED_ATOMS: &[Atom] = &[
     Atom::NetWmStateHidden,
     Atom::NetWmStateFullscreen,
+    Atom::NetWmStateMaximizedHorz,
+    Atom::NetWmStateMaximizedVert,
     Atom::NetWmStateDemandsAttention,
     Atom::NetNumberOfDesktops,
     Atom::NetClientList,
@@ -86,7 +90,7 @@ pub fn startup_hook<X: XConn>(_state: &mut State<X>, x: &X) -> Result<()> {
 ///   - _NET_WM_DESKTOP      :: moving clients between workspaces
 ///   - _NET_ACTIVE_WINDOW   :: focus a new client and handle workspace switching
 ///   - _NET_CLOSE_WINDOW    :: closing a client window
-///   - _NET_WM_STATE        :: support for fullscreen windows
+///   - _NET_WM_STATE        :: support for fullscreen and maximized windows
 pub fn event_hook<X: XConn>(event: &XEvent, state: &mut State<X>, x: &X) -> Result<bool> {
     let ClientMessage {
         id, dtype, data, ..
@@ -117,10 +121,8 @@ pub fn event_hook<X: XConn>(event: &XEvent, state: &mut State<X>, x: &X) -> Resu
         // If the request came from a pager, the first data element should be 2.
         // For pager requests, set the active client (see docs linked at the top of
         // this file for more details on the semantics of this message)
-        "_NET_ACTIVE_WINDOW" => {
-            if data.as_u32()[0] == 2 {
-                x.set_active_client(*id, state)?;
-            }
+        "_NET_ACTIVE_WINDOW" if data.as_u32()[0] == 2 => {
+            x.set_active_client(*id, state)?;
         }
 
         // Attempt to remove the requested client
@@ -128,8 +130,8 @@ pub fn event_hook<X: XConn>(event: &XEvent, state: &mut State<X>, x: &X) -> Resu
             cs.remove_client(id);
         })?,
 
-        // Handle clients that want fullscreen behaviour
-        "_NET_WM_STATE" => handle_fullscreen_message(*id, data, state, x)?,
+        // Handle clients that want fullscreen or maximized behaviour
+        "_NET_WM_STATE" => handle_wm_state_message(*id, data, state, x)?,
 
         // Leave other client messages for the default event handling
         _ => (),
@@ -138,7 +140,7 @@ pub fn event_hook<X: XConn>(event: &XEvent, state: &mut State<X>, x: &X) -> Resu
     Ok(true)
 }
 
-fn handle_fullscreen_message<X: XConn>(
+fn handle_wm_state_message<X: XConn>(
     id: Xid,
     data: &ClientMessageData,
     state: &mut State<X>,
@@ -150,11 +152,21 @@ fn handle_fullscreen_message<X: XConn>(
         return Ok(());
     }
 
-    let full_screen = x.intern_atom(Atom::NetWmStateFullscreen.as_ref())?;
+    let full_screen = *x.intern_atom(Atom::NetWmStateFullscreen.as_ref())?;
+    let horz = *x.intern_atom(Atom::NetWmStateMaximizedHorz.as_ref())?;
+    let vert = *x.intern_atom(Atom::NetWmStateMaximizedVert.as_ref())?;
     let raw_action = data32.remove(0);
 
-    // Only handling fullscreen messages and only for known clients
-    if !(data32.contains(&full_screen) && state.client_set.contains(&id)) {
+    // Only the first two elements are properties: the third is the source indication
+    data32.truncate(2);
+    let (fullscreen, h, v) = (
+        data32.contains(&full_screen),
+        data32.contains(&horz),
+        data32.contains(&vert),
+    );
+
+    // Only handling fullscreen and maximize messages and only for known clients
+    if !((fullscreen || h || v) && state.client_set.contains(&id)) {
         return Ok(());
     }
 
@@ -163,11 +175,36 @@ fn handle_fullscreen_message<X: XConn>(
         1 => FullScreenAction::Add,
         2 => FullScreenAction::Toggle,
         action => {
-            warn!(%action, "invalid fullscreen action: expected 0, 1 or 2");
+            warn!(%action, "invalid _NET_WM_STATE action: expected 0, 1 or 2");
             return Ok(());
         }
     };
 
+    if !fullscreen {
+        let direction = match (h, v) {
+            (true, true) => MaximizeDirection::Both,
+            (true, false) => MaximizeDirection::Horizontal,
+            _ => MaximizeDirection::Vertical,
+        };
+
+        return set_maximized_state(id, direction, action, state, x);
+    }
+
+    // Clients in fake fullscreen only have their fullscreen property updated
+    if state.is_fake_fullscreen(&id) {
+        let currently_fullscreen = matches!(
+            x.get_prop(id, Atom::NetWmState.as_ref()),
+            Ok(Some(Prop::Cardinal(vals))) if vals.contains(&full_screen)
+        );
+        let fullscreen = match action {
+            FullScreenAction::Add => true,
+            FullScreenAction::Remove => false,
+            FullScreenAction::Toggle => !currently_fullscreen,
+        };
+
+        return set_fullscreen_prop(x, id, fullscreen);
+    }
+
     set_fullscreen_state(id, action, state, x)
 }
 
@@ -271,3 +308,57 @@ where
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::WindowManager,
+        pure::geometry::Rect,
+        x::{event::ClientEventMask, TestConn},
+    };
+    use std::collections::HashMap;
+
+    fn wm_state_message(wm: &WindowManager<TestConn>, id: Xid, action: u32, atoms: &[Atom]) {
+        let mut data = [action, 0, 0, 1, 0];
+        for (i, a) in atoms.iter().enumerate() {
+            data[i + 1] = *wm.conn().intern_atom(a.as_ref()).unwrap();
+        }
+
+        wm.conn()
+            .push_event(XEvent::ClientMessage(ClientMessage::new(
+                id,
+                ClientEventMask::SubstructureNotify,
+                Atom::NetWmState.as_ref(),
+                data.into(),
+            )));
+    }
+
+    #[test]
+    fn maximize_messages_float_clients_over_the_screen_with_their_border() {
+        let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+        let config = add_ewmh_hooks(Config::default());
+        let mut wm = WindowManager::new(config, HashMap::new(), HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+
+        let id = wm.conn().spawn_client("st");
+        wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        let maximized = [Atom::NetWmStateMaximizedHorz, Atom::NetWmStateMaximizedVert];
+        wm_state_message(&wm, id, 1, &maximized);
+        wm.run_pending_events();
+
+        assert!(wm.state.client_set.is_floating(&id));
+        assert_eq!(
+            wm.conn().client_geometry(id).unwrap(),
+            Rect::new(0, 0, 1000, 800).shrink_in(2)
+        );
+
+        wm_state_message(&wm, id, 0, &maximized);
+        wm.run_pending_events();
+
+        assert!(!wm.state.client_set.is_floating(&id));
+        assert_eq!(wm.state.maximized_state(&id), None);
+    }
+}
@@ -0,0 +1,190 @@
+//! Remembering the position of floating clients between runs of the same program.
+//!
+//! [FloatingGeometryMemory] records the last position and size of each floating client along
+//! with its WM_CLASS and the tag of the workspace it was on. When a new client with the same
+//! class is later opened as floating on that workspace, it is placed back where the previous
+//! one was left:
+//!
+//! ```no_run
+//! use penrose::{
+//!     core::{Config, WindowManager},
+//!     extensions::hooks::floating_geometry::FloatingGeometryMemory,
+//!     x11rb::RustConn,
+//! };
+//! use std::collections::HashMap;
+//!
+//! let config = Config {
+//!     floating_classes: vec!["gimp".to_owned(), "qalculate-gtk".to_owned()],
+//!     ..Config::default()
+//! };
+//! let wm = WindowManager::new(config, HashMap::new(), HashMap::new(), RustConn::new()?)?;
+//! let wm = FloatingGeometryMemory::default().add_to(wm);
+//! # Ok::<(), penrose::Error>(())
+//! ```
+//!
+//! Positions are stored relative to the screen the client was on so they are restored in the
+//! same place on screens of a different size. Saved positions are only held in memory and are
+//! lost when the window manager exits.
+use crate::{
+    core::{State, WindowManager},
+    pure::geometry::RelativeRect,
+    x::{Atom, Prop, XConn},
+    Result, Xid,
+};
+use std::collections::HashMap;
+
+/// A state extension for restoring the position of floating clients based on their WM_CLASS
+/// and workspace tag.
+///
+/// See the module level docs for details.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FloatingGeometryMemory {
+    saved: HashMap<(String, String), RelativeRect>,
+    classes: HashMap<Xid, String>,
+}
+
+impl FloatingGeometryMemory {
+    /// Add this [FloatingGeometryMemory] to the given [WindowManager] along with the hooks
+    /// required to run it.
+    pub fn add_to<X>(self, mut wm: WindowManager<X>) -> WindowManager<X>
+    where
+        X: XConn + 'static,
+    {
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_manage_hook(manage_hook);
+        wm.state.config.compose_or_set_refresh_hook(refresh_hook);
+
+        wm
+    }
+
+    /// The saved position for floating clients with the given class on the given tag, if there
+    /// is one.
+    pub fn saved_position(&self, class: &str, tag: &str) -> Option<RelativeRect> {
+        self.saved.get(&(class.to_owned(), tag.to_owned())).copied()
+    }
+
+    fn class_for<X: XConn>(&mut self, id: Xid, x: &X) -> Result<String> {
+        if let Some(class) = self.classes.get(&id) {
+            return Ok(class.clone());
+        }
+
+        let class = match x.get_prop(id, Atom::WmClass.as_ref())? {
+            Some(Prop::UTF8String(strs)) => strs.last().cloned().unwrap_or_default(),
+            _ => String::new(),
+        };
+        self.classes.insert(id, class.clone());
+
+        Ok(class)
+    }
+}
+
+/// Restore the saved position of newly managed floating clients.
+pub fn manage_hook<X: XConn + 'static>(id: Xid, state: &mut State<X>, x: &X) -> Result<()> {
+    if !state.client_set.is_floating(&id) {
+        return Ok(());
+    }
+    let tag = match state.client_set.tag_for_client(&id) {
+        Some(tag) => tag.to_owned(),
+        None => return Ok(()),
+    };
+
+    let s = state.extension::<FloatingGeometryMemory>()?;
+    let mut memory = s.borrow_mut();
+    let class = memory.class_for(id, x)?;
+
+    if let Some(r) = memory.saved_position(&class, &tag) {
+        state.client_set.floating.insert(id, r);
+    }
+
+    Ok(())
+}
+
+/// Record the current position of all floating clients.
+pub fn refresh_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> Result<()> {
+    let s = state.extension::<FloatingGeometryMemory>()?;
+    let mut memory = s.borrow_mut();
+    let cs = &state.client_set;
+
+    memory.classes.retain(|id, _| cs.contains(id));
+
+    for (&id, &r) in cs.floating.iter() {
+        // Fullscreen clients are floated over the whole screen which we don't want to remember
+        if state.is_fullscreen(&id) {
+            continue;
+        }
+        let tag = match cs.tag_for_client(&id) {
+            Some(tag) => tag.to_owned(),
+            None => continue,
+        };
+        let class = memory.class_for(id, x)?;
+        memory.saved.insert((class, tag), r);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builtin::actions::modify_with,
+        core::bindings::{KeyBindings, KeyCode},
+        pure::geometry::Rect,
+        x::TestConn,
+    };
+
+    const MOVE: KeyCode = KeyCode { mask: 0, code: 10 };
+    const R: Rect = Rect::new(100, 200, 300, 250);
+    const BORDER: u32 = 2;
+
+    fn wm() -> WindowManager<TestConn> {
+        let mut bindings: KeyBindings<TestConn> = HashMap::new();
+        bindings.insert(
+            MOVE,
+            modify_with(|cs| {
+                if let Some(&id) = cs.current_client() {
+                    _ = cs.float(id, R);
+                }
+            }),
+        );
+
+        TestConn::new()
+            .with_screens(vec![Rect::new(0, 0, 1000, 800)])
+            .start_headless_wm(bindings, |mut wm| {
+                wm.state.config.floating_classes = vec!["calc".to_owned()];
+                FloatingGeometryMemory::default().add_to(wm)
+            })
+    }
+
+    #[test]
+    fn floating_position_is_restored_for_the_same_class_and_tag() {
+        let mut wm = wm();
+
+        let id = wm.conn().spawn_client("calc");
+        wm.conn().press_key(MOVE);
+        wm.run_pending_events();
+        assert_eq!(wm.conn().client_geometry(id).unwrap(), R.shrink_in(BORDER));
+        wm.conn().close_client(id);
+        wm.run_pending_events();
+
+        let id = wm.conn().spawn_client("calc");
+        wm.run_pending_events();
+
+        assert_eq!(wm.conn().client_geometry(id).unwrap(), R.shrink_in(BORDER));
+    }
+
+    #[test]
+    fn positions_are_not_shared_between_tags() {
+        let mut wm = wm();
+
+        wm.conn().spawn_client("calc");
+        wm.conn().press_key(MOVE);
+        wm.run_pending_events();
+        wm.state.client_set.focus_tag("2");
+
+        let id = wm.conn().spawn_client("calc");
+        wm.run_pending_events();
+
+        assert_ne!(wm.conn().client_geometry(id).unwrap(), R.shrink_in(BORDER));
+    }
+}
@@ -0,0 +1,271 @@
+//! Detecting fullscreen games and leaving them alone while they are focused.
+//!
+//! Games and other SDL applications often go fullscreen and then grab the pointer (or set
+//! override-redirect on their window) so that they have full control over input and rendering.
+//! Repositioning or restacking such clients each time the window manager refreshes can cause
+//! them to flicker, drop out of fullscreen or lose their grab.
+//!
+//! [GameMode] detects focused fullscreen clients that have done either of these things and
+//! suspends positioning them until they lose focus or leave fullscreen. Hooks can be attached
+//! for when game mode is entered and exited in order to disable the compositor or hide a status
+//! bar while the game is running:
+//!
+//! ```no_run
+//! use penrose::{
+//!     core::{Config, State, WindowManager},
+//!     extensions::hooks::{add_ewmh_hooks, game_mode::GameMode},
+//!     util::spawn,
+//!     x11rb::RustConn,
+//! };
+//! use std::collections::HashMap;
+//!
+//! let config = add_ewmh_hooks(Config::default());
+//! let wm = WindowManager::new(config, HashMap::new(), HashMap::new(), RustConn::new()?)?;
+//!
+//! let wm = GameMode::new()
+//!     .with_enter_hook(|_: &mut State<RustConn>, _: &RustConn| spawn("pkill picom"))
+//!     .with_exit_hook(|_: &mut State<RustConn>, _: &RustConn| spawn("picom -b"))
+//!     .add_to(wm);
+//! # Ok::<(), penrose::Error>(())
+//! ```
+//!
+//! Once a client has been detected it remains in game mode for as long as it stays focused and
+//! fullscreen, even if it temporarily releases its pointer grab (for example while showing an
+//! in-game menu).
+use crate::{
+    core::{hooks::StateHook, State, WindowManager},
+    x::{XConn, XConnExt},
+    Result, Xid,
+};
+use std::fmt;
+use tracing::{debug, error};
+
+/// A state extension for detecting focused fullscreen games and suspending positioning them.
+///
+/// See the module level docs for details.
+pub struct GameMode<X: XConn> {
+    active: Option<Xid>,
+    enter_hook: Option<Box<dyn StateHook<X>>>,
+    exit_hook: Option<Box<dyn StateHook<X>>>,
+}
+
+impl<X: XConn> fmt::Debug for GameMode<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GameMode")
+            .field("active", &self.active)
+            .field("enter_hook", &self.enter_hook.is_some())
+            .field("exit_hook", &self.exit_hook.is_some())
+            .finish()
+    }
+}
+
+impl<X: XConn> Default for GameMode<X> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<X: XConn> GameMode<X> {
+    /// Create a new [GameMode] with no enter or exit hooks.
+    pub fn new() -> Self {
+        Self {
+            active: None,
+            enter_hook: None,
+            exit_hook: None,
+        }
+    }
+
+    /// Run the given hook each time a game client is detected and game mode is entered.
+    pub fn with_enter_hook<H>(mut self, hook: H) -> Self
+    where
+        H: StateHook<X> + 'static,
+    {
+        self.enter_hook = Some(hook.boxed());
+
+        self
+    }
+
+    /// Run the given hook each time the game client loses focus or leaves fullscreen.
+    pub fn with_exit_hook<H>(mut self, hook: H) -> Self
+    where
+        H: StateHook<X> + 'static,
+    {
+        self.exit_hook = Some(hook.boxed());
+
+        self
+    }
+
+    /// The client currently running in game mode, if there is one.
+    pub fn active_client(&self) -> Option<Xid> {
+        self.active
+    }
+}
+
+impl<X: XConn + 'static> GameMode<X> {
+    /// Add this [GameMode] to the given [WindowManager] along with the refresh hook required to
+    /// run it.
+    pub fn add_to(self, mut wm: WindowManager<X>) -> WindowManager<X> {
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_refresh_hook(refresh_hook);
+
+        wm
+    }
+}
+
+// A focused fullscreen client is treated as a game if it has set override-redirect on its
+// window or is holding a grab on the pointer.
+fn is_game<X: XConn>(id: Xid, state: &State<X>, x: &X) -> Result<bool> {
+    if !state.is_fullscreen(&id) {
+        return Ok(false);
+    }
+
+    if x.get_window_attributes(id)?.override_redirect {
+        return Ok(true);
+    }
+
+    // Any pointer grab held while the user is dragging a window is our own
+    if state.held_mouse_state.is_some() {
+        return Ok(false);
+    }
+
+    x.pointer_is_grabbed()
+}
+
+/// Enter or exit game mode based on the currently focused client.
+pub fn refresh_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> Result<()> {
+    let s = state.extension::<GameMode<X>>()?;
+    let current = s.borrow().active;
+    let focused = state.client_set.current_client().copied();
+
+    let next = match (current, focused) {
+        (Some(id), Some(f)) if id == f && state.is_fullscreen(&id) => Some(id),
+        (_, Some(f)) if is_game(f, state, x)? => Some(f),
+        _ => None,
+    };
+
+    if next == current {
+        return Ok(());
+    }
+
+    s.borrow_mut().active = next;
+    let (mut enter_hook, mut exit_hook) = {
+        let mut game_mode = s.borrow_mut();
+        (game_mode.enter_hook.take(), game_mode.exit_hook.take())
+    };
+
+    if let Some(id) = current {
+        debug!(%id, "exiting game mode");
+        state.resume_positioning(&id);
+        if let Some(h) = exit_hook.as_mut() {
+            if let Err(e) = h.call(state, x) {
+                error!(%e, "error running game mode exit hook");
+            }
+        }
+    }
+
+    if let Some(id) = next {
+        debug!(%id, "entering game mode");
+        state.suspend_positioning(id);
+        if let Some(h) = enter_hook.as_mut() {
+            if let Err(e) = h.call(state, x) {
+                error!(%e, "error running game mode enter hook");
+            }
+        }
+    }
+
+    let mut game_mode = s.borrow_mut();
+    game_mode.enter_hook = enter_hook;
+    game_mode.exit_hook = exit_hook;
+    drop(game_mode);
+
+    // The previous game client was skipped when positioning clients during this refresh
+    if current.is_some() {
+        x.refresh(state)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builtin::actions::modify_with,
+        core::bindings::{KeyCode, KeyEventHandler},
+        extensions::actions::toggle_fullscreen,
+        pure::geometry::Rect,
+        x::TestConn,
+    };
+    use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+    const FULLSCREEN: KeyCode = KeyCode { mask: 0, code: 10 };
+    const FOCUS_DOWN: KeyCode = KeyCode { mask: 0, code: 11 };
+
+    fn wm(entered: Rc<Cell<usize>>, exited: Rc<Cell<usize>>) -> WindowManager<TestConn> {
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(FULLSCREEN, toggle_fullscreen());
+        bindings.insert(FOCUS_DOWN, modify_with(|cs| cs.focus_down()));
+
+        TestConn::new()
+            .with_screens(vec![Rect::new(0, 0, 1000, 800)])
+            .start_headless_wm(bindings, |wm| {
+                GameMode::new()
+                    .with_enter_hook(move |_: &mut State<TestConn>, _: &TestConn| {
+                        entered.set(entered.get() + 1);
+                        Ok(())
+                    })
+                    .with_exit_hook(move |_: &mut State<TestConn>, _: &TestConn| {
+                        exited.set(exited.get() + 1);
+                        Ok(())
+                    })
+                    .add_to(wm)
+            })
+    }
+
+    fn active(wm: &WindowManager<TestConn>) -> Option<Xid> {
+        let s = wm.state.extension::<GameMode<TestConn>>().unwrap();
+        let active = s.borrow().active_client();
+
+        active
+    }
+
+    #[test]
+    fn fullscreen_clients_without_a_grab_are_not_games() {
+        let (entered, exited) = (Rc::new(Cell::new(0)), Rc::new(Cell::new(0)));
+        let mut wm = wm(entered.clone(), exited.clone());
+
+        let id = wm.conn().spawn_client("mpv");
+        wm.conn().press_key(FULLSCREEN);
+        wm.run_pending_events();
+
+        assert!(wm.state.is_fullscreen(&id));
+        assert_eq!(active(&wm), None);
+        assert!(!wm.state.is_positioning_suspended(&id));
+        assert_eq!(entered.get(), 0);
+    }
+
+    #[test]
+    fn game_mode_is_entered_and_exited_with_focus() {
+        let (entered, exited) = (Rc::new(Cell::new(0)), Rc::new(Cell::new(0)));
+        let mut wm = wm(entered.clone(), exited.clone());
+
+        wm.conn().spawn_client("st");
+        let game = wm.conn().spawn_client("game");
+        wm.run_pending_events();
+
+        wm.conn().set_pointer_grabbed(true);
+        wm.conn().press_key(FULLSCREEN);
+        wm.run_pending_events();
+
+        assert_eq!(active(&wm), Some(game));
+        assert!(wm.state.is_positioning_suspended(&game));
+        assert_eq!((entered.get(), exited.get()), (1, 0));
+
+        wm.conn().press_key(FOCUS_DOWN);
+        wm.run_pending_events();
+
+        assert_eq!(active(&wm), None);
+        assert!(!wm.state.is_positioning_suspended(&game));
+        assert_eq!((entered.get(), exited.get()), (1, 1));
+    }
+}
@@ -0,0 +1,402 @@
+//! Running actions when the pointer hits the edge or corner of a screen.
+//!
+//! [HotEdges] places thin input only windows along the edges and in the corners of each screen
+//! and runs the [KeyEventHandler] bound to that edge or corner whenever the pointer enters one.
+//! Any action that can be bound to a key can be used, for example switching workspaces, opening
+//! an overview of the open windows or revealing an auto-hidden status bar:
+//!
+//! ```no_run
+//! use penrose::{
+//!     builtin::actions::modify_with,
+//!     core::{Config, WindowManager},
+//!     extensions::hooks::hot_edges::{HotEdge, HotEdges},
+//!     x11rb::RustConn,
+//! };
+//! use std::collections::HashMap;
+//!
+//! let conn = RustConn::new()?;
+//! let wm = WindowManager::new(Config::default(), HashMap::new(), HashMap::new(), conn)?;
+//!
+//! let wm = HotEdges::new()
+//!     .with_action(HotEdge::Left, modify_with(|cs| cs.focus_previous_workspace()))
+//!     .with_action(HotEdge::Right, modify_with(|cs| cs.focus_next_workspace()))
+//!     .with_action(HotEdge::TopLeft, modify_with(|cs| cs.toggle_tag()))
+//!     .add_to(wm)?;
+//!
+//! wm.run()
+//! # ;Ok::<(), penrose::Error>(())
+//! ```
+//!
+//! Edges that are shared with a neighbouring screen (along with the corners at either end of
+//! them) are skipped so that moving the pointer between screens does not trigger them. Actions
+//! are only run when the pointer first enters an edge: it needs to leave and come back again
+//! for the action to run a second time.
+//!
+//! > **NOTE**: The windows used to detect the pointer are kept above all other windows so they
+//! >           will intercept clicks on the outermost pixels of each screen, and on the
+//! >           `corner_size` x `corner_size` square in each corner that has an action.
+use crate::{
+    core::{bindings::KeyEventHandler, State, WindowManager},
+    pure::geometry::Rect,
+    x::{ClientAttr, ClientConfig, WinType, XConn, XEvent},
+    x11rb::RustConn,
+    Result, Xid,
+};
+use std::{cell::RefCell, collections::HashMap, fmt};
+use tracing::{error, trace};
+
+type Action<X> = Option<Box<dyn KeyEventHandler<X>>>;
+
+/// An edge or corner of a screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HotEdge {
+    /// The top edge of the screen
+    Top,
+    /// The bottom edge of the screen
+    Bottom,
+    /// The left edge of the screen
+    Left,
+    /// The right edge of the screen
+    Right,
+    /// The top left corner of the screen
+    TopLeft,
+    /// The top right corner of the screen
+    TopRight,
+    /// The bottom left corner of the screen
+    BottomLeft,
+    /// The bottom right corner of the screen
+    BottomRight,
+}
+
+impl HotEdge {
+    // The edges of the screen that this edge or corner lies on.
+    fn sides(&self) -> &'static [HotEdge] {
+        use HotEdge::*;
+
+        match self {
+            Top => &[Top],
+            Bottom => &[Bottom],
+            Left => &[Left],
+            Right => &[Right],
+            TopLeft => &[Top, Left],
+            TopRight => &[Top, Right],
+            BottomLeft => &[Bottom, Left],
+            BottomRight => &[Bottom, Right],
+        }
+    }
+
+    /// The region of the screen `r` covered by this edge or corner.
+    ///
+    /// Corners are squares of `corner_size` pixels and edges are a single pixel wide, running
+    /// between the corners at either end.
+    pub fn region(&self, r: Rect, corner_size: u32) -> Rect {
+        use HotEdge::*;
+
+        let c = corner_size.min(r.w / 2).min(r.h / 2).max(1);
+        let (right, bottom) = (r.x + r.w, r.y + r.h);
+
+        match self {
+            Top => Rect::new(r.x + c, r.y, r.w - 2 * c, 1),
+            Bottom => Rect::new(r.x + c, bottom - 1, r.w - 2 * c, 1),
+            Left => Rect::new(r.x, r.y + c, 1, r.h - 2 * c),
+            Right => Rect::new(right - 1, r.y + c, 1, r.h - 2 * c),
+            TopLeft => Rect::new(r.x, r.y, c, c),
+            TopRight => Rect::new(right - c, r.y, c, c),
+            BottomLeft => Rect::new(r.x, bottom - c, c, c),
+            BottomRight => Rect::new(right - c, bottom - c, c, c),
+        }
+    }
+
+    // Whether or not the given side of `r` borders any of the other screens.
+    fn is_shared(&self, r: &Rect, screens: &[Rect]) -> bool {
+        let overlaps = |a: u32, a_len: u32, b: u32, b_len: u32| a < b + b_len && b < a + a_len;
+
+        screens.iter().filter(|s| *s != r).any(|s| match self {
+            HotEdge::Top => s.y + s.h == r.y && overlaps(s.x, s.w, r.x, r.w),
+            HotEdge::Bottom => r.y + r.h == s.y && overlaps(s.x, s.w, r.x, r.w),
+            HotEdge::Left => s.x + s.w == r.x && overlaps(s.y, s.h, r.y, r.h),
+            HotEdge::Right => r.x + r.w == s.x && overlaps(s.y, s.h, r.y, r.h),
+            _ => false,
+        })
+    }
+}
+
+/// A state extension for running actions when the pointer hits the edge or corner of a screen.
+///
+/// See the module level docs for details.
+pub struct HotEdges<X: XConn> {
+    // Actions are removed while they run so that they are free to make use of the extension
+    actions: Vec<(HotEdge, Action<X>)>,
+    corner_size: u32,
+    conn: Option<RustConn>,
+    windows: HashMap<Xid, usize>,
+}
+
+impl<X: XConn> fmt::Debug for HotEdges<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HotEdges")
+            .field(
+                "edges",
+                &self.actions.iter().map(|(e, _)| e).collect::<Vec<_>>(),
+            )
+            .field("corner_size", &self.corner_size)
+            .field("windows", &self.windows)
+            .finish()
+    }
+}
+
+impl<X: XConn> Default for HotEdges<X> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<X: XConn> HotEdges<X> {
+    /// Create a new [HotEdges] with no actions and 5 pixel corners.
+    pub fn new() -> Self {
+        Self {
+            actions: Vec::new(),
+            corner_size: 5,
+            conn: None,
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Run the given action when the pointer enters `edge` on any screen.
+    pub fn with_action(mut self, edge: HotEdge, action: Box<dyn KeyEventHandler<X>>) -> Self {
+        self.actions.push((edge, Some(action)));
+
+        self
+    }
+
+    /// Set the size in pixels of the square used to detect the pointer in each corner.
+    pub fn with_corner_size(mut self, corner_size: u32) -> Self {
+        self.corner_size = corner_size;
+
+        self
+    }
+
+    // The index of the action and the region to cover for each edge or corner on the given
+    // screens, skipping those that border another screen.
+    fn regions(&self, screens: &[Rect]) -> Vec<(usize, Rect)> {
+        screens
+            .iter()
+            .flat_map(|r| {
+                self.actions
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (e, _))| !e.sides().iter().any(|s| s.is_shared(r, screens)))
+                    .map(|(i, (e, _))| (i, e.region(*r, self.corner_size)))
+            })
+            .collect()
+    }
+
+    fn create_windows(&mut self, x: &X) -> Result<()> {
+        let conn = match &self.conn {
+            Some(conn) => conn,
+            None => return Ok(()),
+        };
+
+        let screens = x.screen_details()?;
+        for (i, r) in self.regions(&screens) {
+            let id = conn.create_window(WinType::InputOnly, r, false)?;
+            // Selecting for events using the window manager connection means that events
+            // for the window will be passed to our event hook.
+            x.set_client_attributes(id, &[ClientAttr::UiEventMask])?;
+            conn.map(id)?;
+            conn.set_client_config(id, &[ClientConfig::StackTop])?;
+            self.windows.insert(id, i);
+        }
+        conn.flush();
+
+        Ok(())
+    }
+
+    fn destroy_windows(&mut self) {
+        if let Some(conn) = &self.conn {
+            for (id, _) in self.windows.drain() {
+                if let Err(e) = conn.destroy_window(id) {
+                    error!(%e, %id, "unable to destroy hot edge window");
+                }
+            }
+        }
+    }
+
+    // Newly mapped clients are stacked above the hot edge windows so we need to move them back
+    // to the top of the stack.
+    fn raise_windows(&self) -> Result<()> {
+        if let Some(conn) = &self.conn {
+            for &id in self.windows.keys() {
+                conn.set_client_config(id, &[ClientConfig::StackTop])?;
+            }
+            conn.flush();
+        }
+
+        Ok(())
+    }
+}
+
+impl<X: XConn + 'static> HotEdges<X> {
+    /// Add this [HotEdges] to the given [WindowManager] along with the hooks required to run it.
+    ///
+    /// This opens a new connection to the X server for managing the windows used to detect
+    /// the pointer.
+    pub fn add_to(mut self, mut wm: WindowManager<X>) -> Result<WindowManager<X>> {
+        self.conn = Some(RustConn::new()?);
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_startup_hook(startup_hook);
+        wm.state.config.compose_or_set_event_hook(event_hook);
+        wm.state.config.compose_or_set_refresh_hook(refresh_hook);
+
+        Ok(wm)
+    }
+}
+
+/// Create the windows used to detect the pointer on each screen.
+pub fn startup_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> Result<()> {
+    state
+        .extension::<HotEdges<X>>()?
+        .borrow_mut()
+        .create_windows(x)
+}
+
+/// Run the action for an edge or corner when the pointer enters it, and recreate the edges
+/// when the screens change.
+pub fn event_hook<X: XConn + 'static>(event: &XEvent, state: &mut State<X>, x: &X) -> Result<bool> {
+    let s = state.extension::<HotEdges<X>>()?;
+
+    let id = match event {
+        XEvent::Enter(p) => p.id,
+        XEvent::RandrNotify => return recreate_windows(&s, x),
+        XEvent::ConfigureNotify(e) if e.is_root => return recreate_windows(&s, x),
+        _ => return Ok(true),
+    };
+
+    let ix = match s.borrow().windows.get(&id) {
+        Some(&ix) => ix,
+        None => return Ok(true),
+    };
+
+    let (edge, action) = {
+        let mut edges = s.borrow_mut();
+        let (edge, action) = &mut edges.actions[ix];
+        (*edge, action.take())
+    };
+
+    if let Some(mut action) = action {
+        trace!(?edge, "running hot edge action");
+        let res = action.call(state, x);
+        s.borrow_mut().actions[ix].1 = Some(action);
+        res?;
+    }
+
+    Ok(false)
+}
+
+fn recreate_windows<X: XConn>(s: &RefCell<HotEdges<X>>, x: &X) -> Result<bool> {
+    let mut edges = s.borrow_mut();
+    edges.destroy_windows();
+    edges.create_windows(x)?;
+
+    Ok(true)
+}
+
+/// Keep the windows used to detect the pointer above all other windows.
+pub fn refresh_hook<X: XConn + 'static>(state: &mut State<X>, _: &X) -> Result<()> {
+    state.extension::<HotEdges<X>>()?.borrow().raise_windows()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builtin::actions::key_handler,
+        core::Config,
+        pure::geometry::Point,
+        x::{event::PointerChange, TestConn},
+    };
+    use simple_test_case::test_case;
+    use std::{cell::Cell, rc::Rc};
+
+    const SCREEN: Rect = Rect {
+        x: 0,
+        y: 0,
+        w: 1000,
+        h: 800,
+    };
+
+    #[test_case(HotEdge::Top, Rect::new(5, 0, 990, 1); "top")]
+    #[test_case(HotEdge::Bottom, Rect::new(5, 799, 990, 1); "bottom")]
+    #[test_case(HotEdge::Left, Rect::new(0, 5, 1, 790); "left")]
+    #[test_case(HotEdge::Right, Rect::new(999, 5, 1, 790); "right")]
+    #[test_case(HotEdge::TopLeft, Rect::new(0, 0, 5, 5); "top left")]
+    #[test_case(HotEdge::BottomRight, Rect::new(995, 795, 5, 5); "bottom right")]
+    #[test]
+    fn region(edge: HotEdge, expected: Rect) {
+        assert_eq!(edge.region(SCREEN, 5), expected);
+    }
+
+    #[test]
+    fn edges_between_screens_are_skipped() {
+        let screens = [SCREEN, Rect::new(1000, 0, 1000, 800)];
+        let edges: HotEdges<TestConn> = [HotEdge::Left, HotEdge::Right, HotEdge::TopRight]
+            .into_iter()
+            .fold(HotEdges::new(), |edges, e| {
+                edges.with_action(
+                    e,
+                    key_handler(|_: &mut State<TestConn>, _: &TestConn| Ok(())),
+                )
+            });
+
+        let regions = edges.regions(&screens);
+
+        assert_eq!(
+            regions,
+            vec![
+                (0, HotEdge::Left.region(screens[0], 5)),
+                (1, HotEdge::Right.region(screens[1], 5)),
+                (2, HotEdge::TopRight.region(screens[1], 5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn entering_an_edge_runs_its_action() {
+        let calls = Rc::new(Cell::new(0));
+        let c = calls.clone();
+        let mut edges = HotEdges::new().with_action(
+            HotEdge::Top,
+            key_handler(move |_: &mut State<TestConn>, _: &TestConn| {
+                c.set(c.get() + 1);
+                Ok(())
+            }),
+        );
+        edges.windows.insert(Xid(42), 0);
+
+        let x = TestConn::new();
+        let mut wm = WindowManager::new(
+            Config::default(),
+            HashMap::new(),
+            HashMap::new(),
+            TestConn::new(),
+        )
+        .unwrap();
+        wm.state.add_extension(edges);
+
+        let enter = |id| {
+            XEvent::Enter(PointerChange {
+                id,
+                abs: Point::new(0, 0),
+                relative: Point::new(0, 0),
+                same_screen: true,
+            })
+        };
+
+        let handled = event_hook(&enter(Xid(42)), &mut wm.state, &x).unwrap();
+        let ignored = event_hook(&enter(Xid(7)), &mut wm.state, &x).unwrap();
+
+        assert!(!handled);
+        assert!(ignored);
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -0,0 +1,344 @@
+//! Running hooks when the user has been idle for a period of time.
+//!
+//! [IdleHooks] uses the X [ScreenSaver extension][1] to track how long it has been since the
+//! last keyboard or mouse input and runs [StateHook]s once configurable idle thresholds have
+//! been passed, along with an optional hook that is run when activity resumes. This can be used
+//! to dim the screen, lock the session or pause notifications directly from your config:
+//!
+//! ```no_run
+//! use penrose::{
+//!     core::{Config, State, WindowManager},
+//!     extensions::hooks::idle::IdleHooks,
+//!     util::spawn,
+//!     x11rb::RustConn,
+//! };
+//! use std::{collections::HashMap, time::Duration};
+//!
+//! let conn = RustConn::new()?;
+//! let wm = WindowManager::new(Config::default(), HashMap::new(), HashMap::new(), conn)?;
+//!
+//! let wm = IdleHooks::new()
+//!     .with_idle_hook(Duration::from_secs(120), |_: &mut State<RustConn>, _: &RustConn| {
+//!         spawn("brightnessctl set 20%")
+//!     })
+//!     .with_idle_hook(Duration::from_secs(300), |_: &mut State<RustConn>, _: &RustConn| {
+//!         spawn("slock")
+//!     })
+//!     .with_resume_hook(|_: &mut State<RustConn>, _: &RustConn| {
+//!         spawn("brightnessctl set 100%")
+//!     })
+//!     .add_to(wm)?;
+//!
+//! wm.run()
+//! # ;Ok::<(), penrose::Error>(())
+//! ```
+//!
+//! The idle time is polled from a background thread (once a second by default) so hooks may run
+//! up to one poll interval after their threshold has passed.
+//!
+//!   [1]: https://www.x.org/releases/X11R7.7/doc/scrnsaverproto/saver.html
+use crate::{
+    core::{hooks::StateHook, State, WindowManager},
+    x::{XConn, XEvent},
+    x11rb::Waker,
+    Result,
+};
+use std::{
+    fmt, mem,
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+use tracing::error;
+use x11rb::{connection::Connection, protocol::screensaver::ConnectionExt};
+
+// The type of the client message sent to the root window in order to wake up the window manager
+// event loop when the idle state changes.
+const WAKEUP_ATOM: &str = "_PENROSE_IDLE_WAKEUP";
+
+/// A change in the idle state of the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IdleEvent {
+    /// The idle threshold at the given index has been passed
+    Idle(usize),
+    /// There has been user input after at least one idle threshold was passed
+    Resume,
+}
+
+/// Tracks which idle thresholds have been passed based on the current idle time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IdleTracker {
+    thresholds: Vec<Duration>,
+    passed: usize,
+}
+
+impl IdleTracker {
+    /// `thresholds` must be sorted in ascending order.
+    fn new(thresholds: Vec<Duration>) -> Self {
+        Self {
+            thresholds,
+            passed: 0,
+        }
+    }
+
+    fn update(&mut self, idle: Duration) -> Vec<IdleEvent> {
+        let mut events = Vec::new();
+        let now_passed = self.thresholds.iter().take_while(|&&t| idle >= t).count();
+
+        if now_passed < self.passed {
+            events.push(IdleEvent::Resume);
+            self.passed = 0;
+        }
+
+        events.extend((self.passed..now_passed).map(IdleEvent::Idle));
+        self.passed = now_passed;
+
+        events
+    }
+}
+
+/// A state extension for running hooks when the user has been idle for a period of time.
+///
+/// See the module level docs for details.
+pub struct IdleHooks<X: XConn> {
+    idle_hooks: Vec<(Duration, Box<dyn StateHook<X>>)>,
+    resume_hook: Option<Box<dyn StateHook<X>>>,
+    poll_interval: Duration,
+    rx: Option<Receiver<IdleEvent>>,
+}
+
+impl<X: XConn> fmt::Debug for IdleHooks<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdleHooks")
+            .field(
+                "thresholds",
+                &self.idle_hooks.iter().map(|(d, _)| d).collect::<Vec<_>>(),
+            )
+            .field("resume_hook", &self.resume_hook.is_some())
+            .field("poll_interval", &self.poll_interval)
+            .finish()
+    }
+}
+
+impl<X: XConn> Default for IdleHooks<X> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<X: XConn> IdleHooks<X> {
+    /// Create a new [IdleHooks] with no hooks that polls the idle time once a second.
+    pub fn new() -> Self {
+        Self {
+            idle_hooks: Vec::new(),
+            resume_hook: None,
+            poll_interval: Duration::from_secs(1),
+            rx: None,
+        }
+    }
+
+    /// Run the given hook once the user has been idle for at least `threshold`.
+    ///
+    /// Each hook is run at most once per idle period.
+    pub fn with_idle_hook<H>(mut self, threshold: Duration, hook: H) -> Self
+    where
+        H: StateHook<X> + 'static,
+    {
+        self.idle_hooks.push((threshold, hook.boxed()));
+        self.idle_hooks.sort_by_key(|(d, _)| *d);
+
+        self
+    }
+
+    /// Run the given hook when there is user input after at least one idle hook has been run.
+    pub fn with_resume_hook<H>(mut self, hook: H) -> Self
+    where
+        H: StateHook<X> + 'static,
+    {
+        self.resume_hook = Some(hook.boxed());
+
+        self
+    }
+
+    /// Set how often the idle time is checked.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+
+        self
+    }
+}
+
+impl<X: XConn + 'static> IdleHooks<X> {
+    /// Start polling the idle time and add this [IdleHooks] to the given [WindowManager] along
+    /// with the event hook required to run it.
+    pub fn add_to(mut self, mut wm: WindowManager<X>) -> Result<WindowManager<X>> {
+        let (tx, rx) = channel();
+        let tracker = IdleTracker::new(self.idle_hooks.iter().map(|(d, _)| *d).collect());
+        let waker = Waker::new(WAKEUP_ATOM)?;
+        let (conn, screen) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen].root;
+
+        // Check that the extension is available before starting the poll loop
+        conn.screensaver_query_info(root)?.reply()?;
+        let interval = self.poll_interval;
+        thread::spawn(move || {
+            if let Err(e) = poll_idle_time(conn, root, interval, tracker, tx, waker) {
+                error!(%e, "unable to query idle time: idle hooks will no longer run");
+            }
+        });
+
+        self.rx = Some(rx);
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_event_hook(event_hook);
+
+        Ok(wm)
+    }
+}
+
+fn poll_idle_time(
+    conn: impl Connection,
+    root: u32,
+    interval: Duration,
+    mut tracker: IdleTracker,
+    tx: Sender<IdleEvent>,
+    waker: Waker,
+) -> Result<()> {
+    loop {
+        let info = conn.screensaver_query_info(root)?.reply()?;
+        let events = tracker.update(Duration::from_millis(info.ms_since_user_input as u64));
+
+        if !events.is_empty() {
+            for e in events {
+                if tx.send(e).is_err() {
+                    return Ok(()); // The window manager has exited
+                }
+            }
+            waker.wake();
+        }
+
+        thread::sleep(interval);
+    }
+}
+
+/// Run any idle or resume hooks whose conditions have been met.
+pub fn event_hook<X: XConn + 'static>(event: &XEvent, state: &mut State<X>, x: &X) -> Result<bool> {
+    match event {
+        XEvent::ClientMessage(m) if m.dtype == WAKEUP_ATOM => (),
+        _ => return Ok(true),
+    }
+
+    let s = state.extension::<IdleHooks<X>>()?;
+    let events: Vec<IdleEvent> = match &s.borrow().rx {
+        Some(rx) => rx.try_iter().collect(),
+        None => return Ok(false),
+    };
+
+    // Hooks are removed while they run so that they are free to make use of the extension
+    // themselves.
+    let mut idle_hooks = mem::take(&mut s.borrow_mut().idle_hooks);
+    let mut resume_hook = s.borrow_mut().resume_hook.take();
+
+    for e in events {
+        let res = match e {
+            IdleEvent::Idle(ix) => match idle_hooks.get_mut(ix) {
+                Some((_, hook)) => hook.call(state, x),
+                None => Ok(()),
+            },
+            IdleEvent::Resume => match resume_hook.as_mut() {
+                Some(hook) => hook.call(state, x),
+                None => Ok(()),
+            },
+        };
+
+        if let Err(e) = res {
+            error!(%e, "error running idle hook");
+        }
+    }
+
+    let mut hooks = s.borrow_mut();
+    hooks.idle_hooks = idle_hooks;
+    hooks.resume_hook = resume_hook;
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::Config,
+        x::{
+            event::{ClientEventMask, ClientMessage},
+            TestConn,
+        },
+    };
+    use simple_test_case::test_case;
+    use std::collections::HashMap;
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    #[test_case(&[0, 5, 10], &[]; "no idle time")]
+    #[test_case(&[0, 60, 70], &[IdleEvent::Idle(0)]; "first threshold")]
+    #[test_case(&[0, 400], &[IdleEvent::Idle(0), IdleEvent::Idle(1)]; "both thresholds at once")]
+    #[test_case(&[60, 0], &[IdleEvent::Idle(0), IdleEvent::Resume]; "resume after idle")]
+    #[test_case(
+        &[300, 1, 60],
+        &[IdleEvent::Idle(0), IdleEvent::Idle(1), IdleEvent::Resume, IdleEvent::Idle(0)];
+        "idle again after resume"
+    )]
+    #[test]
+    fn tracker_events(idle: &[u64], expected: &[IdleEvent]) {
+        let mut tracker = IdleTracker::new(vec![secs(60), secs(300)]);
+        let events: Vec<IdleEvent> = idle.iter().flat_map(|&s| tracker.update(secs(s))).collect();
+
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn hooks_are_run_on_wakeup() {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            HashMap::new(),
+            HashMap::new(),
+            TestConn::new(),
+        )
+        .unwrap();
+        wm.start_headless().unwrap();
+
+        let (tx, rx) = channel();
+        let mut hooks = IdleHooks::new()
+            .with_idle_hook(secs(60), |s: &mut State<TestConn>, _: &TestConn| {
+                s.client_set.focus_tag("2");
+                Ok(())
+            })
+            .with_resume_hook(|s: &mut State<TestConn>, _: &TestConn| {
+                s.client_set.focus_tag("3");
+                Ok(())
+            });
+        hooks.rx = Some(rx);
+        wm.state.add_extension(hooks);
+        wm.state.config.compose_or_set_event_hook(event_hook);
+
+        let root = wm.state.root();
+        let wakeup = || {
+            XEvent::ClientMessage(ClientMessage::new(
+                root,
+                ClientEventMask::SubstructureNotify,
+                WAKEUP_ATOM,
+                [0u32; 5].into(),
+            ))
+        };
+
+        tx.send(IdleEvent::Idle(0)).unwrap();
+        wm.conn().push_event(wakeup());
+        wm.run_pending_events();
+        assert_eq!(wm.state.client_set.current_tag(), "2");
+
+        tx.send(IdleEvent::Resume).unwrap();
+        wm.conn().push_event(wakeup());
+        wm.run_pending_events();
+        assert_eq!(wm.state.client_set.current_tag(), "3");
+    }
+}
@@ -4,7 +4,10 @@
 //! itself when the manage hook is called.
 use crate::{
     core::{hooks::ManageHook, State},
-    pure::geometry::{Rect, RelativeRect},
+    pure::{
+        geometry::{Rect, RelativeRect},
+        FocusPolicy, Position,
+    },
     x::{Query, XConn},
     Result, Xid,
 };
@@ -101,6 +104,33 @@ impl<X: XConn> ManageHook<X> for FloatingRelative {
     }
 }
 
+/// Move the specified client to the given [Position] within the stack of its workspace,
+/// overriding [Config::insert_point][crate::core::Config::insert_point].
+#[derive(Debug)]
+pub struct InsertAt(pub Position);
+impl<X: XConn> ManageHook<X> for InsertAt {
+    fn call(&mut self, client: Xid, state: &mut State<X>, _: &X) -> Result<()> {
+        state.client_set.move_client_to_position(&client, self.0);
+        Ok(())
+    }
+}
+
+/// Move the specified client to the given [Position] within the stack of its workspace,
+/// either giving it focus or retaining the existing focus depending on the [FocusPolicy].
+///
+/// Using [FocusPolicy::Keep] allows for opening windows in the background without them
+/// stealing focus from the client you are currently working in.
+#[derive(Debug)]
+pub struct InsertAtWithFocus(pub Position, pub FocusPolicy);
+impl<X: XConn> ManageHook<X> for InsertAtWithFocus {
+    fn call(&mut self, client: Xid, state: &mut State<X>, _: &X) -> Result<()> {
+        state
+            .client_set
+            .move_client_to_position_with_focus(&client, self.0, self.1);
+        Ok(())
+    }
+}
+
 /// Move the specified client to the named workspace.
 #[derive(Debug)]
 pub struct SetWorkspace(pub &'static str);
@@ -1,9 +1,19 @@
 //! Hook implementations and helpers for adding to your Penrose window manager
 pub mod default_workspaces;
 pub mod ewmh;
+pub mod floating_geometry;
+pub mod game_mode;
+#[cfg(feature = "x11rb")]
+pub mod hot_edges;
+#[cfg(feature = "x11rb")]
+pub mod idle;
 pub mod manage;
 pub mod named_scratchpads;
+pub mod notifications;
+pub mod output_tags;
 pub mod startup;
+pub mod tag_pinning;
+pub mod wallpaper;
 pub mod window_swallowing;
 
 pub use ewmh::add_ewmh_hooks;
@@ -0,0 +1,388 @@
+//! Controlling where notification windows are shown and hiding them in do-not-disturb mode.
+//!
+//! Notification daemons such as `dunst` typically display their notifications using
+//! override-redirect windows that are never managed by the window manager, meaning that they
+//! are shown wherever the daemon decides and can not be hidden when you want to focus.
+//! [NotificationPolicy] detects these windows when they are mapped and can move them to a corner
+//! of the focused screen, suppress them entirely while do-not-disturb mode is active and run a
+//! hook each time one appears:
+//!
+//! ```no_run
+//! use penrose::{
+//!     core::{bindings::KeyEventHandler, Config, State, WindowManager},
+//!     extensions::hooks::notifications::{toggle_do_not_disturb, Corner, NotificationPolicy},
+//!     x11rb::RustConn,
+//!     Xid,
+//! };
+//! use std::collections::HashMap;
+//! use tracing::info;
+//!
+//! let mut raw_bindings: HashMap<String, Box<dyn KeyEventHandler<RustConn>>> = HashMap::new();
+//! raw_bindings.insert("M-n".to_owned(), toggle_do_not_disturb());
+//!
+//! # let key_bindings = penrose::core::bindings::parse_keybindings_with_xmodmap(raw_bindings)?;
+//! let conn = RustConn::new()?;
+//! let wm = WindowManager::new(Config::default(), key_bindings, HashMap::new(), conn)?;
+//! let wm = NotificationPolicy::new()
+//!     .with_corner(Corner::BottomRight, 10)
+//!     .with_notification_hook(|id: Xid, _: &mut State<RustConn>, _: &RustConn| {
+//!         info!(%id, "new notification");
+//!         Ok(())
+//!     })
+//!     .add_to(wm);
+//! # Ok::<(), penrose::Error>(())
+//! ```
+//!
+//! By default, notification windows are identified by having a `_NET_WM_WINDOW_TYPE` of
+//! `_NET_WM_WINDOW_TYPE_NOTIFICATION`. Notification windows that are not override-redirect are
+//! left unmanaged so that they do not take focus or affect the layout of the focused workspace.
+//! Windows created by penrose itself (such as the popups provided by `penrose_ui`) are identified
+//! by their `_NET_WM_PID` and are never treated as notifications.
+use crate::{
+    builtin::actions::key_handler,
+    core::{bindings::KeyEventHandler, hooks::ManageHook, State, WindowManager},
+    pure::geometry::Rect,
+    x::{query::WindowType, Atom, ClientConfig, Query, XConn, XConnExt, XEvent},
+    Result, Xid,
+};
+use std::{collections::HashSet, fmt, process};
+use tracing::{debug, error};
+
+/// A corner of the screen to display notifications in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Corner {
+    /// The top left corner of the screen
+    TopLeft,
+    /// The top right corner of the screen
+    #[default]
+    TopRight,
+    /// The bottom left corner of the screen
+    BottomLeft,
+    /// The bottom right corner of the screen
+    BottomRight,
+}
+
+/// A state extension for positioning and suppressing notification windows.
+///
+/// See the module level docs for details.
+pub struct NotificationPolicy<X: XConn> {
+    query: Box<dyn Query<X>>,
+    corner: Option<(Corner, u32)>,
+    dnd: bool,
+    visible: HashSet<Xid>,
+    suppressed: HashSet<Xid>,
+    hook: Option<Box<dyn ManageHook<X>>>,
+}
+
+impl<X: XConn> fmt::Debug for NotificationPolicy<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NotificationPolicy")
+            .field("query", &self.query)
+            .field("corner", &self.corner)
+            .field("dnd", &self.dnd)
+            .field("visible", &self.visible)
+            .field("suppressed", &self.suppressed)
+            .field("hook", &self.hook.is_some())
+            .finish()
+    }
+}
+
+impl<X: XConn> Default for NotificationPolicy<X> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<X: XConn> NotificationPolicy<X> {
+    /// Create a new [NotificationPolicy] that leaves notifications where they are placed by the
+    /// program showing them.
+    pub fn new() -> Self {
+        Self {
+            query: Box::new(WindowType(&[Atom::NetWindowTypeNotification])),
+            corner: None,
+            dnd: false,
+            visible: HashSet::new(),
+            suppressed: HashSet::new(),
+            hook: None,
+        }
+    }
+
+    /// Use the given [Query] to identify notification windows in place of checking their
+    /// window type.
+    pub fn with_query<Q>(mut self, query: Q) -> Self
+    where
+        Q: Query<X> + 'static,
+    {
+        self.query = Box::new(query);
+
+        self
+    }
+
+    /// Move notifications to the given corner of the focused screen, `gap` pixels from its
+    /// edges.
+    pub fn with_corner(mut self, corner: Corner, gap: u32) -> Self {
+        self.corner = Some((corner, gap));
+
+        self
+    }
+
+    /// Run the given hook each time a notification window is shown.
+    ///
+    /// The hook is not run for notifications that are suppressed while do-not-disturb mode is
+    /// active.
+    pub fn with_notification_hook<H>(mut self, hook: H) -> Self
+    where
+        H: ManageHook<X> + 'static,
+    {
+        self.hook = Some(hook.boxed());
+
+        self
+    }
+
+    /// Whether or not do-not-disturb mode is currently active.
+    pub fn is_dnd(&self) -> bool {
+        self.dnd
+    }
+
+    fn is_notification(&self, id: Xid, x: &X) -> bool {
+        x.window_pid(id) != Some(process::id()) && self.query.run(id, x).unwrap_or(false)
+    }
+
+    fn position(&self, id: Xid, state: &State<X>, x: &X) -> Result<()> {
+        let (corner, gap) = match self.corner {
+            Some(c) => c,
+            None => return Ok(()),
+        };
+
+        let r = x.client_geometry(id)?;
+        let s = state.client_set.current_screen().geometry();
+        let px = match corner {
+            Corner::TopLeft | Corner::BottomLeft => s.x + gap,
+            Corner::TopRight | Corner::BottomRight => (s.x + s.w).saturating_sub(gap + r.w),
+        };
+        let py = match corner {
+            Corner::TopLeft | Corner::TopRight => s.y + gap,
+            Corner::BottomLeft | Corner::BottomRight => (s.y + s.h).saturating_sub(gap + r.h),
+        };
+
+        x.set_client_config(id, &[ClientConfig::Position(Rect::new(px, py, r.w, r.h))])
+    }
+}
+
+impl<X: XConn + 'static> NotificationPolicy<X> {
+    /// Add this [NotificationPolicy] to the given [WindowManager] along with the event hook
+    /// required to run it.
+    pub fn add_to(self, mut wm: WindowManager<X>) -> WindowManager<X> {
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_event_hook(event_hook);
+
+        wm
+    }
+}
+
+/// Toggle do-not-disturb mode for the [NotificationPolicy] added to this window manager.
+///
+/// While do-not-disturb mode is active all notification windows are hidden. Any that are still
+/// open when it is disabled are shown again.
+pub fn toggle_do_not_disturb<X: XConn + 'static>() -> Box<dyn KeyEventHandler<X>> {
+    key_handler(|state: &mut State<X>, x: &X| {
+        let s = state.extension::<NotificationPolicy<X>>()?;
+        let mut policy = s.borrow_mut();
+        policy.dnd = !policy.dnd;
+        debug!(dnd = policy.dnd, "toggling do-not-disturb mode");
+
+        let (hide, ids): (bool, Vec<Xid>) = if policy.dnd {
+            (true, policy.visible.drain().collect())
+        } else {
+            (false, policy.suppressed.drain().collect())
+        };
+
+        for id in ids {
+            let res = if hide {
+                policy.suppressed.insert(id);
+                x.unmap(id)
+            } else {
+                x.map(id)
+            };
+
+            if let Err(e) = res {
+                error!(%e, %id, "unable to update notification window");
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Position, suppress and run the notification hook for notification windows as they are
+/// mapped.
+pub fn event_hook<X: XConn + 'static>(event: &XEvent, state: &mut State<X>, x: &X) -> Result<bool> {
+    let s = state.extension::<NotificationPolicy<X>>()?;
+
+    match *event {
+        // Notifications that are not override-redirect are handled here rather than being
+        // managed: they will generate a MapNotify once they are mapped.
+        XEvent::MapRequest(id) => {
+            let policy = s.borrow();
+            if state.client_set.contains(&id)
+                || x.get_window_attributes(id)?.override_redirect
+                || !policy.is_notification(id, x)
+            {
+                return Ok(true);
+            }
+            drop(policy);
+
+            if s.borrow().dnd {
+                s.borrow_mut().suppressed.insert(id);
+            } else {
+                s.borrow().position(id, state, x)?;
+                x.map(id)?;
+            }
+
+            Ok(false)
+        }
+
+        XEvent::MapNotify(id) => {
+            let mut policy = s.borrow_mut();
+            if state.client_set.contains(&id) || !policy.is_notification(id, x) {
+                return Ok(true);
+            }
+
+            if policy.dnd {
+                debug!(%id, "suppressing notification window");
+                policy.suppressed.insert(id);
+                x.unmap(id)?;
+                return Ok(true);
+            }
+
+            policy.position(id, state, x)?;
+            policy.visible.insert(id);
+            let mut hook = policy.hook.take();
+            drop(policy);
+
+            if let Some(h) = hook.as_mut() {
+                if let Err(e) = h.call(id, state, x) {
+                    error!(%e, "error running notification hook");
+                }
+            }
+            s.borrow_mut().hook = hook;
+
+            Ok(true)
+        }
+
+        XEvent::UnmapNotify(id) => {
+            s.borrow_mut().visible.remove(&id);
+            Ok(true)
+        }
+
+        XEvent::Destroy(id) => {
+            let mut policy = s.borrow_mut();
+            policy.visible.remove(&id);
+            policy.suppressed.remove(&id);
+            Ok(true)
+        }
+
+        _ => Ok(true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::bindings::KeyCode,
+        x::{Prop, TestConn},
+    };
+    use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+    const DND: KeyCode = KeyCode { mask: 0, code: 10 };
+
+    fn wm(shown: Rc<Cell<usize>>) -> WindowManager<TestConn> {
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(DND, toggle_do_not_disturb());
+
+        TestConn::new()
+            .with_screens(vec![Rect::new(0, 0, 1000, 800)])
+            .start_headless_wm(bindings, |wm| {
+                NotificationPolicy::new()
+                    .with_corner(Corner::BottomRight, 10)
+                    .with_notification_hook(move |_: Xid, _: &mut State<TestConn>, _: &TestConn| {
+                        shown.set(shown.get() + 1);
+                        Ok(())
+                    })
+                    .add_to(wm)
+            })
+    }
+
+    // Override-redirect windows map themselves so we need to simulate the resulting MapNotify
+    fn show_notification(wm: &mut WindowManager<TestConn>) -> Xid {
+        show_notification_with_pid(wm, None)
+    }
+
+    fn show_notification_with_pid(wm: &mut WindowManager<TestConn>, pid: Option<u32>) -> Xid {
+        let id = wm.conn().spawn_override_redirect("dunst");
+        let notification = Prop::Atom(vec![Atom::NetWindowTypeNotification.as_ref().to_owned()]);
+        wm.conn()
+            .set_prop(id, Atom::NetWmWindowType.as_ref(), notification)
+            .unwrap();
+        if let Some(pid) = pid {
+            wm.conn()
+                .set_prop(id, "_NET_WM_PID", Prop::Cardinal(vec![pid]))
+                .unwrap();
+        }
+        wm.conn().map(id).unwrap();
+        wm.conn().push_event(XEvent::MapNotify(id));
+        wm.run_pending_events();
+
+        id
+    }
+
+    #[test]
+    fn notifications_are_moved_to_the_configured_corner() {
+        let shown = Rc::new(Cell::new(0));
+        let mut wm = wm(shown.clone());
+
+        let id = show_notification(&mut wm);
+        let r = wm.conn().client_geometry(id).unwrap();
+
+        assert_eq!((r.x + r.w, r.y + r.h), (990, 790));
+        assert!(wm.conn().is_mapped(id));
+        assert!(!wm.state.client_set.contains(&id));
+        assert_eq!(shown.get(), 1);
+    }
+
+    #[test]
+    fn notifications_are_suppressed_in_dnd_mode() {
+        let shown = Rc::new(Cell::new(0));
+        let mut wm = wm(shown.clone());
+        let visible = show_notification(&mut wm);
+
+        wm.conn().press_key(DND);
+        wm.run_pending_events();
+        assert!(!wm.conn().is_mapped(visible));
+
+        let id = show_notification(&mut wm);
+        assert!(!wm.conn().is_mapped(id));
+        assert_eq!(shown.get(), 1);
+
+        wm.conn().press_key(DND);
+        wm.run_pending_events();
+        assert!(wm.conn().is_mapped(visible));
+        assert!(wm.conn().is_mapped(id));
+    }
+
+    #[test]
+    fn windows_created_by_penrose_are_not_notifications() {
+        let shown = Rc::new(Cell::new(0));
+        let mut wm = wm(shown.clone());
+
+        let other = show_notification_with_pid(&mut wm, Some(process::id() + 1));
+        let own = show_notification_with_pid(&mut wm, Some(process::id()));
+        let r = wm.conn().client_geometry(own).unwrap();
+
+        assert_ne!((r.x + r.w, r.y + r.h), (990, 790));
+        assert!(wm.conn().is_mapped(own));
+        assert!(wm.conn().is_mapped(other));
+        assert_eq!(shown.get(), 1);
+    }
+}
@@ -0,0 +1,196 @@
+//! Pinning workspace tags to monitors by the name of their RandR output.
+//!
+//! [OutputTags] keeps track of which tag should be shown on each named output (for example
+//! "eDP-1" for a laptop panel or "DP-1" for an external monitor) and restores that distribution
+//! of tags on startup and whenever monitors are connected or disconnected. This means that
+//! docking and undocking a laptop puts your preferred workspaces back where you expect them.
+//! The names of the outputs available on your system can be found by running `xrandr`.
+//!
+//! ```no_run
+//! use penrose::{
+//!     core::{Config, WindowManager},
+//!     extensions::hooks::output_tags::OutputTags,
+//!     x11rb::RustConn,
+//! };
+//! use std::collections::HashMap;
+//!
+//! let wm = WindowManager::new(Config::default(), HashMap::new(), HashMap::new(), RustConn::new()?)?;
+//!
+//! let wm = OutputTags::new()
+//!     .with_tag("eDP-1", "1")
+//!     .with_tag("DP-1", "9")
+//!     .add_to(wm);
+//! # Ok::<(), penrose::Error>(())
+//! ```
+use crate::{
+    core::{State, WindowManager},
+    x::{XConn, XConnExt, XEvent},
+    Result,
+};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// A state extension for pinning workspace tags to named RandR outputs.
+///
+/// See the module level docs for details.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputTags {
+    tags: HashMap<String, String>,
+    pending: bool,
+}
+
+impl Default for OutputTags {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OutputTags {
+    /// Create a new [OutputTags] without any tags pinned to outputs.
+    pub fn new() -> Self {
+        Self {
+            tags: HashMap::new(),
+            pending: true,
+        }
+    }
+
+    /// Show the given tag on the named output whenever it is connected.
+    pub fn with_tag(mut self, output: impl Into<String>, tag: impl Into<String>) -> Self {
+        self.tags.insert(output.into(), tag.into());
+
+        self
+    }
+
+    /// The tag pinned to the named output, if there is one.
+    pub fn tag_for_output(&self, output: &str) -> Option<&str> {
+        self.tags.get(output).map(|s| s.as_str())
+    }
+
+    /// Add this [OutputTags] to the given [WindowManager] along with the event and refresh
+    /// hooks required to run it.
+    pub fn add_to<X: XConn + 'static>(self, mut wm: WindowManager<X>) -> WindowManager<X> {
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_event_hook(event_hook);
+        wm.state.config.compose_or_set_refresh_hook(refresh_hook);
+
+        wm
+    }
+}
+
+/// Mark the pinned tags as needing to be restored when the connected screens change.
+pub fn event_hook<X: XConn + 'static>(event: &XEvent, state: &mut State<X>, _: &X) -> Result<bool> {
+    if matches!(event, XEvent::RandrNotify)
+        || matches!(event, XEvent::ConfigureNotify(e) if e.is_root)
+    {
+        state.extension::<OutputTags>()?.borrow_mut().pending = true;
+    }
+
+    Ok(true)
+}
+
+/// Move pinned tags back to their outputs following a change in the connected screens.
+pub fn refresh_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> Result<()> {
+    let s = state.extension::<OutputTags>()?;
+    let mut output_tags = s.borrow_mut();
+    if !output_tags.pending {
+        return Ok(());
+    }
+    output_tags.pending = false;
+
+    let cs = &mut state.client_set;
+    let current = cs.current_screen().index();
+    let mut moved = false;
+
+    for (index, name) in x.screen_names()?.into_iter().enumerate() {
+        let tag = match output_tags.tags.get(&name) {
+            Some(tag) if cs.contains_tag(tag) => tag,
+            _ => continue,
+        };
+
+        let on_screen = cs
+            .screens()
+            .any(|s| s.index() == index && &s.workspace.tag == tag);
+        if !on_screen {
+            debug!(%name, %tag, "restoring tag to its output");
+            cs.focus_screen(index);
+            cs.pull_tag_to_screen(tag);
+            moved = true;
+        }
+    }
+    drop(output_tags);
+
+    if moved {
+        state.client_set.focus_screen(current);
+        x.refresh(state)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pure::geometry::Rect, x::TestConn};
+
+    const LEFT: Rect = Rect::new(0, 0, 1000, 800);
+    const RIGHT: Rect = Rect::new(1000, 0, 1000, 800);
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn visible_tags(wm: &WindowManager<TestConn>) -> Vec<String> {
+        let mut screens: Vec<_> = wm.state.client_set.screens().collect();
+        screens.sort_by_key(|s| s.index());
+
+        screens.iter().map(|s| s.workspace.tag.clone()).collect()
+    }
+
+    fn wm(conn: TestConn) -> WindowManager<TestConn> {
+        conn.start_headless_wm(HashMap::new(), |wm| {
+            OutputTags::new()
+                .with_tag("eDP-1", "3")
+                .with_tag("DP-1", "9")
+                .add_to(wm)
+        })
+    }
+
+    #[test]
+    fn pinned_tags_are_shown_on_startup() {
+        let conn = TestConn::new()
+            .with_screens(vec![LEFT, RIGHT])
+            .with_screen_names(names(&["DP-1", "HDMI-1"]));
+
+        let wm = wm(conn);
+
+        assert_eq!(visible_tags(&wm), vec!["9", "2"]);
+        assert_eq!(wm.state.client_set.current_screen().index(), 0);
+    }
+
+    #[test]
+    fn pinned_tags_are_restored_when_screens_change() {
+        let conn = TestConn::new()
+            .with_screens(vec![LEFT])
+            .with_screen_names(names(&["eDP-1"]));
+        let mut wm = wm(conn);
+        assert_eq!(visible_tags(&wm), vec!["3"]);
+
+        wm.conn().set_screen_names(names(&["eDP-1", "DP-1"]));
+        wm.conn().set_screens(vec![LEFT, RIGHT]);
+        wm.run_pending_events();
+
+        assert_eq!(visible_tags(&wm), vec!["3", "9"]);
+    }
+
+    #[test]
+    fn unknown_tags_are_ignored() {
+        let conn = TestConn::new()
+            .with_screens(vec![LEFT])
+            .with_screen_names(names(&["eDP-1"]));
+        let wm = conn.start_headless_wm(HashMap::new(), |wm| {
+            OutputTags::new().with_tag("eDP-1", "missing").add_to(wm)
+        });
+
+        assert_eq!(visible_tags(&wm), vec!["1"]);
+    }
+}
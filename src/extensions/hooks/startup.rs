@@ -1,11 +1,19 @@
 //! Startup hooks for direct adding to your penrose config.
 use crate::{
-    core::{hooks::StateHook, State},
-    util::spawn,
-    x::XConn,
+    core::{hooks::StateHook, State, WindowManager},
+    util::{spawn, SpawnSpec},
+    x::{Atom, Prop, XConn},
     Result,
 };
-use std::borrow::Cow;
+use nix::{
+    sys::signal::{kill, Signal},
+    unistd::Pid,
+};
+use std::{borrow::Cow, collections::HashMap};
+use tracing::{debug, warn};
+
+/// The property on the root window used by [Autostart] to record the programs it has spawned.
+pub const AUTOSTART_PROP: &str = "_PENROSE_AUTOSTART";
 
 /// Spawn a client program on window manager startup
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -36,3 +44,273 @@ where
         spawn(self.prog.as_ref())
     }
 }
+
+#[derive(Debug, Clone)]
+struct AutostartEntry {
+    name: String,
+    spec: SpawnSpec,
+    class: Option<String>,
+}
+
+/// A state extension for spawning programs on startup without duplicating them when penrose
+/// is restarted in place.
+///
+/// The process ID of each program spawned is recorded in the [AUTOSTART_PROP] property on the
+/// root window, which persists for as long as the X server is running. On startup, programs
+/// are skipped if their recorded process is still running or (if a window class has been
+/// given for the program) if a window with that class already exists.
+///
+/// ```no_run
+/// use penrose::{
+///     core::{Config, WindowManager},
+///     extensions::hooks::startup::Autostart,
+///     x11rb::RustConn,
+/// };
+/// use std::collections::HashMap;
+///
+/// let wm = WindowManager::new(Config::default(), HashMap::new(), HashMap::new(), RustConn::new()?)?;
+///
+/// let wm = Autostart::new()
+///     .with_program("picom -b")
+///     .with_program_and_class("nm-applet", "Nm-applet")
+///     .add_to(wm);
+/// # Ok::<(), penrose::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Autostart {
+    entries: Vec<AutostartEntry>,
+    kill_on_exit: bool,
+    running: HashMap<String, u32>,
+}
+
+impl Autostart {
+    /// Create a new [Autostart] with no programs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn the given program (and its whitespace separated arguments) on startup.
+    pub fn with_program(self, prog: impl Into<String>) -> Self {
+        let prog = prog.into();
+        let spec = SpawnSpec::from(prog.as_str());
+
+        self.with_spec(prog, spec, None)
+    }
+
+    /// Spawn the given program on startup unless a window with the given class already exists.
+    pub fn with_program_and_class(self, prog: impl Into<String>, class: impl Into<String>) -> Self {
+        let prog = prog.into();
+        let spec = SpawnSpec::from(prog.as_str());
+
+        self.with_spec(prog, spec, Some(class.into()))
+    }
+
+    /// Spawn the program described by the given [SpawnSpec] on startup, unless a window with the
+    /// given class already exists.
+    ///
+    /// The name is used to identify the program across restarts so it should be unique.
+    pub fn with_spec(
+        mut self,
+        name: impl Into<String>,
+        spec: SpawnSpec,
+        class: Option<String>,
+    ) -> Self {
+        self.entries.push(AutostartEntry {
+            name: name.into(),
+            spec,
+            class,
+        });
+
+        self
+    }
+
+    /// Terminate all of the programs that were spawned when the window manager exits.
+    pub fn kill_on_exit(mut self) -> Self {
+        self.kill_on_exit = true;
+
+        self
+    }
+
+    /// The process IDs of the programs that were spawned (or found to be already running)
+    /// on startup, keyed by name.
+    pub fn running(&self) -> &HashMap<String, u32> {
+        &self.running
+    }
+
+    /// Add this [Autostart] to the given [WindowManager] along with the startup and shutdown
+    /// hooks required to run it.
+    pub fn add_to<X: XConn + 'static>(self, mut wm: WindowManager<X>) -> WindowManager<X> {
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_startup_hook(startup_hook);
+        wm.state.config.compose_or_set_shutdown_hook(shutdown_hook);
+
+        wm
+    }
+}
+
+/// Spawn any autostart programs that are not already running and record their process IDs.
+pub fn startup_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> Result<()> {
+    let s = state.extension::<Autostart>()?;
+    let mut autostart = s.borrow_mut();
+    let recorded = recorded_pids(x)?;
+    let classes = existing_classes(x)?;
+
+    let Autostart {
+        entries, running, ..
+    } = &mut *autostart;
+    running.clear();
+
+    for e in entries.iter() {
+        if let Some(&pid) = recorded.get(&e.name) {
+            if is_alive(pid) {
+                debug!(name = %e.name, %pid, "autostart program is already running");
+                running.insert(e.name.clone(), pid);
+                continue;
+            }
+        }
+
+        if let Some(class) = &e.class {
+            if classes.contains(class) {
+                debug!(name = %e.name, %class, "window for autostart program already exists");
+                continue;
+            }
+        }
+
+        match e.spec.spawn_for_pid() {
+            Ok(pid) => _ = running.insert(e.name.clone(), pid),
+            Err(e) => warn!(%e, "unable to spawn autostart program"),
+        }
+    }
+
+    let mut val: Vec<String> = running
+        .iter()
+        .map(|(name, pid)| format!("{pid}:{name}"))
+        .collect();
+    val.sort();
+
+    x.set_prop(x.root(), AUTOSTART_PROP, Prop::UTF8String(val))
+}
+
+/// Terminate the autostart programs if [Autostart::kill_on_exit] was set.
+pub fn shutdown_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> Result<()> {
+    let s = state.extension::<Autostart>()?;
+    let mut autostart = s.borrow_mut();
+    if !autostart.kill_on_exit {
+        return Ok(());
+    }
+
+    for (name, pid) in autostart.running.drain() {
+        debug!(%name, %pid, "terminating autostart program");
+        if let Err(e) = kill(Pid::from_raw(pid as i32), Signal::SIGTERM) {
+            warn!(%e, %name, %pid, "unable to terminate autostart program");
+        }
+    }
+
+    x.delete_prop(x.root(), AUTOSTART_PROP)
+}
+
+fn recorded_pids<X: XConn>(x: &X) -> Result<HashMap<String, u32>> {
+    let strs = match x.get_prop(x.root(), AUTOSTART_PROP)? {
+        Some(Prop::UTF8String(strs)) => strs,
+        _ => return Ok(HashMap::new()),
+    };
+
+    Ok(strs
+        .iter()
+        .flat_map(|s| {
+            let (pid, name) = s.split_once(':')?;
+            Some((name.to_owned(), pid.parse().ok()?))
+        })
+        .collect())
+}
+
+fn existing_classes<X: XConn>(x: &X) -> Result<Vec<String>> {
+    let mut classes = Vec::new();
+    for id in x.existing_clients()? {
+        if let Ok(Some(Prop::UTF8String(mut strs))) = x.get_prop(id, Atom::WmClass.as_ref()) {
+            if strs.len() > 1 {
+                classes.push(strs.swap_remove(1));
+            }
+        }
+    }
+
+    Ok(classes)
+}
+
+fn is_alive(pid: u32) -> bool {
+    kill(Pid::from_raw(pid as i32), None).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::Config, x::TestConn};
+
+    fn wm_with(autostart: Autostart, conn: TestConn) -> WindowManager<TestConn> {
+        let wm =
+            WindowManager::new(Config::default(), HashMap::new(), HashMap::new(), conn).unwrap();
+        let mut wm = autostart.add_to(wm);
+        wm.start_headless().unwrap();
+
+        wm
+    }
+
+    fn running(wm: &WindowManager<TestConn>) -> HashMap<String, u32> {
+        let s = wm.state.extension::<Autostart>().unwrap();
+        let running = s.borrow().running().clone();
+
+        running
+    }
+
+    #[test]
+    fn programs_are_recorded_on_the_root_window() {
+        let wm = wm_with(Autostart::new().with_program("true"), TestConn::new());
+        let pid = running(&wm)["true"];
+
+        let prop = wm.conn().get_prop(wm.state.root(), AUTOSTART_PROP).unwrap();
+
+        assert_eq!(prop, Some(Prop::UTF8String(vec![format!("{pid}:true")])));
+    }
+
+    #[test]
+    fn running_programs_are_not_respawned() {
+        let conn = TestConn::new();
+        let pid = std::process::id();
+        conn.set_prop(
+            conn.root(),
+            AUTOSTART_PROP,
+            Prop::UTF8String(vec![format!("{pid}:sleep 5")]),
+        )
+        .unwrap();
+
+        let wm = wm_with(Autostart::new().with_program("sleep 5"), conn);
+
+        assert_eq!(running(&wm)["sleep 5"], pid);
+    }
+
+    #[test]
+    fn programs_with_existing_windows_are_not_spawned() {
+        let conn = TestConn::new();
+        conn.create_existing_client("st");
+
+        let wm = wm_with(Autostart::new().with_program_and_class("false", "st"), conn);
+
+        assert!(running(&wm).is_empty());
+    }
+
+    #[test]
+    fn kill_on_exit_clears_the_recorded_programs() {
+        let mut wm = wm_with(
+            Autostart::new().with_program("true").kill_on_exit(),
+            TestConn::new(),
+        );
+
+        wm.shutdown_headless();
+
+        assert!(running(&wm).is_empty());
+        assert_eq!(
+            wm.conn().get_prop(wm.state.root(), AUTOSTART_PROP).unwrap(),
+            None
+        );
+    }
+}
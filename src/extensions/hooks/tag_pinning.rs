@@ -0,0 +1,197 @@
+//! Pinning clients to a tag so that they can not be moved to another workspace.
+//!
+//! Once a client has been pinned to a tag by [PinnedClients], any attempt to move it to a
+//! different workspace is undone the next time the on screen state is refreshed. Clients can be
+//! pinned to their current tag using the [toggle_pin_focused] action or pinned by a manage hook
+//! using [PinToTag], which works well with the [manage_hooks][crate::manage_hooks] macro for
+//! keeping programs such as chat apps anchored to a particular tag:
+//!
+//! ```no_run
+//! use penrose::{
+//!     core::{Config, WindowManager},
+//!     extensions::hooks::tag_pinning::{PinToTag, PinnedClients},
+//!     manage_hooks,
+//!     x::query::ClassName,
+//!     x11rb::RustConn,
+//! };
+//! use std::collections::HashMap;
+//!
+//! let config = Config {
+//!     manage_hook: Some(manage_hooks! {
+//!         ClassName("discord") => PinToTag("9"),
+//!         ClassName("Signal") => PinToTag("9"),
+//!     }),
+//!     ..Config::default()
+//! };
+//!
+//! let wm = WindowManager::new(config, HashMap::new(), HashMap::new(), RustConn::new()?)?;
+//! let wm = PinnedClients::default().add_to(wm);
+//! # Ok::<(), penrose::Error>(())
+//! ```
+use crate::{
+    builtin::actions::key_handler,
+    core::{bindings::KeyEventHandler, hooks::ManageHook, State, WindowManager},
+    x::{XConn, XConnExt},
+    Result, Xid,
+};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// A state extension for tracking which clients are pinned to which tags.
+///
+/// See the module level docs for details.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PinnedClients {
+    pins: HashMap<Xid, String>,
+}
+
+impl PinnedClients {
+    /// Add this [PinnedClients] to the given [WindowManager] along with the refresh hook
+    /// required to run it.
+    pub fn add_to<X>(self, mut wm: WindowManager<X>) -> WindowManager<X>
+    where
+        X: XConn + 'static,
+    {
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_refresh_hook(refresh_hook);
+
+        wm
+    }
+
+    /// Pin the given client to the given tag.
+    pub fn pin(&mut self, client: Xid, tag: impl Into<String>) {
+        self.pins.insert(client, tag.into());
+    }
+
+    /// Unpin the given client, returning the tag it was pinned to if it was pinned.
+    pub fn unpin(&mut self, client: &Xid) -> Option<String> {
+        self.pins.remove(client)
+    }
+
+    /// The tag that the given client is pinned to, if it is pinned.
+    pub fn pinned_tag(&self, client: &Xid) -> Option<&str> {
+        self.pins.get(client).map(|s| s.as_str())
+    }
+}
+
+/// Move the client to the named workspace and pin it there.
+///
+/// This requires [PinnedClients] to have been added to your window manager.
+#[derive(Debug)]
+pub struct PinToTag(pub &'static str);
+impl<X: XConn + 'static> ManageHook<X> for PinToTag {
+    fn call(&mut self, client: Xid, state: &mut State<X>, _: &X) -> Result<()> {
+        state
+            .extension::<PinnedClients>()?
+            .borrow_mut()
+            .pin(client, self.0);
+        state.client_set.move_client_to_tag(&client, self.0);
+
+        Ok(())
+    }
+}
+
+/// Pin the focused client to its current tag, or unpin it if it is already pinned.
+pub fn toggle_pin_focused<X: XConn + 'static>() -> Box<dyn KeyEventHandler<X>> {
+    key_handler(|state: &mut State<X>, _: &X| {
+        let (id, tag) = match state.client_set.current_client() {
+            Some(&id) => (id, state.client_set.current_tag().to_owned()),
+            None => return Ok(()),
+        };
+
+        let s = state.extension::<PinnedClients>()?;
+        let mut pinned = s.borrow_mut();
+        if pinned.unpin(&id).is_none() {
+            debug!(%id, %tag, "pinning client to tag");
+            pinned.pin(id, tag);
+        }
+
+        Ok(())
+    })
+}
+
+/// Move any pinned clients that are no longer on the tag they are pinned to back to it.
+pub fn refresh_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> Result<()> {
+    let s = state.extension::<PinnedClients>()?;
+    let mut pinned = s.borrow_mut();
+    let cs = &mut state.client_set;
+
+    pinned.pins.retain(|id, _| cs.contains(id));
+
+    let mut moved = false;
+    for (id, tag) in pinned.pins.iter() {
+        if cs.tag_for_client(id) != Some(tag.as_str()) && cs.contains_tag(tag) {
+            debug!(%id, %tag, "returning pinned client to its tag");
+            cs.move_client_to_tag(id, tag);
+            moved = true;
+        }
+    }
+    drop(pinned);
+
+    if moved {
+        x.refresh(state)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builtin::actions::modify_with,
+        core::bindings::KeyCode,
+        manage_hooks,
+        pure::geometry::Rect,
+        x::{query::ClassName, TestConn},
+    };
+
+    const PIN: KeyCode = KeyCode { mask: 0, code: 10 };
+    const MOVE_TO_2: KeyCode = KeyCode { mask: 0, code: 11 };
+
+    fn wm() -> WindowManager<TestConn> {
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(PIN, toggle_pin_focused());
+        bindings.insert(MOVE_TO_2, modify_with(|cs| cs.move_focused_to_tag("2")));
+
+        TestConn::new()
+            .with_screens(vec![Rect::new(0, 0, 1000, 800)])
+            .start_headless_wm(bindings, |mut wm| {
+                wm.state.config.manage_hook = Some(manage_hooks! {
+                    ClassName("chat") => PinToTag("9"),
+                });
+                PinnedClients::default().add_to(wm)
+            })
+    }
+
+    #[test]
+    fn pinned_clients_are_returned_to_their_tag() {
+        let mut wm = wm();
+        let id = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        wm.conn().press_key(PIN);
+        wm.conn().press_key(MOVE_TO_2);
+        wm.run_pending_events();
+        assert_eq!(wm.state.client_set.tag_for_client(&id), Some("1"));
+
+        wm.conn().press_key(PIN);
+        wm.conn().press_key(MOVE_TO_2);
+        wm.run_pending_events();
+        assert_eq!(wm.state.client_set.tag_for_client(&id), Some("2"));
+    }
+
+    #[test]
+    fn clients_can_be_pinned_by_manage_hooks() {
+        let mut wm = wm();
+        let id = wm.conn().spawn_client("chat");
+        wm.run_pending_events();
+
+        assert_eq!(wm.state.client_set.tag_for_client(&id), Some("9"));
+
+        wm.state.client_set.focus_client(&id);
+        wm.conn().press_key(MOVE_TO_2);
+        wm.run_pending_events();
+        assert_eq!(wm.state.client_set.tag_for_client(&id), Some("9"));
+    }
+}
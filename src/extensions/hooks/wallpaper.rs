@@ -0,0 +1,260 @@
+//! Setting a different wallpaper for each workspace.
+//!
+//! [TagWallpapers] sets the root window background to the image associated with the tag of the
+//! focused workspace each time it changes, using an external program such as `feh` or
+//! `xwallpaper` to load and draw the image:
+//!
+//! ```no_run
+//! use penrose::{
+//!     core::{Config, WindowManager},
+//!     extensions::hooks::wallpaper::{TagWallpapers, WallpaperSetter},
+//!     x11rb::RustConn,
+//! };
+//! use std::collections::HashMap;
+//!
+//! let conn = RustConn::new()?;
+//! let wm = WindowManager::new(Config::default(), HashMap::new(), HashMap::new(), conn)?;
+//! let wm = TagWallpapers::new(WallpaperSetter::Feh)
+//!     .with_wallpaper("1", "/home/me/pictures/mountains.png")
+//!     .with_wallpaper("2", "/home/me/pictures/forest.jpg")
+//!     .with_default("/home/me/pictures/default.png")
+//!     .add_to(wm);
+//! # Ok::<(), penrose::Error>(())
+//! ```
+//!
+//! The same image is used for all screens. Tags without an image of their own use the default
+//! image if one has been set, otherwise the wallpaper is left unchanged when they are focused.
+//!
+//! Pseudo-transparent clients (such as terminals that draw a copy of the background behind their
+//! text) locate the current background using the `_XROOTPMAP_ID` and `ESETROOT_PMAP_ID`
+//! properties on the root window. Programs for setting the wallpaper are responsible for
+//! creating the background pixmap and setting these properties but not all of them set both, so
+//! after each change whichever one has been set is copied to the other.
+use crate::{
+    core::{State, WindowManager},
+    util::SpawnSpec,
+    x::{Atom, Prop, XConn},
+    Result,
+};
+use std::{collections::HashMap, time::Duration};
+use tracing::{debug, error};
+
+// Setting the wallpaper blocks the event loop so that we can update the root window properties
+// once it has completed: a hung setter should not lock up the window manager.
+const SETTER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The program used to set the root window background.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WallpaperSetter {
+    /// `feh --no-fehbg --bg-fill <image>`
+    Feh,
+    /// `xwallpaper --zoom <image>`
+    Xwallpaper,
+    /// A custom program and arguments, run with the image path appended as the final argument.
+    Command(String, Vec<String>),
+}
+
+impl WallpaperSetter {
+    fn command<'a>(&'a self, image: &'a str) -> (&'a str, Vec<&'a str>) {
+        let (prog, mut args): (&str, Vec<&str>) = match self {
+            Self::Feh => ("feh", vec!["--no-fehbg", "--bg-fill"]),
+            Self::Xwallpaper => ("xwallpaper", vec!["--zoom"]),
+            Self::Command(prog, args) => (prog, args.iter().map(|s| s.as_str()).collect()),
+        };
+        args.push(image);
+
+        (prog, args)
+    }
+
+    fn set(&self, image: &str) -> Result<()> {
+        let (prog, args) = self.command(image);
+        SpawnSpec::new(prog)
+            .with_args(args)
+            .spawn_for_output_with_timeout(SETTER_TIMEOUT)?;
+
+        Ok(())
+    }
+}
+
+/// A state extension for setting the wallpaper based on the focused workspace.
+///
+/// See the module level docs for details.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagWallpapers {
+    setter: WallpaperSetter,
+    wallpapers: HashMap<String, String>,
+    default: Option<String>,
+    current: Option<String>,
+}
+
+impl TagWallpapers {
+    /// Create a new [TagWallpapers] with no images set that uses the given [WallpaperSetter].
+    pub fn new(setter: WallpaperSetter) -> Self {
+        Self {
+            setter,
+            wallpapers: HashMap::new(),
+            default: None,
+            current: None,
+        }
+    }
+
+    /// Use the given image as the wallpaper when the workspace with the given tag is focused.
+    pub fn with_wallpaper(mut self, tag: impl Into<String>, image: impl Into<String>) -> Self {
+        self.wallpapers.insert(tag.into(), image.into());
+
+        self
+    }
+
+    /// Use the given image as the wallpaper for any tags that do not have an image of their own.
+    pub fn with_default(mut self, image: impl Into<String>) -> Self {
+        self.default = Some(image.into());
+
+        self
+    }
+
+    /// The image most recently set as the wallpaper, if there is one.
+    pub fn current_wallpaper(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    /// The image that should be used as the wallpaper when the given tag is focused.
+    pub fn wallpaper_for(&self, tag: &str) -> Option<&str> {
+        self.wallpapers
+            .get(tag)
+            .or(self.default.as_ref())
+            .map(|s| s.as_str())
+    }
+
+    /// Add this [TagWallpapers] to the given [WindowManager] along with the refresh hook
+    /// required to run it.
+    pub fn add_to<X>(self, mut wm: WindowManager<X>) -> WindowManager<X>
+    where
+        X: XConn + 'static,
+    {
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_refresh_hook(refresh_hook);
+
+        wm
+    }
+}
+
+/// Set the wallpaper for the focused tag if it differs from the current wallpaper.
+pub fn refresh_hook<X: XConn + 'static>(state: &mut State<X>, x: &X) -> Result<()> {
+    let s = state.extension::<TagWallpapers>()?;
+    let mut wallpapers = s.borrow_mut();
+
+    let image = match wallpapers.wallpaper_for(state.client_set.current_tag()) {
+        Some(image) if wallpapers.current_wallpaper() != Some(image) => image.to_owned(),
+        _ => return Ok(()),
+    };
+
+    debug!(%image, "setting wallpaper");
+    if let Err(e) = wallpapers.setter.set(&image) {
+        error!(%e, %image, "unable to set wallpaper");
+    }
+    wallpapers.current = Some(image);
+
+    sync_root_pixmap_props(x)
+}
+
+/// Ensure that the `_XROOTPMAP_ID` and `ESETROOT_PMAP_ID` properties on the root window both
+/// refer to the current background pixmap if either of them has been set.
+///
+/// If the two properties disagree then `_XROOTPMAP_ID` is assumed to be correct.
+pub fn sync_root_pixmap_props<X: XConn>(x: &X) -> Result<()> {
+    let root = x.root();
+    let (xroot, esetroot) = (Atom::XRootPmapId.as_ref(), Atom::EsetrootPmapId.as_ref());
+
+    match (x.get_prop(root, xroot)?, x.get_prop(root, esetroot)?) {
+        (Some(p @ Prop::Pixmap(_)), current) if current.as_ref() != Some(&p) => {
+            x.set_prop(root, esetroot, p)
+        }
+        (None, Some(p @ Prop::Pixmap(_))) => x.set_prop(root, xroot, p),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builtin::actions::modify_with,
+        core::bindings::{KeyCode, KeyEventHandler},
+        pure::geometry::Rect,
+        x::TestConn,
+        Xid,
+    };
+
+    const FOCUS_2: KeyCode = KeyCode { mask: 0, code: 10 };
+    const FOCUS_3: KeyCode = KeyCode { mask: 0, code: 11 };
+
+    fn wm() -> WindowManager<TestConn> {
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(FOCUS_2, modify_with(|cs| cs.focus_tag("2")));
+        bindings.insert(FOCUS_3, modify_with(|cs| cs.focus_tag("3")));
+
+        TestConn::new()
+            .with_screens(vec![Rect::new(0, 0, 1000, 800)])
+            .start_headless_wm(bindings, |wm| {
+                TagWallpapers::new(WallpaperSetter::Command("true".to_owned(), vec![]))
+                    .with_wallpaper("1", "one.png")
+                    .with_wallpaper("3", "three.png")
+                    .with_default("default.png")
+                    .add_to(wm)
+            })
+    }
+
+    fn current(wm: &WindowManager<TestConn>) -> Option<String> {
+        let s = wm.state.extension::<TagWallpapers>().unwrap();
+        let current = s.borrow().current_wallpaper().map(|s| s.to_owned());
+
+        current
+    }
+
+    #[test]
+    fn setter_commands_have_the_image_as_the_final_argument() {
+        let setter = WallpaperSetter::Command("setbg".to_owned(), vec!["--fill".to_owned()]);
+
+        assert_eq!(
+            setter.command("bg.png"),
+            ("setbg", vec!["--fill", "bg.png"])
+        );
+        assert_eq!(
+            WallpaperSetter::Feh.command("bg.png"),
+            ("feh", vec!["--no-fehbg", "--bg-fill", "bg.png"])
+        );
+    }
+
+    #[test]
+    fn wallpaper_follows_the_focused_tag() {
+        let mut wm = wm();
+        assert_eq!(current(&wm).as_deref(), Some("one.png"));
+
+        wm.conn().press_key(FOCUS_2);
+        wm.run_pending_events();
+        assert_eq!(current(&wm).as_deref(), Some("default.png"));
+
+        wm.conn().press_key(FOCUS_3);
+        wm.run_pending_events();
+        assert_eq!(current(&wm).as_deref(), Some("three.png"));
+    }
+
+    #[test]
+    fn root_pixmap_props_are_kept_in_sync() {
+        let mut wm = wm();
+        let root = wm.conn().root();
+        let pixmap = Prop::Pixmap(vec![Xid(99)]);
+        wm.conn()
+            .set_prop(root, Atom::XRootPmapId.as_ref(), pixmap.clone())
+            .unwrap();
+
+        wm.conn().press_key(FOCUS_2);
+        wm.run_pending_events();
+
+        let prop = wm
+            .conn()
+            .get_prop(root, Atom::EsetrootPmapId.as_ref())
+            .unwrap();
+        assert_eq!(prop, Some(pixmap));
+    }
+}
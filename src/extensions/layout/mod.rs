@@ -1,6 +1,6 @@
 //! Layout behaviour that is more specialised or complex than the builtin layouts.
 use crate::{
-    builtin::layout::messages::{ExpandMain, ShrinkMain},
+    builtin::layout::messages::{Balance, ExpandMain, ResizeMain, ShrinkMain},
     core::layout::{Layout, Message},
     pure::{geometry::Rect, Stack},
     Xid,
@@ -19,7 +19,8 @@ pub mod quickcheck_tests;
 ///   <https://dwm.suckless.org/patches/fibonacci/>
 ///
 /// The ratio between the main and secondary regions can be adjusted by sending [ShrinkMain]
-/// and [ExpandMain] messages to this layout.
+/// and [ExpandMain] messages to this layout (or by dragging with the mouse using [ResizeMain]) and
+/// reset to an even split by sending a [Balance] message.
 ///
 /// ```text
 /// ....................................
@@ -134,6 +135,10 @@ impl Layout for Fibonacci {
             if self.ratio < 0.0 {
                 self.ratio = 0.0;
             }
+        } else if let Some(&Balance) = m.downcast_ref() {
+            self.ratio = 0.5;
+        } else if let Some(&ResizeMain { dx, .. }) = m.downcast_ref() {
+            self.ratio = (self.ratio + dx).clamp(0.0, 1.0);
         };
 
         None
@@ -292,6 +297,10 @@ impl Layout for Tatami {
             if self.ratio < 0.0 {
                 self.ratio = 0.0;
             }
+        } else if let Some(&Balance) = m.downcast_ref() {
+            self.ratio = 0.5;
+        } else if let Some(&ResizeMain { dx, .. }) = m.downcast_ref() {
+            self.ratio = (self.ratio + dx).clamp(0.0, 1.0);
         };
 
         None
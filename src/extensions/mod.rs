@@ -1,6 +1,12 @@
 //! Extensions to the base behaviour of Penrose
 
 pub mod actions;
+#[cfg(feature = "configfile")]
+pub mod configfile;
+#[cfg(feature = "dbus")]
+pub mod dbus;
 pub mod hooks;
 pub mod layout;
+#[cfg(feature = "xsmp")]
+pub mod session;
 pub mod util;
@@ -0,0 +1,722 @@
+//! Participation in X Session Management (XSMP).
+//!
+//! Enabling the `xsmp` feature provides [SessionClient], a state extension that registers
+//! penrose with the session manager named by the `SESSION_MANAGER` environment variable (using
+//! `libSM` and `libICE`) so that it behaves correctly under desktop environments and display
+//! managers that make use of XSMP:
+//!
+//! - On each checkpoint ("save yourself") the command required to restart penrose as part of
+//!   the current session is sent to the session manager and an optional [StateHook] is run to
+//!   save any additional state you need.
+//! - When the session ends the session manager asks penrose to "die", at which point the main
+//!   event loop exits cleanly and any shutdown hooks are run.
+//!
+//! ```no_run
+//! use penrose::{
+//!     core::{Config, State, WindowManager},
+//!     extensions::session::SessionClient,
+//!     x11rb::RustConn,
+//! };
+//! use std::collections::HashMap;
+//!
+//! let conn = RustConn::new()?;
+//! let wm = WindowManager::new(Config::default(), HashMap::new(), HashMap::new(), conn)?;
+//!
+//! let wm = SessionClient::new()
+//!     .with_checkpoint_hook(|state: &mut State<RustConn>, _: &RustConn| {
+//!         println!("checkpoint on tag {}", state.client_set.current_tag());
+//!         Ok(())
+//!     })
+//!     .add_to(wm)?;
+//!
+//! wm.run()
+//! # ;Ok::<(), penrose::Error>(())
+//! ```
+//!
+//! If there is no session manager running then [SessionClient::add_to] returns the window
+//! manager unmodified.
+//!
+//! The session manager identifies clients across sessions using a client ID that it hands out
+//! when penrose first connects. This ID is added to the restart command as `--sm-client-id <id>`
+//! and is picked up from the command line arguments by [SessionClient::new] when penrose is
+//! restarted by the session manager.
+use crate::{
+    core::{hooks::StateHook, State, WindowManager},
+    x::{XConn, XEvent},
+    x11rb::Waker,
+    Error, Result,
+};
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use std::{
+    env,
+    ffi::{c_char, c_int, c_uchar, c_ulong, c_void, CStr, CString},
+    fmt,
+    os::fd::BorrowedFd,
+    ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, sync_channel, Receiver, Sender, SyncSender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+use tracing::{error, info, warn};
+
+/// The type of the client message sent to the root window in order to wake up the window manager
+/// event loop when a message is received from the session manager.
+pub const WAKEUP_ATOM: &str = "_PENROSE_SESSION_WAKEUP";
+
+/// The command line flag used to pass the session manager client ID to penrose on restart.
+pub const CLIENT_ID_FLAG: &str = "--sm-client-id";
+
+// How often the background thread checks whether or not it has been asked to close the
+// connection to the session manager while waiting for messages.
+const POLL_TIMEOUT_MS: u16 = 500;
+
+#[derive(Debug)]
+enum Request {
+    Checkpoint(Sender<bool>),
+    Die,
+}
+
+/// A state extension for registering with an X session manager.
+///
+/// See the module level docs for details.
+pub struct SessionClient<X: XConn> {
+    previous_id: Option<String>,
+    client_id: Option<String>,
+    restart_command: Vec<String>,
+    checkpoint_hook: Option<Box<dyn StateHook<X>>>,
+    rx: Option<Receiver<Request>>,
+    closing: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl<X: XConn> fmt::Debug for SessionClient<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionClient")
+            .field("previous_id", &self.previous_id)
+            .field("client_id", &self.client_id)
+            .field("restart_command", &self.restart_command)
+            .finish()
+    }
+}
+
+impl<X: XConn> Default for SessionClient<X> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<X: XConn> SessionClient<X> {
+    /// Create a new [SessionClient] that restarts penrose using the current command line
+    /// arguments, resuming the previous session if a client ID was passed to penrose using
+    /// [CLIENT_ID_FLAG].
+    pub fn new() -> Self {
+        let (restart_command, previous_id) = split_client_id(env::args());
+
+        Self {
+            previous_id,
+            client_id: None,
+            restart_command,
+            checkpoint_hook: None,
+            rx: None,
+            closing: Arc::new(AtomicBool::new(false)),
+            handle: None,
+        }
+    }
+
+    /// Set the command (and its arguments) used by the session manager to restart penrose.
+    ///
+    /// The client ID for the session is appended using [CLIENT_ID_FLAG].
+    pub fn with_restart_command<S: Into<String>>(
+        mut self,
+        cmd: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.restart_command = cmd.into_iter().map(Into::into).collect();
+
+        self
+    }
+
+    /// Set the client ID of a previous session to resume, overriding any ID passed on the
+    /// command line.
+    pub fn with_previous_id(mut self, id: impl Into<String>) -> Self {
+        self.previous_id = Some(id.into());
+
+        self
+    }
+
+    /// Set a [StateHook] to run each time the session manager asks penrose to save its state.
+    ///
+    /// The session manager is told that saving failed if the hook returns an error.
+    pub fn with_checkpoint_hook<H>(mut self, hook: H) -> Self
+    where
+        H: StateHook<X> + 'static,
+    {
+        self.checkpoint_hook = Some(hook.boxed());
+
+        self
+    }
+
+    /// The client ID assigned by the session manager, if penrose is connected to one.
+    pub fn client_id(&self) -> Option<&str> {
+        self.client_id.as_deref()
+    }
+}
+
+impl<X: XConn + 'static> SessionClient<X> {
+    /// Connect to the session manager and add this [SessionClient] to the given
+    /// [WindowManager] along with the hooks required to run it.
+    pub fn add_to(mut self, mut wm: WindowManager<X>) -> Result<WindowManager<X>> {
+        if env::var_os("SESSION_MANAGER").is_none() {
+            info!("SESSION_MANAGER is not set: not connecting to a session manager");
+            return Ok(wm);
+        }
+
+        let (tx, rx) = channel();
+        let (ready_tx, ready_rx) = sync_channel(1);
+        let ctx = Context {
+            tx,
+            waker: Waker::new(WAKEUP_ATOM)?,
+            restart_command: self.restart_command.clone(),
+            client_id: String::new(),
+        };
+        let previous_id = self.previous_id.clone();
+        let closing = self.closing.clone();
+
+        let handle = thread::spawn(move || run_session(ctx, previous_id, closing, ready_tx));
+        let client_id = ready_rx
+            .recv()
+            .map_err(|_| Error::Custom("session manager thread exited".to_owned()))??;
+
+        info!(%client_id, "registered with session manager");
+        self.client_id = Some(client_id);
+        self.rx = Some(rx);
+        self.handle = Some(handle);
+
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_event_hook(event_hook);
+        wm.state.config.compose_or_set_shutdown_hook(shutdown_hook);
+
+        Ok(wm)
+    }
+}
+
+/// Run any checkpoint or shutdown requests received from the session manager.
+pub fn event_hook<X: XConn + 'static>(event: &XEvent, state: &mut State<X>, x: &X) -> Result<bool> {
+    match event {
+        XEvent::ClientMessage(m) if m.dtype == WAKEUP_ATOM => (),
+        _ => return Ok(true),
+    }
+
+    let s = state.extension::<SessionClient<X>>()?;
+    let requests: Vec<Request> = match &s.borrow().rx {
+        Some(rx) => rx.try_iter().collect(),
+        None => return Ok(false),
+    };
+
+    for r in requests {
+        match r {
+            Request::Checkpoint(reply) => {
+                // The hook is removed while it runs so that it is free to make use of the
+                // extension itself.
+                let hook = s.borrow_mut().checkpoint_hook.take();
+                let success = match hook {
+                    Some(mut hook) => {
+                        let res = hook.call(state, x);
+                        s.borrow_mut().checkpoint_hook = Some(hook);
+                        if let Err(e) = &res {
+                            error!(%e, "error returned from session checkpoint hook");
+                        }
+                        res.is_ok()
+                    }
+                    None => true,
+                };
+                _ = reply.send(success);
+            }
+
+            Request::Die => {
+                info!("session manager requested shutdown: exiting");
+                state.running = false;
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+/// Close the connection to the session manager.
+pub fn shutdown_hook<X: XConn + 'static>(state: &mut State<X>, _: &X) -> Result<()> {
+    let s = state.extension::<SessionClient<X>>()?;
+    let mut client = s.borrow_mut();
+    client.closing.store(true, Ordering::SeqCst);
+
+    if let Some(handle) = client.handle.take() {
+        if handle.join().is_err() {
+            warn!("session manager thread panicked");
+        }
+    }
+
+    Ok(())
+}
+
+// Strip the session manager client ID (if there is one) from a set of command line arguments.
+fn split_client_id(args: impl IntoIterator<Item = String>) -> (Vec<String>, Option<String>) {
+    let mut args = args.into_iter();
+    let mut cmd = Vec::new();
+    let mut id = None;
+
+    while let Some(arg) = args.next() {
+        if arg == CLIENT_ID_FLAG {
+            id = args.next();
+        } else if let Some(s) = arg
+            .strip_prefix(CLIENT_ID_FLAG)
+            .and_then(|s| s.strip_prefix('='))
+        {
+            id = Some(s.to_owned());
+        } else {
+            cmd.push(arg);
+        }
+    }
+
+    (cmd, id)
+}
+
+// State shared with the libSM callbacks, which are only ever run on the session thread.
+struct Context {
+    tx: Sender<Request>,
+    waker: Waker,
+    restart_command: Vec<String>,
+    client_id: String,
+}
+
+impl Context {
+    fn send(&self, r: Request) -> bool {
+        if self.tx.send(r).is_err() {
+            warn!("window manager is no longer running");
+            return false;
+        }
+        self.waker.wake();
+
+        true
+    }
+
+    fn properties(&self) -> Vec<(&'static str, &'static str, Vec<Vec<u8>>)> {
+        let bytes = |s: &str| s.as_bytes().to_vec();
+        let clone: Vec<Vec<u8>> = self.restart_command.iter().map(|s| bytes(s)).collect();
+        let mut restart = clone.clone();
+        restart.push(bytes(CLIENT_ID_FLAG));
+        restart.push(bytes(&self.client_id));
+
+        let mut props = vec![
+            (ffi::SM_RESTART_COMMAND, ffi::SM_LIST_OF_ARRAY8, restart),
+            (ffi::SM_CLONE_COMMAND, ffi::SM_LIST_OF_ARRAY8, clone),
+            (
+                ffi::SM_RESTART_STYLE_HINT,
+                ffi::SM_CARD8,
+                vec![vec![ffi::SM_RESTART_IMMEDIATELY]],
+            ),
+            (
+                ffi::SM_PROCESS_ID,
+                ffi::SM_ARRAY8,
+                vec![bytes(&std::process::id().to_string())],
+            ),
+        ];
+
+        if let Some(prog) = self.restart_command.first() {
+            props.push((ffi::SM_PROGRAM, ffi::SM_ARRAY8, vec![bytes(prog)]));
+        }
+        if let Ok(user) = env::var("USER") {
+            props.push((ffi::SM_USER_ID, ffi::SM_ARRAY8, vec![bytes(&user)]));
+        }
+        if let Ok(dir) = env::current_dir() {
+            let dir = dir.to_string_lossy();
+            props.push((ffi::SM_CURRENT_DIRECTORY, ffi::SM_ARRAY8, vec![bytes(&dir)]));
+        }
+
+        props
+    }
+}
+
+// Open the connection to the session manager and process messages from it until either the
+// connection is closed by the session manager or the window manager shuts down.
+fn run_session(
+    ctx: Context,
+    previous_id: Option<String>,
+    closing: Arc<AtomicBool>,
+    ready: SyncSender<Result<String>>,
+) {
+    let mut callbacks = ffi::SmcCallbacks {
+        save_yourself: ffi::Callback {
+            callback: save_yourself,
+            client_data: ptr::null_mut(),
+        },
+        die: ffi::Callback {
+            callback: die,
+            client_data: ptr::null_mut(),
+        },
+        save_complete: ffi::Callback {
+            callback: noop,
+            client_data: ptr::null_mut(),
+        },
+        shutdown_cancelled: ffi::Callback {
+            callback: noop,
+            client_data: ptr::null_mut(),
+        },
+    };
+
+    let previous_id = previous_id.and_then(|id| CString::new(id).ok());
+    let mut client_id: *mut c_char = ptr::null_mut();
+    let mut err = [0 as c_char; 256];
+
+    // The callbacks are only run from IceProcessMessages below and the context is not freed
+    // until after the connection has been closed.
+    let ctx = Box::into_raw(Box::new(ctx));
+    callbacks.save_yourself.client_data = ctx as *mut c_void;
+    callbacks.die.client_data = ctx as *mut c_void;
+
+    // SAFETY: all pointers passed are either null or valid for the duration of the call, and
+    //         the error buffer length matches the buffer we are passing.
+    let conn = unsafe {
+        ffi::SmcOpenConnection(
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ffi::SM_PROTO_MAJOR,
+            ffi::SM_PROTO_MINOR,
+            ffi::CALLBACK_MASK,
+            &mut callbacks,
+            previous_id.as_ref().map_or(ptr::null(), |id| id.as_ptr()),
+            &mut client_id,
+            err.len() as c_int,
+            err.as_mut_ptr(),
+        )
+    };
+
+    if conn.is_null() {
+        // SAFETY: libSM writes a null terminated error message into the buffer on failure.
+        let msg = unsafe { CStr::from_ptr(err.as_ptr()) }.to_string_lossy();
+        let msg = format!("unable to connect to session manager: {msg}");
+        _ = ready.send(Err(Error::Custom(msg)));
+        // SAFETY: ctx was created by Box::into_raw above and there is no open connection
+        drop(unsafe { Box::from_raw(ctx) });
+        return;
+    }
+
+    // SAFETY: the connection was opened successfully so client_id is a valid C string that we
+    //         now own and need to free. No callbacks can run while we update the context.
+    let id = unsafe {
+        let id = CStr::from_ptr(client_id).to_string_lossy().into_owned();
+        ffi::free(client_id as *mut c_void);
+        (*ctx).client_id = id.clone();
+        id
+    };
+    _ = ready.send(Ok(id));
+
+    // SAFETY: conn is a valid connection
+    let fd = unsafe { ffi::IceConnectionNumber(ffi::SmcGetIceConnection(conn)) };
+
+    while !closing.load(Ordering::SeqCst) {
+        // SAFETY: the file descriptor remains open until the connection is closed below
+        let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+        let mut fds = [PollFd::new(fd, PollFlags::POLLIN)];
+        match poll(&mut fds, PollTimeout::from(POLL_TIMEOUT_MS)) {
+            Ok(0) => continue,
+            Ok(_) => (),
+            Err(nix::errno::Errno::EINTR) => continue,
+            Err(e) => {
+                error!(%e, "unable to poll session manager connection");
+                break;
+            }
+        }
+
+        // SAFETY: conn is a valid connection and the callbacks it runs only access ctx
+        let status = unsafe {
+            ffi::IceProcessMessages(
+                ffi::SmcGetIceConnection(conn),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        if status != ffi::ICE_PROCESS_MESSAGES_SUCCESS {
+            warn!("connection to session manager lost");
+            break;
+        }
+    }
+
+    // SAFETY: conn is a valid connection that is not used again after being closed, after
+    //         which no callbacks can make use of ctx.
+    unsafe {
+        ffi::SmcCloseConnection(conn, 0, ptr::null_mut());
+        drop(Box::from_raw(ctx));
+    }
+}
+
+extern "C" fn save_yourself(
+    conn: ffi::SmcConn,
+    client_data: *mut c_void,
+    _save_type: c_int,
+    _shutdown: c_int,
+    _interact_style: c_int,
+    _fast: c_int,
+) {
+    // SAFETY: client_data is the Context owned by run_session which outlives the connection
+    let ctx = unsafe { &*(client_data as *const Context) };
+    set_properties(conn, ctx);
+
+    let (tx, rx) = channel();
+    let success = ctx.send(Request::Checkpoint(tx)) && rx.recv().unwrap_or(false);
+
+    // SAFETY: conn is the valid connection this callback was run for
+    unsafe { ffi::SmcSaveYourselfDone(conn, success as c_int) };
+}
+
+extern "C" fn die(_conn: ffi::SmcConn, client_data: *mut c_void) {
+    // SAFETY: client_data is the Context owned by run_session which outlives the connection
+    let ctx = unsafe { &*(client_data as *const Context) };
+    ctx.send(Request::Die);
+}
+
+extern "C" fn noop(_conn: ffi::SmcConn, _client_data: *mut c_void) {}
+
+fn set_properties(conn: ffi::SmcConn, ctx: &Context) {
+    let props = ctx.properties();
+
+    // Keep all of the owned data alive until after SmcSetProperties has copied it
+    let names: Vec<(CString, CString)> = props
+        .iter()
+        .map(|(name, ty, _)| {
+            (
+                CString::new(*name).expect("no null bytes"),
+                CString::new(*ty).expect("no null bytes"),
+            )
+        })
+        .collect();
+    let mut vals: Vec<Vec<ffi::SmPropValue>> = props
+        .iter()
+        .map(|(_, _, vals)| {
+            vals.iter()
+                .map(|v| ffi::SmPropValue {
+                    length: v.len() as c_int,
+                    value: v.as_ptr() as *mut c_void,
+                })
+                .collect()
+        })
+        .collect();
+    let mut sm_props: Vec<ffi::SmProp> = names
+        .iter()
+        .zip(vals.iter_mut())
+        .map(|((name, ty), vals)| ffi::SmProp {
+            name: name.as_ptr() as *mut c_char,
+            ty: ty.as_ptr() as *mut c_char,
+            num_vals: vals.len() as c_int,
+            vals: vals.as_mut_ptr(),
+        })
+        .collect();
+    let mut ptrs: Vec<*mut ffi::SmProp> = sm_props.iter_mut().map(|p| p as *mut _).collect();
+
+    // SAFETY: every pointer in ptrs (and the data they reference) is valid for the duration of
+    //         the call and libSM does not retain them.
+    unsafe { ffi::SmcSetProperties(conn, ptrs.len() as c_int, ptrs.as_mut_ptr()) };
+}
+
+// Minimal bindings to the parts of libSM and libICE required for registering as a client.
+#[allow(non_snake_case)]
+mod ffi {
+    use super::*;
+
+    pub(super) type SmcConn = *mut c_void;
+    pub(super) type IceConn = *mut c_void;
+
+    pub(super) const SM_PROTO_MAJOR: c_int = 1;
+    pub(super) const SM_PROTO_MINOR: c_int = 0;
+    // SmcSaveYourselfProcMask | SmcDieProcMask | SmcSaveCompleteProcMask |
+    // SmcShutdownCancelledProcMask
+    pub(super) const CALLBACK_MASK: c_ulong = 0b1111;
+    pub(super) const ICE_PROCESS_MESSAGES_SUCCESS: c_int = 0;
+
+    pub(super) const SM_PROGRAM: &str = "Program";
+    pub(super) const SM_RESTART_COMMAND: &str = "RestartCommand";
+    pub(super) const SM_CLONE_COMMAND: &str = "CloneCommand";
+    pub(super) const SM_RESTART_STYLE_HINT: &str = "RestartStyleHint";
+    pub(super) const SM_PROCESS_ID: &str = "ProcessID";
+    pub(super) const SM_USER_ID: &str = "UserID";
+    pub(super) const SM_CURRENT_DIRECTORY: &str = "CurrentDirectory";
+
+    pub(super) const SM_ARRAY8: &str = "ARRAY8";
+    pub(super) const SM_LIST_OF_ARRAY8: &str = "LISTofARRAY8";
+    pub(super) const SM_CARD8: &str = "CARD8";
+
+    pub(super) const SM_RESTART_IMMEDIATELY: c_uchar = 2;
+
+    #[repr(C)]
+    pub(super) struct Callback<F> {
+        pub(super) callback: F,
+        pub(super) client_data: *mut c_void,
+    }
+
+    type SaveYourselfProc = extern "C" fn(SmcConn, *mut c_void, c_int, c_int, c_int, c_int);
+    type Proc = extern "C" fn(SmcConn, *mut c_void);
+
+    #[repr(C)]
+    pub(super) struct SmcCallbacks {
+        pub(super) save_yourself: Callback<SaveYourselfProc>,
+        pub(super) die: Callback<Proc>,
+        pub(super) save_complete: Callback<Proc>,
+        pub(super) shutdown_cancelled: Callback<Proc>,
+    }
+
+    #[repr(C)]
+    pub(super) struct SmPropValue {
+        pub(super) length: c_int,
+        pub(super) value: *mut c_void,
+    }
+
+    #[repr(C)]
+    pub(super) struct SmProp {
+        pub(super) name: *mut c_char,
+        pub(super) ty: *mut c_char,
+        pub(super) num_vals: c_int,
+        pub(super) vals: *mut SmPropValue,
+    }
+
+    #[link(name = "SM")]
+    extern "C" {
+        pub(super) fn SmcOpenConnection(
+            network_ids_list: *mut c_char,
+            context: *mut c_void,
+            xsmp_major_rev: c_int,
+            xsmp_minor_rev: c_int,
+            mask: c_ulong,
+            callbacks: *mut SmcCallbacks,
+            previous_id: *const c_char,
+            client_id_ret: *mut *mut c_char,
+            error_length: c_int,
+            error_string_ret: *mut c_char,
+        ) -> SmcConn;
+        pub(super) fn SmcCloseConnection(
+            conn: SmcConn,
+            count: c_int,
+            reason_msgs: *mut *mut c_char,
+        ) -> c_int;
+        pub(super) fn SmcSetProperties(conn: SmcConn, num_props: c_int, props: *mut *mut SmProp);
+        pub(super) fn SmcSaveYourselfDone(conn: SmcConn, success: c_int);
+        pub(super) fn SmcGetIceConnection(conn: SmcConn) -> IceConn;
+    }
+
+    #[link(name = "ICE")]
+    extern "C" {
+        pub(super) fn IceConnectionNumber(conn: IceConn) -> c_int;
+        pub(super) fn IceProcessMessages(
+            conn: IceConn,
+            reply_wait: *mut c_void,
+            reply_ready_ret: *mut c_int,
+        ) -> c_int;
+    }
+
+    extern "C" {
+        pub(super) fn free(p: *mut c_void);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::Config,
+        x::{
+            event::{ClientEventMask, ClientMessage},
+            TestConn,
+        },
+    };
+    use simple_test_case::test_case;
+    use std::collections::HashMap;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test_case("penrose", "penrose", None; "no id")]
+    #[test_case("penrose --sm-client-id abc", "penrose", Some("abc"); "separate id")]
+    #[test_case("penrose --sm-client-id=abc", "penrose", Some("abc"); "inline id")]
+    #[test_case("penrose -v --sm-client-id abc -x", "penrose -v -x", Some("abc"); "other args")]
+    #[test]
+    fn split_client_id_works(raw: &str, cmd: &str, id: Option<&str>) {
+        let (c, i) = split_client_id(args(raw));
+
+        assert_eq!(c, args(cmd));
+        assert_eq!(i.as_deref(), id);
+    }
+
+    fn wm_with_client(client: SessionClient<TestConn>) -> WindowManager<TestConn> {
+        let mut wm = WindowManager::new(
+            Config::default(),
+            HashMap::new(),
+            HashMap::new(),
+            TestConn::new(),
+        )
+        .unwrap();
+        wm.start_headless().unwrap();
+        wm.state.add_extension(client);
+        wm.state.config.compose_or_set_event_hook(event_hook);
+
+        wm
+    }
+
+    fn wake(wm: &WindowManager<TestConn>) {
+        let wakeup = ClientMessage::new(
+            wm.state.root(),
+            ClientEventMask::SubstructureNotify,
+            WAKEUP_ATOM,
+            [0u32; 5].into(),
+        );
+        wm.conn().push_event(XEvent::ClientMessage(wakeup));
+    }
+
+    #[test_case(true; "hook succeeds")]
+    #[test_case(false; "hook fails")]
+    #[test]
+    fn checkpoint_runs_hook_and_replies(succeed: bool) {
+        let (tx, rx) = channel();
+        let mut client = SessionClient::<TestConn>::new().with_checkpoint_hook(
+            move |state: &mut State<TestConn>, _: &TestConn| {
+                state.client_set.focus_tag("2");
+                if succeed {
+                    Ok(())
+                } else {
+                    Err(Error::Custom("failed".to_owned()))
+                }
+            },
+        );
+        client.rx = Some(rx);
+        let mut wm = wm_with_client(client);
+
+        let (reply_tx, reply_rx) = channel();
+        tx.send(Request::Checkpoint(reply_tx)).unwrap();
+        wake(&wm);
+        wm.run_pending_events();
+
+        assert_eq!(reply_rx.try_recv(), Ok(succeed));
+        assert_eq!(wm.state.client_set.current_tag(), "2");
+        assert!(wm
+            .state
+            .extension::<SessionClient<TestConn>>()
+            .unwrap()
+            .borrow()
+            .checkpoint_hook
+            .is_some());
+    }
+
+    #[test]
+    fn die_stops_the_event_loop() {
+        let (tx, rx) = channel();
+        let mut client = SessionClient::<TestConn>::new();
+        client.rx = Some(rx);
+        let mut wm = wm_with_client(client);
+
+        tx.send(Request::Die).unwrap();
+        wake(&wm);
+        wm.run_pending_events();
+
+        assert!(!wm.state.running);
+    }
+}
@@ -0,0 +1,313 @@
+//! Changing the log level at runtime and keeping recent log lines in memory.
+//!
+//! Enabling the `logging` feature provides [LogControl], which installs a global `tracing`
+//! subscriber that logs to stderr while also keeping the most recent lines in a [LogBuffer].
+//! Once added to the window manager as a state extension, the active filter can be changed
+//! and the buffer dumped to a file from key bindings, so that a misbehaving hook can be debugged
+//! without restarting penrose with `RUST_LOG=trace`:
+//!
+//! ```no_run
+//! use penrose::{
+//!     core::{bindings::KeyEventHandler, Config, WindowManager},
+//!     extensions::util::logging::{dump_log_buffer, reset_log_filter, set_log_filter, LogControl},
+//!     x11rb::RustConn,
+//! };
+//! use std::collections::HashMap;
+//!
+//! let logs = LogControl::init("info", 1000)?;
+//!
+//! let mut raw_bindings: HashMap<String, Box<dyn KeyEventHandler<RustConn>>> = HashMap::new();
+//! raw_bindings.insert("M-A-t".to_owned(), set_log_filter("penrose=trace"));
+//! raw_bindings.insert("M-A-r".to_owned(), reset_log_filter());
+//! raw_bindings.insert("M-A-d".to_owned(), dump_log_buffer("/tmp/penrose.log"));
+//!
+//! # let key_bindings = penrose::core::bindings::parse_keybindings_with_xmodmap(raw_bindings)?;
+//! let wm = WindowManager::new(Config::default(), key_bindings, HashMap::new(), RustConn::new()?)?;
+//! let wm = logs.add_to(wm);
+//! # Ok::<(), penrose::Error>(())
+//! ```
+//!
+//! The same actions can be registered with the [DBusServer][0] in order to run them from
+//! outside of the window manager.
+//!
+//! Filters use the same syntax as the `RUST_LOG` environment variable, see the
+//! [EnvFilter][1] docs for details. The buffer only contains lines that passed the filter that
+//! was active at the time they were logged.
+//!
+//!   [0]: crate::extensions::dbus::DBusServer
+//!   [1]: tracing_subscriber::EnvFilter
+use crate::{
+    builtin::actions::key_handler,
+    core::{bindings::KeyEventHandler, State, WindowManager},
+    x::XConn,
+    Error, Result,
+};
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use tracing::info;
+use tracing_subscriber::{
+    fmt::{self, MakeWriter},
+    prelude::*,
+    reload, EnvFilter, Registry,
+};
+
+/// A fixed size, shared buffer of the most recently logged lines.
+///
+/// Once the buffer is full the oldest line is dropped each time a new line is logged.
+#[derive(Debug, Clone)]
+pub struct LogBuffer {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    /// Create a new empty [LogBuffer] holding at most `capacity` lines.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    fn guard(&self) -> MutexGuard<'_, VecDeque<String>> {
+        match self.lines.lock() {
+            Ok(lines) => lines,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// The lines currently held in the buffer, from oldest to newest.
+    pub fn lines(&self) -> Vec<String> {
+        self.guard().iter().cloned().collect()
+    }
+
+    /// Remove all lines from the buffer.
+    pub fn clear(&self) {
+        self.guard().clear();
+    }
+
+    fn push(&self, line: String) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut lines = self.guard();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+}
+
+/// An [io::Write] implementation for a single log event, adding the lines written to it to the
+/// parent [LogBuffer] when it is dropped.
+#[derive(Debug)]
+pub struct LineWriter {
+    buffer: LogBuffer,
+    pending: Vec<u8>,
+}
+
+impl io::Write for LineWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LineWriter {
+    fn drop(&mut self) {
+        for line in String::from_utf8_lossy(&self.pending).lines() {
+            self.buffer.push(line.to_owned());
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogBuffer {
+    type Writer = LineWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LineWriter {
+            buffer: self.clone(),
+            pending: Vec::new(),
+        }
+    }
+}
+
+fn parse_filter(filter: &str) -> Result<EnvFilter> {
+    EnvFilter::try_new(filter).map_err(|e| Error::InvalidLogFilter {
+        filter: filter.to_owned(),
+        reason: e.to_string(),
+    })
+}
+
+/// A state extension for changing the active log filter and reading recently logged lines.
+///
+/// See the module level docs for details.
+#[derive(Debug)]
+pub struct LogControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+    buffer: LogBuffer,
+    initial_filter: String,
+}
+
+impl LogControl {
+    /// Install a global `tracing` subscriber that logs to stderr and keeps the most recent
+    /// `capacity` lines in memory, using `filter` as the initial log filter.
+    ///
+    /// # Errors
+    /// Returns an error if `filter` is invalid or if a global subscriber has already been set.
+    pub fn init(filter: &str, capacity: usize) -> Result<Self> {
+        let (subscriber, control) = Self::subscriber(filter, capacity)?;
+        subscriber
+            .try_init()
+            .map_err(|_| Error::LoggingAlreadyInitialised)?;
+
+        Ok(control)
+    }
+
+    fn subscriber(
+        filter: &str,
+        capacity: usize,
+    ) -> Result<(impl tracing::Subscriber + Send + Sync, Self)> {
+        let (filter_layer, handle) = reload::Layer::new(parse_filter(filter)?);
+        let buffer = LogBuffer::new(capacity);
+
+        let subscriber = tracing_subscriber::registry()
+            .with(filter_layer)
+            .with(fmt::layer().with_writer(io::stderr))
+            .with(fmt::layer().with_ansi(false).with_writer(buffer.clone()));
+
+        let control = Self {
+            handle,
+            buffer,
+            initial_filter: filter.to_owned(),
+        };
+
+        Ok((subscriber, control))
+    }
+
+    /// Add this [LogControl] to the given [WindowManager] as a state extension so that it can
+    /// be used by the actions in this module.
+    pub fn add_to<X: XConn>(self, mut wm: WindowManager<X>) -> WindowManager<X> {
+        wm.state.add_extension(self);
+
+        wm
+    }
+
+    /// Replace the active log filter.
+    pub fn set_filter(&self, filter: &str) -> Result<()> {
+        let new = parse_filter(filter)?;
+        self.handle
+            .reload(new)
+            .map_err(|e| Error::Custom(e.to_string()))
+    }
+
+    /// The active log filter.
+    pub fn current_filter(&self) -> String {
+        self.handle
+            .with_current(|f| f.to_string())
+            .unwrap_or_default()
+    }
+
+    /// The filter that this [LogControl] was initialised with.
+    pub fn initial_filter(&self) -> &str {
+        &self.initial_filter
+    }
+
+    /// The buffer holding recently logged lines.
+    pub fn buffer(&self) -> &LogBuffer {
+        &self.buffer
+    }
+}
+
+/// Replace the active log filter of the [LogControl] state extension.
+pub fn set_log_filter<X: XConn>(filter: impl Into<String>) -> Box<dyn KeyEventHandler<X>> {
+    let filter = filter.into();
+
+    key_handler(move |state: &mut State<X>, _: &X| {
+        state
+            .extension::<LogControl>()?
+            .borrow()
+            .set_filter(&filter)?;
+        info!(%filter, "log filter updated");
+
+        Ok(())
+    })
+}
+
+/// Restore the log filter that the [LogControl] state extension was initialised with.
+pub fn reset_log_filter<X: XConn>() -> Box<dyn KeyEventHandler<X>> {
+    key_handler(|state: &mut State<X>, _: &X| {
+        let s = state.extension::<LogControl>()?;
+        let control = s.borrow();
+        control.set_filter(control.initial_filter())?;
+        info!(filter = %control.initial_filter(), "log filter reset");
+
+        Ok(())
+    })
+}
+
+/// Write the lines currently held in the [LogControl] buffer to the given file, replacing its
+/// contents.
+pub fn dump_log_buffer<X: XConn>(path: impl Into<PathBuf>) -> Box<dyn KeyEventHandler<X>> {
+    let path = path.into();
+
+    key_handler(move |state: &mut State<X>, _: &X| {
+        let mut lines = state.extension::<LogControl>()?.borrow().buffer().lines();
+        lines.push(String::new());
+        fs::write(&path, lines.join("\n"))?;
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::{debug, info};
+
+    #[test]
+    fn the_buffer_drops_the_oldest_lines() {
+        let buffer = LogBuffer::new(2);
+        for line in ["one", "two", "three"] {
+            buffer.push(line.to_owned());
+        }
+
+        assert_eq!(buffer.lines(), vec!["two", "three"]);
+    }
+
+    #[test]
+    fn invalid_filters_are_an_error() {
+        let res = LogControl::subscriber("penrose=nope", 10);
+
+        assert!(matches!(res, Err(Error::InvalidLogFilter { .. })));
+    }
+
+    #[test]
+    fn the_filter_can_be_changed_at_runtime() {
+        let (subscriber, control) = LogControl::subscriber("info", 10).unwrap();
+
+        tracing::subscriber::with_default(subscriber, || {
+            debug!("hidden");
+            info!("shown");
+            control.set_filter("debug").unwrap();
+            debug!("now shown");
+
+            assert_eq!(control.current_filter(), "debug");
+        });
+
+        let lines = control.buffer().lines();
+        assert_eq!(lines.len(), 2, "{lines:?}");
+        assert!(lines[0].ends_with("shown"));
+        assert!(lines[1].ends_with("now shown"));
+    }
+}
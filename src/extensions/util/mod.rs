@@ -7,6 +7,10 @@ use crate::{
 
 pub mod debug;
 pub mod dmenu;
+#[cfg(feature = "logging")]
+pub mod logging;
+pub mod theme;
+pub mod worker;
 
 /// Detect the current monitor set up and arrange the monitors if needed using [xrandr][1].
 ///
@@ -0,0 +1,202 @@
+//! A shared color scheme and set of fonts for the window manager and its UI elements.
+//!
+//! [Theme] is stored as a state extension so that anything with access to [State] can look up
+//! the colors, font and sizes it should be using. Adding a theme to the window manager sets the
+//! window border style from the theme, and the [set_theme] action can be used to switch between
+//! themes while penrose is running:
+//!
+//! ```no_run
+//! use penrose::{
+//!     core::{bindings::KeyEventHandler, Config, WindowManager},
+//!     extensions::util::theme::{set_theme, Theme},
+//!     x11rb::RustConn,
+//! };
+//! use std::collections::HashMap;
+//!
+//! let mut raw_bindings: HashMap<String, Box<dyn KeyEventHandler<RustConn>>> = HashMap::new();
+//! raw_bindings.insert("M-S-l".to_owned(), set_theme(Theme::light()));
+//! raw_bindings.insert("M-S-d".to_owned(), set_theme(Theme::dark()));
+//!
+//! # let key_bindings = penrose::core::bindings::parse_keybindings_with_xmodmap(raw_bindings)?;
+//! let wm = WindowManager::new(Config::default(), key_bindings, HashMap::new(), RustConn::new()?)?;
+//! let wm = Theme::dark().add_to(wm);
+//! # Ok::<(), penrose::Error>(())
+//! ```
+//!
+//! Status bars, popups and other elements provided by `penrose_ui` can be set to follow the
+//! active theme, restyling themselves whenever it changes.
+use crate::{
+    builtin::actions::key_handler,
+    core::{bindings::KeyEventHandler, BorderStyle, State, WindowManager},
+    x::{XConn, XConnExt},
+    Color,
+};
+
+/// A color scheme along with the fonts, paddings and border width to use for rendering the
+/// window manager and its UI elements.
+///
+/// See the module level docs for details.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// A name for identifying this theme
+    pub name: String,
+    /// The color used for text
+    pub fg: Color,
+    /// The color used for backgrounds
+    pub bg: Color,
+    /// The color used for the focused window border and to highlight the active workspace
+    pub highlight: Color,
+    /// The color used for unfocused window borders and de-emphasised text
+    pub muted: Color,
+    /// The color used for windows and workspaces requesting attention
+    pub urgent: Color,
+    /// The font to use for rendering text
+    pub font: String,
+    /// The point size to use for rendering text
+    pub point_size: u8,
+    /// Padding in pixels to the left and right of rendered text
+    pub padding: (u32, u32),
+    /// The width in pixels of window borders
+    pub border_width: u32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    /// A dark theme using the same colors as the default [Config][0].
+    ///
+    ///   [0]: crate::core::Config
+    pub fn dark() -> Self {
+        Self {
+            name: "dark".to_owned(),
+            fg: 0xebdbb2ff.into(),
+            bg: 0x282828ff.into(),
+            highlight: 0xcc241dff.into(),
+            muted: 0x3c3836ff.into(),
+            urgent: 0xd79921ff.into(),
+            font: "monospace".to_owned(),
+            point_size: 12,
+            padding: (4, 4),
+            border_width: 2,
+        }
+    }
+
+    /// A light counterpart to [Theme::dark].
+    pub fn light() -> Self {
+        Self {
+            name: "light".to_owned(),
+            fg: 0x3c3836ff.into(),
+            bg: 0xfbf1c7ff.into(),
+            highlight: 0x458588ff.into(),
+            muted: 0xd5c4a1ff.into(),
+            urgent: 0xd79921ff.into(),
+            ..Self::dark()
+        }
+    }
+
+    /// Derive a theme from a background and highlight color, picking whichever of black or
+    /// white is more readable for the text and mixing the two for the muted color.
+    pub fn from_colors(name: impl Into<String>, bg: Color, highlight: Color) -> Self {
+        let fg = bg.readable_fg().mix(bg, 0.15);
+
+        Self {
+            name: name.into(),
+            fg,
+            bg,
+            highlight,
+            muted: bg.mix(fg, 0.2),
+            ..Self::dark()
+        }
+    }
+
+    /// The window [BorderStyle] for this theme.
+    pub fn border_style(&self) -> BorderStyle {
+        BorderStyle {
+            width: self.border_width,
+            focused: self.highlight,
+            normal: self.muted,
+            urgent: self.urgent,
+        }
+    }
+
+    /// Add this [Theme] to the given [WindowManager] as the active theme, setting the border
+    /// style of its [Config][0] to match.
+    ///
+    ///   [0]: crate::core::Config
+    pub fn add_to<X: XConn>(self, mut wm: WindowManager<X>) -> WindowManager<X> {
+        wm.state.config.set_border_style(self.border_style());
+        wm.state.add_extension(self);
+
+        wm
+    }
+}
+
+/// The currently active [Theme], if one has been added to the window manager.
+pub fn current_theme<X: XConn>(state: &State<X>) -> Option<Theme> {
+    state.extension::<Theme>().ok().map(|t| t.borrow().clone())
+}
+
+/// Make the given [Theme] the active theme, applying its border style to all managed clients
+/// immediately.
+///
+/// UI elements following the active theme are restyled when the window manager next refreshes,
+/// which is triggered by running this action.
+pub fn set_theme<X: XConn>(theme: Theme) -> Box<dyn KeyEventHandler<X>> {
+    key_handler(move |s: &mut State<X>, x: &X| {
+        s.config.set_border_style(theme.border_style());
+        match s.extension::<Theme>() {
+            Ok(t) => *t.borrow_mut() = theme.clone(),
+            Err(_) => s.add_extension(theme.clone()),
+        }
+        x.update_borders(s)?;
+
+        x.refresh(s)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::bindings::KeyCode, x::TestConn};
+    use std::collections::HashMap;
+
+    const SET_LIGHT: KeyCode = KeyCode { mask: 0, code: 10 };
+
+    #[test]
+    fn add_to_sets_the_border_style() {
+        let wm = TestConn::new().start_headless_wm(HashMap::new(), |wm| Theme::light().add_to(wm));
+
+        assert_eq!(
+            wm.state.config.border_style(),
+            Theme::light().border_style()
+        );
+        assert_eq!(current_theme(&wm.state), Some(Theme::light()));
+    }
+
+    #[test]
+    fn set_theme_replaces_the_active_theme() {
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(SET_LIGHT, set_theme(Theme::light()));
+
+        let mut wm = TestConn::new().start_headless_wm(bindings, |wm| Theme::dark().add_to(wm));
+
+        wm.conn().press_key(SET_LIGHT);
+        wm.run_pending_events();
+
+        assert_eq!(current_theme(&wm.state), Some(Theme::light()));
+        assert_eq!(wm.state.config.focused_border, Theme::light().highlight);
+    }
+
+    #[test]
+    fn derived_themes_have_readable_text() {
+        let dark = Theme::from_colors("derived", 0x1d2021ff.into(), 0x458588ff.into());
+        let light = Theme::from_colors("derived", 0xf9f5d7ff.into(), 0x458588ff.into());
+
+        assert!(dark.bg.contrast_ratio(&dark.fg) > 7.0);
+        assert!(light.bg.contrast_ratio(&light.fg) > 7.0);
+    }
+}
@@ -0,0 +1,207 @@
+//! Running blocking work on a background thread without freezing the window manager.
+//!
+//! Key bindings and hooks are run on the main event loop, so any slow work they carry out (such
+//! as HTTP requests or reading large files) prevents penrose from responding to new events until
+//! it completes. [run_in_background] runs the slow part of an action on its own thread and then
+//! passes the result to a callback that is run on the main event loop with access to the window
+//! manager [State] and [XConn].
+//!
+//! ```no_run
+//! use penrose::{
+//!     builtin::actions::key_handler,
+//!     core::{bindings::KeyEventHandler, Config, State, WindowManager},
+//!     extensions::util::worker::{add_background_worker, run_in_background},
+//!     util::spawn_for_output,
+//!     x::XConnExt,
+//!     x11rb::RustConn,
+//! };
+//! use std::collections::HashMap;
+//!
+//! fn fetch_weather() -> Box<dyn KeyEventHandler<RustConn>> {
+//!     key_handler(|state: &mut State<RustConn>, _: &RustConn| {
+//!         run_in_background(
+//!             state,
+//!             || spawn_for_output("curl -s wttr.in?format=3").unwrap_or_default(),
+//!             |weather: String, state: &mut State<RustConn>, x: &RustConn| {
+//!                 println!("{weather}");
+//!                 x.refresh(state)
+//!             },
+//!         )
+//!     })
+//! }
+//!
+//! let conn = RustConn::new()?;
+//! let wm = WindowManager::new(Config::default(), HashMap::new(), HashMap::new(), conn)?;
+//! let wm = add_background_worker(wm)?;
+//! # Ok::<(), penrose::Error>(())
+//! ```
+use crate::{
+    core::{State, WindowManager},
+    x::{XConn, XEvent},
+    Result,
+};
+use std::{
+    fmt,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+use tracing::error;
+
+/// The type of the client message used to wake up the window manager event loop when
+/// background work has completed.
+pub const WAKEUP_ATOM: &str = "_PENROSE_WORKER_WAKEUP";
+
+type Callback<X> = Box<dyn FnOnce(&mut State<X>, &X) -> Result<()> + Send>;
+
+/// A state extension for running callbacks on the main event loop once work being carried out
+/// on a background thread has completed.
+///
+/// Completed work is checked for each time an event is processed, with the `wake` function
+/// provided to [BackgroundWorker::new] being used to trigger an event once the work is done.
+pub struct BackgroundWorker<X: XConn> {
+    tx: Sender<Callback<X>>,
+    rx: Receiver<Callback<X>>,
+    wake: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl<X: XConn> fmt::Debug for BackgroundWorker<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackgroundWorker").finish()
+    }
+}
+
+impl<X: XConn + 'static> BackgroundWorker<X> {
+    /// Create a new [BackgroundWorker] that calls `wake` from the background thread whenever
+    /// work has completed in order to wake up the main event loop.
+    pub fn new<F>(wake: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let (tx, rx) = channel();
+
+        Self {
+            tx,
+            rx,
+            wake: Arc::new(wake),
+        }
+    }
+
+    /// Add this [BackgroundWorker] to the given [WindowManager] along with the event hook used
+    /// to run completed callbacks.
+    pub fn add_to(self, mut wm: WindowManager<X>) -> WindowManager<X> {
+        wm.state.add_extension(self);
+        wm.state.config.compose_or_set_event_hook(event_hook);
+
+        wm
+    }
+}
+
+/// Add a [BackgroundWorker] to the given [WindowManager] that wakes the main event loop using a
+/// [Waker][crate::x11rb::Waker].
+#[cfg(feature = "x11rb")]
+pub fn add_background_worker<X>(wm: WindowManager<X>) -> Result<WindowManager<X>>
+where
+    X: XConn + 'static,
+{
+    let waker = crate::x11rb::Waker::new(WAKEUP_ATOM)?;
+
+    Ok(BackgroundWorker::new(move || waker.wake()).add_to(wm))
+}
+
+/// Run `work` on a background thread and then pass its result to `then` on the main event loop.
+///
+/// Errors returned by `then` are logged. This requires a [BackgroundWorker] to have been added
+/// to the window manager.
+pub fn run_in_background<X, T, W, F>(state: &State<X>, work: W, then: F) -> Result<()>
+where
+    X: XConn + 'static,
+    T: Send + 'static,
+    W: FnOnce() -> T + Send + 'static,
+    F: FnOnce(T, &mut State<X>, &X) -> Result<()> + Send + 'static,
+{
+    let s = state.extension::<BackgroundWorker<X>>()?;
+    let worker = s.borrow();
+    let tx = worker.tx.clone();
+    let wake = worker.wake.clone();
+
+    thread::spawn(move || {
+        let t = work();
+        let callback: Callback<X> = Box::new(move |state, x| then(t, state, x));
+        if tx.send(callback).is_ok() {
+            (wake)();
+        }
+    });
+
+    Ok(())
+}
+
+/// Run the callbacks for any background work that has completed.
+pub fn event_hook<X: XConn + 'static>(event: &XEvent, state: &mut State<X>, x: &X) -> Result<bool> {
+    let s = state.extension::<BackgroundWorker<X>>()?;
+    let callbacks: Vec<Callback<X>> = s.borrow().rx.try_iter().collect();
+
+    for callback in callbacks {
+        if let Err(e) = callback(state, x) {
+            error!(%e, "error running background work callback");
+        }
+    }
+
+    match event {
+        XEvent::ClientMessage(m) if m.dtype == WAKEUP_ATOM => Ok(false),
+        _ => Ok(true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::Config,
+        x::{
+            event::{ClientEventMask, ClientMessage},
+            TestConn,
+        },
+    };
+    use std::{collections::HashMap, sync::mpsc::channel, time::Duration};
+
+    #[test]
+    fn callbacks_are_run_on_the_main_loop() {
+        let (woken_tx, woken_rx) = channel();
+        let woken_tx = std::sync::Mutex::new(woken_tx);
+        let wm = WindowManager::new(
+            Config::default(),
+            HashMap::new(),
+            HashMap::new(),
+            TestConn::new(),
+        )
+        .unwrap();
+        let mut wm =
+            BackgroundWorker::new(move || woken_tx.lock().unwrap().send(()).unwrap()).add_to(wm);
+        wm.start_headless().unwrap();
+
+        run_in_background(
+            &wm.state,
+            || "3".to_owned(),
+            |tag: String, state: &mut State<TestConn>, _: &TestConn| {
+                state.client_set.focus_tag(tag);
+                Ok(())
+            },
+        )
+        .unwrap();
+        woken_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        let wakeup = ClientMessage::new(
+            wm.state.root(),
+            ClientEventMask::SubstructureNotify,
+            WAKEUP_ATOM,
+            [0u32; 5].into(),
+        );
+        wm.conn().push_event(XEvent::ClientMessage(wakeup));
+        wm.run_pending_events();
+
+        assert_eq!(wm.state.client_set.current_tag(), "3");
+    }
+}
@@ -71,6 +71,7 @@ use serde::{Deserialize, Serialize};
 use std::any::TypeId;
 
 pub mod builtin;
+mod color_names;
 pub mod core;
 pub mod extensions;
 mod macros;
@@ -79,6 +80,8 @@ pub mod util;
 pub mod x;
 #[cfg(feature = "x11rb")]
 pub mod x11rb;
+#[cfg(feature = "xcb")]
+pub mod xcb;
 
 #[doc(inline)]
 pub use crate::core::Xid;
@@ -95,6 +98,15 @@ pub enum Error {
     #[error("{0}")]
     Custom(String),
 
+    /// The connection to the X server was lost and could not be recovered
+    #[error("connection to the X server was lost: {0}")]
+    ConnectionLost(String),
+
+    /// An error was returned when communicating over D-Bus
+    #[cfg(feature = "dbus")]
+    #[error(transparent)]
+    DBus(#[from] zbus::Error),
+
     /// There were not enough workspaces to cover the number of connected screens
     #[error("Only {n_ws} workspaces were provided but at least {n_screens} are required")]
     InsufficientWorkspaces {
@@ -104,6 +116,17 @@ pub enum Error {
         n_screens: usize,
     },
 
+    /// One or more problems were found when building a [Config][crate::core::Config] using a
+    /// [ConfigBuilder][crate::core::config::ConfigBuilder]
+    #[error(
+        "Invalid config: {}",
+        .problems.iter().map(|p| p.to_string()).collect::<Vec<_>>().join("; ")
+    )]
+    InvalidConfig {
+        /// Every problem that was found
+        problems: Vec<crate::core::config::ConfigProblem>,
+    },
+
     /// Data received as part of a client message had an invalid format
     #[error("invalid client message data: format={format}")]
     InvalidClientMessage {
@@ -111,6 +134,23 @@ pub enum Error {
         format: u8,
     },
 
+    /// Attempt to create a `Color` from a string that is not in a supported format
+    #[error("Invalid color: '{color}'")]
+    InvalidColor {
+        /// The string that was used
+        color: String,
+    },
+
+    /// A tracing filter directive was unable to be parsed
+    #[cfg(feature = "logging")]
+    #[error("Invalid log filter '{filter}': {reason}")]
+    InvalidLogFilter {
+        /// The filter that was used
+        filter: String,
+        /// Why parsing failed
+        reason: String,
+    },
+
     /// Attempt to create a `Color` from an invalid hex string
     #[error("Invalid Hex color code: '{hex_code}'")]
     InvalidHexColor {
@@ -144,6 +184,11 @@ pub enum Error {
         prop: String,
     },
 
+    /// Unable to serialize or deserialize JSON data
+    #[cfg(feature = "serde")]
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
     /// Duplicate tags were provided for one or more workspaces
     #[error("The following tags have been used multiple times for different workspaces: {tags:?}")]
     NonUniqueTags {
@@ -151,10 +196,28 @@ pub enum Error {
         tags: Vec<String>,
     },
 
+    /// There is no 32-bit TrueColor visual available for creating ARGB windows
+    #[error("No 32-bit TrueColor visual is available for creating ARGB windows")]
+    NoArgbVisual,
+
     /// Penrose is running without any screens to connect to
     #[error("There are no screens available")]
     NoScreens,
 
+    /// A global tracing subscriber has already been installed
+    #[cfg(feature = "logging")]
+    #[error("A global tracing subscriber has already been set")]
+    LoggingAlreadyInitialised,
+
+    /// A user provided hook, layout or binding panicked
+    #[error("{what} panicked: {message}")]
+    Panicked {
+        /// The hook, layout or binding that panicked
+        what: String,
+        /// The message the panic was raised with
+        message: String,
+    },
+
     /// ParseIntError
     #[error(transparent)]
     ParseInt(#[from] std::num::ParseIntError),
@@ -163,10 +226,32 @@ pub enum Error {
     #[error("Error initialising randr: {0}")]
     Randr(String),
 
+    /// Unable to deserialize a config file in the RON format
+    #[cfg(feature = "configfile")]
+    #[error(transparent)]
+    Ron(#[from] ron::error::SpannedError),
+
+    /// Unable to deserialize a config file in the TOML format
+    #[cfg(feature = "configfile")]
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+
+    /// A config file was given that does not have a supported file extension
+    #[cfg(feature = "configfile")]
+    #[error("'{path}' is not a .toml or .ron file")]
+    UnknownConfigFileFormat {
+        /// The path to the config file
+        path: String,
+    },
+
     /// An operation was requested on a client window that is unknown
     #[error("Client {0} is not in found")]
     UnknownClient(Xid),
 
+    /// A screen index was referenced that does not correspond to a connected screen
+    #[error("There is no screen with index {0}")]
+    UnknownScreen(usize),
+
     /// A keybinding has been specified for an unknown key name for this machine.
     #[error("'{name}' is not a known key name")]
     UnknownKeyName {
@@ -188,6 +273,13 @@ pub enum Error {
         button: u8,
     },
 
+    /// An [XConn][crate::x::XConn] implementation does not support the requested operation
+    #[error("{method} is not supported by this XConn implementation")]
+    Unsupported {
+        /// The name of the unsupported method
+        method: &'static str,
+    },
+
     /// An attempt was made to fetch a state extension for a type that has not been stored
     #[error("{type_id:?} was requested as a state extension but not found")]
     UnknownStateExtension {
@@ -195,6 +287,17 @@ pub enum Error {
         type_id: TypeId,
     },
 
+    /// A workspace tag was referenced that is not known to the window manager
+    #[error("'{tag}' is not a known workspace tag")]
+    UnknownTag {
+        /// The unrecognised tag
+        tag: String,
+    },
+
+    /// Another window manager is already running
+    #[error("Another window manager is already running")]
+    WmAlreadyRunning,
+
     // TODO: These backend specific errors should be abstracted out to a
     //       set of common error variants that they can be mapped to without
     //       needing to extend the enum conditionally when flags are enabled
@@ -222,6 +325,46 @@ pub enum Error {
     #[cfg(feature = "x11rb")]
     #[error("X11 error: {0:?}")]
     X11rbX11Error(X11Error),
+
+    /// An error that occurred on an xcb connection to the X server
+    #[cfg(feature = "xcb")]
+    #[error(transparent)]
+    XcbConnection(#[from] ::xcb::ConnError),
+
+    /// An error returned by the X server in response to an xcb request
+    #[cfg(feature = "xcb")]
+    #[error(transparent)]
+    XcbProtocol(#[from] ::xcb::ProtocolError),
+}
+
+#[cfg(feature = "xcb")]
+impl From<::xcb::Error> for Error {
+    fn from(e: ::xcb::Error) -> Self {
+        match e {
+            ::xcb::Error::Connection(e) => Error::XcbConnection(e),
+            ::xcb::Error::Protocol(e) => Error::XcbProtocol(e),
+        }
+    }
+}
+
+impl Error {
+    /// Whether or not this error indicates that the connection to the X server has been broken,
+    /// as opposed to an error with an individual request.
+    pub fn is_connection_error(&self) -> bool {
+        match self {
+            Error::ConnectionLost(_) => true,
+
+            #[cfg(feature = "x11rb")]
+            Error::X11rbConnection(_)
+            | Error::X11rbReplyError(ReplyError::ConnectionError(_))
+            | Error::X11rbReplyOrIdError(ReplyOrIdError::ConnectionError(_)) => true,
+
+            #[cfg(feature = "xcb")]
+            Error::XcbConnection(_) => true,
+
+            _ => false,
+        }
+    }
 }
 
 /// A Result where the error type is a penrose [Error]
@@ -235,11 +378,38 @@ pub struct Color {
 }
 
 impl Color {
+    /// Opaque black
+    pub const BLACK: Color = Color {
+        rgba_hex: 0x000000FF,
+    };
+
+    /// Opaque white
+    pub const WHITE: Color = Color {
+        rgba_hex: 0xFFFFFFFF,
+    };
+
     /// Create a new Color from a hex encoded u32: 0xRRGGBBAA
     pub fn new_from_hex(rgba_hex: u32) -> Self {
         Self { rgba_hex }
     }
 
+    /// Look up one of the named colors from the X11 color database (`rgb.txt`).
+    ///
+    /// Names are matched ignoring case and spaces, so "Light Sky Blue" and "lightskyblue" are
+    /// the same color.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name: String = name
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        color_names::X11_COLORS
+            .binary_search_by(|(n, _)| n.cmp(&name.as_str()))
+            .ok()
+            .map(|i| Self::new_from_hex((color_names::X11_COLORS[i].1 << 8) + 0xFF))
+    }
+
     /// The RGB information of this color as 0.0-1.0 range floats representing
     /// proportions of 255 for each of R, G, B
     pub fn rgb(&self) -> (f64, f64, f64) {
@@ -280,6 +450,94 @@ impl Color {
     pub fn argb_u32(&self) -> u32 {
         ((self.rgba_hex & 0x000000FF) << 24) + (self.rgba_hex >> 8)
     }
+
+    /// 0xAARRGGBB representation of this Color with the red, green and blue channels
+    /// premultiplied by the alpha channel, as expected by compositors for ARGB windows.
+    pub fn premultiplied_argb_u32(&self) -> u32 {
+        let [r, g, b, a] = self.rgba_hex.to_be_bytes();
+        let mul = |c: u8| (c as u32 * a as u32 / 255) as u8;
+
+        u32::from_be_bytes([a, mul(r), mul(g), mul(b)])
+    }
+
+    /// The alpha channel of this Color (0 being fully transparent and 255 fully opaque)
+    pub fn alpha(&self) -> u8 {
+        (self.rgba_hex & 0xFF) as u8
+    }
+
+    /// A copy of this Color with its alpha channel set to the given value.
+    pub fn with_alpha(&self, alpha: u8) -> Self {
+        Self::new_from_hex((self.rgba_hex & 0xFFFFFF00) + alpha as u32)
+    }
+
+    /// Mix this Color with another, where an `amount` of 0.0 is this Color and 1.0 is `other`.
+    ///
+    /// The alpha channel of this Color is preserved.
+    pub fn mix(&self, other: Color, amount: f64) -> Self {
+        let amount = amount.clamp(0.0, 1.0);
+        let [r1, g1, b1, a] = self.rgba_hex.to_be_bytes();
+        let [r2, g2, b2, _] = other.rgba_hex.to_be_bytes();
+        let mix = |c1: u8, c2: u8| (c1 as f64 + (c2 as f64 - c1 as f64) * amount).round() as u8;
+
+        Self::new_from_hex(u32::from_be_bytes([
+            mix(r1, r2),
+            mix(g1, g2),
+            mix(b1, b2),
+            a,
+        ]))
+    }
+
+    /// A lighter version of this Color, mixed with white by the given amount (0.0 - 1.0).
+    pub fn lighten(&self, amount: f64) -> Self {
+        self.mix(Self::WHITE, amount)
+    }
+
+    /// A darker version of this Color, mixed with black by the given amount (0.0 - 1.0).
+    pub fn darken(&self, amount: f64) -> Self {
+        self.mix(Self::BLACK, amount)
+    }
+
+    /// The relative luminance of this Color as defined by the
+    /// [WCAG](https://www.w3.org/TR/WCAG21/#dfn-relative-luminance), ignoring alpha.
+    pub fn luminance(&self) -> f64 {
+        let linear = |c: f64| {
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let (r, g, b) = self.rgb();
+
+        0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+    }
+
+    /// The contrast ratio between this Color and another, from 1.0 (no contrast) to 21.0
+    /// (black and white).
+    pub fn contrast_ratio(&self, other: &Color) -> f64 {
+        let (l1, l2) = (self.luminance(), other.luminance());
+
+        (l1.max(l2) + 0.05) / (l1.min(l2) + 0.05)
+    }
+
+    /// Of the given candidates, the one with the highest contrast against this Color when used
+    /// as a foreground color on top of it.
+    pub fn most_readable(&self, candidates: &[Color]) -> Option<Color> {
+        candidates
+            .iter()
+            .copied()
+            .max_by(|a, b| self.contrast_ratio(a).total_cmp(&self.contrast_ratio(b)))
+    }
+
+    /// Either black or white, whichever is more readable as a foreground color on top of this
+    /// Color.
+    pub fn readable_fg(&self) -> Color {
+        if self.contrast_ratio(&Self::BLACK) >= self.contrast_ratio(&Self::WHITE) {
+            Self::BLACK
+        } else {
+            Self::WHITE
+        }
+    }
 }
 
 impl From<u32> for Color {
@@ -316,18 +574,70 @@ impl TryFrom<String> for Color {
     }
 }
 
+fn parse_hex(s: &str, digits: &str) -> Result<Color> {
+    let hex = u32::from_str_radix(digits, 16)?;
+
+    if digits.len() == 6 {
+        Ok(Color::new_from_hex((hex << 8) + 0xFF))
+    } else if digits.len() == 8 {
+        Ok(Color::new_from_hex(hex))
+    } else {
+        Err(Error::InvalidHexColor { hex_code: s.into() })
+    }
+}
+
+fn parse_percentage(s: &str) -> Option<u8> {
+    let p: f64 = s.strip_suffix('%')?.trim().parse().ok()?;
+
+    (0.0..=100.0)
+        .contains(&p)
+        .then(|| (p / 100.0 * 255.0).round() as u8)
+}
+
+// rgb(r, g, b) or rgba(r, g, b, a) with channels in the range 0-255 (or as percentages) and
+// alpha in the range 0.0-1.0 (or as a percentage)
+fn parse_rgb_function(s: &str) -> Option<Color> {
+    let (name, args) = s.strip_suffix(')')?.split_once('(')?;
+    let args: Vec<&str> = args.split(',').map(str::trim).collect();
+
+    let channel = |s: &str| parse_percentage(s).or_else(|| s.parse().ok());
+    let a = match (name.trim(), args.len()) {
+        ("rgb", 3) => 0xFF,
+        ("rgba", 4) => parse_percentage(args[3]).or_else(|| {
+            let a: f64 = args[3].parse().ok()?;
+            (0.0..=1.0).contains(&a).then(|| (a * 255.0).round() as u8)
+        })?,
+        _ => return None,
+    };
+    let (r, g, b) = (channel(args[0])?, channel(args[1])?, channel(args[2])?);
+
+    Some(Color::new_from_hex(u32::from_be_bytes([r, g, b, a])))
+}
+
+/// Colors can be parsed from strings in the following formats:
+///   - hex strings in the form `#RRGGBB` or `#RRGGBBAA` (with or without the leading `#`)
+///   - `rgb(r, g, b)` or `rgba(r, g, b, a)` with red, green and blue given as either 0-255 or a
+///     percentage and alpha given as either 0.0-1.0 or a percentage
+///   - the name of an X11 color such as `steelblue` (see [Color::from_name])
+///
+/// Colors without an alpha channel are treated as being fully opaque.
 impl TryFrom<&str> for Color {
     type Error = Error;
 
     fn try_from(s: &str) -> Result<Self> {
-        let hex = u32::from_str_radix(s.strip_prefix('#').unwrap_or(s), 16)?;
-
-        if s.len() == 7 {
-            Ok(Self::new_from_hex((hex << 8) + 0xFF))
-        } else if s.len() == 9 {
-            Ok(Self::new_from_hex(hex))
+        let s = s.trim();
+        let invalid = || Error::InvalidColor { color: s.into() };
+
+        if let Some(digits) = s.strip_prefix('#') {
+            parse_hex(s, digits)
+        } else if s.contains('(') {
+            parse_rgb_function(s).ok_or_else(invalid)
+        } else if let Some(c) = Self::from_name(s) {
+            Ok(c)
+        } else if s.chars().all(|c| c.is_ascii_hexdigit()) {
+            parse_hex(s, s)
         } else {
-            Err(Error::InvalidHexColor { hex_code: s.into() })
+            Err(invalid())
         }
     }
 }
@@ -346,4 +656,101 @@ mod tests {
 
         assert_eq!(&c.as_rgb_hex_string(), expected);
     }
+
+    #[test_case("#AABBCC", 0xAABBCCFF; "rgb with hash")]
+    #[test_case("AABBCC", 0xAABBCCFF; "rgb without hash")]
+    #[test_case("#AABBCC80", 0xAABBCC80; "rgba with hash")]
+    #[test_case("aabbcc80", 0xAABBCC80; "rgba without hash")]
+    #[test_case("#00000000", 0x00000000; "fully transparent")]
+    #[test]
+    fn color_parsing_works(s: &str, expected: u32) {
+        let c = Color::try_from(s).unwrap();
+
+        assert_eq!(c.rgba_u32(), expected);
+    }
+
+    #[test_case("rgb(170, 187, 204)", 0xAABBCCFF; "rgb function")]
+    #[test_case("rgba(170,187,204,0.5)", 0xAABBCC80; "rgba function")]
+    #[test_case("rgba(100%, 0%, 50%, 100%)", 0xFF0080FF; "percentages")]
+    #[test_case("steelblue", 0x4682B4FF; "x11 name")]
+    #[test_case("Light Sky Blue", 0x87CEFAFF; "x11 name with case and spaces")]
+    #[test_case("gray50", 0x7F7F7FFF; "numbered x11 name")]
+    #[test_case(" #AABBCC ", 0xAABBCCFF; "surrounding whitespace")]
+    #[test]
+    fn extended_color_parsing_works(s: &str, expected: u32) {
+        let c = Color::try_from(s).unwrap();
+
+        assert_eq!(c.rgba_u32(), expected);
+    }
+
+    #[test_case("#ABC"; "too short")]
+    #[test_case("#AABBCCDDEE"; "too long")]
+    #[test_case("#GGHHII"; "invalid digits")]
+    #[test_case("rgb(1, 2)"; "too few channels")]
+    #[test_case("rgb(1, 2, 3, 4)"; "alpha for rgb")]
+    #[test_case("rgb(256, 0, 0)"; "channel out of range")]
+    #[test_case("rgba(0, 0, 0, 1.5)"; "alpha out of range")]
+    #[test_case("notacolor"; "unknown name")]
+    #[test]
+    fn invalid_color_strings_error(s: &str) {
+        assert!(Color::try_from(s).is_err());
+    }
+
+    #[test_case(0xFF8040FF, 0xFFFF8040; "opaque is unchanged")]
+    #[test_case(0xFF804000, 0x00000000; "transparent is zeroed")]
+    #[test_case(0xFF808080, 0x80804040; "half alpha halves channels")]
+    #[test]
+    fn premultiplied_argb_is_correct(rgba_hex: u32, expected: u32) {
+        let c: Color = rgba_hex.into();
+
+        assert_eq!(c.premultiplied_argb_u32(), expected);
+    }
+
+    #[test]
+    fn with_alpha_only_changes_alpha() {
+        let c = Color::new_from_hex(0x11223344).with_alpha(0xAA);
+
+        assert_eq!(c.rgba_u32(), 0x112233AA);
+    }
+
+    #[test_case(0.0, 0x804020FF; "zero is unchanged")]
+    #[test_case(0.5, 0xC0A090FF; "half")]
+    #[test_case(1.0, 0xFFFFFFFF; "one is white")]
+    #[test]
+    fn lighten_mixes_with_white(amount: f64, expected: u32) {
+        let c = Color::new_from_hex(0x804020FF).lighten(amount);
+
+        assert_eq!(c.rgba_u32(), expected);
+    }
+
+    #[test]
+    fn darken_mixes_with_black_and_preserves_alpha() {
+        let c = Color::new_from_hex(0x80402080).darken(0.5);
+
+        assert_eq!(c.rgba_u32(), 0x40201080);
+    }
+
+    #[test]
+    fn black_and_white_have_maximum_contrast() {
+        let ratio = Color::BLACK.contrast_ratio(&Color::WHITE);
+
+        assert!((ratio - 21.0).abs() < 1e-9);
+    }
+
+    #[test_case(0xFFFF00FF, Color::BLACK; "yellow")]
+    #[test_case(0x000080FF, Color::WHITE; "navy")]
+    #[test_case(0x282828FF, Color::WHITE; "dark grey")]
+    #[test_case(0xEBDBB2FF, Color::BLACK; "light grey")]
+    #[test]
+    fn readable_fg_is_correct(bg: u32, expected: Color) {
+        assert_eq!(Color::new_from_hex(bg).readable_fg(), expected);
+    }
+
+    #[test]
+    fn most_readable_picks_the_highest_contrast() {
+        let bg = Color::new_from_hex(0x282828FF);
+        let candidates = [0x3C3836FF.into(), 0xEBDBB2FF.into(), 0x928374FF.into()];
+
+        assert_eq!(bg.most_readable(&candidates), Some(0xEBDBB2FF.into()));
+    }
 }
@@ -80,13 +80,9 @@ macro_rules! map {
 macro_rules! manage_hooks {
     { $($query:expr => $hook:expr),+, } => {
         {
-            use $crate::core::hooks::ManageHook as _;
-            let mut _hooks = vec![];
-            $(
-                _hooks.push(
-                    $crate::core::hooks::ManageHook::boxed(($query, $hook))
-                );
-            )+
+            let _hooks = vec![
+                $($crate::core::hooks::ManageHook::boxed(($query, $hook)),)+
+            ];
             Box::new(_hooks)
         }
     };
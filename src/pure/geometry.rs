@@ -55,6 +55,16 @@ impl From<&Rect> for Point {
 
 /// An X window / screen position: top left corner + extent as percentages
 /// of the current screen containing the window.
+///
+/// Floating client positions are stored as RelativeRects so that they keep their proportions
+/// when the resolution of a screen changes or a client is moved to a different screen.
+/// ```
+/// # use penrose::pure::geometry::{Rect, RelativeRect};
+/// let r = RelativeRect::centered(0.5, 0.5);
+///
+/// assert_eq!(r.applied_to(&Rect::new(0, 0, 1920, 1080)), Rect::new(480, 270, 960, 540));
+/// assert_eq!(r.applied_to(&Rect::new(0, 0, 2560, 1440)), Rect::new(640, 360, 1280, 720));
+/// ```
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Default, Debug, PartialEq, Clone, Copy)]
 pub struct RelativeRect {
@@ -77,6 +87,35 @@ impl RelativeRect {
         }
     }
 
+    /// A RelativeRect with the given width and height ratios, centered within its parent.
+    ///
+    /// Values are clamped to be in the range 0.0 to 1.0.
+    pub fn centered(w: f64, h: f64) -> Self {
+        let (w, h) = (w.clamp(0.0, 1.0), h.clamp(0.0, 1.0));
+
+        Self::new((1.0 - w) / 2.0, (1.0 - h) / 2.0, w, h)
+    }
+
+    /// The horizontal offset from the left edge of the parent as a fraction of its width.
+    pub fn x(&self) -> f64 {
+        self.x
+    }
+
+    /// The vertical offset from the top edge of the parent as a fraction of its height.
+    pub fn y(&self) -> f64 {
+        self.y
+    }
+
+    /// The width as a fraction of the width of the parent.
+    pub fn w(&self) -> f64 {
+        self.w
+    }
+
+    /// The height as a fraction of the height of the parent.
+    pub fn h(&self) -> f64 {
+        self.h
+    }
+
     /// All available space within a given Rect
     pub fn fullscreen() -> Self {
         Self {
@@ -234,6 +273,31 @@ impl Rect {
         Self { w, h, ..*self }
     }
 
+    /// Shrink this Rect by `px` pixels on each side, keeping it centered in its current
+    /// position. Gaps that would consume the entire Rect are clamped so that the result is
+    /// never smaller than 1x1.
+    /// ```
+    /// # use penrose::pure::geometry::Rect;
+    /// let r = Rect::new(0, 0, 100, 200);
+    ///
+    /// assert_eq!(r.apply_gaps(10), Rect::new(10, 10, 80, 180));
+    /// assert_eq!(r.apply_gaps(500), Rect::new(49, 49, 2, 102));
+    /// ```
+    pub fn apply_gaps(&self, px: u32) -> Self {
+        if self.w == 0 || self.h == 0 {
+            return *self;
+        }
+
+        let px = px.min((self.w - 1) / 2).min((self.h - 1) / 2);
+
+        Self {
+            x: self.x + px,
+            y: self.y + px,
+            w: self.w - 2 * px,
+            h: self.h - 2 * px,
+        }
+    }
+
     /// Create a new [Rect] with width equal to `factor` x `self.w`
     /// ```
     /// # use penrose::pure::geometry::Rect;
@@ -317,6 +381,45 @@ impl Rect {
         }
     }
 
+    /// The region covered by both this Rect and `other`.
+    ///
+    /// Returns `None` if the two Rects do not overlap.
+    /// ```
+    /// # use penrose::pure::geometry::Rect;
+    /// let r = Rect::new(0, 0, 100, 100);
+    ///
+    /// assert_eq!(r.intersection(&Rect::new(50, 80, 100, 100)), Some(Rect::new(50, 80, 50, 20)));
+    /// assert_eq!(r.intersection(&Rect::new(100, 0, 100, 100)), None);
+    /// ```
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x = max(self.x, other.x);
+        let y = max(self.y, other.y);
+        let x2 = min(self.x + self.w, other.x + other.w);
+        let y2 = min(self.y + self.h, other.y + other.h);
+
+        if x2 <= x || y2 <= y {
+            None
+        } else {
+            Some(Rect::new(x, y, x2 - x, y2 - y))
+        }
+    }
+
+    /// The smallest Rect that contains both this Rect and `other`.
+    /// ```
+    /// # use penrose::pure::geometry::Rect;
+    /// let r = Rect::new(0, 0, 100, 100);
+    ///
+    /// assert_eq!(r.union(&Rect::new(200, 50, 100, 100)), Rect::new(0, 0, 300, 150));
+    /// ```
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = min(self.x, other.x);
+        let y = min(self.y, other.y);
+        let x2 = max(self.x + self.w, other.x + other.w);
+        let y2 = max(self.y + self.h, other.y + other.h);
+
+        Rect::new(x, y, x2 - x, y2 - y)
+    }
+
     /// Check whether this Rect is physically larger than `other` regardless
     /// of position.
     pub fn is_larger_than(&self, other: &Rect) -> bool {
@@ -371,6 +474,85 @@ impl Rect {
             .collect()
     }
 
+    /// Split this `Rect` into `n` rows that exactly cover it.
+    ///
+    /// Unlike [Rect::as_rows], any remaining pixels are distributed one at a time to the
+    /// first rows rather than being dropped. A value of 0 for `n` is treated as 1.
+    /// ```
+    /// # use penrose::pure::geometry::Rect;
+    /// let rows = Rect::new(0, 0, 100, 11).split_rows(3);
+    ///
+    /// assert_eq!(
+    ///     rows,
+    ///     vec![Rect::new(0, 0, 100, 4), Rect::new(0, 4, 100, 4), Rect::new(0, 8, 100, 3)]
+    /// );
+    /// ```
+    pub fn split_rows(&self, n: u32) -> Vec<Rect> {
+        let hs = distribute_evenly(self.h, n);
+        self.split_rows_with_heights(&hs)
+    }
+
+    /// Split this `Rect` into `n` columns that exactly cover it.
+    ///
+    /// Unlike [Rect::as_columns], any remaining pixels are distributed one at a time to the
+    /// first columns rather than being dropped. A value of 0 for `n` is treated as 1.
+    pub fn split_columns(&self, n: u32) -> Vec<Rect> {
+        let ws = distribute_evenly(self.w, n);
+        self.split_columns_with_widths(&ws)
+    }
+
+    /// Split this `Rect` into rows with heights proportional to the given weights (for
+    /// example percentages).
+    ///
+    /// Heights are rounded using the largest remainder method so the rows always exactly
+    /// cover this Rect. Negative weights are treated as 0 and if all weights are 0 the rows
+    /// are sized evenly.
+    /// ```
+    /// # use penrose::pure::geometry::Rect;
+    /// let rows = Rect::new(0, 0, 100, 100).split_rows_weighted(&[1.0, 1.0, 1.0]);
+    ///
+    /// assert_eq!(rows.iter().map(|r| r.h).collect::<Vec<_>>(), vec![34, 33, 33]);
+    /// ```
+    pub fn split_rows_weighted(&self, weights: &[f64]) -> Vec<Rect> {
+        let hs = distribute_weighted(self.h, weights);
+        self.split_rows_with_heights(&hs)
+    }
+
+    /// Split this `Rect` into columns with widths proportional to the given weights (for
+    /// example percentages).
+    ///
+    /// Widths are rounded using the largest remainder method so the columns always exactly
+    /// cover this Rect. Negative weights are treated as 0 and if all weights are 0 the
+    /// columns are sized evenly.
+    pub fn split_columns_weighted(&self, weights: &[f64]) -> Vec<Rect> {
+        let ws = distribute_weighted(self.w, weights);
+        self.split_columns_with_widths(&ws)
+    }
+
+    fn split_rows_with_heights(&self, hs: &[u32]) -> Vec<Rect> {
+        let mut y = self.y;
+
+        hs.iter()
+            .map(|&h| {
+                let r = Rect::new(self.x, y, self.w, h);
+                y += h;
+                r
+            })
+            .collect()
+    }
+
+    fn split_columns_with_widths(&self, ws: &[u32]) -> Vec<Rect> {
+        let mut x = self.x;
+
+        ws.iter()
+            .map(|&w| {
+                let r = Rect::new(x, self.y, w, self.h);
+                x += w;
+                r
+            })
+            .collect()
+    }
+
     /// Divides this rect into two columns where the first has the given width.
     ///
     /// Returns `None` if new_width is out of bounds
@@ -486,6 +668,39 @@ impl Rect {
     }
 }
 
+// Split total into n parts that sum to total, with larger parts first
+fn distribute_evenly(total: u32, n: u32) -> Vec<u32> {
+    let n = max(n, 1);
+    let (base, rem) = (total / n, total % n);
+
+    (0..n).map(|i| base + (i < rem) as u32).collect()
+}
+
+// Split total into parts proportional to the given weights using the largest remainder
+// method so that the parts always sum to total
+fn distribute_weighted(total: u32, weights: &[f64]) -> Vec<u32> {
+    let weights: Vec<f64> = weights.iter().map(|&w| w.max(0.0)).collect();
+    let sum: f64 = weights.iter().sum();
+    if weights.is_empty() || sum <= 0.0 || !sum.is_finite() {
+        return distribute_evenly(total, weights.len() as u32);
+    }
+
+    let exact: Vec<f64> = weights.iter().map(|w| w / sum * total as f64).collect();
+    let mut parts: Vec<u32> = exact.iter().map(|e| e.floor() as u32).collect();
+    let mut by_remainder: Vec<usize> = (0..parts.len()).collect();
+    by_remainder.sort_by(|&a, &b| {
+        let (ra, rb) = (exact[a] - exact[a].floor(), exact[b] - exact[b].floor());
+        rb.total_cmp(&ra).then(a.cmp(&b))
+    });
+
+    let assigned: u32 = parts.iter().sum();
+    for &i in by_remainder.iter().take((total - assigned) as usize) {
+        parts[i] += 1;
+    }
+
+    parts
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -594,6 +809,58 @@ mod tests {
         assert_eq!(r, expected);
     }
 
+    #[test_case(r(0, 0, 10, 10), r(5, 5, 10, 10), Some(r(5, 5, 5, 5)); "overlapping")]
+    #[test_case(r(0, 0, 10, 10), r(2, 2, 4, 4), Some(r(2, 2, 4, 4)); "contained")]
+    #[test_case(r(0, 0, 10, 10), r(10, 0, 10, 10), None; "touching")]
+    #[test_case(r(0, 0, 10, 10), r(20, 20, 10, 10), None; "disjoint")]
+    #[test]
+    fn intersection(r1: Rect, r2: Rect, expected: Option<Rect>) {
+        assert_eq!(r1.intersection(&r2), expected);
+        assert_eq!(r2.intersection(&r1), expected);
+    }
+
+    #[test_case(r(0, 0, 10, 10), r(5, 5, 10, 10), r(0, 0, 15, 15); "overlapping")]
+    #[test_case(r(0, 0, 10, 10), r(2, 2, 4, 4), r(0, 0, 10, 10); "contained")]
+    #[test_case(r(0, 0, 10, 10), r(20, 30, 10, 10), r(0, 0, 30, 40); "disjoint")]
+    #[test]
+    fn union(r1: Rect, r2: Rect, expected: Rect) {
+        assert_eq!(r1.union(&r2), expected);
+        assert_eq!(r2.union(&r1), expected);
+    }
+
+    #[test_case(0, vec![100]; "zero")]
+    #[test_case(1, vec![100]; "one")]
+    #[test_case(3, vec![34, 33, 33]; "uneven")]
+    #[test_case(4, vec![25, 25, 25, 25]; "even")]
+    #[test_case(7, vec![15, 15, 14, 14, 14, 14, 14]; "many")]
+    #[test]
+    fn split_rows_and_columns_cover_the_rect(n: u32, sizes: Vec<u32>) {
+        let r = r(10, 20, 100, 100);
+        let rows = r.split_rows(n);
+        let cols = r.split_columns(n);
+
+        assert_eq!(rows.iter().map(|r| r.h).collect::<Vec<_>>(), sizes);
+        assert_eq!(cols.iter().map(|r| r.w).collect::<Vec<_>>(), sizes);
+        assert_eq!(rows.iter().fold(rows[0], |acc, r| acc.union(r)), r);
+        assert_eq!(cols.iter().fold(cols[0], |acc, r| acc.union(r)), r);
+    }
+
+    #[test_case(&[50.0, 50.0], vec![51, 50]; "halves")]
+    #[test_case(&[1.0, 1.0, 1.0], vec![34, 34, 33]; "thirds")]
+    #[test_case(&[60.0, 30.0, 10.0], vec![61, 30, 10]; "percentages")]
+    #[test_case(&[0.0, 0.0], vec![51, 50]; "all zero")]
+    #[test_case(&[-1.0, 1.0], vec![0, 101]; "negative")]
+    #[test]
+    fn split_weighted_covers_the_rect(weights: &[f64], sizes: Vec<u32>) {
+        let r = r(0, 0, 101, 101);
+        let rows = r.split_rows_weighted(weights);
+        let cols = r.split_columns_weighted(weights);
+
+        assert_eq!(rows.iter().map(|r| r.h).collect::<Vec<_>>(), sizes);
+        assert_eq!(cols.iter().map(|r| r.w).collect::<Vec<_>>(), sizes);
+        assert_eq!(rows.iter().map(|r| r.h).sum::<u32>(), 101);
+    }
+
     #[test]
     fn contains_rect() {
         let r1 = Rect::new(10, 10, 50, 50);
@@ -699,6 +966,23 @@ mod tests {
         }
     }
 
+    #[test_case(1.0, 1.0, rr(0.0, 0.0, 1.0, 1.0); "fullscreen")]
+    #[test_case(0.5, 0.25, rr(0.25, 0.375, 0.5, 0.25); "smaller")]
+    #[test_case(2.0, -1.0, rr(0.0, 0.5, 1.0, 0.0); "out of bounds values are clamped")]
+    #[test]
+    fn relative_rect_centered(w: f64, h: f64, expected: RelativeRect) {
+        assert_eq!(RelativeRect::centered(w, h), expected);
+    }
+
+    #[test]
+    fn relative_positions_survive_resolution_changes() {
+        let (old, new) = (r(0, 0, 1000, 500), r(1000, 0, 2000, 1000));
+        let relative = r(100, 50, 200, 100).relative_to(&old);
+
+        assert_eq!(relative.applied_to(&new), r(1200, 100, 400, 200));
+        assert_eq!(relative.applied_to(&new).relative_to(&new), relative);
+    }
+
     #[test_case(r(0, 0, 200, 100), r(0, 0, 200, 100), rr(0.0, 0.0, 1.0, 1.0); "fullscreen")]
     #[test_case(r(0, 0, 50, 50), r(0, 0, 200, 100), rr(0.0, 0.0, 0.25, 0.5); "subregion with same xy")]
     #[test_case(r(10, 10, 50, 50), r(0, 0, 200, 100), rr(0.05, 0.1, 0.25, 0.5); "subregion with different xy")]
@@ -12,7 +12,7 @@ mod workspace;
 #[doc(inline)]
 pub use screen::Screen;
 #[doc(inline)]
-pub use stack::{Position, Stack};
+pub use stack::{FocusPolicy, Position, Stack};
 #[doc(inline)]
 pub use stack_set::StackSet;
 #[doc(inline)]
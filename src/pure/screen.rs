@@ -5,11 +5,14 @@ use crate::{
     },
     Xid,
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fmt};
 
 /// A wrapper around a single [Workspace] that includes the physical screen
 /// size as a [Rect].
-#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
 pub struct Screen<C> {
     pub(crate) index: usize,
     /// The [Workspace] current visible on this screen
@@ -17,6 +20,16 @@ pub struct Screen<C> {
     pub(crate) r: Rect,
 }
 
+impl<C> Default for Screen<C> {
+    fn default() -> Self {
+        Self {
+            index: Default::default(),
+            workspace: Default::default(),
+            r: Default::default(),
+        }
+    }
+}
+
 impl<C: fmt::Display> fmt::Display for Screen<C> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -1,8 +1,9 @@
 use crate::pop_where;
 use std::{
+    cmp::Ordering,
     collections::vec_deque::{self, VecDeque},
     fmt,
-    iter::{once, IntoIterator},
+    iter::{once, FusedIterator, IntoIterator},
     mem::{swap, take},
 };
 
@@ -41,6 +42,16 @@ pub enum Position {
     Tail,
 }
 
+/// Whether or not an element inserted into a [Stack] should take focus.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum FocusPolicy {
+    /// The inserted element becomes the new focus
+    #[default]
+    Take,
+    /// The element that currently holds focus retains it
+    Keep,
+}
+
 /// A [Stack] can be thought of as a linked list with a hole punched in it to mark
 /// a single element that currently holds focus (though in practice it is implemented
 /// using a [VecDeque] for efficiency purposes). By convention, the main element is
@@ -74,6 +85,14 @@ impl<T: fmt::Display> fmt::Display for Stack<T> {
     }
 }
 
+// The internal location of an element within a Stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Loc {
+    Up(usize),
+    Focus,
+    Down(usize),
+}
+
 impl<T> Stack<T> {
     /// Create a new Stack specifying the focused element and and elements
     /// above and below it.
@@ -104,10 +123,7 @@ impl<T> Stack<T> {
     {
         let mut it = iter.into_iter();
 
-        let focus = match it.next() {
-            Some(t) => t,
-            None => return None,
-        };
+        let focus = it.next()?;
 
         Some(Self {
             up: VecDeque::default(),
@@ -196,6 +212,171 @@ impl<T> Stack<T> {
         self.down.back().unwrap_or(&self.focus)
     }
 
+    /// Return a reference to the element at position `n` in this [Stack], counting from
+    /// zero at the head, if there is one.
+    ///
+    /// ```
+    /// # use penrose::stack;
+    /// let s = stack!([1, 2], 3, [4, 5]);
+    ///
+    /// assert_eq!(s.get(0), Some(&1));
+    /// assert_eq!(s.get(2), Some(&3));
+    /// assert_eq!(s.get(5), None);
+    /// ```
+    pub fn get(&self, n: usize) -> Option<&T> {
+        match self.locate(n) {
+            Loc::Up(i) => self.up.get(i),
+            Loc::Focus => Some(&self.focus),
+            Loc::Down(i) => self.down.get(i),
+        }
+    }
+
+    /// The position of the focused element in this [Stack], counting from zero at the head.
+    pub fn focused_index(&self) -> usize {
+        self.up.len()
+    }
+
+    /// Move focus to the element at position `n` in this [Stack], counting from zero at the
+    /// head. The order of the elements in the stack is unchanged.
+    ///
+    /// If there is no element at position `n` then the stack is unmodified.
+    pub fn focus_nth(&mut self, n: usize) -> &mut Self {
+        if n >= self.len() {
+            return self;
+        }
+
+        while self.up.len() > n {
+            self.focus_up();
+        }
+        while self.up.len() < n {
+            self.focus_down();
+        }
+
+        self
+    }
+
+    /// Swap the elements at positions `i` and `j` in this [Stack], counting from zero at
+    /// the head. The currently focused element is maintained by this operation.
+    ///
+    /// If either position is out of bounds then the stack is unmodified.
+    ///
+    /// ```
+    /// # use penrose::stack;
+    /// let mut s = stack!([1, 2], 3, [4, 5]);
+    /// s.swap_nth(2, 4);
+    ///
+    /// assert_eq!(s, stack!([1, 2, 5, 4], 3));
+    /// ```
+    pub fn swap_nth(&mut self, i: usize, j: usize) -> &mut Self {
+        let n = self.len();
+        if i == j || i >= n || j >= n {
+            return self;
+        }
+
+        let focus = self.focused_index();
+        match (self.locate(i), self.locate(j)) {
+            (Loc::Up(a), Loc::Up(b)) => self.up.swap(a, b),
+            (Loc::Down(a), Loc::Down(b)) => self.down.swap(a, b),
+            (Loc::Up(a), Loc::Down(b)) | (Loc::Down(b), Loc::Up(a)) => {
+                swap(&mut self.up[a], &mut self.down[b])
+            }
+            (Loc::Up(a), Loc::Focus) | (Loc::Focus, Loc::Up(a)) => {
+                swap(&mut self.focus, &mut self.up[a])
+            }
+            (Loc::Down(a), Loc::Focus) | (Loc::Focus, Loc::Down(a)) => {
+                swap(&mut self.focus, &mut self.down[a])
+            }
+            (Loc::Focus, Loc::Focus) => (),
+        }
+
+        if focus == i {
+            self.focus_nth(j)
+        } else if focus == j {
+            self.focus_nth(i)
+        } else {
+            self
+        }
+    }
+
+    /// Move the focused element to position `n` in this [Stack], counting from zero at the
+    /// head. The relative order of the remaining elements is unchanged and focus stays with
+    /// the moved element.
+    ///
+    /// If there is no position `n` in the stack then it is unmodified.
+    ///
+    /// ```
+    /// # use penrose::stack;
+    /// let mut s = stack!([1, 2], 3, [4, 5]);
+    /// s.move_focused_to_index(0);
+    ///
+    /// assert_eq!(s, stack!(3, [1, 2, 4, 5]));
+    /// ```
+    pub fn move_focused_to_index(&mut self, n: usize) -> &mut Self {
+        if n >= self.len() {
+            return self;
+        }
+
+        while self.up.len() > n {
+            self.swap_up();
+        }
+        while self.up.len() < n {
+            self.swap_down();
+        }
+
+        self
+    }
+
+    /// Sort the elements of this [Stack] using the given comparison function. The sort is
+    /// stable and the currently focused element is maintained by this operation.
+    ///
+    /// ```
+    /// # use penrose::stack;
+    /// let mut s = stack!([5, 1], 3, [4, 2]);
+    /// s.sort_by(|a, b| a.cmp(b));
+    ///
+    /// assert_eq!(s, stack!([1, 2], 3, [4, 5]));
+    /// ```
+    pub fn sort_by<F>(&mut self, mut cmp: F) -> &mut Self
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        // Elements that compare equal to the focus keep their position relative to it
+        let mut before = 0;
+        let mut others = Vec::with_capacity(self.len() - 1);
+        for (is_up, t) in self
+            .up
+            .drain(..)
+            .rev()
+            .map(|t| (true, t))
+            .chain(self.down.drain(..).map(|t| (false, t)))
+        {
+            match cmp(&t, &self.focus) {
+                Ordering::Less => before += 1,
+                Ordering::Equal if is_up => before += 1,
+                _ => (),
+            }
+            others.push(t);
+        }
+
+        others.sort_by(&mut cmp);
+        self.down = others.split_off(before).into();
+        self.up = others.into_iter().rev().collect();
+
+        self
+    }
+
+    // Where the element at position n (counting from the head) is stored.
+    // Positions past the tail of the stack map to an out of bounds index into down.
+    fn locate(&self, n: usize) -> Loc {
+        let n_up = self.up.len();
+
+        match n.cmp(&n_up) {
+            Ordering::Less => Loc::Up(n_up - 1 - n),
+            Ordering::Equal => Loc::Focus,
+            Ordering::Greater => Loc::Down(n - n_up - 1),
+        }
+    }
+
     /// Swap the current head element with the focused element in the
     /// stack order. Focus stays with the original focused element.
     pub fn swap_focus_and_head(&mut self) -> &mut Self {
@@ -286,6 +467,24 @@ impl<T> Stack<T> {
         self
     }
 
+    /// Insert the given element at the requested [Position] in the [Stack], either moving focus
+    /// to it or retaining the current focus depending on the given [FocusPolicy].
+    ///
+    /// When retaining focus, an element inserted at [Position::Focus] is placed directly above
+    /// the current focus so that the resulting order matches that of [Stack::insert_at].
+    pub fn insert_at_with_focus(&mut self, pos: Position, t: T, policy: FocusPolicy) -> &mut Self {
+        use Position::*;
+
+        match (pos, policy) {
+            (Focus, FocusPolicy::Keep) => self.insert_at(Before, t),
+            (_, FocusPolicy::Keep) | (Focus, FocusPolicy::Take) => self.insert_at(pos, t),
+            (Before, FocusPolicy::Take) => self.insert_at(pos, t).focus_up(),
+            (After, FocusPolicy::Take) => self.insert_at(pos, t).focus_down(),
+            (Head, FocusPolicy::Take) => self.insert_at(pos, t).focus_head(),
+            (Tail, FocusPolicy::Take) => self.insert_at(pos, t).focus_tail(),
+        }
+    }
+
     /// Remove the focused element of this Stack. If this was the only element then
     /// the stack is dropped and None is returned.
     pub fn remove_focused(mut self) -> (T, Option<Self>) {
@@ -329,6 +528,56 @@ impl<T> Stack<T> {
         }
     }
 
+    /// Split this [Stack] into the elements above the focus, the focused element and the
+    /// elements below the focus. Both halves are returned in stack order (head to tail).
+    ///
+    /// ```
+    /// # use penrose::stack;
+    /// let s = stack!([1, 2], 3, [4, 5]);
+    ///
+    /// assert_eq!(s.split_at_focus(), (vec![1, 2], 3, vec![4, 5]));
+    /// ```
+    pub fn split_at_focus(self) -> (Vec<T>, T, Vec<T>) {
+        (
+            self.up.into_iter().rev().collect(),
+            self.focus,
+            self.down.into_iter().collect(),
+        )
+    }
+
+    /// Add the given elements to the end of this [Stack], after the current tail.
+    /// The focused element is maintained by this operation.
+    ///
+    /// ```
+    /// # use penrose::stack;
+    /// let mut s = stack!([1], 2, [3]);
+    /// s.append(stack!([4], 5, [6]));
+    ///
+    /// assert_eq!(s, stack!([1], 2, [3, 4, 5, 6]));
+    /// ```
+    pub fn append(&mut self, other: impl IntoIterator<Item = T>) -> &mut Self {
+        self.down.extend(other);
+
+        self
+    }
+
+    /// Add the given elements to the start of this [Stack], before the current head.
+    /// The focused element is maintained by this operation.
+    ///
+    /// ```
+    /// # use penrose::stack;
+    /// let mut s = stack!([1], 2, [3]);
+    /// s.prepend(stack!([4], 5, [6]));
+    ///
+    /// assert_eq!(s, stack!([4, 5, 6, 1], 2, [3]));
+    /// ```
+    pub fn prepend(&mut self, other: impl IntoIterator<Item = T>) -> &mut Self {
+        let elems: Vec<T> = other.into_iter().collect();
+        self.up.extend(elems.into_iter().rev());
+
+        self
+    }
+
     /// Map a function over all elements in this [Stack], returning a new one.
     pub fn map<F, U>(self, f: F) -> Stack<U>
     where
@@ -569,6 +818,12 @@ impl<T: Clone> Stack<T> {
 }
 
 impl<T: PartialEq> Stack<T> {
+    /// The position of the first element equal to `t` in this [Stack], counting from zero at
+    /// the head, if there is one.
+    pub fn position_of(&self, t: &T) -> Option<usize> {
+        self.iter().position(|e| e == t)
+    }
+
     /// Check whether a given element is in this Stack
     pub fn contains(&self, t: &T) -> bool {
         &self.focus == t || self.up.contains(t) || self.down.contains(t)
@@ -602,8 +857,30 @@ impl<T> Iterator for IntoIter<T> {
             .or_else(|| self.focus.take())
             .or_else(|| self.down.pop_front())
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.len();
+        (n, Some(n))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.down
+            .pop_back()
+            .or_else(|| self.focus.take())
+            .or_else(|| self.up.pop_front())
+    }
 }
 
+impl<T> ExactSizeIterator for IntoIter<T> {
+    fn len(&self) -> usize {
+        self.up.len() + self.focus.is_some() as usize + self.down.len()
+    }
+}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
 impl<T> IntoIterator for Stack<T> {
     type Item = T;
     type IntoIter = IntoIter<T>;
@@ -634,8 +911,30 @@ impl<'a, T> Iterator for Iter<'a, T> {
             .or_else(|| self.focus.take())
             .or_else(|| self.down.next())
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.len();
+        (n, Some(n))
+    }
 }
 
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.down
+            .next_back()
+            .or_else(|| self.focus.take())
+            .or_else(|| self.up.next())
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {
+    fn len(&self) -> usize {
+        self.up.len() + self.focus.is_some() as usize + self.down.len()
+    }
+}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
 impl<'a, T> IntoIterator for &'a Stack<T> {
     type Item = &'a T;
     type IntoIter = Iter<'a, T>;
@@ -662,8 +961,30 @@ impl<'a, T> Iterator for IterMut<'a, T> {
             .or_else(|| self.focus.take())
             .or_else(|| self.down.next())
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.len();
+        (n, Some(n))
+    }
+}
+
+impl<T> DoubleEndedIterator for IterMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.down
+            .next_back()
+            .or_else(|| self.focus.take())
+            .or_else(|| self.up.next())
+    }
 }
 
+impl<T> ExactSizeIterator for IterMut<'_, T> {
+    fn len(&self) -> usize {
+        self.up.len() + self.focus.is_some() as usize + self.down.len()
+    }
+}
+
+impl<T> FusedIterator for IterMut<'_, T> {}
+
 impl<'a, T> IntoIterator for &'a mut Stack<T> {
     type Item = &'a mut T;
     type IntoIter = IterMut<'a, T>;
@@ -937,6 +1258,188 @@ mod tests {
 
         assert_eq!(s, expected);
     }
+
+    #[test_case(0, Some(1); "head")]
+    #[test_case(1, Some(2); "up")]
+    #[test_case(2, Some(3); "focus")]
+    #[test_case(3, Some(4); "down")]
+    #[test_case(4, Some(5); "tail")]
+    #[test_case(5, None; "out of bounds")]
+    #[test]
+    fn get(n: usize, expected: Option<usize>) {
+        let s = stack!([1, 2], 3, [4, 5]);
+
+        assert_eq!(s.get(n), expected.as_ref());
+        if let Some(t) = expected {
+            assert_eq!(s.position_of(&t), Some(n));
+        }
+    }
+
+    #[test_case(0, stack!(1, [2,3,4,5]); "head")]
+    #[test_case(1, stack!([1], 2, [3,4,5]); "up")]
+    #[test_case(2, stack!([1,2], 3, [4,5]); "focus")]
+    #[test_case(3, stack!([1,2,3], 4, [5]); "down")]
+    #[test_case(4, stack!([1,2,3,4], 5); "tail")]
+    #[test_case(5, stack!([1,2], 3, [4,5]); "out of bounds")]
+    #[test]
+    fn focus_nth(n: usize, expected: Stack<usize>) {
+        let mut s = stack!([1, 2], 3, [4, 5]);
+        s.focus_nth(n);
+
+        assert_eq!(s, expected);
+        assert_eq!(s.focused_index(), s.position_of(s.focused()).unwrap());
+    }
+
+    #[test_case(stack!(1), (vec![], 1, vec![]); "single element")]
+    #[test_case(stack!([1,2], 3), (vec![1,2], 3, vec![]); "focus at tail")]
+    #[test_case(stack!(1, [2,3]), (vec![], 1, vec![2,3]); "focus at head")]
+    #[test_case(stack!([1,2], 3, [4,5]), (vec![1,2], 3, vec![4,5]); "focus in middle")]
+    #[test]
+    fn split_at_focus(s: Stack<u8>, expected: (Vec<u8>, u8, Vec<u8>)) {
+        assert_eq!(s.split_at_focus(), expected);
+    }
+
+    #[test]
+    fn split_and_rebuild_is_identity() {
+        let s = stack!([1, 2], 3, [4, 5]);
+        let (up, focus, down) = s.clone().split_at_focus();
+
+        assert_eq!(Stack::new(up, focus, down), s);
+    }
+
+    #[test_case(stack!(1), stack!(2), stack!(1, [2]), stack!([2], 1); "single elements")]
+    #[test_case(stack!([1], 2, [3]), stack!([4], 5), stack!([1], 2, [3,4,5]), stack!([4,5,1], 2, [3]); "focus in middle")]
+    #[test_case(stack!(1, [2]), stack!(3, [4]), stack!(1, [2,3,4]), stack!([3,4], 1, [2]); "focus at head")]
+    #[test]
+    fn append_and_prepend(
+        s: Stack<u8>,
+        other: Stack<u8>,
+        appended: Stack<u8>,
+        prepended: Stack<u8>,
+    ) {
+        let mut a = s.clone();
+        a.append(other.clone());
+        let mut p = s;
+        p.prepend(other);
+
+        assert_eq!(a, appended);
+        assert_eq!(p, prepended);
+    }
+
+    #[test_case(0, 1, stack!([2,1], 3, [4,5]); "swap up elements")]
+    #[test_case(3, 4, stack!([1,2], 3, [5,4]); "swap down elements")]
+    #[test_case(0, 4, stack!([5,2], 3, [4,1]); "swap up and down")]
+    #[test_case(2, 0, stack!(3, [2,1,4,5]); "swap focus with up")]
+    #[test_case(4, 2, stack!([1,2,5,4], 3); "swap focus with down")]
+    #[test_case(2, 2, stack!([1,2], 3, [4,5]); "same index")]
+    #[test_case(1, 5, stack!([1,2], 3, [4,5]); "out of bounds")]
+    #[test]
+    fn swap_nth(i: usize, j: usize, expected: Stack<u8>) {
+        let mut s = stack!([1, 2], 3, [4, 5]);
+        s.swap_nth(i, j);
+
+        assert_eq!(s, expected);
+    }
+
+    #[test_case(0, stack!(3, [1,2,4,5]); "to head")]
+    #[test_case(1, stack!([1], 3, [2,4,5]); "up one")]
+    #[test_case(2, stack!([1,2], 3, [4,5]); "current index")]
+    #[test_case(3, stack!([1,2,4], 3, [5]); "down one")]
+    #[test_case(4, stack!([1,2,4,5], 3); "to tail")]
+    #[test_case(5, stack!([1,2], 3, [4,5]); "out of bounds")]
+    #[test]
+    fn move_focused_to_index(n: usize, expected: Stack<u8>) {
+        let mut s = stack!([1, 2], 3, [4, 5]);
+        s.move_focused_to_index(n);
+
+        assert_eq!(s, expected);
+    }
+
+    #[test_case(stack!(1), stack!(1); "single element")]
+    #[test_case(stack!([1,2], 3, [4,5]), stack!([1,2], 3, [4,5]); "already sorted")]
+    #[test_case(stack!([5,4], 3, [2,1]), stack!([1,2], 3, [4,5]); "reversed")]
+    #[test_case(stack!([3,1], 5, [4,2]), stack!([1,2,3,4], 5); "focus moves to tail")]
+    #[test_case(stack!([3,5], 1, [4,2]), stack!(1, [2,3,4,5]); "focus moves to head")]
+    #[test]
+    fn sort_by(mut s: Stack<u8>, expected: Stack<u8>) {
+        s.sort_by(|a, b| a.cmp(b));
+
+        assert_eq!(s, expected);
+    }
+
+    #[test]
+    fn sort_by_is_stable_around_the_focus() {
+        let mut s = stack!([(1, 'a'), (0, 'b')], (1, 'c'), [(0, 'd'), (1, 'e')]);
+        s.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(
+            s,
+            stack!([(0, 'b'), (0, 'd'), (1, 'a')], (1, 'c'), [(1, 'e')])
+        );
+    }
+
+    #[test]
+    fn iterators_are_double_ended_and_exact_size() {
+        let mut s = stack!([1, 2], 3, [4, 5]);
+
+        assert_eq!(s.iter().len(), 5);
+        assert_eq!(
+            s.iter().rev().copied().collect::<Vec<_>>(),
+            vec![5, 4, 3, 2, 1]
+        );
+        assert_eq!(
+            s.iter_mut().rev().map(|t| *t).collect::<Vec<_>>(),
+            vec![5, 4, 3, 2, 1]
+        );
+
+        let mut it = s.clone().into_iter();
+        assert_eq!(it.len(), 5);
+        assert_eq!((it.next(), it.next_back()), (Some(1), Some(5)));
+        assert_eq!((it.next_back(), it.next_back()), (Some(4), Some(3)));
+        assert_eq!(it.len(), 1);
+        assert_eq!((it.next_back(), it.next()), (Some(2), None));
+        assert_eq!((it.next(), it.next_back()), (None, None));
+    }
+
+    #[test_case(stack!(1); "single element")]
+    #[test_case(stack!([1,2], 3); "focus at tail")]
+    #[test_case(stack!(1, [2,3]); "focus at head")]
+    #[test_case(stack!([1,2], 3, [4,5]); "focus in middle")]
+    #[test]
+    fn iterating_from_both_ends_meets_in_the_middle(s: Stack<u8>) {
+        let expected = s.clone().flatten();
+        let mut it = s.iter();
+        let (mut front, mut back) = (vec![], vec![]);
+
+        while let Some(t) = it.next() {
+            front.push(*t);
+            match it.next_back() {
+                Some(t) => back.push(*t),
+                None => break,
+            }
+        }
+        front.extend(back.into_iter().rev());
+
+        assert_eq!(front, expected);
+    }
+
+    #[test_case(Position::Focus, FocusPolicy::Take, stack!([1,2], 6, [3,4,5]); "focus take")]
+    #[test_case(Position::Focus, FocusPolicy::Keep, stack!([1,2,6], 3, [4,5]); "focus keep")]
+    #[test_case(Position::Before, FocusPolicy::Take, stack!([1,2], 6, [3,4,5]); "before take")]
+    #[test_case(Position::Before, FocusPolicy::Keep, stack!([1,2,6], 3, [4,5]); "before keep")]
+    #[test_case(Position::After, FocusPolicy::Take, stack!([1,2,3], 6, [4,5]); "after take")]
+    #[test_case(Position::After, FocusPolicy::Keep, stack!([1,2], 3, [6,4,5]); "after keep")]
+    #[test_case(Position::Head, FocusPolicy::Take, stack!(6, [1,2,3,4,5]); "head take")]
+    #[test_case(Position::Head, FocusPolicy::Keep, stack!([6,1,2], 3, [4,5]); "head keep")]
+    #[test_case(Position::Tail, FocusPolicy::Take, stack!([1,2,3,4,5], 6); "tail take")]
+    #[test_case(Position::Tail, FocusPolicy::Keep, stack!([1,2], 3, [4,5,6]); "tail keep")]
+    #[test]
+    fn insert_at_with_focus(pos: Position, policy: FocusPolicy, expected: Stack<usize>) {
+        let mut s = stack!([1, 2], 3, [4, 5]);
+        s.insert_at_with_focus(pos, 6, policy);
+
+        assert_eq!(s, expected);
+    }
 }
 
 #[cfg(test)]
@@ -1,14 +1,18 @@
 use crate::{
-    core::layout::LayoutStack,
+    core::layout::{IntoMessage, LayoutStack},
     pop_where,
     pure::{
         diff::{ScreenState, Snapshot},
-        geometry::{Rect, RelativeRect, RelativeTo},
+        geometry::{Point, Rect, RelativeRect, RelativeTo},
         workspace::check_workspace_invariants,
-        Position, Screen, Stack, Workspace,
+        FocusPolicy, Position, Screen, Stack, Workspace,
     },
     stack, Error, Result, Xid,
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "serde")]
+use std::collections::HashSet;
 use std::{
     cmp::Ordering,
     collections::{HashMap, VecDeque},
@@ -18,6 +22,36 @@ use std::{
 use tracing::debug;
 
 /// The side-effect free internal state representation of the window manager.
+///
+/// A [StackSet] is generic over the type used to identify clients so that it can be used
+/// independently of X. Penrose itself uses [Xid] but any type that is `Clone`, `Eq` and `Hash`
+/// can be used as a client handle, which is useful for testing your own manipulations of the
+/// client set or for experimenting with other display servers. `Eq` is required for locating
+/// clients within each [Workspace] and `Hash` is required for tracking floating clients.
+///
+/// [StackSet::float] and [StackSet::toggle_floating_state] are only available for [Xid]
+/// clients as the errors they return identify the X client involved.
+///
+/// ```
+/// use penrose::{
+///     core::layout::LayoutStack,
+///     pure::{geometry::Rect, StackSet},
+/// };
+///
+/// let mut cs: StackSet<&str> = StackSet::try_new(
+///     LayoutStack::default(),
+///     ["1", "2", "3"],
+///     vec![Rect::new(0, 0, 1920, 1080)],
+/// )?;
+///
+/// cs.insert("firefox");
+/// cs.insert("alacritty");
+/// cs.move_focused_to_tag("2");
+///
+/// assert_eq!(cs.tag_for_client(&"alacritty"), Some("2"));
+/// assert_eq!(cs.current_client(), Some(&"firefox"));
+/// # Ok::<(), penrose::Error>(())
+/// ```
 #[derive(Default, Debug, Clone)]
 pub struct StackSet<C>
 where
@@ -29,12 +63,17 @@ where
     pub(crate) previous_tag: String,      // The last tag to be focused before the current one
     pub(crate) invisible_tags: Vec<String>, // Tags that should never be focused
     pub(crate) killed_clients: Vec<C>, // clients that have been removed and need processing on the X side
+    pub(crate) primary: usize,         // index of the screen marked as primary
 }
 
 impl<C> StackSet<C>
 where
     C: Clone + PartialEq + Eq + Hash,
 {
+    /// The version of the format used when serializing a [StackSet] with the `serde` feature
+    /// enabled. Deserializing data written with a different version is an error.
+    pub const SCHEMA_VERSION: u32 = 1;
+
     /// Create a new [StackSet] of empty stacks with the given workspace names.
     ///
     /// # Errors
@@ -95,9 +134,30 @@ where
             previous_tag,
             invisible_tags: vec![],
             killed_clients: vec![],
+            primary: 0,
         })
     }
 
+    // On startup the first workspace is placed on the primary screen and focused, swapping it
+    // with the workspace that would otherwise have been shown there.
+    pub(crate) fn start_on_primary_screen(&mut self, index: usize) {
+        if index == 0 || index >= self.screens.len() {
+            return;
+        }
+
+        let mut screens: Vec<_> = self
+            .screens
+            .iter_mut()
+            .filter(|s| s.index == 0 || s.index == index)
+            .collect();
+        if let [a, b] = &mut screens[..] {
+            swap(&mut a.workspace, &mut b.workspace);
+        }
+
+        self.primary = index;
+        self.focus_screen(index);
+    }
+
     /// Set focus to the [Screen] with the specified index.
     ///
     /// If there is no matching screen then the [StackSet] is unmodified.
@@ -236,6 +296,36 @@ where
         }
     }
 
+    /// Focus the client at position `n` (counting from zero at the head) of the current
+    /// [Stack] without modifying the order of the stack.
+    ///
+    /// This is a no-op if the current stack has fewer than `n + 1` clients.
+    pub fn focus_nth_client(&mut self, n: usize) {
+        if let Some(ref mut stack) = self.screens.focus.workspace.stack {
+            stack.focus_nth(n);
+        }
+    }
+
+    /// Move the focused client to position `n` (counting from zero at the head) of the
+    /// current [Stack], maintaining focus on the moved client.
+    ///
+    /// This is a no-op if the current stack has fewer than `n + 1` clients.
+    pub fn move_focused_to_index(&mut self, n: usize) {
+        if let Some(ref mut stack) = self.screens.focus.workspace.stack {
+            stack.move_focused_to_index(n);
+        }
+    }
+
+    /// Swap the clients at positions `i` and `j` (counting from zero at the head) of the
+    /// current [Stack]. The currently focused client is maintained by this operation.
+    ///
+    /// This is a no-op if either position is not present in the current stack.
+    pub fn swap_nth_clients(&mut self, i: usize, j: usize) {
+        if let Some(ref mut stack) = self.screens.focus.workspace.stack {
+            stack.swap_nth(i, j);
+        }
+    }
+
     /// Insert the given client to the current [Stack] in a default [Position].
     pub fn insert(&mut self, client: C) {
         self.insert_at(Position::default(), client)
@@ -379,12 +469,81 @@ where
         self.move_client_to_tag(client, self.screens.focus.workspace.tag.clone());
     }
 
+    /// Move the given client to the requested [Position] within the stack of the [Workspace]
+    /// it is currently on, giving it focus on that workspace. Positions are relative to the
+    /// element that held focus once the client has been removed from the stack.
+    pub fn move_client_to_position(&mut self, client: &C, pos: Position) {
+        self.move_client_to_position_with_focus(client, pos, FocusPolicy::Take)
+    }
+
+    /// Move the given client to the requested [Position] within the stack of the [Workspace]
+    /// it is currently on, with focus on that workspace following the given [FocusPolicy].
+    /// Positions are relative to the element that held focus once the client has been removed
+    /// from the stack.
+    pub fn move_client_to_position_with_focus(
+        &mut self,
+        client: &C,
+        pos: Position,
+        policy: FocusPolicy,
+    ) {
+        let tag = match self.tag_for_client(client) {
+            Some(tag) => tag.to_owned(),
+            None => return,
+        };
+
+        self.modify_workspace(&tag, |w| {
+            if let Some(c) = w.remove(client) {
+                w.insert_at_with_focus(pos, c, policy);
+            }
+        });
+    }
+
+    /// Move the focused client to the head of the current [Stack] so that it becomes the main
+    /// client, keeping focus on it.
+    ///
+    /// This matches the behaviour of `zoom` in dwm: if the focused client is already the
+    /// first tiled client then the next tiled client is moved to the head of the stack and
+    /// focused instead, swapping back to the previous main client. This is a no-op if the
+    /// focused client is floating or there are no other tiled clients to promote.
+    pub fn promote_focused_to_main(&mut self) {
+        let focus = match self.current_client() {
+            Some(c) if !self.is_floating(c) => c.clone(),
+            _ => return,
+        };
+
+        let target = {
+            let mut tiled = self
+                .current_workspace()
+                .clients()
+                .filter(|c| !self.is_floating(c));
+
+            match tiled.next() {
+                Some(main) if *main == focus => match tiled.next() {
+                    Some(next) => next.clone(),
+                    None => return,
+                },
+                _ => focus,
+            }
+        };
+
+        self.move_client_to_position(&target, Position::Head);
+    }
+
     /// Insert a client as the current focus for the given tag.
     ///
     /// NOTE: This will silently fail if the tag is not in the StackSet which
     ///       is why the method is not in the public API
     pub(crate) fn insert_as_focus_for(&mut self, tag: &str, c: C) {
-        self.modify_workspace(tag, |w| w.insert_as_focus(c));
+        self.insert_as_focus_at_for(tag, Position::Focus, c);
+    }
+
+    /// Insert a client at the given [Position] for the given tag, giving it focus on that
+    /// workspace.
+    ///
+    /// NOTE: This will silently fail if the tag is not in the StackSet which
+    ///       is why the method is not in the public API
+    pub(crate) fn insert_as_focus_at_for(&mut self, tag: &str, pos: Position, c: C) {
+        self.modify_workspace(tag, |w| w.insert_as_focus_at(pos, c));
     }
 
     /// Is the given tag present in the [StackSet]?
@@ -480,6 +639,21 @@ where
         self.screens.iter().find(|s| s.workspace.contains(client))
     }
 
+    /// Return a reference to the screen containing the given point, if there is one.
+    ///
+    /// Points on the shared edge of two adjacent screens are reported as being on the
+    /// screen with the lower index.
+    pub fn screen_containing_point<P>(&self, p: P) -> Option<&Screen<C>>
+    where
+        P: Into<Point>,
+    {
+        let p = p.into();
+        let mut screens: Vec<&Screen<C>> = self.screens.iter().collect();
+        screens.sort_by_key(|s| s.index);
+
+        screens.into_iter().find(|s| s.r.contains_point(p))
+    }
+
     /// Find the tag of the [Workspace] with the given NetWmDesktop ID.
     pub fn tag_for_workspace_id(&self, id: usize) -> Option<String> {
         self.workspaces()
@@ -507,6 +681,125 @@ where
         &self.screens.focus
     }
 
+    /// An immutable reference to the primary [Screen].
+    ///
+    /// This is the screen marked as primary by the X server, or the screen with index 0 if no
+    /// primary screen has been set.
+    pub fn primary_screen(&self) -> &Screen<C> {
+        self.screens
+            .iter()
+            .find(|s| s.index == self.primary)
+            .unwrap_or(&self.screens.focus)
+    }
+
+    /// Mark the [Screen] with the given index as the primary screen.
+    ///
+    /// If there is no matching screen then the [StackSet] is unmodified.
+    pub(crate) fn set_primary_screen(&mut self, index: usize) {
+        if index < self.screens.len() {
+            self.primary = index;
+        }
+    }
+
+    /// Update the [Screen]s in this [StackSet] to match the given screen dimensions.
+    ///
+    /// Additional screens are populated using hidden workspaces (generating new default
+    /// workspaces if there are not enough available) and workspaces on removed screens are
+    /// hidden. If the currently focused screen is removed then focus moves to the first screen.
+    ///
+    /// # Errors
+    /// This method will return [Error::NoScreens] if `rects` is empty.
+    pub(crate) fn update_screens(&mut self, rects: Vec<Rect>) -> Result<()> {
+        let n_old = self.screens.len();
+        let n_new = rects.len();
+
+        if n_new == 0 {
+            return Err(Error::NoScreens);
+        }
+
+        match n_new.cmp(&n_old) {
+            // Just a change in dimensions
+            Ordering::Equal => (),
+
+            // We have more screens now: pull in hidden workspaces to fill them
+            // If we run out of workspaces we backfill using generated defaults
+            Ordering::Greater => {
+                let padding = self.take_from_hidden(n_new - n_old);
+                for (n, w) in padding.into_iter().enumerate() {
+                    self.screens.insert_at(
+                        Position::Tail,
+                        Screen {
+                            workspace: w,
+                            index: n_old + n,
+                            r: Rect::default(),
+                        },
+                    );
+                }
+            }
+
+            // We have fewer screens now: focus moves to the first screen and
+            // we drop from the back of the stack
+            Ordering::Less => {
+                let mut raw = take(&mut self.screens).flatten();
+                let removed = raw.split_off(n_new);
+                self.hidden.extend(removed.into_iter().map(|s| s.workspace));
+                self.screens = Stack::from_iter_unchecked(raw);
+            }
+        }
+
+        if self.primary >= n_new {
+            self.primary = 0;
+        }
+
+        // self.screens.len() is now correct so update the screen dimensions
+        for (s, r) in self.screens.iter_mut().zip(rects) {
+            s.r = r;
+        }
+
+        Ok(())
+    }
+
+    // This is a little fiddly...
+    // Rather than hard erroring if we end up with new screens being detected that
+    // push us over the number of available workspaces, we pad the workspace set
+    // with ones we generate with default values. In doing this we need to make sure
+    // that any _invisible_ workspaces are kept to one side so that they do not end
+    // up focused on a screen by mistake.
+    fn take_from_hidden(&mut self, n: usize) -> Vec<Workspace<C>> {
+        let next_id = self.workspaces().map(|w| w.id).max().unwrap_or(0) + 1;
+        let mut tmp = Vec::with_capacity(self.hidden.len());
+        let mut hidden = VecDeque::new();
+
+        // Filter out any hidden tags first
+        for w in take(&mut self.hidden) {
+            if self.invisible_tags.contains(&w.tag) {
+                hidden.push_front(w);
+            } else {
+                tmp.push(w);
+            }
+        }
+
+        // Sort so that we populate the new screens with workspaces in order of ID.
+        // Without this we are basing things off of the order we ended up with after
+        // whatever workspace focus changes the user has made while we are running.
+        tmp.sort_by_key(|w| w.id);
+
+        // Pad the remaining workspace count with empty default workspaces if we are
+        // below the number we need.
+        if tmp.len() < n {
+            for m in 0..(n - tmp.len()) {
+                tmp.push(Workspace::new_default(next_id + m));
+            }
+        } else {
+            let extra = tmp.split_off(n);
+            hidden.extend(extra);
+        }
+
+        self.hidden = hidden;
+
+        tmp
+    }
+
     /// An immutable reference to the current [Workspace]
     pub fn current_workspace(&self) -> &Workspace<C> {
         &self.screens.focus.workspace
@@ -718,6 +1011,30 @@ where
             .chain(self.hidden.iter_mut())
     }
 
+    /// Send a message to every layout of every [Workspace], including those that are not
+    /// currently visible. A new message is created for each workspace using `f`.
+    pub fn broadcast_message<F, M>(&mut self, f: F)
+    where
+        F: Fn() -> M,
+        M: IntoMessage,
+    {
+        for w in self.workspaces_mut() {
+            w.broadcast_message(f());
+        }
+    }
+
+    /// Replace the layouts of every [Workspace] with the given [LayoutStack], restoring the
+    /// layout that was active for each workspace when this [StackSet] was serialized if it is
+    /// present in the new stack.
+    ///
+    /// Layouts are not serialized, so this should be called with the layouts from your config
+    /// after deserializing a [StackSet].
+    pub fn restore_layouts(&mut self, layouts: &LayoutStack) {
+        for w in self.workspaces_mut() {
+            w.restore_layouts(layouts.clone());
+        }
+    }
+
     /// Iterate over the [Workspace] currently displayed on a screen in an arbitrary order.
     pub fn on_screen_workspaces(&self) -> impl Iterator<Item = &Workspace<C>> {
         self.screens.iter().map(|s| &s.workspace)
@@ -758,18 +1075,7 @@ impl StackSet<Xid> {
     /// This is a test implementation that runs the `State::visible_client_positions`
     /// logic using a stub XConn and no layout hook.
     pub(crate) fn visible_client_positions(&self) -> Vec<(Xid, Rect)> {
-        let mut s = crate::core::State {
-            client_set: self.clone(),
-            config: Default::default(),
-            extensions: anymap::AnyMap::new(),
-            root: Xid(0),
-            mapped: Default::default(),
-            pending_unmap: Default::default(),
-            current_event: None,
-            diff: Default::default(),
-            running: false,
-            held_mouse_state: None,
-        };
+        let mut s = crate::core::State::new_with_client_set(self.clone());
 
         s.visible_client_positions(&crate::x::StubXConn)
     }
@@ -831,93 +1137,6 @@ impl StackSet<Xid> {
 
         Ok(rect)
     }
-
-    pub(crate) fn update_screens(&mut self, rects: Vec<Rect>) -> Result<()> {
-        let n_old = self.screens.len();
-        let n_new = rects.len();
-
-        if n_new == 0 {
-            return Err(Error::NoScreens);
-        }
-
-        match n_new.cmp(&n_old) {
-            // Just a change in dimensions
-            Ordering::Equal => (),
-
-            // We have more screens now: pull in hidden workspaces to fill them
-            // If we run out of workspaces we backfill using generated defaults
-            Ordering::Greater => {
-                let padding = self.take_from_hidden(n_new - n_old);
-                for (n, w) in padding.into_iter().enumerate() {
-                    self.screens.insert_at(
-                        Position::Tail,
-                        Screen {
-                            workspace: w,
-                            index: n_old + n,
-                            r: Rect::default(),
-                        },
-                    );
-                }
-            }
-
-            // We have fewer screens now: focus moves to the first screen and
-            // we drop from the back of the stack
-            Ordering::Less => {
-                let mut raw = take(&mut self.screens).flatten();
-                let removed = raw.split_off(n_new);
-                self.hidden.extend(removed.into_iter().map(|s| s.workspace));
-                self.screens = Stack::from_iter_unchecked(raw);
-            }
-        }
-
-        // self.screens.len() is now correct so update the screen dimensions
-        for (s, r) in self.screens.iter_mut().zip(rects) {
-            s.r = r;
-        }
-
-        Ok(())
-    }
-
-    // This is a little fiddly...
-    // Rather than hard erroring if we end up with new screens being detected that
-    // push us over the number of available workspaces, we pad the workspace set
-    // with ones we generate with default values. In doing this we need to make sure
-    // that any _invisible_ workspaces are kept to one side so that they do not end
-    // up focused on a screen by mistake.
-    fn take_from_hidden(&mut self, n: usize) -> Vec<Workspace<Xid>> {
-        let next_id = self.workspaces().map(|w| w.id).max().unwrap_or(0) + 1;
-        let mut tmp = Vec::with_capacity(self.hidden.len());
-        let mut hidden = VecDeque::new();
-
-        // Filter out any hidden tags first
-        for w in take(&mut self.hidden) {
-            if self.invisible_tags.contains(&w.tag) {
-                hidden.push_front(w);
-            } else {
-                tmp.push(w);
-            }
-        }
-
-        // Sort so that we populate the new screens with workspaces in order of ID.
-        // Without this we are basing things off of the order we ended up with after
-        // whatever workspace focus changes the user has made while we are running.
-        tmp.sort_by_key(|w| w.id);
-
-        // Pad the remaining workspace count with empty default workspaces if we are
-        // below the number we need.
-        if tmp.len() < n {
-            for m in 0..(n - tmp.len()) {
-                tmp.push(Workspace::new_default(next_id + m));
-            }
-        } else {
-            let extra = tmp.split_off(n);
-            hidden.extend(extra);
-        }
-
-        self.hidden = hidden;
-
-        tmp
-    }
 }
 
 impl<C> StackSet<C>
@@ -943,6 +1162,99 @@ where
     }
 }
 
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct StackSetRef<'a, C> {
+    version: u32,
+    screens: &'a Stack<Screen<C>>,
+    hidden: &'a VecDeque<Workspace<C>>,
+    floating: Vec<(&'a C, &'a RelativeRect)>,
+    previous_tag: &'a str,
+    invisible_tags: &'a [String],
+    primary: usize,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct StackSetRepr<C> {
+    version: u32,
+    screens: Stack<Screen<C>>,
+    hidden: VecDeque<Workspace<C>>,
+    floating: Vec<(C, RelativeRect)>,
+    previous_tag: String,
+    invisible_tags: Vec<String>,
+    primary: usize,
+}
+
+#[cfg(feature = "serde")]
+impl<C> Serialize for StackSet<C>
+where
+    C: Clone + PartialEq + Eq + Hash + Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        StackSetRef {
+            version: Self::SCHEMA_VERSION,
+            screens: &self.screens,
+            hidden: &self.hidden,
+            floating: self.floating.iter().collect(),
+            previous_tag: &self.previous_tag,
+            invisible_tags: &self.invisible_tags,
+            primary: self.primary,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, C> Deserialize<'de> for StackSet<C>
+where
+    C: Clone + PartialEq + Eq + Hash + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        use serde::de::Error as _;
+
+        let r = StackSetRepr::deserialize(deserializer)?;
+        if r.version != Self::SCHEMA_VERSION {
+            return Err(D::Error::custom(format!(
+                "unsupported StackSet schema version {}: expected {}",
+                r.version,
+                Self::SCHEMA_VERSION
+            )));
+        }
+
+        let mut tags = HashSet::new();
+        let ws = r
+            .screens
+            .iter()
+            .map(|s| &s.workspace)
+            .chain(r.hidden.iter());
+        for w in ws {
+            if !tags.insert(w.tag.as_str()) {
+                return Err(D::Error::custom(format!(
+                    "duplicate workspace tag: {}",
+                    w.tag
+                )));
+            }
+        }
+        if r.primary >= r.screens.len() {
+            return Err(D::Error::custom(format!(
+                "primary screen index out of bounds: {}",
+                r.primary
+            )));
+        }
+
+        Ok(Self {
+            screens: r.screens,
+            hidden: r.hidden,
+            floating: r.floating.into_iter().collect(),
+            previous_tag: r.previous_tag,
+            invisible_tags: r.invisible_tags,
+            killed_clients: Vec::new(),
+            primary: r.primary,
+        })
+    }
+}
+
 macro_rules! defer_to_current_stack {
     ($(
         $(#[$doc_str:meta])*
@@ -1048,6 +1360,28 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn broadcast_message_reaches_all_workspaces() {
+        use crate::builtin::layout::messages::Mirror;
+
+        let mut s = test_stack_set(5, 2);
+        s.broadcast_message(|| Mirror);
+
+        assert!(s.workspaces().all(|w| w.layout_name() == "Mirror"));
+    }
+
+    #[test_case(Point::new(500, 500), Some(0); "first screen")]
+    #[test_case(Point::new(1500, 2500), Some(1); "second screen")]
+    #[test_case(Point::new(1000, 2000), Some(0); "shared corner")]
+    #[test_case(Point::new(1500, 500), None; "off screen")]
+    #[test]
+    fn screen_containing_point(p: Point, expected: Option<usize>) {
+        let mut s = test_stack_set(5, 2);
+        s.focus_screen(1);
+
+        assert_eq!(s.screen_containing_point(p).map(|s| s.index()), expected);
+    }
+
     #[test_case("1", &["1", "2"]; "current focused workspace")]
     #[test_case("2", &["1", "2"]; "visible on other screen")]
     #[test_case("3", &["3", "2"]; "currently hidden")]
@@ -1378,6 +1712,44 @@ pub mod tests {
         assert_eq!(s.workspace(tag).unwrap().focus(), Some(&client));
     }
 
+    #[test_case(3, Position::Focus, vec![0, 3, 1, 2]; "focus")]
+    #[test_case(3, Position::Before, vec![0, 3, 1, 2]; "before")]
+    #[test_case(3, Position::After, vec![0, 1, 3, 2]; "after")]
+    #[test_case(3, Position::Head, vec![3, 0, 1, 2]; "head")]
+    #[test_case(0, Position::Tail, vec![1, 2, 3, 0]; "tail")]
+    #[test]
+    fn move_client_to_position(client: u8, pos: Position, expected: Vec<u8>) {
+        let mut s =
+            test_stack_set_with_stacks(vec![Some(stack!([0], 1, [2, 3])), Some(stack!(4, [5]))], 1);
+
+        s.move_client_to_position(&client, pos);
+
+        let ws = s.workspace("1").unwrap();
+        assert_eq!(ws.focus(), Some(&client));
+        assert_eq!(ws.clients().copied().collect::<Vec<_>>(), expected);
+        assert_eq!(s.workspace("2").unwrap().focus(), Some(&4));
+    }
+
+    #[test_case(stack!([1, 2], 3, [4]), vec![3, 1, 2, 4], 3; "focus not main")]
+    #[test_case(stack!(1, [2, 3, 4]), vec![3, 1, 2, 4], 3; "focus is main skipping floating")]
+    #[test_case(stack!(1), vec![1], 1; "single client")]
+    #[test_case(stack!(3, [4, 5]), vec![4, 3, 5], 4; "focus is main")]
+    #[test_case(stack!([1], 2, [3]), vec![1, 2, 3], 2; "focus is floating")]
+    #[test_case(stack!([2], 1, [3, 4]), vec![3, 2, 1, 4], 3; "floating head")]
+    #[test]
+    fn promote_focused_to_main(stack: Stack<u8>, expected: Vec<u8>, focus: u8) {
+        let mut s = test_stack_set_with_stacks(vec![Some(stack)], 1);
+        s.floating.insert(2, RelativeRect::new(0.0, 0.0, 0.5, 0.5));
+
+        s.promote_focused_to_main();
+
+        assert_eq!(s.current_client(), Some(&focus));
+        assert_eq!(
+            s.current_workspace().clients().copied().collect::<Vec<_>>(),
+            expected
+        );
+    }
+
     fn focused_tags(ss: &StackSet<Xid>) -> Vec<&String> {
         ss.screens.iter().map(|s| &s.workspace.tag).collect()
     }
@@ -1427,6 +1799,72 @@ pub mod tests {
         assert_eq!(ss.workspaces().count(), expected);
     }
 
+    #[test_case(0, vec!["1", "2", "3"]; "first screen")]
+    #[test_case(1, vec!["2", "1", "3"]; "middle screen")]
+    #[test_case(2, vec!["3", "2", "1"]; "last screen")]
+    #[test_case(3, vec!["1", "2", "3"]; "out of bounds")]
+    #[test]
+    fn start_on_primary_screen(primary: usize, tags: Vec<&str>) {
+        let mut ss: StackSet<Xid> = StackSet::try_new(
+            LayoutStack::default(),
+            ["1", "2", "3", "4"],
+            vec![Rect::default(); 3],
+        )
+        .expect("enough workspaces to cover the number of screens");
+
+        ss.start_on_primary_screen(primary);
+
+        assert_eq!(focused_tags(&ss), tags);
+        assert_eq!(ss.current_tag(), "1");
+        assert_eq!(ss.primary_screen().index(), ss.current_screen().index());
+    }
+
+    #[test]
+    fn update_screens_resets_a_removed_primary_screen() {
+        let mut ss: StackSet<Xid> =
+            StackSet::try_new(LayoutStack::default(), ["1", "2"], vec![Rect::default(); 2])
+                .expect("enough workspaces to cover the number of screens");
+        ss.set_primary_screen(1);
+
+        ss.update_screens(vec![Rect::default()]).unwrap();
+
+        assert_eq!(ss.primary_screen().index(), 0);
+    }
+
+    // A client handle that is not Copy and has no relation to X
+    #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+    struct Handle(String);
+
+    fn handle(s: &str) -> Handle {
+        Handle(s.to_owned())
+    }
+
+    #[test]
+    fn non_xid_client_handles_can_be_managed() {
+        let mut ss: StackSet<Handle> = StackSet::try_new(
+            LayoutStack::default(),
+            ["1", "2", "3"],
+            vec![Rect::default(); 2],
+        )
+        .expect("enough workspaces to cover the number of screens");
+
+        ss.insert(handle("a"));
+        ss.insert(handle("b"));
+        ss.move_client_to_tag(&handle("a"), "3");
+        ss.focus_client(&handle("a"));
+
+        assert_eq!(ss.current_tag(), "3");
+        assert_eq!(ss.current_client(), Some(&handle("a")));
+        assert_eq!(ss.tag_for_client(&handle("b")), Some("1"));
+
+        ss.update_screens(vec![Rect::default(); 3]).unwrap();
+        assert_eq!(ss.screens().count(), 3);
+        assert!(ss.contains(&handle("a")) && ss.contains(&handle("b")));
+
+        assert_eq!(ss.remove_client(&handle("b")), Some(handle("b")));
+        assert!(!ss.contains(&handle("b")));
+    }
+
     #[test]
     fn update_screens_with_empty_vec_is_an_error() {
         let mut ss: StackSet<Xid> =
@@ -1437,6 +1875,71 @@ pub mod tests {
 
         assert!(matches!(res, Err(Error::NoScreens)));
     }
+
+    #[cfg(feature = "serde")]
+    mod serde_tests {
+        use super::*;
+        use crate::{
+            builtin::layout::{MainAndStack, Monocle},
+            core::layout::Layout,
+        };
+
+        fn layouts() -> LayoutStack {
+            stack!(MainAndStack::side(1, 0.6, 0.1), Monocle::boxed())
+        }
+
+        fn populated_stack_set() -> StackSet<Xid> {
+            let mut s = test_xid_stack_set(5, 2);
+            s.restore_layouts(&layouts());
+            s.insert(Xid(1));
+            s.insert(Xid(2));
+            s.float_unchecked(Xid(2), Rect::new(10, 20, 300, 400));
+            s.focus_tag("4");
+            s.insert(Xid(3));
+            s.current_workspace_mut().next_layout();
+            s.focus_tag("1");
+
+            s
+        }
+
+        #[test]
+        fn round_trip_preserves_state() {
+            let s = populated_stack_set();
+            let json = serde_json::to_string(&s).unwrap();
+            let mut restored: StackSet<Xid> = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+            assert_eq!(restored.current_tag(), "1");
+            assert_eq!(restored.tag_for_client(&Xid(3)), Some("4"));
+            assert!(restored.is_floating(&Xid(2)));
+            assert_eq!(restored.previous_tag, "4");
+
+            restored.restore_layouts(&layouts());
+            let ws = restored.workspace("4").unwrap();
+            assert_eq!(ws.layout_name(), Monocle.name());
+            assert_eq!(ws.layouts.len(), 2);
+        }
+
+        #[test]
+        fn unknown_schema_versions_are_rejected() {
+            let mut val = serde_json::to_value(populated_stack_set()).unwrap();
+            val["version"] = (StackSet::<Xid>::SCHEMA_VERSION + 1).into();
+
+            let res: std::result::Result<StackSet<Xid>, _> = serde_json::from_value(val);
+
+            assert!(res.is_err());
+        }
+
+        #[test]
+        fn duplicate_tags_are_rejected() {
+            let mut val = serde_json::to_value(populated_stack_set()).unwrap();
+            val["hidden"][0]["tag"] = "1".into();
+
+            let res: std::result::Result<StackSet<Xid>, _> = serde_json::from_value(val);
+
+            assert!(res.is_err());
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1450,7 +1953,7 @@ mod quickcheck_tests {
     where
         C: Copy + Clone + PartialEq + Eq + Hash,
     {
-        pub fn try_from_arbitrary_vec(mut up: Vec<C>, g: &mut Gen) -> Option<Self> {
+        pub(crate) fn try_from_arbitrary_vec(mut up: Vec<C>, g: &mut Gen) -> Option<Self> {
             let focus = match up.len() {
                 0 => return None,
                 1 => return Some(stack!(up.remove(0))),
@@ -1465,7 +1968,7 @@ mod quickcheck_tests {
     }
 
     impl StackSet<Xid> {
-        pub fn minimal_unknown_client(&self) -> Xid {
+        pub(crate) fn minimal_unknown_client(&self) -> Xid {
             let mut c = 0;
 
             while self.contains(&Xid(c)) {
@@ -1475,11 +1978,11 @@ mod quickcheck_tests {
             Xid(c)
         }
 
-        pub fn first_hidden_tag(&self) -> Option<String> {
+        pub(crate) fn first_hidden_tag(&self) -> Option<String> {
             self.hidden.iter().map(|w| w.tag.clone()).next()
         }
 
-        pub fn last_tag(&self) -> String {
+        pub(crate) fn last_tag(&self) -> String {
             self.workspaces()
                 .last()
                 .expect("at least one workspace")
@@ -1487,7 +1990,7 @@ mod quickcheck_tests {
                 .clone()
         }
 
-        pub fn last_visible_client(&self) -> Option<&Xid> {
+        pub(crate) fn last_visible_client(&self) -> Option<&Xid> {
             self.screens
                 .down
                 .back()
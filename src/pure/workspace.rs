@@ -1,19 +1,33 @@
 use crate::{
+    builtin::layout::messages::ResetLayout,
     core::layout::{IntoMessage, LayoutStack},
-    pure::{Position, Stack},
+    pure::{FocusPolicy, Position, Stack},
     stack, Error, Result,
 };
-use std::{fmt, mem::take};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{cmp::Ordering, fmt, mem::take};
 
 /// A wrapper around a [Stack] of windows belonging to a single "workspace" or virtual
 /// desktop. When this workspace is active on a given screen, the windows contained in
 /// its stack will be positioned using the active layout of its [LayoutStack].
+///
+/// The state of each layout (e.g. ratios and main area counts) is tracked independently
+/// per workspace and retained when switching between layouts. A copy of the initial layouts
+/// is kept so that they can be reset by sending a [ResetLayout] message.
+///
+/// With the `serde` feature enabled a Workspace can be serialized and deserialized. Layouts
+/// are trait objects so only the name of the active layout is stored: a deserialized
+/// Workspace uses the default [LayoutStack] until the layouts from your config are restored
+/// using [StackSet::restore_layouts][crate::pure::StackSet::restore_layouts].
 #[derive(Debug, Clone)]
 pub struct Workspace<T> {
     pub(crate) id: usize,
     pub(crate) tag: String,
     pub(crate) layouts: LayoutStack,
+    pub(crate) default_layouts: LayoutStack,
     pub(crate) stack: Option<Stack<T>>,
+    pub(crate) restored_layout: Option<String>, // active layout name read during deserialization
 }
 
 impl<T> Default for Workspace<T> {
@@ -22,7 +36,9 @@ impl<T> Default for Workspace<T> {
             id: Default::default(),
             tag: Default::default(),
             layouts: Default::default(),
+            default_layouts: Default::default(),
             stack: Default::default(),
+            restored_layout: None,
         }
     }
 }
@@ -52,8 +68,10 @@ impl<T> Workspace<T> {
         Self {
             id,
             tag: tag.into(),
+            default_layouts: layouts.clone(),
             layouts,
             stack,
+            restored_layout: None,
         }
     }
 
@@ -106,20 +124,47 @@ impl<T> Workspace<T> {
     }
 
     pub(crate) fn insert_as_focus(&mut self, c: T) {
+        self.insert_as_focus_at(Position::Focus, c)
+    }
+
+    pub(crate) fn insert_as_focus_at(&mut self, pos: Position, c: T) {
+        self.insert_at_with_focus(pos, c, FocusPolicy::Take)
+    }
+
+    /// Insert the given client at the requested [Position] in the stack of this workspace,
+    /// either moving focus to it or retaining the current focus depending on the given
+    /// [FocusPolicy].
+    pub fn insert_at_with_focus(&mut self, pos: Position, c: T, policy: FocusPolicy) {
         self.stack = Some(match take(&mut self.stack) {
             None => stack!(c),
             Some(mut s) => {
-                s.insert_at(Position::Focus, c);
+                s.insert_at_with_focus(pos, c, policy);
                 s
             }
         });
     }
 
+    /// Sort the clients of this workspace using the given comparison function. The sort is
+    /// stable and the currently focused client is maintained by this operation.
+    pub fn sort_clients_by<F>(&mut self, cmp: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        if let Some(s) = self.stack.as_mut() {
+            s.sort_by(cmp);
+        }
+    }
+
     /// Pass the given message on to the currently focused layout.
     pub fn handle_message<M>(&mut self, m: M)
     where
         M: IntoMessage,
     {
+        let m = m.into_message();
+        if m.downcast_ref::<ResetLayout>().is_some() {
+            return self.reset_layout();
+        }
+
         self.layouts.handle_message(m)
     }
 
@@ -128,9 +173,33 @@ impl<T> Workspace<T> {
     where
         M: IntoMessage,
     {
+        let m = m.into_message();
+        if m.downcast_ref::<ResetLayout>().is_some() {
+            return self.reset_all_layouts();
+        }
+
         self.layouts.broadcast_message(m)
     }
 
+    /// Reset the currently active layout back to the state it was in when it was
+    /// first added to this workspace.
+    pub fn reset_layout(&mut self) {
+        let ix = self.layouts.up.len();
+        if let Some(l) = self.default_layouts.iter().nth(ix) {
+            self.layouts.focus = l.clone();
+        }
+    }
+
+    /// Reset all layouts available to this workspace back to the state they were in
+    /// when they were first added, retaining the currently active layout.
+    pub fn reset_all_layouts(&mut self) {
+        let ix = self.layouts.up.len();
+        self.layouts = self.default_layouts.clone();
+        for _ in 0..ix {
+            self.layouts.focus_down();
+        }
+    }
+
     /// Switch to the next available layout for this workspace.
     pub fn next_layout(&mut self) {
         self.layouts.focus_down();
@@ -144,6 +213,7 @@ impl<T> Workspace<T> {
     /// Replace the current [LayoutStack] with a new one, returning the layouts that
     /// were previously active.
     pub fn set_available_layouts(&mut self, mut layouts: LayoutStack) -> LayoutStack {
+        self.default_layouts = layouts.clone();
         std::mem::swap(&mut self.layouts, &mut layouts);
 
         layouts
@@ -158,6 +228,66 @@ impl<T> Workspace<T> {
     pub fn set_layout_by_name(&mut self, name: &str) {
         self.layouts.focus_element_by(|l| l.name() == name)
     }
+
+    // Replace the layouts of a deserialized workspace, restoring the active layout if the
+    // layout it was using is still available.
+    pub(crate) fn restore_layouts(&mut self, layouts: LayoutStack) {
+        self.set_available_layouts(layouts);
+        if let Some(name) = self.restored_layout.take() {
+            self.set_layout_by_name(&name);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct WorkspaceRef<'a, T> {
+    id: usize,
+    tag: &'a str,
+    layout: String,
+    stack: &'a Option<Stack<T>>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct WorkspaceRepr<T> {
+    id: usize,
+    tag: String,
+    layout: String,
+    stack: Option<Stack<T>>,
+}
+
+#[cfg(feature = "serde")]
+impl<T: Serialize> Serialize for Workspace<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        WorkspaceRef {
+            id: self.id,
+            tag: &self.tag,
+            layout: match &self.restored_layout {
+                Some(name) => name.clone(),
+                None => self.layout_name(),
+            },
+            stack: &self.stack,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Workspace<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let WorkspaceRepr {
+            id,
+            tag,
+            layout,
+            stack,
+        } = WorkspaceRepr::deserialize(deserializer)?;
+
+        let mut ws = Workspace::new(id, tag, LayoutStack::default(), stack);
+        ws.restored_layout = Some(layout);
+
+        Ok(ws)
+    }
 }
 
 impl<T: PartialEq> Workspace<T> {
@@ -203,9 +333,105 @@ pub(crate) fn check_workspace_invariants<T>(workspaces: &[Workspace<T>]) -> Resu
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::stack;
+    use crate::{
+        builtin::layout::messages::IncMain,
+        core::layout::{Layout, Message},
+        pure::geometry::Rect,
+        stack, Xid,
+    };
     use simple_test_case::test_case;
 
+    #[derive(Debug, Clone, Copy)]
+    struct Counter(&'static str, i8);
+
+    impl Layout for Counter {
+        fn name(&self) -> String {
+            format!("{}-{}", self.0, self.1)
+        }
+
+        fn boxed_clone(&self) -> Box<dyn Layout> {
+            Box::new(*self)
+        }
+
+        fn layout(
+            &mut self,
+            _: &Stack<Xid>,
+            _: Rect,
+        ) -> (Option<Box<dyn Layout>>, Vec<(Xid, Rect)>) {
+            (None, vec![])
+        }
+
+        fn handle_message(&mut self, m: &Message) -> Option<Box<dyn Layout>> {
+            if let Some(&IncMain(n)) = m.downcast_ref() {
+                self.1 += n;
+            }
+
+            None
+        }
+    }
+
+    fn counter_workspace() -> Workspace<u8> {
+        let a: Box<dyn Layout> = Box::new(Counter("a", 0));
+        let b: Box<dyn Layout> = Box::new(Counter("b", 0));
+        let mut w = Workspace::new(0, "test", stack!(a, b), None);
+        w.broadcast_message(IncMain(2));
+        w.next_layout();
+        w.handle_message(IncMain(1));
+
+        w
+    }
+
+    #[test]
+    fn layout_state_persists_when_cycling_layouts() {
+        let mut w = counter_workspace();
+        w.next_layout();
+
+        let names: Vec<String> = w.layouts.iter().map(|l| l.name()).collect();
+
+        assert_eq!(w.layout_name(), "a-2");
+        assert_eq!(names, vec!["a-2", "b-3"]);
+    }
+
+    #[test]
+    fn reset_layout_only_resets_the_active_layout() {
+        let mut w = counter_workspace();
+        w.handle_message(ResetLayout);
+
+        let names: Vec<String> = w.layouts.iter().map(|l| l.name()).collect();
+
+        assert_eq!(w.layout_name(), "b-0");
+        assert_eq!(names, vec!["a-2", "b-0"]);
+    }
+
+    #[test]
+    fn reset_layout_is_recognised_when_already_wrapped_as_a_message() {
+        let mut w = counter_workspace();
+        w.handle_message(ResetLayout.into_message());
+
+        assert_eq!(w.layout_name(), "b-0");
+    }
+
+    #[test]
+    fn broadcast_reset_layout_resets_all_layouts() {
+        let mut w = counter_workspace();
+        w.broadcast_message(ResetLayout);
+
+        let names: Vec<String> = w.layouts.iter().map(|l| l.name()).collect();
+
+        assert_eq!(w.layout_name(), "b-0");
+        assert_eq!(names, vec!["a-0", "b-0"]);
+    }
+
+    #[test]
+    fn broadcast_reset_layout_is_recognised_when_already_wrapped_as_a_message() {
+        let mut w = counter_workspace();
+        w.broadcast_message(ResetLayout.into_message());
+
+        let names: Vec<String> = w.layouts.iter().map(|l| l.name()).collect();
+
+        assert_eq!(names, vec!["a-0", "b-0"]);
+    }
+
     #[test_case(Some(stack!([1, 2], 3, [4, 5])), Some(5), true; "known in stack")]
     #[test_case(Some(stack!(5)), Some(5), false; "known focus only")]
     #[test_case(Some(stack!([1, 2], 3, [4])), None, true; "unknown")]
@@ -4,111 +4,61 @@ use crate::{
     pure::{geometry::Rect, Stack},
     Result, Xid,
 };
-use std::{
-    io::Read,
-    process::{Command, Stdio},
-};
-use tracing::trace;
+use std::{process::Command, time::Duration};
+
+mod spawn;
+#[cfg(feature = "x11rb")]
+pub mod xephyr;
+
+pub use spawn::SpawnSpec;
+
+pub(crate) use spawn::{untrack_children, waiting_for_children, WaitingForChild};
 
 /// Run an external command
 ///
-/// This redirects the process stdout and stderr to /dev/null.
+/// This redirects the process stdout and stderr to /dev/null. See [SpawnSpec] for more control
+/// over how the command is run.
 pub fn spawn<S: Into<String>>(cmd: S) -> Result<()> {
-    let s = cmd.into();
-    let parts: Vec<&str> = s.split_whitespace().collect();
-    let result = if parts.len() > 1 {
-        Command::new(parts[0])
-            .args(&parts[1..])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-    } else {
-        Command::new(parts[0])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-    };
-
-    match result {
-        Ok(_) => Ok(()),
-        Err(e) => Err(e.into()),
-    }
+    SpawnSpec::from(cmd.into()).spawn()
 }
 
 /// Run an external command with the specified command line arguments
 ///
 /// This redirects the process stdout and stderr to /dev/null.
 pub fn spawn_with_args<S: Into<String>>(cmd: S, args: &[&str]) -> Result<()> {
-    let result = Command::new(cmd.into())
-        .args(args)
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn();
-
-    match result {
-        Ok(_) => Ok(()),
-        Err(e) => Err(e.into()),
-    }
+    SpawnSpec::new(cmd).with_args(args.iter().copied()).spawn()
 }
 
 /// Run an external command and return its output.
-///
-/// > [`std::process::Command::output`] will not work within penrose due to the
-/// > way that signal handling is set up. Use this function if you need to access the
-/// > output of a process that you spawn.
 pub fn spawn_for_output<S: Into<String>>(cmd: S) -> std::io::Result<String> {
-    let cmd = cmd.into();
-    trace!(?cmd, "spawning subprocess for output");
-    let parts: Vec<&str> = cmd.split_whitespace().collect();
-    let result = if parts.len() > 1 {
-        Command::new(parts[0])
-            .stdout(Stdio::piped())
-            .args(&parts[1..])
-            .spawn()
-    } else {
-        Command::new(parts[0]).stdout(Stdio::piped()).spawn()
-    };
-
-    trace!(?cmd, "reading output");
-    let mut child = result?;
-    let mut buff = String::new();
-    child
-        .stdout
-        .take()
-        .expect("to have output")
-        .read_to_string(&mut buff)
-        .map(|_| buff)
+    SpawnSpec::from(cmd.into()).spawn_for_output()
 }
 
 /// Run an external command with arguments and return its output.
-///
-/// > [`std::process::Command::output`] will not work within penrose due to the
-/// > way that signal handling is set up. Use this function if you need to access the
-/// > output of a process that you spawn.
 pub fn spawn_for_output_with_args<S: Into<String>>(
     cmd: S,
     args: &[&str],
 ) -> std::io::Result<String> {
-    let cmd = cmd.into();
-
-    trace!(?cmd, ?args, "spawning subprocess for output");
-    let mut child = Command::new(&cmd)
-        .stdout(Stdio::piped())
-        .args(args)
-        .spawn()?;
+    SpawnSpec::new(cmd)
+        .with_args(args.iter().copied())
+        .spawn_for_output()
+}
 
-    trace!(?cmd, ?args, "reading output");
-    let mut buff = String::new();
-    child
-        .stdout
-        .take()
-        .unwrap()
-        .read_to_string(&mut buff)
-        .map(|_| buff)
+/// Run an external command with arguments and return its output, killing it and returning an
+/// error of kind [TimedOut][std::io::ErrorKind::TimedOut] if it has not exited within `timeout`.
+pub fn spawn_for_output_with_timeout<S: Into<String>>(
+    cmd: S,
+    args: &[&str],
+    timeout: Duration,
+) -> std::io::Result<String> {
+    SpawnSpec::new(cmd)
+        .with_args(args.iter().copied())
+        .spawn_for_output_with_timeout(timeout)
 }
 
 /// Use `notify-send` to display a message to the user
 pub fn notify(msg: &str) -> std::io::Result<()> {
+    let _waiting = WaitingForChild::new();
     Command::new("notify-send").arg(msg).output().map(|_| ())
 }
 
@@ -0,0 +1,357 @@
+//! Configurable spawning of external programs.
+use crate::Result;
+use std::{
+    fmt, io,
+    io::Read,
+    os::unix::process::CommandExt,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::channel,
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use tracing::{error, trace};
+
+// The IDs of child processes that have been spawned without waiting for them to exit. These are
+// reaped by the window manager when it receives SIGCHLD and have their exit status passed to the
+// child exit hook.
+static SPAWNED_CHILDREN: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+// The number of child processes currently being waited on by penrose itself. Other exited
+// children are only reaped by the window manager while this is zero so that it does not steal
+// the exit status of a child that something else is waiting on.
+static WAITING_FOR_CHILDREN: AtomicUsize = AtomicUsize::new(0);
+
+// Held for as long as a child process is being waited on in-process, from before it is spawned
+// until after its exit status has been collected.
+pub(crate) struct WaitingForChild;
+
+impl WaitingForChild {
+    pub(crate) fn new() -> Self {
+        WAITING_FOR_CHILDREN.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for WaitingForChild {
+    fn drop(&mut self) {
+        WAITING_FOR_CHILDREN.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub(crate) fn waiting_for_children() -> bool {
+    WAITING_FOR_CHILDREN.load(Ordering::SeqCst) > 0
+}
+
+pub(crate) fn track_child(pid: u32) {
+    match SPAWNED_CHILDREN.lock() {
+        Ok(mut pids) => pids.push(pid),
+        Err(poisoned) => poisoned.into_inner().push(pid),
+    }
+}
+
+// Remove and return the tracked children for which `f` returns true.
+pub(crate) fn untrack_children(mut f: impl FnMut(u32) -> bool) -> Vec<u32> {
+    let mut pids = match SPAWNED_CHILDREN.lock() {
+        Ok(pids) => pids,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    let mut removed = Vec::new();
+    pids.retain(|&pid| {
+        let remove = f(pid);
+        if remove {
+            removed.push(pid);
+        }
+        !remove
+    });
+
+    removed
+}
+
+/// A description of an external program to run along with how it should be run.
+///
+/// For simple cases a [SpawnSpec] can be created from a string containing the program name
+/// and any arguments separated by whitespace:
+///
+/// ```no_run
+/// # use penrose::util::SpawnSpec;
+/// SpawnSpec::from("st -e htop").spawn()?;
+/// # Ok::<(), penrose::Error>(())
+/// ```
+///
+/// More complicated commands can be built up using the `with_*` methods:
+///
+/// ```no_run
+/// # use penrose::util::SpawnSpec;
+/// SpawnSpec::new("make")
+///     .with_args(["-j", "8"])
+///     .with_env("RUSTFLAGS", "-Dwarnings")
+///     .with_cwd("/home/me/projects/penrose")
+///     .with_setsid()
+///     .with_output_callback(|out| println!("build finished: {out}"))
+///     .spawn()?;
+/// # Ok::<(), penrose::Error>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct SpawnSpec {
+    program: String,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    cwd: Option<PathBuf>,
+    setsid: bool,
+    on_output: Option<Arc<dyn Fn(String) + Send + Sync>>,
+}
+
+impl fmt::Debug for SpawnSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpawnSpec")
+            .field("program", &self.program)
+            .field("args", &self.args)
+            .field("env", &self.env)
+            .field("cwd", &self.cwd)
+            .field("setsid", &self.setsid)
+            .field("on_output", &self.on_output.is_some())
+            .finish()
+    }
+}
+
+impl From<&str> for SpawnSpec {
+    fn from(s: &str) -> Self {
+        let mut parts = s.split_whitespace();
+        let program = parts.next().unwrap_or_default();
+
+        Self::new(program).with_args(parts)
+    }
+}
+
+impl From<String> for SpawnSpec {
+    fn from(s: String) -> Self {
+        Self::from(s.as_str())
+    }
+}
+
+impl SpawnSpec {
+    /// Create a new [SpawnSpec] for running the given program with no arguments.
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Add the given arguments to the command line of the program.
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+
+        self
+    }
+
+    /// Set an environment variable for the program, overriding any value inherited from penrose.
+    pub fn with_env(mut self, key: impl Into<String>, val: impl Into<String>) -> Self {
+        self.env.push((key.into(), val.into()));
+
+        self
+    }
+
+    /// Set the working directory the program is run in.
+    pub fn with_cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.cwd = Some(cwd.into());
+
+        self
+    }
+
+    /// Run the program in a new session so that it is detached from penrose and will not
+    /// receive signals sent to the window manager's process group.
+    pub fn with_setsid(mut self) -> Self {
+        self.setsid = true;
+
+        self
+    }
+
+    /// Capture the stdout of the program and pass it to the given callback once the program
+    /// exits.
+    ///
+    /// The callback is run on a background thread so it is not able to modify the window
+    /// manager state directly.
+    pub fn with_output_callback<F>(mut self, f: F) -> Self
+    where
+        F: Fn(String) + Send + Sync + 'static,
+    {
+        self.on_output = Some(Arc::new(f));
+
+        self
+    }
+
+    fn command(&self, stdout: Stdio) -> Command {
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&self.args).stdout(stdout);
+
+        for (k, v) in self.env.iter() {
+            cmd.env(k, v);
+        }
+        if let Some(cwd) = &self.cwd {
+            cmd.current_dir(cwd);
+        }
+        if self.setsid {
+            // SAFETY: setsid is async-signal-safe and does not allocate
+            unsafe {
+                cmd.pre_exec(|| nix::unistd::setsid().map(|_| ()).map_err(io::Error::from));
+            }
+        }
+
+        cmd
+    }
+
+    /// Run the program without waiting for it to exit.
+    ///
+    /// Unless an output callback has been set, stdout and stderr are redirected to /dev/null.
+    pub fn spawn(&self) -> Result<()> {
+        self.spawn_for_pid().map(|_| ())
+    }
+
+    /// Run the program without waiting for it to exit, returning its process ID.
+    ///
+    /// Unless an output callback has been set, stdout and stderr are redirected to /dev/null.
+    /// The exit status of the program is passed to the [ChildExitHook][0] if one is set.
+    ///
+    ///   [0]: crate::core::hooks::ChildExitHook
+    pub fn spawn_for_pid(&self) -> Result<u32> {
+        trace!(spec = ?self, "spawning subprocess");
+        let on_output = match &self.on_output {
+            Some(f) => f.clone(),
+            None => {
+                let child = self.command(Stdio::null()).stderr(Stdio::null()).spawn()?;
+                let pid = child.id();
+                track_child(pid);
+                return Ok(pid);
+            }
+        };
+
+        let waiting = WaitingForChild::new();
+        let child = self.command(Stdio::piped()).stderr(Stdio::null()).spawn()?;
+        let pid = child.id();
+        let program = self.program.clone();
+        thread::spawn(move || {
+            let res = read_stdout(child);
+            drop(waiting);
+            match res {
+                Ok(s) => (on_output)(s),
+                Err(e) => error!(%e, %program, "unable to read subprocess output"),
+            }
+        });
+
+        Ok(pid)
+    }
+
+    /// Run the program and return its output.
+    pub fn spawn_for_output(&self) -> io::Result<String> {
+        trace!(spec = ?self, "spawning subprocess for output");
+        let _waiting = WaitingForChild::new();
+        read_stdout(self.command(Stdio::piped()).spawn()?)
+    }
+
+    /// Run the program and return its output, killing it if it has not exited before the
+    /// given timeout.
+    pub fn spawn_for_output_with_timeout(&self, timeout: Duration) -> io::Result<String> {
+        trace!(spec = ?self, ?timeout, "spawning subprocess for output with timeout");
+        let _waiting = WaitingForChild::new();
+        let mut child = self.command(Stdio::piped()).spawn()?;
+        let mut stdout = child.stdout.take().expect("to have piped stdout");
+
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            let mut buf = String::new();
+            _ = tx.send(stdout.read_to_string(&mut buf).map(|_| buf));
+        });
+
+        match rx.recv_timeout(timeout) {
+            Ok(res) => {
+                child.wait()?;
+                res
+            }
+            Err(_) => {
+                _ = child.kill();
+                _ = child.wait();
+                Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("{} did not exit within {timeout:?}", self.program),
+                ))
+            }
+        }
+    }
+}
+
+fn read_stdout(mut child: Child) -> io::Result<String> {
+    let mut buf = String::new();
+    child
+        .stdout
+        .take()
+        .expect("to have piped stdout")
+        .read_to_string(&mut buf)?;
+    child.wait()?;
+
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn from_str_splits_args() {
+        let spec = SpawnSpec::from("st -e  htop");
+
+        assert_eq!(spec.program, "st");
+        assert_eq!(spec.args, vec!["-e", "htop"]);
+    }
+
+    #[test]
+    fn env_and_cwd_are_applied() {
+        let out = SpawnSpec::new("sh")
+            .with_args(["-c", "echo $PENROSE_TEST $(pwd)"])
+            .with_env("PENROSE_TEST", "hello")
+            .with_cwd("/")
+            .spawn_for_output()
+            .unwrap();
+
+        assert_eq!(out, "hello /\n");
+    }
+
+    #[test]
+    fn output_callback_receives_stdout() {
+        let (tx, rx) = channel();
+        let tx = Mutex::new(tx);
+        SpawnSpec::from("echo hello")
+            .with_output_callback(move |s| tx.lock().unwrap().send(s).unwrap())
+            .spawn()
+            .unwrap();
+
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), "hello\n");
+    }
+
+    #[test]
+    fn timeout_kills_slow_programs() {
+        let res =
+            SpawnSpec::from("sleep 5").spawn_for_output_with_timeout(Duration::from_millis(50));
+
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn fast_programs_complete_within_timeout() {
+        let res =
+            SpawnSpec::from("echo done").spawn_for_output_with_timeout(Duration::from_secs(5));
+
+        assert_eq!(res.unwrap(), "done\n");
+    }
+}
@@ -0,0 +1,324 @@
+//! A harness for running end-to-end tests of a window manager config inside of Xephyr.
+//!
+//! [Xephyr] launches a nested X server on the requested display, runs a [WindowManager] built
+//! from your config inside of it and allows for spawning real client programs (such as `xterm`
+//! or `xeyes`) so that the resulting client geometry and window properties can be checked.
+//!
+//! Running these tests requires `Xephyr` to be installed along with any client programs you
+//! spawn so they are typically marked as `#[ignore]` and run explicitly:
+//!
+//! ```no_run
+//! use penrose::{
+//!     core::{Config, WindowManager},
+//!     pure::geometry::Rect,
+//!     util::xephyr::Xephyr,
+//! };
+//! use std::{collections::HashMap, time::Duration};
+//!
+//! let mut xephyr = Xephyr::start(5, 800, 600)?;
+//! xephyr.run_wm(|conn| {
+//!     WindowManager::new(Config::default(), HashMap::new(), HashMap::new(), conn)
+//! })?;
+//!
+//! xephyr.spawn_client("xterm", &[])?;
+//! xephyr.spawn_client("xeyes", &[])?;
+//! xephyr.wait_for_clients(2, Duration::from_secs(5))?;
+//!
+//! xephyr.assert_client_geometries(&[
+//!     Rect::new(0, 0, 476, 596),
+//!     Rect::new(480, 0, 316, 596),
+//! ]);
+//! # Ok::<(), penrose::Error>(())
+//! ```
+//!
+//! The window manager is told to exit and all spawned processes are killed when the [Xephyr]
+//! harness is dropped.
+use crate::{
+    core::{State, WindowManager},
+    pure::geometry::Rect,
+    x::{
+        event::{ClientEventMask, ClientMessage},
+        property::MapState,
+        Prop, XConn, XEvent,
+    },
+    x11rb::RustConn,
+    Error, Result, Xid,
+};
+use std::{
+    process::{Child, Command, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use tracing::{error, warn};
+use x11rb::protocol::xproto::{ConnectionExt, EventMask};
+
+// The type of the client message sent to the root window in order to tell the window manager
+// being tested to exit.
+const EXIT_ATOM: &str = "_PENROSE_XEPHYR_EXIT";
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A nested X server for running end-to-end tests against a [WindowManager].
+///
+/// See the module level docs for details.
+#[derive(Debug)]
+pub struct Xephyr {
+    display: String,
+    server: Child,
+    conn: RustConn,
+    wm: Option<JoinHandle<Result<()>>>,
+    clients: Vec<Child>,
+}
+
+impl Xephyr {
+    /// Start a new Xephyr server on display `:n` with a single screen of the given size, waiting
+    /// for it to begin accepting connections.
+    pub fn start(n: u32, w: u32, h: u32) -> Result<Self> {
+        let display = format!(":{n}");
+        let server = Command::new("Xephyr")
+            .args(["+extension", "RANDR", "-screen", &format!("{w}x{h}")])
+            .args(["-ac", "-br", "-noreset", &display])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let conn = match poll_until(STARTUP_TIMEOUT, || RustConn::new_for_display(&display).ok()) {
+            Some(conn) => conn,
+            None => {
+                let mut server = server;
+                _ = server.kill();
+                return Err(Error::Custom(format!(
+                    "timed out waiting for Xephyr to start on display {display}"
+                )));
+            }
+        };
+
+        Ok(Self {
+            display,
+            server,
+            conn,
+            wm: None,
+            clients: Vec::new(),
+        })
+    }
+
+    /// The name of the display this server is running on.
+    pub fn display(&self) -> &str {
+        &self.display
+    }
+
+    /// A connection to the nested X server for making queries against its current state.
+    pub fn conn(&self) -> &RustConn {
+        &self.conn
+    }
+
+    /// Build a [WindowManager] using the provided function and run it on a background thread,
+    /// waiting for it to start managing the root window before returning.
+    ///
+    /// The [WindowManager] needs to be constructed on the thread it runs on so `build` is passed
+    /// a connection to the nested X server for you to use in place of `RustConn::new`.
+    pub fn run_wm<F>(&mut self, build: F) -> Result<()>
+    where
+        F: FnOnce(RustConn) -> Result<WindowManager<RustConn>> + Send + 'static,
+    {
+        if self.wm.is_some() {
+            return Err(Error::Custom(
+                "a window manager is already running".to_owned(),
+            ));
+        }
+
+        let display = self.display.clone();
+        self.wm = Some(thread::spawn(move || {
+            let mut wm = build(RustConn::new_for_display(&display)?)?;
+            wm.state.config.compose_or_set_event_hook(
+                |e: &XEvent, state: &mut State<RustConn>, _: &RustConn| {
+                    if let XEvent::ClientMessage(m) = e {
+                        if m.dtype == EXIT_ATOM {
+                            state.running = false;
+                            return Ok(false);
+                        }
+                    }
+
+                    Ok(true)
+                },
+            );
+
+            wm.run()
+        }));
+
+        let root = *self.conn.root();
+        let started = poll_until(STARTUP_TIMEOUT, || {
+            let attrs = self.conn.connection().get_window_attributes(root).ok()?;
+            let attrs = attrs.reply().ok()?;
+
+            attrs
+                .all_event_masks
+                .contains(EventMask::SUBSTRUCTURE_REDIRECT)
+                .then_some(())
+        });
+
+        if started.is_some() {
+            return Ok(());
+        }
+
+        // If the window manager failed to start then surface the error it returned
+        match self.wm.take().map(|h| h.is_finished().then(|| h.join())) {
+            Some(Some(Ok(Err(e)))) => Err(e),
+            Some(Some(Err(_))) => Err(Error::Custom("window manager panicked".to_owned())),
+            _ => Err(Error::Custom(
+                "timed out waiting for the window manager to start".to_owned(),
+            )),
+        }
+    }
+
+    /// Spawn a client program running against the nested X server.
+    ///
+    /// The process will be killed when this harness is dropped.
+    pub fn spawn_client(&mut self, program: &str, args: &[&str]) -> Result<()> {
+        let child = Command::new(program)
+            .args(args)
+            .env("DISPLAY", &self.display)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        self.clients.push(child);
+
+        Ok(())
+    }
+
+    /// The currently visible top level client windows in the order returned by the X server.
+    ///
+    /// Override redirect windows (such as menus and notifications) are not included.
+    pub fn visible_clients(&self) -> Result<Vec<Xid>> {
+        let mut visible = Vec::new();
+        for id in self.conn.existing_clients()? {
+            let attrs = self.conn.get_window_attributes(id)?;
+            if attrs.map_state == MapState::Viewable && !attrs.override_redirect {
+                visible.push(id);
+            }
+        }
+
+        Ok(visible)
+    }
+
+    /// Wait for at least `n` top level client windows to be visible, returning their IDs.
+    pub fn wait_for_clients(&self, n: usize, timeout: Duration) -> Result<Vec<Xid>> {
+        poll_until(timeout, || {
+            self.visible_clients().ok().filter(|ids| ids.len() >= n)
+        })
+        .ok_or_else(|| Error::Custom(format!("timed out waiting for {n} visible clients")))
+    }
+
+    /// The geometry of each currently visible client, sorted by position.
+    pub fn client_geometries(&self) -> Result<Vec<Rect>> {
+        let mut rects = self
+            .visible_clients()?
+            .into_iter()
+            .map(|id| self.conn.client_geometry(id))
+            .collect::<Result<Vec<_>>>()?;
+        rects.sort_by_key(|r| (r.x, r.y, r.w, r.h));
+
+        Ok(rects)
+    }
+
+    /// Assert that the currently visible clients have exactly the given geometry.
+    ///
+    /// The order of `expected` is not significant.
+    ///
+    /// # Panics
+    /// This method will panic if the geometry does not match or the client geometry could not
+    /// be read from the X server.
+    pub fn assert_client_geometries(&self, expected: &[Rect]) {
+        let mut expected = expected.to_vec();
+        expected.sort_by_key(|r| (r.x, r.y, r.w, r.h));
+        let actual = self
+            .client_geometries()
+            .expect("unable to read client geometry");
+
+        assert_eq!(actual, expected, "client geometry does not match");
+    }
+
+    /// Assert that the given window has a property set to the expected value.
+    ///
+    /// # Panics
+    /// This method will panic if the property is not set to `expected` or the property could
+    /// not be read from the X server.
+    pub fn assert_prop(&self, id: Xid, prop: &str, expected: Prop) {
+        let actual = self
+            .conn
+            .get_prop(id, prop)
+            .expect("unable to read property");
+
+        assert_eq!(
+            actual,
+            Some(expected),
+            "unexpected value for {prop} on {id}"
+        );
+    }
+
+    /// Assert that the root window has a property set to the expected value. This is useful for
+    /// checking EWMH properties such as `_NET_ACTIVE_WINDOW` and `_NET_CURRENT_DESKTOP`.
+    ///
+    /// # Panics
+    /// This method will panic if the property is not set to `expected` or the property could
+    /// not be read from the X server.
+    pub fn assert_root_prop(&self, prop: &str, expected: Prop) {
+        self.assert_prop(self.conn.root(), prop, expected)
+    }
+
+    fn stop_wm(&mut self) {
+        let handle = match self.wm.take() {
+            Some(handle) => handle,
+            None => return,
+        };
+
+        let msg = ClientMessage::new(
+            self.conn.root(),
+            ClientEventMask::SubstructureNotify,
+            EXIT_ATOM,
+            [0u32; 5].into(),
+        );
+        if let Err(e) = self.conn.send_client_message(msg) {
+            error!(%e, "unable to tell window manager to exit");
+            return;
+        }
+        self.conn.flush();
+
+        match handle.join() {
+            Ok(Err(e)) => warn!(%e, "window manager exited with an error"),
+            Err(_) => warn!("window manager panicked"),
+            Ok(Ok(())) => (),
+        }
+    }
+}
+
+impl Drop for Xephyr {
+    fn drop(&mut self) {
+        self.stop_wm();
+
+        // Errors are ignored as the processes may have already exited (and the window manager
+        // reaps its child processes so they may have been reaped already).
+        for child in self.clients.iter_mut() {
+            _ = child.kill();
+            _ = child.wait();
+        }
+        _ = self.server.kill();
+        _ = self.server.wait();
+    }
+}
+
+fn poll_until<T, F>(timeout: Duration, mut f: F) -> Option<T>
+where
+    F: FnMut() -> Option<T>,
+{
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(t) = f() {
+            return Some(t);
+        }
+        if Instant::now() >= deadline {
+            return None;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
@@ -50,6 +50,12 @@ pub enum Atom {
     /// WM_TAKE_FOCUS
     #[strum(serialize = "WM_TAKE_FOCUS")]
     WmTakeFocus,
+    /// _XROOTPMAP_ID
+    #[strum(serialize = "_XROOTPMAP_ID")]
+    XRootPmapId,
+    /// ESETROOT_PMAP_ID
+    #[strum(serialize = "ESETROOT_PMAP_ID")]
+    EsetrootPmapId,
     /// _NET_ACTIVE_WINDOW
     #[strum(serialize = "_NET_ACTIVE_WINDOW")]
     NetActiveWindow,
@@ -101,6 +107,12 @@ pub enum Atom {
     /// _NET_WM_STRUT
     #[strum(serialize = "_NET_WM_STRUT")]
     NetWmStrut,
+    /// _NET_WM_USER_TIME
+    #[strum(serialize = "_NET_WM_USER_TIME")]
+    NetWmUserTime,
+    /// _NET_WM_USER_TIME_WINDOW
+    #[strum(serialize = "_NET_WM_USER_TIME_WINDOW")]
+    NetWmUserTimeWindow,
     /// _NET_WM_STATE_DEMANDS_ATTENTION
     #[strum(serialize = "_NET_WM_STATE_DEMANDS_ATTENTION")]
     NetWmStateDemandsAttention,
@@ -110,6 +122,12 @@ pub enum Atom {
     /// _NET_WM_STATE_HIDDEN
     #[strum(serialize = "_NET_WM_STATE_HIDDEN")]
     NetWmStateHidden,
+    /// _NET_WM_STATE_MAXIMIZED_HORZ
+    #[strum(serialize = "_NET_WM_STATE_MAXIMIZED_HORZ")]
+    NetWmStateMaximizedHorz,
+    /// _NET_WM_STATE_MAXIMIZED_VERT
+    #[strum(serialize = "_NET_WM_STATE_MAXIMIZED_VERT")]
+    NetWmStateMaximizedVert,
     /// _NET_WM_WINDOW_TYPE
     #[strum(serialize = "_NET_WM_WINDOW_TYPE")]
     NetWmWindowType,
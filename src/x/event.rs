@@ -34,12 +34,16 @@ pub enum XEvent {
     Destroy(Xid),
     /// A grabbed key combination has been entered by the user
     KeyPress(KeyCode),
+    /// The active keyboard layout (XKB group) has changed to the given index
+    KeyboardLayoutChange(u8),
     /// The mouse pointer has left the current client window
     Leave(PointerChange),
     /// Keybindings have changed
     MappingNotify,
     /// A client window is requesting to be positioned and rendered on the screen.
     MapRequest(Xid),
+    /// A window has been mapped (including override-redirect windows that are not managed)
+    MapNotify(Xid),
     /// A mouse button has been pressed or released
     MouseEvent(MouseEvent),
     /// The mouse has moved while a grabbed mouse state is held
@@ -54,6 +58,9 @@ pub enum XEvent {
     ScreenChange,
     /// A client is being unmapped
     UnmapNotify(Xid),
+    /// Another window manager has taken ownership of the window manager selection held by
+    /// penrose and is replacing it
+    WmReplaced,
 }
 
 impl std::fmt::Display for XEvent {
@@ -69,9 +76,11 @@ impl std::fmt::Display for XEvent {
             FocusIn(_) => write!(f, "FocusIn"),
             Destroy(_) => write!(f, "Destroy"),
             KeyPress(_) => write!(f, "KeyPress"),
+            KeyboardLayoutChange(_) => write!(f, "KeyboardLayoutChange"),
             Leave(_) => write!(f, "Leave"),
             MappingNotify => write!(f, "MappingNotify"),
             MapRequest(_) => write!(f, "MapRequest"),
+            MapNotify(_) => write!(f, "MapNotify"),
             MouseEvent(_) => write!(f, "MouseEvent"),
             MotionNotify(_) => write!(f, "MotionNotify"),
             PropertyNotify(_) => write!(f, "PropertyNotify"),
@@ -79,6 +88,7 @@ impl std::fmt::Display for XEvent {
             ResizeRequest(_) => write!(f, "ResizeRequest"),
             ScreenChange => write!(f, "ScreenChange"),
             UnmapNotify(_) => write!(f, "UnmapNotify"),
+            WmReplaced => write!(f, "WmReplaced"),
         }
     }
 }
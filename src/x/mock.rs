@@ -7,7 +7,7 @@ use crate::{
     x::{
         event::{ClientMessage, XEvent},
         property::{Prop, WindowAttributes, WmState},
-        ClientAttr, ClientConfig, XConn,
+        ClientAttr, ClientConfig, PointerQuery, XConn,
     },
     Result, Xid,
 };
@@ -18,7 +18,7 @@ use crate::{
 ///
 /// Any implementation of `MockXConn` will automatically implement `XConn` by forwarding on
 /// calls to `$method` to `mock_$method`.
-#[allow(unused_variables)]
+#[allow(unused_variables, missing_docs)]
 pub trait MockXConn {
     fn mock_root(&self) -> Xid {
         Xid(0)
@@ -28,10 +28,26 @@ pub trait MockXConn {
         unimplemented!("mock_screen_details")
     }
 
+    fn mock_screen_names(&self) -> Result<Vec<String>> {
+        unimplemented!("mock_screen_names")
+    }
+
+    fn mock_primary_screen(&self) -> Result<Option<usize>> {
+        unimplemented!("mock_primary_screen")
+    }
+
+    fn mock_set_primary_screen(&self, index: usize) -> Result<()> {
+        unimplemented!("mock_set_primary_screen")
+    }
+
     fn mock_cursor_position(&self) -> Result<Point> {
         unimplemented!("mock_cursor_position")
     }
 
+    fn mock_query_pointer(&self) -> Result<PointerQuery> {
+        unimplemented!("mock_query_pointer")
+    }
+
     fn mock_grab(&self, key_codes: &[KeyCode], mouse_states: &[MouseState]) -> Result<()> {
         unimplemented!("mock_grab")
     }
@@ -40,6 +56,10 @@ pub trait MockXConn {
         unimplemented!("mock_next_event")
     }
 
+    fn mock_poll_next_event(&self) -> Result<Option<XEvent>> {
+        unimplemented!("mock_poll_next_event")
+    }
+
     fn mock_flush(&self) {}
 
     fn mock_intern_atom(&self, atom: &str) -> Result<Xid> {
@@ -117,6 +137,34 @@ pub trait MockXConn {
     fn mock_warp_pointer(&self, id: Xid, x: i16, y: i16) -> Result<()> {
         unimplemented!("mock_warp_pointer")
     }
+
+    fn mock_keyboard_layouts(&self) -> Result<Vec<String>> {
+        unimplemented!("mock_keyboard_layouts")
+    }
+
+    fn mock_keyboard_layout(&self) -> Result<u8> {
+        unimplemented!("mock_keyboard_layout")
+    }
+
+    fn mock_set_keyboard_layout(&self, index: u8) -> Result<()> {
+        unimplemented!("mock_set_keyboard_layout")
+    }
+
+    fn mock_pointer_is_grabbed(&self) -> Result<bool> {
+        unimplemented!("mock_pointer_is_grabbed")
+    }
+
+    fn mock_grab_client_buttons(&self, client: Xid) -> Result<()> {
+        unimplemented!("mock_grab_client_buttons")
+    }
+
+    fn mock_ungrab_client_buttons(&self, client: Xid) -> Result<()> {
+        unimplemented!("mock_ungrab_client_buttons")
+    }
+
+    fn mock_allow_pointer_events(&self, replay: bool) -> Result<()> {
+        unimplemented!("mock_allow_pointer_events")
+    }
 }
 
 impl<T> XConn for T
@@ -131,10 +179,26 @@ where
         self.mock_screen_details()
     }
 
+    fn screen_names(&self) -> Result<Vec<String>> {
+        self.mock_screen_names()
+    }
+
+    fn primary_screen(&self) -> Result<Option<usize>> {
+        self.mock_primary_screen()
+    }
+
+    fn set_primary_screen(&self, index: usize) -> Result<()> {
+        self.mock_set_primary_screen(index)
+    }
+
     fn cursor_position(&self) -> Result<Point> {
         self.mock_cursor_position()
     }
 
+    fn query_pointer(&self) -> Result<PointerQuery> {
+        self.mock_query_pointer()
+    }
+
     fn grab(&self, key_codes: &[KeyCode], mouse_states: &[MouseState]) -> Result<()> {
         self.mock_grab(key_codes, mouse_states)
     }
@@ -143,6 +207,10 @@ where
         self.mock_next_event()
     }
 
+    fn poll_next_event(&self) -> Result<Option<XEvent>> {
+        self.mock_poll_next_event()
+    }
+
     fn flush(&self) {
         self.mock_flush()
     }
@@ -222,6 +290,34 @@ where
     fn warp_pointer(&self, id: Xid, x: i16, y: i16) -> Result<()> {
         self.mock_warp_pointer(id, x, y)
     }
+
+    fn keyboard_layouts(&self) -> Result<Vec<String>> {
+        self.mock_keyboard_layouts()
+    }
+
+    fn keyboard_layout(&self) -> Result<u8> {
+        self.mock_keyboard_layout()
+    }
+
+    fn set_keyboard_layout(&self, index: u8) -> Result<()> {
+        self.mock_set_keyboard_layout(index)
+    }
+
+    fn pointer_is_grabbed(&self) -> Result<bool> {
+        self.mock_pointer_is_grabbed()
+    }
+
+    fn grab_client_buttons(&self, client: Xid) -> Result<()> {
+        self.mock_grab_client_buttons(client)
+    }
+
+    fn ungrab_client_buttons(&self, client: Xid) -> Result<()> {
+        self.mock_ungrab_client_buttons(client)
+    }
+
+    fn allow_pointer_events(&self, replay: bool) -> Result<()> {
+        self.mock_allow_pointer_events(replay)
+    }
 }
 
 /// A stub XConn implementation that doesn't implement _any_ methods.
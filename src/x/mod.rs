@@ -3,21 +3,31 @@ use crate::{
     builtin::layout::messages::Hide,
     core::{
         bindings::{KeyCode, MouseState},
-        ClientSet, Config, State,
+        catch_panic,
+        metrics::HookKind,
+        AppliedPosition, ClientSet, Config, Maximized, PanicPolicy, State,
     },
-    pure::geometry::{Point, Rect},
-    x::{atom::AUTO_FLOAT_WINDOW_TYPES, event::ClientMessage, property::WmState},
-    Color, Result, Xid,
+    pure::geometry::{Point, Rect, RelativeTo},
+    x::{
+        event::ClientMessage,
+        property::{WmNormalHints, WmState},
+    },
+    Color, Error, Result, Xid,
 };
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    os::fd::RawFd,
+    time::Instant,
+};
 use tracing::{debug, error, trace};
 
 pub mod atom;
 pub mod event;
 pub mod property;
 pub mod query;
+pub mod test_conn;
 
 #[cfg(test)]
 pub mod mock;
@@ -29,6 +39,7 @@ pub use atom::Atom;
 pub use event::XEvent;
 pub use property::{Prop, WindowAttributes};
 pub use query::Query;
+pub use test_conn::TestConn;
 
 /// A window type to be specified when creating a new window in the X server
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -41,6 +52,10 @@ pub enum WinType {
     /// A regular window. The [Atom] passed should be a
     /// valid _NET_WM_WINDOW_TYPE (this is not enforced)
     InputOutput(Atom),
+    /// A regular window using a 32-bit ARGB visual so that its contents can be rendered with
+    /// transparency when a compositor is running. As with `InputOutput`, the [Atom] passed
+    /// should be a valid _NET_WM_WINDOW_TYPE.
+    InputOutputArgb(Atom),
 }
 
 /// On screen configuration options for X clients (not all are curently implemented)
@@ -73,6 +88,22 @@ pub enum ClientAttr {
     ClientUnmapMask,
     /// Set the pre-defined root event mask
     RootEventMask,
+    /// Set the pre-defined event mask for windows belonging to UI elements such as a status bar
+    /// (button presses, the pointer entering the window and exposure)
+    UiEventMask,
+}
+
+/// The current state of the mouse pointer as reported by [XConn::query_pointer].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PointerQuery {
+    /// The absolute (x, y) coordinate of the pointer
+    pub pos: Point,
+    /// The index of the screen containing the pointer, matching the ordering of the screens
+    /// returned by [XConn::screen_details]
+    pub screen: Option<usize>,
+    /// The top level window currently under the pointer, if there is one
+    pub child: Option<Xid>,
 }
 
 /// A handle on a running X11 connection that we can use for issuing X requests.
@@ -87,14 +118,72 @@ pub trait XConn {
     fn root(&self) -> Xid;
     /// Ask the X server for the dimensions of each currently available screen.
     fn screen_details(&self) -> Result<Vec<Rect>>;
+    /// Ask the X server for the name of the output (e.g. "eDP-1") driving each screen, in the
+    /// order returned by [XConn::screen_details].
+    ///
+    /// By default no output names are known.
+    fn screen_names(&self) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+    /// Ask the X server for the index (in the order returned by [XConn::screen_details]) of the
+    /// primary screen, if one has been set.
+    ///
+    /// By default no primary screen is reported.
+    fn primary_screen(&self) -> Result<Option<usize>> {
+        Ok(None)
+    }
+    /// Mark the screen with the given index (in the order returned by [XConn::screen_details])
+    /// as the primary screen.
+    ///
+    /// By default this returns [Error::Unsupported].
+    fn set_primary_screen(&self, _index: usize) -> Result<()> {
+        Err(Error::Unsupported {
+            method: "set_primary_screen",
+        })
+    }
     /// Ask the X server for the current (x, y) coordinate of the mouse cursor.
     fn cursor_position(&self) -> Result<Point>;
+    /// Ask the X server for the current position of the mouse cursor along with the screen and
+    /// top level window that it is over.
+    ///
+    /// By default this is determined using [XConn::cursor_position] and [XConn::screen_details]
+    /// without reporting the window under the cursor.
+    fn query_pointer(&self) -> Result<PointerQuery> {
+        let pos = self.cursor_position()?;
+        let screen = self
+            .screen_details()?
+            .iter()
+            .position(|r| r.contains_point(pos));
+
+        Ok(PointerQuery {
+            pos,
+            screen,
+            child: None,
+        })
+    }
 
     /// Grab the specified key and mouse states, intercepting them for processing within
     /// the window manager itself.
     fn grab(&self, key_codes: &[KeyCode], mouse_states: &[MouseState]) -> Result<()>;
     /// Block and wait for the next event from the X server so it can be processed.
     fn next_event(&self) -> Result<XEvent>;
+    /// Return the next event from the X server if one has already been received, without
+    /// blocking.
+    ///
+    /// This is only required for implementations that provide a [XConn::connection_fd] and by
+    /// default returns [Error::Unsupported].
+    fn poll_next_event(&self) -> Result<Option<XEvent>> {
+        Err(Error::Unsupported {
+            method: "poll_next_event",
+        })
+    }
+    /// The file descriptor of the underlying connection to the X server (if there is one) so
+    /// that it can be polled for new events alongside other sources.
+    ///
+    /// See [EventLoop][crate::core::event_loop::EventLoop] for details.
+    fn connection_fd(&self) -> Option<RawFd> {
+        None
+    }
     /// Flush any pending events to the X server.
     fn flush(&self);
 
@@ -129,6 +218,10 @@ pub trait XConn {
     /// Set the current [WmState] for a given client window.
     fn set_wm_state(&self, client: Xid, wm_state: WmState) -> Result<()>;
     /// Set a specific property on a given client window.
+    ///
+    /// Each [Prop] variant should be written using the property type that it is parsed from by
+    /// [XConn::get_prop] so that the value can be read back unchanged. [Prop::Bytes] has no
+    /// associated property type and is written as an array of CARDINAL values.
     fn set_prop(&self, client: Xid, name: &str, val: Prop) -> Result<()>;
     /// Delete a property for a given client window.
     fn delete_prop(&self, client: Xid, prop_name: &str) -> Result<()>;
@@ -143,6 +236,56 @@ pub trait XConn {
     /// This method should not be called directly: use `warp_pointer_to_window` or `warp_pointer_to_screen`
     /// instead.
     fn warp_pointer(&self, id: Xid, x: i16, y: i16) -> Result<()>;
+
+    /// The names of the keyboard layouts (XKB groups) that are currently configured.
+    ///
+    /// By default no keyboard layouts are reported.
+    fn keyboard_layouts(&self) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+    /// The index of the currently active keyboard layout (XKB group).
+    ///
+    /// By default the first layout is reported as being active.
+    fn keyboard_layout(&self) -> Result<u8> {
+        Ok(0)
+    }
+    /// Set the currently active keyboard layout (XKB group) by index.
+    ///
+    /// By default this returns [Error::Unsupported].
+    fn set_keyboard_layout(&self, _index: u8) -> Result<()> {
+        Err(Error::Unsupported {
+            method: "set_keyboard_layout",
+        })
+    }
+
+    /// Check whether or not another X client currently has an active grab on the pointer.
+    ///
+    /// By default the pointer is never reported as being grabbed.
+    fn pointer_is_grabbed(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Grab mouse button presses on the given client window so that clicking on it can be used
+    /// to focus it. The pointer is frozen after each grabbed press until [XConn::allow_pointer_events]
+    /// is called.
+    ///
+    /// By default this is a no-op, meaning that [Config::click_to_focus] has no effect.
+    fn grab_client_buttons(&self, _client: Xid) -> Result<()> {
+        Ok(())
+    }
+    /// Release any mouse button grabs held on the given client window.
+    ///
+    /// By default this is a no-op.
+    fn ungrab_client_buttons(&self, _client: Xid) -> Result<()> {
+        Ok(())
+    }
+    /// Release the pointer after a grabbed button press, optionally replaying the press so that
+    /// it is also received by the client that was clicked.
+    ///
+    /// By default this is a no-op.
+    fn allow_pointer_events(&self, _replay: bool) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// Extended functionality for [XConn] impls in order to run the window manager.
@@ -220,7 +363,12 @@ pub trait XConnExt: XConn + Sized {
     where
         F: FnMut(&mut ClientSet),
     {
+        let start = Instant::now();
         f(&mut state.client_set); // mutating the existing state
+        follow_transient_parents(state);
+        update_fullscreen_state(self, state)?;
+        clear_stale_positioning_state(state);
+        clear_stale_zoom(state);
 
         let ss = state.position_and_snapshot(self);
         state.diff.update(ss);
@@ -228,21 +376,38 @@ pub trait XConnExt: XConn + Sized {
         notify_killed(self, state)?;
         set_window_props(self, state)?;
         notify_hidden_workspaces(state);
+        update_smart_borders(self, state)?;
         self.position_clients(state)?;
         set_window_visibility(self, state)?;
         set_focus(self, state)?;
+        update_click_to_focus_grabs(self, state)?;
         handle_pointer_change(self, state)?;
 
         // TODO: clear enterWindow events from the event queue if this was because of mouse focus (?)
 
+        let policy = state.config.panic_policy;
         let mut hook = state.config.refresh_hook.take();
         if let Some(ref mut h) = hook {
             trace!("running user refresh hook");
-            if let Err(e) = h.call(state, self) {
-                error!(%e, "error returned from user refresh hook");
+            let hook_start = Instant::now();
+            let res = catch_panic(policy, "refresh hook", || h.call(state, self));
+            state.metrics.record_hook(HookKind::Refresh, hook_start);
+            match res {
+                Err(e @ Error::Panicked { .. }) if policy == PanicPolicy::Disable => {
+                    error!(%e, "disabling user refresh hook");
+                    state.run_error_hook(&e, self);
+                    hook = None;
+                }
+                Err(e) => {
+                    error!(%e, "error returned from user refresh hook");
+                    state.run_error_hook(&e, self);
+                }
+                Ok(()) => (),
             }
         }
         state.config.refresh_hook = hook;
+        self.flush();
+        state.metrics.record_refresh(start);
 
         Ok(())
     }
@@ -254,7 +419,16 @@ pub trait XConnExt: XConn + Sized {
     }
 
     /// Check whether or not the given client should be assigned floating status or not.
-    fn client_should_float(&self, client: Xid, floating_classes: &[String]) -> Result<bool> {
+    ///
+    /// Clients are floated if they have one of the configured floating classes or if any of the
+    /// configured [float rules][Config::float_rules] match.
+    fn client_should_float(&self, client: Xid, config: &Config<Self>) -> Result<bool> {
+        let Config {
+            floating_classes,
+            float_rules,
+            ..
+        } = config;
+
         trace!(%client, "fetching WmClass prop");
         if let Some(Prop::UTF8String(strs)) = self.get_prop(client, Atom::WmClass.as_ref())? {
             if strs.iter().any(|c| floating_classes.contains(c)) {
@@ -263,18 +437,14 @@ pub trait XConnExt: XConn + Sized {
             }
         }
 
-        trace!(%client, "fetching NetWmWindowType prop");
-        let window_types = self.get_prop(client, Atom::NetWmWindowType.as_ref())?;
-        debug!(?window_types, "client window types");
-
-        let float_types: Vec<&str> = AUTO_FLOAT_WINDOW_TYPES.iter().map(|a| a.as_ref()).collect();
-
-        let should_float = match window_types {
-            Some(Prop::Atom(atoms)) => atoms.iter().any(|a| float_types.contains(&a.as_ref())),
-            _ => false,
-        };
+        for rule in float_rules.iter() {
+            if rule.run(client, self)? {
+                debug!(%client, "window matched a float rule: setting to floating state");
+                return Ok(true);
+            }
+        }
 
-        Ok(should_float)
+        Ok(false)
     }
 
     /// Update the border color of the given client window.
@@ -286,6 +456,39 @@ pub trait XConnExt: XConn + Sized {
         self.set_client_attributes(id, &[ClientAttr::BorderColor(color.argb_u32())])
     }
 
+    /// The border color that should currently be used for the given client based on whether or
+    /// not it is focused or requesting attention.
+    fn border_color_for(&self, client: Xid, state: &State<Self>) -> Result<Color> {
+        let config = &state.config;
+        if state.client_set.current_client() == Some(&client) {
+            Ok(config.focused_border)
+        } else if self.client_is_urgent(client)? {
+            Ok(config.urgent_border)
+        } else {
+            Ok(config.normal_border)
+        }
+    }
+
+    /// Re-apply the border width and colors from the current [Config] to all managed clients.
+    ///
+    /// Clients that are fullscreen or have had their border removed by smart borders are left
+    /// without a border. A refresh is needed afterwards for clients to be repositioned to account
+    /// for any change in border width.
+    fn update_borders(&self, state: &State<Self>) -> Result<()> {
+        for &c in state.client_set.clients() {
+            let border_px = if state.is_fullscreen(&c) || state.borderless.contains(&c) {
+                0
+            } else {
+                state.config.border_width
+            };
+
+            self.set_client_config(c, &[ClientConfig::BorderPx(border_px)])?;
+            self.set_client_border_color(c, self.border_color_for(c, state)?)?;
+        }
+
+        Ok(())
+    }
+
     /// Set the initial window properties for a newly managed window.
     fn set_initial_properties(&self, client: Xid, config: &Config<Self>) -> Result<()> {
         let Config {
@@ -307,9 +510,7 @@ pub trait XConnExt: XConn + Sized {
 
     /// Update the geometry of a given client based on the given [Rect].
     fn position_client(&self, client: Xid, mut r: Rect) -> Result<()> {
-        let p = Atom::WmNormalHints.as_ref();
-        if let Ok(Some(Prop::WmNormalHints(hints))) = self.get_prop(client, p) {
-            trace!(%client, ?hints, "client has WmNormalHints: applying size hints");
+        if let Some(hints) = self.size_hints_for(client) {
             r = hints.apply_to(r);
         }
 
@@ -317,24 +518,75 @@ pub trait XConnExt: XConn + Sized {
         self.set_client_config(client, &[ClientConfig::Position(r)])
     }
 
+    /// The WM_NORMAL_HINTS set for the given client, if any.
+    fn size_hints_for(&self, client: Xid) -> Option<WmNormalHints> {
+        match self.get_prop(client, Atom::WmNormalHints.as_ref()) {
+            Ok(Some(Prop::WmNormalHints(hints))) => {
+                trace!(%client, ?hints, "client has WmNormalHints");
+                Some(hints)
+            }
+            _ => None,
+        }
+    }
+
     /// Restack and set the geometry for an ordered list of client windows and their
     /// associated positions. The provided positions are shrunk by the current border
     /// size in order to position the windows correctly within the frame given by the
     /// border.
     ///
+    /// Clients that have had their positioning suspended are left untouched and clients that
+    /// have had their border removed by [Config::smart_borders] are not shrunk.
+    ///
+    /// Only clients whose geometry differs from the last time they were positioned are
+    /// reconfigured, and the clients are only restacked if their stacking order has changed.
+    /// Any size hints required for the new positions are fetched before the new geometry is
+    /// sent to the X server so that all of the configure requests are sent together. Clients
+    /// that are moved or resized by other means are repositioned on the first refresh after
+    /// the X server has notified the window manager of the change.
+    ///
     /// See `restack` for details of stacking order is determined.
     fn position_clients(&self, state: &State<Self>) -> Result<()> {
         let border = state.config.border_width;
-        let positions = &state.diff.after.positions;
+        let positions: Vec<(Xid, Rect)> = state
+            .diff
+            .after
+            .positions
+            .iter()
+            .filter(|(id, _)| !state.is_positioning_suspended(id))
+            .copied()
+            .collect();
         let screen_positions: Vec<_> = state.client_set.screens().map(|s| s.r).collect();
 
-        self.restack(positions.iter().map(|(id, _)| id))?;
+        let mut applied_positions = state.applied_positions.borrow_mut();
+        applied_positions.retain(|id, _| state.client_set.contains(id));
 
-        for &(c, mut r) in positions.iter() {
-            if !screen_positions.contains(&r) {
+        let stack: Vec<Xid> = positions.iter().map(|&(id, _)| id).collect();
+        let mut applied_stack = state.applied_stack.borrow_mut();
+        if stack != *applied_stack {
+            self.restack(stack.iter())?;
+            *applied_stack = stack;
+        }
+
+        let mut changed = Vec::with_capacity(positions.len());
+        for (c, mut r) in positions {
+            // Clients filling the screen are left without a border unless they are maximized
+            let fills_screen = screen_positions.contains(&r) && !state.maximized.contains_key(&c);
+            if !fills_screen && !state.borderless.contains(&c) {
                 r = r.shrink_in(border);
             }
-            self.position_client(c, r)?;
+            if applied_positions.get(&c).map(|p| p.r) != Some(r) {
+                changed.push((c, r, self.size_hints_for(c)));
+            }
+        }
+
+        for (c, r, hints) in changed {
+            let applied = hints.map(|h| h.apply_to(r)).unwrap_or(r);
+            trace!(%c, r=?applied, "positioning client");
+            self.set_client_config(c, &[ClientConfig::Position(applied)])?;
+            applied_positions
+                .entry(c)
+                .and_modify(|p| p.update(r, applied))
+                .or_insert_with(|| AppliedPosition::new(r, applied));
         }
 
         Ok(())
@@ -420,6 +672,44 @@ pub trait XConnExt: XConn + Sized {
         }
     }
 
+    /// Check whether or not a given client window is currently requesting attention, either via
+    /// the ICCCM urgency hint or the EWMH `_NET_WM_STATE_DEMANDS_ATTENTION` state.
+    fn client_is_urgent(&self, id: Xid) -> Result<bool> {
+        if let Some(Prop::WmHints(hints)) = self.get_prop(id, Atom::WmHints.as_ref())? {
+            if hints.is_urgent() {
+                return Ok(true);
+            }
+        }
+
+        let demands_attention = Atom::NetWmStateDemandsAttention.as_ref();
+        match self.get_prop(id, Atom::NetWmState.as_ref())? {
+            Some(Prop::Atom(atoms)) => Ok(atoms.iter().any(|a| a == demands_attention)),
+            _ => Ok(false),
+        }
+    }
+
+    /// Set a property on the given window to an array of CARDINAL values.
+    fn set_cardinal_prop(&self, id: Xid, name: &str, vals: &[u32]) -> Result<()> {
+        self.set_prop(id, name, Prop::Cardinal(vals.to_vec()))
+    }
+
+    /// Set a property on the given window to a list of UTF8_STRING values.
+    fn set_string_prop<S: AsRef<str>>(&self, id: Xid, name: &str, strs: &[S]) -> Result<()> {
+        let strs = strs.iter().map(|s| s.as_ref().to_owned()).collect();
+        self.set_prop(id, name, Prop::UTF8String(strs))
+    }
+
+    /// Set a property on the given window to an array of atoms, interning them as needed.
+    fn set_atom_prop<S: AsRef<str>>(&self, id: Xid, name: &str, atoms: &[S]) -> Result<()> {
+        let atoms = atoms.iter().map(|a| a.as_ref().to_owned()).collect();
+        self.set_prop(id, name, Prop::Atom(atoms))
+    }
+
+    /// Set a property on the given window to a list of window IDs.
+    fn set_window_prop(&self, id: Xid, name: &str, ids: &[Xid]) -> Result<()> {
+        self.set_prop(id, name, Prop::Window(ids.to_vec()))
+    }
+
     /// Request a window's PID via the _NET_WM_PID property.
     ///
     /// **NOTE**: Not all programs set this property.
@@ -473,31 +763,67 @@ pub(crate) fn manage_without_refresh<X: XConn>(
         _ => (tag.map(|t| t.to_string()), None),
     };
 
-    let should_float =
-        transient_for.is_some() || x.client_should_float(id, &state.config.floating_classes)?;
+    let should_float = x.client_should_float(id, &state.config)?;
+    let previous_focus = state.client_set.current_client().copied();
 
+    let pos = state.config.insert_point;
     match owned_tag {
-        Some(tag) => state.client_set.insert_as_focus_for(tag.as_ref(), id),
-        None => state.client_set.insert(id),
+        Some(tag) => state
+            .client_set
+            .insert_as_focus_at_for(tag.as_ref(), pos, id),
+        None => {
+            let tag = state.client_set.current_tag().to_owned();
+            state.client_set.insert_as_focus_at_for(&tag, pos, id);
+        }
+    }
+
+    if let Some(parent) = transient_for {
+        if let Some(tag) = state.client_set.tag_for_client(&parent) {
+            state.transients.insert(id, (parent, tag.to_owned()));
+        }
     }
 
     if should_float {
         debug!(%id, "client should float");
         let r = floating_client_position(id, transient_for, state, x)?;
-        if state.client_set.float(id, r).is_err() {
-            error!(%id, "attempted to float client which was not in state");
+        match state.client_set.float(id, r) {
+            Ok(()) => (),
+            // Clients on hidden workspaces (such as transients whose parent is not currently
+            // visible) are positioned relative to the focused screen until they are shown
+            Err(Error::ClientIsNotVisible(_)) => {
+                let r = r.relative_to(&state.client_set.screens.focus.r);
+                state.client_set.floating.insert(id, r);
+            }
+            Err(_) => error!(%id, "attempted to float client which was not in state"),
         }
     }
 
+    let policy = state.config.panic_policy;
     let mut hook = state.config.manage_hook.take();
     if let Some(ref mut h) = hook {
         trace!("running user manage hook");
-        if let Err(e) = h.call(id, state, x) {
-            error!(%e, "error returned from user manage hook");
+        let start = Instant::now();
+        let res = state.catch_panic("manage hook", x, true, |state| h.call(id, state, x));
+        state.metrics.record_hook(HookKind::Manage, start);
+        match res {
+            Err(e @ Error::Panicked { .. }) if policy == PanicPolicy::Disable => {
+                error!(%e, "disabling user manage hook");
+                state.run_error_hook(&e, x);
+                hook = None;
+            }
+            Err(e) => {
+                error!(%e, "error returned from user manage hook");
+                state.run_error_hook(&e, x);
+            }
+            Ok(()) => (),
         }
     }
     state.config.manage_hook = hook;
 
+    if state.running && state.config.prevent_focus_stealing {
+        prevent_focus_stealing(id, previous_focus, transient_for, state, x)?;
+    }
+
     debug!(
         floating=?state.client_set.floating, "floating clients"
     );
@@ -505,7 +831,87 @@ pub(crate) fn manage_without_refresh<X: XConn>(
     Ok(())
 }
 
+// If a newly managed client has taken focus from the previously focused client without being
+// permitted to do so then focus is returned to the previous client and the new client is marked
+// as urgent instead.
+fn prevent_focus_stealing<X: XConn>(
+    id: Xid,
+    previous_focus: Option<Xid>,
+    transient_for: Option<Xid>,
+    state: &mut State<X>,
+    x: &X,
+) -> Result<()> {
+    let prev = match previous_focus {
+        Some(prev) if prev != id && state.client_set.contains(&prev) => prev,
+        _ => return Ok(()),
+    };
+
+    if state.client_set.current_client() != Some(&id) || transient_for == Some(prev) {
+        return Ok(());
+    }
+
+    if client_may_take_focus(id, prev, &state.config, x)? {
+        return Ok(());
+    }
+
+    debug!(%id, %prev, "preventing new client from stealing focus");
+    state.client_set.focus_client(&prev);
+    set_demands_attention_prop(x, id)
+}
+
+fn client_may_take_focus<X: XConn>(id: Xid, prev: Xid, config: &Config<X>, x: &X) -> Result<bool> {
+    for rule in config.allow_focus_rules.iter() {
+        if rule.run(id, x)? {
+            return Ok(true);
+        }
+    }
+
+    for rule in config.deny_focus_rules.iter() {
+        if rule.run(id, x)? {
+            return Ok(false);
+        }
+    }
+
+    match (user_time(id, x), user_time(prev, x)) {
+        // A user time of 0 means that the client should not be focused when it is mapped
+        (Some(0), _) => Ok(false),
+        // X server timestamps wrap around so compare them as a signed offset
+        (Some(t), Some(t_prev)) => Ok(t.wrapping_sub(t_prev) as i32 >= 0),
+        _ => Ok(true),
+    }
+}
+
+// The _NET_WM_USER_TIME for a client, read from its _NET_WM_USER_TIME_WINDOW if it has one.
+fn user_time<X: XConn>(id: Xid, x: &X) -> Option<u32> {
+    let time_window = match x.get_prop(id, Atom::NetWmUserTimeWindow.as_ref()) {
+        Ok(Some(Prop::Window(ids))) if !ids.is_empty() => ids[0],
+        _ => id,
+    };
+
+    match x.get_prop(time_window, Atom::NetWmUserTime.as_ref()) {
+        Ok(Some(Prop::Cardinal(vals))) => vals.first().copied(),
+        _ => None,
+    }
+}
+
+// Add the EWMH demands attention state to the _NET_WM_STATE property of a client.
+fn set_demands_attention_prop<X: XConn>(x: &X, id: Xid) -> Result<()> {
+    let net_wm_state = Atom::NetWmState.as_ref();
+    let demands_attention = Atom::NetWmStateDemandsAttention.as_ref();
+
+    let mut wstate = match x.get_prop(id, net_wm_state) {
+        Ok(Some(Prop::Atom(atoms))) => atoms,
+        _ => vec![],
+    };
+    if !wstate.iter().any(|a| a == demands_attention) {
+        wstate.push(demands_attention.to_owned());
+    }
+
+    x.set_prop(id, net_wm_state, Prop::Atom(wstate))
+}
+
 /// When positioning a floating client we try to position them in priority order of:
+///   - centered over their parent (if transient and the parent is visible)
 ///   - the client's requested position if it is not at the origin
 ///   - centered in their parent's screen (if transient)
 ///   - centered in the focused screen
@@ -519,18 +925,35 @@ fn floating_client_position<X: XConn>(
     let r_initial = x.client_geometry(id)?;
     debug!(?r_initial, "initial geometry");
 
+    let parent_screen = transient_for.and_then(|parent| {
+        state
+            .client_set
+            .screen_for_client(&parent)
+            .map(|s| (parent, s.r))
+    });
+
+    if let Some((parent, _)) = parent_screen {
+        trace!(%parent, "fetching parent geometry");
+        let r_parent = x.client_geometry(parent)?;
+        debug!(?r_parent, "parent geometry");
+
+        if let Some(r) = r_initial.centered_in(&r_parent) {
+            debug!(?r, "centering over parent");
+            return Ok(r);
+        }
+    }
+
     if (r_initial.x, r_initial.y) != (0, 0) {
         debug!(?r_initial, "accepting client's requested position");
         return Ok(r_initial);
     }
 
-    let r_parent = transient_for
-        .and_then(|parent| state.client_set.screen_for_client(&parent))
-        .unwrap_or(&state.client_set.screens.focus)
-        .r;
-    debug!(?r_parent, "parent geometry");
+    let r_screen = parent_screen
+        .map(|(_, r)| r)
+        .unwrap_or(state.client_set.screens.focus.r);
+    debug!(?r_screen, "parent screen geometry");
 
-    let r_final = r_initial.centered_in(&r_parent).unwrap_or_else(|| {
+    let r_final = r_initial.centered_in(&r_screen).unwrap_or_else(|| {
         r_initial
             .centered_in(&state.client_set.screens.focus.r)
             .unwrap_or(r_initial)
@@ -540,6 +963,140 @@ fn floating_client_position<X: XConn>(
     Ok(r_final)
 }
 
+// Move transient clients along with their parent when the parent is moved to a different
+// workspace, dropping any whose parent or transient client is no longer being managed.
+fn follow_transient_parents<X: XConn>(state: &mut State<X>) {
+    let cs = &mut state.client_set;
+    state
+        .transients
+        .retain(|id, (parent, _)| cs.contains(id) && cs.contains(parent));
+
+    for (id, (parent, tag)) in state.transients.iter_mut() {
+        let parent_tag = match cs.tag_for_client(parent) {
+            Some(t) if t != tag => t.to_owned(),
+            _ => continue,
+        };
+
+        debug!(%id, %parent, %parent_tag, "parent has moved workspace: moving transient client");
+        cs.move_client_to_tag(id, &parent_tag);
+        *tag = parent_tag;
+    }
+}
+
+/// Set or clear the EWMH fullscreen property for a client and update its border to match.
+pub(crate) fn set_fullscreen_props<X: XConn>(
+    x: &X,
+    id: Xid,
+    fullscreen: bool,
+    border_width: u32,
+) -> Result<()> {
+    set_fullscreen_prop(x, id, fullscreen)?;
+    let border_px = if fullscreen { 0 } else { border_width };
+
+    x.set_client_config(id, &[ClientConfig::BorderPx(border_px)])
+}
+
+/// Set or clear the EWMH fullscreen property for a client without modifying its border.
+pub(crate) fn set_fullscreen_prop<X: XConn>(x: &X, id: Xid, fullscreen: bool) -> Result<()> {
+    let net_wm_state = Atom::NetWmState.as_ref();
+    let full_screen = *x.intern_atom(Atom::NetWmStateFullscreen.as_ref())?;
+
+    let mut wstate = match x.get_prop(id, net_wm_state) {
+        Ok(Some(Prop::Cardinal(vals))) => vals,
+        _ => vec![],
+    };
+    wstate.retain(|&val| val != full_screen);
+    if fullscreen {
+        wstate.push(full_screen);
+    }
+
+    x.set_prop(id, net_wm_state, Prop::Cardinal(wstate))
+}
+
+/// Set the EWMH maximized properties for a client to match the given [Maximized] state.
+pub(crate) fn set_maximized_prop<X: XConn>(x: &X, id: Xid, m: Maximized) -> Result<()> {
+    let net_wm_state = Atom::NetWmState.as_ref();
+    let horz = *x.intern_atom(Atom::NetWmStateMaximizedHorz.as_ref())?;
+    let vert = *x.intern_atom(Atom::NetWmStateMaximizedVert.as_ref())?;
+
+    let mut wstate = match x.get_prop(id, net_wm_state) {
+        Ok(Some(Prop::Cardinal(vals))) => vals,
+        _ => vec![],
+    };
+    wstate.retain(|&val| val != horz && val != vert);
+    if m.horizontal {
+        wstate.push(horz);
+    }
+    if m.vertical {
+        wstate.push(vert);
+    }
+
+    x.set_prop(id, net_wm_state, Prop::Cardinal(wstate))
+}
+
+// Drop fullscreen state for clients that are no longer being managed and, if configured,
+// take the previously focused client out of fullscreen when focus has moved to another
+// client on the same workspace.
+fn update_fullscreen_state<X: XConn>(x: &X, state: &mut State<X>) -> Result<()> {
+    state
+        .fullscreen
+        .retain(|id, _| state.client_set.contains(id));
+    state
+        .fake_fullscreen
+        .retain(|id| state.client_set.contains(id));
+
+    // Maximized clients that have been sunk by something other than unmaximizing them are no
+    // longer maximized
+    let cs = &state.client_set;
+    let sunk: Vec<Xid> = state
+        .maximized
+        .keys()
+        .filter(|id| !cs.is_floating(id))
+        .copied()
+        .collect();
+    state.maximized.retain(|id, _| cs.is_floating(id));
+    for id in sunk.into_iter().filter(|id| cs.contains(id)) {
+        set_maximized_prop(x, id, Maximized::default())?;
+    }
+
+    if !state.config.exit_fullscreen_on_focus_change {
+        return Ok(());
+    }
+
+    let previous = match state.diff.after.focused_client {
+        Some(id) if state.is_fullscreen(&id) => id,
+        _ => return Ok(()),
+    };
+
+    let focus_moved_on_workspace = matches!(state.client_set.current_client(), Some(&id) if id != previous)
+        && state.client_set.current_workspace().contains(&previous);
+
+    if focus_moved_on_workspace {
+        debug!(%previous, "focus changed: removing fullscreen state");
+        state.exit_fullscreen(previous);
+        set_fullscreen_props(x, previous, false, state.config.border_width)?;
+    }
+
+    Ok(())
+}
+
+// A zoomed client is restored to its normal position as soon as it loses focus
+fn clear_stale_zoom<X: XConn>(state: &mut State<X>) {
+    if let Some(id) = state.zoomed {
+        if state.client_set.current_client() != Some(&id) {
+            trace!(%id, "zoomed client lost focus: clearing zoom");
+            state.zoomed = None;
+        }
+    }
+}
+
+// Positioning is only tracked as being suspended for clients that are still being managed
+fn clear_stale_positioning_state<X: XConn>(state: &mut State<X>) {
+    state
+        .positioning_suspended
+        .retain(|id| state.client_set.contains(id));
+}
+
 fn notify_killed<X: XConn>(x: &X, state: &mut State<X>) -> Result<()> {
     for &c in state.diff.killed_clients() {
         x.kill(c)?;
@@ -549,8 +1106,12 @@ fn notify_killed<X: XConn>(x: &X, state: &mut State<X>) -> Result<()> {
 }
 
 fn set_window_props<X: XConn>(x: &X, state: &mut State<X>) -> Result<()> {
+    let focused = state.client_set.current_client();
     for &c in state.diff.new_clients() {
         x.set_initial_properties(c, &state.config)?;
+        if Some(&c) != focused && x.client_is_urgent(c).unwrap_or(false) {
+            x.set_client_border_color(c, state.config.urgent_border)?;
+        }
     }
 
     if let Some(focused) = state.diff.before.focused_client {
@@ -565,6 +1126,42 @@ fn set_window_props<X: XConn>(x: &X, state: &mut State<X>) -> Result<()> {
     Ok(())
 }
 
+// With smart borders enabled, the border is removed from clients that are the only tiled client
+// on their screen. Fullscreen clients have their border managed separately when they enter and
+// exit fullscreen.
+fn update_smart_borders<X: XConn>(x: &X, state: &mut State<X>) -> Result<()> {
+    let borderless: HashSet<Xid> = if state.config.smart_borders {
+        let cs = &state.client_set;
+        cs.screens()
+            .filter_map(|s| {
+                let mut tiled = s.workspace.clients().filter(|&c| !cs.is_floating(c));
+                match (tiled.next(), tiled.next()) {
+                    (Some(&c), None) if !state.is_fullscreen(&c) => Some(c),
+                    _ => None,
+                }
+            })
+            .collect()
+    } else {
+        HashSet::new()
+    };
+
+    for &c in borderless.difference(&state.borderless) {
+        trace!(%c, "removing border from only tiled client on screen");
+        x.set_client_config(c, &[ClientConfig::BorderPx(0)])?;
+    }
+
+    for &c in state.borderless.difference(&borderless) {
+        if state.client_set.contains(&c) && !state.is_fullscreen(&c) {
+            trace!(%c, "restoring client border");
+            x.set_client_config(c, &[ClientConfig::BorderPx(state.config.border_width)])?;
+        }
+    }
+
+    state.borderless = borderless;
+
+    Ok(())
+}
+
 fn notify_hidden_workspaces<X: XConn>(state: &mut State<X>) {
     let previous_visible_tags = state.diff.previous_visible_tags();
 
@@ -636,12 +1233,47 @@ fn set_focus<X: XConn>(x: &X, state: &mut State<X>) -> Result<()> {
     }
 }
 
+// Button presses are grabbed on unfocused clients so that clicking on them can focus them. The
+// grab is released once a client gains focus so that clicks are passed directly to it.
+fn update_click_to_focus_grabs<X: XConn>(x: &X, state: &State<X>) -> Result<()> {
+    if !state.config.click_to_focus {
+        return Ok(());
+    }
+
+    let focused = state.diff.focused_client();
+    for &c in state.diff.new_clients() {
+        if Some(c) != focused {
+            x.grab_client_buttons(c)?;
+        }
+    }
+
+    if !state.diff.focused_client_changed() {
+        return Ok(());
+    }
+
+    if let Some(previous) = state.diff.before.focused_client {
+        if state.client_set.contains(&previous) {
+            x.grab_client_buttons(previous)?;
+        }
+    }
+
+    if let Some(id) = focused {
+        x.ungrab_client_buttons(id)?;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{map, Error, Result};
+    use crate::{
+        map,
+        x::property::{WmHints, WmHintsFlags, WmNormalHints, WmNormalHintsFlags, WmState},
+        Error, Result,
+    };
     use simple_test_case::test_case;
-    use std::collections::HashMap;
+    use std::{cell::RefCell, collections::HashMap};
 
     #[derive(Default)]
     struct TransientXConn {
@@ -657,6 +1289,10 @@ mod tests {
             Ok(vec![TEST_SCREEN, TEST_SCREEN_2])
         }
 
+        fn mock_primary_screen(&self) -> Result<Option<usize>> {
+            Ok(None)
+        }
+
         fn mock_get_prop(&self, client: Xid, prop_name: &str) -> Result<Option<Prop>> {
             let maybe_prop = if prop_name == Atom::WmTransientFor.as_ref() {
                 self.transient_ids
@@ -681,9 +1317,16 @@ mod tests {
         Rect::new(0, 0, 600, 400),
         Rect::new(0, 0, 20, 20),
         0,
-        Rect::new(502, 374, 20, 20);
+        Rect::new(290, 190, 20, 20);
         "fit inside parent"
     )]
+    #[test_case(
+        Rect::new(1500, 100, 800, 600),
+        Rect::new(0, 0, 200, 100),
+        1,
+        Rect::new(1800, 350, 200, 100);
+        "fit inside parent on another screen"
+    )]
     #[test_case(
         Rect::new(0, 0, 100, 200),
         Rect::new(0, 0, 200, 200),
@@ -729,4 +1372,171 @@ mod tests {
 
         assert_eq!(r, expected, "client position is as expected");
     }
+
+    #[derive(Default)]
+    struct PropXConn {
+        props: RefCell<HashMap<(Xid, String), Prop>>,
+    }
+
+    impl MockXConn for PropXConn {
+        fn mock_intern_atom(&self, atom: &str) -> Result<Xid> {
+            Ok(Xid(if atom == Atom::NetWmStateFullscreen.as_ref() {
+                42
+            } else {
+                1
+            }))
+        }
+
+        fn mock_get_prop(&self, client: Xid, prop_name: &str) -> Result<Option<Prop>> {
+            let key = (client, prop_name.to_string());
+
+            Ok(self.props.borrow().get(&key).cloned())
+        }
+
+        fn mock_set_prop(&self, client: Xid, name: &str, val: Prop) -> Result<()> {
+            self.props
+                .borrow_mut()
+                .insert((client, name.to_string()), val);
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn typed_prop_setters_write_the_matching_prop_variant() {
+        let conn = PropXConn::default();
+        let id = Xid(1);
+
+        conn.set_cardinal_prop(id, "CARD", &[1, 2]).unwrap();
+        conn.set_string_prop(id, "STR", &["a", "b"]).unwrap();
+        conn.set_atom_prop(id, "ATOMS", &[Atom::NetWmStateHidden])
+            .unwrap();
+        conn.set_window_prop(id, "WINS", &[Xid(3)]).unwrap();
+
+        let get = |name| conn.get_prop(id, name).unwrap();
+        assert_eq!(get("CARD"), Some(Prop::Cardinal(vec![1, 2])));
+        assert_eq!(
+            get("STR"),
+            Some(Prop::UTF8String(vec!["a".to_owned(), "b".to_owned()]))
+        );
+        assert_eq!(
+            get("ATOMS"),
+            Some(Prop::Atom(vec![Atom::NetWmStateHidden.as_ref().to_owned()]))
+        );
+        assert_eq!(get("WINS"), Some(Prop::Window(vec![Xid(3)])));
+    }
+
+    #[test_case(vec![], true, vec![42]; "set when empty")]
+    #[test_case(vec![7], true, vec![7, 42]; "set retaining other state")]
+    #[test_case(vec![42], true, vec![42]; "set is idempotent")]
+    #[test_case(vec![7, 42], false, vec![7]; "clear retaining other state")]
+    #[test_case(vec![], false, vec![]; "clear when empty")]
+    #[test]
+    fn set_fullscreen_prop_works(initial: Vec<u32>, fullscreen: bool, expected: Vec<u32>) {
+        let conn = PropXConn::default();
+        let net_wm_state = Atom::NetWmState.as_ref();
+        conn.set_prop(Xid(1), net_wm_state, Prop::Cardinal(initial))
+            .unwrap();
+
+        set_fullscreen_prop(&conn, Xid(1), fullscreen).unwrap();
+
+        let prop = conn.get_prop(Xid(1), net_wm_state).unwrap();
+        assert_eq!(prop, Some(Prop::Cardinal(expected)));
+    }
+
+    #[test_case(None, None, false; "no props")]
+    #[test_case(Some(WmHintsFlags::INPUT_HINT), None, false; "hints without urgency")]
+    #[test_case(Some(WmHintsFlags::URGENCY_HINT), None, true; "urgency hint")]
+    #[test_case(None, Some(Atom::NetWmStateFullscreen), false; "other net wm state")]
+    #[test_case(None, Some(Atom::NetWmStateDemandsAttention), true; "demands attention")]
+    #[test]
+    fn client_is_urgent_works(flags: Option<WmHintsFlags>, state: Option<Atom>, expected: bool) {
+        let conn = PropXConn::default();
+
+        if let Some(flags) = flags {
+            let hints = WmHints::new(
+                flags,
+                true,
+                WmState::Normal,
+                0,
+                Xid(0),
+                Point::default(),
+                0,
+                0,
+            );
+            conn.set_prop(Xid(1), Atom::WmHints.as_ref(), Prop::WmHints(hints))
+                .unwrap();
+        }
+
+        if let Some(atom) = state {
+            let prop = Prop::Atom(vec![atom.as_ref().to_owned()]);
+            conn.set_prop(Xid(1), Atom::NetWmState.as_ref(), prop)
+                .unwrap();
+        }
+
+        assert_eq!(conn.client_is_urgent(Xid(1)).unwrap(), expected);
+    }
+
+    fn size_hints(min: Rect, max: Rect) -> Prop {
+        let flags = WmNormalHintsFlags::P_MIN_SIZE | WmNormalHintsFlags::P_MAX_SIZE;
+
+        Prop::WmNormalHints(WmNormalHints::new(flags, None, Some(min), Some(max), None))
+    }
+
+    #[test_case(None, false; "no props")]
+    #[test_case(Some((Atom::WmClass, Prop::UTF8String(vec!["dmenu".into()]))), true; "floating class")]
+    #[test_case(Some((Atom::WmClass, Prop::UTF8String(vec!["firefox".into()]))), false; "tiled class")]
+    #[test_case(Some((Atom::WmTransientFor, Prop::Window(vec![Xid(2)]))), true; "transient")]
+    #[test_case(
+        Some((Atom::NetWmWindowType, Prop::Atom(vec![Atom::NetWindowTypeDialog.as_ref().into()]))),
+        true;
+        "dialog"
+    )]
+    #[test_case(
+        Some((Atom::NetWmWindowType, Prop::Atom(vec![Atom::NetWindowTypeNormal.as_ref().into()]))),
+        false;
+        "normal window type"
+    )]
+    #[test_case(
+        Some((Atom::WmNormalHints, size_hints(Rect::new(0, 0, 200, 100), Rect::new(0, 0, 200, 100)))),
+        true;
+        "fixed size"
+    )]
+    #[test_case(
+        Some((Atom::WmNormalHints, size_hints(Rect::new(0, 0, 200, 100), Rect::new(0, 0, 800, 600)))),
+        false;
+        "resizable"
+    )]
+    #[test]
+    fn client_should_float_default_rules(prop: Option<(Atom, Prop)>, expected: bool) {
+        let conn = PropXConn::default();
+        if let Some((atom, prop)) = prop {
+            conn.set_prop(Xid(1), atom.as_ref(), prop).unwrap();
+        }
+
+        let should_float = conn.client_should_float(Xid(1), &Config::default());
+
+        assert_eq!(should_float.unwrap(), expected);
+    }
+
+    #[test]
+    fn float_rules_can_be_customised() {
+        let conn = PropXConn::default();
+        let prop = Prop::Window(vec![Xid(2)]);
+        conn.set_prop(Xid(1), Atom::WmTransientFor.as_ref(), prop)
+            .unwrap();
+        let dialog = Prop::Atom(vec![Atom::NetWindowTypeDialog.as_ref().into()]);
+        conn.set_prop(Xid(3), Atom::NetWmWindowType.as_ref(), dialog)
+            .unwrap();
+
+        let config = Config {
+            float_rules: vec![Box::new(
+                query::WindowType(&[Atom::NetWindowTypeDialog]).and(query::IsTransient.not()),
+            )],
+            ..Config::default()
+        };
+
+        assert!(!conn.client_should_float(Xid(1), &config).unwrap());
+        assert!(conn.client_should_float(Xid(3), &config).unwrap());
+    }
 }
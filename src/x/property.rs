@@ -22,6 +22,8 @@ pub enum Prop {
     UTF8String(Vec<String>),
     /// An X window IDs
     Window(Vec<Xid>),
+    /// One or more X pixmap IDs
+    Pixmap(Vec<Xid>),
     /// The WmHints properties for this window
     WmHints(WmHints),
     /// The WmNormalHints properties for this window
@@ -164,6 +166,11 @@ impl WmHints {
         }
     }
 
+    /// Whether or not the urgency hint is set for this client.
+    pub fn is_urgent(&self) -> bool {
+        self.flags.contains(WmHintsFlags::URGENCY_HINT)
+    }
+
     /// Try to construct a [WmHints] instance from raw bytes.
     ///
     /// This method expects a slice of 9 u32s corresponding to the C struct layout shown below.
@@ -216,6 +223,27 @@ impl WmHints {
             window_group: raw[8],
         })
     }
+
+    /// Convert this [WmHints] back into the raw bytes expected by [WmHints::try_from_bytes].
+    pub fn to_bytes(&self) -> [u32; 9] {
+        let initial_state = match self.initial_state {
+            WmState::Withdrawn => 0,
+            WmState::Normal => 1,
+            WmState::Iconic => 2,
+        };
+
+        [
+            self.flags.bits(),
+            self.accepts_input as u32,
+            initial_state,
+            self.icon_pixmap,
+            *self.icon_win,
+            self.icon_position.x,
+            self.icon_position.y,
+            self.icon_mask,
+            self.window_group,
+        ]
+    }
 }
 
 /// Client requested hints about window geometry.
@@ -338,6 +366,34 @@ impl WmNormalHints {
             user_specified: if_set(x, y, user_w, user_h),
         })
     }
+
+    /// Convert this [WmNormalHints] back into the raw bytes expected by
+    /// [WmNormalHints::try_from_bytes].
+    ///
+    /// The position of the hints is taken from the first of the user specified, base, min and
+    /// max sizes that is set. Increment, aspect ratio and gravity hints are not tracked by
+    /// [WmNormalHints] and so are always zero.
+    pub fn to_bytes(&self) -> [u32; 18] {
+        let (x, y) = [self.user_specified, self.base, self.min, self.max]
+            .into_iter()
+            .flatten()
+            .map(|r| (r.x, r.y))
+            .next()
+            .unwrap_or_default();
+        let size = |r: Option<Rect>| r.map(|r| (r.w, r.h)).unwrap_or_default();
+        let (user_w, user_h) = size(self.user_specified);
+        let (min_w, min_h) = size(self.min);
+        let (max_w, max_h) = size(self.max);
+        let (base_w, base_h) = size(self.base);
+
+        let mut raw = [0; 18];
+        raw[0] = self.flags.bits();
+        raw[1..9].copy_from_slice(&[x, y, user_w, user_h, min_w, min_h, max_w, max_h]);
+        raw[15] = base_w;
+        raw[16] = base_h;
+
+        raw
+    }
 }
 
 /// Window Attributes honoured by penose.
@@ -386,3 +442,44 @@ impl WindowAttributes {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wm_hints_round_trip_through_bytes() {
+        let hints = WmHints::new(
+            WmHintsFlags::INPUT_HINT | WmHintsFlags::STATE_HINT | WmHintsFlags::URGENCY_HINT,
+            true,
+            WmState::Iconic,
+            1,
+            Xid(2),
+            Point::new(3, 4),
+            5,
+            6,
+        );
+
+        let parsed = WmHints::try_from_bytes(&hints.to_bytes()).unwrap();
+
+        assert_eq!(parsed, hints);
+    }
+
+    #[test]
+    fn wm_normal_hints_round_trip_through_bytes() {
+        let flags = WmNormalHintsFlags::P_MIN_SIZE
+            | WmNormalHintsFlags::P_MAX_SIZE
+            | WmNormalHintsFlags::P_BASE_SIZE;
+        let hints = WmNormalHints::new(
+            flags,
+            Some(Rect::new(10, 20, 100, 100)),
+            Some(Rect::new(10, 20, 50, 60)),
+            Some(Rect::new(10, 20, 500, 600)),
+            None,
+        );
+
+        let parsed = WmNormalHints::try_from_bytes(&hints.to_bytes()).unwrap();
+
+        assert_eq!(parsed, hints);
+    }
+}
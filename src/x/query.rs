@@ -144,6 +144,61 @@ where
     }
 }
 
+/// A [Query] for identifying transient windows (those that have set WM_TRANSIENT_FOR).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IsTransient;
+
+impl<X> Query<X> for IsTransient
+where
+    X: XConn,
+{
+    fn run(&self, id: Xid, x: &X) -> Result<bool> {
+        match x.get_prop(id, Atom::WmTransientFor.as_ref())? {
+            Some(Prop::Window(ids)) => Ok(!ids.is_empty()),
+            _ => Ok(false),
+        }
+    }
+}
+
+/// A [Query] for identifying windows that have any of the given types set under the
+/// _NET_WM_WINDOW_TYPE property.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WindowType(pub &'static [Atom]);
+
+impl<X> Query<X> for WindowType
+where
+    X: XConn,
+{
+    fn run(&self, id: Xid, x: &X) -> Result<bool> {
+        match x.get_prop(id, Atom::NetWmWindowType.as_ref())? {
+            Some(Prop::Atom(atoms)) => {
+                Ok(atoms.iter().any(|a| self.0.iter().any(|t| t.as_ref() == a)))
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+/// A [Query] for identifying windows that can not be resized: those whose WM_NORMAL_HINTS
+/// property sets the same minimum and maximum size.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FixedSize;
+
+impl<X> Query<X> for FixedSize
+where
+    X: XConn,
+{
+    fn run(&self, id: Xid, x: &X) -> Result<bool> {
+        match x.get_prop(id, Atom::WmNormalHints.as_ref())? {
+            Some(Prop::WmNormalHints(hints)) => match (hints.min, hints.max) {
+                (Some(min), Some(max)) => Ok(min.w == max.w && min.h == max.h),
+                _ => Ok(false),
+            },
+            _ => Ok(false),
+        }
+    }
+}
+
 /// A meta [Query] for combining two queries with a logical AND.
 #[derive(Debug)]
 pub struct AndQuery<X: XConn> {
@@ -0,0 +1,1452 @@
+//! A simulated X server for testing window manager configurations without a display.
+//!
+//! [TestConn] implements [XConn] by tracking the state of a set of fake client windows in memory
+//! and allowing events to be injected into the queue read by the [WindowManager] main loop. This
+//! makes it possible to write unit tests for manage hooks, layouts and custom actions in the
+//! same way as any other Rust code.
+//!
+//! ```
+//! use penrose::{
+//!     core::{Config, WindowManager},
+//!     pure::geometry::Rect,
+//!     x::{TestConn, XConn},
+//! };
+//! use std::collections::HashMap;
+//!
+//! let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+//! let mut wm = WindowManager::new(Config::default(), HashMap::new(), HashMap::new(), conn)?;
+//! wm.start_headless()?;
+//!
+//! let first = wm.conn().spawn_client("st");
+//! let second = wm.conn().spawn_client("st");
+//! wm.run_pending_events();
+//!
+//! assert_eq!(wm.state.client_set.current_client(), Some(&second));
+//! assert_eq!(wm.conn().focused_client(), Some(second));
+//! assert!(wm.conn().is_mapped(first) && wm.conn().is_mapped(second));
+//! assert_eq!(wm.conn().client_geometry(second)?, Rect::new(0, 0, 596, 796));
+//! assert_eq!(wm.conn().client_geometry(first)?, Rect::new(600, 0, 396, 796));
+//! # Ok::<(), penrose::Error>(())
+//! ```
+//!
+//!   [WindowManager]: crate::core::WindowManager
+use crate::{
+    core::{
+        bindings::{KeyBindings, KeyCode, MouseButton, MouseEvent, MouseEventKind, MouseState},
+        Config, WindowManager,
+    },
+    pure::geometry::{Point, Rect},
+    x::{
+        atom::Atom,
+        event::{ClientMessage, ConfigureEvent, XEvent},
+        property::{MapState, Prop, WindowAttributes, WindowClass, WmState},
+        ClientAttr, ClientConfig, PointerQuery, XConn,
+    },
+    Color, Error, Result, Xid,
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, VecDeque},
+};
+
+const ROOT: Xid = Xid(1);
+const DEFAULT_SCREEN: Rect = Rect::new(0, 0, 1920, 1080);
+const DEFAULT_CLIENT_SIZE: Rect = Rect::new(0, 0, 640, 480);
+
+#[derive(Debug, Clone)]
+struct TestWindow {
+    r: Rect,
+    mapped: bool,
+    override_redirect: bool,
+    wm_state: Option<WmState>,
+    border_color: Option<u32>,
+    border_width: u32,
+    buttons_grabbed: bool,
+    props: HashMap<String, Prop>,
+}
+
+impl TestWindow {
+    fn new(r: Rect) -> Self {
+        Self {
+            r,
+            mapped: false,
+            override_redirect: false,
+            wm_state: None,
+            border_color: None,
+            border_width: 0,
+            buttons_grabbed: false,
+            props: HashMap::new(),
+        }
+    }
+}
+
+/// A headless [XConn] implementation that simulates an X server in memory.
+///
+/// Clients are created using [TestConn::create_client] or [TestConn::spawn_client] and events
+/// can be injected using [TestConn::push_event] and its associated helper methods. Requests made
+/// by the window manager (mapping, positioning and focusing clients etc) are applied to the
+/// simulated state so that their effects can be checked in tests.
+///
+/// Mirroring a real X server, unmapping a visible client and killing a client result in
+/// `UnmapNotify` and `Destroy` events being queued respectively. Use
+/// [WindowManager::run_pending_events][crate::core::WindowManager::run_pending_events] to
+/// process the queue.
+///
+/// See the module level docs for an example.
+#[derive(Debug)]
+pub struct TestConn {
+    screens: RefCell<Vec<Rect>>,
+    screen_names: RefCell<Vec<String>>,
+    primary: Cell<Option<usize>>,
+    cursor: Cell<Point>,
+    focused: Cell<Xid>,
+    next_id: Cell<u32>,
+    atoms: RefCell<Vec<String>>,
+    windows: RefCell<HashMap<Xid, TestWindow>>,
+    events: RefCell<VecDeque<XEvent>>,
+    sent_messages: RefCell<Vec<ClientMessage>>,
+    keyboard_layouts: RefCell<Vec<String>>,
+    keyboard_layout: Cell<u8>,
+    pointer_grabbed: Cell<bool>,
+    replayed_clicks: Cell<usize>,
+    configure_requests: Cell<usize>,
+    disconnected: Cell<bool>,
+}
+
+impl Default for TestConn {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestConn {
+    /// Create a new [TestConn] with a single 1920x1080 screen and no clients.
+    pub fn new() -> Self {
+        let mut windows = HashMap::new();
+        windows.insert(ROOT, TestWindow::new(DEFAULT_SCREEN));
+
+        Self {
+            screens: RefCell::new(vec![DEFAULT_SCREEN]),
+            screen_names: RefCell::new(Vec::new()),
+            primary: Cell::new(None),
+            cursor: Cell::new(Point::default()),
+            focused: Cell::new(ROOT),
+            next_id: Cell::new(*ROOT + 1),
+            atoms: RefCell::new(Vec::new()),
+            windows: RefCell::new(windows),
+            events: RefCell::new(VecDeque::new()),
+            sent_messages: RefCell::new(Vec::new()),
+            keyboard_layouts: RefCell::new(vec!["English (US)".to_owned()]),
+            keyboard_layout: Cell::new(0),
+            pointer_grabbed: Cell::new(false),
+            replayed_clicks: Cell::new(0),
+            configure_requests: Cell::new(0),
+            disconnected: Cell::new(false),
+        }
+    }
+
+    /// Set the screens reported by this connection when the window manager starts.
+    ///
+    /// # Panics
+    /// This method will panic if `screens` is empty.
+    pub fn with_screens(self, screens: Vec<Rect>) -> Self {
+        assert!(!screens.is_empty(), "at least one screen is required");
+        self.windows.borrow_mut().get_mut(&ROOT).unwrap().r = bounding_rect(&screens);
+        *self.screens.borrow_mut() = screens;
+
+        self
+    }
+
+    /// Set the output names reported for each screen by this connection.
+    ///
+    /// Screens without a name given here are reported as "SCREEN-$index".
+    pub fn with_screen_names(self, names: Vec<String>) -> Self {
+        *self.screen_names.borrow_mut() = names;
+
+        self
+    }
+
+    /// Set the index of the screen reported as primary by this connection when the window
+    /// manager starts.
+    pub fn with_primary_screen(self, index: usize) -> Self {
+        self.primary.set(Some(index));
+
+        self
+    }
+
+    /// Set the names of the keyboard layouts reported by this connection, with the first being
+    /// active.
+    ///
+    /// # Panics
+    /// This method will panic if `layouts` is empty.
+    pub fn with_keyboard_layouts(self, layouts: Vec<String>) -> Self {
+        assert!(
+            !layouts.is_empty(),
+            "at least one keyboard layout is required"
+        );
+        *self.keyboard_layouts.borrow_mut() = layouts;
+
+        self
+    }
+
+    /// Create a [WindowManager] using this connection, the default [Config] and the given key
+    /// bindings and start it without entering the main event loop.
+    ///
+    /// `add_to` is called with the new window manager before it is started so that extensions
+    /// can be added and the config modified, in the same way as a normal `main.rs`.
+    ///
+    /// # Panics
+    /// This method will panic if the window manager can not be created or started.
+    pub fn start_headless_wm<F>(
+        self,
+        key_bindings: KeyBindings<TestConn>,
+        add_to: F,
+    ) -> WindowManager<TestConn>
+    where
+        F: FnOnce(WindowManager<TestConn>) -> WindowManager<TestConn>,
+    {
+        let wm = WindowManager::new(Config::default(), key_bindings, HashMap::new(), self)
+            .expect("unable to create a WindowManager from a TestConn");
+        let mut wm = add_to(wm);
+        wm.start_headless()
+            .expect("unable to start a headless WindowManager");
+
+        wm
+    }
+
+    /// Create a new unmapped client window with the given WM_CLASS.
+    ///
+    /// The client will not be managed by the window manager until a `MapRequest` is processed
+    /// for it: see [TestConn::spawn_client].
+    pub fn create_client(&self, class: &str) -> Xid {
+        let id = Xid(self.next_id.get());
+        self.next_id.set(*id + 1);
+
+        let mut w = TestWindow::new(DEFAULT_CLIENT_SIZE);
+        w.props.insert(
+            Atom::WmClass.as_ref().to_owned(),
+            Prop::UTF8String(vec![class.to_owned(), class.to_owned()]),
+        );
+        self.windows.borrow_mut().insert(id, w);
+
+        id
+    }
+
+    /// Create a new client window with the given WM_CLASS and queue a `MapRequest` for it,
+    /// simulating a program being launched.
+    pub fn spawn_client(&self, class: &str) -> Xid {
+        let id = self.create_client(class);
+        self.push_event(XEvent::MapRequest(id));
+
+        id
+    }
+
+    /// Create a new client that is already mapped, simulating a client that was present before
+    /// the window manager started.
+    pub fn create_existing_client(&self, class: &str) -> Xid {
+        let id = self.create_client(class);
+        self.update_window(id, |w| {
+            w.mapped = true;
+            w.wm_state = Some(WmState::Normal);
+        });
+
+        id
+    }
+
+    /// Create a new override redirect window (such as a menu or notification) that should be
+    /// ignored by the window manager, and queue a `MapRequest` for it.
+    pub fn spawn_override_redirect(&self, class: &str) -> Xid {
+        let id = self.create_client(class);
+        self.update_window(id, |w| w.override_redirect = true);
+        self.push_event(XEvent::MapRequest(id));
+
+        id
+    }
+
+    /// Simulate a client closing itself, removing the window and queuing a `Destroy` event.
+    pub fn close_client(&self, id: Xid) {
+        if self.windows.borrow_mut().remove(&id).is_some() {
+            self.push_event(XEvent::Destroy(id));
+        }
+    }
+
+    /// Queue an event to be processed by the window manager.
+    pub fn push_event(&self, event: XEvent) {
+        self.events.borrow_mut().push_back(event);
+    }
+
+    /// Queue a `KeyPress` event for the given [KeyCode].
+    pub fn press_key(&self, key: KeyCode) {
+        self.push_event(XEvent::KeyPress(key));
+    }
+
+    /// Replace the current screens and queue a `RandrNotify` event, simulating monitors being
+    /// connected, disconnected or reconfigured.
+    ///
+    /// # Panics
+    /// This method will panic if `screens` is empty.
+    pub fn set_screens(&self, screens: Vec<Rect>) {
+        assert!(!screens.is_empty(), "at least one screen is required");
+        self.windows.borrow_mut().get_mut(&ROOT).unwrap().r = bounding_rect(&screens);
+        *self.screens.borrow_mut() = screens;
+        self.push_event(XEvent::RandrNotify);
+    }
+
+    /// Set the output names reported for each screen without notifying the window manager.
+    ///
+    /// Call this before [TestConn::set_screens] to simulate a monitor being plugged in or
+    /// removed.
+    pub fn set_screen_names(&self, names: Vec<String>) {
+        *self.screen_names.borrow_mut() = names;
+    }
+
+    /// Move the simulated mouse cursor to the given absolute position.
+    pub fn set_cursor_position(&self, p: Point) {
+        self.cursor.set(p);
+    }
+
+    /// Simulate a left click on the given client, queuing a button press event for it if the
+    /// window manager is currently grabbing button presses on that client.
+    pub fn click_client(&self, id: Xid) {
+        if self.buttons_grabbed(id) {
+            let state = MouseState::new(MouseButton::Left, vec![]);
+            let e = MouseEvent::new(id, 0, 0, 0, 0, state, MouseEventKind::Press).with_window(id);
+            self.push_event(XEvent::MouseEvent(e));
+        }
+    }
+
+    /// Simulate another client grabbing (or releasing) the pointer.
+    pub fn set_pointer_grabbed(&self, grabbed: bool) {
+        self.pointer_grabbed.set(grabbed);
+    }
+
+    /// Simulate losing the connection to the X server: once all currently queued events have
+    /// been returned, requesting the next event returns a connection error.
+    pub fn disconnect(&self) {
+        self.disconnected.set(true);
+    }
+
+    /// Remove the next event from the queue if there is one.
+    pub fn pop_event(&self) -> Option<XEvent> {
+        self.events.borrow_mut().pop_front()
+    }
+
+    /// The number of events currently waiting to be processed.
+    pub fn pending_events(&self) -> usize {
+        self.events.borrow().len()
+    }
+
+    /// The client currently holding input focus, if any.
+    pub fn focused_client(&self) -> Option<Xid> {
+        Some(self.focused.get()).filter(|&id| id != ROOT)
+    }
+
+    /// Whether or not the given client exists and is currently mapped.
+    pub fn is_mapped(&self, id: Xid) -> bool {
+        self.windows.borrow().get(&id).map(|w| w.mapped) == Some(true)
+    }
+
+    /// Whether or not the given client window still exists.
+    pub fn client_exists(&self, id: Xid) -> bool {
+        id != ROOT && self.windows.borrow().contains_key(&id)
+    }
+
+    /// The border color most recently set for the given client, if any.
+    pub fn border_color(&self, id: Xid) -> Option<Color> {
+        self.windows
+            .borrow()
+            .get(&id)
+            .and_then(|w| w.border_color)
+            // Border colors are set as argb but Color::new_from_hex expects rgba
+            .map(|argb| Color::new_from_hex(argb.rotate_left(8)))
+    }
+
+    /// The border width in pixels most recently set for the given client, if it exists.
+    pub fn border_width(&self, id: Xid) -> Option<u32> {
+        self.windows.borrow().get(&id).map(|w| w.border_width)
+    }
+
+    /// Whether or not the window manager is currently grabbing button presses on the given client.
+    pub fn buttons_grabbed(&self, id: Xid) -> bool {
+        self.windows.borrow().get(&id).map(|w| w.buttons_grabbed) == Some(true)
+    }
+
+    /// The number of grabbed button presses that have been replayed to the client clicked on.
+    pub fn replayed_clicks(&self) -> usize {
+        self.replayed_clicks.get()
+    }
+
+    /// The number of requests to change the geometry or stacking order of a window that have
+    /// been made via this connection so far.
+    pub fn configure_requests(&self) -> usize {
+        self.configure_requests.get()
+    }
+
+    /// All client messages sent via this connection so far, in the order they were sent.
+    pub fn sent_client_messages(&self) -> Vec<ClientMessage> {
+        self.sent_messages.borrow().clone()
+    }
+
+    // Requests that modify a window are not checked by the x11rb backend so errors from the X
+    // server for unknown windows are ignored: we mirror that here.
+    fn update_window<F>(&self, id: Xid, f: F)
+    where
+        F: FnOnce(&mut TestWindow),
+    {
+        if let Some(w) = self.windows.borrow_mut().get_mut(&id) {
+            f(w);
+        }
+    }
+
+    fn with_window<T, F>(&self, id: Xid, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut TestWindow) -> T,
+    {
+        match self.windows.borrow_mut().get_mut(&id) {
+            Some(w) => Ok(f(w)),
+            None => Err(Error::UnknownClient(id)),
+        }
+    }
+}
+
+fn bounding_rect(screens: &[Rect]) -> Rect {
+    let x2 = screens.iter().map(|r| r.x + r.w).max().unwrap_or(0);
+    let y2 = screens.iter().map(|r| r.y + r.h).max().unwrap_or(0);
+
+    Rect::new(0, 0, x2, y2)
+}
+
+impl XConn for TestConn {
+    fn root(&self) -> Xid {
+        ROOT
+    }
+
+    fn screen_details(&self) -> Result<Vec<Rect>> {
+        Ok(self.screens.borrow().clone())
+    }
+
+    fn screen_names(&self) -> Result<Vec<String>> {
+        let names = self.screen_names.borrow();
+
+        Ok((0..self.screens.borrow().len())
+            .map(|i| {
+                names
+                    .get(i)
+                    .cloned()
+                    .unwrap_or_else(|| format!("SCREEN-{i}"))
+            })
+            .collect())
+    }
+
+    fn primary_screen(&self) -> Result<Option<usize>> {
+        let n_screens = self.screens.borrow().len();
+
+        Ok(self.primary.get().filter(|&ix| ix < n_screens))
+    }
+
+    fn set_primary_screen(&self, index: usize) -> Result<()> {
+        if index >= self.screens.borrow().len() {
+            return Err(Error::UnknownScreen(index));
+        }
+
+        if self.primary.replace(Some(index)) != Some(index) {
+            self.push_event(XEvent::RandrNotify);
+        }
+
+        Ok(())
+    }
+
+    fn cursor_position(&self) -> Result<Point> {
+        Ok(self.cursor.get())
+    }
+
+    // Windows are not restacked so the most recently created window under the cursor is on top
+    fn query_pointer(&self) -> Result<PointerQuery> {
+        let pos = self.cursor.get();
+        let screen = self
+            .screens
+            .borrow()
+            .iter()
+            .position(|r| r.contains_point(pos));
+        let child = self
+            .windows
+            .borrow()
+            .iter()
+            .filter(|&(&id, w)| id != ROOT && w.mapped && w.r.contains_point(pos))
+            .map(|(&id, _)| id)
+            .max();
+
+        Ok(PointerQuery { pos, screen, child })
+    }
+
+    fn grab(&self, _: &[KeyCode], _: &[MouseState]) -> Result<()> {
+        Ok(())
+    }
+
+    fn next_event(&self) -> Result<XEvent> {
+        match self.pop_event() {
+            Some(event) => Ok(event),
+            None if self.disconnected.get() => {
+                Err(Error::ConnectionLost("TestConn disconnected".to_owned()))
+            }
+            None => Err(Error::Custom("no events queued on TestConn".to_owned())),
+        }
+    }
+
+    fn poll_next_event(&self) -> Result<Option<XEvent>> {
+        match self.pop_event() {
+            Some(event) => Ok(Some(event)),
+            None if self.disconnected.get() => {
+                Err(Error::ConnectionLost("TestConn disconnected".to_owned()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn flush(&self) {}
+
+    fn intern_atom(&self, atom: &str) -> Result<Xid> {
+        let mut atoms = self.atoms.borrow_mut();
+        let ix = match atoms.iter().position(|a| a == atom) {
+            Some(ix) => ix,
+            None => {
+                atoms.push(atom.to_owned());
+                atoms.len() - 1
+            }
+        };
+
+        Ok(Xid(ix as u32 + 1))
+    }
+
+    fn atom_name(&self, xid: Xid) -> Result<String> {
+        self.atoms
+            .borrow()
+            .get((*xid as usize).wrapping_sub(1))
+            .cloned()
+            .ok_or_else(|| Error::Custom(format!("{xid} is not a known atom")))
+    }
+
+    fn client_geometry(&self, client: Xid) -> Result<Rect> {
+        self.with_window(client, |w| w.r)
+    }
+
+    fn existing_clients(&self) -> Result<Vec<Xid>> {
+        let mut ids: Vec<Xid> = self
+            .windows
+            .borrow()
+            .keys()
+            .copied()
+            .filter(|&id| id != ROOT)
+            .collect();
+        ids.sort();
+
+        Ok(ids)
+    }
+
+    fn map(&self, client: Xid) -> Result<()> {
+        self.update_window(client, |w| w.mapped = true);
+
+        Ok(())
+    }
+
+    fn unmap(&self, client: Xid) -> Result<()> {
+        let mut was_mapped = false;
+        self.update_window(client, |w| {
+            was_mapped = std::mem::replace(&mut w.mapped, false)
+        });
+        if was_mapped {
+            self.push_event(XEvent::UnmapNotify(client));
+        }
+
+        Ok(())
+    }
+
+    fn kill(&self, client: Xid) -> Result<()> {
+        if client != ROOT && self.windows.borrow_mut().remove(&client).is_some() {
+            self.push_event(XEvent::Destroy(client));
+        }
+
+        Ok(())
+    }
+
+    fn focus(&self, client: Xid) -> Result<()> {
+        if self.windows.borrow().contains_key(&client) {
+            self.focused.set(client);
+        }
+
+        Ok(())
+    }
+
+    fn get_prop(&self, client: Xid, prop_name: &str) -> Result<Option<Prop>> {
+        self.with_window(client, |w| w.props.get(prop_name).cloned())
+    }
+
+    fn list_props(&self, client: Xid) -> Result<Vec<String>> {
+        self.with_window(client, |w| w.props.keys().cloned().collect())
+    }
+
+    fn get_wm_state(&self, client: Xid) -> Result<Option<WmState>> {
+        self.with_window(client, |w| w.wm_state.clone())
+    }
+
+    fn get_window_attributes(&self, client: Xid) -> Result<WindowAttributes> {
+        self.with_window(client, |w| {
+            let map_state = if w.mapped {
+                MapState::Viewable
+            } else {
+                MapState::Unmapped
+            };
+
+            WindowAttributes::new(w.override_redirect, map_state, WindowClass::InputOutput)
+        })
+    }
+
+    fn set_wm_state(&self, client: Xid, wm_state: WmState) -> Result<()> {
+        self.update_window(client, |w| w.wm_state = Some(wm_state));
+
+        Ok(())
+    }
+
+    fn set_prop(&self, client: Xid, name: &str, val: Prop) -> Result<()> {
+        self.update_window(client, |w| {
+            w.props.insert(name.to_owned(), val);
+        });
+
+        Ok(())
+    }
+
+    fn delete_prop(&self, client: Xid, prop_name: &str) -> Result<()> {
+        self.update_window(client, |w| {
+            w.props.remove(prop_name);
+        });
+
+        Ok(())
+    }
+
+    fn set_client_attributes(&self, client: Xid, attrs: &[ClientAttr]) -> Result<()> {
+        self.update_window(client, |w| {
+            for attr in attrs {
+                if let ClientAttr::BorderColor(c) = attr {
+                    w.border_color = Some(*c);
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn set_client_config(&self, client: Xid, data: &[ClientConfig]) -> Result<()> {
+        let n = data
+            .iter()
+            .filter(|c| !matches!(c, ClientConfig::BorderPx(_)))
+            .count();
+        self.configure_requests
+            .set(self.configure_requests.get() + n);
+
+        let mut moved = None;
+        self.update_window(client, |w| {
+            for conf in data {
+                match conf {
+                    ClientConfig::Position(r) if w.r != *r => {
+                        w.r = *r;
+                        moved = Some(*r);
+                    }
+                    ClientConfig::BorderPx(px) => w.border_width = *px,
+                    _ => (),
+                }
+            }
+        });
+
+        // The window manager selects for substructure notify events on the root window so it
+        // is told about any changes to the geometry of top level windows.
+        if let Some(r) = moved {
+            self.push_event(XEvent::ConfigureNotify(ConfigureEvent {
+                id: client,
+                r,
+                is_root: false,
+            }));
+        }
+
+        Ok(())
+    }
+
+    fn send_client_message(&self, msg: ClientMessage) -> Result<()> {
+        self.sent_messages.borrow_mut().push(msg);
+
+        Ok(())
+    }
+
+    fn warp_pointer(&self, id: Xid, x: i16, y: i16) -> Result<()> {
+        let r = self.client_geometry(id)?;
+        self.cursor.set(Point::new(
+            (r.x as i32 + x as i32) as u32,
+            (r.y as i32 + y as i32) as u32,
+        ));
+
+        Ok(())
+    }
+
+    fn keyboard_layouts(&self) -> Result<Vec<String>> {
+        Ok(self.keyboard_layouts.borrow().clone())
+    }
+
+    fn keyboard_layout(&self) -> Result<u8> {
+        Ok(self.keyboard_layout.get())
+    }
+
+    fn set_keyboard_layout(&self, index: u8) -> Result<()> {
+        // XKB wraps out of range groups back into the configured range
+        let index = index % self.keyboard_layouts.borrow().len() as u8;
+        if self.keyboard_layout.replace(index) != index {
+            self.push_event(XEvent::KeyboardLayoutChange(index));
+        }
+
+        Ok(())
+    }
+
+    fn pointer_is_grabbed(&self) -> Result<bool> {
+        Ok(self.pointer_grabbed.get())
+    }
+
+    fn grab_client_buttons(&self, client: Xid) -> Result<()> {
+        self.update_window(client, |w| w.buttons_grabbed = true);
+
+        Ok(())
+    }
+
+    fn ungrab_client_buttons(&self, client: Xid) -> Result<()> {
+        self.update_window(client, |w| w.buttons_grabbed = false);
+
+        Ok(())
+    }
+
+    fn allow_pointer_events(&self, replay: bool) -> Result<()> {
+        if replay {
+            self.replayed_clicks.set(self.replayed_clicks.get() + 1);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builtin::actions::{
+            exit,
+            floating::{MouseDragHandler, MouseResizeHandler},
+            key_handler, modify_with, next_keyboard_layout, previous_keyboard_layout,
+            set_border_style, set_primary_screen,
+        },
+        core::{
+            bindings::{
+                KeyEventHandler, MotionNotifyEvent, MouseButton, MouseEvent, MouseEventHandler,
+                MouseEventKind, MouseState,
+            },
+            BorderStyle, Config, WindowManager,
+        },
+        extensions::hooks::manage::{InsertAt, InsertAtWithFocus},
+        pure::{FocusPolicy, Position},
+        x::{event::PropertyEvent, query::ClassName, XConnExt},
+    };
+    use simple_test_case::test_case;
+    use std::collections::HashMap;
+
+    const KEY: KeyCode = KeyCode { mask: 0, code: 42 };
+
+    fn wm_with_bindings(
+        bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>>,
+    ) -> WindowManager<TestConn> {
+        wm_with_config_and_bindings(Config::default(), bindings)
+    }
+
+    fn wm_with_config_and_bindings(
+        config: Config<TestConn>,
+        bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>>,
+    ) -> WindowManager<TestConn> {
+        let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+        let mut wm = WindowManager::new(config, bindings, HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+
+        wm
+    }
+
+    fn click_to_focus_config() -> Config<TestConn> {
+        Config {
+            click_to_focus: true,
+            raise_floating_on_click: true,
+            pass_through_focus_click: true,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn spawned_clients_are_managed_and_tiled() {
+        let mut wm = wm_with_bindings(HashMap::new());
+        let ids: Vec<Xid> = (0..3).map(|_| wm.conn().spawn_client("st")).collect();
+        wm.run_pending_events();
+
+        assert_eq!(wm.state.client_set.current_client(), Some(&ids[2]));
+        assert_eq!(wm.conn().focused_client(), Some(ids[2]));
+        assert!(ids.iter().all(|&id| wm.conn().is_mapped(id)));
+
+        let mut rects: Vec<Rect> = ids
+            .iter()
+            .map(|&id| wm.conn().client_geometry(id).unwrap())
+            .collect();
+        rects.sort_by_key(|r| (r.x, r.y));
+        assert_eq!(rects[0], Rect::new(0, 0, 596, 796));
+        assert_eq!(rects[1], Rect::new(600, 0, 396, 396));
+        assert_eq!(rects[2], Rect::new(600, 400, 396, 396));
+    }
+
+    #[test]
+    fn unchanged_clients_are_not_reconfigured_on_refresh() {
+        let noop = KeyCode { mask: 0, code: 43 };
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(KEY, modify_with(|cs| cs.toggle_tag()));
+        bindings.insert(noop, modify_with(|_| ()));
+        let mut wm = wm_with_bindings(bindings);
+        let ids: Vec<Xid> = (0..3).map(|_| wm.conn().spawn_client("st")).collect();
+        wm.run_pending_events();
+        wm.state.client_set.focus_tag("2");
+        wm.conn().press_key(noop);
+        wm.run_pending_events();
+
+        let n = wm.conn().configure_requests();
+        wm.conn().press_key(noop);
+        wm.run_pending_events();
+        assert_eq!(wm.conn().configure_requests(), n);
+
+        // Switching back to a tag whose layout is unchanged only requires restacking the clients
+        wm.conn().press_key(KEY);
+        wm.run_pending_events();
+        assert_eq!(wm.conn().configure_requests(), n + ids.len() - 1);
+        assert!(ids.iter().all(|&id| wm.conn().is_mapped(id)));
+    }
+
+    #[test]
+    fn clients_moved_outside_of_position_clients_are_repositioned() {
+        let noop = KeyCode { mask: 0, code: 43 };
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(noop, modify_with(|_| ()));
+        bindings.insert(
+            KEY,
+            key_handler(|state, x: &TestConn| {
+                let id = *state.client_set.current_client().unwrap();
+                x.position_client(id, Rect::new(5, 5, 50, 50))
+            }),
+        );
+        let mut wm = wm_with_bindings(bindings);
+        let ids: Vec<Xid> = (0..2).map(|_| wm.conn().spawn_client("st")).collect();
+        wm.run_pending_events();
+        let before: Vec<Rect> = ids
+            .iter()
+            .map(|&id| wm.conn().client_geometry(id).unwrap())
+            .collect();
+
+        wm.conn().press_key(KEY);
+        wm.run_pending_events();
+        let focused = *wm.state.client_set.current_client().unwrap();
+        assert_eq!(
+            wm.conn().client_geometry(focused).unwrap(),
+            Rect::new(5, 5, 50, 50)
+        );
+
+        wm.conn().press_key(noop);
+        wm.run_pending_events();
+        let after: Vec<Rect> = ids
+            .iter()
+            .map(|&id| wm.conn().client_geometry(id).unwrap())
+            .collect();
+        assert_eq!(after, before);
+    }
+
+    #[test]
+    fn override_redirect_windows_are_ignored() {
+        let mut wm = wm_with_bindings(HashMap::new());
+        let id = wm.conn().spawn_override_redirect("dunst");
+        wm.run_pending_events();
+
+        assert!(!wm.state.client_set.contains(&id));
+    }
+
+    #[test]
+    fn closed_clients_are_removed() {
+        let mut wm = wm_with_bindings(HashMap::new());
+        let id = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+        wm.conn().close_client(id);
+        wm.run_pending_events();
+
+        assert!(!wm.state.client_set.contains(&id));
+        assert_eq!(wm.conn().focused_client(), None);
+    }
+
+    #[test]
+    fn key_bindings_are_run() {
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(KEY, key_handler(|s, x: &TestConn| x.kill_focused(s)));
+        let mut wm = wm_with_bindings(bindings);
+
+        let id = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+        wm.conn().press_key(KEY);
+        wm.run_pending_events();
+
+        assert!(!wm.conn().client_exists(id));
+        assert!(!wm.state.client_set.contains(&id));
+    }
+
+    #[test]
+    fn hidden_clients_are_unmapped_without_being_removed() {
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(KEY, modify_with(|cs| cs.focus_tag("2")));
+        let mut wm = wm_with_bindings(bindings);
+
+        let id = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+        wm.conn().press_key(KEY);
+        wm.run_pending_events();
+
+        assert!(!wm.conn().is_mapped(id));
+        assert!(wm.state.client_set.contains(&id));
+        assert_eq!(wm.conn().pending_events(), 0);
+    }
+
+    #[test]
+    fn exit_stops_processing_events() {
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(KEY, exit());
+        let mut wm = wm_with_bindings(bindings);
+
+        wm.conn().press_key(KEY);
+        let id = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        assert!(!wm.state.client_set.contains(&id));
+        assert_eq!(wm.conn().pending_events(), 1);
+    }
+
+    #[test]
+    fn keyboard_layout_actions_cycle_layouts() {
+        const PREV: KeyCode = KeyCode { mask: 0, code: 43 };
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(KEY, next_keyboard_layout());
+        bindings.insert(PREV, previous_keyboard_layout());
+        let conn = TestConn::new().with_keyboard_layouts(vec!["us".to_owned(), "de".to_owned()]);
+        let mut wm = WindowManager::new(Config::default(), bindings, HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+
+        wm.conn().press_key(KEY);
+        wm.run_pending_events();
+        assert_eq!(wm.conn().keyboard_layout().unwrap(), 1);
+
+        wm.conn().press_key(KEY);
+        wm.run_pending_events();
+        assert_eq!(wm.conn().keyboard_layout().unwrap(), 0);
+
+        wm.conn().press_key(PREV);
+        wm.run_pending_events();
+        assert_eq!(wm.conn().keyboard_layout().unwrap(), 1);
+    }
+
+    #[test_case(None, 0; "no primary")]
+    #[test_case(Some(0), 0; "first screen")]
+    #[test_case(Some(1), 1; "second screen")]
+    #[test]
+    fn the_first_workspace_starts_focused_on_the_primary_screen(
+        primary: Option<usize>,
+        expected: usize,
+    ) {
+        let mut conn = TestConn::new().with_screens(vec![
+            Rect::new(0, 0, 1000, 800),
+            Rect::new(1000, 0, 1000, 800),
+        ]);
+        if let Some(index) = primary {
+            conn = conn.with_primary_screen(index);
+        }
+        let mut wm =
+            WindowManager::new(Config::default(), HashMap::new(), HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+
+        let cs = &wm.state.client_set;
+        assert_eq!(cs.primary_screen().index(), expected);
+        assert_eq!(cs.current_screen().index(), expected);
+        assert_eq!(cs.current_tag(), "1");
+    }
+
+    #[test]
+    fn set_primary_screen_marks_the_current_screen_as_primary() {
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(KEY, set_primary_screen());
+        let conn = TestConn::new().with_screens(vec![
+            Rect::new(0, 0, 1000, 800),
+            Rect::new(1000, 0, 1000, 800),
+        ]);
+        let mut wm = WindowManager::new(Config::default(), bindings, HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+
+        wm.state.client_set.focus_screen(1);
+        wm.conn().press_key(KEY);
+        wm.run_pending_events();
+
+        assert_eq!(wm.conn().primary_screen().unwrap(), Some(1));
+        assert_eq!(wm.state.client_set.primary_screen().index(), 1);
+    }
+
+    #[test]
+    fn transient_clients_follow_their_parent_between_workspaces() {
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(KEY, modify_with(|cs| cs.move_focused_to_tag("3")));
+        let mut wm = wm_with_bindings(bindings);
+
+        let parent = wm.conn().spawn_client("gimp");
+        wm.run_pending_events();
+        let dialog = wm.conn().create_client("gimp");
+        let prop = Prop::Window(vec![parent]);
+        wm.conn()
+            .set_prop(dialog, Atom::WmTransientFor.as_ref(), prop)
+            .unwrap();
+        wm.conn().push_event(XEvent::MapRequest(dialog));
+        wm.run_pending_events();
+        assert!(wm.state.client_set.is_floating(&dialog));
+
+        // Focus the parent so that it is the client moved by the key binding
+        wm.state.client_set.focus_client(&parent);
+        wm.conn().press_key(KEY);
+        wm.run_pending_events();
+
+        assert_eq!(wm.state.client_set.tag_for_client(&parent), Some("3"));
+        assert_eq!(wm.state.client_set.tag_for_client(&dialog), Some("3"));
+        assert!(wm.state.client_set.is_floating(&dialog));
+        assert!(!wm.conn().is_mapped(dialog));
+    }
+
+    #[test]
+    fn clicking_an_unfocused_client_focuses_it() {
+        let mut wm = wm_with_config_and_bindings(click_to_focus_config(), HashMap::new());
+        let first = wm.conn().spawn_client("st");
+        let second = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        assert!(wm.conn().buttons_grabbed(first));
+        assert!(!wm.conn().buttons_grabbed(second));
+
+        wm.conn().click_client(first);
+        wm.run_pending_events();
+
+        assert_eq!(wm.state.client_set.current_client(), Some(&first));
+        assert_eq!(wm.conn().focused_client(), Some(first));
+        assert!(!wm.conn().buttons_grabbed(first));
+        assert!(wm.conn().buttons_grabbed(second));
+        assert_eq!(wm.conn().replayed_clicks(), 1);
+    }
+
+    #[test]
+    fn clicking_a_floating_client_raises_it() {
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(
+            KEY,
+            modify_with(|cs| {
+                let ids: Vec<Xid> = cs.clients().copied().collect();
+                for id in ids {
+                    _ = cs.float(id, Rect::new(100, 100, 200, 200));
+                }
+            }),
+        );
+        let mut wm = wm_with_config_and_bindings(click_to_focus_config(), bindings);
+        let first = wm.conn().spawn_client("st");
+        let second = wm.conn().spawn_client("st");
+        wm.conn().press_key(KEY);
+        wm.run_pending_events();
+
+        let top = |wm: &WindowManager<TestConn>| wm.state.diff.after.positions.last().map(|p| p.0);
+        assert_eq!(top(&wm), Some(second));
+
+        wm.conn().click_client(first);
+        wm.run_pending_events();
+
+        assert_eq!(wm.state.client_set.current_client(), Some(&first));
+        assert_eq!(top(&wm), Some(first));
+    }
+
+    #[test]
+    fn click_to_focus_is_disabled_by_default() {
+        let mut wm = wm_with_bindings(HashMap::new());
+
+        let first = wm.conn().spawn_client("st");
+        wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        assert!(!wm.conn().buttons_grabbed(first));
+    }
+
+    #[test]
+    fn smart_borders_remove_the_border_from_a_single_tiled_client() {
+        let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+        let config = Config {
+            smart_borders: true,
+            ..Config::default()
+        };
+        let mut wm = WindowManager::new(config, HashMap::new(), HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+
+        let first = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        assert_eq!(wm.conn().border_width(first), Some(0));
+        assert_eq!(
+            wm.conn().client_geometry(first).unwrap(),
+            Rect::new(0, 0, 1000, 800)
+        );
+
+        let second = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        assert_eq!(wm.conn().border_width(first), Some(2));
+        assert_eq!(wm.conn().border_width(second), Some(2));
+
+        wm.conn().close_client(second);
+        wm.run_pending_events();
+
+        assert_eq!(wm.conn().border_width(first), Some(0));
+    }
+
+    #[test]
+    fn set_border_style_applies_to_existing_clients() {
+        let style = BorderStyle {
+            width: 5,
+            focused: 0xff0000ff.into(),
+            normal: 0x00ff00ff.into(),
+            urgent: 0x0000ffff.into(),
+        };
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(KEY, set_border_style(style));
+        let mut wm = wm_with_bindings(bindings);
+
+        let first = wm.conn().spawn_client("st");
+        let second = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+        wm.conn().press_key(KEY);
+        wm.run_pending_events();
+
+        assert_eq!(wm.state.config.border_style(), style);
+        assert_eq!(wm.conn().border_width(first), Some(5));
+        assert_eq!(wm.conn().border_width(second), Some(5));
+        assert_eq!(wm.conn().border_color(first), Some(style.normal));
+        assert_eq!(wm.conn().border_color(second), Some(style.focused));
+        assert_eq!(
+            wm.conn().client_geometry(second).unwrap(),
+            Rect::new(0, 0, 600, 800).shrink_in(5)
+        );
+    }
+
+    #[test]
+    fn urgent_clients_use_the_urgent_border_color() {
+        let mut wm = wm_with_bindings(HashMap::new());
+
+        let first = wm.conn().spawn_client("st");
+        wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        let demands_attention = vec![Atom::NetWmStateDemandsAttention.as_ref().to_owned()];
+        wm.conn()
+            .set_prop(
+                first,
+                Atom::NetWmState.as_ref(),
+                Prop::Atom(demands_attention),
+            )
+            .unwrap();
+        wm.conn().push_event(XEvent::PropertyNotify(PropertyEvent {
+            id: first,
+            atom: Atom::NetWmState.as_ref().to_owned(),
+            is_root: false,
+        }));
+        wm.run_pending_events();
+
+        let urgent_border = wm.state.config.urgent_border;
+        assert_eq!(wm.conn().border_color(first), Some(urgent_border));
+    }
+
+    #[test]
+    fn query_pointer_reports_the_screen_and_window_under_the_cursor() {
+        let conn = TestConn::new().with_screens(vec![
+            Rect::new(0, 0, 1000, 800),
+            Rect::new(1000, 0, 1000, 800),
+        ]);
+        let mut wm =
+            WindowManager::new(Config::default(), HashMap::new(), HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+
+        let id = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        wm.conn().set_cursor_position(Point::new(500, 400));
+        let q = wm.conn().query_pointer().unwrap();
+        assert_eq!(q.pos, Point::new(500, 400));
+        assert_eq!(q.screen, Some(0));
+        assert_eq!(q.child, Some(id));
+
+        wm.conn().set_cursor_position(Point::new(1500, 400));
+        let q = wm.conn().query_pointer().unwrap();
+        assert_eq!(q.screen, Some(1));
+        assert_eq!(q.child, None);
+    }
+
+    #[test]
+    fn client_at_point_uses_the_most_recent_layout() {
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(KEY, modify_with(|cs| cs.move_focused_to_tag("2")));
+        let mut wm = wm_with_bindings(bindings);
+
+        let first = wm.conn().spawn_client("st");
+        let second = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+
+        let r_main = wm.conn().client_geometry(second).unwrap();
+        let r_stack = wm.conn().client_geometry(first).unwrap();
+        assert_eq!(wm.state.client_at_point(r_main.midpoint()), Some(second));
+        assert_eq!(wm.state.client_at_point(r_stack.midpoint()), Some(first));
+
+        wm.conn().press_key(KEY);
+        wm.run_pending_events();
+
+        assert_eq!(wm.state.client_at_point(r_main.midpoint()), Some(first));
+    }
+
+    #[test]
+    fn client_rects_match_the_geometry_sent_to_the_server() {
+        let mut bindings: HashMap<KeyCode, Box<dyn KeyEventHandler<TestConn>>> = HashMap::new();
+        bindings.insert(KEY, modify_with(|cs| cs.move_focused_to_tag("2")));
+        let mut wm = wm_with_bindings(bindings);
+
+        let ids: Vec<Xid> = (0..3).map(|_| wm.conn().spawn_client("st")).collect();
+        wm.run_pending_events();
+
+        let border = wm.state.config.border_width;
+        for &id in ids.iter() {
+            let r = wm.state.client_rect(id).unwrap();
+            assert_eq!(r.shrink_in(border), wm.conn().client_geometry(id).unwrap());
+        }
+        assert_eq!(wm.state.client_rects().count(), 3);
+
+        wm.conn().press_key(KEY);
+        wm.run_pending_events();
+
+        assert_eq!(wm.state.client_rect(ids[2]), None);
+        assert_eq!(wm.state.client_rects().count(), 2);
+    }
+
+    fn wm_preventing_focus_stealing(allow_class: &'static str) -> WindowManager<TestConn> {
+        let config = Config {
+            prevent_focus_stealing: true,
+            allow_focus_rules: vec![Box::new(ClassName(allow_class))],
+            ..Config::default()
+        };
+        let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+        let mut wm = WindowManager::new(config, HashMap::new(), HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+
+        wm
+    }
+
+    fn spawn_client_with_user_time(conn: &TestConn, class: &str, t: u32) -> Xid {
+        let id = conn.create_client(class);
+        let user_time = Atom::NetWmUserTime.as_ref();
+        conn.set_prop(id, user_time, Prop::Cardinal(vec![t]))
+            .unwrap();
+        conn.push_event(XEvent::MapRequest(id));
+
+        id
+    }
+
+    #[test_case(0, false; "user time of zero")]
+    #[test_case(50, false; "older user time")]
+    #[test_case(100, true; "same user time")]
+    #[test_case(200, true; "newer user time")]
+    #[test]
+    fn new_clients_only_take_focus_with_a_recent_user_time(t: u32, takes_focus: bool) {
+        let mut wm = wm_preventing_focus_stealing("allowed");
+        let first = spawn_client_with_user_time(wm.conn(), "st", 100);
+        wm.run_pending_events();
+        let second = spawn_client_with_user_time(wm.conn(), "st", t);
+        wm.run_pending_events();
+
+        let expected = if takes_focus { second } else { first };
+        assert_eq!(wm.state.client_set.current_client(), Some(&expected));
+        assert_eq!(wm.conn().focused_client(), Some(expected));
+        assert_eq!(wm.conn().client_is_urgent(second).unwrap(), !takes_focus);
+        if !takes_focus {
+            let urgent_border = wm.state.config.urgent_border;
+            assert_eq!(wm.conn().border_color(second), Some(urgent_border));
+        }
+    }
+
+    #[test]
+    fn allow_focus_rules_override_the_user_time() {
+        let mut wm = wm_preventing_focus_stealing("allowed");
+        wm.conn().spawn_client("st");
+        wm.run_pending_events();
+        let id = spawn_client_with_user_time(wm.conn(), "allowed", 0);
+        wm.run_pending_events();
+
+        assert_eq!(wm.state.client_set.current_client(), Some(&id));
+        assert!(!wm.conn().client_is_urgent(id).unwrap());
+    }
+
+    #[test]
+    fn clients_can_be_opened_in_the_background() {
+        let mut config = Config::default();
+        config.compose_or_set_manage_hook((
+            ClassName("background"),
+            InsertAtWithFocus(Position::Tail, FocusPolicy::Keep),
+        ));
+        let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+        let mut wm = WindowManager::new(config, HashMap::new(), HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+
+        let first = wm.conn().spawn_client("st");
+        let second = wm.conn().spawn_client("st");
+        let background = wm.conn().spawn_client("background");
+        wm.run_pending_events();
+
+        let clients: Vec<Xid> = wm
+            .state
+            .client_set
+            .current_workspace()
+            .clients()
+            .copied()
+            .collect();
+        assert_eq!(clients, vec![second, first, background]);
+        assert_eq!(wm.state.client_set.current_client(), Some(&second));
+        assert_eq!(wm.conn().focused_client(), Some(second));
+        assert!(wm.conn().is_mapped(background));
+    }
+
+    #[test]
+    fn new_clients_are_inserted_at_the_configured_insert_point() {
+        let mut config = Config {
+            insert_point: Position::Tail,
+            ..Config::default()
+        };
+        config.compose_or_set_manage_hook((ClassName("main"), InsertAt(Position::Head)));
+        let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+        let mut wm = WindowManager::new(config, HashMap::new(), HashMap::new(), conn).unwrap();
+        wm.start_headless().unwrap();
+
+        let first = wm.conn().spawn_client("st");
+        let second = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+        let clients: Vec<Xid> = wm
+            .state
+            .client_set
+            .current_workspace()
+            .clients()
+            .copied()
+            .collect();
+        assert_eq!(clients, vec![first, second]);
+        assert_eq!(wm.state.client_set.current_client(), Some(&second));
+
+        let main = wm.conn().spawn_client("main");
+        wm.run_pending_events();
+        let clients: Vec<Xid> = wm
+            .state
+            .client_set
+            .current_workspace()
+            .clients()
+            .copied()
+            .collect();
+        assert_eq!(clients, vec![main, first, second]);
+        assert_eq!(wm.conn().focused_client(), Some(main));
+    }
+
+    #[test]
+    fn dragging_a_tiled_client_resizes_the_layout() {
+        let state = MouseState::new(MouseButton::Right, vec![]);
+        let mut mouse_bindings: HashMap<MouseState, Box<dyn MouseEventHandler<TestConn>>> =
+            HashMap::new();
+        mouse_bindings.insert(
+            state.clone(),
+            MouseResizeHandler::boxed_with_tiled_resizing(),
+        );
+        let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+        let mut wm =
+            WindowManager::new(Config::default(), HashMap::new(), mouse_bindings, conn).unwrap();
+        wm.start_headless().unwrap();
+
+        let stack = wm.conn().spawn_client("st");
+        let main = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+        assert_eq!(
+            wm.conn().client_geometry(main).unwrap(),
+            Rect::new(0, 0, 596, 796)
+        );
+
+        let press = MouseEvent::new(
+            stack,
+            700,
+            400,
+            100,
+            400,
+            state.clone(),
+            MouseEventKind::Press,
+        );
+        wm.conn().push_event(XEvent::MouseEvent(press));
+        let motion = MotionNotifyEvent::new(stack, 800, 400, 200, 400, vec![]);
+        wm.conn().push_event(XEvent::MotionNotify(motion));
+        let release = MouseEvent::new(stack, 800, 400, 200, 400, state, MouseEventKind::Release);
+        wm.conn().push_event(XEvent::MouseEvent(release));
+        wm.run_pending_events();
+
+        assert!(wm.state.client_set.floating.is_empty());
+        assert_eq!(
+            wm.conn().client_geometry(main).unwrap(),
+            Rect::new(0, 0, 696, 796)
+        );
+        assert_eq!(
+            wm.conn().client_geometry(stack).unwrap(),
+            Rect::new(700, 0, 296, 796)
+        );
+    }
+
+    #[test]
+    fn dropping_a_tiled_client_on_another_swaps_their_positions() {
+        let state = MouseState::new(MouseButton::Left, vec![]);
+        let mut mouse_bindings: HashMap<MouseState, Box<dyn MouseEventHandler<TestConn>>> =
+            HashMap::new();
+        mouse_bindings.insert(state.clone(), MouseDragHandler::boxed_with_tiled_swapping());
+        let conn = TestConn::new().with_screens(vec![Rect::new(0, 0, 1000, 800)]);
+        let mut wm =
+            WindowManager::new(Config::default(), HashMap::new(), mouse_bindings, conn).unwrap();
+        wm.start_headless().unwrap();
+
+        let stack = wm.conn().spawn_client("st");
+        let main = wm.conn().spawn_client("st");
+        wm.run_pending_events();
+        let main_r = wm.conn().client_geometry(main).unwrap();
+        let stack_r = wm.conn().client_geometry(stack).unwrap();
+
+        let press = MouseEvent::new(
+            stack,
+            700,
+            400,
+            100,
+            400,
+            state.clone(),
+            MouseEventKind::Press,
+        );
+        wm.conn().push_event(XEvent::MouseEvent(press));
+        let motion = MotionNotifyEvent::new(stack, 300, 400, -400, 400, vec![]);
+        wm.conn().push_event(XEvent::MotionNotify(motion));
+        let release = MouseEvent::new(stack, 300, 400, -400, 400, state, MouseEventKind::Release);
+        wm.conn().push_event(XEvent::MouseEvent(release));
+        wm.run_pending_events();
+
+        assert!(wm.state.client_set.floating.is_empty());
+        assert_eq!(wm.conn().client_geometry(stack).unwrap(), main_r);
+        assert_eq!(wm.conn().client_geometry(main).unwrap(), stack_r);
+        assert_eq!(wm.state.client_set.current_client(), Some(&main));
+    }
+}
@@ -20,6 +20,7 @@ use tracing::warn;
 use x11rb::{
     connection::Connection,
     protocol::{
+        xkb::StatePart,
         xproto::{ClientMessageEvent, KeyButMask, ModMask},
         ErrorKind, Event,
     },
@@ -33,39 +34,48 @@ pub(crate) fn convert_event<C: Connection>(conn: &Conn<C>, event: Event) -> Resu
         Event::RandrScreenChangeNotify(_) => Ok(Some(XEvent::ScreenChange)),
 
         Event::ButtonPress(event) => Ok(to_mouse_state(event.detail, event.state).map(|state| {
-            XEvent::MouseEvent(MouseEvent::new(
-                Xid(event.child),
-                event.root_x,
-                event.root_y,
-                event.event_x,
-                event.event_y,
-                state,
-                MouseEventKind::Press,
-            ))
+            XEvent::MouseEvent(
+                MouseEvent::new(
+                    Xid(event.child),
+                    event.root_x,
+                    event.root_y,
+                    event.event_x,
+                    event.event_y,
+                    state,
+                    MouseEventKind::Press,
+                )
+                .with_window(Xid(event.event)),
+            )
         })),
 
         Event::ButtonRelease(event) => Ok(to_mouse_state(event.detail, event.state).map(|state| {
-            XEvent::MouseEvent(MouseEvent::new(
-                Xid(event.child),
-                event.root_x,
-                event.root_y,
-                event.event_x,
-                event.event_y,
-                state,
-                MouseEventKind::Release,
-            ))
+            XEvent::MouseEvent(
+                MouseEvent::new(
+                    Xid(event.child),
+                    event.root_x,
+                    event.root_y,
+                    event.event_x,
+                    event.event_y,
+                    state,
+                    MouseEventKind::Release,
+                )
+                .with_window(Xid(event.event)),
+            )
         })),
 
         // NOTE: the '1' here is not actually used
         Event::MotionNotify(event) => Ok(to_mouse_state(1, event.state).map(|state| {
-            XEvent::MotionNotify(MotionNotifyEvent::new(
-                Xid(event.child),
-                event.root_x,
-                event.root_y,
-                event.event_x,
-                event.event_y,
-                state.modifiers,
-            ))
+            XEvent::MotionNotify(
+                MotionNotifyEvent::new(
+                    Xid(event.child),
+                    event.root_x,
+                    event.root_y,
+                    event.event_x,
+                    event.event_y,
+                    state.modifiers,
+                )
+                .with_window(Xid(event.event)),
+            )
         })),
 
         Event::KeyPress(event) => {
@@ -81,6 +91,11 @@ pub(crate) fn convert_event<C: Connection>(conn: &Conn<C>, event: Event) -> Resu
 
         Event::MapRequest(event) => Ok(Some(XEvent::MapRequest(Xid(event.window)))),
 
+        // Managed clients also report their own map events so only take those sent to the root
+        Event::MapNotify(event) if event.event == *conn.root() => {
+            Ok(Some(XEvent::MapNotify(Xid(event.window))))
+        }
+
         Event::UnmapNotify(event) => Ok(Some(XEvent::UnmapNotify(Xid(event.window)))),
 
         Event::EnterNotify(event) => Ok(Some(XEvent::Enter(PointerChange {
@@ -146,6 +161,14 @@ pub(crate) fn convert_event<C: Connection>(conn: &Conn<C>, event: Event) -> Resu
             is_root: event.window == *conn.root(),
         }))),
 
+        Event::SelectionClear(event) if conn.is_wm_selection_owner(event.owner) => {
+            Ok(Some(XEvent::WmReplaced))
+        }
+
+        Event::XkbStateNotify(event) if event.changed.contains(StatePart::GROUP_STATE) => {
+            Ok(Some(XEvent::KeyboardLayoutChange(u8::from(event.group))))
+        }
+
         // Map known error codes that we know how to handle into penrose Errors
         Event::Error(X11Error {
             error_kind: ErrorKind::Window,
@@ -11,6 +11,23 @@
 //! works. x11rb also [offers](https://github.com/psychon/x11rb/blob/master/doc/generated_code.md)
 //! some explanation on how the XML is turned into Rust code.
 //!
+//! ## Replacing a running window manager
+//! Penrose can take part in the ICCCM manager selection protocol (`WM_S0`) so that it is possible
+//! to switch between window managers without restarting the X server. Calling
+//! [Conn::acquire_wm_selection] before constructing your `WindowManager` will fail if another
+//! window manager is already running unless `replace` is `true`, in which case the running window
+//! manager is asked to exit and penrose takes over once it has done so:
+//!
+//! ```no_run
+//! # use penrose::x11rb::RustConn;
+//! let mut conn = RustConn::new()?;
+//! conn.acquire_wm_selection(std::env::args().any(|a| a == "--replace"))?;
+//! # Ok::<(), penrose::Error>(())
+//! ```
+//!
+//! Once the selection has been acquired, penrose will exit its main event loop (running any
+//! shutdown hooks) if another window manager later replaces it.
+//!
 //! The original implementation of this was by @psychon (Uli Schlachter).
 //! Re-write for the new 0.3.0 API by @sminez (Innes Anderson-Morrison).
 //!
@@ -24,22 +41,32 @@ use crate::{
         atom::Atom,
         event::{ClientEventMask, ClientMessage, ClientMessageKind},
         property::{Prop, WindowAttributes, WmHints, WmNormalHints, WmState},
-        ClientAttr, ClientConfig, WinType, XConn, XConnExt, XEvent,
+        ClientAttr, ClientConfig, PointerQuery, WinType, XConn, XConnExt, XEvent,
     },
     Error, Result, Xid,
 };
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::HashMap,
+    os::fd::{AsRawFd, RawFd},
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
 use strum::IntoEnumIterator;
-use tracing::error;
+use tracing::{error, warn};
 use x11rb::{
     connection::Connection,
     protocol::{
         randr::{self, ConnectionExt as _, NotifyMask},
+        xkb::{
+            self, ConnectionExt as _, NameDetail, SelectEventsAux, SelectEventsAuxStateNotify,
+            StatePart,
+        },
         xproto::{
-            AtomEnum, ChangeWindowAttributesAux, ClientMessageData, ClientMessageEvent,
-            ColormapAlloc, ConfigureWindowAux, ConnectionExt as _, CreateWindowAux, EventMask,
-            GrabMode, InputFocus, MapState, ModMask, PropMode, StackMode, WindowClass,
-            CLIENT_MESSAGE_EVENT,
+            Allow, AtomEnum, ButtonIndex, ChangeWindowAttributesAux, ClientMessageData,
+            ClientMessageEvent, ColormapAlloc, ConfigureWindowAux, ConnectionExt as _,
+            CreateWindowAux, EventMask, GrabMode, GrabStatus, InputFocus, MapState, ModMask,
+            PropMode, StackMode, VisualClass, Visualid, WindowClass, CLIENT_MESSAGE_EVENT,
         },
     },
     rust_connection::RustConnection,
@@ -51,10 +78,46 @@ use x11rb::{
 use x11rb::xcb_ffi::XCBConnection;
 
 pub mod conversions;
+pub mod waker;
 
 use conversions::convert_event;
+pub use waker::Waker;
 
 const RANDR_VER: (u32, u32) = (1, 2);
+const XKB_VER: (u16, u16) = (1, 0);
+const XKB_CORE_KBD: xkb::DeviceSpec = 256; // XkbUseCoreKbd
+const REPLACE_TIMEOUT: Duration = Duration::from_secs(5);
+const REPLACE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+fn select_xkb_events(conn: &impl Connection) -> Result<()> {
+    let reply = conn.xkb_use_extension(XKB_VER.0, XKB_VER.1)?.reply()?;
+    if !reply.supported {
+        return Err(Error::Custom("XKB not supported".to_owned()));
+    }
+
+    let details = SelectEventsAux::new().state_notify(SelectEventsAuxStateNotify {
+        affect_state: StatePart::GROUP_STATE,
+        state_details: StatePart::GROUP_STATE,
+    });
+    conn.xkb_select_events(
+        XKB_CORE_KBD,
+        xkb::EventType::from(0u16),
+        xkb::EventType::from(0u16),
+        xkb::MapPart::from(0u16),
+        xkb::MapPart::from(0u16),
+        &details,
+    )?
+    .check()?;
+
+    Ok(())
+}
+
+fn root_event_mask() -> EventMask {
+    EventMask::PROPERTY_CHANGE
+        | EventMask::SUBSTRUCTURE_REDIRECT
+        | EventMask::SUBSTRUCTURE_NOTIFY
+        | EventMask::BUTTON_MOTION
+}
 
 #[derive(Debug)]
 pub(crate) struct Atoms {
@@ -94,8 +157,10 @@ impl Atoms {
 #[derive(Debug)]
 pub struct Conn<C: Connection> {
     conn: C,
+    fd: RawFd,
     root: u32,
     atoms: Atoms,
+    wm_selection_owner: Option<u32>,
 }
 
 /// A pure rust based connection to the X server using a [RustConnection].
@@ -106,8 +171,18 @@ impl Conn<RustConnection> {
     /// [x11rb::rust_connection::RustConnection].
     pub fn new() -> Result<Self> {
         let (conn, _) = RustConnection::connect(None).map_err(Error::from)?;
+        let fd = conn.stream().as_raw_fd();
 
-        Self::new_for_connection(conn)
+        Self::new_for_connection(conn, fd)
+    }
+
+    /// Construct a [RustConn] for the given X display (e.g. ":1") rather than the one specified
+    /// by the `DISPLAY` environment variable.
+    pub fn new_for_display(display: &str) -> Result<Self> {
+        let (conn, _) = RustConnection::connect(Some(display)).map_err(Error::from)?;
+        let fd = conn.stream().as_raw_fd();
+
+        Self::new_for_connection(conn, fd)
     }
 }
 
@@ -121,8 +196,9 @@ impl Conn<XCBConnection> {
     /// [x11rb::xcb_ffi::XCBConnection].
     pub fn new() -> Result<Self> {
         let (conn, _) = XCBConnection::connect(None).map_err(Error::from)?;
+        let fd = conn.as_raw_fd();
 
-        Self::new_for_connection(conn)
+        Self::new_for_connection(conn, fd)
     }
 }
 
@@ -130,7 +206,7 @@ impl<C> Conn<C>
 where
     C: Connection,
 {
-    fn new_for_connection(conn: C) -> Result<Self> {
+    fn new_for_connection(conn: C, fd: RawFd) -> Result<Self> {
         let root = conn.setup().roots[0].root;
         conn.prefetch_extension_information(randr::X11_EXTENSION_NAME)?;
         let atoms = Atoms::new(&conn)?;
@@ -154,7 +230,18 @@ where
         let mask = NotifyMask::OUTPUT_CHANGE | NotifyMask::CRTC_CHANGE | NotifyMask::SCREEN_CHANGE;
         conn.randr_select_input(root, mask)?;
 
-        let xconn = Self { conn, root, atoms };
+        // XKB is only needed for keyboard layout support so we don't fail if it is unavailable
+        if let Err(e) = select_xkb_events(&conn) {
+            warn!(%e, "unable to initialise XKB: keyboard layout changes will not be reported");
+        }
+
+        let xconn = Self {
+            conn,
+            fd,
+            root,
+            atoms,
+            wm_selection_owner: None,
+        };
 
         xconn.set_client_attributes(Xid(root), &[ClientAttr::RootEventMask])?;
 
@@ -166,12 +253,160 @@ where
         &self.conn
     }
 
+    /// Take ownership of the ICCCM `WM_S0` manager selection, announcing that penrose is the
+    /// window manager for the default screen.
+    ///
+    /// If another window manager currently owns the selection then this returns
+    /// [Error::WmAlreadyRunning] unless `replace` is `true`, in which case the selection is taken
+    /// from the running window manager and this waits for it to exit before selecting for the
+    /// events required to manage clients. Once acquired, an [XEvent::WmReplaced] event is
+    /// generated if another window manager takes the selection from penrose.
+    pub fn acquire_wm_selection(&mut self, replace: bool) -> Result<()> {
+        let selection = *self.intern_atom("WM_S0")?;
+        let current = self.conn.get_selection_owner(selection)?.reply()?.owner;
+
+        if current != x11rb::NONE {
+            if !replace {
+                return Err(Error::WmAlreadyRunning);
+            }
+
+            // We need to know when the current owner has been destroyed so that we can take over.
+            // If this fails then the window has already gone.
+            let aux = ChangeWindowAttributesAux::new().event_mask(EventMask::STRUCTURE_NOTIFY);
+            if self
+                .conn
+                .change_window_attributes(current, &aux)?
+                .check()
+                .is_err()
+            {
+                warn!("current window manager selection owner has already exited");
+            }
+        }
+
+        // The ICCCM requires manager selections to be acquired using a real timestamp rather
+        // than CurrentTime so that the time of acquisition can be reported to other clients.
+        let owner = self.create_window(WinType::CheckWin, Rect::new(0, 0, 1, 1), false)?;
+        let time = match self.server_time(*owner) {
+            Ok(time) => time,
+            Err(e) => {
+                self.destroy_window(owner)?;
+                return Err(e);
+            }
+        };
+        self.conn.set_selection_owner(*owner, selection, time)?;
+        if self.conn.get_selection_owner(selection)?.reply()?.owner != *owner {
+            self.destroy_window(owner)?;
+            return Err(Error::Custom(
+                "unable to acquire the window manager selection".to_owned(),
+            ));
+        }
+
+        if current != x11rb::NONE {
+            self.wait_for_destroy(current)?;
+        }
+
+        let data = [time, selection, *owner, 0, 0];
+        let event = ClientMessageEvent::new(32, self.root, *self.intern_atom("MANAGER")?, data);
+        self.conn
+            .send_event(false, self.root, EventMask::STRUCTURE_NOTIFY, event)?;
+
+        // Selecting for substructure redirect on the root window will have failed when this
+        // connection was opened if another window manager was running at the time.
+        let aux = ChangeWindowAttributesAux::new().event_mask(root_event_mask());
+        if self
+            .conn
+            .change_window_attributes(self.root, &aux)?
+            .check()
+            .is_err()
+        {
+            self.destroy_window(owner)?;
+            return Err(Error::WmAlreadyRunning);
+        }
+
+        self.wm_selection_owner = Some(*owner);
+
+        Ok(())
+    }
+
+    // Obtain a timestamp from the X server by making a zero-length append to a property of a
+    // window that we own and waiting for the resulting PropertyNotify event. As with
+    // wait_for_destroy, other events received while waiting are dropped.
+    fn server_time(&self, id: u32) -> Result<u32> {
+        let aux = ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE);
+        self.conn.change_window_attributes(id, &aux)?;
+        self.conn.change_property8(
+            PropMode::APPEND,
+            id,
+            AtomEnum::WM_NAME,
+            AtomEnum::STRING,
+            &[],
+        )?;
+        self.flush();
+
+        let deadline = Instant::now() + REPLACE_TIMEOUT;
+        let res = loop {
+            if Instant::now() >= deadline {
+                break Err(Error::Custom(
+                    "timed out waiting for a timestamp from the X server".to_owned(),
+                ));
+            }
+
+            match self.conn.poll_for_event()? {
+                Some(x11rb::protocol::Event::PropertyNotify(e)) if e.window == id => {
+                    break Ok(e.time)
+                }
+                Some(_) => (),
+                None => thread::sleep(REPLACE_POLL_INTERVAL),
+            }
+        };
+
+        let aux = ChangeWindowAttributesAux::new().event_mask(EventMask::NO_EVENT);
+        self.conn.change_window_attributes(id, &aux)?;
+
+        res
+    }
+
+    // Wait for the previous owner of the window manager selection to be destroyed. Other events
+    // received while waiting are dropped as the root window events have not been selected yet.
+    fn wait_for_destroy(&self, id: u32) -> Result<()> {
+        let deadline = Instant::now() + REPLACE_TIMEOUT;
+        self.flush();
+
+        while Instant::now() < deadline {
+            match self.conn.poll_for_event()? {
+                Some(x11rb::protocol::Event::DestroyNotify(e)) if e.window == id => return Ok(()),
+                Some(_) => (),
+                None => thread::sleep(REPLACE_POLL_INTERVAL),
+            }
+        }
+
+        Err(Error::Custom(
+            "timed out waiting for the running window manager to exit".to_owned(),
+        ))
+    }
+
+    pub(crate) fn is_wm_selection_owner(&self, id: u32) -> bool {
+        self.wm_selection_owner == Some(id)
+    }
+
     /// Create and map a new window to the screen with the specified [WinType].
     pub fn create_window(&self, ty: WinType, r: Rect, managed: bool) -> Result<Xid> {
-        let (ty, mut win_aux, class) = match ty {
-            WinType::CheckWin => (None, CreateWindowAux::new(), WindowClass::INPUT_OUTPUT),
+        let (ty, mut win_aux, class, depth, visual) = match ty {
+            WinType::CheckWin => (
+                None,
+                CreateWindowAux::new(),
+                WindowClass::INPUT_OUTPUT,
+                x11rb::COPY_DEPTH_FROM_PARENT,
+                x11rb::COPY_FROM_PARENT,
+            ),
 
-            WinType::InputOnly => (None, CreateWindowAux::new(), WindowClass::INPUT_ONLY),
+            WinType::InputOnly => (
+                None,
+                CreateWindowAux::new(),
+                WindowClass::INPUT_ONLY,
+                x11rb::COPY_DEPTH_FROM_PARENT,
+                x11rb::COPY_FROM_PARENT,
+            ),
 
             WinType::InputOutput(a) => {
                 let colormap = self.conn.generate_id()?;
@@ -190,7 +425,32 @@ where
                     .border_pixel(screen.black_pixel)
                     .colormap(colormap);
 
-                (Some(a), win_aux, WindowClass::INPUT_OUTPUT)
+                (
+                    Some(a),
+                    win_aux,
+                    WindowClass::INPUT_OUTPUT,
+                    x11rb::COPY_DEPTH_FROM_PARENT,
+                    x11rb::COPY_FROM_PARENT,
+                )
+            }
+
+            WinType::InputOutputArgb(a) => {
+                let visual = self.argb_visual().ok_or(Error::NoArgbVisual)?;
+                let colormap = self.conn.generate_id()?;
+                let screen = &self.conn.setup().roots[0];
+
+                self.conn
+                    .create_colormap(ColormapAlloc::NONE, colormap, screen.root, visual)?;
+
+                // A border pixel and colormap must be provided when the depth of the window
+                // differs from that of its parent.
+                let win_aux = CreateWindowAux::new()
+                    .event_mask(EventMask::EXPOSURE | EventMask::STRUCTURE_NOTIFY)
+                    .background_pixel(0)
+                    .border_pixel(0)
+                    .colormap(colormap);
+
+                (Some(a), win_aux, WindowClass::INPUT_OUTPUT, 32, visual)
             }
         };
 
@@ -203,7 +463,7 @@ where
         let border_width = 0;
 
         self.conn.create_window(
-            x11rb::COPY_DEPTH_FROM_PARENT,
+            depth,
             *id,
             self.root,
             x as i16,
@@ -212,7 +472,7 @@ where
             h as u16,
             border_width,
             class,
-            x11rb::COPY_FROM_PARENT,
+            visual,
             &win_aux,
         )?;
 
@@ -220,6 +480,10 @@ where
         if let Some(atom) = ty {
             let net_name = Atom::NetWmWindowType.as_ref();
             self.set_prop(id, net_name, Prop::Atom(vec![atom.as_ref().into()]))?;
+            // Marking the window as belonging to this process allows for penrose to identify
+            // windows that it has created itself.
+            let pid = Prop::Cardinal(vec![std::process::id()]);
+            self.set_prop(id, "_NET_WM_PID", pid)?;
             self.map(id)?;
         }
 
@@ -228,12 +492,43 @@ where
         Ok(id)
     }
 
+    // The first 32-bit TrueColor visual supported by the default screen (if there is one)
+    fn argb_visual(&self) -> Option<Visualid> {
+        self.conn.setup().roots[0]
+            .allowed_depths
+            .iter()
+            .filter(|d| d.depth == 32)
+            .flat_map(|d| d.visuals.iter())
+            .find(|v| v.class == VisualClass::TRUE_COLOR)
+            .map(|v| v.visual_id)
+    }
+
     /// Destroy the window identified by the given `Xid`.
     pub fn destroy_window(&self, id: Xid) -> Result<()> {
         self.conn.destroy_window(*id)?;
 
         Ok(())
     }
+
+    // CRTCs that are currently driving an output, in the order used for screen indices.
+    fn active_crtcs(&self) -> Result<Vec<(randr::Crtc, randr::GetCrtcInfoReply)>> {
+        let resources = self.conn.randr_get_screen_resources(self.root)?.reply()?;
+
+        // Send queries for all CRTCs
+        let cookies = resources
+            .crtcs
+            .iter()
+            .map(|&c| Ok((c, self.conn.randr_get_crtc_info(c, 0)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let crtcs = cookies
+            .into_iter()
+            .flat_map(|(c, cookie)| Some((c, cookie.reply().ok()?)))
+            .filter(|(_, reply)| reply.width > 0)
+            .collect();
+
+        Ok(crtcs)
+    }
 }
 
 impl<C> XConn for Conn<C>
@@ -245,24 +540,10 @@ where
     }
 
     fn screen_details(&self) -> Result<Vec<Rect>> {
-        let resources = self.conn.randr_get_screen_resources(self.root)?.reply()?;
-
-        // Send queries for all CRTCs
-        let crtcs = resources
-            .crtcs
-            .iter()
-            .map(|c| {
-                self.conn
-                    .randr_get_crtc_info(*c, 0)
-                    .map_err(|err| err.into())
-            })
-            .collect::<Result<Vec<_>>>()?;
-
-        let rects = crtcs
+        let rects = self
+            .active_crtcs()?
             .into_iter()
-            .flat_map(|cookie| cookie.reply().ok())
-            .filter(|reply| reply.width > 0)
-            .map(|reply| {
+            .map(|(_, reply)| {
                 Rect::new(
                     reply.x as u32,
                     reply.y as u32,
@@ -275,12 +556,62 @@ where
         Ok(rects)
     }
 
+    fn screen_names(&self) -> Result<Vec<String>> {
+        self.active_crtcs()?
+            .into_iter()
+            .map(|(_, reply)| match reply.outputs.first() {
+                Some(&output) => {
+                    let info = self.conn.randr_get_output_info(output, 0)?.reply()?;
+                    Ok(String::from_utf8_lossy(&info.name).into_owned())
+                }
+                None => Ok(String::new()),
+            })
+            .collect()
+    }
+
+    fn primary_screen(&self) -> Result<Option<usize>> {
+        let output = self
+            .conn
+            .randr_get_output_primary(self.root)?
+            .reply()?
+            .output;
+        if output == x11rb::NONE {
+            return Ok(None);
+        }
+
+        let crtc = self.conn.randr_get_output_info(output, 0)?.reply()?.crtc;
+
+        Ok(self.active_crtcs()?.iter().position(|&(c, _)| c == crtc))
+    }
+
+    fn set_primary_screen(&self, index: usize) -> Result<()> {
+        let output = match self.active_crtcs()?.get(index) {
+            Some((_, reply)) if !reply.outputs.is_empty() => reply.outputs[0],
+            _ => return Err(Error::UnknownScreen(index)),
+        };
+        self.conn.randr_set_output_primary(self.root, output)?;
+
+        Ok(())
+    }
+
     fn cursor_position(&self) -> Result<Point> {
         let reply = self.conn.query_pointer(self.root)?.reply()?;
 
         Ok(Point::new(reply.root_x as u32, reply.root_y as u32))
     }
 
+    fn query_pointer(&self) -> Result<PointerQuery> {
+        let reply = self.conn.query_pointer(self.root)?.reply()?;
+        let pos = Point::new(reply.root_x as u32, reply.root_y as u32);
+        let screen = self
+            .screen_details()?
+            .iter()
+            .position(|r| r.contains_point(pos));
+        let child = (reply.child != x11rb::NONE).then_some(Xid(reply.child));
+
+        Ok(PointerQuery { pos, screen, child })
+    }
+
     fn grab(&self, key_codes: &[KeyCode], mouse_states: &[MouseState]) -> Result<()> {
         // Release any grabbed keys that we currently have before attempting to grab
         // the requested key codes.
@@ -340,6 +671,20 @@ where
         }
     }
 
+    fn poll_next_event(&self) -> Result<Option<XEvent>> {
+        while let Some(event) = self.conn.poll_for_event()? {
+            if let Some(event) = convert_event(self, event)? {
+                return Ok(Some(event));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn connection_fd(&self) -> Option<RawFd> {
+        Some(self.fd)
+    }
+
     fn flush(&self) {
         self.conn.flush().unwrap_or(());
     }
@@ -482,6 +827,17 @@ where
                     .collect(),
             ),
 
+            "PIXMAP" => Prop::Pixmap(
+                r.value32()
+                    .ok_or_else(|| Error::InvalidPropertyData {
+                        id,
+                        prop: prop_name.to_owned(),
+                        ty: prop_type.to_owned(),
+                    })?
+                    .map(Xid)
+                    .collect(),
+            ),
+
             "WM_HINTS" => Prop::WmHints(WmHints::try_from_bytes(
                 &r.value32()
                     .ok_or_else(|| Error::InvalidPropertyData {
@@ -595,13 +951,13 @@ where
 
         let (ty, data) = match val {
             Prop::UTF8String(strs) => {
-                self.conn.change_property8(
-                    PropMode::REPLACE,
-                    *id,
-                    a,
-                    AtomEnum::STRING,
-                    strs.join("\0").as_bytes(),
-                )?;
+                let ty = *self.intern_atom(Atom::UTF8String.as_ref())?;
+                let data: Vec<u8> = strs
+                    .iter()
+                    .flat_map(|s| s.bytes().chain(std::iter::once(0)))
+                    .collect();
+                self.conn
+                    .change_property8(PropMode::REPLACE, *id, a, ty, &data)?;
 
                 return Ok(());
             }
@@ -614,14 +970,15 @@ where
                     .collect::<Result<Vec<u32>>>()?,
             ),
 
-            Prop::Cardinal(vals) => (AtomEnum::CARDINAL, vals),
+            Prop::Cardinal(vals) | Prop::Bytes(vals) => (AtomEnum::CARDINAL, vals),
 
             Prop::Window(ids) => (AtomEnum::WINDOW, ids.into_iter().map(|id| *id).collect()),
 
-            // FIXME: handle changing WmHints and WmNormalHints correctly in change_prop
-            Prop::Bytes(_) | Prop::WmHints(_) | Prop::WmNormalHints(_) => {
-                panic!("unable to change Prop, WmHints or WmNormalHints properties");
-            }
+            Prop::Pixmap(ids) => (AtomEnum::PIXMAP, ids.into_iter().map(|id| *id).collect()),
+
+            Prop::WmHints(hints) => (AtomEnum::WM_HINTS, hints.to_bytes().to_vec()),
+
+            Prop::WmNormalHints(hints) => (AtomEnum::WM_SIZE_HINTS, hints.to_bytes().to_vec()),
         };
 
         self.conn
@@ -639,10 +996,7 @@ where
         let client_unmap_mask =
             EventMask::ENTER_WINDOW | EventMask::LEAVE_WINDOW | EventMask::PROPERTY_CHANGE;
 
-        let root_event_mask = EventMask::PROPERTY_CHANGE
-            | EventMask::SUBSTRUCTURE_REDIRECT
-            | EventMask::SUBSTRUCTURE_NOTIFY
-            | EventMask::BUTTON_MOTION;
+        let ui_event_mask = EventMask::BUTTON_PRESS | EventMask::ENTER_WINDOW | EventMask::EXPOSURE;
 
         let mut aux = ChangeWindowAttributesAux::new();
         for conf in attrs.iter() {
@@ -650,7 +1004,8 @@ where
                 ClientAttr::BorderColor(c) => aux = aux.border_pixel(*c),
                 ClientAttr::ClientEventMask => aux = aux.event_mask(client_event_mask),
                 ClientAttr::ClientUnmapMask => aux = aux.event_mask(client_unmap_mask),
-                ClientAttr::RootEventMask => aux = aux.event_mask(root_event_mask),
+                ClientAttr::RootEventMask => aux = aux.event_mask(root_event_mask()),
+                ClientAttr::UiEventMask => aux = aux.event_mask(ui_event_mask),
             }
         }
         self.conn.change_window_attributes(*id, &aux)?;
@@ -708,4 +1063,106 @@ where
 
         Ok(())
     }
+
+    fn keyboard_layouts(&self) -> Result<Vec<String>> {
+        let reply = self
+            .conn
+            .xkb_get_names(XKB_CORE_KBD, NameDetail::GROUP_NAMES)?
+            .reply()?;
+
+        reply
+            .value_list
+            .groups
+            .unwrap_or_default()
+            .into_iter()
+            .map(|atom| self.atom_name(Xid(atom)))
+            .collect()
+    }
+
+    fn keyboard_layout(&self) -> Result<u8> {
+        let reply = self.conn.xkb_get_state(XKB_CORE_KBD)?.reply()?;
+
+        Ok(u8::from(reply.group))
+    }
+
+    fn set_keyboard_layout(&self, index: u8) -> Result<()> {
+        self.conn.xkb_latch_lock_state(
+            XKB_CORE_KBD,
+            ModMask::from(0u16),
+            ModMask::from(0u16),
+            true,
+            xkb::Group::from(index),
+            ModMask::from(0u16),
+            false,
+            0,
+        )?;
+
+        Ok(())
+    }
+
+    fn pointer_is_grabbed(&self) -> Result<bool> {
+        // There is no way to query grabs held by other clients directly so we attempt to grab
+        // the pointer ourselves and immediately release it if we succeed.
+        let reply = self
+            .conn
+            .grab_pointer(
+                false,
+                self.root,
+                EventMask::NO_EVENT,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+                x11rb::NONE,
+                x11rb::NONE,
+                CURRENT_TIME,
+            )?
+            .reply()?;
+
+        if reply.status == GrabStatus::SUCCESS {
+            self.conn.ungrab_pointer(CURRENT_TIME)?;
+            return Ok(false);
+        }
+
+        Ok(matches!(
+            reply.status,
+            GrabStatus::ALREADY_GRABBED | GrabStatus::FROZEN
+        ))
+    }
+
+    fn grab_client_buttons(&self, client: Xid) -> Result<()> {
+        // Only the left, middle and right buttons are grabbed as presses of other buttons are
+        // dropped when converting events, which would leave the pointer frozen.
+        for button in [ButtonIndex::M1, ButtonIndex::M2, ButtonIndex::M3] {
+            self.conn.grab_button(
+                false,                   // don't pass grabbed events through to the client
+                *client,                 // the window to grab
+                EventMask::BUTTON_PRESS, // which events are reported to us
+                GrabMode::SYNC,          // freeze the pointer until we allow events
+                GrabMode::ASYNC,         // don't lock keyboard input while grabbing
+                x11rb::NONE,             // don't confine the cursor to a specific window
+                x11rb::NONE,             // don't change the cursor type
+                button,                  // the button to grab
+                ModMask::ANY,            // grab regardless of modifiers
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn ungrab_client_buttons(&self, client: Xid) -> Result<()> {
+        self.conn
+            .ungrab_button(ButtonIndex::ANY, *client, ModMask::ANY)?;
+
+        Ok(())
+    }
+
+    fn allow_pointer_events(&self, replay: bool) -> Result<()> {
+        let mode = if replay {
+            Allow::REPLAY_POINTER
+        } else {
+            Allow::ASYNC_POINTER
+        };
+        self.conn.allow_events(mode, CURRENT_TIME)?;
+
+        Ok(())
+    }
 }
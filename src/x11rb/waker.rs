@@ -0,0 +1,45 @@
+//! Waking the window manager event loop from other threads.
+use crate::Result;
+use tracing::error;
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{ClientMessageEvent, ConnectionExt, EventMask},
+    rust_connection::RustConnection,
+};
+
+/// A dedicated connection to the X server for waking up the window manager event loop from a
+/// background thread.
+///
+/// The main event loop blocks waiting for the next event from the X server, so work that is
+/// completed on another thread will not be seen until something else happens. Calling
+/// [Waker::wake] sends a client message with the given type to the root window which is then
+/// passed to any event hooks, allowing them to check for completed work.
+#[derive(Debug)]
+pub struct Waker {
+    conn: RustConnection,
+    root: u32,
+    atom: u32,
+}
+
+impl Waker {
+    /// Open a new connection to the X server that sends client messages of type `atom` when
+    /// woken.
+    pub fn new(atom: &str) -> Result<Self> {
+        let (conn, screen) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen].root;
+        let atom = conn.intern_atom(false, atom.as_bytes())?.reply()?.atom;
+
+        Ok(Self { conn, root, atom })
+    }
+
+    /// Send a client message to the root window so that the window manager event hooks are run.
+    pub fn wake(&self) {
+        let event = ClientMessageEvent::new(32, self.root, self.atom, [0u32; 5]);
+        let mask = EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT;
+
+        if let Err(e) = self.conn.send_event(false, self.root, mask, event) {
+            error!(%e, "unable to wake window manager");
+        }
+        _ = self.conn.flush();
+    }
+}
@@ -0,0 +1,245 @@
+//! Conversions to Penrose types from xcb types
+use crate::{
+    core::bindings::{
+        KeyCode, ModifierKey, MotionNotifyEvent, MouseButton, MouseEvent, MouseEventKind,
+        MouseState,
+    },
+    pure::geometry::{Point, Rect},
+    x::{
+        event::{
+            ClientEventMask, ClientMessage, ClientMessageData, ConfigureEvent, ExposeEvent,
+            PointerChange, PropertyEvent, ResizeRequestEvent,
+        },
+        XConn, XEvent,
+    },
+    xcb::Conn,
+    Error, Result, Xid,
+};
+use strum::IntoEnumIterator;
+use tracing::warn;
+use xcb::{
+    randr,
+    x::{self, KeyButMask, ModMask},
+    xkb::{self, StatePart},
+    Event, ProtocolError, Xid as _,
+};
+
+pub(crate) fn convert_event(conn: &Conn, event: Event) -> Result<Option<XEvent>> {
+    match event {
+        Event::RandR(randr::Event::Notify(_)) => Ok(Some(XEvent::RandrNotify)),
+
+        Event::RandR(randr::Event::ScreenChangeNotify(_)) => Ok(Some(XEvent::ScreenChange)),
+
+        Event::X(x::Event::ButtonPress(event)) => Ok(to_mouse_state(event.detail(), event.state())
+            .map(|state| {
+                XEvent::MouseEvent(
+                    MouseEvent::new(
+                        Xid(event.child().resource_id()),
+                        event.root_x(),
+                        event.root_y(),
+                        event.event_x(),
+                        event.event_y(),
+                        state,
+                        MouseEventKind::Press,
+                    )
+                    .with_window(Xid(event.event().resource_id())),
+                )
+            })),
+
+        Event::X(x::Event::ButtonRelease(event)) => {
+            Ok(to_mouse_state(event.detail(), event.state()).map(|state| {
+                XEvent::MouseEvent(
+                    MouseEvent::new(
+                        Xid(event.child().resource_id()),
+                        event.root_x(),
+                        event.root_y(),
+                        event.event_x(),
+                        event.event_y(),
+                        state,
+                        MouseEventKind::Release,
+                    )
+                    .with_window(Xid(event.event().resource_id())),
+                )
+            }))
+        }
+
+        // NOTE: the '1' here is not actually used
+        Event::X(x::Event::MotionNotify(event)) => {
+            Ok(to_mouse_state(1, event.state()).map(|state| {
+                XEvent::MotionNotify(
+                    MotionNotifyEvent::new(
+                        Xid(event.child().resource_id()),
+                        event.root_x(),
+                        event.root_y(),
+                        event.event_x(),
+                        event.event_y(),
+                        state.modifiers,
+                    )
+                    .with_window(Xid(event.event().resource_id())),
+                )
+            }))
+        }
+
+        Event::X(x::Event::KeyPress(event)) => {
+            let code = KeyCode {
+                mask: event.state().bits() as u16,
+                code: event.detail(),
+            };
+            let numlock = ModMask::N2.bits() as u16;
+            Ok(Some(XEvent::KeyPress(code.ignoring_modifier(numlock))))
+        }
+
+        Event::X(x::Event::MapRequest(event)) => {
+            Ok(Some(XEvent::MapRequest(Xid(event.window().resource_id()))))
+        }
+
+        // Managed clients also report their own map events so only take those sent to the root
+        Event::X(x::Event::MapNotify(event)) if event.event().resource_id() == *conn.root() => {
+            Ok(Some(XEvent::MapNotify(Xid(event.window().resource_id()))))
+        }
+
+        Event::X(x::Event::UnmapNotify(event)) => {
+            Ok(Some(XEvent::UnmapNotify(Xid(event.window().resource_id()))))
+        }
+
+        Event::X(x::Event::EnterNotify(event)) => Ok(Some(XEvent::Enter(PointerChange {
+            id: Xid(event.event().resource_id()),
+            abs: Point::new(event.root_x() as u32, event.root_y() as u32),
+            relative: Point::new(event.event_x() as u32, event.event_y() as u32),
+            same_screen: event.same_screen_focus() == 0,
+        }))),
+
+        Event::X(x::Event::LeaveNotify(event)) => Ok(Some(XEvent::Leave(PointerChange {
+            id: Xid(event.event().resource_id()),
+            abs: Point::new(event.root_x() as u32, event.root_y() as u32),
+            relative: Point::new(event.event_x() as u32, event.event_y() as u32),
+            same_screen: event.same_screen_focus() == 0,
+        }))),
+
+        Event::X(x::Event::DestroyNotify(event)) => {
+            Ok(Some(XEvent::Destroy(Xid(event.window().resource_id()))))
+        }
+
+        Event::X(x::Event::ConfigureNotify(event)) => {
+            Ok(Some(XEvent::ConfigureNotify(ConfigureEvent {
+                id: Xid(event.window().resource_id()),
+                r: Rect::new(
+                    event.x() as u32,
+                    event.y() as u32,
+                    event.width() as u32,
+                    event.height() as u32,
+                ),
+                is_root: event.window().resource_id() == *conn.root(),
+            })))
+        }
+
+        Event::X(x::Event::ConfigureRequest(event)) => {
+            Ok(Some(XEvent::ConfigureRequest(ConfigureEvent {
+                id: Xid(event.window().resource_id()),
+                r: Rect::new(
+                    event.x() as u32,
+                    event.y() as u32,
+                    event.width() as u32,
+                    event.height() as u32,
+                ),
+                is_root: event.window().resource_id() == *conn.root(),
+            })))
+        }
+
+        Event::X(x::Event::ResizeRequest(event)) => {
+            Ok(Some(XEvent::ResizeRequest(ResizeRequestEvent {
+                id: Xid(event.window().resource_id()),
+                width: event.width() as u32,
+                height: event.height() as u32,
+            })))
+        }
+
+        Event::X(x::Event::Expose(event)) => Ok(Some(XEvent::Expose(ExposeEvent {
+            id: Xid(event.window().resource_id()),
+            r: Rect::new(
+                event.x() as u32,
+                event.y() as u32,
+                event.width() as u32,
+                event.height() as u32,
+            ),
+            count: event.count() as usize,
+        }))),
+
+        Event::X(x::Event::ClientMessage(event)) => Ok(Some(to_client_message(conn, event)?)),
+
+        Event::X(x::Event::PropertyNotify(event)) => {
+            Ok(Some(XEvent::PropertyNotify(PropertyEvent {
+                id: Xid(event.window().resource_id()),
+                atom: conn.atom_name(Xid(event.atom().resource_id()))?,
+                is_root: event.window().resource_id() == *conn.root(),
+            })))
+        }
+
+        Event::X(x::Event::SelectionClear(event))
+            if conn.is_wm_selection_owner(event.owner().resource_id()) =>
+        {
+            Ok(Some(XEvent::WmReplaced))
+        }
+
+        Event::Xkb(xkb::Event::StateNotify(event))
+            if event.changed().contains(StatePart::GROUP_STATE) =>
+        {
+            Ok(Some(XEvent::KeyboardLayoutChange(event.group() as u8)))
+        }
+
+        // Ignoring other event types
+        _ => Ok(None),
+    }
+}
+
+/// Map errors returned by xcb into penrose Errors, converting the ones that we know how to
+/// handle into the corresponding penrose variant.
+pub(crate) fn convert_error(error: xcb::Error) -> Error {
+    match error {
+        xcb::Error::Protocol(ProtocolError::X(x::Error::Window(e), _)) => {
+            Error::UnknownClient(Xid(e.bad_value()))
+        }
+
+        // Other errors are returned directly
+        e => Error::from(e),
+    }
+}
+
+fn to_mouse_state(detail: u8, state: KeyButMask) -> Option<MouseState> {
+    fn is_held(key: &ModifierKey, mask: u16) -> bool {
+        mask & u16::from(*key) > 0
+    }
+    let button = match detail {
+        1 => MouseButton::Left,
+        2 => MouseButton::Middle,
+        3 => MouseButton::Right,
+        4 => MouseButton::ScrollUp,
+        5 => MouseButton::ScrollDown,
+        _ => {
+            warn!(button = detail, "dropping unknown mouse button event");
+            return None;
+        }
+    };
+    let state = state.bits() as u16;
+    let modifiers = ModifierKey::iter().filter(|m| is_held(m, state)).collect();
+    Some(MouseState { button, modifiers })
+}
+
+fn to_client_message(conn: &Conn, event: x::ClientMessageEvent) -> Result<XEvent> {
+    let name = conn.atom_name(Xid(event.r#type().resource_id()))?;
+    let data = match event.format() {
+        8 | 16 | 32 => match event.data() {
+            x::ClientMessageData::Data8(data) => ClientMessageData::from(data),
+            x::ClientMessageData::Data16(data) => ClientMessageData::from(data),
+            x::ClientMessageData::Data32(data) => ClientMessageData::from(data),
+        },
+        format => return Err(Error::InvalidClientMessage { format }),
+    };
+
+    Ok(XEvent::ClientMessage(ClientMessage::new(
+        Xid(event.window().resource_id()),
+        ClientEventMask::NoEventMask,
+        name,
+        data,
+    )))
+}
@@ -0,0 +1,1303 @@
+//! Helpers and utilities for using the xcb crate as a back end for penrose
+//!
+//! Docs for the `X11` core protocol can be found [here][1]. The xcb crate provides safe Rust
+//! bindings on top of the system libxcb, with the protocol bindings being generated from the same
+//! XML spec used by libxcb itself. For X11 extensions, there are usually separate documentations.
+//! For example, the RandR extension is documented in [2].
+//!
+//! Enabling the `xcb` feature provides [Conn], an [XConn] implementation that talks to the X
+//! server using the [xcb](https://docs.rs/xcb) crate rather than x11rb. This differs from the
+//! `XcbConn` provided by the `x11rb-xcb` feature, which still uses x11rb for the protocol bindings
+//! and only swaps out the underlying connection. Using [Conn] allows for sharing an
+//! [xcb::Connection] with other code in the same process that is already written against the
+//! xcb crate (see [Conn::connection]).
+//!
+//! ```no_run
+//! # use penrose::xcb::Conn;
+//! let mut conn = Conn::new()?;
+//! conn.acquire_wm_selection(std::env::args().any(|a| a == "--replace"))?;
+//! # Ok::<(), penrose::Error>(())
+//! ```
+//!
+//! [1]: https://www.x.org/releases/X11R7.6/doc/xproto/x11protocol.html
+//! [2]: https://gitlab.freedesktop.org/xorg/proto/randrproto/-/blob/master/randrproto.txt
+use crate::{
+    core::bindings::{KeyCode, MouseState},
+    pure::geometry::{Point, Rect},
+    x::{
+        self,
+        atom::Atom,
+        event::{ClientEventMask, ClientMessage, ClientMessageKind},
+        property::{Prop, WindowAttributes, WmHints, WmNormalHints, WmState},
+        ClientAttr, ClientConfig, PointerQuery, WinType, XConn, XConnExt, XEvent,
+    },
+    Error, Result, Xid,
+};
+use std::{
+    collections::HashMap,
+    os::fd::{AsRawFd, RawFd},
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+use strum::IntoEnumIterator;
+use tracing::{error, warn};
+use xcb::{
+    randr,
+    x::{
+        self as xproto, Allow, ButtonIndex, ClientMessageData, ClientMessageEvent, ColormapAlloc,
+        ConfigWindow, Cw, EventMask, GrabMode, GrabStatus, InputFocus, MapState, ModMask, PropMode,
+        StackMode, VisualClass, Visualid, WindowClass, CURRENT_TIME,
+    },
+    xkb::{self, EventType, MapPart, NameDetail, SelectEventsDetails, StatePart},
+    Extension, Xid as _, XidNew,
+};
+
+pub mod conversions;
+
+use conversions::{convert_error, convert_event};
+
+const RANDR_VER: (u32, u32) = (1, 2);
+const XKB_VER: (u16, u16) = (1, 0);
+const XKB_CORE_KBD: xkb::DeviceSpec = 256; // XkbUseCoreKbd
+const REPLACE_TIMEOUT: Duration = Duration::from_secs(5);
+const REPLACE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+fn select_xkb_events(conn: &xcb::Connection) -> Result<()> {
+    let cookie = conn.send_request(&xkb::UseExtension {
+        wanted_major: XKB_VER.0,
+        wanted_minor: XKB_VER.1,
+    });
+    if !conn.wait_for_reply(cookie)?.supported() {
+        return Err(Error::Custom("XKB not supported".to_owned()));
+    }
+
+    conn.send_and_check_request(&xkb::SelectEvents {
+        device_spec: XKB_CORE_KBD,
+        affect_which: EventType::STATE_NOTIFY,
+        clear: EventType::empty(),
+        select_all: EventType::empty(),
+        affect_map: MapPart::empty(),
+        map: MapPart::empty(),
+        details: &[SelectEventsDetails::StateNotify {
+            affect_state: StatePart::GROUP_STATE,
+            state_details: StatePart::GROUP_STATE,
+        }],
+    })?;
+
+    Ok(())
+}
+
+fn root_event_mask() -> EventMask {
+    EventMask::PROPERTY_CHANGE
+        | EventMask::SUBSTRUCTURE_REDIRECT
+        | EventMask::SUBSTRUCTURE_NOTIFY
+        | EventMask::BUTTON_MOTION
+}
+
+fn button_index(button: u8) -> ButtonIndex {
+    match button {
+        1 => ButtonIndex::N1,
+        2 => ButtonIndex::N2,
+        3 => ButtonIndex::N3,
+        4 => ButtonIndex::N4,
+        5 => ButtonIndex::N5,
+        _ => ButtonIndex::Any,
+    }
+}
+
+// The data held in a property reply as u32s if it has a 32-bit format.
+fn value32(r: &xproto::GetPropertyReply) -> Option<Vec<u32>> {
+    (r.format() == 32).then(|| r.value::<u32>().to_vec())
+}
+
+/// Handles communication with an X server via the xcb crate.
+pub struct Conn {
+    conn: xcb::Connection,
+    root: xproto::Window,
+    atoms: HashMap<Atom, u32>,
+    wm_selection_owner: Option<u32>,
+}
+
+impl std::fmt::Debug for Conn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Conn")
+            .field("root", &self.root)
+            .field("atoms", &self.atoms)
+            .field("wm_selection_owner", &self.wm_selection_owner)
+            .finish()
+    }
+}
+
+impl Conn {
+    /// Construct a [Conn] for the X display specified by the `DISPLAY` environment variable.
+    pub fn new() -> Result<Self> {
+        Self::connect(None)
+    }
+
+    /// Construct a [Conn] for the given X display (e.g. ":1") rather than the one specified
+    /// by the `DISPLAY` environment variable.
+    pub fn new_for_display(display: &str) -> Result<Self> {
+        Self::connect(Some(display))
+    }
+
+    fn connect(display: Option<&str>) -> Result<Self> {
+        let (conn, _) = xcb::Connection::connect_with_extensions(
+            display,
+            &[],
+            &[Extension::RandR, Extension::Xkb],
+        )?;
+
+        if !conn.active_extensions().any(|e| e == Extension::RandR) {
+            return Err(Error::Randr("RandR not supported".to_string()));
+        }
+
+        let root = conn
+            .get_setup()
+            .roots()
+            .next()
+            .ok_or_else(|| Error::Custom("X server has no screens".to_owned()))?
+            .root();
+
+        // First send all requests...
+        let atom_requests: Vec<_> = Atom::iter()
+            .map(|atom| {
+                let cookie = conn.send_request(&xproto::InternAtom {
+                    only_if_exists: false,
+                    name: atom.as_ref().as_bytes(),
+                });
+                (atom, cookie)
+            })
+            .collect();
+
+        // ..then get all the replies (so that we only need one instead of many round-trips to the
+        // X11 server)
+        let atoms = atom_requests
+            .into_iter()
+            .map(|(atom, cookie)| Ok((atom, conn.wait_for_reply(cookie)?.atom().resource_id())))
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        let cookie = conn.send_request(&randr::QueryVersion {
+            major_version: RANDR_VER.0,
+            minor_version: RANDR_VER.1,
+        });
+        let randr_ver = conn.wait_for_reply(cookie)?;
+        let (maj, min) = (randr_ver.major_version(), randr_ver.minor_version());
+        if (maj, min) != RANDR_VER {
+            return Err(Error::Randr(format!(
+                "penrose requires RandR version >= {}.{}: detected {}.{}\nplease update RandR to a newer version",
+                RANDR_VER.0, RANDR_VER.1, maj, min
+            )));
+        }
+
+        let enable = randr::NotifyMask::OUTPUT_CHANGE
+            | randr::NotifyMask::CRTC_CHANGE
+            | randr::NotifyMask::SCREEN_CHANGE;
+        conn.send_request(&randr::SelectInput {
+            window: root,
+            enable,
+        });
+
+        // XKB is only needed for keyboard layout support so we don't fail if it is unavailable
+        if let Err(e) = select_xkb_events(&conn) {
+            warn!(%e, "unable to initialise XKB: keyboard layout changes will not be reported");
+        }
+
+        let xconn = Self {
+            conn,
+            root,
+            atoms,
+            wm_selection_owner: None,
+        };
+
+        xconn.set_client_attributes(xconn.root(), &[ClientAttr::RootEventMask])?;
+
+        Ok(xconn)
+    }
+
+    /// Get a handle to the underlying connection.
+    pub fn connection(&self) -> &xcb::Connection {
+        &self.conn
+    }
+
+    /// Take ownership of the ICCCM `WM_S0` manager selection, announcing that penrose is the
+    /// window manager for the default screen.
+    ///
+    /// If another window manager currently owns the selection then this returns
+    /// [Error::WmAlreadyRunning] unless `replace` is `true`, in which case the selection is taken
+    /// from the running window manager and this waits for it to exit before selecting for the
+    /// events required to manage clients. Once acquired, an [XEvent::WmReplaced] event is
+    /// generated if another window manager takes the selection from penrose.
+    pub fn acquire_wm_selection(&mut self, replace: bool) -> Result<()> {
+        let selection = xproto::Atom::new(*self.intern_atom("WM_S0")?);
+        let cookie = self
+            .conn
+            .send_request(&xproto::GetSelectionOwner { selection });
+        let current = self.conn.wait_for_reply(cookie)?.owner();
+
+        if !current.is_none() {
+            if !replace {
+                return Err(Error::WmAlreadyRunning);
+            }
+
+            // We need to know when the current owner has been destroyed so that we can take over.
+            // If this fails then the window has already gone.
+            let req = xproto::ChangeWindowAttributes {
+                window: current,
+                value_list: &[Cw::EventMask(EventMask::STRUCTURE_NOTIFY)],
+            };
+            if self.conn.send_and_check_request(&req).is_err() {
+                warn!("current window manager selection owner has already exited");
+            }
+        }
+
+        // The ICCCM requires manager selections to be acquired using a real timestamp rather
+        // than CurrentTime so that the time of acquisition can be reported to other clients.
+        let owner = self.create_window(WinType::CheckWin, Rect::new(0, 0, 1, 1), false)?;
+        let owner_win = xproto::Window::new(*owner);
+        let time = match self.server_time(owner_win) {
+            Ok(time) => time,
+            Err(e) => {
+                self.destroy_window(owner)?;
+                return Err(e);
+            }
+        };
+        self.conn.send_request(&xproto::SetSelectionOwner {
+            owner: owner_win,
+            selection,
+            time,
+        });
+        let cookie = self
+            .conn
+            .send_request(&xproto::GetSelectionOwner { selection });
+        if self.conn.wait_for_reply(cookie)?.owner() != owner_win {
+            self.destroy_window(owner)?;
+            return Err(Error::Custom(
+                "unable to acquire the window manager selection".to_owned(),
+            ));
+        }
+
+        if !current.is_none() {
+            self.wait_for_destroy(current)?;
+        }
+
+        let manager = xproto::Atom::new(*self.intern_atom("MANAGER")?);
+        let data = [time, selection.resource_id(), *owner, 0, 0];
+        let event = ClientMessageEvent::new(self.root, manager, ClientMessageData::Data32(data));
+        self.conn.send_request(&xproto::SendEvent {
+            propagate: false,
+            destination: xproto::SendEventDest::Window(self.root),
+            event_mask: EventMask::STRUCTURE_NOTIFY,
+            event: &event,
+        });
+
+        // Selecting for substructure redirect on the root window will have failed when this
+        // connection was opened if another window manager was running at the time.
+        let req = xproto::ChangeWindowAttributes {
+            window: self.root,
+            value_list: &[Cw::EventMask(root_event_mask())],
+        };
+        if self.conn.send_and_check_request(&req).is_err() {
+            self.destroy_window(owner)?;
+            return Err(Error::WmAlreadyRunning);
+        }
+
+        self.wm_selection_owner = Some(*owner);
+
+        Ok(())
+    }
+
+    // Obtain a timestamp from the X server by making a zero-length append to a property of a
+    // window that we own and waiting for the resulting PropertyNotify event. As with
+    // wait_for_destroy, other events received while waiting are dropped.
+    fn server_time(&self, id: xproto::Window) -> Result<u32> {
+        self.conn.send_request(&xproto::ChangeWindowAttributes {
+            window: id,
+            value_list: &[Cw::EventMask(EventMask::PROPERTY_CHANGE)],
+        });
+        self.conn.send_request(&xproto::ChangeProperty::<u8> {
+            mode: PropMode::Append,
+            window: id,
+            property: xproto::ATOM_WM_NAME,
+            r#type: xproto::ATOM_STRING,
+            data: &[],
+        });
+        self.flush();
+
+        let deadline = Instant::now() + REPLACE_TIMEOUT;
+        let res = loop {
+            if Instant::now() >= deadline {
+                break Err(Error::Custom(
+                    "timed out waiting for a timestamp from the X server".to_owned(),
+                ));
+            }
+
+            match self.conn.poll_for_event() {
+                Ok(Some(xcb::Event::X(xproto::Event::PropertyNotify(e)))) if e.window() == id => {
+                    break Ok(e.time())
+                }
+                Ok(Some(_)) | Err(xcb::Error::Protocol(_)) => (),
+                Ok(None) => thread::sleep(REPLACE_POLL_INTERVAL),
+                Err(e) => break Err(e.into()),
+            }
+        };
+
+        self.conn.send_request(&xproto::ChangeWindowAttributes {
+            window: id,
+            value_list: &[Cw::EventMask(EventMask::NO_EVENT)],
+        });
+
+        res
+    }
+
+    // Wait for the previous owner of the window manager selection to be destroyed. Other events
+    // received while waiting are dropped as the root window events have not been selected yet.
+    fn wait_for_destroy(&self, id: xproto::Window) -> Result<()> {
+        let deadline = Instant::now() + REPLACE_TIMEOUT;
+        self.flush();
+
+        while Instant::now() < deadline {
+            match self.conn.poll_for_event() {
+                Ok(Some(xcb::Event::X(xproto::Event::DestroyNotify(e)))) if e.window() == id => {
+                    return Ok(())
+                }
+                Ok(Some(_)) | Err(xcb::Error::Protocol(_)) => (),
+                Ok(None) => thread::sleep(REPLACE_POLL_INTERVAL),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Err(Error::Custom(
+            "timed out waiting for the running window manager to exit".to_owned(),
+        ))
+    }
+
+    pub(crate) fn is_wm_selection_owner(&self, id: u32) -> bool {
+        self.wm_selection_owner == Some(id)
+    }
+
+    fn screen(&self) -> &xproto::Screen {
+        self.conn
+            .get_setup()
+            .roots()
+            .next()
+            .expect("screen presence checked on connect")
+    }
+
+    fn known_atom(&self, atom: Atom) -> u32 {
+        *self.atoms.get(&atom).unwrap()
+    }
+
+    fn atom(&self, name: &str) -> Result<xproto::Atom> {
+        Ok(xproto::Atom::new(*self.intern_atom(name)?))
+    }
+
+    /// Create and map a new window to the screen with the specified [WinType].
+    pub fn create_window(&self, ty: WinType, r: Rect, managed: bool) -> Result<Xid> {
+        // (background pixel, border pixel, colormap) for windows that are drawn to
+        let (ty, attrs, class, depth, visual) = match ty {
+            WinType::CheckWin => (
+                None,
+                None,
+                WindowClass::InputOutput,
+                xproto::COPY_FROM_PARENT as u8,
+                xproto::COPY_FROM_PARENT,
+            ),
+
+            WinType::InputOnly => (
+                None,
+                None,
+                WindowClass::InputOnly,
+                xproto::COPY_FROM_PARENT as u8,
+                xproto::COPY_FROM_PARENT,
+            ),
+
+            WinType::InputOutput(a) => {
+                let colormap = self.conn.generate_id();
+                let screen = self.screen();
+
+                self.conn.send_request(&xproto::CreateColormap {
+                    alloc: ColormapAlloc::None,
+                    mid: colormap,
+                    window: screen.root(),
+                    visual: screen.root_visual(),
+                });
+
+                (
+                    Some(a),
+                    Some((0, screen.black_pixel(), colormap)),
+                    WindowClass::InputOutput,
+                    xproto::COPY_FROM_PARENT as u8,
+                    xproto::COPY_FROM_PARENT,
+                )
+            }
+
+            WinType::InputOutputArgb(a) => {
+                let visual = self.argb_visual().ok_or(Error::NoArgbVisual)?;
+                let colormap = self.conn.generate_id();
+
+                self.conn.send_request(&xproto::CreateColormap {
+                    alloc: ColormapAlloc::None,
+                    mid: colormap,
+                    window: self.root,
+                    visual,
+                });
+
+                // A border pixel and colormap must be provided when the depth of the window
+                // differs from that of its parent.
+                (
+                    Some(a),
+                    Some((0, 0, colormap)),
+                    WindowClass::InputOutput,
+                    32,
+                    visual,
+                )
+            }
+        };
+
+        // The value list needs to be in the same order as the bits of the value mask
+        let mut value_list = Vec::new();
+        if let Some((back_pixel, border_pixel, _)) = attrs {
+            value_list.push(Cw::BackPixel(back_pixel));
+            value_list.push(Cw::BorderPixel(border_pixel));
+        }
+        if !managed {
+            value_list.push(Cw::OverrideRedirect(true));
+        }
+        if let Some((_, _, colormap)) = attrs {
+            value_list.push(Cw::EventMask(
+                EventMask::EXPOSURE | EventMask::STRUCTURE_NOTIFY,
+            ));
+            value_list.push(Cw::Colormap(colormap));
+        }
+
+        let Rect { x, y, w, h } = r;
+        let win: xproto::Window = self.conn.generate_id();
+        let id = Xid(win.resource_id());
+        let border_width = 0;
+
+        self.conn.send_request(&xproto::CreateWindow {
+            depth,
+            wid: win,
+            parent: self.root,
+            x: x as i16,
+            y: y as i16,
+            width: w as u16,
+            height: h as u16,
+            border_width,
+            class,
+            visual,
+            value_list: &value_list,
+        });
+
+        // Input only windows don't need mapping
+        if let Some(atom) = ty {
+            let net_name = Atom::NetWmWindowType.as_ref();
+            self.set_prop(id, net_name, Prop::Atom(vec![atom.as_ref().into()]))?;
+            // Marking the window as belonging to this process allows for penrose to identify
+            // windows that it has created itself.
+            let pid = Prop::Cardinal(vec![std::process::id()]);
+            self.set_prop(id, "_NET_WM_PID", pid)?;
+            self.map(id)?;
+        }
+
+        self.flush();
+
+        Ok(id)
+    }
+
+    // The first 32-bit TrueColor visual supported by the default screen (if there is one)
+    fn argb_visual(&self) -> Option<Visualid> {
+        self.screen()
+            .allowed_depths()
+            .filter(|d| d.depth() == 32)
+            .flat_map(|d| d.visuals().iter())
+            .find(|v| v.class() == VisualClass::TrueColor)
+            .map(|v| v.visual_id())
+    }
+
+    /// Destroy the window identified by the given `Xid`.
+    pub fn destroy_window(&self, id: Xid) -> Result<()> {
+        self.conn.send_request(&xproto::DestroyWindow {
+            window: xproto::Window::new(*id),
+        });
+
+        Ok(())
+    }
+
+    // CRTCs that are currently driving an output, in the order used for screen indices.
+    fn active_crtcs(&self) -> Result<Vec<(randr::Crtc, randr::GetCrtcInfoReply)>> {
+        let cookie = self
+            .conn
+            .send_request(&randr::GetScreenResources { window: self.root });
+        let resources = self.conn.wait_for_reply(cookie)?;
+
+        // Send queries for all CRTCs
+        let cookies: Vec<_> = resources
+            .crtcs()
+            .iter()
+            .map(|&crtc| {
+                let cookie = self.conn.send_request(&randr::GetCrtcInfo {
+                    crtc,
+                    config_timestamp: 0,
+                });
+                (crtc, cookie)
+            })
+            .collect();
+
+        let crtcs = cookies
+            .into_iter()
+            .flat_map(|(c, cookie)| Some((c, self.conn.wait_for_reply(cookie).ok()?)))
+            .filter(|(_, reply)| reply.width() > 0)
+            .collect();
+
+        Ok(crtcs)
+    }
+}
+
+impl XConn for Conn {
+    fn root(&self) -> Xid {
+        Xid(self.root.resource_id())
+    }
+
+    fn screen_details(&self) -> Result<Vec<Rect>> {
+        let rects = self
+            .active_crtcs()?
+            .into_iter()
+            .map(|(_, reply)| {
+                Rect::new(
+                    reply.x() as u32,
+                    reply.y() as u32,
+                    reply.width() as u32,
+                    reply.height() as u32,
+                )
+            })
+            .collect();
+
+        Ok(rects)
+    }
+
+    fn screen_names(&self) -> Result<Vec<String>> {
+        self.active_crtcs()?
+            .into_iter()
+            .map(|(_, reply)| match reply.outputs().first() {
+                Some(&output) => {
+                    let cookie = self.conn.send_request(&randr::GetOutputInfo {
+                        output,
+                        config_timestamp: 0,
+                    });
+                    let info = self.conn.wait_for_reply(cookie)?;
+                    Ok(String::from_utf8_lossy(info.name()).into_owned())
+                }
+                None => Ok(String::new()),
+            })
+            .collect()
+    }
+
+    fn primary_screen(&self) -> Result<Option<usize>> {
+        let cookie = self
+            .conn
+            .send_request(&randr::GetOutputPrimary { window: self.root });
+        let output = self.conn.wait_for_reply(cookie)?.output();
+        if output.is_none() {
+            return Ok(None);
+        }
+
+        let cookie = self.conn.send_request(&randr::GetOutputInfo {
+            output,
+            config_timestamp: 0,
+        });
+        let crtc = self.conn.wait_for_reply(cookie)?.crtc();
+
+        Ok(self.active_crtcs()?.iter().position(|(c, _)| *c == crtc))
+    }
+
+    fn set_primary_screen(&self, index: usize) -> Result<()> {
+        let output = match self.active_crtcs()?.get(index) {
+            Some((_, reply)) if !reply.outputs().is_empty() => reply.outputs()[0],
+            _ => return Err(Error::UnknownScreen(index)),
+        };
+        self.conn.send_request(&randr::SetOutputPrimary {
+            window: self.root,
+            output,
+        });
+
+        Ok(())
+    }
+
+    fn cursor_position(&self) -> Result<Point> {
+        let cookie = self
+            .conn
+            .send_request(&xproto::QueryPointer { window: self.root });
+        let reply = self.conn.wait_for_reply(cookie)?;
+
+        Ok(Point::new(reply.root_x() as u32, reply.root_y() as u32))
+    }
+
+    fn query_pointer(&self) -> Result<PointerQuery> {
+        let cookie = self
+            .conn
+            .send_request(&xproto::QueryPointer { window: self.root });
+        let reply = self.conn.wait_for_reply(cookie)?;
+        let pos = Point::new(reply.root_x() as u32, reply.root_y() as u32);
+        let screen = self
+            .screen_details()?
+            .iter()
+            .position(|r| r.contains_point(pos));
+        let child = (!reply.child().is_none()).then_some(Xid(reply.child().resource_id()));
+
+        Ok(PointerQuery { pos, screen, child })
+    }
+
+    fn grab(&self, key_codes: &[KeyCode], mouse_states: &[MouseState]) -> Result<()> {
+        // Release any grabbed keys that we currently have before attempting to grab
+        // the requested key codes.
+        let req = xproto::UngrabKey {
+            key: xproto::GRAB_ANY,
+            grab_window: self.root,
+            modifiers: ModMask::ANY,
+        };
+        if let Err(e) = self.conn.send_and_check_request(&req) {
+            error!(%e, "unable to ungrab keys");
+        };
+
+        // We need to explicitly grab NumLock as an additional modifier and then drop it later on
+        // when we are passing events through to the WindowManager as NumLock alters the modifier
+        // mask when it is active.
+        let modifiers = &[0, ModMask::N2.bits() as u16];
+        let mode = GrabMode::Async;
+        let mask = EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE | EventMask::BUTTON_MOTION;
+
+        for m in modifiers.iter() {
+            for k in key_codes.iter() {
+                self.conn.send_request(&xproto::GrabKey {
+                    owner_events: false,    // don't pass grabbed events through to the client
+                    grab_window: self.root, // the window to grab: in this case the root window
+                    modifiers: ModMask::from_bits_truncate((k.mask | m) as u32), // modifiers to grab
+                    key: k.code,                                                 // keycode to grab
+                    pointer_mode: mode,  // don't lock pointer input while grabbing
+                    keyboard_mode: mode, // don't lock keyboard input while grabbing
+                });
+            }
+        }
+
+        for m in modifiers.iter() {
+            for state in mouse_states.iter() {
+                self.conn.send_request(&xproto::GrabButton {
+                    owner_events: false,    // don't pass grabbed events through to the client
+                    grab_window: self.root, // the window to grab: in this case the root window
+                    event_mask: mask,       // which events are reported to the client
+                    pointer_mode: mode,     // don't lock pointer input while grabbing
+                    keyboard_mode: mode,    // don't lock keyboard input while grabbing
+                    confine_to: xproto::Window::none(), // don't confine the cursor to a specific window
+                    cursor: xproto::Cursor::none(),     // don't change the cursor type
+                    button: button_index(state.button()), // the button to grab
+                    modifiers: ModMask::from_bits_truncate((state.mask() | m) as u32), // modifiers to grab
+                });
+            }
+        }
+
+        self.flush();
+
+        Ok(())
+    }
+
+    fn next_event(&self) -> Result<XEvent> {
+        loop {
+            let event = self.conn.wait_for_event().map_err(convert_error)?;
+            if let Some(event) = convert_event(self, event)? {
+                return Ok(event);
+            }
+        }
+    }
+
+    fn poll_next_event(&self) -> Result<Option<XEvent>> {
+        while let Some(event) = self.conn.poll_for_event().map_err(convert_error)? {
+            if let Some(event) = convert_event(self, event)? {
+                return Ok(Some(event));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn connection_fd(&self) -> Option<RawFd> {
+        Some(self.conn.as_raw_fd())
+    }
+
+    fn flush(&self) {
+        self.conn.flush().unwrap_or(());
+    }
+
+    fn intern_atom(&self, atom: &str) -> Result<Xid> {
+        let id = match Atom::from_str(atom) {
+            Ok(known) => self.known_atom(known),
+            Err(_) => {
+                let cookie = self.conn.send_request(&xproto::InternAtom {
+                    only_if_exists: false,
+                    name: atom.as_bytes(),
+                });
+                self.conn.wait_for_reply(cookie)?.atom().resource_id()
+            }
+        };
+
+        Ok(Xid(id))
+    }
+
+    fn atom_name(&self, xid: Xid) -> Result<String> {
+        // Is the atom already known?
+        if let Some((atom, _)) = self.atoms.iter().find(|(_, &value)| *xid == value) {
+            return Ok(atom.as_ref().to_string());
+        }
+
+        // Nope, ask the X11 server
+        let cookie = self.conn.send_request(&xproto::GetAtomName {
+            atom: xproto::Atom::new(*xid),
+        });
+        let reply = self.conn.wait_for_reply(cookie)?;
+
+        Ok(reply.name().to_utf8().into_owned())
+    }
+
+    fn client_geometry(&self, id: Xid) -> Result<Rect> {
+        let cookie = self.conn.send_request(&xproto::GetGeometry {
+            drawable: xproto::Drawable::Window(xproto::Window::new(*id)),
+        });
+        let res = self.conn.wait_for_reply(cookie)?;
+
+        Ok(Rect::new(
+            res.x() as u32,
+            res.y() as u32,
+            res.width() as u32,
+            res.height() as u32,
+        ))
+    }
+
+    fn existing_clients(&self) -> Result<Vec<Xid>> {
+        let cookie = self
+            .conn
+            .send_request(&xproto::QueryTree { window: self.root });
+        let reply = self.conn.wait_for_reply(cookie)?;
+        let ids = reply
+            .children()
+            .iter()
+            .map(|w| Xid(w.resource_id()))
+            .collect();
+
+        Ok(ids)
+    }
+
+    fn map(&self, client: Xid) -> Result<()> {
+        let req = xproto::MapWindow {
+            window: xproto::Window::new(*client),
+        };
+        // Errors are ignored as the client may have already been destroyed
+        let _ = self.conn.send_and_check_request(&req);
+
+        Ok(())
+    }
+
+    fn unmap(&self, client: Xid) -> Result<()> {
+        let req = xproto::UnmapWindow {
+            window: xproto::Window::new(*client),
+        };
+        // Errors are ignored as the client may have already been destroyed
+        let _ = self.conn.send_and_check_request(&req);
+
+        Ok(())
+    }
+
+    fn kill(&self, client: Xid) -> Result<()> {
+        let supports_delete = self
+            .client_supports_protocol(client, Atom::WmDeleteWindow.as_ref())
+            .unwrap_or(false);
+
+        if supports_delete {
+            let msg = ClientMessageKind::DeleteWindow(client).as_message(self)?;
+            self.send_client_message(msg)?;
+            self.flush();
+        } else {
+            self.conn
+                .send_request(&xproto::KillClient { resource: *client });
+        }
+
+        Ok(())
+    }
+
+    fn focus(&self, id: Xid) -> Result<()> {
+        self.conn.send_request(&xproto::SetInputFocus {
+            revert_to: InputFocus::Parent,
+            focus: xproto::Window::new(*id),
+            time: CURRENT_TIME,
+        });
+
+        Ok(())
+    }
+
+    fn get_prop(&self, id: Xid, prop_name: &str) -> Result<Option<Prop>> {
+        let cookie = self.conn.send_request(&xproto::GetProperty {
+            delete: false,
+            window: xproto::Window::new(*id),
+            property: self.atom(prop_name)?,
+            r#type: xproto::ATOM_ANY,
+            long_offset: 0,
+            long_length: 1024,
+        });
+        let r = self.conn.wait_for_reply(cookie)?;
+
+        let prop_type = match r.r#type().resource_id() {
+            0 => return Ok(None), // Null response
+            id => self.atom_name(Xid(id))?,
+        };
+
+        let invalid = || Error::InvalidPropertyData {
+            id,
+            prop: prop_name.to_owned(),
+            ty: prop_type.to_owned(),
+        };
+
+        let p = match prop_type.as_ref() {
+            "ATOM" => Prop::Atom(
+                value32(&r)
+                    .ok_or_else(invalid)?
+                    .into_iter()
+                    .map(|a| self.atom_name(Xid(a)))
+                    .collect::<Result<Vec<String>>>()?,
+            ),
+
+            "CARDINAL" => Prop::Cardinal(value32(&r).ok_or_else(invalid)?),
+
+            "STRING" | "UTF8_STRING" => {
+                if r.format() != 8 {
+                    return Err(invalid());
+                } else {
+                    Prop::UTF8String(
+                        String::from_utf8(r.value::<u8>().to_vec())?
+                            .trim_matches('\0')
+                            .split('\0')
+                            .map(|s| s.to_string())
+                            .collect(),
+                    )
+                }
+            }
+
+            "WINDOW" => Prop::Window(
+                value32(&r)
+                    .ok_or_else(invalid)?
+                    .into_iter()
+                    .map(Xid)
+                    .collect(),
+            ),
+
+            "PIXMAP" => Prop::Pixmap(
+                value32(&r)
+                    .ok_or_else(invalid)?
+                    .into_iter()
+                    .map(Xid)
+                    .collect(),
+            ),
+
+            "WM_HINTS" => {
+                Prop::WmHints(WmHints::try_from_bytes(&value32(&r).ok_or_else(invalid)?)?)
+            }
+
+            "WM_SIZE_HINTS" => Prop::WmNormalHints(WmNormalHints::try_from_bytes(
+                &value32(&r).ok_or_else(invalid)?,
+            )?),
+
+            // Default to returning the raw bytes as u32s which the user can then
+            // convert as needed if the prop type is not one we recognise
+            _ => Prop::Bytes(match r.format() {
+                8 => r.value::<u8>().iter().map(|&b| b.into()).collect(),
+                16 => r.value::<u16>().iter().map(|&b| b.into()).collect(),
+                32 => r.value::<u32>().to_vec(),
+                format => {
+                    error!(
+                        "prop type for {} was {} which claims to have a data format of {}",
+                        prop_name, prop_type, format
+                    );
+
+                    return Ok(None);
+                }
+            }),
+        };
+
+        Ok(Some(p))
+    }
+
+    fn list_props(&self, id: Xid) -> Result<Vec<String>> {
+        let cookie = self.conn.send_request(&xproto::ListProperties {
+            window: xproto::Window::new(*id),
+        });
+
+        self.conn
+            .wait_for_reply(cookie)?
+            .atoms()
+            .iter()
+            .map(|a| self.atom_name(Xid(a.resource_id())))
+            .collect()
+    }
+
+    fn delete_prop(&self, id: Xid, prop_name: &str) -> Result<()> {
+        self.conn.send_request(&xproto::DeleteProperty {
+            window: xproto::Window::new(*id),
+            property: self.atom(prop_name)?,
+        });
+
+        Ok(())
+    }
+
+    fn get_window_attributes(&self, id: Xid) -> Result<WindowAttributes> {
+        let cookie = self.conn.send_request(&xproto::GetWindowAttributes {
+            window: xproto::Window::new(*id),
+        });
+        let win_attrs = self.conn.wait_for_reply(cookie)?;
+
+        let map_state = match win_attrs.map_state() {
+            MapState::Unmapped => x::property::MapState::Unmapped,
+            MapState::Unviewable => x::property::MapState::UnViewable,
+            MapState::Viewable => x::property::MapState::Viewable,
+        };
+
+        let window_class = match win_attrs.class() {
+            WindowClass::CopyFromParent => x::property::WindowClass::CopyFromParent,
+            WindowClass::InputOutput => x::property::WindowClass::InputOutput,
+            WindowClass::InputOnly => x::property::WindowClass::InputOnly,
+        };
+
+        Ok(WindowAttributes::new(
+            win_attrs.override_redirect(),
+            map_state,
+            window_class,
+        ))
+    }
+
+    fn get_wm_state(&self, client: Xid) -> Result<Option<WmState>> {
+        match self.get_prop(client, Atom::WmState.as_ref())? {
+            Some(Prop::Bytes(data)) => match data[0] {
+                0 => Ok(Some(WmState::Withdrawn)),
+                1 => Ok(Some(WmState::Normal)),
+                3 => Ok(Some(WmState::Iconic)),
+                _ => Ok(None),
+            },
+
+            _ => Ok(None),
+        }
+    }
+
+    fn set_wm_state(&self, id: Xid, wm_state: WmState) -> Result<()> {
+        let a = self.atom(Atom::WmState.as_ref())?;
+        let state = match wm_state {
+            WmState::Withdrawn => 0,
+            WmState::Normal => 1,
+            WmState::Iconic => 3,
+        };
+
+        self.conn.send_request(&xproto::ChangeProperty::<u32> {
+            mode: PropMode::Replace,
+            window: xproto::Window::new(*id),
+            property: a,
+            r#type: a,
+            data: &[state],
+        });
+
+        Ok(())
+    }
+
+    fn set_prop(&self, id: Xid, name: &str, val: Prop) -> Result<()> {
+        let a = self.atom(name)?;
+        let window = xproto::Window::new(*id);
+
+        let (ty, data) = match val {
+            Prop::UTF8String(strs) => {
+                let ty = self.atom(Atom::UTF8String.as_ref())?;
+                let data: Vec<u8> = strs
+                    .iter()
+                    .flat_map(|s| s.bytes().chain(std::iter::once(0)))
+                    .collect();
+                self.conn.send_request(&xproto::ChangeProperty {
+                    mode: PropMode::Replace,
+                    window,
+                    property: a,
+                    r#type: ty,
+                    data: &data,
+                });
+
+                return Ok(());
+            }
+
+            Prop::Atom(atoms) => (
+                xproto::ATOM_ATOM,
+                atoms
+                    .iter()
+                    .map(|a| self.intern_atom(a).map(|id| *id))
+                    .collect::<Result<Vec<u32>>>()?,
+            ),
+
+            Prop::Cardinal(vals) | Prop::Bytes(vals) => (xproto::ATOM_CARDINAL, vals),
+
+            Prop::Window(ids) => (xproto::ATOM_WINDOW, ids.into_iter().map(|id| *id).collect()),
+
+            Prop::Pixmap(ids) => (xproto::ATOM_PIXMAP, ids.into_iter().map(|id| *id).collect()),
+
+            Prop::WmHints(hints) => (xproto::ATOM_WM_HINTS, hints.to_bytes().to_vec()),
+
+            Prop::WmNormalHints(hints) => (xproto::ATOM_WM_SIZE_HINTS, hints.to_bytes().to_vec()),
+        };
+
+        self.conn.send_request(&xproto::ChangeProperty {
+            mode: PropMode::Replace,
+            window,
+            property: a,
+            r#type: ty,
+            data: &data,
+        });
+
+        Ok(())
+    }
+
+    fn set_client_attributes(&self, id: Xid, attrs: &[ClientAttr]) -> Result<()> {
+        let client_event_mask = EventMask::ENTER_WINDOW
+            | EventMask::LEAVE_WINDOW
+            | EventMask::PROPERTY_CHANGE
+            | EventMask::STRUCTURE_NOTIFY;
+
+        let client_unmap_mask =
+            EventMask::ENTER_WINDOW | EventMask::LEAVE_WINDOW | EventMask::PROPERTY_CHANGE;
+
+        let ui_event_mask = EventMask::BUTTON_PRESS | EventMask::ENTER_WINDOW | EventMask::EXPOSURE;
+
+        let (mut border_pixel, mut event_mask) = (None, None);
+        for conf in attrs.iter() {
+            match conf {
+                ClientAttr::BorderColor(c) => border_pixel = Some(*c),
+                ClientAttr::ClientEventMask => event_mask = Some(client_event_mask),
+                ClientAttr::ClientUnmapMask => event_mask = Some(client_unmap_mask),
+                ClientAttr::RootEventMask => event_mask = Some(root_event_mask()),
+                ClientAttr::UiEventMask => event_mask = Some(ui_event_mask),
+            }
+        }
+
+        // The value list needs to be in the same order as the bits of the value mask
+        let value_list: Vec<Cw> = border_pixel
+            .map(Cw::BorderPixel)
+            .into_iter()
+            .chain(event_mask.map(Cw::EventMask))
+            .collect();
+
+        self.conn.send_request(&xproto::ChangeWindowAttributes {
+            window: xproto::Window::new(*id),
+            value_list: &value_list,
+        });
+
+        Ok(())
+    }
+
+    fn set_client_config(&self, id: Xid, data: &[ClientConfig]) -> Result<()> {
+        let (mut border_width, mut position) = (None, None);
+        let (mut sibling, mut stack_mode) = (None, None);
+        for conf in data.iter() {
+            match conf {
+                ClientConfig::BorderPx(px) => border_width = Some(*px),
+                ClientConfig::Position(r) => position = Some(*r),
+                ClientConfig::StackBelow(s) => {
+                    sibling = Some(xproto::Window::new(s.0));
+                    stack_mode = Some(StackMode::Below);
+                }
+                ClientConfig::StackAbove(s) => {
+                    sibling = Some(xproto::Window::new(s.0));
+                    stack_mode = Some(StackMode::Above);
+                }
+                ClientConfig::StackBottom => stack_mode = Some(StackMode::Below),
+                ClientConfig::StackTop => stack_mode = Some(StackMode::Above),
+            }
+        }
+
+        // The value list needs to be in the same order as the bits of the value mask
+        let mut value_list = Vec::new();
+        if let Some(r) = position {
+            value_list.extend([
+                ConfigWindow::X(r.x as i32),
+                ConfigWindow::Y(r.y as i32),
+                ConfigWindow::Width(r.w),
+                ConfigWindow::Height(r.h),
+            ]);
+        }
+        value_list.extend(border_width.map(ConfigWindow::BorderWidth));
+        value_list.extend(sibling.map(ConfigWindow::Sibling));
+        value_list.extend(stack_mode.map(ConfigWindow::StackMode));
+
+        self.conn.send_request(&xproto::ConfigureWindow {
+            window: xproto::Window::new(*id),
+            value_list: &value_list,
+        });
+
+        Ok(())
+    }
+
+    fn send_client_message(&self, msg: ClientMessage) -> Result<()> {
+        let type_ = self.atom(&msg.dtype)?;
+        let data = match msg.data {
+            x::event::ClientMessageData::U8(u8s) => ClientMessageData::Data8(u8s),
+            x::event::ClientMessageData::U16(u16s) => ClientMessageData::Data16(u16s),
+            x::event::ClientMessageData::U32(u32s) => ClientMessageData::Data32(u32s),
+        };
+        let window = xproto::Window::new(*msg.id);
+        let event = ClientMessageEvent::new(window, type_, data);
+        let event_mask = match msg.mask {
+            ClientEventMask::NoEventMask => EventMask::NO_EVENT,
+            ClientEventMask::StructureNotify => EventMask::STRUCTURE_NOTIFY,
+            ClientEventMask::SubstructureNotify => EventMask::SUBSTRUCTURE_NOTIFY,
+        };
+
+        self.conn.send_request(&xproto::SendEvent {
+            propagate: false,
+            destination: xproto::SendEventDest::Window(window),
+            event_mask,
+            event: &event,
+        });
+
+        Ok(())
+    }
+
+    fn warp_pointer(&self, id: Xid, x: i16, y: i16) -> Result<()> {
+        self.conn.send_request(&xproto::WarpPointer {
+            src_window: xproto::Window::none(),
+            dst_window: xproto::Window::new(*id),
+            src_x: 0,
+            src_y: 0,
+            src_width: 0,
+            src_height: 0,
+            dst_x: x,
+            dst_y: y,
+        });
+
+        Ok(())
+    }
+
+    fn keyboard_layouts(&self) -> Result<Vec<String>> {
+        let cookie = self.conn.send_request(&xkb::GetNames {
+            device_spec: XKB_CORE_KBD,
+            which: NameDetail::GROUP_NAMES,
+        });
+        let reply = self.conn.wait_for_reply(cookie)?;
+
+        reply
+            .value_list()
+            .into_iter()
+            .find_map(|v| match v {
+                xkb::GetNamesReplyValueList::GroupNames(atoms) => Some(atoms),
+                _ => None,
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .map(|atom| self.atom_name(Xid(atom.resource_id())))
+            .collect()
+    }
+
+    fn keyboard_layout(&self) -> Result<u8> {
+        let cookie = self.conn.send_request(&xkb::GetState {
+            device_spec: XKB_CORE_KBD,
+        });
+
+        Ok(self.conn.wait_for_reply(cookie)?.group() as u8)
+    }
+
+    fn set_keyboard_layout(&self, index: u8) -> Result<()> {
+        let group_lock = match index {
+            0 => xkb::Group::N1,
+            1 => xkb::Group::N2,
+            2 => xkb::Group::N3,
+            3 => xkb::Group::N4,
+            _ => {
+                return Err(Error::Custom(format!(
+                    "invalid keyboard layout index: {index}"
+                )))
+            }
+        };
+
+        self.conn.send_request(&xkb::LatchLockState {
+            device_spec: XKB_CORE_KBD,
+            affect_mod_locks: ModMask::empty(),
+            mod_locks: ModMask::empty(),
+            lock_group: true,
+            group_lock,
+            affect_mod_latches: ModMask::empty(),
+            latch_group: false,
+            group_latch: 0,
+        });
+
+        Ok(())
+    }
+
+    fn pointer_is_grabbed(&self) -> Result<bool> {
+        // There is no way to query grabs held by other clients directly so we attempt to grab
+        // the pointer ourselves and immediately release it if we succeed.
+        let cookie = self.conn.send_request(&xproto::GrabPointer {
+            owner_events: false,
+            grab_window: self.root,
+            event_mask: EventMask::NO_EVENT,
+            pointer_mode: GrabMode::Async,
+            keyboard_mode: GrabMode::Async,
+            confine_to: xproto::Window::none(),
+            cursor: xproto::Cursor::none(),
+            time: CURRENT_TIME,
+        });
+        let reply = self.conn.wait_for_reply(cookie)?;
+
+        if reply.status() == GrabStatus::Success {
+            self.conn
+                .send_request(&xproto::UngrabPointer { time: CURRENT_TIME });
+            return Ok(false);
+        }
+
+        Ok(matches!(
+            reply.status(),
+            GrabStatus::AlreadyGrabbed | GrabStatus::Frozen
+        ))
+    }
+
+    fn grab_client_buttons(&self, client: Xid) -> Result<()> {
+        // Only the left, middle and right buttons are grabbed as presses of other buttons are
+        // dropped when converting events, which would leave the pointer frozen.
+        for button in [ButtonIndex::N1, ButtonIndex::N2, ButtonIndex::N3] {
+            self.conn.send_request(&xproto::GrabButton {
+                owner_events: false, // don't pass grabbed events through to the client
+                grab_window: xproto::Window::new(*client), // the window to grab
+                event_mask: EventMask::BUTTON_PRESS, // which events are reported to us
+                pointer_mode: GrabMode::Sync, // freeze the pointer until we allow events
+                keyboard_mode: GrabMode::Async, // don't lock keyboard input while grabbing
+                confine_to: xproto::Window::none(), // don't confine the cursor to a specific window
+                cursor: xproto::Cursor::none(), // don't change the cursor type
+                button,              // the button to grab
+                modifiers: ModMask::ANY, // grab regardless of modifiers
+            });
+        }
+
+        Ok(())
+    }
+
+    fn ungrab_client_buttons(&self, client: Xid) -> Result<()> {
+        self.conn.send_request(&xproto::UngrabButton {
+            button: ButtonIndex::Any,
+            grab_window: xproto::Window::new(*client),
+            modifiers: ModMask::ANY,
+        });
+
+        Ok(())
+    }
+
+    fn allow_pointer_events(&self, replay: bool) -> Result<()> {
+        let mode = if replay {
+            Allow::ReplayPointer
+        } else {
+            Allow::AsyncPointer
+        };
+        self.conn.send_request(&xproto::AllowEvents {
+            mode,
+            time: CURRENT_TIME,
+        });
+
+        Ok(())
+    }
+}
@@ -11,9 +11,10 @@ use std::fs;
 
 const R_SCREEN: Rect = Rect::new(0, 0, 1920, 1200);
 const MAX_CLIENTS: usize = 10;
-const LAYOUTS: [&str; 6] = [
+const LAYOUTS: [&str; 7] = [
     "MainAndStack",
     "CenteredMain",
+    "CenteredMainAlternating",
     "Grid",
     "Monocle",
     "Fibonacci",
@@ -24,6 +25,7 @@ fn get_layout(name: &str) -> Box<dyn Layout> {
     match name {
         "MainAndStack" => MainAndStack::boxed_default(),
         "CenteredMain" => CenteredMain::boxed_default(),
+        "CenteredMainAlternating" => CenteredMain::vertical_alternating(1, 0.6, 0.1),
         "Grid" => Grid::boxed(),
         "Monocle" => Monocle::boxed(),
         "Fibonacci" => Fibonacci::boxed_default(),